@@ -28,6 +28,10 @@ std::compile_error!("Cannot disable authentication for non-devel environments.")
 static KEY: OnceCell<[u8; 16]> = OnceCell::new();
 static ONE_WEEK: i64 = 60 * 60 * 24 * 7;
 
+/// Scopes a token can be restricted to. `write:media` implies `read:media` and `admin` implies
+/// every scope.
+pub const SCOPES: [&str; 3] = ["read:media", "write:media", "admin"];
+
 pub fn generate_key() -> [u8; 16] {
     rand::thread_rng().gen()
 }
@@ -54,6 +58,22 @@ pub struct UserRolesToken {
     /// The roles of the user, usually owner or user
     // TODO: Use a enum here maybe considering theres like two possibilities lol?
     roles: Vec<String>,
+    /// The scopes this token is restricted to. Tokens without scopes, ie those handed out on
+    /// login, have full access.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scopes: Option<Vec<String>>,
+}
+
+/// Claims of a token granting access to stream a single mediafile. These tokens are embedded in
+/// signed urls so that clients which cant set headers, ie `<video>` tags, can stream media.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StreamToken {
+    /// Id of the mediafile this token grants access to.
+    pub mediafile_id: i64,
+    /// Username of the user the token was minted for.
+    pub user: String,
+    /// Timestamp when the token expires.
+    pub exp: i64,
 }
 
 #[derive(Debug)]
@@ -75,6 +95,8 @@ pub enum JWTError {
     Invalid,
     InvalidKey,
     BadCount,
+    /// The token is valid but lacks the scope required by the route.
+    MissingScope(&'static str),
 }
 
 impl warp::reject::Reject for JWTError {}
@@ -96,6 +118,28 @@ impl UserRolesToken {
         self.roles.contains(&role.to_string())
     }
 
+    /// Method checks if this token grants `scope`. Unscoped tokens grant every scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        let scopes = match self.scopes.as_ref() {
+            Some(x) => x,
+            None => return true,
+        };
+
+        scopes.iter().any(|x| {
+            let implied = match (x.strip_prefix("write:"), scope.strip_prefix("read:")) {
+                (Some(written), Some(read)) => written == read,
+                _ => false,
+            };
+
+            x == scope || x == "admin" || implied
+        })
+    }
+
+    /// Method returns the scopes this token is restricted to, `None` if it is unscoped.
+    pub fn scopes(&self) -> Option<&[String]> {
+        self.scopes.as_deref()
+    }
+
     /// Method returns the username from the token
     pub fn get_user(&self) -> String {
         self.user.clone()
@@ -105,6 +149,11 @@ impl UserRolesToken {
         &self.user
     }
 
+    /// Method returns the timestamp at which this token expires.
+    pub fn expires_at(&self) -> i64 {
+        self.exp
+    }
+
     /// Method returns the id of this token
     pub fn get_id(&self) -> u128 {
         self.id
@@ -138,6 +187,33 @@ impl UserRolesToken {
 /// let check_token = jwt_check(token_1).unwrap();
 /// ```
 pub fn jwt_generate(user: String, roles: Vec<String>) -> String {
+    jwt_generate_with_scopes(user, roles, None)
+}
+
+/// Function generates a new JWT token restricted to `scopes` and signs it with our KEY. Passing
+/// `None` generates an unscoped token with full access.
+///
+/// # Example
+/// ```
+/// use auth::{jwt_check, jwt_generate_with_scopes};
+///
+/// auth::set_jwt_key(auth::generate_key());
+///
+/// let token = jwt_generate_with_scopes("test".into(), vec![], Some(vec!["read:media".into()]));
+/// let claims = jwt_check(token).unwrap().claims;
+///
+/// assert!(claims.has_scope("read:media"));
+/// assert!(!claims.has_scope("write:media"));
+/// assert!(!claims.has_scope("admin"));
+///
+/// let token = jwt_generate_with_scopes("test".into(), vec![], Some(vec!["write:media".into()]));
+/// assert!(jwt_check(token).unwrap().claims.has_scope("read:media"));
+/// ```
+pub fn jwt_generate_with_scopes(
+    user: String,
+    roles: Vec<String>,
+    scopes: Option<Vec<String>>,
+) -> String {
     let now = get_time().sec;
     let payload = UserRolesToken {
         id: uuid::Uuid::new_v4().to_u128_le(),
@@ -145,6 +221,37 @@ pub fn jwt_generate(user: String, roles: Vec<String>) -> String {
         exp: now + ONE_WEEK,
         user,
         roles,
+        scopes,
+    };
+
+    encode(
+        &Header::new(Algorithm::HS512),
+        &payload,
+        &EncodingKey::from_secret(get_key()),
+    )
+    .unwrap()
+}
+
+/// Function generates a token granting `user` access to stream the mediafile `mediafile_id` for
+/// the next `ttl` seconds, signed with our KEY.
+///
+/// # Example
+/// ```
+/// use auth::{stream_token_check, stream_token_generate};
+///
+/// auth::set_jwt_key(auth::generate_key());
+///
+/// let token = stream_token_generate(1, "test".into(), 60);
+/// assert_eq!(stream_token_check(&token, 1).unwrap().user, "test");
+///
+/// // tokens are only valid for the mediafile they were minted for.
+/// assert!(stream_token_check(&token, 2).is_err());
+/// ```
+pub fn stream_token_generate(mediafile_id: i64, user: String, ttl: i64) -> String {
+    let payload = StreamToken {
+        mediafile_id,
+        user,
+        exp: get_time().sec + ttl,
     };
 
     encode(
@@ -155,6 +262,25 @@ pub fn jwt_generate(user: String, roles: Vec<String>) -> String {
     .unwrap()
 }
 
+/// Function checks that `token` is a valid, unexpired stream token for the mediafile
+/// `mediafile_id`. Regular JWT tokens are never accepted as stream tokens and vice versa.
+pub fn stream_token_check(
+    token: &str,
+    mediafile_id: i64,
+) -> Result<StreamToken, jsonwebtoken::errors::Error> {
+    let token = decode::<StreamToken>(
+        token,
+        &DecodingKey::from_secret(get_key()),
+        &Validation::new(Algorithm::HS512),
+    )?;
+
+    if token.claims.mediafile_id != mediafile_id {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+
+    Ok(token.claims)
+}
+
 /// Function checks the token supplied and validates it
 /// # Arguments
 /// * `token` - JWT token we want to validate
@@ -193,11 +319,32 @@ pub fn jwt_check(_: String) -> Result<TokenData<UserRolesToken>, jsonwebtoken::e
             exp: i64::MAX,
             user: "Admin".into(),
             roles: vec!["owner".into()],
+            scopes: None,
         },
     })
 }
 
+/// Filter authenticates the request and requires full access, ie an unscoped token or one with
+/// the `admin` scope. Routes which can be called with narrower tokens use
+/// [`with_scope`](with_scope) instead.
 pub fn with_auth() -> impl Filter<Extract = (Wrapper,), Error = Rejection> + Clone {
+    with_scope("admin")
+}
+
+/// Filter authenticates the request and requires the token to grant `scope`.
+pub fn with_scope(
+    scope: &'static str,
+) -> impl Filter<Extract = (Wrapper,), Error = Rejection> + Clone {
+    authenticate().and_then(move |x: Wrapper| async move {
+        if x.0.claims.has_scope(scope) {
+            Ok(x)
+        } else {
+            Err(reject::custom(JWTError::MissingScope(scope)))
+        }
+    })
+}
+
+fn authenticate() -> impl Filter<Extract = (Wrapper,), Error = Rejection> + Clone {
     headers_cloned().and_then(|x: HeaderMap| async move {
         match x.get(AUTHORIZATION) {
             Some(k) => match k.to_str().ok().and_then(|x| jwt_check(x.into()).ok()) {
@@ -0,0 +1 @@
+ALTER TABLE _tblmedia ADD COLUMN placeholder INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+CREATE TABLE ratings (
+    id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    media_id INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    populated INTEGER NOT NULL,
+
+    PRIMARY KEY (id),
+    FOREIGN KEY(media_id) REFERENCES _tblmedia (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX ratings_idx ON ratings(user_id, media_id);
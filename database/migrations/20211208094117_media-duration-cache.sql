@@ -0,0 +1,32 @@
+-- Cache of the longest duration of the mediafiles attached to a media.
+ALTER TABLE _tblmedia ADD COLUMN duration INTEGER;
+
+UPDATE _tblmedia SET duration = (
+    SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = _tblmedia.id
+);
+
+CREATE TRIGGER mediafile_duration_insert
+AFTER INSERT ON mediafile
+WHEN new.media_id IS NOT NULL
+BEGIN
+    UPDATE _tblmedia SET duration = (
+        SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = new.media_id
+    ) WHERE _tblmedia.id = new.media_id;
+END;
+
+CREATE TRIGGER mediafile_duration_update
+AFTER UPDATE OF media_id, duration ON mediafile
+BEGIN
+    UPDATE _tblmedia SET duration = (
+        SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = _tblmedia.id
+    ) WHERE _tblmedia.id = new.media_id OR _tblmedia.id = old.media_id;
+END;
+
+CREATE TRIGGER mediafile_duration_delete
+AFTER DELETE ON mediafile
+WHEN old.media_id IS NOT NULL
+BEGIN
+    UPDATE _tblmedia SET duration = (
+        SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = old.media_id
+    ) WHERE _tblmedia.id = old.media_id;
+END;
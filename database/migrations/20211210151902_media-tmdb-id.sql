@@ -0,0 +1,3 @@
+ALTER TABLE _tblmedia ADD COLUMN tmdb_id INTEGER;
+
+CREATE INDEX media_tmdb_idx ON _tblmedia(tmdb_id);
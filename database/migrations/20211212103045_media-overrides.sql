@@ -0,0 +1,7 @@
+CREATE TABLE media_overrides (
+    media_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+
+    PRIMARY KEY (media_id, field),
+    FOREIGN KEY(media_id) REFERENCES _tblmedia (id) ON DELETE CASCADE ON UPDATE CASCADE
+);
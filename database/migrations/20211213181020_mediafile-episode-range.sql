@@ -0,0 +1,3 @@
+-- Last episode contained in a file that spans multiple episodes, ie `S01E01-E02`. The first
+-- episode is stored in `episode`.
+ALTER TABLE mediafile ADD COLUMN episode_end INTEGER;
@@ -0,0 +1,10 @@
+CREATE TABLE webhooks (
+    id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    -- Comma separated list of event types delivered to this webhook, empty means all events.
+    event_types TEXT NOT NULL DEFAULT '',
+    -- Key used to sign payloads delivered to this webhook.
+    secret TEXT NOT NULL,
+
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,22 @@
+CREATE TABLE people (
+    id INTEGER PRIMARY KEY,
+    -- Id of the person on TMDB, used to share people between medias.
+    tmdb_id INTEGER NOT NULL UNIQUE,
+    name TEXT NOT NULL
+);
+
+CREATE INDEX people_name_idx ON people(name);
+
+CREATE TABLE media_cast (
+    id INTEGER PRIMARY KEY,
+    media_id INTEGER NOT NULL,
+    person_id INTEGER NOT NULL,
+    -- Name of the character played by the person.
+    character TEXT,
+    -- Billing order of the person, lower is more prominent.
+    ordering INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY (person_id) REFERENCES people(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX media_cast_idx ON media_cast(media_id, person_id);
@@ -0,0 +1,16 @@
+CREATE TABLE keyword (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX keyword_name_idx ON keyword(name COLLATE NOCASE);
+
+CREATE TABLE keyword_media (
+    id INTEGER PRIMARY KEY,
+    keyword_id INTEGER NOT NULL,
+    media_id INTEGER NOT NULL,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY (keyword_id) REFERENCES keyword(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX keyword_media_idx ON keyword_media(keyword_id, media_id);
@@ -0,0 +1,2 @@
+-- Runtime of a media in seconds as reported by TMDB. Used when no mediafile has a duration.
+ALTER TABLE _tblmedia ADD COLUMN tmdb_runtime INTEGER;
@@ -0,0 +1,3 @@
+-- HDR format of the primary video stream, one of `hdr10`, `dolby_vision`, `hlg` or `sdr`. NULL if
+-- the file couldnt be probed or has no color metadata.
+ALTER TABLE mediafile ADD COLUMN hdr TEXT;
@@ -0,0 +1,12 @@
+CREATE TABLE playback_windows (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    -- Day of the week the window applies to, 0 is monday.
+    weekday INTEGER NOT NULL,
+    -- Minutes since midnight from which and until which playback is allowed.
+    start_minute INTEGER NOT NULL,
+    end_minute INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX playback_windows_idx ON playback_windows(user_id);
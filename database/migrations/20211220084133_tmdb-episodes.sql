@@ -0,0 +1,13 @@
+-- Full episode catalog of a tv show as listed on TMDB, including episodes we have no files for.
+CREATE TABLE tmdb_episodes (
+    id INTEGER PRIMARY KEY,
+    tvshow_id INTEGER NOT NULL,
+    season INTEGER NOT NULL,
+    episode INTEGER NOT NULL,
+    name TEXT,
+    -- Date the episode first aired formatted as `YYYY-MM-DD`.
+    air_date TEXT,
+    FOREIGN KEY (tvshow_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX tmdb_episodes_idx ON tmdb_episodes(tvshow_id, season, episode);
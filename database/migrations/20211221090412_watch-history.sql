@@ -0,0 +1,12 @@
+-- Every time a user finished watching a media.
+CREATE TABLE watch_history (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    media_id INTEGER NOT NULL,
+    -- Unix timestamp of when the media was finished.
+    watched_at INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY(media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE INDEX watch_history_idx ON watch_history(watched_at, media_id);
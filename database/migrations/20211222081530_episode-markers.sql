@@ -0,0 +1,8 @@
+-- Timestamps in seconds of the intro and credits of episodes, used by players to offer skipping.
+CREATE TABLE episode_markers (
+    episode_id INTEGER PRIMARY KEY,
+    intro_start INTEGER,
+    intro_end INTEGER,
+    credits_start INTEGER,
+    FOREIGN KEY(episode_id) REFERENCES episode(id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+-- Unix timestamp of when the metadata of a media was last fetched from TMDB. NULL for media that
+-- were matched before this was tracked.
+ALTER TABLE _tblmedia ADD COLUMN metadata_refreshed_at INTEGER;
+
+CREATE INDEX media_metadata_refreshed_at_idx ON _tblmedia(metadata_refreshed_at);
@@ -0,0 +1,20 @@
+-- Name, overview and air date of a season as listed on TMDB. Air dates are formatted as
+-- `YYYY-MM-DD`.
+ALTER TABLE _tblseason ADD COLUMN name TEXT;
+ALTER TABLE _tblseason ADD COLUMN overview TEXT;
+ALTER TABLE _tblseason ADD COLUMN air_date TEXT;
+
+DROP VIEW season;
+
+CREATE VIEW season AS
+SELECT _tblseason.id, _tblseason.season_number,
+    _tblseason.tvshowid, _tblseason.added, assets.local_path as poster,
+    _tblseason.name, _tblseason.overview, _tblseason.air_date
+FROM _tblseason
+LEFT OUTER JOIN assets ON _tblseason.poster = assets.id;
+
+CREATE TRIGGER season_delete
+INSTEAD OF DELETE ON season
+BEGIN
+    DELETE FROM _tblseason WHERE _tblseason.id = old.id;
+END;
@@ -0,0 +1,6 @@
+-- Libraries flagged as anime resolve absolute episode numbers, ie `Episode 125`, to a season and
+-- episode and navigate episodes by their absolute order.
+ALTER TABLE library ADD COLUMN anime BOOLEAN NOT NULL DEFAULT 0;
+
+-- Position of the episode across all regular seasons of the show, NULL outside of anime libraries.
+ALTER TABLE episode ADD COLUMN absolute_number INTEGER;
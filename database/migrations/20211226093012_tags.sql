@@ -0,0 +1,19 @@
+-- Free-form tags created by users, kept apart from the genres fetched from TMDB so that
+-- rematching a media never touches them.
+CREATE TABLE tag (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    user_created BOOLEAN NOT NULL DEFAULT 1
+);
+
+CREATE UNIQUE INDEX tag_name_idx ON tag(name COLLATE NOCASE);
+
+CREATE TABLE tag_media (
+    id INTEGER PRIMARY KEY,
+    tag_id INTEGER NOT NULL,
+    media_id INTEGER NOT NULL,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX tag_media_idx ON tag_media(tag_id, media_id);
@@ -0,0 +1,15 @@
+-- TMDB results that matched a media about as well as the one it was matched to. A media with
+-- candidates needs to be reviewed by a user, rematching the media clears them.
+CREATE TABLE match_candidate (
+    id INTEGER PRIMARY KEY,
+    media_id INTEGER NOT NULL,
+    tmdb_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    release_date TEXT,
+    poster_path TEXT,
+    -- how confident the scanner is that this result is what the file is, between 0 and 1.
+    confidence REAL NOT NULL,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX match_candidate_idx ON match_candidate(media_id, tmdb_id);
@@ -0,0 +1,16 @@
+-- Title a media is sorted by, ie `Matrix` for `The Matrix`. NULL sorts the media by its name.
+ALTER TABLE _tblmedia ADD COLUMN sort_title TEXT;
+
+-- Language of the titles in a library, used to strip leading articles from sort titles. NULL is
+-- treated as english.
+ALTER TABLE library ADD COLUMN language TEXT;
+
+-- Existing libraries have no language set thus only english articles are stripped.
+UPDATE _tblmedia SET sort_title = substr(name, 5)
+WHERE name LIKE 'the %' AND length(trim(substr(name, 5))) > 0 AND NOT media_type = "episode";
+
+UPDATE _tblmedia SET sort_title = substr(name, 4)
+WHERE name LIKE 'an %' AND length(trim(substr(name, 4))) > 0 AND NOT media_type = "episode";
+
+UPDATE _tblmedia SET sort_title = substr(name, 3)
+WHERE name LIKE 'a %' AND length(trim(substr(name, 3))) > 0 AND NOT media_type = "episode";
@@ -0,0 +1,12 @@
+-- Titles a media is known under in other countries, as reported by TMDB. Used by the search to
+-- find media by their foreign titles.
+CREATE TABLE alternate_title (
+    id INTEGER PRIMARY KEY,
+    media_id INTEGER NOT NULL,
+    -- ISO 3166-1 code of the country the title is used in, ie `FR`.
+    country TEXT NOT NULL,
+    title TEXT NOT NULL,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX alternate_title_idx ON alternate_title(media_id, country, title);
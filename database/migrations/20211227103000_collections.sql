@@ -0,0 +1,4 @@
+-- TMDB collection a movie belongs to, ie `The Lord of the Rings Collection`.
+ALTER TABLE _tblmedia ADD COLUMN collection_id INTEGER;
+
+CREATE INDEX media_collection_idx ON _tblmedia(collection_id);
@@ -0,0 +1,4 @@
+-- Whether the file has a closed caption (CC) or a SDH (subtitles for the deaf and hard of hearing)
+-- subtitle track. Tracks without any marker count as neither.
+ALTER TABLE mediafile ADD COLUMN has_cc BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE mediafile ADD COLUMN has_sdh BOOLEAN NOT NULL DEFAULT 0;
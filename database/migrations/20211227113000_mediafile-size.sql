@@ -0,0 +1,2 @@
+-- Size of the file in bytes as of the last scan. NULL if the file hasnt been scanned since.
+ALTER TABLE mediafile ADD COLUMN size INTEGER;
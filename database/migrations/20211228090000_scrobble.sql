@@ -0,0 +1,31 @@
+CREATE TABLE scrobble_integrations (
+    user_id TEXT NOT NULL,
+    -- Base url of the service, scrobbles are posted to `<url>/scrobble/<action>`.
+    url TEXT NOT NULL,
+    -- Access token sent as a bearer token with every scrobble.
+    token TEXT NOT NULL,
+
+    PRIMARY KEY (user_id),
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE scrobbles (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    -- Either `start` or `stop`.
+    action TEXT NOT NULL,
+    -- Either `movie` or `episode`.
+    media_type TEXT NOT NULL,
+    -- TMDB id of the movie, or of the show for episodes.
+    tmdb_id INTEGER NOT NULL,
+    season INTEGER,
+    episode INTEGER,
+    -- Progress through the media in percent.
+    progress REAL NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Unix timestamp before which the scrobble isn't sent.
+    next_attempt INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY(user_id) REFERENCES scrobble_integrations(user_id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX scrobbles_idx ON scrobbles(next_attempt);
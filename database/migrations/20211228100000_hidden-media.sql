@@ -0,0 +1,11 @@
+-- Media a user hid from their browse. Hiding is per user and doesnt affect anyone else.
+CREATE TABLE hidden_media (
+    user_id TEXT NOT NULL,
+    media_id INTEGER NOT NULL,
+    -- Unix timestamp of when the media was hidden.
+    hidden_at INTEGER NOT NULL,
+
+    PRIMARY KEY (user_id, media_id),
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY(media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+-- Progress of a user through the individual versions of a media. The overall progress through a
+-- media is still kept in `progress`, this only remembers where the user left off in each version
+-- and which version they last played.
+CREATE TABLE version_progress (
+    user_id TEXT NOT NULL,
+    mediafile_id INTEGER NOT NULL,
+    -- Offset in seconds the user left the version at. Stale for the version that is being played,
+    -- its offset is the one in `progress`.
+    delta INTEGER NOT NULL,
+    -- Unix timestamp of when the user last switched to or away from the version.
+    populated INTEGER NOT NULL,
+
+    PRIMARY KEY (user_id, mediafile_id),
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY(mediafile_id) REFERENCES mediafile(id) ON DELETE CASCADE
+);
@@ -0,0 +1,50 @@
+-- Full-text index over the names and alternate titles of all non-episode media, keyed by the id
+-- of the media. Triggers keep it up to date, `POST /api/v1/admin/maintenance/reindex_search`
+-- rebuilds it from scratch, ie after bulk imports.
+CREATE VIRTUAL TABLE media_search USING fts5(
+    name,
+    alternate_titles,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO media_search (rowid, name, alternate_titles)
+SELECT id, name, (SELECT group_concat(title, ' ') FROM alternate_title WHERE media_id = _tblmedia.id)
+FROM _tblmedia WHERE NOT media_type = "episode";
+
+CREATE TRIGGER media_search_insert
+AFTER INSERT ON _tblmedia
+WHEN NOT new.media_type = "episode"
+BEGIN
+    INSERT INTO media_search (rowid, name, alternate_titles) VALUES (new.id, new.name, NULL);
+END;
+
+CREATE TRIGGER media_search_update
+AFTER UPDATE OF name ON _tblmedia
+WHEN NOT new.media_type = "episode"
+BEGIN
+    DELETE FROM media_search WHERE rowid = new.id;
+    INSERT INTO media_search (rowid, name, alternate_titles)
+    SELECT new.id, new.name, group_concat(title, ' ') FROM alternate_title WHERE media_id = new.id;
+END;
+
+CREATE TRIGGER media_search_delete
+AFTER DELETE ON _tblmedia
+BEGIN
+    DELETE FROM media_search WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER media_search_alternate_title_insert
+AFTER INSERT ON alternate_title
+BEGIN
+    UPDATE media_search SET alternate_titles = (
+        SELECT group_concat(title, ' ') FROM alternate_title WHERE media_id = new.media_id
+    ) WHERE rowid = new.media_id;
+END;
+
+CREATE TRIGGER media_search_alternate_title_delete
+AFTER DELETE ON alternate_title
+BEGIN
+    UPDATE media_search SET alternate_titles = (
+        SELECT group_concat(title, ' ') FROM alternate_title WHERE media_id = old.media_id
+    ) WHERE rowid = old.media_id;
+END;
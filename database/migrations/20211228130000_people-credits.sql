@@ -0,0 +1,17 @@
+-- Url of the profile picture of a person on TMDB.
+ALTER TABLE people ADD COLUMN profile_path TEXT;
+
+-- People that worked on a media behind the camera, ie its director.
+CREATE TABLE media_crew (
+    id INTEGER PRIMARY KEY,
+    media_id INTEGER NOT NULL,
+    person_id INTEGER NOT NULL,
+    -- Job of the person as reported by TMDB, ie `Director` or `Screenplay`.
+    job TEXT NOT NULL,
+    FOREIGN KEY (media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY (person_id) REFERENCES people(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX media_crew_idx ON media_crew(media_id, person_id, job);
+CREATE INDEX media_crew_person_idx ON media_crew(person_id);
+CREATE INDEX media_cast_person_idx ON media_cast(person_id);
@@ -0,0 +1,10 @@
+-- Dominant color of the poster of a media as `#rrggbb`, used by clients to tint their UI.
+ALTER TABLE _tblmedia ADD COLUMN accent_color TEXT;
+
+-- the color belongs to the old poster once it changes, it is recomputed from the new one.
+CREATE TRIGGER media_accent_color_reset
+AFTER UPDATE OF poster ON _tblmedia
+WHEN old.poster IS NOT new.poster
+BEGIN
+    UPDATE _tblmedia SET accent_color = NULL WHERE id = new.id;
+END;
@@ -0,0 +1,13 @@
+-- Alternate watch orders of shows, ie chronological, along with the position of each episode in
+-- them. Episodes of a show missing from a order are watched after the ones in it.
+CREATE TABLE episode_order (
+    tvshow_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    episode_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (tvshow_id, name, episode_id),
+    FOREIGN KEY(tvshow_id) REFERENCES _tblmedia(id) ON DELETE CASCADE,
+    FOREIGN KEY(episode_id) REFERENCES episode(id) ON DELETE CASCADE
+);
+
+CREATE INDEX episode_order_idx ON episode_order(episode_id, name);
@@ -0,0 +1,26 @@
+-- Ordered lists of media created by users, they can mix movies and episodes. Playlists are only
+-- visible to the user that created them.
+CREATE TABLE playlist (
+    id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    -- Unix timestamp of when the playlist was created.
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX playlist_user_idx ON playlist(user_id);
+
+-- A media can be added to the same playlist more than once, items are thus addressed by their id.
+CREATE TABLE playlist_item (
+    id INTEGER PRIMARY KEY,
+    playlist_id INTEGER NOT NULL,
+    media_id INTEGER NOT NULL,
+    -- Position of the item within the playlist starting at 0. Deleting a media leaves a gap which
+    -- is closed the next time the playlist is reordered.
+    position INTEGER NOT NULL,
+    FOREIGN KEY(playlist_id) REFERENCES playlist(id) ON DELETE CASCADE,
+    FOREIGN KEY(media_id) REFERENCES _tblmedia(id) ON DELETE CASCADE
+);
+
+CREATE INDEX playlist_item_idx ON playlist_item(playlist_id, position);
@@ -0,0 +1,39 @@
+-- Episodes belong to the library of their show. Merging shows of different libraries used to
+-- leave episodes in the library they were scanned into, thus hiding or deleting a library only
+-- affected part of a show. Mediafiles keep the library they were scanned into.
+UPDATE _tblmedia SET library_id = COALESCE((
+    SELECT show.library_id FROM episode
+    INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+    INNER JOIN _tblmedia show ON show.id = _tblseason.tvshowid
+    WHERE episode.id = _tblmedia.id
+), library_id)
+WHERE _tblmedia.id IN (SELECT id FROM episode);
+
+CREATE TRIGGER episode_library_insert
+AFTER INSERT ON episode
+BEGIN
+    UPDATE _tblmedia SET library_id = COALESCE((
+        SELECT show.library_id FROM _tblseason
+        INNER JOIN _tblmedia show ON show.id = _tblseason.tvshowid
+        WHERE _tblseason.id = new.seasonid
+    ), library_id) WHERE _tblmedia.id = new.id;
+END;
+
+CREATE TRIGGER episode_library_update
+AFTER UPDATE OF seasonid ON episode
+BEGIN
+    UPDATE _tblmedia SET library_id = COALESCE((
+        SELECT show.library_id FROM _tblseason
+        INNER JOIN _tblmedia show ON show.id = _tblseason.tvshowid
+        WHERE _tblseason.id = new.seasonid
+    ), library_id) WHERE _tblmedia.id = new.id;
+END;
+
+-- seasons are moved between shows when shows are merged.
+CREATE TRIGGER season_library_update
+AFTER UPDATE OF tvshowid ON _tblseason
+BEGIN
+    UPDATE _tblmedia SET library_id = COALESCE((
+        SELECT show.library_id FROM _tblmedia show WHERE show.id = new.tvshowid
+    ), library_id) WHERE _tblmedia.id IN (SELECT id FROM episode WHERE seasonid = new.id);
+END;
@@ -0,0 +1,15 @@
+-- Ids of a media on IMDB and TVDB as listed on TMDB, used by clients to link out.
+ALTER TABLE _tblmedia ADD COLUMN imdb_id TEXT;
+ALTER TABLE _tblmedia ADD COLUMN tvdb_id INTEGER;
+-- Unix timestamp of when the external ids were fetched, NULL if they never were. Distinguishes
+-- media TMDB has no external ids for from media scanned before external ids were stored.
+ALTER TABLE _tblmedia ADD COLUMN external_ids_fetched_at INTEGER;
+
+-- the ids belong to the old match once a media is matched against another TMDB entry.
+CREATE TRIGGER media_external_ids_reset
+AFTER UPDATE OF tmdb_id ON _tblmedia
+WHEN old.tmdb_id IS NOT new.tmdb_id
+BEGIN
+    UPDATE _tblmedia SET imdb_id = NULL, tvdb_id = NULL, external_ids_fetched_at = NULL
+    WHERE id = new.id;
+END;
@@ -0,0 +1,2 @@
+-- Metadata agent the scanner matches the media of a library with, ie `tmdb`.
+ALTER TABLE library ADD COLUMN agent TEXT NOT NULL DEFAULT 'tmdb';
@@ -0,0 +1,12 @@
+-- Chapters embedded in the container of a mediafile, timestamps are in seconds. Files without
+-- chapters simply have no rows.
+CREATE TABLE chapter (
+    id INTEGER PRIMARY KEY,
+    mediafile_id INTEGER NOT NULL,
+    title TEXT,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL,
+    FOREIGN KEY(mediafile_id) REFERENCES mediafile(id) ON DELETE CASCADE
+);
+
+CREATE INDEX chapter_idx ON chapter(mediafile_id, start_time);
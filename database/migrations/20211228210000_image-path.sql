@@ -0,0 +1,8 @@
+-- Path of images hosted by TMDB, ie `/abc.jpg`, which can be joined with any size TMDB offers to
+-- build the url of a smaller or larger copy. NULL for images that didnt come from TMDB.
+ALTER TABLE assets ADD COLUMN image_path TEXT;
+
+-- remote urls are formatted as `https://image.tmdb.org/t/p/<size>/<path>`.
+UPDATE assets
+SET image_path = '/' || ltrim(substr(substr(remote_url, 28), instr(substr(remote_url, 28), '/') + 1), '/')
+WHERE remote_url LIKE 'https://image.tmdb.org/t/p/%/%';
@@ -0,0 +1,68 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Title a media is known under in another country, ie "Le Parrain" for "The Godfather" in
+/// France.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct AlternateTitle {
+    /// ISO 3166-1 code of the country the title is used in, ie `FR`.
+    pub country: String,
+    pub title: String,
+}
+
+impl AlternateTitle {
+    /// Method returns the alternate titles of a media sorted by country.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of a media object
+    pub async fn get_by_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            AlternateTitle,
+            "SELECT country, title FROM alternate_title
+            WHERE media_id = ?
+            ORDER BY country ASC, title ASC",
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+}
+
+/// Alternate title entry that can be inserted into the db.
+#[derive(Clone)]
+pub struct InsertableAlternateTitle {
+    /// ISO 3166-1 code of the country the title is used in, ie `FR`.
+    pub country: String,
+    pub title: String,
+}
+
+impl InsertableAlternateTitle {
+    /// Method inserts the alternate title of a media. Titles a media already has for the country
+    /// are ignored.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media the title belongs to.
+    pub async fn insert_for_media(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "INSERT OR IGNORE INTO alternate_title (media_id, country, title)
+            VALUES ($1, $2, $3)",
+            media_id,
+            self.country,
+            self.title
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+}
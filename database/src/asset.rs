@@ -1,12 +1,30 @@
 use crate::DatabaseError;
 use std::path::PathBuf;
 
+/// Prefix of the urls of images hosted by TMDB, followed by the size and path of the image.
+pub const TMDB_IMAGE_URL: &str = "https://image.tmdb.org/t/p/";
+
 #[derive(Debug, Clone, Default)]
 pub struct Asset {
     pub id: i64,
     pub remote_url: Option<String>,
     pub local_path: String,
     pub file_ext: String,
+    /// Path of the image on TMDB, ie `/abc.jpg`, if the asset was fetched from TMDB.
+    pub image_path: Option<String>,
+}
+
+/// Function returns the path of a image hosted by TMDB, ie `/abc.jpg` for
+/// `https://image.tmdb.org/t/p/w500/abc.jpg`. The path can be joined with any size TMDB offers.
+pub fn tmdb_image_path(url: &str) -> Option<String> {
+    let (_size, path) = url.strip_prefix(TMDB_IMAGE_URL)?.split_once('/')?;
+    let path = path.trim_start_matches('/');
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Some(format!("/{}", path))
 }
 
 impl Asset {
@@ -68,6 +86,18 @@ impl Asset {
         .id)
     }
 
+    /// Returns all assets that were fetched from a remote url and can thus be re-fetched if their
+    /// local copy is removed.
+    pub async fn get_all_remote(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(
+            sqlx::query_as!(Asset, "SELECT * FROM assets WHERE remote_url IS NOT NULL")
+                .fetch_all(&mut *conn)
+                .await?,
+        )
+    }
+
     pub async fn get_url_by_file(
         conn: &mut crate::Transaction<'_>,
         path: &PathBuf,
@@ -81,6 +111,25 @@ impl Asset {
         .await?
         .remote_url)
     }
+
+    /// Method returns the path on TMDB of the asset stored at `local_path`, `None` if there is no
+    /// such asset or it didnt come from TMDB.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `local_path` - path the asset is served under, ie `images/abc.jpg`.
+    pub async fn get_image_path_by_file(
+        conn: &mut crate::Transaction<'_>,
+        local_path: &str,
+    ) -> Result<Option<String>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            "SELECT image_path FROM assets WHERE local_path = ?",
+            local_path
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .flatten())
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -93,6 +142,7 @@ pub struct InsertableAsset {
 impl InsertableAsset {
     pub async fn insert(self, conn: &mut crate::Transaction<'_>) -> Result<Asset, DatabaseError> {
         let local_path = self.local_path.clone();
+        let image_path = self.remote_url.as_deref().and_then(tmdb_image_path);
 
         if let Ok(x) = sqlx::query_as_unchecked!(
             Asset,
@@ -107,11 +157,12 @@ impl InsertableAsset {
 
         sqlx::query_as_unchecked!(
             Asset,
-            "INSERT OR IGNORE INTO assets (remote_url, local_path, file_ext)
-                VALUES ($1, $2, $3)",
+            "INSERT OR IGNORE INTO assets (remote_url, local_path, file_ext, image_path)
+                VALUES ($1, $2, $3, $4)",
             self.remote_url,
             self.local_path,
-            self.file_ext
+            self.file_ext,
+            image_path
         )
         .execute(&mut *conn)
         .await?;
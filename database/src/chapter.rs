@@ -0,0 +1,67 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Chapter embedded in the container of a mediafile, timestamps are in seconds.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Chapter {
+    /// Title of the chapter, `None` if the container doesnt name it.
+    pub title: Option<String>,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Chapter {
+    /// Method returns the chapters of a mediafile ordered by when they start. Returns a empty
+    /// list if the mediafile has no chapters.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `mediafile_id` - id of the mediafile.
+    pub async fn get_of_mediafile(
+        conn: &mut crate::Transaction<'_>,
+        mediafile_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Chapter,
+            r#"SELECT title, start_time as "start: f64", end_time as "end: f64" FROM chapter
+            WHERE mediafile_id = ?
+            ORDER BY start_time ASC, id ASC"#,
+            mediafile_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method replaces the chapters of a mediafile.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `mediafile_id` - id of the mediafile.
+    /// * `chapters` - the new chapters of the mediafile.
+    pub async fn set_for_mediafile(
+        conn: &mut crate::Transaction<'_>,
+        mediafile_id: i64,
+        chapters: &[Self],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM chapter WHERE mediafile_id = ?", mediafile_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for chapter in chapters {
+            sqlx::query!(
+                "INSERT INTO chapter (mediafile_id, title, start_time, end_time)
+                VALUES ($1, $2, $3, $4)",
+                mediafile_id,
+                chapter.title,
+                chapter.start,
+                chapter.end
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -15,6 +15,9 @@ pub struct Episode {
     pub seasonid: i64,
     /// episode number
     pub episode: i64,
+    /// Position of the episode across all regular seasons of the show, only set for episodes of
+    /// libraries flagged as anime.
+    pub absolute_number: Option<i64>,
 
     /// Regerence to a media object which represents this epsiode.
     /// We are essnetially aliasing and wrapping around Media transparently, behind the
@@ -23,6 +26,21 @@ pub struct Episode {
     pub media: Media,
 }
 
+/// Episode of a show along with the progress of a user through it, returned by
+/// [`Episode::get_flat_for_show`](Episode::get_flat_for_show).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct FlatEpisode {
+    pub id: i64,
+    pub name: String,
+    pub season: i64,
+    pub episode: i64,
+    pub thumbnail_url: Option<String>,
+    /// Offset in seconds the user has watched up to, `0` if the user never started the episode.
+    pub progress: i64,
+    /// Duration of the episode in seconds, `0` if unknown.
+    pub duration: i64,
+}
+
 /// This struct is purely used for querying episodes which later gets converted into a Episode
 /// struct
 #[derive(PartialEq, Debug, Copy, Clone, sqlx::FromRow)]
@@ -30,6 +48,7 @@ pub struct EpisodeWrapper {
     pub id: i64,
     pub seasonid: i64,
     pub episode_: i64,
+    pub absolute_number: Option<i64>,
 }
 
 impl Episode {
@@ -39,7 +58,7 @@ impl Episode {
     ) -> Result<Self, DatabaseError> {
         let wrapper = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT id as "id!", seasonid, episode_
+            r#"SELECT id as "id!", seasonid, episode_, absolute_number
             FROM episode
             WHERE seasonid = ?
             ORDER BY episode_ ASC"#,
@@ -59,11 +78,11 @@ impl Episode {
     ) -> Result<Self, DatabaseError> {
         let wrapper = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT episode.id as "id!", seasonid, episode_
+            r#"SELECT episode.id as "id!", seasonid, episode_, episode.absolute_number
             FROM episode
             INNER JOIN season on season.id = episode.seasonid
             WHERE season.tvshowid = ?
-            ORDER BY episode_ ASC, season.season_number ASC
+            ORDER BY season.season_number = 0, season.season_number ASC, episode_ ASC
             LIMIT 1"#,
             tv_id
         )
@@ -88,11 +107,12 @@ impl Episode {
 
         let wrappers = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT episode.id as "id!", episode.episode_, episode.seasonid FROM episode
+            r#"SELECT episode.id as "id!", episode.episode_, episode.seasonid, episode.absolute_number
+                FROM episode
                 INNER JOIN season ON season.id = episode.seasonid
                 INNER JOIN tv_show ON tv_show.id = season.tvshowid
                 WHERE tv_show.id = ?
-                ORDER BY season.season_number, episode.episode_"#,
+                ORDER BY season.season_number = 0, season.season_number, episode.episode_"#,
             tv_show_id
         )
         .fetch_all(&mut *conn)
@@ -107,6 +127,81 @@ impl Episode {
         Ok(episodes)
     }
 
+    /// Method returns a page of the episodes of a show across all seasons, ordered by season and
+    /// episode with specials last, along with the progress of `uid` through them. Returns the
+    /// page along with the total number of episodes of the show.
+    ///
+    /// If `order` is set the episodes are ordered by the alternate watch order of that name
+    /// instead, see [`EpisodeOrder`](crate::episode_order::EpisodeOrder). Episodes missing from
+    /// the order come last in the standard order.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the show.
+    /// * `uid` - username of the user whose progress is returned.
+    /// * `order` - name of the alternate watch order to use.
+    /// * `limit` - max number of episodes to return.
+    /// * `offset` - number of episodes to skip.
+    pub async fn get_flat_for_show(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        uid: &str,
+        order: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<FlatEpisode>, i64), DatabaseError> {
+        let items = sqlx::query_as!(
+            FlatEpisode,
+            r#"SELECT episode.id as "id!", _tblmedia.name, season.season_number as season,
+                episode.episode_ as episode, assets.local_path as thumbnail_url,
+                COALESCE(progress.delta, 0) as "progress!: i64",
+                COALESCE((SELECT MAX(duration) FROM mediafile
+                    WHERE mediafile.media_id = episode.id), 0) as "duration!: i64"
+            FROM episode
+            INNER JOIN season ON season.id = episode.seasonid
+            INNER JOIN _tblmedia ON _tblmedia.id = episode.id
+            LEFT OUTER JOIN assets ON assets.id = _tblmedia.backdrop
+            LEFT OUTER JOIN progress ON progress.media_id = episode.id AND progress.user_id = $2
+            LEFT OUTER JOIN episode_order ON episode_order.episode_id = episode.id
+                AND episode_order.name = $5
+            WHERE season.tvshowid = $1
+            ORDER BY episode_order.position IS NULL, episode_order.position,
+                season.season_number = 0, season.season_number, episode.episode_
+            LIMIT $3 OFFSET $4"#,
+            tv_id,
+            uid,
+            limit,
+            offset,
+            order
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let total = Self::count_of_show(&mut *conn, tv_id).await?;
+
+        Ok((items, total))
+    }
+
+    /// Method returns the number of episodes of a show across all seasons.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the show.
+    pub async fn count_of_show(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM episode
+            INNER JOIN season ON season.id = episode.seasonid
+            WHERE season.tvshowid = ?"#,
+            tv_id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count)
+    }
+
     // FIXME: This function might be especially heavy on the DB.
     /// Method returns all of the episodes belonging to a season.
     ///
@@ -119,7 +214,8 @@ impl Episode {
     ) -> Result<Vec<Episode>, DatabaseError> {
         let wrappers = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT id as "id!", episode_, seasonid FROM episode WHERE seasonid = ?"#,
+            r#"SELECT id as "id!", episode_, seasonid, absolute_number FROM episode
+            WHERE seasonid = ?"#,
             season_id
         )
         .fetch_all(&mut *conn)
@@ -151,7 +247,8 @@ impl Episode {
     ) -> Result<Episode, DatabaseError> {
         let wrapper = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT episode.id as "id!", episode.episode_, episode.seasonid  FROM episode
+            r#"SELECT episode.id as "id!", episode.episode_, episode.seasonid, episode.absolute_number
+            FROM episode
             INNER JOIN season ON season.id = episode.seasonid
             WHERE season.tvshowid = ?
             AND season.season_number = ?
@@ -223,16 +320,43 @@ impl Episode {
         Ok(record.season_number)
     }
 
-    /// Function will query for the episode after the episode passed in.
+    /// Function will query for the episode after the episode passed in. Specials (season 0) are
+    /// never reached from a regular season, and navigating within the specials stays there.
+    /// Episodes with an absolute number navigate by absolute order instead.
     pub async fn get_next_episode(
         &self,
         conn: &mut crate::Transaction<'_>,
     ) -> Result<Episode, DatabaseError> {
+        if let Some(absolute_number) = self.absolute_number {
+            let record = sqlx::query_as!(
+                EpisodeWrapper,
+                r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_, episode.absolute_number
+                FROM episode
+                INNER JOIN season ON season.id = episode.seasonid
+                WHERE season.tvshowid = (
+                    SELECT _tblseason.tvshowid FROM _tblseason
+                    WHERE _tblseason.id = ?
+                ) AND episode.absolute_number > ?
+                ORDER BY episode.absolute_number
+                LIMIT 1"#,
+                self.seasonid,
+                absolute_number
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if let Some(record) = record {
+                let ep = Media::get(conn, record.id as i64).await?;
+                return Ok(record.into_episode(ep));
+            }
+        }
+
         let season_number = self.get_season_number(&mut *conn).await?;
 
         let record = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_ FROM episode
+            r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_, episode.absolute_number
+            FROM episode
             INNER JOIN season ON season.id = episode.seasonid
             WHERE season.tvshowid = (
                 SELECT _tblseason.tvshowid FROM _tblseason
@@ -240,12 +364,13 @@ impl Episode {
             ) AND ((
                 episode.episode_ > ? AND
                 season.season_number = ?
-            ) OR season.season_number > ?)
+            ) OR (season.season_number > ? AND ? > 0))
             ORDER BY season.season_number, episode.episode_
             LIMIT 1"#,
             self.seasonid,
             self.episode,
             season_number,
+            season_number,
             season_number
         )
         .fetch_one(&mut *conn)
@@ -256,16 +381,44 @@ impl Episode {
         Ok(record.into_episode(ep))
     }
 
-    /// Function will query for the episode after the episode passed in.
+    /// Function will query for the episode before the episode passed in. Like
+    /// [`get_next_episode`](Episode::get_next_episode) this never crosses between specials
+    /// (season 0) and the regular seasons, and episodes with an absolute number navigate by
+    /// absolute order.
     pub async fn get_prev_episode(
         &self,
         conn: &mut crate::Transaction<'_>,
     ) -> Result<Episode, DatabaseError> {
+        if let Some(absolute_number) = self.absolute_number {
+            let record = sqlx::query_as!(
+                EpisodeWrapper,
+                r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_, episode.absolute_number
+                FROM episode
+                INNER JOIN season ON season.id = episode.seasonid
+                WHERE season.tvshowid = (
+                    SELECT _tblseason.tvshowid FROM _tblseason
+                    WHERE _tblseason.id = ?
+                ) AND episode.absolute_number < ?
+                ORDER BY episode.absolute_number DESC
+                LIMIT 1"#,
+                self.seasonid,
+                absolute_number
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            if let Some(record) = record {
+                let ep = Media::get(conn, record.id as i64).await?;
+                return Ok(record.into_episode(ep));
+            }
+        }
+
         let season_number = self.get_season_number(&mut *conn).await?;
 
         let record = sqlx::query_as!(
             EpisodeWrapper,
-            r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_ FROM episode
+            r#"SELECT episode.id as "id!", episode.seasonid, episode.episode_, episode.absolute_number
+            FROM episode
             INNER JOIN season ON season.id = episode.seasonid
             WHERE season.tvshowid = (
                 SELECT _tblseason.tvshowid FROM _tblseason
@@ -273,7 +426,7 @@ impl Episode {
             ) AND ((
                 episode.episode_ < ? AND
                 season.season_number = ?
-            ) OR season.season_number < ?)
+            ) OR (season.season_number < ? AND season.season_number > 0))
             ORDER BY season.season_number DESC, episode.episode_ DESC
             LIMIT 1"#,
             self.seasonid,
@@ -320,6 +473,28 @@ impl Episode {
         Ok(Some(result.into_episode(ep)))
     }
 
+    /// Method sets the absolute number of a episode.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the episode.
+    /// * `absolute_number` - position of the episode across all regular seasons of the show.
+    pub async fn set_absolute_number(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        absolute_number: Option<i64>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE episode SET absolute_number = ? WHERE id = ?",
+            absolute_number,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
     /// Method deletes a episode based on the tv show id, season number, and episode number
     ///
     /// # Arguments
@@ -384,6 +559,7 @@ impl EpisodeWrapper {
             id: self.id,
             seasonid: self.seasonid,
             episode: self.episode_,
+            absolute_number: self.absolute_number,
             media,
         }
     }
@@ -0,0 +1,99 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Intro of a episode, timestamps are in seconds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct Intro {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Credits of a episode, they are assumed to run until the end of the episode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct Credits {
+    pub start: i64,
+}
+
+/// Struct holds the markers of a episode which players use to offer skipping the intro and
+/// credits. Markers that aren't known are `None`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct EpisodeMarkers {
+    #[serde(default)]
+    pub intro: Option<Intro>,
+    #[serde(default)]
+    pub credits: Option<Credits>,
+}
+
+impl EpisodeMarkers {
+    /// Method returns the markers of a episode, all markers are `None` if none were set.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `episode_id` - id of the episode.
+    pub async fn get(
+        conn: &mut crate::Transaction<'_>,
+        episode_id: i64,
+    ) -> Result<Self, DatabaseError> {
+        let record = sqlx::query!(
+            r#"SELECT intro_start as "intro_start: i64", intro_end as "intro_end: i64",
+                credits_start as "credits_start: i64"
+            FROM episode_markers WHERE episode_id = ?"#,
+            episode_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(record
+            .map(|x| Self {
+                intro: x
+                    .intro_start
+                    .zip(x.intro_end)
+                    .map(|(start, end)| Intro { start, end }),
+                credits: x.credits_start.map(|start| Credits { start }),
+            })
+            .unwrap_or_default())
+    }
+
+    /// Method replaces the markers of a episode.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `episode_id` - id of the episode.
+    pub async fn set(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        episode_id: i64,
+    ) -> Result<(), DatabaseError> {
+        let intro_start = self.intro.map(|x| x.start);
+        let intro_end = self.intro.map(|x| x.end);
+        let credits_start = self.credits.map(|x| x.start);
+
+        sqlx::query!(
+            "INSERT OR REPLACE INTO episode_markers (episode_id, intro_start, intro_end, credits_start)
+            VALUES ($1, $2, $3, $4)",
+            episode_id,
+            intro_start,
+            intro_end,
+            credits_start
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the markers are consistent, ie they arent negative, the intro starts
+    /// before it ends and it ends before the credits start.
+    pub fn is_valid(&self) -> bool {
+        let intro = self.intro.map_or(true, |x| x.start >= 0 && x.start < x.end);
+        let credits = self.credits.map_or(true, |x| x.start >= 0);
+        let ordered = match (self.intro, self.credits) {
+            (Some(intro), Some(credits)) => intro.end <= credits.start,
+            _ => true,
+        };
+
+        intro && credits && ordered
+    }
+}
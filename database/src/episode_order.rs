@@ -0,0 +1,100 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+
+/// Name of the standard order of episodes, by season and episode with specials last. It is
+/// available for every show and cant be replaced.
+pub const DEFAULT_ORDER: &str = "default";
+
+/// Watch order of a show along with the number of episodes placed in it, returned by
+/// [`EpisodeOrder::get_for_show`](EpisodeOrder::get_for_show).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct EpisodeOrder {
+    pub name: String,
+    pub episode_count: i64,
+}
+
+impl EpisodeOrder {
+    /// Method returns the alternate watch orders of a show ordered by name. The standard order
+    /// isnt stored and thus not returned.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the show.
+    pub async fn get_for_show(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            EpisodeOrder,
+            r#"SELECT name as "name!", COUNT(*) as "episode_count!: i64" FROM episode_order
+            WHERE tvshow_id = ?
+            GROUP BY name
+            ORDER BY name ASC"#,
+            tv_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns whether a show has a alternate watch order named `name`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the show.
+    /// * `name` - name of the order.
+    pub async fn exists(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        name: &str,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT 1 as found FROM episode_order WHERE tvshow_id = ? AND name = ? LIMIT 1",
+            tv_id,
+            name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some())
+    }
+
+    /// Method replaces the watch order `name` of a show with `episodes` in the order given.
+    /// Passing no episodes removes the order.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the show.
+    /// * `name` - name of the order.
+    /// * `episodes` - ids of the episodes of the show in watch order.
+    pub async fn set(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        name: &str,
+        episodes: &[i64],
+    ) -> Result<usize, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM episode_order WHERE tvshow_id = ? AND name = ?",
+            tv_id,
+            name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for (position, episode_id) in episodes.iter().enumerate() {
+            let position = position as i64;
+
+            sqlx::query!(
+                "INSERT INTO episode_order (tvshow_id, name, episode_id, position)
+                VALUES ($1, $2, $3, $4)",
+                tv_id,
+                name,
+                episode_id,
+                position
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(episodes.len())
+    }
+}
@@ -11,6 +11,14 @@ pub struct Genre {
     pub name: String,
 }
 
+/// Number of medias tagged with a genre
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct GenreCount {
+    /// Genre name, ie "Action"
+    pub name: String,
+    pub count: i64,
+}
+
 /// Intermediary table showing the relationship between a media and a genre
 #[derive(Clone, Debug, PartialEq)]
 pub struct GenreMedia {
@@ -59,6 +67,37 @@ impl Genre {
         .await?)
     }
 
+    /// Method returns each genre used within a library along with the number of medias tagged
+    /// with it, ordered by the number of medias descending. Medias hidden by the user aren't
+    /// counted.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of a library
+    /// * `uid` - username of the user.
+    pub async fn get_counts_by_library(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        uid: &str,
+    ) -> Result<Vec<GenreCount>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            GenreCount,
+            r#"SELECT genre.name, COUNT(genre_media.media_id) as "count!: i64" FROM genre
+                INNER JOIN genre_media ON genre_media.genre_id = genre.id
+                INNER JOIN _tblmedia ON _tblmedia.id = genre_media.media_id
+                WHERE _tblmedia.library_id = ? AND NOT _tblmedia.media_type = "episode"
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = ?)
+                GROUP BY genre.id
+                ORDER BY COUNT(genre_media.media_id) DESC, genre.name ASC"#,
+            library_id,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
     /// Method returns a genre based on genre_id and media_id
     ///
     /// # Arguments
@@ -79,6 +118,39 @@ impl Genre {
         .await?)
     }
 
+    /// Method replaces the genres a media is tagged with by the genres supplied. Genres that dont
+    /// exist yet are created.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of a media object
+    /// * `names` - names of the genres the media should be tagged with
+    pub async fn set_for_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        names: &[String],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM genre_media WHERE media_id = ?", media_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for name in names {
+            let genre_id = InsertableGenre { name: name.clone() }
+                .insert(&mut *conn)
+                .await?;
+
+            sqlx::query!(
+                "INSERT OR IGNORE INTO genre_media (genre_id, media_id) VALUES ($1, $2)",
+                genre_id,
+                media_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
     /// Method removes a genre from the genre table based on its id
     ///
     /// # Arguments
@@ -0,0 +1,103 @@
+use crate::media::Media;
+use crate::DatabaseError;
+
+use std::time::SystemTime;
+
+/// Media a user hid from their browse. Hidden media are left out of listings and searches of that
+/// user only, everyone else still sees them.
+pub struct HiddenMedia;
+
+impl HiddenMedia {
+    /// Method hides a media for a user. Hiding a media twice is a noop.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_id` - id of the media.
+    pub async fn hide(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_id: i64,
+    ) -> Result<(), DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO hidden_media (user_id, media_id, hidden_at) VALUES ($1, $2, $3)",
+            uid,
+            media_id,
+            timestamp
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Method unhides a media for a user. Returns the number of rows removed, `0` if the media
+    /// wasnt hidden.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_id` - id of the media.
+    pub async fn unhide(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "DELETE FROM hidden_media WHERE user_id = ? AND media_id = ?",
+            uid,
+            media_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns whether a user hid a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_id` - id of the media.
+    pub async fn is_hidden(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_id: i64,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            "SELECT media_id FROM hidden_media WHERE user_id = ? AND media_id = ?",
+            uid,
+            media_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some())
+    }
+
+    /// Method returns the media a user hid, most recently hidden first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Vec<Media>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Media,
+            r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                INNER JOIN hidden_media ON hidden_media.media_id = media.id
+                WHERE hidden_media.user_id = ?
+                ORDER BY hidden_media.hidden_at DESC, media.id ASC"#,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+}
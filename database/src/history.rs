@@ -0,0 +1,244 @@
+use crate::progress::WATCHED_THRESHOLD;
+use crate::DatabaseError;
+
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Struct represents a single play of a media, ie a user watching it to completion.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct History {
+    pub id: i64,
+    pub user_id: String,
+    pub media_id: i64,
+    /// Unix timestamp of when the media was finished.
+    pub watched_at: i64,
+}
+
+/// A media along with how often it has been played.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct PopularMedia {
+    pub id: i64,
+    pub name: String,
+    pub poster_path: Option<String>,
+    pub rating: Option<i64>,
+    pub plays: i64,
+}
+
+/// Aggregate watch time of a user within a time range, returned by
+/// [`History::get_stats_for_user`](History::get_stats_for_user).
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct WatchStats {
+    /// Seconds spent watching, the duration of every finished media plus the offset of media
+    /// that were started but not finished.
+    pub seconds_watched: i64,
+    /// Number of plays, a media finished twice counts twice. Media finished without a play being
+    /// recorded count once.
+    pub items_finished: i64,
+    /// Genres of the finished media ordered by their number of plays.
+    pub top_genres: Vec<GenrePlays>,
+}
+
+/// A genre along with how often media of it have been played.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct GenrePlays {
+    pub name: String,
+    pub plays: i64,
+}
+
+impl History {
+    /// Method records that a user has finished watching a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    pub async fn record(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        mid: i64,
+    ) -> Result<i64, DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Ok(sqlx::query!(
+            "INSERT INTO watch_history (user_id, media_id, watched_at) VALUES ($1, $2, $3)",
+            uid,
+            mid,
+            timestamp
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+
+    /// Method returns all plays of a user, most recent first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_all_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            History,
+            "SELECT * FROM watch_history WHERE user_id = ? ORDER BY watched_at DESC, id DESC",
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the most played media ordered by their number of plays since `since`, ties
+    /// are broken by rating. Plays of episodes count towards their tv show. Media hidden by the user
+    /// and media in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `since` - unix timestamp from which on plays are counted.
+    /// * `limit` - max number of media to return.
+    pub async fn get_popular(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<PopularMedia>, DatabaseError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, PopularMedia>(
+            r#"SELECT media.id, media.name, media.poster_path, media.rating,
+                COUNT(watch_history.id) as plays
+            FROM watch_history
+            LEFT JOIN episode ON episode.id = watch_history.media_id
+            LEFT JOIN season ON season.id = episode.seasonid
+            JOIN media ON media.id = COALESCE(season.tvshowid, watch_history.media_id)
+            JOIN library ON library.id = media.library_id
+
+            WHERE NOT library.hidden
+            AND watch_history.watched_at >= ?
+            AND NOT EXISTS (
+                SELECT 1 FROM hidden_media
+                WHERE hidden_media.media_id = media.id AND hidden_media.user_id = ?)
+
+            GROUP BY media.id
+            ORDER BY plays DESC, media.rating IS NULL, media.rating DESC, media.id
+            LIMIT ?"#,
+        )
+        .bind(since)
+        .bind(uid)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the watch stats of a user between `from` and `to`. Plays of episodes count
+    /// towards the genres of their tv show. Returns zeros if the user watched nothing in the range.
+    ///
+    /// Media are finished once they were played or, for users who dont have plays recorded
+    /// automatically, once their progress is past [`WATCHED_THRESHOLD`]. The time watched is an
+    /// approximation, we only know the latest offset into a media and when it was reached, not
+    /// when the user started watching. Every media thus counts with its offset or duration, but
+    /// never with more than the time that passed between `from` and when it was last watched, such
+    /// that media started before the range arent counted in full.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `from` - unix timestamp from which on activity is counted.
+    /// * `to` - unix timestamp up to which activity is counted, exclusive.
+    /// * `genre_limit` - max number of genres to return.
+    pub async fn get_stats_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        from: i64,
+        to: i64,
+        genre_limit: i64,
+    ) -> Result<WatchStats, DatabaseError> {
+        let finished = sqlx::query!(
+            r#"SELECT COUNT(watch_history.id) as "items_finished!: i64",
+                COALESCE(SUM(MIN(_tblmedia.duration, watch_history.watched_at - $2)), 0)
+                    as "seconds!: i64"
+            FROM watch_history
+            LEFT JOIN _tblmedia ON _tblmedia.id = watch_history.media_id
+            WHERE watch_history.user_id = $1
+            AND watch_history.watched_at >= $2 AND watch_history.watched_at < $3"#,
+            uid,
+            from,
+            to
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // finished media without a play in the range, ie because plays arent recorded for the user.
+        let unrecorded = sqlx::query!(
+            r#"SELECT COUNT(progress.id) as "items_finished!: i64",
+                COALESCE(SUM(MIN(_tblmedia.duration, progress.populated - $2)), 0)
+                    as "seconds!: i64"
+            FROM progress
+            JOIN _tblmedia ON _tblmedia.id = progress.media_id
+            WHERE progress.user_id = $1
+            AND progress.populated >= $2 AND progress.populated < $3
+            AND _tblmedia.duration > 0
+            AND CAST(progress.delta AS REAL) / _tblmedia.duration > $4
+            AND NOT EXISTS (
+                SELECT 1 FROM watch_history
+                WHERE watch_history.user_id = $1 AND watch_history.media_id = progress.media_id
+                AND watch_history.watched_at >= $2 AND watch_history.watched_at < $3)"#,
+            uid,
+            from,
+            to,
+            WATCHED_THRESHOLD
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // media past the watched threshold are already counted above.
+        let in_progress = sqlx::query_scalar!(
+            r#"SELECT COALESCE(SUM(MIN(progress.delta, progress.populated - $2)), 0)
+                as "seconds!: i64"
+            FROM progress
+            LEFT JOIN _tblmedia ON _tblmedia.id = progress.media_id
+            WHERE progress.user_id = $1
+            AND progress.populated >= $2 AND progress.populated < $3
+            AND NOT (COALESCE(_tblmedia.duration, 0) > 0
+                AND CAST(progress.delta AS REAL) / _tblmedia.duration > $4)"#,
+            uid,
+            from,
+            to,
+            WATCHED_THRESHOLD
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        let top_genres = sqlx::query_as::<_, GenrePlays>(
+            r#"SELECT genre.name, COUNT(watch_history.id) as plays
+            FROM watch_history
+            LEFT JOIN episode ON episode.id = watch_history.media_id
+            LEFT JOIN season ON season.id = episode.seasonid
+            JOIN genre_media ON genre_media.media_id = COALESCE(season.tvshowid, watch_history.media_id)
+            JOIN genre ON genre.id = genre_media.genre_id
+
+            WHERE watch_history.user_id = ?
+            AND watch_history.watched_at >= ? AND watch_history.watched_at < ?
+
+            GROUP BY genre.id
+            ORDER BY plays DESC, genre.name
+            LIMIT ?"#,
+        )
+        .bind(uid)
+        .bind(from)
+        .bind(to)
+        .bind(genre_limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(WatchStats {
+            seconds_watched: finished.seconds + unrecorded.seconds + in_progress,
+            items_finished: finished.items_finished + unrecorded.items_finished,
+            top_genres,
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use crate::media::Media;
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Struct shows a single keyword entry. Keywords are finer grained tags than genres.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Keyword {
+    pub id: i64,
+    /// Keyword name, ie "time travel"
+    pub name: String,
+}
+
+impl Keyword {
+    /// Method returns a keyword based on its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of a keyword
+    pub async fn get_by_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Self, DatabaseError> {
+        Ok(
+            sqlx::query_as!(Keyword, "SELECT * FROM keyword WHERE id = ?", id)
+                .fetch_one(&mut *conn)
+                .await?,
+        )
+    }
+
+    /// Method returns all keywords a media is tagged with.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of a media object
+    pub async fn get_by_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Keyword,
+            r#"SELECT keyword.id as "id!", keyword.name FROM keyword
+                INNER JOIN keyword_media ON keyword_media.keyword_id = keyword.id
+                WHERE keyword_media.media_id = ?
+                ORDER BY keyword.name ASC"#,
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all medias in visible libraries tagged with a keyword, sorted by name. Medias
+    /// hidden by the user are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of a keyword
+    /// * `uid` - username of the user.
+    pub async fn get_media(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        uid: &str,
+    ) -> Result<Vec<Media>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Media,
+            r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                INNER JOIN keyword_media ON keyword_media.media_id = media.id
+                INNER JOIN library ON library.id = media.library_id
+                WHERE keyword_media.keyword_id = ? AND NOT library.hidden
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = media.id AND hidden_media.user_id = ?)
+                ORDER BY COALESCE(media.sort_title, media.name) ASC"#,
+            id,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+}
+
+/// Keyword entry that can be inserted into the db.
+#[derive(Clone)]
+pub struct InsertableKeyword {
+    /// Keyword name
+    pub name: String,
+}
+
+impl InsertableKeyword {
+    /// Method inserts a new keyword into the table otherwise returns the id of a existing entry.
+    /// Like genres, keywords are matched case insensitively.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        let name = self.name.clone().to_uppercase();
+
+        if let Some(record) = sqlx::query!(
+            "SELECT id FROM keyword
+            WHERE UPPER(keyword.name) LIKE ?",
+            name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            return Ok(record.id);
+        }
+
+        Ok(
+            sqlx::query!(r#"INSERT INTO keyword (name) VALUES ($1)"#, self.name)
+                .execute(&mut *conn)
+                .await?
+                .last_insert_rowid(),
+        )
+    }
+
+    /// Method inserts the keyword and tags a media with it.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media to tag.
+    pub async fn insert_for_media(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<i64, DatabaseError> {
+        let keyword_id = self.insert(&mut *conn).await?;
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO keyword_media (keyword_id, media_id)
+            VALUES ($1, $2)",
+            keyword_id,
+            media_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(keyword_id)
+    }
+}
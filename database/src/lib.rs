@@ -10,23 +10,41 @@ use std::sync::atomic::Ordering;
 use sqlx::ConnectOptions;
 use tracing::{info, instrument};
 
+pub mod alternate_title;
 pub mod asset;
+pub mod chapter;
 pub mod episode;
+pub mod episode_markers;
+pub mod episode_order;
 pub mod error;
 pub mod genre;
+pub mod hidden_media;
+pub mod history;
+pub mod keyword;
 pub mod library;
+pub mod match_candidate;
 pub mod media;
 pub mod mediafile;
 pub mod movie;
+pub mod person;
+pub mod playback_window;
+pub mod playlist;
 pub mod progress;
+pub mod rating;
 #[cfg(feature = "sqlite")]
 pub mod rw_pool;
+pub mod scrobble;
+pub mod search_index;
 pub mod season;
+pub mod tag;
 #[cfg(test)]
 pub mod tests;
+pub mod tmdb_episode;
 pub mod tv;
 pub mod user;
 pub mod utils;
+pub mod version_progress;
+pub mod webhook;
 
 pub use crate::error::DatabaseError;
 /// Ugly hack because of a shitty deadlock in `Pool`
@@ -34,6 +34,22 @@ impl Default for MediaType {
     }
 }
 
+/// Enum represents the source a library fetches the metadata of its media from. When returned in
+/// a http response, the fields are lowercase.
+#[derive(Copy, Serialize, Debug, Clone, Eq, PartialEq, Deserialize, Hash, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum MetadataAgent {
+    /// [The Movie Database](https://www.themoviedb.org).
+    Tmdb,
+}
+
+impl Default for MetadataAgent {
+    fn default() -> Self {
+        Self::Tmdb
+    }
+}
+
 /// Library struct which we can use to deserialize database queries into.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Library {
@@ -50,6 +66,20 @@ pub struct Library {
     /// moment only `movie` and `tv` are supported
     // TODO: support mixed content, music
     pub media_type: MediaType,
+
+    /// Whether the library contains anime, in which case absolute episode numbers are resolved
+    /// to a season and episode.
+    #[serde(default)]
+    pub anime: bool,
+
+    /// Language of the titles in the library, ie `en`, used to strip leading articles when
+    /// sorting its media. Libraries without a language are treated as english.
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Metadata agent the media of the library are matched with.
+    #[serde(default)]
+    pub agent: MetadataAgent,
 }
 
 impl Library {
@@ -59,7 +89,7 @@ impl Library {
     /// This method will not return the locations indexed for this library, if you need those you
     /// must query for them separately.
     pub async fn get_all(conn: &mut crate::Transaction<'_>) -> Vec<Self> {
-        sqlx::query!(r#"SELECT id, name, media_type as "media_type: MediaType" FROM library WHERE NOT hidden"#)
+        sqlx::query!(r#"SELECT id, name, media_type as "media_type: MediaType", anime as "anime: bool", language, agent as "agent: MetadataAgent" FROM library WHERE NOT hidden"#)
             .fetch_all(&mut *conn)
             .await
             .unwrap_or_default()
@@ -68,6 +98,9 @@ impl Library {
                 id: x.id,
                 name: x.name,
                 media_type: x.media_type,
+                anime: x.anime,
+                language: x.language,
+                agent: x.agent,
                 locations: vec![],
             })
             .collect()
@@ -97,7 +130,9 @@ impl Library {
         lib_id: i64,
     ) -> Result<Self, DatabaseError> {
         let library = sqlx::query!(
-            r#"SELECT id, name, media_type as "media_type: MediaType" FROM library
+            r#"SELECT id, name, media_type as "media_type: MediaType", anime as "anime: bool",
+                language, agent as "agent: MetadataAgent"
+            FROM library
             WHERE id = ?"#,
             lib_id
         )
@@ -116,6 +151,9 @@ impl Library {
             id: library.id,
             name: library.name,
             media_type: library.media_type,
+            anime: library.anime,
+            language: library.language,
+            agent: library.agent,
             locations,
         })
     }
@@ -135,6 +173,62 @@ impl Library {
             .rows_affected() as usize)
     }
 
+    /// Method returns the metadata agent of a library.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the library.
+    pub async fn get_agent(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<MetadataAgent, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT agent as "agent: MetadataAgent" FROM library WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method sets the metadata agent of a library, media matched before keep their metadata
+    /// until they are rematched or refreshed. Returns the number of libraries updated, `0` if the
+    /// library doesnt exist.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the library.
+    /// * `agent` - the new metadata agent.
+    pub async fn set_agent(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        agent: MetadataAgent,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("UPDATE library SET agent = ? WHERE id = ?", agent, id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+
+    /// Method returns whether a library has been hidden, ie because it is being deleted.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the library.
+    pub async fn is_hidden(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT hidden as "hidden: bool" FROM library WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .hidden)
+    }
+
     pub async fn mark_hidden(
         conn: &mut crate::Transaction<'_>,
         id: i64,
@@ -154,6 +248,12 @@ pub struct InsertableLibrary {
     pub name: String,
     pub locations: Vec<String>,
     pub media_type: MediaType,
+    #[serde(default)]
+    pub anime: bool,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub agent: MetadataAgent,
 }
 
 impl InsertableLibrary {
@@ -163,9 +263,13 @@ impl InsertableLibrary {
     /// * `conn` - mutable reference to a sqlx transaction.
     pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
         let lib_id = sqlx::query!(
-            r#"INSERT INTO library (name, media_type) VALUES ($1, $2)"#,
+            r#"INSERT INTO library (name, media_type, anime, language, agent)
+            VALUES ($1, $2, $3, $4, $5)"#,
             self.name,
-            self.media_type
+            self.media_type,
+            self.anime,
+            self.language,
+            self.agent
         )
         .execute(&mut *conn)
         .await?
@@ -0,0 +1,111 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A TMDB result that matched a media about as well as the result the media was matched to. Media
+/// with candidates need to be reviewed by a user.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Default)]
+pub struct MatchCandidate {
+    #[serde(skip_serializing)]
+    pub media_id: i64,
+    pub tmdb_id: i64,
+    pub title: String,
+    pub release_date: Option<String>,
+    pub poster_path: Option<String>,
+    /// How confident the scanner is that this result is what the file is, between `0.0` and
+    /// `1.0`.
+    pub confidence: f64,
+}
+
+impl MatchCandidate {
+    /// Method returns the candidates of a media, most confident first. Empty if the media doesnt
+    /// need to be reviewed.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    pub async fn get_for_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MatchCandidate,
+            "SELECT media_id, tmdb_id, title, release_date, poster_path, confidence
+            FROM match_candidate
+            WHERE media_id = ?
+            ORDER BY confidence DESC, id ASC",
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the ids of all media in visible libraries that need to be reviewed, sorted
+    /// by name.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_media_needing_review(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT DISTINCT _tblmedia.id as "id!" FROM _tblmedia
+            JOIN match_candidate ON match_candidate.media_id = _tblmedia.id
+            JOIN library ON library.id = _tblmedia.library_id
+            WHERE NOT library.hidden
+            ORDER BY _tblmedia.name ASC"#
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method replaces the candidates of a media, flagging it for review.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    /// * `candidates` - the candidates of the media.
+    pub async fn set_for_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        candidates: &[Self],
+    ) -> Result<(), DatabaseError> {
+        Self::clear_for_media(&mut *conn, media_id).await?;
+
+        for candidate in candidates {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO match_candidate
+                    (media_id, tmdb_id, title, release_date, poster_path, confidence)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                media_id,
+                candidate.tmdb_id,
+                candidate.title,
+                candidate.release_date,
+                candidate.poster_path,
+                candidate.confidence
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Method removes the candidates of a media, marking it as reviewed.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    pub async fn clear_for_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM match_candidate WHERE media_id = ?", media_id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+}
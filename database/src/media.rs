@@ -4,13 +4,99 @@ use crate::DatabaseError;
 use serde::Deserialize;
 use serde::Serialize;
 
+use std::collections::HashSet;
+
+/// Score a media gets per genre it shares with the seed of
+/// [`Media::get_similar`](Media::get_similar).
+pub const SIMILAR_GENRE_WEIGHT: i64 = 2;
+/// Score a media gets per cast member it shares with the seed of
+/// [`Media::get_similar`](Media::get_similar).
+pub const SIMILAR_CAST_WEIGHT: i64 = 1;
+/// Score a media gets for belonging to the same collection as the seed of
+/// [`Media::get_similar`](Media::get_similar).
+pub const SIMILAR_COLLECTION_WEIGHT: i64 = 5;
+
+/// Number of candidates per media requested that [`Media::get_random`](Media::get_random) probes
+/// at most before giving up on filling the sample.
+const RANDOM_PROBE_FACTOR: i64 = 2;
+
+/// Condition matching the movies and shows in `media` that the user `$2` hasn't watched yet, given
+/// the watched threshold `$3`. Shows are watched once every episode is.
+const UNWATCHED: &str = r#"NOT EXISTS (
+        SELECT 1 FROM progress
+        JOIN _tblmedia ON _tblmedia.id = progress.media_id
+        WHERE progress.media_id = media.id AND progress.user_id = $2
+        AND _tblmedia.duration > 0
+        AND CAST(progress.delta AS REAL) / _tblmedia.duration > $3)
+    AND NOT (media.media_type = "tv"
+        AND EXISTS (
+            SELECT 1 FROM season
+            JOIN episode ON episode.seasonid = season.id
+            WHERE season.tvshowid = media.id)
+        AND NOT EXISTS (
+            SELECT 1 FROM season
+            JOIN episode ON episode.seasonid = season.id
+            JOIN _tblmedia ON _tblmedia.id = episode.id
+            LEFT OUTER JOIN progress
+                ON progress.media_id = episode.id AND progress.user_id = $2
+            WHERE season.tvshowid = media.id
+            AND (progress.delta IS NULL
+                OR _tblmedia.duration IS NULL
+                OR _tblmedia.duration = 0
+                OR NOT CAST(progress.delta AS REAL) / _tblmedia.duration > $3)))"#;
+
+/// A media along with when it was added, returned by
+/// [`Media::get_added_between`](Media::get_added_between) and
+/// [`Media::get_recently_added_page`](Media::get_recently_added_page).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct AddedMedia {
+    pub id: i64,
+    pub name: String,
+    pub poster_path: Option<String>,
+    pub media_type: MediaType,
+    pub added: String,
+}
+
+/// A media whose metadata is due for a refresh, returned by
+/// [`Media::get_stale`](Media::get_stale).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct StaleMedia {
+    pub id: i64,
+    pub name: String,
+    pub media_type: MediaType,
+    /// Unix timestamp of the last refresh, `None` if it was never recorded.
+    pub metadata_refreshed_at: Option<i64>,
+}
+
+/// A media similar to a seed media along with how relevant it is, returned by
+/// [`Media::get_similar`](Media::get_similar).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct SimilarMedia {
+    pub id: i64,
+    pub name: String,
+    pub poster_path: Option<String>,
+    pub media_type: MediaType,
+    /// Relevance of the media to the seed, higher is more relevant.
+    pub score: i64,
+}
+
+/// Ids of a media on other databases than TMDB, returned by
+/// [`Media::get_external_ids`](Media::get_external_ids).
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ExternalIds {
+    /// Id of the media on IMDB, ie `tt0133093`.
+    pub imdb_id: Option<String>,
+    /// Id of the media on TVDB, only known for tv shows.
+    pub tvdb_id: Option<i64>,
+}
+
 /// Marker trait used to mark media types that inherit from Media.
 /// Used internally by InsertableTVShow.
 pub trait MediaTrait {}
 
 /// Media struct that represents a media object, usually a movie, tv show or a episode of a tv
 /// show. This struct is returned by several methods and can be serialized to json.
-#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default, sqlx::FromRow)]
 pub struct Media {
     /// unique id automatically assigned by postgres.
     pub id: i64,
@@ -37,6 +123,74 @@ pub struct Media {
     pub media_type: MediaType,
 }
 
+/// Sources a poster of a media can be resolved from, see [`Media::poster_url`].
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PosterSource {
+    /// Poster fetched from TMDB.
+    Tmdb,
+    /// Frame extracted from a file of the media.
+    Frame,
+    /// Generated image showing the name of the media.
+    Placeholder,
+}
+
+impl PosterSource {
+    /// Fallback chain used if none is configured.
+    pub fn default_chain() -> Vec<Self> {
+        vec![Self::Tmdb, Self::Frame, Self::Placeholder]
+    }
+}
+
+/// Returns the leading articles stripped from titles in `language` when sorting them. Languages
+/// we dont know the articles of have none, libraries without a language are treated as english.
+pub fn leading_articles(language: Option<&str>) -> &'static [&'static str] {
+    match language.unwrap_or("en").to_lowercase().as_str() {
+        "en" => &["the", "an", "a"],
+        "de" => &["der", "die", "das", "eine", "ein"],
+        "fr" => &["les", "le", "la", "l'", "une", "un"],
+        "es" => &["los", "las", "el", "la", "una", "un"],
+        "it" => &["gli", "il", "lo", "la", "le", "l'", "una", "uno", "un", "i"],
+        "nl" => &["het", "de", "een"],
+        "pt" => &["os", "as", "o", "a", "uma", "um"],
+        _ => &[],
+    }
+}
+
+/// Returns the title `name` is sorted by in a library in `language`, ie `Matrix` for
+/// `The Matrix`. Returns `None` if `name` doesnt start with an article and thus is sorted by
+/// itself.
+pub fn sort_title(name: &str, language: Option<&str>) -> Option<String> {
+    for article in leading_articles(language) {
+        let rest = match name.get(..article.len()) {
+            Some(x) if x.eq_ignore_ascii_case(article) => &name[article.len()..],
+            _ => continue,
+        };
+
+        // articles like `l'` are followed by the word directly, every other article is followed
+        // by whitespace.
+        if !article.ends_with('\'') && !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let rest = rest.trim_start();
+        if !rest.is_empty() {
+            return Some(rest.to_string());
+        }
+    }
+
+    None
+}
+
+/// Function advances the splitmix64 generator `state` and returns its next output.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 impl PartialEq for Media {
     fn eq(&self, other: &Media) -> bool {
         self.id == other.id
@@ -58,7 +212,7 @@ impl Media {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
                 Media,
-                r#"SELECT id, library_id, name, description, rating, year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND NOT media_type = "episode""#,
+                r#"SELECT id, library_id, name, description, rating, year, added, poster_path, backdrop_path, media_type as "media_type: _" FROM media WHERE library_id = ? AND NOT media_type = "episode" ORDER BY COALESCE(sort_title, name)"#,
                 library_id
             )
             .fetch_all(&mut *conn)
@@ -80,6 +234,39 @@ impl Media {
             .await?)
     }
 
+    /// Method returns the media with the given ids in no particular order. Ids of media that dont
+    /// exist or are in hidden libraries are skipped.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `ids` - ids of the media objects.
+    pub async fn get_many(
+        conn: &mut crate::Transaction<'_>,
+        ids: &[i64],
+    ) -> Result<Vec<Self>, DatabaseError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let query = format!(
+            "SELECT media.id, media.library_id, media.name, media.description, media.rating,
+                media.year, media.added, media.poster_path, media.backdrop_path, media.media_type
+            FROM media
+            JOIN library ON library.id = media.library_id
+            WHERE NOT library.hidden AND media.id IN ({})",
+            placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, Self>(&query);
+
+        for id in ids {
+            query = query.bind(id);
+        }
+
+        Ok(query.fetch_all(&mut *conn).await?)
+    }
+
     /// Method to get a entry in a library based on name and library
     ///
     /// # Arguments
@@ -115,132 +302,1398 @@ impl Media {
             ).fetch_one(&mut *conn).await?)
     }
 
-    /// Method returns the top rated medias
-    pub async fn get_top_rated(
+    /// Method resolves the poster of this media by walking `chain` until a source is available
+    /// and returns the url of the poster along with the source used. Frames and placeholders are
+    /// generated lazily once their url is requested.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `chain` - poster sources in order of preference.
+    pub async fn poster_url(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        chain: &[PosterSource],
+    ) -> Result<Option<(String, PosterSource)>, DatabaseError> {
+        for source in chain {
+            match source {
+                PosterSource::Tmdb => {
+                    if let Some(x) = self.poster_path.as_ref().filter(|x| !x.is_empty()) {
+                        return Ok(Some((x.clone(), *source)));
+                    }
+                }
+                PosterSource::Frame => {
+                    let has_files = match self.media_type {
+                        MediaType::Tv => {
+                            !crate::mediafile::MediaFile::get_of_show(&mut *conn, self.id)
+                                .await?
+                                .is_empty()
+                        }
+                        _ => !crate::mediafile::MediaFile::get_of_media(&mut *conn, self.id)
+                            .await?
+                            .is_empty(),
+                    };
+
+                    if has_files {
+                        return Ok(Some((format!("images/frames/{}.jpg", self.id), *source)));
+                    }
+                }
+                PosterSource::Placeholder => {
+                    return Ok(Some((
+                        format!("images/placeholders/{}.svg", self.id),
+                        *source,
+                    )));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Method returns the TMDB id a media was matched against, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_tmdb_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT tmdb_id as "tmdb_id: i64" FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .tmdb_id)
+    }
+
+    /// Method returns the id of the TMDB collection a movie belongs to, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_collection_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT collection_id as "collection_id: i64" FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .collection_id)
+    }
+
+    /// Method sets the id of the TMDB collection a movie belongs to.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    /// * `collection_id` - id of the collection on TMDB, `None` if the movie isnt part of one.
+    pub async fn set_collection_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        collection_id: Option<i64>,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET collection_id = ? WHERE id = ?",
+            collection_id,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the title a media is sorted by, `None` if it is sorted by its name.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_sort_title(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<String>, DatabaseError> {
+        Ok(
+            sqlx::query!("SELECT sort_title FROM _tblmedia WHERE id = ?", id)
+                .fetch_one(&mut *conn)
+                .await?
+                .sort_title,
+        )
+    }
+
+    /// Method derives the sort title of a media from its name and the language of its library,
+    /// see [`sort_title`](sort_title). Sort titles set manually by a user are left untouched and
+    /// episodes, which are sorted by their number, never get one.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn refresh_sort_title(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        if Self::get_overrides(&mut *conn, id)
+            .await?
+            .iter()
+            .any(|x| x == "sort_title")
+        {
+            return Ok(0);
+        }
+
+        let record = sqlx::query!(
+            r#"SELECT _tblmedia.name, _tblmedia.media_type as "media_type: MediaType",
+                library.language
+            FROM _tblmedia
+            LEFT OUTER JOIN library ON library.id = _tblmedia.library_id
+            WHERE _tblmedia.id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if record.media_type == MediaType::Episode {
+            return Ok(0);
+        }
+
+        let sort_title = sort_title(&record.name, record.language.as_deref());
+
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET sort_title = ? WHERE id = ?",
+            sort_title,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method sets the TMDB id a media was matched against. As the metadata of the media has just
+    /// been fetched from TMDB it is marked as refreshed too.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    /// * `tmdb_id` - id of the media on TMDB.
+    pub async fn set_tmdb_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        tmdb_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        let rows = sqlx::query!("UPDATE _tblmedia SET tmdb_id = ? WHERE id = ?", tmdb_id, id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected() as usize;
+
+        Self::mark_metadata_refreshed(&mut *conn, id).await?;
+
+        Ok(rows)
+    }
+
+    /// Method records that the metadata of a media has just been fetched from TMDB.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn mark_metadata_refreshed(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET metadata_refreshed_at = strftime('%s', 'now') WHERE id = ?",
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the ids of the media matched against TMDB which have no poster, ie because
+    /// they were scanned while posters couldnt be fetched. Media whose poster was overridden by a
+    /// user, episodes and media in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_missing_poster(
         conn: &mut crate::Transaction<'_>,
-        limit: i64,
     ) -> Result<Vec<i64>, DatabaseError> {
         Ok(sqlx::query_scalar!(
-            r#"SELECT _tblmedia.id
-                FROM _tblmedia
-                JOIN library ON library.id = _tblmedia.library_id
-                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
-                ORDER BY rating DESC
-                LIMIT ?"#,
-            limit
+            r#"SELECT _tblmedia.id as "id!: i64" FROM _tblmedia
+            JOIN library ON library.id = _tblmedia.library_id
+            LEFT OUTER JOIN assets ON assets.id = _tblmedia.poster
+            WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+            AND _tblmedia.tmdb_id IS NOT NULL
+            AND assets.id IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM media_overrides
+                WHERE media_overrides.media_id = _tblmedia.id AND media_overrides.field = "poster"
+            )
+            ORDER BY _tblmedia.id ASC"#
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the dominant color of the poster of a media as `#rrggbb`, `None` if it
+    /// wasnt computed yet or the media has no poster.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_accent_color(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<String>, DatabaseError> {
+        Ok(
+            sqlx::query!("SELECT accent_color FROM _tblmedia WHERE id = ?", id)
+                .fetch_one(&mut *conn)
+                .await?
+                .accent_color,
+        )
+    }
+
+    /// Method sets the dominant color of the poster of a media. The color is cleared whenever
+    /// the poster of the media changes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    /// * `color` - color formatted as `#rrggbb`.
+    pub async fn set_accent_color(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        color: &str,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET accent_color = ? WHERE id = ?",
+            color,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the ids of the media which have a poster but no accent color, ie because
+    /// they were scanned before accent colors were computed. Episodes and media in hidden
+    /// libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_missing_accent_color(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT _tblmedia.id as "id!: i64" FROM _tblmedia
+            JOIN library ON library.id = _tblmedia.library_id
+            JOIN assets ON assets.id = _tblmedia.poster
+            WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+            AND _tblmedia.accent_color IS NULL
+            ORDER BY _tblmedia.id ASC"#
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the media matched against TMDB whose metadata was last refreshed before
+    /// `cutoff`, oldest first. Media whose refresh was never recorded come first. Episodes and
+    /// media in hidden libraries are excluded.
+    ///
+    /// Returns the requested page of media along with the total number of stale media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `cutoff` - unix timestamp before which metadata is considered stale.
+    /// * `limit` - max number of media to return.
+    /// * `offset` - number of media to skip.
+    pub async fn get_stale(
+        conn: &mut crate::Transaction<'_>,
+        cutoff: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<StaleMedia>, i64), DatabaseError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        let items = sqlx::query_as::<_, StaleMedia>(
+            r#"SELECT _tblmedia.id, _tblmedia.name, _tblmedia.media_type,
+                _tblmedia.metadata_refreshed_at
+            FROM _tblmedia
+            JOIN library ON library.id = _tblmedia.library_id
+            WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+            AND _tblmedia.tmdb_id IS NOT NULL
+            AND (_tblmedia.metadata_refreshed_at IS NULL OR _tblmedia.metadata_refreshed_at < $1)
+            ORDER BY _tblmedia.metadata_refreshed_at IS NOT NULL,
+                _tblmedia.metadata_refreshed_at, _tblmedia.id
+            LIMIT $2 OFFSET $3"#,
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let total = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "total!: i64"
+            FROM _tblmedia
+            JOIN library ON library.id = _tblmedia.library_id
+            WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+            AND _tblmedia.tmdb_id IS NOT NULL
+            AND (_tblmedia.metadata_refreshed_at IS NULL OR _tblmedia.metadata_refreshed_at < $1)"#,
+            cutoff
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok((items, total))
+    }
+
+    /// Method returns the runtime of a media in seconds as reported by TMDB, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_tmdb_runtime(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT tmdb_runtime as "tmdb_runtime: i64" FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .tmdb_runtime)
+    }
+
+    /// Method sets the runtime of a media as reported by TMDB.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    /// * `runtime` - runtime of the media in seconds.
+    pub async fn set_tmdb_runtime(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        runtime: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET tmdb_runtime = ? WHERE id = ?",
+            runtime,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the IMDB and TVDB ids of a media. Returns `None` if they were never fetched,
+    /// ie the media was scanned before external ids were stored.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_external_ids(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<ExternalIds>, DatabaseError> {
+        let record = sqlx::query!(
+            r#"SELECT imdb_id, tvdb_id as "tvdb_id: i64",
+                external_ids_fetched_at as "external_ids_fetched_at: i64"
+            FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(record.external_ids_fetched_at.map(|_| ExternalIds {
+            imdb_id: record.imdb_id,
+            tvdb_id: record.tvdb_id,
+        }))
+    }
+
+    /// Method sets the IMDB and TVDB ids of a media. The ids are cleared once the media is matched
+    /// against another TMDB id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    /// * `ids` - ids of the media, `None` where TMDB doesnt know them.
+    pub async fn set_external_ids(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        ids: &ExternalIds,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET imdb_id = ?, tvdb_id = ?,
+                external_ids_fetched_at = strftime('%s', 'now')
+            WHERE id = ?",
+            ids.imdb_id,
+            ids.tvdb_id,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the id of a media within a library that was matched against a TMDB id.
+    /// This is used by the scanners to attach files of the same movie or show to one media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library to look in.
+    /// * `tmdb_id` - id of the media on TMDB.
+    /// * `media_type` - media type of the media, either movie or tv.
+    pub async fn get_id_by_tmdb_id(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+        tmdb_id: i64,
+        media_type: MediaType,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT id as "id!: i64" FROM _tblmedia
+            WHERE library_id = ? AND tmdb_id = ? AND media_type = ?
+            ORDER BY id ASC
+            LIMIT 1"#,
+            library_id,
+            tmdb_id,
+            media_type
+        )
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all medias across visible libraries that were matched against a TMDB id.
+    /// Episodes are never returned.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tmdb_id` - id of the media on TMDB.
+    pub async fn get_all_by_tmdb_id(
+        conn: &mut crate::Transaction<'_>,
+        tmdb_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+                Media,
+                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                JOIN _tblmedia ON _tblmedia.id = media.id
+                JOIN library ON library.id = media.library_id
+                WHERE _tblmedia.tmdb_id = ? AND NOT media.media_type = "episode" AND NOT library.hidden
+                ORDER BY media.id ASC"#,
+                tmdb_id
+            )
+            .fetch_all(&mut *conn)
+            .await?)
+    }
+
+    /// Method returns all medias which share their TMDB id with another media of the same type.
+    /// The result is ordered by TMDB id so that duplicates follow each other.
+    pub async fn get_duplicates(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<(i64, Self)>, DatabaseError> {
+        let ids = sqlx::query!(
+            r#"SELECT _tblmedia.id as "id!: i64", _tblmedia.tmdb_id as "tmdb_id!: i64" FROM _tblmedia
+            JOIN library ON library.id = _tblmedia.library_id
+            WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+            AND (_tblmedia.tmdb_id, _tblmedia.media_type) IN (
+                SELECT tmdb_id, media_type FROM _tblmedia
+                WHERE tmdb_id IS NOT NULL AND NOT media_type = "episode"
+                GROUP BY tmdb_id, media_type
+                HAVING COUNT(id) > 1
+            )
+            ORDER BY _tblmedia.tmdb_id, _tblmedia.id"#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut duplicates = Vec::with_capacity(ids.len());
+        for record in ids {
+            duplicates.push((record.tmdb_id, Self::get(&mut *conn, record.id).await?));
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Method returns the top rated medias, excluding media hidden by the user.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `limit` - max number of medias to return.
+    pub async fn get_top_rated(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        limit: i64,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT _tblmedia.id
+                FROM _tblmedia
+                JOIN library ON library.id = _tblmedia.library_id
+                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = ?)
+                ORDER BY rating DESC
+                LIMIT ?"#,
+            uid,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the recently added medias, excluding media hidden by the user.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `limit` - max number of medias to return.
+    pub async fn get_recently_added(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        limit: i64,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT _tblmedia.id
+                FROM _tblmedia
+                JOIN library ON library.id = _tblmedia.library_id
+                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = ?)
+                ORDER BY added DESC
+                LIMIT ?"#,
+            uid,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns a page of the recently added medias, most recent first, along with the total
+    /// number of medias. Medias hidden by the user and medias in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `limit` - max number of medias to return.
+    /// * `offset` - number of medias to skip.
+    pub async fn get_recently_added_page(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AddedMedia>, i64), DatabaseError> {
+        let query = r#"SELECT media.id, media.name, media.poster_path,
+                media.media_type as media_type, media.added
+            FROM media
+            JOIN library ON library.id = media.library_id
+            WHERE NOT media.media_type = "episode" AND NOT library.hidden
+            AND NOT EXISTS (
+                SELECT 1 FROM hidden_media
+                WHERE hidden_media.media_id = media.id AND hidden_media.user_id = $1)"#;
+
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        let items = sqlx::query_as::<_, AddedMedia>(&format!(
+            "{} ORDER BY media.added DESC, media.id LIMIT $2 OFFSET $3",
+            query
+        ))
+        .bind(uid)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM ({})", query))
+            .bind(uid)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok((items, total))
+    }
+
+    /// Method returns the medias added within `[from, to)` ordered by when they were added, most
+    /// recent first. Episodes aren't returned themselves, but if `by_episode` is set a tv show is
+    /// considered added when its newest episode was added. Medias hidden by the user and medias in
+    /// hidden libraries are excluded.
+    ///
+    /// Returns the requested page of medias along with the total number of medias in the range.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `from` - inclusive lower bound, ie `2021-12-01`.
+    /// * `to` - exclusive upper bound, ie `2021-12-08`.
+    /// * `by_episode` - whether shows are keyed on their newest episode.
+    /// * `limit` - max number of medias to return.
+    /// * `offset` - number of medias to skip.
+    pub async fn get_added_between(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        from: &str,
+        to: &str,
+        by_episode: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AddedMedia>, i64), DatabaseError> {
+        // `added` is stored as `YYYY-MM-DD HH:MM:SS UTC` thus comparing it against dates as
+        // strings yields the right order.
+        let query = r#"SELECT * FROM (
+                SELECT media.id, media.name, media.poster_path,
+                    media.media_type as media_type,
+                    CASE WHEN $3 AND media.media_type = "tv" THEN COALESCE((
+                        SELECT MAX(_tblmedia.added) FROM _tblmedia
+                        JOIN episode ON episode.id = _tblmedia.id
+                        JOIN season ON season.id = episode.seasonid
+                        WHERE season.tvshowid = media.id
+                    ), media.added) ELSE media.added END as added
+                FROM media
+                JOIN library ON library.id = media.library_id
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = media.id AND hidden_media.user_id = $4)
+            )
+            WHERE added >= $1 AND added < $2"#;
+
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        let items = sqlx::query_as::<_, AddedMedia>(&format!(
+            "{} ORDER BY added DESC, id LIMIT $5 OFFSET $6",
+            query
+        ))
+        .bind(from)
+        .bind(to)
+        .bind(by_episode)
+        .bind(uid)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM ({})", query))
+            .bind(from)
+            .bind(to)
+            .bind(by_episode)
+            .bind(uid)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok((items, total))
+    }
+
+    /// Method returns up to `limit` random movies and shows. Placeholders, media hidden by the
+    /// user and media in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `limit` - max number of media to return.
+    pub async fn get_random_with(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        limit: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+                Media,
+                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path as "poster_path?", backdrop_path as "backdrop_path?", media.media_type as "media_type: _"
+                FROM media
+                JOIN library ON media.library_id = library.id
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND NOT media.placeholder
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = media.id AND hidden_media.user_id = ?)
+                GROUP BY media.id
+                ORDER BY RANDOM()
+                LIMIT ?
+                "#,
+                uid,
+                limit
+        ).fetch_all(&mut *conn).await?)
+    }
+
+    /// Method returns a random sample of up to `limit` movies and shows. Placeholders, media
+    /// hidden by the user and media in hidden libraries are excluded.
+    ///
+    /// Samples taken with the same `seed` are in the same order as long as the media don't
+    /// change, which is used for picks that should stay put for a while, ie daily picks. Without
+    /// a seed every call returns a new sample.
+    ///
+    /// The sample is drawn by probing random offsets into the candidates, one media per probe, so
+    /// that neither all candidates nor all their ids are ever loaded. Only the probed media are
+    /// checked for whether the user watched them. At most `limit` * [`RANDOM_PROBE_FACTOR`] media
+    /// are probed, thus the sample can come up short if the user watched most candidates.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_type` - only sample media of this type if set, either movie or tv.
+    /// * `unwatched_only` - whether media the user has already watched are excluded.
+    /// * `seed` - seed of the sample.
+    /// * `limit` - max number of media to return.
+    pub async fn get_random(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_type: Option<MediaType>,
+        unwatched_only: bool,
+        seed: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        // the candidates are numbered by their id, offsets thus keep pointing at the same media
+        // as long as the media don't change.
+        let candidates = r#"SELECT media.id FROM media
+            JOIN library ON library.id = media.library_id
+            WHERE NOT media.media_type = "episode" AND NOT library.hidden
+            AND NOT media.placeholder
+            AND ($4 IS NULL OR media.media_type = $4)
+            AND NOT EXISTS (
+                SELECT 1 FROM hidden_media
+                WHERE hidden_media.media_id = media.id AND hidden_media.user_id = $2)
+            ORDER BY media.id"#;
+
+        // the arguments are bound by their number, so the ones not used by the count are bound
+        // too in order to keep the numbering shared with the probes.
+        let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM ({})", candidates))
+            .bind(unwatched_only)
+            .bind(uid)
+            .bind(crate::progress::WATCHED_THRESHOLD)
+            .bind(media_type)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let mut state = match seed {
+            Some(x) => x as u64,
+            None => {
+                sqlx::query_scalar::<_, i64>("SELECT RANDOM()")
+                    .fetch_one(&mut *conn)
+                    .await? as u64
+            }
+        };
+
+        let limit = limit.max(0);
+        let attempts = total.min(limit * RANDOM_PROBE_FACTOR);
+        let mut probed = HashSet::new();
+        let mut sample = Vec::new();
+
+        while (sample.len() as i64) < limit && (probed.len() as i64) < attempts {
+            // offsets that were probed already are skipped over to the next free one.
+            let mut offset = (next_random(&mut state) % total as u64) as i64;
+            while !probed.insert(offset) {
+                offset = (offset + 1) % total;
+            }
+
+            // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+            let found = sqlx::query_as::<_, Self>(&format!(
+                r#"SELECT media.id, media.library_id, media.name, media.description, media.rating,
+                    media.year, media.added, media.poster_path, media.backdrop_path, media.media_type
+                FROM media
+                WHERE media.id = ({candidates} LIMIT 1 OFFSET $5)
+                AND (NOT $1 OR {unwatched})"#,
+                candidates = candidates,
+                unwatched = UNWATCHED
+            ))
+            .bind(unwatched_only)
+            .bind(uid)
+            .bind(crate::progress::WATCHED_THRESHOLD)
+            .bind(media_type)
+            .bind(offset)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            sample.extend(found);
+        }
+
+        Ok(sample)
+    }
+
+    /// Method returns the movies and shows most similar to the media `seed` that the user hasn't
+    /// watched yet, most relevant first. Media score [`SIMILAR_GENRE_WEIGHT`] per genre and
+    /// [`SIMILAR_CAST_WEIGHT`] per cast member shared with the seed, and
+    /// [`SIMILAR_COLLECTION_WEIGHT`] if they belong to the same collection. Media that share
+    /// nothing with the seed, the seed itself, media hidden by the user, placeholders and media in
+    /// hidden libraries are excluded.
+    ///
+    /// Movies are watched once the user watched past
+    /// [`WATCHED_THRESHOLD`](crate::progress::WATCHED_THRESHOLD), shows once the user watched all
+    /// of their episodes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `seed` - id of the movie or show the results are similar to.
+    /// * `uid` - username of the user.
+    /// * `limit` - max number of media to return.
+    pub async fn get_similar(
+        conn: &mut crate::Transaction<'_>,
+        seed: i64,
+        uid: &str,
+        limit: i64,
+    ) -> Result<Vec<SimilarMedia>, DatabaseError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, SimilarMedia>(&format!(
+            r#"SELECT * FROM (
+                SELECT media.id, media.name, media.poster_path, media.media_type,
+                    (SELECT COUNT(*) FROM genre_media
+                        JOIN genre_media AS seed ON seed.genre_id = genre_media.genre_id
+                        WHERE genre_media.media_id = media.id AND seed.media_id = $1) * $5
+                    + (SELECT COUNT(*) FROM media_cast
+                        JOIN media_cast AS seed ON seed.person_id = media_cast.person_id
+                        WHERE media_cast.media_id = media.id AND seed.media_id = $1) * $6
+                    + COALESCE(media.collection_id = (
+                        SELECT collection_id FROM _tblmedia WHERE id = $1), 0) * $7 as score
+                FROM media
+                JOIN library ON library.id = media.library_id
+
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND NOT media.placeholder
+                AND NOT media.id = $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = media.id AND hidden_media.user_id = $2)
+                AND {unwatched}
+            )
+            WHERE score > 0
+            ORDER BY score DESC, id ASC
+            LIMIT $4"#,
+            unwatched = UNWATCHED
+        ))
+        .bind(seed)
+        .bind(uid)
+        .bind(crate::progress::WATCHED_THRESHOLD)
+        .bind(limit)
+        .bind(SIMILAR_GENRE_WEIGHT)
+        .bind(SIMILAR_CAST_WEIGHT)
+        .bind(SIMILAR_COLLECTION_WEIGHT)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    pub async fn get_search(
+        conn: &mut crate::Transaction<'_>,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        let query = format!("%{}%", query);
+        Ok(sqlx::query_as!(
+                Media,
+                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                JOIN library ON library.id = media.library_id
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND UPPER(media.name) LIKE ?
+                ORDER BY COALESCE(media.sort_title, media.name)
+                LIMIT ?
+                "#,
+                query,
+                limit
+        ).fetch_all(&mut *conn).await?)
+    }
+
+    pub async fn get_of_genre(
+        conn: &mut crate::Transaction<'_>,
+        genre_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+                Media,
+                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                INNER JOIN genre_media ON genre_media.media_id = media.id
+                JOIN library ON library.id = media.library_id
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND genre_media.genre_id = ?
+                ORDER BY COALESCE(media.sort_title, media.name)
+                "#,
+                genre_id,
+        ).fetch_all(&mut *conn).await?)
+    }
+
+    pub async fn get_of_year(
+        conn: &mut crate::Transaction<'_>,
+        year: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+                Media,
+                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                JOIN library ON library.id = media.library_id
+                WHERE NOT media.media_type = "episode" AND NOT library.hidden
+                AND year = ?
+                ORDER BY COALESCE(media.sort_title, media.name)
+                "#,
+                year,
+        ).fetch_all(&mut *conn).await?)
+    }
+
+    pub async fn get_first_duration(&self, conn: &mut crate::Transaction<'_>) -> i64 {
+        sqlx::query!(r#"
+            SELECT COALESCE(mediafile.duration, 0) as "duration!: i64" FROM mediafile
+            WHERE mediafile.media_id = ?
+            ORDER BY mediafile.duration DESC
+            LIMIT 1
+        "#, self.id).fetch_one(&mut *conn).await.map(|x| x.duration).unwrap_or(0)
+    }
+
+    /// Method checks whether a media object is a placeholder, ie it was added manually and no
+    /// mediafiles have been matched to it yet.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn is_placeholder(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<bool, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT placeholder as "placeholder!: bool" FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .placeholder)
+    }
+
+    /// Method clears the placeholder flag of a media object. This is called by the scanners once
+    /// a file gets matched to a media that was previously added manually.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn reconcile_placeholder(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "UPDATE _tblmedia SET placeholder = 0 WHERE id = ? AND placeholder",
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns the cached duration of a media. The cache holds the longest duration of
+    /// the mediafiles attached to this media and is kept up to date by triggers whenever a
+    /// mediafile is added, changed or removed. Returns `None` if the duration hasnt been computed
+    /// yet, in which case callers should fall back to querying the mediafiles directly.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_cached_duration(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT duration as "duration: i64" FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .duration)
+    }
+
+    /// Method returns the number of media, including episodes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn count(conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        Ok(
+            sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!: i64" FROM _tblmedia"#)
+                .fetch_one(&mut *conn)
+                .await?,
+        )
+    }
+
+    /// Method recomputes the cached duration of the first `limit` media whose id is greater than
+    /// `after`. Returns the id of the last media processed and the number of cached durations
+    /// that changed, or `None` if there are no media left.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `after` - id after which to start, `0` for the first batch.
+    /// * `limit` - number of media to process.
+    pub async fn recompute_cached_durations(
+        conn: &mut crate::Transaction<'_>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Option<(i64, usize)>, DatabaseError> {
+        let last = sqlx::query_scalar!(
+            r#"SELECT MAX(id) as "id: i64" FROM (
+                SELECT id FROM _tblmedia WHERE id > ? ORDER BY id LIMIT ?
+            )"#,
+            after,
+            limit
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let last = match last {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let updated = sqlx::query!(
+            "UPDATE _tblmedia SET duration = (
+                SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = _tblmedia.id
+            )
+            WHERE id > ? AND id <= ? AND duration IS NOT (
+                SELECT MAX(mediafile.duration) FROM mediafile WHERE mediafile.media_id = _tblmedia.id
+            )",
+            after,
+            last
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+
+        Ok(Some((last, updated)))
+    }
+
+    /// Method returns the names of the metadata fields of a media that were manually edited by a
+    /// user. Automatic metadata refreshes must leave these fields untouched.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_overrides(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Vec<String>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT field as "field!" FROM media_overrides WHERE media_id = ? ORDER BY field"#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the current values of all manually overridden fields of a media. Fields
+    /// that aren't overridden are left as `None`. Calling
+    /// [`UpdateMedia::update_manual`](UpdateMedia::update_manual) with the result after a
+    /// metadata refresh restores the user's edits along with their override markers.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn get_overridden_values(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<UpdateMedia, DatabaseError> {
+        let overrides = Self::get_overrides(&mut *conn, id).await?;
+        let is_overridden = |field: &str| overrides.iter().any(|x| x == field);
+
+        let record = sqlx::query!(
+            r#"SELECT name, description, rating, year, poster, backdrop, sort_title FROM _tblmedia WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(UpdateMedia {
+            name: Some(record.name).filter(|_| is_overridden("name")),
+            description: record.description.filter(|_| is_overridden("description")),
+            rating: record.rating.filter(|_| is_overridden("rating")),
+            year: record.year.filter(|_| is_overridden("year")),
+            poster: record.poster.filter(|_| is_overridden("poster")),
+            backdrop: record.backdrop.filter(|_| is_overridden("backdrop")),
+            sort_title: record.sort_title.filter(|_| is_overridden("sort_title")),
+            ..Default::default()
+        })
+    }
+
+    /// Method clears all manual overrides of a media, allowing automatic metadata to take over
+    /// again on the next refresh.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object.
+    pub async fn clear_overrides(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM media_overrides WHERE media_id = ?", id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
         )
-        .fetch_all(&mut *conn)
-        .await?)
     }
 
-    /// Method returns the recently added medias
-    pub async fn get_recently_added(
+    pub async fn decouple_mediafiles(
         conn: &mut crate::Transaction<'_>,
-        limit: i64,
+        id: i64,
     ) -> Result<Vec<i64>, DatabaseError> {
         Ok(sqlx::query_scalar!(
-            r#"SELECT _tblmedia.id
-                FROM _tblmedia
-                JOIN library ON library.id = _tblmedia.library_id
-                WHERE NOT _tblmedia.media_type = "episode" AND NOT library.hidden
-                ORDER BY added DESC
-                LIMIT ?"#,
-            limit
+            r#"UPDATE mediafile SET media_id = NULL WHERE media_id = ? RETURNING id AS "id: i64""#,
+            id
         )
         .fetch_all(&mut *conn)
         .await?)
     }
 
-    pub async fn get_random_with(
+    /// Method moves a media object into another library. If the media is a tv show, all of its
+    /// episodes get moved too. The mediafiles attached to the media and its episodes are moved
+    /// along with it. Callers must make sure that the media type of the target library matches.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object we want to move.
+    /// * `library_id` - id of the library we want to move the media into.
+    pub async fn move_to_library(
         conn: &mut crate::Transaction<'_>,
-        limit: i64,
-    ) -> Result<Vec<Self>, DatabaseError> {
-        Ok(sqlx::query_as!(
-                Media,
-                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path as "poster_path?", backdrop_path as "backdrop_path?", media.media_type as "media_type: _"
-                FROM media
-                JOIN library ON media.library_id = library.id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
-                GROUP BY media.id
-                ORDER BY RANDOM()
-                LIMIT ?
-                "#,
-                limit
-        ).fetch_all(&mut *conn).await?)
-    }
+        id: i64,
+        library_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        let mediafiles = sqlx::query!(
+            r#"UPDATE mediafile SET library_id = $2
+            WHERE media_id = $1
+            OR media_id IN (
+                SELECT episode.id FROM episode
+                INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+                WHERE _tblseason.tvshowid = $1
+            )"#,
+            id,
+            library_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
 
-    pub async fn get_search(
-        conn: &mut crate::Transaction<'_>,
-        query: &str,
-        limit: i64,
-    ) -> Result<Vec<Self>, DatabaseError> {
-        let query = format!("%{}%", query);
-        Ok(sqlx::query_as!(
-                Media,
-                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
-                FROM media
-                JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
-                AND UPPER(media.name) LIKE ?
-                LIMIT ?
-                "#,
-                query,
-                limit
-        ).fetch_all(&mut *conn).await?)
-    }
+        let medias = sqlx::query!(
+            r#"UPDATE _tblmedia SET library_id = $2
+            WHERE id = $1
+            OR id IN (
+                SELECT episode.id FROM episode
+                INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+                WHERE _tblseason.tvshowid = $1
+            )"#,
+            id,
+            library_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
 
-    pub async fn get_of_genre(
-        conn: &mut crate::Transaction<'_>,
-        genre_id: i64,
-    ) -> Result<Vec<Self>, DatabaseError> {
-        Ok(sqlx::query_as!(
-                Media,
-                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
-                FROM media
-                INNER JOIN genre_media ON genre_media.media_id = media.id
-                JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
-                AND genre_media.genre_id = ?
-                "#,
-                genre_id,
-        ).fetch_all(&mut *conn).await?)
+        Ok((mediafiles + medias) as usize)
     }
 
-    pub async fn get_of_year(
+    /// Method merges the media `id` into the media `into`. All mediafiles, progress, ratings,
+    /// plays, tags and playlist items of `id` are moved over. Users with progress on both medias
+    /// keep the furthest offset, while ratings, tags and hides of `into` win over those of `id`.
+    /// For tv shows, seasons which `into` doesnt have are moved over as a whole, while the
+    /// episodes of overlapping seasons are moved or, if `into` already has the same episode, have
+    /// their mediafiles and user data attached to that episode. Episodes moved over join the
+    /// library of `into` while their mediafiles stay in the library they were scanned into.
+    /// Afterwards `id` is deleted.
+    ///
+    /// Both medias must have the same media type, callers are expected to check this.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object that should be merged and removed.
+    /// * `into` - id of the media object that should be kept.
+    pub async fn merge_into(
         conn: &mut crate::Transaction<'_>,
-        year: i64,
-    ) -> Result<Vec<Self>, DatabaseError> {
-        Ok(sqlx::query_as!(
-                Media,
-                r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
-                FROM media
-                JOIN library ON library.id = media.library_id
-                WHERE NOT media.media_type = "episode" AND NOT library.hidden
-                AND year = ?
-                "#,
-                year,
-        ).fetch_all(&mut *conn).await?)
-    }
+        id: i64,
+        into: i64,
+    ) -> Result<(), DatabaseError> {
+        // episodes which exist on both sides, the ones of `id` are removed at the end.
+        let episodes = sqlx::query!(
+            r#"SELECT source.id as "source!: i64", target.id as "target!: i64" FROM episode source
+            INNER JOIN _tblseason ss ON ss.id = source.seasonid
+            INNER JOIN _tblseason ts ON ts.season_number = ss.season_number AND ts.tvshowid = $2
+            INNER JOIN episode target ON target.seasonid = ts.id AND target.episode_ = source.episode_
+            WHERE ss.tvshowid = $1"#,
+            id,
+            into
+        )
+        .fetch_all(&mut *conn)
+        .await?;
 
-    pub async fn get_first_duration(&self, conn: &mut crate::Transaction<'_>) -> i64 {
-        sqlx::query!(r#"
-            SELECT COALESCE(mediafile.duration, 0) as "duration!: i64" FROM mediafile
-            WHERE mediafile.media_id = ?
-            ORDER BY mediafile.duration DESC
-            LIMIT 1
-        "#, self.id).fetch_one(&mut *conn).await.map(|x| x.duration).unwrap_or(0)
+        // movies: files are attached to the media directly.
+        sqlx::query!(
+            "UPDATE mediafile SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // tv shows: move seasons that dont exist in `into` yet.
+        sqlx::query!(
+            r#"UPDATE _tblseason SET tvshowid = $2
+            WHERE tvshowid = $1
+            AND season_number NOT IN (SELECT season_number FROM _tblseason WHERE tvshowid = $2)"#,
+            id,
+            into
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // episodes which exist on both sides keep the episode of `into` but get the files.
+        sqlx::query!(
+            r#"UPDATE mediafile SET media_id = (
+                SELECT target.id FROM episode target
+                INNER JOIN _tblseason ts ON ts.id = target.seasonid
+                INNER JOIN episode source ON source.id = mediafile.media_id
+                INNER JOIN _tblseason ss ON ss.id = source.seasonid
+                WHERE ts.tvshowid = $2
+                AND ts.season_number = ss.season_number
+                AND target.episode_ = source.episode_
+            )
+            WHERE media_id IN (
+                SELECT source.id FROM episode source
+                INNER JOIN _tblseason ss ON ss.id = source.seasonid
+                INNER JOIN _tblseason ts ON ts.season_number = ss.season_number AND ts.tvshowid = $2
+                INNER JOIN episode target ON target.seasonid = ts.id AND target.episode_ = source.episode_
+                WHERE ss.tvshowid = $1
+            )"#,
+            id,
+            into
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // the remaining episodes of overlapping seasons are moved to the season of `into`.
+        sqlx::query!(
+            r#"UPDATE OR IGNORE episode SET seasonid = (
+                SELECT ts.id FROM _tblseason ts
+                INNER JOIN _tblseason ss ON ss.season_number = ts.season_number
+                WHERE ss.id = episode.seasonid AND ts.tvshowid = $2
+            )
+            WHERE seasonid IN (SELECT id FROM _tblseason WHERE tvshowid = $1)"#,
+            id,
+            into
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Self::move_user_data(&mut *conn, id, into).await?;
+
+        // watch orders `into` doesnt have yet are moved over, the ones it has are kept as is.
+        sqlx::query!(
+            r#"UPDATE episode_order SET tvshow_id = $2
+            WHERE tvshow_id = $1
+            AND name NOT IN (SELECT name FROM episode_order WHERE tvshow_id = $2)"#,
+            id,
+            into
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for episode in episodes {
+            Self::move_user_data(&mut *conn, episode.source, episode.target).await?;
+
+            // moved orders place the episode of `into` where the removed episode was.
+            sqlx::query!(
+                r#"UPDATE OR IGNORE episode_order SET episode_id = $2
+                WHERE tvshow_id = $3 AND episode_id = $1"#,
+                episode.source,
+                episode.target,
+                into
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        // finally remove whatever is left of the merged media.
+        sqlx::query!(
+            r#"DELETE FROM _tblmedia WHERE id IN (
+                SELECT episode.id FROM episode
+                INNER JOIN _tblseason ON _tblseason.id = episode.seasonid
+                WHERE _tblseason.tvshowid = ?
+            )"#,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM episode WHERE seasonid IN (SELECT id FROM _tblseason WHERE tvshowid = ?)",
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!("DELETE FROM _tblseason WHERE tvshowid = ?", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Self::delete(&mut *conn, id).await?;
+
+        Ok(())
     }
 
-    pub async fn decouple_mediafiles(
+    /// Moves the progress, ratings, plays, hides, tags and playlist items of the media `id` over
+    /// to the media `into`. Users with progress on both keep the furthest offset, for the rest
+    /// the rows of `into` win.
+    async fn move_user_data(
         conn: &mut crate::Transaction<'_>,
         id: i64,
-    ) -> Result<Vec<i64>, DatabaseError> {
-        Ok(sqlx::query_scalar!(
-            r#"UPDATE mediafile SET media_id = NULL WHERE media_id = ? RETURNING id AS "id: i64""#,
+        into: i64,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            r#"UPDATE progress SET
+                delta = MAX(delta, (SELECT source.delta FROM progress source
+                    WHERE source.media_id = $1 AND source.user_id = progress.user_id)),
+                populated = MAX(populated, (SELECT source.populated FROM progress source
+                    WHERE source.media_id = $1 AND source.user_id = progress.user_id))
+            WHERE media_id = $2 AND user_id IN (SELECT user_id FROM progress WHERE media_id = $1)"#,
+            id,
+            into
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE OR IGNORE progress SET media_id = ? WHERE media_id = ?",
+            into,
             id
         )
-        .fetch_all(&mut *conn)
-        .await?)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE OR IGNORE ratings SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE OR IGNORE hidden_media SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE OR IGNORE tag_media SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE watch_history SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE playlist_item SET media_id = ? WHERE media_id = ?",
+            into,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
     }
 
     /// Method deletes a media object based on its id.
@@ -252,6 +1705,9 @@ impl Media {
         conn: &mut crate::Transaction<'_>,
         id: i64,
     ) -> Result<usize, DatabaseError> {
+        // progress isnt reliably cascade deleted, thus we have to remove it manually.
+        crate::progress::Progress::delete_for_media(&mut *conn, id).await?;
+
         Ok(sqlx::query!("DELETE FROM _tblmedia WHERE id = ?", id)
             .execute(&mut *conn)
             .await?
@@ -264,6 +1720,14 @@ impl Media {
         conn: &mut crate::Transaction<'_>,
         library_id: i64,
     ) -> Result<usize, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM progress
+            WHERE media_id IN (SELECT id FROM _tblmedia WHERE library_id = ?)",
+            library_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
         Ok(
             sqlx::query!("DELETE FROM _tblmedia WHERE library_id = ?", library_id)
                 .execute(&mut *conn)
@@ -327,6 +1791,8 @@ impl InsertableMedia {
             self.media_type
         ).fetch_one(&mut *conn).await?.id;
 
+        Media::refresh_sort_title(&mut *conn, id).await?;
+
         Ok(id)
     }
 
@@ -363,6 +1829,8 @@ impl InsertableMedia {
             self.media_type
         ).execute(&mut *conn).await?;
 
+        Media::refresh_sort_title(&mut *conn, id).await?;
+
         Ok(id)
     }
 
@@ -373,7 +1841,7 @@ impl InsertableMedia {
         &self,
         conn: &mut crate::Transaction<'_>,
     ) -> Result<i64, DatabaseError> {
-        Ok(sqlx::query!(
+        let id = sqlx::query!(
             r#"INSERT INTO _tblmedia (library_id, name, description, rating, year, added, poster, backdrop, media_type)
             VALUES ($1, $2, $3, $4, $5, $6,$7, $8, $9)"#,
             self.library_id,
@@ -385,13 +1853,39 @@ impl InsertableMedia {
             self.poster,
             self.backdrop,
             self.media_type
-        ).execute(&mut *conn).await?.last_insert_rowid())
+        ).execute(&mut *conn).await?.last_insert_rowid();
+
+        Media::refresh_sort_title(&mut *conn, id).await?;
+
+        Ok(id)
+    }
+
+    /// Method inserts `self` as a placeholder, ie a media object that doesnt have any mediafiles
+    /// yet. This is used to track wanted or unreleased titles. If a media with the same name
+    /// already exists and has files attached to it, it is left untouched.
+    pub async fn insert_placeholder(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<i64, DatabaseError> {
+        let id = self.insert(&mut *conn).await?;
+
+        sqlx::query!(
+            r#"UPDATE _tblmedia SET placeholder = 1
+            WHERE id = $1
+            AND NOT EXISTS (SELECT id FROM mediafile WHERE media_id = $1)
+            AND NOT EXISTS (SELECT id FROM _tblseason WHERE tvshowid = $1)"#,
+            id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(id)
     }
 }
 
 /// Struct which is used when we need to update information about a media object. Same as
-/// [`InsertableMedia`](InsertableMedia) except `library_id` cannot be changed and everything field
-/// is a `Option<T>`.
+/// [`InsertableMedia`](InsertableMedia) except `library_id` and `media_type` cannot be changed and
+/// everything field is a `Option<T>`.
 #[derive(Clone, Default, Deserialize, Debug)]
 pub struct UpdateMedia {
     pub name: Option<String>,
@@ -401,7 +1895,14 @@ pub struct UpdateMedia {
     pub added: Option<String>,
     pub poster: Option<i64>,
     pub backdrop: Option<i64>,
+    /// Title the media is sorted by, see [`sort_title`](sort_title).
+    pub sort_title: Option<String>,
+    /// Never applied, only deserialized so that requests trying to change it can be rejected. See
+    /// [`immutable_fields`](UpdateMedia::immutable_fields).
     pub media_type: Option<MediaType>,
+    /// Never applied, media are moved between libraries with
+    /// [`Media::move_to_library`](Media::move_to_library).
+    pub library_id: Option<i64>,
 }
 
 impl UpdateMedia {
@@ -424,9 +1925,112 @@ impl UpdateMedia {
             "UPDATE _tblmedia SET added = ? WHERE id = ?" => (self.added, id),
             "UPDATE _tblmedia SET poster = ? WHERE id = ?" => (self.poster, id),
             "UPDATE _tblmedia SET backdrop = ? WHERE id = ?" => (self.backdrop, id),
-            "UPDATE _tblmedia SET media_type = ? WHERE id = ?" => (self.media_type, id)
+            "UPDATE _tblmedia SET sort_title = ? WHERE id = ?" => (self.sort_title, id)
         );
 
+        // renamed media are sorted by their new name unless a sort title is set along with it.
+        if self.name.is_some() && self.sort_title.is_none() {
+            Media::refresh_sort_title(&mut *conn, id).await?;
+        }
+
+        Ok(1)
+    }
+
+    /// Method returns the names of the metadata fields set in `self` that can be manually
+    /// overridden.
+    pub fn overridden_fields(&self) -> Vec<&'static str> {
+        let mut fields = vec![];
+
+        if self.name.is_some() {
+            fields.push("name");
+        }
+
+        if self.description.is_some() {
+            fields.push("description");
+        }
+
+        if self.rating.is_some() {
+            fields.push("rating");
+        }
+
+        if self.year.is_some() {
+            fields.push("year");
+        }
+
+        if self.poster.is_some() {
+            fields.push("poster");
+        }
+
+        if self.backdrop.is_some() {
+            fields.push("backdrop");
+        }
+
+        if self.sort_title.is_some() {
+            fields.push("sort_title");
+        }
+
+        fields
+    }
+
+    /// Method returns the names of the fields set in `self` that cannot be changed through an
+    /// update. These are never applied and callers should reject updates setting them.
+    pub fn immutable_fields(&self) -> Vec<&'static str> {
+        let mut fields = vec![];
+
+        if self.media_type.is_some() {
+            fields.push("media_type");
+        }
+
+        if self.library_id.is_some() {
+            fields.push("library_id");
+        }
+
+        fields
+    }
+
+    /// Method updates a media object with automatically fetched metadata like
+    /// [`update`](UpdateMedia::update). Fields manually overridden by a user are restored to
+    /// their overridden values afterwards, thus automatic metadata never replaces a user's edits.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object we want to update
+    pub async fn update_automatic(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        let overrides = Media::get_overridden_values(&mut *conn, id).await?;
+
+        self.update(&mut *conn, id).await?;
+        overrides.update_manual(&mut *conn, id).await?;
+
+        Ok(1)
+    }
+
+    /// Method updates a media object like [`update`](UpdateMedia::update) but additionally marks
+    /// the updated metadata fields as manually overridden.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the media object we want to update
+    pub async fn update_manual(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        self.update(&mut *conn, id).await?;
+
+        for field in self.overridden_fields() {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO media_overrides (media_id, field) VALUES (?, ?)",
+                id,
+                field
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
         Ok(1)
     }
 }
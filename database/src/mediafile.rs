@@ -1,9 +1,12 @@
+use crate::library::MediaType;
 use crate::media::Media;
 use crate::DatabaseError;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use std::collections::HashMap;
+
 /// MediaFile struct which represents a media file on the filesystem. This struct holds some basic
 /// information which the video player on the front end might require.
 #[derive(Serialize, PartialEq, Debug, Clone)]
@@ -54,9 +57,38 @@ pub struct MediaFile {
     pub profile: Option<String>,
     /// Primary audio language
     pub audio_language: Option<String>,
+
+    /// Last episode contained in this file if it spans multiple episodes, ie `S01E01-E02`. In that
+    /// case `episode` holds the first episode and the file is linked to the first episode.
+    pub episode_end: Option<i64>,
+    /// HDR format of the video, one of `hdr10`, `dolby_vision`, `hlg` or `sdr`. `None` if unknown.
+    pub hdr: Option<String>,
+    /// Whether the file has a closed caption subtitle track.
+    pub has_cc: bool,
+    /// Whether the file has a SDH subtitle track, ie subtitles for the deaf and hard of hearing.
+    pub has_sdh: bool,
+    /// Size of the file in bytes as of the last scan. `None` if unknown.
+    pub size: Option<i64>,
+}
+
+/// Summed size of the files of a media type within a library.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct LibrarySize {
+    /// Media type of the files, episodes and their unmatched files count as `tv`.
+    pub media_type: MediaType,
+    /// Summed size of the files in bytes. Files whose size is unknown are left out.
+    pub size: i64,
+    /// Number of files.
+    pub files: i64,
 }
 
 impl MediaFile {
+    /// Method returns whether the file is HDR. Files whose HDR format is unknown are treated as
+    /// SDR.
+    pub fn is_hdr(&self) -> bool {
+        self.hdr.as_deref().map_or(false, |x| x != "sdr")
+    }
+
     /// Method returns all mediafiles associated with a library.
     ///
     /// # Arguments
@@ -94,6 +126,26 @@ impl MediaFile {
         .await?)
     }
 
+    /// Method returns all mediafiles which aren't associated with a media across all libraries,
+    /// ie files that were scanned but couldnt be matched. Mediafiles of hidden libraries are
+    /// excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_orphans(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            "SELECT mediafile.* FROM mediafile
+            JOIN library ON library.id = mediafile.library_id
+            WHERE mediafile.media_id IS NULL AND NOT library.hidden
+            ORDER BY mediafile.library_id, mediafile.target_file"
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
     /// Method returns all mediafiles associated with a Media object.
     ///
     /// # Arguments
@@ -131,6 +183,162 @@ impl MediaFile {
         .await?)
     }
 
+    /// Method returns the file containing the episode supplied if that file spans multiple
+    /// episodes. The file is linked to the first episode in its range, thus this also resolves
+    /// the file for all the following episodes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `episode_id` - id of the episode we are targetting
+    pub async fn get_multi_episode_file(
+        conn: &mut crate::Transaction<'_>,
+        episode_id: i64,
+    ) -> Result<Self, DatabaseError> {
+        Ok(sqlx::query_as!(
+            MediaFile,
+            "SELECT mediafile.* FROM mediafile
+                INNER JOIN episode AS first ON first.id = mediafile.media_id
+                INNER JOIN episode AS target ON target.seasonid = first.seasonid
+                WHERE target.id = ?
+                AND mediafile.episode_end > first.episode_
+                AND target.episode_ BETWEEN first.episode_ AND mediafile.episode_end
+                LIMIT 1",
+            episode_id
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the files spanning multiple episodes of a season keyed by the ids of the
+    /// episodes they contain. This resolves
+    /// [`get_multi_episode_file`](MediaFile::get_multi_episode_file) for a whole season at once.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `season_id` - id of the season we are targetting
+    pub async fn get_multi_episode_files_of_season(
+        conn: &mut crate::Transaction<'_>,
+        season_id: i64,
+    ) -> Result<HashMap<i64, Self>, DatabaseError> {
+        let files = sqlx::query_as!(
+            MediaFile,
+            "SELECT mediafile.* FROM mediafile
+                INNER JOIN episode AS first ON first.id = mediafile.media_id
+                WHERE first.seasonid = ?
+                AND mediafile.episode_end > first.episode_",
+            season_id
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect::<HashMap<_, _>>();
+
+        let contained = sqlx::query!(
+            r#"SELECT target.id as "episode_id!", mediafile.id as "mediafile_id!" FROM mediafile
+                INNER JOIN episode AS first ON first.id = mediafile.media_id
+                INNER JOIN episode AS target ON target.seasonid = first.seasonid
+                WHERE first.seasonid = ?
+                AND mediafile.episode_end > first.episode_
+                AND target.episode_ BETWEEN first.episode_ AND mediafile.episode_end
+                ORDER BY mediafile.id ASC"#,
+            season_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut result = HashMap::new();
+
+        for record in contained {
+            if let Some(file) = files.get(&record.mediafile_id) {
+                result
+                    .entry(record.episode_id)
+                    .or_insert_with(|| file.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Method returns the ids and numbers of the episodes contained in this file ordered by
+    /// episode number.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_contained_episodes(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<(i64, i64)>, DatabaseError> {
+        let episode_end = self.episode_end.or(self.episode);
+
+        Ok(sqlx::query!(
+            r#"SELECT target.id as "id!", target.episode_ as "episode!" FROM episode AS first
+                INNER JOIN episode AS target ON target.seasonid = first.seasonid
+                WHERE first.id = ?
+                AND target.episode_ BETWEEN first.episode_ AND COALESCE(?, first.episode_)
+                ORDER BY target.episode_ ASC"#,
+            self.media_id,
+            episode_end
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.episode))
+        .collect())
+    }
+
+    /// Returns the number of episodes contained in this file.
+    pub fn episode_count(&self) -> i64 {
+        match (self.episode, self.episode_end) {
+            (Some(start), Some(end)) if end > start => end - start + 1,
+            _ => 1,
+        }
+    }
+
+    /// Returns a hint of where `episode` starts in this file in seconds. We dont know where the
+    /// episodes are actually split, thus we assume they all have the same length.
+    pub fn episode_offset(&self, episode: i64) -> Option<i64> {
+        let start = self.episode.unwrap_or(0);
+        let index = episode - start;
+
+        if index < 0 || index >= self.episode_count() {
+            return None;
+        }
+
+        Some(self.episode_length() * index)
+    }
+
+    /// Returns the assumed length of a single episode contained in this file in seconds.
+    pub fn episode_length(&self) -> i64 {
+        self.duration.unwrap_or(0) / self.episode_count()
+    }
+
+    /// Returns the height of the video stream which is stored in `quality`.
+    pub fn height(&self) -> Option<i64> {
+        self.quality.as_deref()?.trim_end_matches('p').parse().ok()
+    }
+
+    /// Returns how preferable the video codec of this file is, lower is better. Codecs that most
+    /// clients can direct play are preferred.
+    pub fn codec_rank(&self) -> usize {
+        const PREFERENCE: &[&str] = &["h264", "vp9", "av1", "hevc"];
+
+        self.codec
+            .as_deref()
+            .and_then(|x| PREFERENCE.iter().position(|&c| c == x))
+            .unwrap_or(PREFERENCE.len())
+    }
+
+    /// Orders files by quality, best first. Files are sorted by resolution descending, then by
+    /// codec preference. Files with an unknown resolution are sorted last.
+    pub fn cmp_quality(&self, other: &Self) -> std::cmp::Ordering {
+        // `None` is smaller than `Some` thus reversing puts files without a height last.
+        other
+            .height()
+            .cmp(&self.height())
+            .then_with(|| self.codec_rank().cmp(&other.codec_rank()))
+    }
+
     /// Method returns all metadata of a mediafile based on the id supplied.
     ///
     /// # Arguments
@@ -173,6 +381,55 @@ impl MediaFile {
         .await?)
     }
 
+    /// Method returns the ids of the mediafiles without a closed caption or SDH track, ie
+    /// because they were scanned before caption tracks were detected.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_without_captions(
+        conn: &mut crate::Transaction<'_>,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT id as "id!: i64" FROM mediafile
+            WHERE NOT has_cc AND NOT has_sdh
+            ORDER BY id ASC"#
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the summed size and number of files in a library, broken down by the media
+    /// type of the files. Sizes are those recorded during the last scan, thus the disk isnt
+    /// touched.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `library_id` - id of the library.
+    pub async fn get_size_of_library(
+        conn: &mut crate::Transaction<'_>,
+        library_id: i64,
+    ) -> Result<Vec<LibrarySize>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            LibrarySize,
+            r#"SELECT
+                CASE _tblmedia.media_type
+                    WHEN 'episode' THEN 'tv'
+                    ELSE COALESCE(_tblmedia.media_type, library.media_type)
+                END as "media_type!: MediaType",
+                COALESCE(SUM(mediafile.size), 0) as "size!: i64",
+                COUNT(mediafile.id) as "files!: i64"
+            FROM mediafile
+            JOIN library ON library.id = mediafile.library_id
+            LEFT OUTER JOIN _tblmedia ON _tblmedia.id = mediafile.media_id
+            WHERE mediafile.library_id = ?
+            GROUP BY 1
+            ORDER BY 1 ASC"#,
+            library_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
     /// Function will return the largest duration for a media.
     pub async fn get_largest_duration(
         conn: &mut crate::Transaction<'_>,
@@ -246,8 +503,13 @@ pub struct InsertableMediaFile {
      ***/
     pub episode: Option<i64>,
     pub season: Option<i64>,
+    pub episode_end: Option<i64>,
     /*** ***/
     pub corrupt: Option<bool>,
+    pub hdr: Option<String>,
+    pub has_cc: bool,
+    pub has_sdh: bool,
+    pub size: Option<i64>,
 }
 
 impl InsertableMediaFile {
@@ -259,8 +521,9 @@ impl InsertableMediaFile {
         let id = sqlx::query!(
             r#"
             INSERT INTO mediafile (media_id, library_id, target_file, raw_name, raw_year, quality,
-            codec, container, audio, original_resolution, duration, episode, season, corrupt, channels, profile, audio_language)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            codec, container, audio, original_resolution, duration, episode, season, corrupt, channels, profile, audio_language,
+            episode_end, hdr, has_cc, has_sdh, size)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
         "#,
             self.media_id,
             self.library_id,
@@ -278,7 +541,12 @@ impl InsertableMediaFile {
             self.corrupt,
             self.channels,
             self.profile,
-            self.audio_language
+            self.audio_language,
+            self.episode_end,
+            self.hdr,
+            self.has_cc,
+            self.has_sdh,
+            self.size
         )
         .execute(&mut *conn)
         .await?
@@ -311,8 +579,13 @@ pub struct UpdateMediaFile {
      ***/
     pub episode: Option<i64>,
     pub season: Option<i64>,
+    pub episode_end: Option<i64>,
     /*** ***/
     pub corrupt: Option<bool>,
+    pub hdr: Option<String>,
+    pub has_cc: Option<bool>,
+    pub has_sdh: Option<bool>,
+    pub size: Option<i64>,
 }
 
 impl UpdateMediaFile {
@@ -340,10 +613,15 @@ impl UpdateMediaFile {
             "UPDATE mediafile SET duration = ? WHERE id = ?" => (self.duration, id),
             "UPDATE mediafile SET episode = ? WHERE id = ?" => (self.episode, id),
             "UPDATE mediafile SET season = ? WHERE id = ?" => (self.season, id),
+            "UPDATE mediafile SET episode_end = ? WHERE id = ?" => (self.episode_end, id),
             "UPDATE mediafile SET corrupt = ? WHERE id = ?" => (self.corrupt, id),
             "UPDATE mediafile SET channels = ? WHERE id = ?" => (self.channels, id),
             "UPDATE mediafile SET profile = ? WHERE id = ?" => (self.profile, id),
-            "UPDATE mediafile SET audio_language = ? WHERE id = ?" => (self.audio_language, id)
+            "UPDATE mediafile SET audio_language = ? WHERE id = ?" => (self.audio_language, id),
+            "UPDATE mediafile SET hdr = ? WHERE id = ?" => (self.hdr, id),
+            "UPDATE mediafile SET has_cc = ? WHERE id = ?" => (self.has_cc, id),
+            "UPDATE mediafile SET has_sdh = ? WHERE id = ?" => (self.has_sdh, id),
+            "UPDATE mediafile SET size = ? WHERE id = ?" => (self.size, id)
         );
 
         Ok(1)
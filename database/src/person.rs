@@ -0,0 +1,252 @@
+use crate::library::MediaType;
+use crate::DatabaseError;
+
+use serde::Serialize;
+
+/// Struct represents a person, ie a actor, as found on TMDB.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct Person {
+    pub id: i64,
+    /// Id of the person on TMDB.
+    pub tmdb_id: i64,
+    pub name: String,
+    /// Url of the profile picture of the person.
+    pub profile_path: Option<String>,
+}
+
+/// Struct represents a person starring in a media.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct CastMember {
+    pub id: i64,
+    pub name: String,
+    /// Name of the character played by the person.
+    pub character: Option<String>,
+    /// Billing order of the person, lower is more prominent.
+    pub ordering: i64,
+}
+
+/// Struct represents a media a person appears in or worked on.
+#[derive(Debug, Clone, Serialize, PartialEq, sqlx::FromRow)]
+pub struct Credit {
+    pub media_id: i64,
+    pub name: String,
+    pub media_type: MediaType,
+    pub year: Option<i64>,
+    pub poster_path: Option<String>,
+    /// Either `Actor` for the cast, or the job of the person, ie `Director`.
+    pub role: String,
+    /// Name of the character played by the person, only set for the cast.
+    pub character: Option<String>,
+}
+
+impl Person {
+    /// Method returns a person by their id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the person.
+    pub async fn get(conn: &mut crate::Transaction<'_>, id: i64) -> Result<Self, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Person,
+            r#"SELECT id as "id!", tmdb_id, name, profile_path FROM people WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the media of libraries that aren't hidden a person appears in or worked
+    /// on, ordered by role with the cast first, then by release year, newest first. A person with
+    /// several jobs on the same media is returned once per job.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the person.
+    pub async fn get_credits(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Vec<Credit>, DatabaseError> {
+        // FIXME: sqlx cant infer the nullability of columns of compound selects, see
+        // https://github.com/launchbadge/sqlx/issues/1249
+        Ok(sqlx::query_as::<_, Credit>(
+            r#"SELECT media_id, media.name, media.media_type, media.year, media.poster_path, role,
+                character
+            FROM (
+                SELECT media_cast.media_id, 'Actor' as role, media_cast.character, 0 as priority
+                FROM media_cast WHERE media_cast.person_id = $1
+                UNION ALL
+                SELECT media_crew.media_id, media_crew.job as role, NULL as character, 1 as priority
+                FROM media_crew WHERE media_crew.person_id = $1
+            ) credits
+            INNER JOIN media ON media.id = credits.media_id
+            INNER JOIN library ON library.id = media.library_id
+            WHERE NOT library.hidden
+            ORDER BY priority ASC, role ASC, media.year DESC, media.name ASC"#,
+        )
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the cast of a media in billing order.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    pub async fn get_cast(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<CastMember>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            CastMember,
+            r#"SELECT people.id as "id!", people.name, media_cast.character, media_cast.ordering
+            FROM people
+            INNER JOIN media_cast ON media_cast.person_id = people.id
+            WHERE media_cast.media_id = ?
+            ORDER BY media_cast.ordering ASC"#,
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method removes the whole cast of a media. People themselves are kept as they might star
+    /// in other medias.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    pub async fn clear_cast(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM media_cast WHERE media_id = ?", media_id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+
+    /// Method removes the whole crew of a media. People themselves are kept as they might have
+    /// worked on other medias.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media.
+    pub async fn clear_crew(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM media_crew WHERE media_id = ?", media_id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+}
+
+/// Struct represents a person that can be inserted into the db.
+#[derive(Debug, Clone, Default)]
+pub struct InsertablePerson {
+    pub tmdb_id: i64,
+    pub name: String,
+    /// Url of the profile picture of the person.
+    pub profile_path: Option<String>,
+}
+
+impl InsertablePerson {
+    /// Method inserts a new person or returns the id of the person with the same TMDB id. The
+    /// name of a existing person is updated to the one supplied, as is their profile picture if
+    /// one is supplied.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        if let Some(id) = sqlx::query_scalar!(
+            r#"SELECT id as "id!: i64" FROM people WHERE tmdb_id = ?"#,
+            self.tmdb_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            sqlx::query!(
+                "UPDATE people SET name = $1, profile_path = COALESCE($2, profile_path)
+                WHERE id = $3",
+                self.name,
+                self.profile_path,
+                id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            return Ok(id);
+        }
+
+        Ok(sqlx::query!(
+            "INSERT INTO people (tmdb_id, name, profile_path) VALUES ($1, $2, $3)",
+            self.tmdb_id,
+            self.name,
+            self.profile_path
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+
+    /// Method inserts the person and links them to the cast of a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media the person stars in.
+    /// * `character` - name of the character played.
+    /// * `ordering` - billing order of the person.
+    pub async fn insert_cast(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        character: Option<String>,
+        ordering: i64,
+    ) -> Result<i64, DatabaseError> {
+        let person_id = self.insert(&mut *conn).await?;
+
+        sqlx::query!(
+            "INSERT OR REPLACE INTO media_cast (media_id, person_id, character, ordering)
+            VALUES ($1, $2, $3, $4)",
+            media_id,
+            person_id,
+            character,
+            ordering
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(person_id)
+    }
+    /// Method inserts the person and links them to the crew of a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media the person worked on.
+    /// * `job` - job of the person, ie `Director`.
+    pub async fn insert_crew(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        job: &str,
+    ) -> Result<i64, DatabaseError> {
+        let person_id = self.insert(&mut *conn).await?;
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO media_crew (media_id, person_id, job) VALUES ($1, $2, $3)",
+            media_id,
+            person_id,
+            job
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(person_id)
+    }
+}
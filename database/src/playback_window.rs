@@ -0,0 +1,91 @@
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Number of minutes in a day.
+pub const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Struct represents a window of time during which a user is allowed to play media. Users without
+/// any windows can play media at any time.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PlaybackWindow {
+    /// Day of the week, `0` is monday and `6` is sunday.
+    pub weekday: i64,
+    /// Minutes since midnight from which playback is allowed.
+    pub start_minute: i64,
+    /// Minutes since midnight until which playback is allowed, exclusive.
+    pub end_minute: i64,
+}
+
+impl PlaybackWindow {
+    /// Method returns the playback windows of a user ordered by day and start.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            PlaybackWindow,
+            "SELECT weekday, start_minute, end_minute FROM playback_windows
+            WHERE user_id = ?
+            ORDER BY weekday, start_minute",
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method replaces the playback windows of a user. Passing no windows lifts the restriction.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `windows` - the new playback windows of the user.
+    pub async fn set_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        windows: &[Self],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM playback_windows WHERE user_id = ?", uid)
+            .execute(&mut *conn)
+            .await?;
+
+        for window in windows {
+            sqlx::query!(
+                "INSERT INTO playback_windows (user_id, weekday, start_minute, end_minute)
+                VALUES ($1, $2, $3, $4)",
+                uid,
+                window.weekday,
+                window.start_minute,
+                window.end_minute
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the window lies within a single day, ie the weekday is valid and it starts
+    /// before it ends.
+    pub fn is_valid(&self) -> bool {
+        (0..7).contains(&self.weekday)
+            && self.start_minute >= 0
+            && self.start_minute < self.end_minute
+            && self.end_minute <= MINUTES_PER_DAY
+    }
+
+    /// Returns whether `minute` on `weekday` falls into this window.
+    pub fn contains(&self, weekday: i64, minute: i64) -> bool {
+        self.weekday == weekday && self.start_minute <= minute && minute < self.end_minute
+    }
+
+    /// Returns whether a user with `windows` may play media at `minute` on `weekday`.
+    pub fn allows(windows: &[Self], weekday: i64, minute: i64) -> bool {
+        windows.is_empty() || windows.iter().any(|x| x.contains(weekday, minute))
+    }
+}
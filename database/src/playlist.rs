@@ -0,0 +1,292 @@
+use crate::library::MediaType;
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use std::time::SystemTime;
+
+/// Ordered list of media created by a user, it can mix movies and episodes. Playlists are only
+/// visible to the user that created them.
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Playlist {
+    pub id: i64,
+    pub name: String,
+    /// Unix timestamp of when the playlist was created.
+    pub created_at: i64,
+    /// Number of items in the playlist.
+    pub item_count: i64,
+}
+
+/// Item of a playlist along with the progress of the owner of the playlist through its media,
+/// returned by [`Playlist::get_items`](Playlist::get_items).
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct PlaylistItem {
+    /// Id of the item, a media can be in a playlist more than once.
+    pub item_id: i64,
+    pub position: i64,
+    pub media_id: i64,
+    pub name: String,
+    pub media_type: MediaType,
+    pub poster_path: Option<String>,
+    pub backdrop_path: Option<String>,
+    /// Offset in seconds the user has watched up to, `0` if the user never started the media.
+    pub progress: i64,
+    /// Duration of the media in seconds, `0` if unknown.
+    pub duration: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InsertablePlaylist {
+    pub name: String,
+}
+
+impl InsertablePlaylist {
+    /// Method creates a empty playlist owned by `uid` and returns its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user creating the playlist.
+    pub async fn insert(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<i64, DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Ok(sqlx::query!(
+            "INSERT INTO playlist (user_id, name, created_at) VALUES ($1, $2, $3)",
+            uid,
+            self.name,
+            timestamp
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+}
+
+impl Playlist {
+    /// Method returns a playlist of a user. Playlists of other users are never returned.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `uid` - username of the user.
+    pub async fn get(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        uid: &str,
+    ) -> Result<Self, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Playlist,
+            r#"SELECT playlist.id as "id!", playlist.name, playlist.created_at,
+                COUNT(playlist_item.id) as "item_count!: i64"
+            FROM playlist
+            LEFT OUTER JOIN playlist_item ON playlist_item.playlist_id = playlist.id
+            WHERE playlist.id = ? AND playlist.user_id = ?
+            GROUP BY playlist.id"#,
+            id,
+            uid
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the playlists of a user sorted by name.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_of_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Playlist,
+            r#"SELECT playlist.id as "id!", playlist.name, playlist.created_at,
+                COUNT(playlist_item.id) as "item_count!: i64"
+            FROM playlist
+            LEFT OUTER JOIN playlist_item ON playlist_item.playlist_id = playlist.id
+            WHERE playlist.user_id = ?
+            GROUP BY playlist.id
+            ORDER BY playlist.name ASC, playlist.id ASC"#,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method deletes a playlist of a user along with its items. Returns the number of playlists
+    /// deleted, `0` if the user has no such playlist.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `uid` - username of the user.
+    pub async fn delete(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        uid: &str,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM playlist WHERE id = ? AND user_id = ?", id, uid)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+
+    /// Method returns the items of a playlist in order along with the progress of `uid` through
+    /// them. Items whose media are in hidden libraries are left out.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `uid` - username of the user whose progress is returned.
+    pub async fn get_items(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        uid: &str,
+    ) -> Result<Vec<PlaylistItem>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            PlaylistItem,
+            r#"SELECT playlist_item.id as "item_id!: i64", playlist_item.position,
+                media.id as "media_id!: i64", media.name, media.media_type as "media_type: MediaType",
+                media.poster_path, media.backdrop_path,
+                COALESCE(progress.delta, 0) as "progress!: i64",
+                COALESCE(_tblmedia.duration, 0) as "duration!: i64"
+            FROM playlist_item
+            INNER JOIN media ON media.id = playlist_item.media_id
+            INNER JOIN _tblmedia ON _tblmedia.id = playlist_item.media_id
+            INNER JOIN library ON library.id = media.library_id
+            LEFT OUTER JOIN progress ON progress.media_id = media.id AND progress.user_id = $2
+            WHERE playlist_item.playlist_id = $1 AND NOT library.hidden
+            ORDER BY playlist_item.position ASC, playlist_item.id ASC"#,
+            id,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method appends a media to the end of a playlist and returns the id of the new item.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `media_id` - id of the media.
+    pub async fn add_item(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        media_id: i64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "INSERT INTO playlist_item (playlist_id, media_id, position)
+            VALUES ($1, $2, (SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_item
+                WHERE playlist_id = $1))",
+            id,
+            media_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+
+    /// Method removes a item from a playlist, the items after it move up. Returns the number of
+    /// items removed, `0` if the playlist has no such item.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `item_id` - id of the item.
+    pub async fn remove_item(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        item_id: i64,
+    ) -> Result<usize, DatabaseError> {
+        let rows = sqlx::query!(
+            "DELETE FROM playlist_item WHERE id = ? AND playlist_id = ?",
+            item_id,
+            id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+
+        if rows > 0 {
+            let items = Self::get_item_ids(&mut *conn, id).await?;
+            Self::renumber(&mut *conn, &items).await?;
+        }
+
+        Ok(rows)
+    }
+
+    /// Method moves a item of a playlist to `position`, the items in between shift to make room.
+    /// Positions past the end move the item to the end. Returns whether the playlist has such a
+    /// item.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the playlist.
+    /// * `item_id` - id of the item.
+    /// * `position` - new position of the item starting at 0.
+    pub async fn move_item(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        item_id: i64,
+        position: usize,
+    ) -> Result<bool, DatabaseError> {
+        let mut items = Self::get_item_ids(&mut *conn, id).await?;
+
+        let current = match items.iter().position(|x| *x == item_id) {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        items.remove(current);
+        items.insert(position.min(items.len()), item_id);
+
+        Self::renumber(&mut *conn, &items).await?;
+
+        Ok(true)
+    }
+
+    /// Returns the ids of the items of a playlist in order.
+    async fn get_item_ids(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT id as "id!: i64" FROM playlist_item
+            WHERE playlist_id = ?
+            ORDER BY position ASC, id ASC"#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Sets the position of every item in `items` to its index.
+    async fn renumber(
+        conn: &mut crate::Transaction<'_>,
+        items: &[i64],
+    ) -> Result<(), DatabaseError> {
+        for (position, item_id) in items.iter().enumerate() {
+            let position = position as i64;
+
+            sqlx::query!(
+                "UPDATE playlist_item SET position = ? WHERE id = ?",
+                position,
+                item_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
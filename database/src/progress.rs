@@ -1,3 +1,4 @@
+use crate::history::History;
 use crate::library::MediaType;
 use crate::media::Media;
 use crate::DatabaseError as DieselError;
@@ -5,6 +6,12 @@ use crate::DatabaseError as DieselError;
 use serde::Serialize;
 use std::time::SystemTime;
 
+/// Fraction of a media's duration after which we consider it watched.
+pub const WATCHED_THRESHOLD: f64 = 0.90;
+
+/// Offsets in seconds within which reported progress is considered unchanged.
+pub const PROGRESS_EPSILON: i64 = 1;
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct Progress {
     pub id: i64,
@@ -14,18 +21,143 @@ pub struct Progress {
     pub populated: i64,
 }
 
+/// Aggregated watch statistics of a media.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct ProgressStats {
+    /// Number of times the media has been watched to completion.
+    pub watch_count: i64,
+    /// Average completion in percent over everyone who started watching the media.
+    pub average_completion: f64,
+    /// Unix timestamp of when the media was last watched, `0` if it never was.
+    pub last_watched: i64,
+}
+
+/// Progress of a user through a media along with the duration of the media.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct MediaProgress {
+    pub media_id: i64,
+    /// Offset in seconds the user has watched up to, `0` if the user never started the media.
+    pub delta: i64,
+    /// Duration of the media in seconds, `0` if unknown.
+    pub duration: i64,
+}
+
+impl MediaProgress {
+    /// Returns whether the user has watched past [`WATCHED_THRESHOLD`](WATCHED_THRESHOLD).
+    pub fn is_finished(&self) -> bool {
+        self.duration > 0 && self.delta as f64 / self.duration as f64 > WATCHED_THRESHOLD
+    }
+}
+
+/// A media every user has watched to completion.
+#[derive(Debug, Clone, Serialize, PartialEq, sqlx::FromRow)]
+pub struct WatchedMedia {
+    pub id: i64,
+    pub name: String,
+    pub media_type: MediaType,
+    /// Unix timestamp of when any user last made progress on the media.
+    pub last_watched: i64,
+}
+
+/// Progress of a user through a tv show.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct ShowProgress {
+    pub id: i64,
+    pub name: String,
+    pub poster_path: Option<String>,
+    /// Number of episodes the user has watched to completion.
+    pub watched_episodes: i64,
+    pub total_episodes: i64,
+}
+
+impl ShowProgress {
+    /// Returns the percentage of episodes watched.
+    pub fn percent(&self) -> f64 {
+        if self.total_episodes == 0 {
+            return 0.0;
+        }
+
+        self.watched_episodes as f64 / self.total_episodes as f64 * 100.0
+    }
+}
+
 impl Progress {
+    /// Returns whether `offset` is within [`PROGRESS_EPSILON`](PROGRESS_EPSILON) of the stored
+    /// progress. Progress that was never stored is never considered the same.
+    pub fn is_same_offset(&self, offset: i64) -> bool {
+        self.populated != 0 && (self.delta - offset).abs() <= PROGRESS_EPSILON
+    }
+
     pub async fn set(
         conn: &mut crate::Transaction<'_>,
         delta: i64,
         uid: String,
         mid: i64,
+    ) -> Result<usize, DieselError> {
+        Self::set_with(&mut *conn, delta, uid, mid, true).await
+    }
+
+    /// Method sets the progress of a user through a media like [`set`](Progress::set). If
+    /// `record_play` is unset crossing the watched threshold only stores the offset, no play is
+    /// recorded in the history of the user.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `delta` - offset in seconds the user has watched up to.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    /// * `record_play` - whether to record a play when the watched threshold is crossed.
+    pub async fn set_with(
+        conn: &mut crate::Transaction<'_>,
+        delta: i64,
+        uid: String,
+        mid: i64,
+        record_play: bool,
     ) -> Result<usize, DieselError> {
         let timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
+        Self::set_at(&mut *conn, delta, uid, mid, timestamp, record_play).await
+    }
+
+    /// Method sets the progress of a user through a media like
+    /// [`set_with`](Progress::set_with), but records it as made at `timestamp` rather than now.
+    /// Used to write progress that was buffered in memory for a while.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `delta` - offset in seconds the user has watched up to.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    /// * `timestamp` - unix timestamp of when the progress was made.
+    /// * `record_play` - whether to record a play when the watched threshold is crossed.
+    pub async fn set_at(
+        conn: &mut crate::Transaction<'_>,
+        delta: i64,
+        uid: String,
+        mid: i64,
+        timestamp: i64,
+        record_play: bool,
+    ) -> Result<usize, DieselError> {
+        // a play is recorded every time the user crosses the watched threshold of a media.
+        if let Some(duration) = Media::get_cached_duration(&mut *conn, mid)
+            .await
+            .ok()
+            .flatten()
+            .filter(|x| *x > 0 && record_play)
+        {
+            let previous = Self::get_for_media_user(&mut *conn, uid.clone(), mid)
+                .await?
+                .delta;
+            let watched = |x: i64| x as f64 / duration as f64 > WATCHED_THRESHOLD;
+
+            if !watched(previous) && watched(delta) {
+                History::record(&mut *conn, &uid, mid).await?;
+            }
+        }
+
         Ok(sqlx::query!(
             "INSERT OR REPLACE INTO progress (delta, media_id, user_id, populated)
             VALUES ($1, $2, $3, $4)",
@@ -39,6 +171,89 @@ impl Progress {
         .rows_affected() as usize)
     }
 
+    /// Method inserts or replaces a progress entry keeping the supplied `populated` timestamp.
+    /// This is used when restoring progress from an export.
+    pub async fn restore(
+        conn: &mut crate::Transaction<'_>,
+        delta: i64,
+        uid: String,
+        mid: i64,
+        populated: i64,
+    ) -> Result<usize, DieselError> {
+        Ok(sqlx::query!(
+            "INSERT OR REPLACE INTO progress (delta, media_id, user_id, populated)
+            VALUES ($1, $2, $3, $4)",
+            delta,
+            mid,
+            uid,
+            populated
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns whether progress of `uid` through `mid` can be stored, ie whether both the
+    /// user and the media still exist.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    pub async fn can_set(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        mid: i64,
+    ) -> Result<bool, DieselError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)
+                AND EXISTS(SELECT 1 FROM _tblmedia WHERE id = $2) as "can_set!: bool""#,
+            uid,
+            mid
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method deletes the progress of all users for a media.
+    pub async fn delete_for_media(
+        conn: &mut crate::Transaction<'_>,
+        mid: i64,
+    ) -> Result<usize, DieselError> {
+        Ok(sqlx::query!("DELETE FROM progress WHERE media_id = ?", mid)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected() as usize)
+    }
+
+    /// Method deletes all progress entries pointing at media that no longer exists and returns
+    /// the number of entries removed.
+    pub async fn prune_orphans(conn: &mut crate::Transaction<'_>) -> Result<usize, DieselError> {
+        Ok(sqlx::query!(
+            "DELETE FROM progress
+            WHERE media_id NOT IN (SELECT _tblmedia.id FROM _tblmedia)"
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+
+    /// Method returns all progress entries of a user.
+    pub async fn get_all_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+    ) -> Result<Vec<Self>, DieselError> {
+        Ok(sqlx::query_as!(
+            Progress,
+            "SELECT progress.* FROM progress
+            WHERE user_id = ?
+            ORDER BY progress.populated DESC",
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
     pub async fn get_for_media_user(
         conn: &mut crate::Transaction<'_>,
         uid: String,
@@ -117,6 +332,74 @@ impl Progress {
         Ok((record.delta, record.duration))
     }
 
+    /// Method returns the progress of a user through several media at once. Media that dont
+    /// exist are omitted, media the user never started have a `delta` of `0`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - id of the user.
+    /// * `ids` - ids of the media.
+    pub async fn get_for_medias(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+        ids: &[i64],
+    ) -> Result<Vec<MediaProgress>, DieselError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let query = format!(
+            "SELECT _tblmedia.id as media_id, COALESCE(progress.delta, 0) as delta,
+                COALESCE(_tblmedia.duration, 0) as duration
+            FROM _tblmedia
+            LEFT OUTER JOIN progress ON progress.media_id = _tblmedia.id AND progress.user_id = ?
+            WHERE _tblmedia.id IN ({})",
+            placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, MediaProgress>(&query).bind(uid);
+
+        for id in ids {
+            query = query.bind(id);
+        }
+
+        Ok(query.fetch_all(&mut *conn).await?)
+    }
+
+    /// Method returns the most recently updated media a user has started but not finished
+    /// watching, ie to resume playback straight from the home screen. For tv shows this is an
+    /// episode. Media in hidden libraries and media without a known duration are skipped.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - id of the user.
+    pub async fn get_last_in_progress(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+    ) -> Result<Option<MediaProgress>, DieselError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, MediaProgress>(
+            r#"SELECT progress.media_id, progress.delta, _tblmedia.duration FROM progress
+            JOIN _tblmedia ON _tblmedia.id = progress.media_id
+            JOIN library ON library.id = _tblmedia.library_id
+
+            WHERE progress.user_id = ?
+            AND NOT progress.populated = 0
+            AND progress.delta > 0
+            AND _tblmedia.duration > 0
+            AND CAST(progress.delta AS REAL) / _tblmedia.duration <= ?
+            AND NOT library.hidden
+
+            ORDER BY progress.populated DESC
+            LIMIT 1"#,
+        )
+        .bind(uid)
+        .bind(WATCHED_THRESHOLD)
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
     pub async fn get_total_for_tv(
         conn: &mut crate::Transaction<'_>,
         uid: String,
@@ -144,6 +427,108 @@ impl Progress {
         .total)
     }
 
+    /// Method returns the watch statistics of a media. For tv shows the statistics of all
+    /// episodes are aggregated.
+    ///
+    /// # Arguments
+    /// * `media_id` - id of the media
+    /// * `uid` - only aggregate the progress of this user, if `None` the progress of all users is
+    /// aggregated.
+    pub async fn get_stats(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        uid: Option<String>,
+    ) -> Result<ProgressStats, DieselError> {
+        Ok(sqlx::query_as::<_, ProgressStats>(
+            r#"SELECT
+                COUNT(CASE WHEN CAST(progress.delta AS REAL) / _tblmedia.duration > $2 THEN 1 END) as watch_count,
+                COALESCE(AVG(CASE WHEN _tblmedia.duration > 0
+                    THEN MIN(CAST(progress.delta AS REAL) / _tblmedia.duration, 1.0) END) * 100, 0.0) as average_completion,
+                COALESCE(MAX(progress.populated), 0) as last_watched
+            FROM progress
+            JOIN _tblmedia ON _tblmedia.id = progress.media_id
+
+            WHERE (progress.media_id = $1
+                OR progress.media_id IN (SELECT episode.id FROM episode
+                    JOIN season ON season.id = episode.seasonid
+                    WHERE season.tvshowid = $1))
+            AND ($3 IS NULL OR progress.user_id = $3)"#,
+        )
+        .bind(media_id)
+        .bind(WATCHED_THRESHOLD)
+        .bind(uid)
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the movies and episodes that every user has watched past
+    /// [`WATCHED_THRESHOLD`](WATCHED_THRESHOLD) and that nobody made progress on since `before`,
+    /// least recently watched first. Media in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `before` - unix timestamp the media must have last been watched before.
+    pub async fn get_watched_by_all(
+        conn: &mut crate::Transaction<'_>,
+        before: i64,
+    ) -> Result<Vec<WatchedMedia>, DieselError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, WatchedMedia>(
+            r#"SELECT _tblmedia.id, _tblmedia.name, _tblmedia.media_type,
+                MAX(progress.populated) as last_watched
+            FROM _tblmedia
+            JOIN progress ON progress.media_id = _tblmedia.id
+            JOIN library ON library.id = _tblmedia.library_id
+
+            WHERE NOT library.hidden
+            AND NOT _tblmedia.media_type = "tv"
+            AND _tblmedia.duration > 0
+
+            GROUP BY _tblmedia.id
+            HAVING COUNT(CASE WHEN CAST(progress.delta AS REAL) / _tblmedia.duration > ? THEN 1 END)
+                = (SELECT COUNT(*) FROM users)
+            AND last_watched < ?
+            ORDER BY last_watched ASC"#,
+        )
+        .bind(WATCHED_THRESHOLD)
+        .bind(before)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the shows of which a user has watched at least one but not all episodes,
+    /// most recently watched first. Shows in hidden libraries are excluded.
+    pub async fn get_in_progress_shows(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+    ) -> Result<Vec<ShowProgress>, DieselError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, ShowProgress>(
+            r#"SELECT media.id, media.name, media.poster_path,
+                COUNT(CASE WHEN CAST(progress.delta AS REAL) / ep.duration > $1 THEN 1 END) as watched_episodes,
+                COUNT(episode.id) as total_episodes
+            FROM media
+            JOIN season ON season.tvshowid = media.id
+            JOIN episode ON episode.seasonid = season.id
+            JOIN _tblmedia AS ep ON ep.id = episode.id
+            JOIN library ON library.id = media.library_id
+            LEFT OUTER JOIN progress ON progress.media_id = episode.id AND progress.user_id = $2
+
+            WHERE NOT library.hidden
+            AND NOT EXISTS (
+                SELECT 1 FROM hidden_media
+                WHERE hidden_media.media_id = media.id AND hidden_media.user_id = $2)
+
+            GROUP BY media.id
+            HAVING watched_episodes > 0 AND watched_episodes < total_episodes
+            ORDER BY MAX(progress.populated) DESC"#,
+        )
+        .bind(WATCHED_THRESHOLD)
+        .bind(uid)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
     pub async fn get_continue_watching(
         conn: &mut crate::Transaction<'_>,
         uid: String,
@@ -158,16 +543,54 @@ impl Progress {
             JOIN library on library.id = _tblmedia.library_id
 
             WHERE NOT progress.populated = 0
-            AND progress.user_id = ?
+            AND progress.user_id = $1
             AND NOT library.hidden
+            AND NOT EXISTS (
+                SELECT 1 FROM hidden_media
+                WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $1)
 
             GROUP BY _tblmedia.id
             ORDER BY progress.populated DESC
-            LIMIT ?"#,
+            LIMIT $2"#,
         )
         .bind(uid)
         .bind(count)
         .fetch_all(&mut *conn)
         .await?)
     }
+
+    /// Method returns the ids of all episodes of a tv show that a user hasn't finished watching,
+    /// paired with the id of the season they belong to. An episode is considered finished once its
+    /// progress exceeds [`WATCHED_THRESHOLD`] of its duration. Episodes without a known duration
+    /// are always treated as unwatched.
+    ///
+    /// # Arguments
+    /// * `uid` - user whose progress we are checking
+    /// * `tv_id` - id of the tv show
+    pub async fn get_unwatched_episodes_of_tv(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+        tv_id: i64,
+    ) -> Result<Vec<(i64, i64)>, DieselError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as(
+            r#"SELECT episode.id, episode.seasonid FROM episode
+            JOIN season ON season.id = episode.seasonid
+            JOIN _tblmedia ON _tblmedia.id = episode.id
+            LEFT OUTER JOIN progress ON progress.media_id = episode.id AND progress.user_id = ?
+
+            WHERE season.tvshowid = ?
+            AND (progress.delta IS NULL
+                OR _tblmedia.duration IS NULL
+                OR _tblmedia.duration = 0
+                OR CAST(progress.delta AS REAL) / _tblmedia.duration <= ?)
+
+            ORDER BY episode.id"#,
+        )
+        .bind(uid)
+        .bind(tv_id)
+        .bind(WATCHED_THRESHOLD)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
 }
@@ -0,0 +1,142 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Lowest score a user can rate a media with.
+pub const MIN_SCORE: i64 = 1;
+/// Highest score a user can rate a media with. This matches the scale used by TMDB.
+pub const MAX_SCORE: i64 = 10;
+
+/// Struct represents a rating a user has given to a media.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Rating {
+    pub id: i64,
+    pub user_id: String,
+    pub media_id: i64,
+    /// Score between [`MIN_SCORE`](MIN_SCORE) and [`MAX_SCORE`](MAX_SCORE).
+    pub score: i64,
+    pub populated: i64,
+}
+
+impl Rating {
+    /// Method returns the rating a user has given to a media if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    pub async fn get_for_media_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+        mid: i64,
+    ) -> Result<Option<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Rating,
+            "SELECT ratings.* FROM ratings
+            WHERE user_id = ?
+            AND media_id = ?",
+            uid,
+            mid
+        )
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all ratings a user has given.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_all_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Rating,
+            "SELECT ratings.* FROM ratings
+            WHERE user_id = ?
+            ORDER BY ratings.populated DESC",
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the average score and the number of local ratings for a media. The average
+    /// is `None` if nobody has rated this media yet.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `mid` - id of the media.
+    pub async fn get_community_rating(
+        conn: &mut crate::Transaction<'_>,
+        mid: i64,
+    ) -> Result<(Option<f64>, i64), DatabaseError> {
+        let record = sqlx::query!(
+            r#"SELECT AVG(score) as "average: f64", COUNT(id) as "count!: i64" FROM ratings
+            WHERE media_id = ?"#,
+            mid
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok((record.average, record.count))
+    }
+
+    /// Method removes the rating a user has given to a media.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `mid` - id of the media.
+    pub async fn delete(
+        conn: &mut crate::Transaction<'_>,
+        uid: String,
+        mid: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!(
+            "DELETE FROM ratings WHERE user_id = ? AND media_id = ?",
+            uid,
+            mid
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+}
+
+/// Struct represents a new rating or a update to an existing rating.
+#[derive(Debug, Clone, Default)]
+pub struct InsertableRating {
+    pub user_id: String,
+    pub media_id: i64,
+    pub score: i64,
+}
+
+impl InsertableRating {
+    /// Method inserts or replaces the rating of a user for a media. Callers are expected to
+    /// validate that `score` is within the scale of [`MIN_SCORE`](MIN_SCORE) and
+    /// [`MAX_SCORE`](MAX_SCORE).
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<usize, DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        Ok(sqlx::query!(
+            "INSERT OR REPLACE INTO ratings (user_id, media_id, score, populated)
+            VALUES ($1, $2, $3, $4)",
+            self.user_id,
+            self.media_id,
+            self.score,
+            timestamp
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize)
+    }
+}
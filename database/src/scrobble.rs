@@ -0,0 +1,273 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+
+/// External service, ie Trakt, a user scrobbles their watch status to.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct ScrobbleIntegration {
+    #[serde(skip_serializing)]
+    pub user_id: String,
+    /// Base url of the service, scrobbles are posted to `<url>/scrobble/<action>`.
+    pub url: String,
+    /// Access token sent as a bearer token with every scrobble.
+    #[serde(skip_serializing)]
+    pub token: String,
+}
+
+impl ScrobbleIntegration {
+    /// Method returns the integration of a user, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Option<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            ScrobbleIntegration,
+            "SELECT user_id, url, token FROM scrobble_integrations WHERE user_id = ?",
+            uid
+        )
+        .fetch_optional(&mut *conn)
+        .await?)
+    }
+
+    /// Method sets up the integration of a user, replacing the existing one. Scrobbles queued for
+    /// the existing integration are kept and sent to the new one.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn set(&self, conn: &mut crate::Transaction<'_>) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO scrobble_integrations (user_id, url, token) VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE SET url = $2, token = $3",
+            self.user_id,
+            self.url,
+            self.token
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Method removes the integration of a user along with its queued scrobbles.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn delete_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM scrobble_integrations WHERE user_id = ?", uid)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+}
+
+/// What a scrobble refers to on the external service.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScrobbleTarget {
+    /// Either `movie` or `episode`.
+    pub media_type: String,
+    /// TMDB id of the movie, or of the show for episodes.
+    pub tmdb_id: i64,
+    pub season: Option<i64>,
+    pub episode: Option<i64>,
+}
+
+impl ScrobbleTarget {
+    /// Method returns the target of a movie or episode. `None` if the media isnt matched against
+    /// TMDB or is a show.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the movie or episode.
+    pub async fn get(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Option<Self>, DatabaseError> {
+        let record = sqlx::query!(
+            r#"SELECT _tblmedia.media_type as "media_type!: String",
+                COALESCE(show.tmdb_id, _tblmedia.tmdb_id) as "tmdb_id: i64",
+                season.season_number as "season?: i64",
+                episode.episode_ as "episode?: i64"
+            FROM _tblmedia
+            LEFT OUTER JOIN episode ON episode.id = _tblmedia.id
+            LEFT OUTER JOIN season ON season.id = episode.seasonid
+            LEFT OUTER JOIN _tblmedia show ON show.id = season.tvshowid
+            WHERE _tblmedia.id = ?"#,
+            media_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(record.and_then(|x| {
+            if x.media_type != "movie" && x.media_type != "episode" {
+                return None;
+            }
+
+            Some(Self {
+                media_type: x.media_type,
+                tmdb_id: x.tmdb_id?,
+                season: x.season,
+                episode: x.episode,
+            })
+        }))
+    }
+}
+
+/// Scrobble waiting to be sent along with the integration it is sent to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scrobble {
+    pub id: i64,
+    pub user_id: String,
+    /// Either `start` or `stop`.
+    pub action: String,
+    pub target: ScrobbleTarget,
+    /// Progress through the media in percent.
+    pub progress: f64,
+    /// Number of failed attempts at sending the scrobble.
+    pub attempts: i64,
+    pub url: String,
+    pub token: String,
+}
+
+impl Scrobble {
+    /// Method returns at most `limit` scrobbles that are due at `now`, oldest first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `now` - unix timestamp.
+    /// * `limit` - maximum number of scrobbles returned.
+    pub async fn get_due(
+        conn: &mut crate::Transaction<'_>,
+        now: i64,
+        limit: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        let records = sqlx::query!(
+            r#"SELECT scrobbles.id as "id!", scrobbles.user_id, action, media_type, tmdb_id,
+                season, episode, progress, attempts, url, token
+            FROM scrobbles
+            INNER JOIN scrobble_integrations
+                ON scrobble_integrations.user_id = scrobbles.user_id
+            WHERE next_attempt <= $1
+            ORDER BY scrobbles.id ASC
+            LIMIT $2"#,
+            now,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|x| Self {
+                id: x.id,
+                user_id: x.user_id,
+                action: x.action,
+                target: ScrobbleTarget {
+                    media_type: x.media_type,
+                    tmdb_id: x.tmdb_id,
+                    season: x.season,
+                    episode: x.episode,
+                },
+                progress: x.progress,
+                attempts: x.attempts,
+                url: x.url,
+                token: x.token,
+            })
+            .collect())
+    }
+
+    /// Method returns the number of scrobbles a user has queued.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn count_for_user(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM scrobbles WHERE user_id = ?"#,
+            uid
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count)
+    }
+
+    /// Method queues a scrobble to be sent right away.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `action` - either `start` or `stop`.
+    /// * `target` - what is being scrobbled.
+    /// * `progress` - progress through the media in percent.
+    pub async fn queue(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        action: &str,
+        target: &ScrobbleTarget,
+        progress: f64,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            "INSERT INTO scrobbles (user_id, action, media_type, tmdb_id, season, episode, progress)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            uid,
+            action,
+            target.media_type,
+            target.tmdb_id,
+            target.season,
+            target.episode,
+            progress
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+
+    /// Method records a failed attempt at sending a scrobble and postpones it until
+    /// `next_attempt`.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the scrobble.
+    /// * `next_attempt` - unix timestamp of when to try again.
+    pub async fn postpone(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        next_attempt: i64,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE scrobbles SET attempts = attempts + 1, next_attempt = $2 WHERE id = $1",
+            id,
+            next_attempt
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Method removes a scrobble from the queue, either because it was sent or because it was
+    /// given up on.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the scrobble.
+    pub async fn delete(conn: &mut crate::Transaction<'_>, id: i64) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM scrobbles WHERE id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+}
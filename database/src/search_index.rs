@@ -0,0 +1,111 @@
+use crate::DatabaseError;
+
+/// Full-text index over the names and alternate titles of all non-episode media. Triggers keep
+/// the index up to date as media and their titles change, thus it only has to be rebuilt after
+/// the database was changed behind our back, ie by bulk imports.
+pub struct SearchIndex;
+
+impl SearchIndex {
+    /// Method returns the number of media that are indexed, ie all media that aren't episodes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn count_indexable(conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM _tblmedia WHERE NOT media_type = "episode""#
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method reindexes the first `limit` non-episode media whose id is greater than `after`.
+    /// Returns the id of the last media reindexed and the number of media reindexed, or `None` if
+    /// there are no media left.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `after` - id after which to start, `0` for the first batch.
+    /// * `limit` - number of media to reindex.
+    pub async fn reindex(
+        conn: &mut crate::Transaction<'_>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Option<(i64, usize)>, DatabaseError> {
+        let last = sqlx::query_scalar!(
+            r#"SELECT MAX(id) as "id: i64" FROM (
+                SELECT id FROM _tblmedia
+                WHERE id > ? AND NOT media_type = "episode"
+                ORDER BY id LIMIT ?
+            )"#,
+            after,
+            limit
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let last = match last {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        sqlx::query!(
+            "DELETE FROM media_search WHERE rowid > ? AND rowid <= ?",
+            after,
+            last
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let indexed = sqlx::query!(
+            r#"INSERT INTO media_search (rowid, name, alternate_titles)
+            SELECT id, name, (
+                SELECT group_concat(title, ' ') FROM alternate_title
+                WHERE alternate_title.media_id = _tblmedia.id
+            )
+            FROM _tblmedia
+            WHERE id > ? AND id <= ? AND NOT media_type = "episode""#,
+            after,
+            last
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+
+        Ok(Some((last, indexed)))
+    }
+
+    /// Method removes entries of media past the last one reindexed, which thus no longer exist.
+    /// Must be called after the last batch of [`reindex`](SearchIndex::reindex).
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `last` - id of the last media reindexed.
+    pub async fn prune(
+        conn: &mut crate::Transaction<'_>,
+        last: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(
+            sqlx::query!("DELETE FROM media_search WHERE rowid > ?", last)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected() as usize,
+        )
+    }
+
+    /// Method returns the ids of the media matching a full-text query, best matches first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `query` - FTS5 query, ie `"godfa"*`.
+    pub async fn search(
+        conn: &mut crate::Transaction<'_>,
+        query: &str,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT rowid as "id!: i64" FROM media_search WHERE media_search MATCH ? ORDER BY rank"#,
+            query
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+}
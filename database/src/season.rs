@@ -15,10 +15,17 @@ pub struct Season {
     pub added: Option<String>,
     /// Id of the asset pointing to the poster.
     pub poster: Option<String>,
+    /// Name of the season as listed on TMDB.
+    pub name: Option<String>,
+    /// Overview of the season as listed on TMDB.
+    pub overview: Option<String>,
+    /// Date the season first aired formatted as `YYYY-MM-DD`.
+    pub air_date: Option<String>,
 }
 
 impl Season {
-    /// Method returns all of the seasons that are linked to a tv show based on a tvshow id
+    /// Method returns all of the seasons that are linked to a tv show based on a tvshow id.
+    /// Specials (season 0) are listed last.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -29,7 +36,10 @@ impl Season {
     ) -> Result<Vec<Self>, DatabaseError> {
         Ok(sqlx::query_as!(
             Self,
-            r#"SELECT id as "id!", season_number, tvshowid, added, poster as "poster?" FROM season WHERE tvshowid = ?"#,
+            r#"SELECT id as "id!", season_number, tvshowid, added, poster as "poster?", name, overview,
+                air_date
+            FROM season WHERE tvshowid = ?
+            ORDER BY season_number = 0, season_number"#,
             tv_id
         )
         .fetch_all(&mut *conn)
@@ -50,8 +60,8 @@ impl Season {
         Ok(sqlx::query_as!(
             Self,
             r#"SELECT id as "id!", season_number ,
-                    tvshowid , added, poster as "poster?"
-               FROM season WHERE id = ? AND season_number = ?"#,
+                    tvshowid , added, poster as "poster?", name, overview, air_date
+               FROM season WHERE tvshowid = ? AND season_number = ?"#,
             tv_id,
             season_num
         )
@@ -92,7 +102,8 @@ impl Season {
         )
     }
 
-    /// Method will return the oldest season for a tv show that is available.
+    /// Method will return the oldest season for a tv show that is available. Specials (season 0)
+    /// are only returned if the show has no other seasons.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -103,10 +114,11 @@ impl Season {
     ) -> Result<Self, DatabaseError> {
         Ok(sqlx::query_as!(
             Self,
-            r#"SELECT id as "id!", season_number, tvshowid, added, poster as "poster?"
+            r#"SELECT id as "id!", season_number, tvshowid, added, poster as "poster?", name, overview,
+                air_date
             FROM season
             WHERE tvshowid = ?
-            ORDER BY season_number ASC"#,
+            ORDER BY season_number = 0, season_number ASC"#,
             tv_id,
         )
         .fetch_one(&mut *conn)
@@ -119,7 +131,8 @@ impl Season {
     ) -> Result<Self, DatabaseError> {
         Ok(sqlx::query_as!(
             Self,
-            r#"SELECT id, season_number, tvshowid, added, poster as "poster?"
+            r#"SELECT id, season_number, tvshowid, added, poster as "poster?", name, overview,
+                air_date
             FROM season WHERE id = ?"#,
             season_id,
         )
@@ -136,10 +149,14 @@ pub struct InsertableSeason {
     pub season_number: i64,
     pub added: String,
     pub poster: Option<i64>,
+    pub name: Option<String>,
+    pub overview: Option<String>,
+    pub air_date: Option<String>,
 }
 
 impl InsertableSeason {
-    /// Method inserts a new season and links it to a tv show based on the id specified.
+    /// Method inserts a new season and links it to a tv show based on the id specified. If the
+    /// season already exists its TMDB metadata is updated instead.
     ///
     /// # Arguments
     /// * `conn` - mutable reference to a sqlx transaction.
@@ -158,19 +175,34 @@ impl InsertableSeason {
         .await?;
 
         if let Some(season) = result {
+            sqlx::query!(
+                "UPDATE _tblseason SET name = COALESCE($1, name), overview = COALESCE($2, overview),
+                    air_date = COALESCE($3, air_date)
+                WHERE id = $4",
+                self.name,
+                self.overview,
+                self.air_date,
+                season.id
+            )
+            .execute(&mut *conn)
+            .await?;
+
             return Ok(season.id);
         }
 
         let id = sqlx::query!(
-            r#"INSERT INTO _tblseason (season_number, added, poster, tvshowid)
-            VALUES ($1, $2, $3, $4)
+            r#"INSERT INTO _tblseason (season_number, added, poster, tvshowid, name, overview, air_date)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             ON CONFLICT DO UPDATE
             SET poster = $3
             RETURNING id as "id!: i64""#,
             self.season_number,
             self.added,
             self.poster,
-            id
+            id,
+            self.name,
+            self.overview,
+            self.air_date
         )
         .fetch_one(&mut *conn)
         .await?
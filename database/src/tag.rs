@@ -0,0 +1,221 @@
+use crate::media::Media;
+use crate::DatabaseError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Struct shows a single tag entry. Unlike genres, tags are created by users and are never
+/// touched by rematches.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Tag {
+    pub id: i64,
+    /// Tag name, ie "Watch with kids"
+    pub name: String,
+    /// Whether the tag was created by a user, lets clients tell tags and genres apart when listing
+    /// them together.
+    pub user_created: bool,
+}
+
+/// Number of medias tagged with a tag
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct TagCount {
+    pub id: i64,
+    /// Tag name, ie "Watch with kids"
+    pub name: String,
+    pub count: i64,
+}
+
+impl Tag {
+    /// Method returns a tag based on its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of a tag
+    pub async fn get_by_id(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<Self, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Tag,
+            r#"SELECT id, name, user_created as "user_created: bool" FROM tag WHERE id = ?"#,
+            id
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all tags a media is tagged with.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of a media object
+    pub async fn get_by_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Tag,
+            r#"SELECT tag.id as "id!", tag.name, tag.user_created as "user_created: bool" FROM tag
+                INNER JOIN tag_media ON tag_media.tag_id = tag.id
+                WHERE tag_media.media_id = ?
+                ORDER BY tag.name ASC"#,
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns every tag along with the number of medias tagged with it, sorted by name.
+    /// Like [`get_media`](Tag::get_media) only medias in visible libraries that the user didnt
+    /// hide are counted.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    pub async fn get_all(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+    ) -> Result<Vec<TagCount>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            TagCount,
+            r#"SELECT tag.id as "id!", tag.name, (
+                    SELECT COUNT(*) FROM tag_media
+                    INNER JOIN media ON media.id = tag_media.media_id
+                    INNER JOIN library ON library.id = media.library_id
+                    WHERE tag_media.tag_id = tag.id AND NOT library.hidden
+                    AND NOT EXISTS (
+                        SELECT 1 FROM hidden_media
+                        WHERE hidden_media.media_id = media.id AND hidden_media.user_id = ?)
+                ) as "count!: i64"
+                FROM tag
+                ORDER BY tag.name ASC"#,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns all medias in visible libraries tagged with a tag, sorted by name. Medias
+    /// hidden by the user are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of a tag
+    /// * `uid` - username of the user.
+    pub async fn get_media(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        uid: &str,
+    ) -> Result<Vec<Media>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            Media,
+            r#"SELECT media.id, media.library_id, media.name, description, rating, year, added, poster_path, backdrop_path, media.media_type as "media_type: _"
+                FROM media
+                INNER JOIN tag_media ON tag_media.media_id = media.id
+                INNER JOIN library ON library.id = media.library_id
+                WHERE tag_media.tag_id = ? AND NOT library.hidden
+                AND NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = media.id AND hidden_media.user_id = ?)
+                ORDER BY COALESCE(media.sort_title, media.name) ASC"#,
+            id,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method removes a tag from a media. The tag is matched case insensitively by its name and
+    /// deleted altogether once no media is tagged with it anymore. Returns the number of medias
+    /// the tag was removed from.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of a media object
+    /// * `name` - name of the tag
+    pub async fn remove_from_media(
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+        name: &str,
+    ) -> Result<usize, DatabaseError> {
+        let removed = sqlx::query!(
+            "DELETE FROM tag_media
+            WHERE media_id = ?
+            AND tag_id IN (SELECT id FROM tag WHERE name = ? COLLATE NOCASE)",
+            media_id,
+            name
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+
+        sqlx::query!(
+            "DELETE FROM tag
+            WHERE name = ? COLLATE NOCASE
+            AND NOT EXISTS (SELECT 1 FROM tag_media WHERE tag_media.tag_id = tag.id)",
+            name
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(removed)
+    }
+}
+
+/// Tag entry that can be inserted into the db.
+#[derive(Clone)]
+pub struct InsertableTag {
+    /// Tag name
+    pub name: String,
+}
+
+impl InsertableTag {
+    /// Method inserts a new tag into the table otherwise returns the id of a existing entry. Like
+    /// genres, tags are matched case insensitively.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        if let Some(record) = sqlx::query!(
+            "SELECT id FROM tag
+            WHERE name = ? COLLATE NOCASE",
+            self.name
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            return Ok(record.id);
+        }
+
+        Ok(
+            sqlx::query!(r#"INSERT INTO tag (name) VALUES ($1)"#, self.name)
+                .execute(&mut *conn)
+                .await?
+                .last_insert_rowid(),
+        )
+    }
+
+    /// Method inserts the tag and tags a media with it.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `media_id` - id of the media to tag.
+    pub async fn insert_for_media(
+        &self,
+        conn: &mut crate::Transaction<'_>,
+        media_id: i64,
+    ) -> Result<i64, DatabaseError> {
+        let tag_id = self.insert(&mut *conn).await?;
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO tag_media (tag_id, media_id)
+            VALUES ($1, $2)",
+            tag_id,
+            media_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(tag_id)
+    }
+}
@@ -0,0 +1,50 @@
+use crate::alternate_title;
+use crate::get_conn_memory;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_for_media() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    for (country, title) in [
+        ("IT", "Il Padrino"),
+        ("FR", "Le Parrain"),
+        ("FR", "Le Parrain"),
+    ] {
+        alternate_title::InsertableAlternateTitle {
+            country: country.into(),
+            title: title.into(),
+        }
+        .insert_for_media(&mut tx, 1)
+        .await
+        .unwrap();
+    }
+
+    let result = alternate_title::AlternateTitle::get_by_media(&mut tx, 1)
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![
+            alternate_title::AlternateTitle {
+                country: "FR".into(),
+                title: "Le Parrain".into(),
+            },
+            alternate_title::AlternateTitle {
+                country: "IT".into(),
+                title: "Il Padrino".into(),
+            },
+        ]
+    );
+
+    let result = alternate_title::AlternateTitle::get_by_media(&mut tx, 2)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
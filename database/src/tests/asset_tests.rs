@@ -0,0 +1,67 @@
+use crate::asset;
+use crate::asset::Asset;
+use crate::asset::InsertableAsset;
+use crate::get_conn_memory;
+use crate::write_tx;
+
+#[test]
+fn test_tmdb_image_path() {
+    assert_eq!(
+        asset::tmdb_image_path("https://image.tmdb.org/t/p/w600_and_h900_bestv2/abc.jpg"),
+        Some("/abc.jpg".into())
+    );
+    // backdrops used to be stored with a duplicate slash.
+    assert_eq!(
+        asset::tmdb_image_path("https://image.tmdb.org/t/p/original//abc.jpg"),
+        Some("/abc.jpg".into())
+    );
+    assert_eq!(
+        asset::tmdb_image_path("https://image.tmdb.org/t/p/w500"),
+        None
+    );
+    assert_eq!(asset::tmdb_image_path("https://example.com/abc.jpg"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_image_path_by_file() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let asset = InsertableAsset {
+        remote_url: Some("https://image.tmdb.org/t/p/original/abc.jpg".into()),
+        local_path: "images/abc.jpg".into(),
+        file_ext: "jpg".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+    assert_eq!(asset.image_path, Some("/abc.jpg".into()));
+
+    InsertableAsset {
+        local_path: "images/avatar.png".into(),
+        file_ext: "png".into(),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        Asset::get_image_path_by_file(&mut tx, "images/abc.jpg")
+            .await
+            .unwrap(),
+        Some("/abc.jpg".into())
+    );
+    assert_eq!(
+        Asset::get_image_path_by_file(&mut tx, "images/avatar.png")
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        Asset::get_image_path_by_file(&mut tx, "images/missing.jpg")
+            .await
+            .unwrap(),
+        None
+    );
+}
@@ -0,0 +1,48 @@
+use crate::chapter::Chapter;
+use crate::get_conn_memory;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::mediafile_tests::insert_mediafile_with_mediaid;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_and_get() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    super::media_tests::insert_many(&mut tx, 1).await;
+    let mediafile = insert_mediafile_with_mediaid(&mut tx, 1).await;
+
+    let result = Chapter::get_of_mediafile(&mut tx, mediafile).await.unwrap();
+    assert!(result.is_empty());
+
+    let chapters = vec![
+        Chapter {
+            title: Some("Opening".into()),
+            start: 0.0,
+            end: 95.5,
+        },
+        Chapter {
+            title: None,
+            start: 95.5,
+            end: 1320.25,
+        },
+    ];
+
+    // chapters are returned in order regardless of how they were stored.
+    let reversed = chapters.iter().rev().cloned().collect::<Vec<_>>();
+    Chapter::set_for_mediafile(&mut tx, mediafile, &reversed)
+        .await
+        .unwrap();
+
+    let result = Chapter::get_of_mediafile(&mut tx, mediafile).await.unwrap();
+    assert_eq!(result, chapters);
+
+    // setting the chapters again replaces them.
+    Chapter::set_for_mediafile(&mut tx, mediafile, &chapters[..1])
+        .await
+        .unwrap();
+
+    let result = Chapter::get_of_mediafile(&mut tx, mediafile).await.unwrap();
+    assert_eq!(result, chapters[..1].to_vec());
+}
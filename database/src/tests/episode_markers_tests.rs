@@ -0,0 +1,91 @@
+use crate::episode;
+use crate::episode_markers::Credits;
+use crate::episode_markers::EpisodeMarkers;
+use crate::episode_markers::Intro;
+use crate::get_conn_memory;
+use crate::media;
+use crate::season;
+use crate::tv;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_and_get() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let episode = episode::InsertableEpisode {
+        media: media::InsertableMedia {
+            library_id: library,
+            name: "TestEpisode".into(),
+            ..Default::default()
+        },
+        seasonid: season,
+        episode: 1,
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let result = EpisodeMarkers::get(&mut tx, episode).await.unwrap();
+    assert_eq!(result, EpisodeMarkers::default());
+
+    let markers = EpisodeMarkers {
+        intro: Some(Intro { start: 10, end: 90 }),
+        credits: Some(Credits { start: 1200 }),
+    };
+    markers.set(&mut tx, episode).await.unwrap();
+
+    let result = EpisodeMarkers::get(&mut tx, episode).await.unwrap();
+    assert_eq!(result, markers);
+
+    // setting the markers again replaces them.
+    let markers = EpisodeMarkers {
+        intro: None,
+        credits: Some(Credits { start: 1100 }),
+    };
+    markers.set(&mut tx, episode).await.unwrap();
+
+    let result = EpisodeMarkers::get(&mut tx, episode).await.unwrap();
+    assert_eq!(result, markers);
+}
+
+#[test]
+fn test_is_valid() {
+    assert!(EpisodeMarkers::default().is_valid());
+    assert!(EpisodeMarkers {
+        intro: Some(Intro { start: 0, end: 60 }),
+        credits: Some(Credits { start: 60 }),
+    }
+    .is_valid());
+
+    assert!(!EpisodeMarkers {
+        intro: Some(Intro { start: 60, end: 10 }),
+        credits: None,
+    }
+    .is_valid());
+    assert!(!EpisodeMarkers {
+        intro: Some(Intro { start: 0, end: 60 }),
+        credits: Some(Credits { start: 30 }),
+    }
+    .is_valid());
+    assert!(!EpisodeMarkers {
+        intro: None,
+        credits: Some(Credits { start: -1 }),
+    }
+    .is_valid());
+}
@@ -0,0 +1,108 @@
+use crate::episode;
+use crate::episode_order::EpisodeOrder;
+use crate::get_conn_memory;
+use crate::media;
+use crate::season;
+use crate::tv;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_episode_orders() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let mut episodes = vec![];
+    for i in 1..=4 {
+        let episode = episode::InsertableEpisode {
+            media: media::InsertableMedia {
+                library_id: library,
+                name: format!("TestEpisode{}", i),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: i,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    assert!(EpisodeOrder::get_for_show(&mut tx, tv)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let chronological = [episodes[2], episodes[0], episodes[1]];
+    EpisodeOrder::set(&mut tx, tv, "chronological", &chronological)
+        .await
+        .unwrap();
+
+    let orders = EpisodeOrder::get_for_show(&mut tx, tv).await.unwrap();
+    assert_eq!(
+        orders,
+        vec![EpisodeOrder {
+            name: "chronological".into(),
+            episode_count: 3,
+        }]
+    );
+
+    assert!(EpisodeOrder::exists(&mut tx, tv, "chronological")
+        .await
+        .unwrap());
+    assert!(!EpisodeOrder::exists(&mut tx, tv, "broadcast")
+        .await
+        .unwrap());
+
+    // episodes missing from the order come last.
+    let (page, total) =
+        episode::Episode::get_flat_for_show(&mut tx, tv, &user, Some("chronological"), 10, 0)
+            .await
+            .unwrap();
+    assert_eq!(total, 4);
+    assert_eq!(
+        page.iter().map(|x| x.episode).collect::<Vec<_>>(),
+        vec![3, 1, 2, 4]
+    );
+
+    // unknown orders fall back to the standard order.
+    let (page, _) = episode::Episode::get_flat_for_show(&mut tx, tv, &user, Some("other"), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        page.iter().map(|x| x.episode).collect::<Vec<_>>(),
+        vec![1, 2, 3, 4]
+    );
+
+    // setting a order again replaces it, setting it to nothing removes it.
+    EpisodeOrder::set(&mut tx, tv, "chronological", &[episodes[3]])
+        .await
+        .unwrap();
+    let orders = EpisodeOrder::get_for_show(&mut tx, tv).await.unwrap();
+    assert_eq!(orders[0].episode_count, 1);
+
+    EpisodeOrder::set(&mut tx, tv, "chronological", &[])
+        .await
+        .unwrap();
+    assert!(EpisodeOrder::get_for_show(&mut tx, tv)
+        .await
+        .unwrap()
+        .is_empty());
+}
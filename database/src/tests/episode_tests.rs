@@ -275,3 +275,331 @@ async fn test_get_next_episode() {
     let second_ep = first_ep.get_next_episode(&mut tx).await.unwrap();
     assert_eq!(second_ep.episode, 2);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_specials_navigation() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    for season_number in 0..=2 {
+        let season = season::InsertableSeason {
+            season_number,
+            ..Default::default()
+        }
+        .insert(&mut tx, tv)
+        .await
+        .unwrap();
+
+        for i in 1..=2 {
+            let _episode = episode::InsertableEpisode {
+                media: media::InsertableMedia {
+                    library_id: _lib,
+                    name: format!("TestEpisode{}x{}", season_number, i),
+                    ..Default::default()
+                },
+                seasonid: season,
+                episode: i,
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+        }
+    }
+
+    // specials are listed last.
+    let result = season::Season::get_all(&mut tx, tv).await.unwrap();
+    let numbers = result.iter().map(|x| x.season_number).collect::<Vec<_>>();
+    assert_eq!(numbers, vec![1, 2, 0]);
+
+    let result = season::Season::get_first(&mut tx, tv).await.unwrap();
+    assert_eq!(result.season_number, 1);
+
+    let first = episode::Episode::get_first_for_show(&mut tx, tv)
+        .await
+        .unwrap();
+    assert_eq!(first.get_season_number(&mut tx).await.unwrap(), 1);
+    assert_eq!(first.episode, 1);
+
+    // the first episode of season 1 must not go back into the specials.
+    assert!(first.get_prev_episode(&mut tx).await.is_err());
+
+    let result = episode::Episode::get_all_of_tv(&mut tx, tv).await.unwrap();
+    assert_eq!(result.len(), 6);
+    let last = result.last().unwrap();
+    assert_eq!(last.get_season_number(&mut tx).await.unwrap(), 0);
+
+    // the last special has no next episode.
+    assert!(last.get_next_episode(&mut tx).await.is_err());
+
+    let prev = last.get_prev_episode(&mut tx).await.unwrap();
+    assert_eq!(prev.get_season_number(&mut tx).await.unwrap(), 0);
+    assert_eq!(prev.episode, 1);
+
+    let result = tv::TVShow::get_season_count(&mut tx, tv, false)
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+
+    let result = tv::TVShow::get_season_count(&mut tx, tv, true)
+        .await
+        .unwrap();
+    assert_eq!(result, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_merge_shows() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+
+    let mut shows = vec![];
+    for name in &["TestShowA", "TestShowB"] {
+        let id = media::InsertableMedia {
+            library_id: _lib,
+            name: name.to_string(),
+            media_type: crate::library::MediaType::Tv,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+        tv::TVShow::insert(&mut tx, id).await.unwrap();
+        shows.push(id);
+    }
+
+    // show a: S01E01, S01E02. show b: S01E02, S01E03, S02E01
+    let layout = vec![
+        (shows[0], vec![(1, vec![1, 2])]),
+        (shows[1], vec![(1, vec![2, 3]), (2, vec![1])]),
+    ];
+
+    let mut duplicate_file = 0;
+    let mut duplicate_episode = 0;
+    let mut merged_episodes = vec![];
+    for (show, seasons) in layout {
+        for (season_number, episodes) in seasons {
+            let season = season::InsertableSeason {
+                season_number,
+                ..Default::default()
+            }
+            .insert(&mut tx, show)
+            .await
+            .unwrap();
+
+            for i in episodes {
+                let episode = episode::InsertableEpisode {
+                    media: media::InsertableMedia {
+                        library_id: _lib,
+                        name: format!("TestEpisode{}{}x{}", show, season_number, i),
+                        ..Default::default()
+                    },
+                    seasonid: season,
+                    episode: i,
+                }
+                .insert(&mut tx)
+                .await
+                .unwrap();
+
+                let mfile = crate::mediafile::InsertableMediaFile {
+                    library_id: _lib,
+                    media_id: Some(episode),
+                    target_file: format!("/dev/null/{}", episode),
+                    raw_name: "Test".into(),
+                    ..Default::default()
+                }
+                .insert(&mut tx)
+                .await
+                .unwrap();
+
+                if show == shows[1] && season_number == 1 && i == 2 {
+                    duplicate_file = mfile;
+                    duplicate_episode = episode;
+                }
+
+                if show == shows[1] {
+                    merged_episodes.push(episode);
+                }
+            }
+        }
+    }
+
+    let user = super::user_tests::insert_user(&mut tx).await;
+    crate::progress::Progress::set(&mut tx, 120, user.clone(), duplicate_episode)
+        .await
+        .unwrap();
+
+    merged_episodes.reverse();
+    crate::episode_order::EpisodeOrder::set(&mut tx, shows[1], "reverse", &merged_episodes)
+        .await
+        .unwrap();
+
+    media::Media::merge_into(&mut tx, shows[1], shows[0])
+        .await
+        .unwrap();
+
+    assert!(media::Media::get(&mut tx, shows[1]).await.is_err());
+
+    let result = episode::Episode::get_all_of_tv(&mut tx, shows[0])
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 4);
+
+    let result = season::Season::get_all(&mut tx, shows[0]).await.unwrap();
+    assert_eq!(result.len(), 2);
+
+    let target = episode::Episode::get(&mut tx, shows[0], 1, 2)
+        .await
+        .unwrap();
+    let result = crate::mediafile::MediaFile::get_one(&mut tx, duplicate_file)
+        .await
+        .unwrap();
+    assert_eq!(result.media_id, Some(target.id));
+
+    // progress on the duplicate episode is kept on the episode that survives.
+    let result = crate::progress::Progress::get_for_media_user(&mut tx, user, target.id)
+        .await
+        .unwrap();
+    assert_eq!(result.delta, 120);
+
+    // the watch order of the merged show is kept with the duplicate replaced by the survivor.
+    let result = crate::episode_order::EpisodeOrder::get_for_show(&mut tx, shows[0])
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![crate::episode_order::EpisodeOrder {
+            name: "reverse".into(),
+            episode_count: 3,
+        }]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_absolute_navigation() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let mut episodes = vec![];
+
+    for season_number in 1..=2 {
+        let season = season::InsertableSeason {
+            season_number,
+            ..Default::default()
+        }
+        .insert(&mut tx, tv)
+        .await
+        .unwrap();
+
+        for i in 1..=2 {
+            let episode = episode::InsertableEpisode {
+                media: media::InsertableMedia {
+                    library_id: _lib,
+                    name: format!("TestEpisode{}x{}", season_number, i),
+                    ..Default::default()
+                },
+                seasonid: season,
+                episode: i,
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            episodes.push(episode);
+        }
+    }
+
+    // the absolute order differs from the season order, ie because local seasons are split
+    // differently than on TMDB.
+    for (episode, absolute_number) in episodes.iter().zip(vec![1, 3, 2, 4]) {
+        episode::Episode::set_absolute_number(&mut tx, *episode, Some(absolute_number))
+            .await
+            .unwrap();
+    }
+
+    let first = episode::Episode::get_by_id(&mut tx, episodes[0])
+        .await
+        .unwrap();
+    assert_eq!(first.absolute_number, Some(1));
+
+    let next = first.get_next_episode(&mut tx).await.unwrap();
+    assert_eq!(next.id, episodes[2]);
+
+    let next = next.get_next_episode(&mut tx).await.unwrap();
+    assert_eq!(next.id, episodes[1]);
+
+    let prev = next.get_prev_episode(&mut tx).await.unwrap();
+    assert_eq!(prev.id, episodes[2]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_flat_for_show() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let mut episodes = vec![];
+
+    for &season_number in &[2, 0, 1] {
+        let season = season::InsertableSeason {
+            season_number,
+            ..Default::default()
+        }
+        .insert(&mut tx, tv)
+        .await
+        .unwrap();
+
+        for &i in &[2, 1] {
+            let episode = episode::InsertableEpisode {
+                media: media::InsertableMedia {
+                    library_id: _lib,
+                    name: format!("TestEpisode{}x{}", season_number, i),
+                    ..Default::default()
+                },
+                seasonid: season,
+                episode: i,
+            }
+            .insert(&mut tx)
+            .await
+            .unwrap();
+
+            episodes.push(episode);
+        }
+    }
+
+    crate::progress::Progress::set(&mut tx, 120, user.clone(), episodes[5])
+        .await
+        .unwrap();
+
+    let (page, total) = episode::Episode::get_flat_for_show(&mut tx, tv, &user, None, 3, 0)
+        .await
+        .unwrap();
+    assert_eq!(total, 6);
+
+    let order = page
+        .iter()
+        .map(|x| (x.season, x.episode))
+        .collect::<Vec<_>>();
+    assert_eq!(order, vec![(1, 1), (1, 2), (2, 1)]);
+    assert_eq!(page[0].id, episodes[5]);
+    assert_eq!(page[0].progress, 120);
+    assert_eq!(page[1].progress, 0);
+
+    // specials come last.
+    let (page, _) = episode::Episode::get_flat_for_show(&mut tx, tv, &user, None, 3, 3)
+        .await
+        .unwrap();
+    let order = page
+        .iter()
+        .map(|x| (x.season, x.episode))
+        .collect::<Vec<_>>();
+    assert_eq!(order, vec![(2, 2), (0, 1), (0, 2)]);
+}
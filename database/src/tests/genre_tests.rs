@@ -5,6 +5,7 @@ use crate::media;
 use crate::write_tx;
 
 use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
 
 pub async fn insert_genre(conn: &mut crate::Transaction<'_>, name: String) -> i64 {
     genre::InsertableGenre { name }
@@ -85,3 +86,63 @@ async fn test_delete() {
     let result = genre::Genre::get_by_id(&mut tx, id).await;
     assert!(result.is_err());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_counts_by_library() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let action = insert_genre(&mut tx, "Action".into()).await;
+    let drama = insert_genre(&mut tx, "Drama".into()).await;
+
+    for &(genre_id, media_id) in &[(action, 1), (drama, 1), (drama, 2), (drama, 3)] {
+        genre::InsertableGenreMedia::insert_pair(genre_id, media_id, &mut tx)
+            .await
+            .unwrap();
+    }
+
+    let result = genre::Genre::get_counts_by_library(&mut tx, library_id, "test")
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![
+            genre::GenreCount {
+                name: "Drama".into(),
+                count: 3
+            },
+            genre::GenreCount {
+                name: "Action".into(),
+                count: 1
+            },
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_for_media() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 1).await;
+
+    let action = insert_genre(&mut tx, "Action".into()).await;
+    genre::InsertableGenreMedia::insert_pair(action, 1, &mut tx)
+        .await
+        .unwrap();
+
+    genre::Genre::set_for_media(&mut tx, 1, &["Drama".into(), "Comedy".into()])
+        .await
+        .unwrap();
+
+    let mut result = genre::Genre::get_by_media(&mut tx, 1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<_>>();
+    result.sort();
+    assert_eq!(result, vec!["Comedy".to_string(), "Drama".to_string()]);
+}
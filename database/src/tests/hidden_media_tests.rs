@@ -0,0 +1,36 @@
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+use super::user_tests;
+use crate::get_conn_memory;
+use crate::hidden_media::HiddenMedia;
+use crate::write_tx;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hide_and_unhide() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+    user_tests::insert_many(&mut tx, 2).await;
+
+    HiddenMedia::hide(&mut tx, "test0", 2).await.unwrap();
+    // hiding twice is fine.
+    HiddenMedia::hide(&mut tx, "test0", 2).await.unwrap();
+
+    assert!(HiddenMedia::is_hidden(&mut tx, "test0", 2).await.unwrap());
+    assert!(!HiddenMedia::is_hidden(&mut tx, "test0", 1).await.unwrap());
+    // other users are unaffected.
+    assert!(!HiddenMedia::is_hidden(&mut tx, "test1", 2).await.unwrap());
+
+    let hidden = HiddenMedia::get_for_user(&mut tx, "test0").await.unwrap();
+    assert_eq!(hidden.iter().map(|x| x.id).collect::<Vec<_>>(), vec![2]);
+    assert!(HiddenMedia::get_for_user(&mut tx, "test1")
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(HiddenMedia::unhide(&mut tx, "test0", 2).await.unwrap(), 1);
+    assert_eq!(HiddenMedia::unhide(&mut tx, "test0", 2).await.unwrap(), 0);
+    assert!(!HiddenMedia::is_hidden(&mut tx, "test0", 2).await.unwrap());
+}
@@ -0,0 +1,239 @@
+use crate::genre;
+use crate::get_conn_memory;
+use crate::hidden_media::HiddenMedia;
+use crate::history::History;
+use crate::history::WatchStats;
+use crate::media;
+use crate::mediafile;
+use crate::progress::Progress;
+use crate::write_tx;
+
+use super::genre_tests::insert_genre;
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::mediafile_tests::insert_mediafile_with_mediaid;
+use super::user_tests::insert_user;
+
+async fn insert_media_with_duration(
+    conn: &mut crate::Transaction<'_>,
+    name: &str,
+    rating: Option<i64>,
+) -> i64 {
+    let media = media::InsertableMedia {
+        library_id: 1,
+        name: name.into(),
+        rating,
+        media_type: crate::library::MediaType::Movie,
+        ..Default::default()
+    }
+    .insert(&mut *conn)
+    .await
+    .unwrap();
+
+    let mediafile = insert_mediafile_with_mediaid(&mut *conn, media).await;
+    mediafile::UpdateMediaFile {
+        duration: Some(100),
+        ..Default::default()
+    }
+    .update(&mut *conn, mediafile)
+    .await
+    .unwrap();
+
+    media
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_progress_records_history() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media_with_duration(&mut tx, "Test", None).await;
+
+    Progress::set(&mut tx, 50, user.clone(), media)
+        .await
+        .unwrap();
+    assert!(History::get_all_for_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .is_empty());
+
+    Progress::set(&mut tx, 95, user.clone(), media)
+        .await
+        .unwrap();
+    // progress reported past the threshold again is still the same play.
+    Progress::set(&mut tx, 99, user.clone(), media)
+        .await
+        .unwrap();
+
+    let history = History::get_all_for_user(&mut tx, &user).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].media_id, media);
+
+    // rewatching the media counts as another play.
+    Progress::set(&mut tx, 0, user.clone(), media)
+        .await
+        .unwrap();
+    Progress::set(&mut tx, 100, user.clone(), media)
+        .await
+        .unwrap();
+
+    let history = History::get_all_for_user(&mut tx, &user).await.unwrap();
+    assert_eq!(history.len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_progress_without_recording_plays() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media_with_duration(&mut tx, "Test", None).await;
+
+    Progress::set_with(&mut tx, 95, user.clone(), media, false)
+        .await
+        .unwrap();
+    assert!(History::get_all_for_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .is_empty());
+    // the offset is stored regardless.
+    assert_eq!(
+        Progress::get_for_media_user(&mut tx, user.clone(), media)
+            .await
+            .unwrap()
+            .delta,
+        95
+    );
+
+    // a finish fires exactly once even if progress is reported past the threshold repeatedly.
+    Progress::set_with(&mut tx, 10, user.clone(), media, true)
+        .await
+        .unwrap();
+    for delta in &[95, 97, 100] {
+        Progress::set_with(&mut tx, *delta, user.clone(), media, true)
+            .await
+            .unwrap();
+    }
+
+    let history = History::get_all_for_user(&mut tx, &user).await.unwrap();
+    assert_eq!(history.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_popular() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+
+    let low = insert_media_with_duration(&mut tx, "Low", Some(2)).await;
+    let high = insert_media_with_duration(&mut tx, "High", Some(8)).await;
+    let most = insert_media(&mut tx).await;
+
+    for _ in 0..3 {
+        History::record(&mut tx, &user, most).await.unwrap();
+    }
+
+    History::record(&mut tx, &user, low).await.unwrap();
+    History::record(&mut tx, &user, high).await.unwrap();
+
+    let result = History::get_popular(&mut tx, &user, 0, 10).await.unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![most, high, low]);
+    assert_eq!(result[0].plays, 3);
+
+    let result = History::get_popular(&mut tx, &user, 0, 1).await.unwrap();
+    assert_eq!(result.len(), 1);
+
+    // plays before the window are ignored.
+    let result = History::get_popular(&mut tx, &user, i64::MAX, 10)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    // media the user hid are left out.
+    HiddenMedia::hide(&mut tx, &user, most).await.unwrap();
+    let result = History::get_popular(&mut tx, &user, 0, 10).await.unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![high, low]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_stats_for_user() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    super::user_tests::insert_many(&mut tx, 1).await;
+
+    let finished = insert_media_with_duration(&mut tx, "Finished", None).await;
+    let started = insert_media_with_duration(&mut tx, "Started", None).await;
+    let rewatched = insert_media_with_duration(&mut tx, "Rewatched", None).await;
+    let old = insert_media_with_duration(&mut tx, "Old", None).await;
+
+    let action = insert_genre(&mut tx, "Action".into()).await;
+    let drama = insert_genre(&mut tx, "Drama".into()).await;
+    for (genre, media) in &[(action, finished), (action, rewatched), (drama, started)] {
+        genre::InsertableGenreMedia::insert_pair(*genre, *media, &mut tx)
+            .await
+            .unwrap();
+    }
+
+    Progress::set(&mut tx, 100, user.clone(), finished)
+        .await
+        .unwrap();
+    Progress::set(&mut tx, 30, user.clone(), started)
+        .await
+        .unwrap();
+    Progress::set_at(&mut tx, 50, user.clone(), old, 100, false)
+        .await
+        .unwrap();
+    History::record(&mut tx, &user, rewatched).await.unwrap();
+    History::record(&mut tx, &user, rewatched).await.unwrap();
+    // plays of other users are never counted.
+    History::record(&mut tx, "test0", started).await.unwrap();
+
+    let stats = History::get_stats_for_user(&mut tx, &user, 1000, i64::MAX, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.items_finished, 3);
+    // two plays of `rewatched`, one of `finished` and the offset of `started`.
+    assert_eq!(stats.seconds_watched, 330);
+    let genres = stats
+        .top_genres
+        .iter()
+        .map(|x| (x.name.as_str(), x.plays))
+        .collect::<Vec<_>>();
+    assert_eq!(genres, vec![("Action", 3)]);
+
+    // progress made before the range is ignored.
+    let stats = History::get_stats_for_user(&mut tx, &user, 0, 1000, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.seconds_watched, 50);
+    assert_eq!(stats.items_finished, 0);
+
+    // only 20 seconds passed between the start of the range and the progress.
+    let stats = History::get_stats_for_user(&mut tx, &user, 80, 1000, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.seconds_watched, 20);
+
+    let stats = History::get_stats_for_user(&mut tx, &user, 0, 1, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats, WatchStats::default());
+
+    // finished media count even if no play was recorded for them.
+    let unrecorded = insert_media_with_duration(&mut tx, "Unrecorded", None).await;
+    Progress::set_at(&mut tx, 100, user.clone(), unrecorded, 2000, false)
+        .await
+        .unwrap();
+
+    let stats = History::get_stats_for_user(&mut tx, &user, 1500, 2500, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.items_finished, 1);
+    assert_eq!(stats.seconds_watched, 100);
+}
@@ -0,0 +1,45 @@
+use crate::get_conn_memory;
+use crate::keyword;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_for_media() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let id = keyword::InsertableKeyword {
+        name: "time travel".into(),
+    }
+    .insert_for_media(&mut tx, 1)
+    .await
+    .unwrap();
+
+    // keywords are deduplicated case insensitively.
+    let result = keyword::InsertableKeyword {
+        name: "Time Travel".into(),
+    }
+    .insert_for_media(&mut tx, 2)
+    .await
+    .unwrap();
+    assert_eq!(result, id);
+
+    let result = keyword::Keyword::get_by_media(&mut tx, 2).await.unwrap();
+    assert_eq!(
+        result,
+        vec![keyword::Keyword {
+            id,
+            name: "time travel".into()
+        }]
+    );
+
+    let result = keyword::Keyword::get_media(&mut tx, id, "test")
+        .await
+        .unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 2]);
+}
@@ -11,6 +11,9 @@ pub async fn create_test_library(conn: &mut crate::Transaction<'_>) -> i64 {
         name: format!("test{}", _LIB.load(Ordering::Relaxed)),
         locations: vec![format!("/dev/null{}", _LIB.load(Ordering::Relaxed))],
         media_type: library::MediaType::Movie,
+        anime: false,
+        language: None,
+        agent: library::MetadataAgent::Tmdb,
     };
 
     _LIB.fetch_add(1, Ordering::SeqCst);
@@ -60,3 +63,40 @@ async fn test_delete() {
     let rows = library::Library::delete(&mut tx, id).await.unwrap();
     assert_eq!(rows, 1);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_agent() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let id = create_test_library(&mut tx).await;
+
+    let result = library::Library::get_agent(&mut tx, id).await.unwrap();
+    assert_eq!(result, library::MetadataAgent::Tmdb);
+
+    let rows = library::Library::set_agent(&mut tx, id, library::MetadataAgent::Tmdb)
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    let result = library::Library::get_one(&mut tx, id).await.unwrap();
+    assert_eq!(result.agent, library::MetadataAgent::Tmdb);
+
+    let rows = library::Library::set_agent(&mut tx, id + 1, library::MetadataAgent::Tmdb)
+        .await
+        .unwrap();
+    assert_eq!(rows, 0);
+    assert!(library::Library::get_agent(&mut tx, id + 1).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_is_hidden() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let id = create_test_library(&mut tx).await;
+
+    assert!(!library::Library::is_hidden(&mut tx, id).await.unwrap());
+
+    library::Library::mark_hidden(&mut tx, id).await.unwrap();
+    assert!(library::Library::is_hidden(&mut tx, id).await.unwrap());
+    assert!(library::Library::is_hidden(&mut tx, id + 1).await.is_err());
+}
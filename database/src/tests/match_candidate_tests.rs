@@ -0,0 +1,64 @@
+use crate::get_conn_memory;
+use crate::match_candidate::MatchCandidate;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_and_clear() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let result = MatchCandidate::get_media_needing_review(&mut tx)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    let candidates = vec![
+        MatchCandidate {
+            tmdb_id: 438631,
+            title: "Dune".into(),
+            release_date: Some("2021-09-15".into()),
+            confidence: 0.875,
+            ..Default::default()
+        },
+        MatchCandidate {
+            tmdb_id: 841,
+            title: "Dune".into(),
+            release_date: Some("1984-12-14".into()),
+            confidence: 0.9,
+            ..Default::default()
+        },
+    ];
+
+    MatchCandidate::set_for_media(&mut tx, 2, &candidates)
+        .await
+        .unwrap();
+
+    let result = MatchCandidate::get_media_needing_review(&mut tx)
+        .await
+        .unwrap();
+    assert_eq!(result, vec![2]);
+
+    let result = MatchCandidate::get_for_media(&mut tx, 2).await.unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].tmdb_id, 841);
+    assert_eq!(result[0].media_id, 2);
+
+    // setting the candidates again replaces them.
+    MatchCandidate::set_for_media(&mut tx, 2, &candidates[..1])
+        .await
+        .unwrap();
+    let result = MatchCandidate::get_for_media(&mut tx, 2).await.unwrap();
+    assert_eq!(result.len(), 1);
+
+    let result = MatchCandidate::clear_for_media(&mut tx, 2).await.unwrap();
+    assert_eq!(result, 1);
+    assert!(MatchCandidate::get_media_needing_review(&mut tx)
+        .await
+        .unwrap()
+        .is_empty());
+}
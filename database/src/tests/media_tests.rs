@@ -1,7 +1,10 @@
+use crate::genre;
 use crate::get_conn_memory;
 use crate::library;
 use crate::media;
 use crate::mediafile;
+use crate::person;
+use crate::progress;
 use crate::write_tx;
 
 use super::library_tests::create_test_library;
@@ -53,6 +56,37 @@ async fn test_get() {
     assert_eq!(media.media_type, library::MediaType::Movie);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_many() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    assert!(media::Media::get_many(&mut tx, &[])
+        .await
+        .unwrap()
+        .is_empty());
+
+    let mut ids = media::Media::get_many(&mut tx, &[3, 1, 99])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![1, 3]);
+
+    // media in hidden libraries are skipped.
+    library::Library::mark_hidden(&mut tx, library_id)
+        .await
+        .unwrap();
+    assert!(media::Media::get_many(&mut tx, &[1, 2])
+        .await
+        .unwrap()
+        .is_empty());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_get_all() {
     let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
@@ -191,3 +225,993 @@ async fn test_update() {
     assert_eq!(result.name, "TestMedia2".to_string());
     assert_eq!(result.rating, Some(5));
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_rejects_type_change() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let media_id = insert_media(&mut tx).await;
+
+    let update: media::UpdateMedia =
+        serde_json::from_str(r#"{"name": "Renamed", "media_type": "tv", "library_id": 2}"#)
+            .unwrap();
+    assert_eq!(update.immutable_fields(), vec!["media_type", "library_id"]);
+
+    let update: media::UpdateMedia = serde_json::from_str(r#"{"name": "Renamed"}"#).unwrap();
+    assert!(update.immutable_fields().is_empty());
+
+    // even if a caller skips the check the type of the media is left untouched.
+    media::UpdateMedia {
+        media_type: Some(library::MediaType::Tv),
+        ..Default::default()
+    }
+    .update(&mut tx, media_id)
+    .await
+    .unwrap();
+
+    let result = media::Media::get(&mut tx, media_id).await.unwrap();
+    assert_eq!(result.media_type, library::MediaType::Movie);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_placeholder() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+
+    let media = media::InsertableMedia {
+        library_id: 1,
+        name: "TestMedia".into(),
+        description: None,
+        rating: Some(10),
+        year: Some(2020),
+        added: "Test".into(),
+        poster: None,
+        backdrop: None,
+        media_type: library::MediaType::Movie,
+    };
+
+    let media_id = media.insert_placeholder(&mut tx).await.unwrap();
+    assert!(media::Media::is_placeholder(&mut tx, media_id)
+        .await
+        .unwrap());
+
+    // the scanner should reuse the placeholder instead of inserting a duplicate.
+    let result = media.insert(&mut tx).await.unwrap();
+    assert_eq!(result, media_id);
+
+    let result = media::Media::reconcile_placeholder(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(result, 1);
+    assert!(!media::Media::is_placeholder(&mut tx, media_id)
+        .await
+        .unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_placeholder_with_files() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+
+    let media_id = insert_media(&mut tx).await;
+    let _ = insert_mediafile_with_mediaid(&mut tx, media_id).await;
+
+    let media = media::InsertableMedia {
+        library_id: 1,
+        name: "TestMedia".into(),
+        added: "Test".into(),
+        media_type: library::MediaType::Movie,
+        ..Default::default()
+    };
+
+    let result = media.insert_placeholder(&mut tx).await.unwrap();
+    assert_eq!(result, media_id);
+    assert!(!media::Media::is_placeholder(&mut tx, media_id)
+        .await
+        .unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_move_to_library() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    let target_id = create_test_library(&mut tx).await;
+
+    let media_id = insert_media(&mut tx).await;
+    let mfile_id = insert_mediafile_with_mediaid(&mut tx, media_id).await;
+
+    let result = media::Media::move_to_library(&mut tx, media_id, target_id)
+        .await
+        .unwrap();
+    assert_eq!(result, 2);
+
+    let result = media::Media::get_all(&mut tx, library_id).await.unwrap();
+    assert!(result.is_empty());
+
+    let result = media::Media::get(&mut tx, media_id).await.unwrap();
+    assert_eq!(result.library_id, target_id);
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert_eq!(result.library_id, target_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cached_duration() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let media_id = insert_media(&mut tx).await;
+
+    let result = media::Media::get_cached_duration(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+
+    let mfile = mediafile::InsertableMediaFile {
+        library_id: 1,
+        media_id: Some(media_id),
+        target_file: "/dev/null".into(),
+        raw_name: "Test".into(),
+        duration: Some(100),
+        ..Default::default()
+    };
+    let mfile_id = mfile.insert(&mut tx).await.unwrap();
+
+    let result = media::Media::get_cached_duration(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(result, Some(100));
+
+    mediafile::MediaFile::delete(&mut tx, mfile_id)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_cached_duration(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_recompute_cached_durations() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let mfile = mediafile::InsertableMediaFile {
+        library_id: 1,
+        media_id: Some(2),
+        target_file: "/dev/null".into(),
+        raw_name: "Test".into(),
+        duration: Some(100),
+        ..Default::default()
+    };
+    mfile.insert(&mut tx).await.unwrap();
+
+    // simulate a stale cache.
+    sqlx::query("UPDATE _tblmedia SET duration = 5 WHERE id = 2")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    assert_eq!(media::Media::count(&mut tx).await.unwrap(), 3);
+
+    let result = media::Media::recompute_cached_durations(&mut tx, 0, 2)
+        .await
+        .unwrap();
+    assert_eq!(result, Some((2, 1)));
+
+    let result = media::Media::get_cached_duration(&mut tx, 2).await.unwrap();
+    assert_eq!(result, Some(100));
+
+    let result = media::Media::recompute_cached_durations(&mut tx, 2, 2)
+        .await
+        .unwrap();
+    assert_eq!(result, Some((3, 0)));
+
+    let result = media::Media::recompute_cached_durations(&mut tx, 3, 2)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tmdb_id_and_duplicates() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let result =
+        media::Media::get_id_by_tmdb_id(&mut tx, library_id, 42, library::MediaType::Movie)
+            .await
+            .unwrap();
+    assert!(result.is_none());
+
+    media::Media::set_tmdb_id(&mut tx, 1, 42).await.unwrap();
+    media::Media::set_tmdb_id(&mut tx, 2, 42).await.unwrap();
+    media::Media::set_tmdb_id(&mut tx, 3, 43).await.unwrap();
+
+    let result = media::Media::get_tmdb_id(&mut tx, 3).await.unwrap();
+    assert_eq!(result, Some(43));
+
+    let result =
+        media::Media::get_id_by_tmdb_id(&mut tx, library_id, 42, library::MediaType::Movie)
+            .await
+            .unwrap();
+    assert_eq!(result, Some(1));
+
+    let result = media::Media::get_duplicates(&mut tx).await.unwrap();
+    let ids = result
+        .iter()
+        .map(|(tmdb, x)| (*tmdb, x.id))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![(42, 1), (42, 2)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_all_by_tmdb_id() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let result = media::Media::get_all_by_tmdb_id(&mut tx, 42).await.unwrap();
+    assert!(result.is_empty());
+
+    media::Media::set_tmdb_id(&mut tx, 1, 42).await.unwrap();
+    media::Media::set_tmdb_id(&mut tx, 3, 42).await.unwrap();
+
+    let result = media::Media::get_all_by_tmdb_id(&mut tx, 42).await.unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 3]);
+
+    // media in hidden libraries are skipped.
+    library::Library::mark_hidden(&mut tx, library_id)
+        .await
+        .unwrap();
+    assert!(media::Media::get_all_by_tmdb_id(&mut tx, 42)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tmdb_runtime() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let result = media::Media::get_tmdb_runtime(&mut tx, 1).await.unwrap();
+    assert!(result.is_none());
+
+    media::Media::set_tmdb_runtime(&mut tx, 1, 5400)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_tmdb_runtime(&mut tx, 1).await.unwrap();
+    assert_eq!(result, Some(5400));
+
+    let result = media::Media::get_tmdb_runtime(&mut tx, 2).await.unwrap();
+    assert!(result.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_collection_id() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 1).await;
+
+    let result = media::Media::get_collection_id(&mut tx, 1).await.unwrap();
+    assert!(result.is_none());
+
+    media::Media::set_collection_id(&mut tx, 1, Some(119))
+        .await
+        .unwrap();
+
+    let result = media::Media::get_collection_id(&mut tx, 1).await.unwrap();
+    assert_eq!(result, Some(119));
+
+    media::Media::set_collection_id(&mut tx, 1, None)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_collection_id(&mut tx, 1).await.unwrap();
+    assert!(result.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_merge_into() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let mfile = insert_mediafile_with_mediaid(&mut tx, 1).await;
+
+    media::Media::merge_into(&mut tx, 1, 2).await.unwrap();
+
+    assert!(media::Media::get(&mut tx, 1).await.is_err());
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile).await.unwrap();
+    assert_eq!(result.media_id, Some(2));
+
+    let result = media::Media::get_all(&mut tx, library_id).await.unwrap();
+    assert_eq!(result.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_merge_into_user_data() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+    super::user_tests::insert_many(&mut tx, 2).await;
+    insert_many(&mut tx, 2).await;
+
+    // conflicting progress resolves to the furthest offset.
+    progress::Progress::set(&mut tx, 300, user.clone(), 1)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 100, user.clone(), 2)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 50, "test0".into(), 1)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 200, "test1".into(), 2)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 20, "test1".into(), 1)
+        .await
+        .unwrap();
+
+    let playlist = crate::playlist::InsertablePlaylist {
+        name: "Watch later".into(),
+    }
+    .insert(&mut tx, &user)
+    .await
+    .unwrap();
+    crate::playlist::Playlist::add_item(&mut tx, playlist, 1)
+        .await
+        .unwrap();
+
+    media::Media::merge_into(&mut tx, 1, 2).await.unwrap();
+
+    for (uid, delta) in [
+        (user.clone(), 300),
+        ("test0".into(), 50),
+        ("test1".into(), 200),
+    ] {
+        let result = progress::Progress::get_for_media_user(&mut tx, uid, 2)
+            .await
+            .unwrap();
+        assert_eq!(result.delta, delta);
+    }
+
+    let result = progress::Progress::get_for_media_user(&mut tx, user.clone(), 1)
+        .await
+        .unwrap();
+    assert_eq!(result.delta, 0);
+
+    let items = crate::playlist::Playlist::get_items(&mut tx, playlist, &user)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].media_id, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_overrides_survive_refresh() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+
+    let media_id = insert_media(&mut tx).await;
+
+    media::UpdateMedia {
+        description: Some("Custom description".into()),
+        ..Default::default()
+    }
+    .update_manual(&mut tx, media_id)
+    .await
+    .unwrap();
+
+    let overrides = media::Media::get_overrides(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(overrides, vec!["description".to_string()]);
+
+    // simulate a rematch which deletes and recreates the media with fresh metadata.
+    let snapshot = media::Media::get_overridden_values(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(snapshot.name, None);
+    assert_eq!(snapshot.description, Some("Custom description".into()));
+
+    media::Media::delete(&mut tx, media_id).await.unwrap();
+
+    media::InsertableMedia {
+        library_id: 1,
+        name: "TestMedia".into(),
+        description: Some("TMDB description".into()),
+        rating: Some(7),
+        year: Some(2020),
+        added: "Test".into(),
+        poster: None,
+        backdrop: None,
+        media_type: library::MediaType::Movie,
+    }
+    .insert_with_id(&mut tx, media_id)
+    .await
+    .unwrap();
+
+    snapshot.update_manual(&mut tx, media_id).await.unwrap();
+
+    let result = media::Media::get(&mut tx, media_id).await.unwrap();
+    assert_eq!(result.description, Some("Custom description".into()));
+    assert_eq!(result.rating, Some(7));
+
+    let overrides = media::Media::get_overrides(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(overrides, vec!["description".to_string()]);
+
+    media::Media::clear_overrides(&mut tx, media_id)
+        .await
+        .unwrap();
+    let overrides = media::Media::get_overrides(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert!(overrides.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_automatic_update_keeps_overrides() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+
+    let media_id = insert_media(&mut tx).await;
+
+    media::UpdateMedia {
+        description: Some("Custom description".into()),
+        ..Default::default()
+    }
+    .update_manual(&mut tx, media_id)
+    .await
+    .unwrap();
+
+    media::UpdateMedia {
+        description: Some("TMDB description".into()),
+        rating: Some(3),
+        ..Default::default()
+    }
+    .update_automatic(&mut tx, media_id)
+    .await
+    .unwrap();
+
+    let result = media::Media::get(&mut tx, media_id).await.unwrap();
+    assert_eq!(result.description, Some("Custom description".into()));
+    assert_eq!(result.rating, Some(3));
+
+    let overrides = media::Media::get_overrides(&mut tx, media_id)
+        .await
+        .unwrap();
+    assert_eq!(overrides, vec!["description".to_string()]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_poster_url() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    let media_id = insert_media(&mut tx).await;
+
+    let media = media::Media::get(&mut tx, media_id).await.unwrap();
+    let chain = media::PosterSource::default_chain();
+
+    // without a poster or any files we fall back to a placeholder.
+    let (_, source) = media.poster_url(&mut tx, &chain).await.unwrap().unwrap();
+    assert_eq!(source, media::PosterSource::Placeholder);
+
+    insert_mediafile_with_mediaid(&mut tx, media_id).await;
+
+    let (url, source) = media.poster_url(&mut tx, &chain).await.unwrap().unwrap();
+    assert_eq!(source, media::PosterSource::Frame);
+    assert_eq!(url, format!("images/frames/{}.jpg", media_id));
+
+    let result = media
+        .poster_url(&mut tx, &[media::PosterSource::Tmdb])
+        .await
+        .unwrap();
+    assert!(result.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_added_between() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+
+    let mut ids = vec![];
+    for (name, added) in [
+        ("Old", "2021-11-30 23:59:59 UTC"),
+        ("First", "2021-12-01 00:00:00 UTC"),
+        ("Second", "2021-12-07 12:00:00 UTC"),
+        ("New", "2021-12-08 00:00:00 UTC"),
+    ] {
+        let media = media::InsertableMedia {
+            library_id: library,
+            name: name.into(),
+            added: added.into(),
+            media_type: library::MediaType::Movie,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        ids.push(media);
+    }
+
+    let (items, total) =
+        media::Media::get_added_between(&mut tx, "test", "2021-12-01", "2021-12-08", false, 10, 0)
+            .await
+            .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(
+        items.into_iter().map(|x| x.id).collect::<Vec<_>>(),
+        vec![ids[2], ids[1]]
+    );
+
+    let (items, total) =
+        media::Media::get_added_between(&mut tx, "test", "2021-12-01", "2021-12-08", false, 1, 1)
+            .await
+            .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(items[0].id, ids[1]);
+
+    let (items, total) = media::Media::get_recently_added_page(&mut tx, "test", 2, 1)
+        .await
+        .unwrap();
+    assert_eq!(total, 4);
+    assert_eq!(
+        items.into_iter().map(|x| x.id).collect::<Vec<_>>(),
+        vec![ids[2], ids[1]]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_missing_poster() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 4).await;
+
+    for id in 1..=3 {
+        media::Media::set_tmdb_id(&mut tx, id, id).await.unwrap();
+    }
+
+    let poster = crate::asset::InsertableAsset {
+        remote_url: None,
+        local_path: "images/poster.jpg".into(),
+        file_ext: "jpg".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    media::UpdateMedia {
+        poster: Some(poster.id),
+        ..Default::default()
+    }
+    .update(&mut tx, 2)
+    .await
+    .unwrap();
+
+    sqlx::query("INSERT INTO media_overrides (media_id, field) VALUES (3, 'poster')")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    // media 2 has a poster, the poster of media 3 was overridden and media 4 isnt matched.
+    let result = media::Media::get_missing_poster(&mut tx).await.unwrap();
+    assert_eq!(result, vec![1]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_accent_color() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let mut posters = vec![];
+    for i in 0..2 {
+        let poster = crate::asset::InsertableAsset {
+            remote_url: None,
+            local_path: format!("images/poster{}.jpg", i),
+            file_ext: "jpg".into(),
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        posters.push(poster.id);
+    }
+
+    for id in 1..=2 {
+        media::UpdateMedia {
+            poster: Some(posters[0]),
+            ..Default::default()
+        }
+        .update(&mut tx, id)
+        .await
+        .unwrap();
+    }
+
+    // media 3 has no poster to compute a color from.
+    let result = media::Media::get_missing_accent_color(&mut tx)
+        .await
+        .unwrap();
+    assert_eq!(result, vec![1, 2]);
+
+    media::Media::set_accent_color(&mut tx, 1, "#1a2b3c")
+        .await
+        .unwrap();
+
+    let color = media::Media::get_accent_color(&mut tx, 1).await.unwrap();
+    assert_eq!(color.as_deref(), Some("#1a2b3c"));
+
+    let result = media::Media::get_missing_accent_color(&mut tx)
+        .await
+        .unwrap();
+    assert_eq!(result, vec![2]);
+
+    // setting the same poster again keeps the color, a new poster clears it.
+    let mut expected = vec![Some("#1a2b3c".to_string()), None];
+    for poster in posters {
+        media::UpdateMedia {
+            poster: Some(poster),
+            ..Default::default()
+        }
+        .update(&mut tx, 1)
+        .await
+        .unwrap();
+
+        let color = media::Media::get_accent_color(&mut tx, 1).await.unwrap();
+        assert_eq!(color, expected.remove(0));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_stale() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+
+    let mut ids = vec![];
+    for name in ["Fresh", "Old", "Unknown", "Unmatched"] {
+        let media = media::InsertableMedia {
+            library_id: library,
+            name: name.into(),
+            media_type: library::MediaType::Movie,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        ids.push(media);
+    }
+
+    for (tmdb_id, id) in ids[..3].iter().enumerate() {
+        media::Media::set_tmdb_id(&mut tx, *id, tmdb_id as i64)
+            .await
+            .unwrap();
+    }
+
+    sqlx::query("UPDATE _tblmedia SET metadata_refreshed_at = 1000 WHERE id = ?")
+        .bind(ids[1])
+        .execute(&mut tx)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE _tblmedia SET metadata_refreshed_at = NULL WHERE id = ?")
+        .bind(ids[2])
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    let (items, total) = media::Media::get_stale(&mut tx, 2000, 10, 0).await.unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(
+        items.iter().map(|x| x.id).collect::<Vec<_>>(),
+        vec![ids[2], ids[1]]
+    );
+    assert_eq!(items[1].metadata_refreshed_at, Some(1000));
+
+    media::Media::mark_metadata_refreshed(&mut tx, ids[1])
+        .await
+        .unwrap();
+    let (items, _) = media::Media::get_stale(&mut tx, 2000, 10, 0).await.unwrap();
+    assert_eq!(items.len(), 1);
+}
+
+#[test]
+fn test_sort_title() {
+    assert_eq!(media::sort_title("The Matrix", None), Some("Matrix".into()));
+    assert_eq!(
+        media::sort_title("An American Tail", None),
+        Some("American Tail".into())
+    );
+    assert_eq!(media::sort_title("Theodora", None), None);
+    assert_eq!(media::sort_title("The", None), None);
+    assert_eq!(media::sort_title("Das Boot", None), None);
+    assert_eq!(
+        media::sort_title("Das Boot", Some("de")),
+        Some("Boot".into())
+    );
+    assert_eq!(
+        media::sort_title("L'Auberge espagnole", Some("fr")),
+        Some("Auberge espagnole".into())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sorted_by_sort_title() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+
+    let mut ids = vec![];
+    for name in ["The Matrix", "Memento", "Nosferatu"] {
+        let media = media::InsertableMedia {
+            library_id: library,
+            name: name.into(),
+            media_type: library::MediaType::Movie,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        ids.push(media);
+    }
+
+    assert_eq!(
+        media::Media::get_sort_title(&mut tx, ids[0]).await.unwrap(),
+        Some("Matrix".into())
+    );
+
+    let names = |media: Vec<media::Media>| media.into_iter().map(|x| x.name).collect::<Vec<_>>();
+
+    let result = media::Media::get_all(&mut tx, library).await.unwrap();
+    assert_eq!(names(result), vec!["The Matrix", "Memento", "Nosferatu"]);
+
+    // sort titles set by users survive renames.
+    media::UpdateMedia {
+        sort_title: Some("Zzz".into()),
+        ..Default::default()
+    }
+    .update_manual(&mut tx, ids[0])
+    .await
+    .unwrap();
+
+    media::UpdateMedia {
+        name: Some("The Matrix Reloaded".into()),
+        ..Default::default()
+    }
+    .update(&mut tx, ids[0])
+    .await
+    .unwrap();
+
+    let result = media::Media::get_all(&mut tx, library).await.unwrap();
+    assert_eq!(
+        names(result),
+        vec!["Memento", "Nosferatu", "The Matrix Reloaded"]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_similar() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+    insert_many(&mut tx, 5).await;
+
+    let action = genre::InsertableGenre {
+        name: "Action".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+    let drama = genre::InsertableGenre {
+        name: "Drama".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    for (genre_id, media_id) in [
+        (action, 1),
+        (drama, 1),
+        (action, 2),
+        (action, 3),
+        (drama, 3),
+    ] {
+        genre::InsertableGenreMedia::insert_pair(genre_id, media_id, &mut tx)
+            .await
+            .unwrap();
+    }
+
+    let actor = person::InsertablePerson {
+        tmdb_id: 1,
+        name: "Actor".into(),
+        ..Default::default()
+    };
+    actor.insert_cast(&mut tx, 1, None, 0).await.unwrap();
+    actor.insert_cast(&mut tx, 3, None, 0).await.unwrap();
+
+    media::Media::set_collection_id(&mut tx, 1, Some(119))
+        .await
+        .unwrap();
+    media::Media::set_collection_id(&mut tx, 4, Some(119))
+        .await
+        .unwrap();
+
+    let similar =
+        |x: Vec<media::SimilarMedia>| x.into_iter().map(|x| (x.id, x.score)).collect::<Vec<_>>();
+
+    let result = media::Media::get_similar(&mut tx, 1, &user, 10)
+        .await
+        .unwrap();
+    assert_eq!(similar(result), vec![(3, 5), (4, 5), (2, 2)]);
+
+    let result = media::Media::get_similar(&mut tx, 1, &user, 1)
+        .await
+        .unwrap();
+    assert_eq!(similar(result), vec![(3, 5)]);
+
+    // watched media are excluded.
+    mediafile::InsertableMediaFile {
+        library_id: 1,
+        media_id: Some(3),
+        target_file: "/dev/null".into(),
+        raw_name: "Test".into(),
+        duration: Some(100),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+    progress::Progress::set(&mut tx, 95, user.clone(), 3)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_similar(&mut tx, 1, &user, 10)
+        .await
+        .unwrap();
+    assert_eq!(similar(result), vec![(4, 5), (2, 2)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_random() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+    insert_many(&mut tx, 10).await;
+
+    let show = media::InsertableMedia {
+        library_id: 1,
+        name: "TestShow".into(),
+        added: "Test".into(),
+        media_type: library::MediaType::Tv,
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let ids = |x: Vec<media::Media>| x.into_iter().map(|x| x.id).collect::<Vec<_>>();
+
+    let result = media::Media::get_random(&mut tx, &user, None, true, None, 5)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 5);
+
+    // the same seed always yields the same sample.
+    let first = media::Media::get_random(&mut tx, &user, None, true, Some(20211228), 20)
+        .await
+        .unwrap();
+    let second = media::Media::get_random(&mut tx, &user, None, true, Some(20211228), 20)
+        .await
+        .unwrap();
+    let first = ids(first);
+    assert_eq!(first, ids(second));
+    assert_eq!(first.len(), 11);
+
+    let mut sorted = first.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (1..=11).collect::<Vec<_>>());
+
+    let result = media::Media::get_random(
+        &mut tx,
+        &user,
+        Some(library::MediaType::Tv),
+        true,
+        Some(20211228),
+        20,
+    )
+    .await
+    .unwrap();
+    assert_eq!(ids(result), vec![show]);
+
+    // watched media are excluded unless asked for.
+    mediafile::InsertableMediaFile {
+        library_id: 1,
+        media_id: Some(3),
+        target_file: "/dev/null".into(),
+        raw_name: "Test".into(),
+        duration: Some(100),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+    progress::Progress::set(&mut tx, 95, user.clone(), 3)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_random(&mut tx, &user, None, true, Some(20211228), 20)
+        .await
+        .unwrap();
+    let result = ids(result);
+    assert_eq!(result.len(), 10);
+    assert!(!result.contains(&3));
+
+    let result = media::Media::get_random(&mut tx, &user, None, false, Some(20211228), 20)
+        .await
+        .unwrap();
+    assert_eq!(ids(result), first);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_external_ids() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library_id = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 1).await;
+
+    media::Media::set_tmdb_id(&mut tx, 1, 603).await.unwrap();
+    let result = media::Media::get_external_ids(&mut tx, 1).await.unwrap();
+    assert!(result.is_none());
+
+    // ids TMDB doesnt know are cached too.
+    let ids = media::ExternalIds {
+        imdb_id: Some("tt0133093".into()),
+        tvdb_id: None,
+    };
+    media::Media::set_external_ids(&mut tx, 1, &ids)
+        .await
+        .unwrap();
+
+    let result = media::Media::get_external_ids(&mut tx, 1).await.unwrap();
+    assert_eq!(result, Some(ids));
+
+    // matching the media against another TMDB entry clears the ids.
+    media::Media::set_tmdb_id(&mut tx, 1, 604).await.unwrap();
+    let result = media::Media::get_external_ids(&mut tx, 1).await.unwrap();
+    assert!(result.is_none());
+}
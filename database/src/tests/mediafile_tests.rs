@@ -80,6 +80,27 @@ async fn test_get_by_lib_null_media() {
     // TODO: check that mfiles with media_id dont get returned
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_orphans() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let id = create_test_library(&mut tx).await;
+
+    let orphan = insert_mediafile(&mut tx).await;
+    let media = super::media_tests::insert_media(&mut tx).await;
+    let _matched = insert_mediafile_with_mediaid(&mut tx, media).await;
+
+    let result = mediafile::MediaFile::get_orphans(&mut tx).await.unwrap();
+    assert_eq!(
+        result.into_iter().map(|x| x.id).collect::<Vec<_>>(),
+        vec![orphan]
+    );
+
+    library::Library::mark_hidden(&mut tx, id).await.unwrap();
+    let result = mediafile::MediaFile::get_orphans(&mut tx).await.unwrap();
+    assert!(result.is_empty());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_get_one() {
     let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
@@ -172,3 +193,235 @@ async fn test_get_of_media() {
     assert_eq!(result[0].media_id, Some(media_id));
     assert_eq!(result[0].id, mfile);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_multi_episode_file() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let lib = create_test_library(&mut tx).await;
+    let tv = super::media_tests::insert_media(&mut tx).await;
+    crate::tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = crate::season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let mut episodes = vec![];
+    for i in 1..=3 {
+        let episode = crate::episode::InsertableEpisode {
+            media: crate::media::InsertableMedia {
+                library_id: lib,
+                name: format!("TestEpisode{}", i),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: i,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    let mfile = mediafile::InsertableMediaFile {
+        library_id: lib,
+        media_id: Some(episodes[0]),
+        target_file: "/dev/null".into(),
+        raw_name: "Test".into(),
+        duration: Some(3000),
+        episode: Some(1),
+        episode_end: Some(2),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let result = mediafile::MediaFile::get_multi_episode_file(&mut tx, episodes[1])
+        .await
+        .unwrap();
+    assert_eq!(result.id, mfile);
+    assert_eq!(result.episode_count(), 2);
+    assert_eq!(result.episode_offset(1), Some(0));
+    assert_eq!(result.episode_offset(2), Some(1500));
+    assert_eq!(result.episode_offset(3), None);
+
+    let contained = result.get_contained_episodes(&mut tx).await.unwrap();
+    assert_eq!(contained, vec![(episodes[0], 1), (episodes[1], 2)]);
+
+    assert!(
+        mediafile::MediaFile::get_multi_episode_file(&mut tx, episodes[2])
+            .await
+            .is_err()
+    );
+
+    let shared = mediafile::MediaFile::get_multi_episode_files_of_season(&mut tx, season)
+        .await
+        .unwrap();
+    assert_eq!(shared.len(), 2);
+    assert_eq!(shared[&episodes[0]].id, mfile);
+    assert_eq!(shared[&episodes[1]].id, mfile);
+    assert!(!shared.contains_key(&episodes[2]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sort_by_quality() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let media_id = super::media_tests::insert_media(&mut tx).await;
+
+    let versions = [
+        (Some("480"), Some("h264")),
+        (None, Some("h264")),
+        (Some("2160"), Some("hevc")),
+        (Some("1080"), Some("hevc")),
+        (Some("1080"), Some("h264")),
+    ];
+
+    for (i, (quality, codec)) in versions.iter().enumerate() {
+        mediafile::InsertableMediaFile {
+            library_id: 1,
+            media_id: Some(media_id),
+            target_file: format!("/dev/null/{}", i),
+            raw_name: "Test".into(),
+            quality: quality.map(ToString::to_string),
+            codec: codec.map(ToString::to_string),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let mut result = mediafile::MediaFile::get_of_media(&mut tx, media_id)
+        .await
+        .unwrap();
+    result.sort_by(mediafile::MediaFile::cmp_quality);
+
+    let result = result
+        .iter()
+        .map(|x| (x.quality.as_deref(), x.codec.as_deref()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        result,
+        vec![
+            (Some("2160"), Some("hevc")),
+            (Some("1080"), Some("h264")),
+            (Some("1080"), Some("hevc")),
+            (Some("480"), Some("h264")),
+            (None, Some("h264")),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hdr() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+
+    let mfile_id = insert_mediafile(&mut tx).await;
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert!(result.hdr.is_none());
+    assert!(!result.is_hdr());
+
+    let update = mediafile::UpdateMediaFile {
+        hdr: Some("dolby_vision".into()),
+        ..Default::default()
+    };
+    update.update(&mut tx, mfile_id).await.unwrap();
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert_eq!(result.hdr.as_deref(), Some("dolby_vision"));
+    assert!(result.is_hdr());
+
+    let update = mediafile::UpdateMediaFile {
+        hdr: Some("sdr".into()),
+        ..Default::default()
+    };
+    update.update(&mut tx, mfile_id).await.unwrap();
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert!(!result.is_hdr());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_captions() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+
+    let mfile_id = insert_mediafile(&mut tx).await;
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert!(!result.has_cc);
+    assert!(!result.has_sdh);
+
+    let update = mediafile::UpdateMediaFile {
+        has_sdh: Some(true),
+        ..Default::default()
+    };
+    update.update(&mut tx, mfile_id).await.unwrap();
+
+    let result = mediafile::MediaFile::get_one(&mut tx, mfile_id)
+        .await
+        .unwrap();
+    assert!(!result.has_cc);
+    assert!(result.has_sdh);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_size_of_library() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library_id = create_test_library(&mut tx).await;
+
+    let result = mediafile::MediaFile::get_size_of_library(&mut tx, library_id)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    let media_id = super::media_tests::insert_media(&mut tx).await;
+
+    for (i, size) in [Some(100), Some(250), None].iter().enumerate() {
+        mediafile::InsertableMediaFile {
+            library_id,
+            media_id: Some(media_id).filter(|_| i > 0),
+            target_file: format!("/dev/null/{}", i),
+            raw_name: "Test".into(),
+            size: *size,
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let result = mediafile::MediaFile::get_size_of_library(&mut tx, library_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![mediafile::LibrarySize {
+            media_type: library::MediaType::Movie,
+            size: 350,
+            files: 3,
+        }]
+    );
+}
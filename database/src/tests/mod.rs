@@ -1,10 +1,29 @@
+pub mod alternate_title_tests;
+pub mod asset_tests;
+pub mod chapter_tests;
+pub mod episode_markers_tests;
+pub mod episode_order_tests;
 pub mod episode_tests;
 pub mod genre_tests;
+pub mod hidden_media_tests;
+pub mod history_tests;
+pub mod keyword_tests;
 pub mod library_tests;
+pub mod match_candidate_tests;
 pub mod media_tests;
 pub mod mediafile_tests;
 pub mod movie_tests;
+pub mod person_tests;
+pub mod playback_window_tests;
+pub mod playlist_tests;
 pub mod progress_tests;
+pub mod rating_tests;
+pub mod scrobble_tests;
+pub mod search_index_tests;
 pub mod season_tests;
+pub mod tag_tests;
+pub mod tmdb_episode_tests;
 pub mod tv_tests;
 pub mod user_tests;
+pub mod version_progress_tests;
+pub mod webhook_tests;
@@ -0,0 +1,107 @@
+use crate::get_conn_memory;
+use crate::library::Library;
+use crate::person;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+use super::media_tests::insert_media;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_cast() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let media_id = insert_media(&mut tx).await;
+
+    let person = person::InsertablePerson {
+        tmdb_id: 31,
+        name: "Tom Hanks".into(),
+        profile_path: None,
+    };
+
+    let id = person
+        .insert_cast(&mut tx, media_id, Some("Forrest Gump".into()), 0)
+        .await
+        .unwrap();
+
+    // inserting the same person again must not create a duplicate.
+    let result = person.insert(&mut tx).await.unwrap();
+    assert_eq!(result, id);
+
+    person::InsertablePerson {
+        tmdb_id: 32,
+        name: "Robin Wright".into(),
+        profile_path: None,
+    }
+    .insert_cast(&mut tx, media_id, None, 1)
+    .await
+    .unwrap();
+
+    let cast = person::Person::get_cast(&mut tx, media_id).await.unwrap();
+    assert_eq!(cast.len(), 2);
+    assert_eq!(cast[0].name, "Tom Hanks");
+    assert_eq!(cast[0].character.as_deref(), Some("Forrest Gump"));
+    assert_eq!(cast[1].ordering, 1);
+
+    let rows = person::Person::clear_cast(&mut tx, media_id).await.unwrap();
+    assert_eq!(rows, 2);
+    assert!(person::Person::get_cast(&mut tx, media_id)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_credits() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let person = person::InsertablePerson {
+        tmdb_id: 138,
+        name: "Quentin Tarantino".into(),
+        profile_path: Some("https://image.tmdb.org/t/p/w185/qt.jpg".into()),
+    };
+
+    let id = person.insert_crew(&mut tx, 1, "Director").await.unwrap();
+    person.insert_crew(&mut tx, 1, "Screenplay").await.unwrap();
+    person.insert_crew(&mut tx, 2, "Director").await.unwrap();
+    person
+        .insert_cast(&mut tx, 1, Some("Mr. Brown".into()), 5)
+        .await
+        .unwrap();
+
+    // a missing profile picture must not erase the known one.
+    person::InsertablePerson {
+        profile_path: None,
+        ..person.clone()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let result = person::Person::get(&mut tx, id).await.unwrap();
+    assert_eq!(result.name, "Quentin Tarantino");
+    assert!(result.profile_path.is_some());
+
+    let credits = person::Person::get_credits(&mut tx, id).await.unwrap();
+    let roles = credits
+        .iter()
+        .map(|x| (x.role.as_str(), x.media_id))
+        .collect::<Vec<_>>();
+    assert_eq!(roles[0], ("Actor", 1));
+    assert_eq!(credits[0].character.as_deref(), Some("Mr. Brown"));
+    assert_eq!(roles.len(), 4);
+    assert_eq!(roles[3], ("Screenplay", 1));
+
+    assert_eq!(person::Person::clear_crew(&mut tx, 2).await.unwrap(), 1);
+
+    // media of hidden libraries are left out.
+    Library::mark_hidden(&mut tx, library).await.unwrap();
+    assert!(person::Person::get_credits(&mut tx, id)
+        .await
+        .unwrap()
+        .is_empty());
+}
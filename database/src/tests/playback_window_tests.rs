@@ -0,0 +1,74 @@
+use super::user_tests::insert_user;
+use crate::get_conn_memory;
+use crate::playback_window::PlaybackWindow;
+use crate::write_tx;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_and_set() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let user = insert_user(&mut tx).await;
+    assert!(PlaybackWindow::get_for_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let windows = vec![
+        PlaybackWindow {
+            weekday: 5,
+            start_minute: 600,
+            end_minute: 1200,
+        },
+        PlaybackWindow {
+            weekday: 0,
+            start_minute: 960,
+            end_minute: 1140,
+        },
+    ];
+
+    PlaybackWindow::set_for_user(&mut tx, &user, &windows)
+        .await
+        .unwrap();
+
+    let result = PlaybackWindow::get_for_user(&mut tx, &user).await.unwrap();
+    assert_eq!(result, vec![windows[1].clone(), windows[0].clone()]);
+
+    PlaybackWindow::set_for_user(&mut tx, &user, &[])
+        .await
+        .unwrap();
+    assert!(PlaybackWindow::get_for_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_allows() {
+    let windows = vec![PlaybackWindow {
+        weekday: 0,
+        start_minute: 960,
+        end_minute: 1140,
+    }];
+
+    assert!(PlaybackWindow::allows(&[], 3, 0));
+    assert!(PlaybackWindow::allows(&windows, 0, 960));
+    assert!(PlaybackWindow::allows(&windows, 0, 1139));
+    assert!(!PlaybackWindow::allows(&windows, 0, 1140));
+    assert!(!PlaybackWindow::allows(&windows, 0, 959));
+    assert!(!PlaybackWindow::allows(&windows, 1, 1000));
+
+    assert!(windows[0].is_valid());
+    assert!(!PlaybackWindow {
+        weekday: 7,
+        start_minute: 0,
+        end_minute: 10,
+    }
+    .is_valid());
+    assert!(!PlaybackWindow {
+        weekday: 0,
+        start_minute: 600,
+        end_minute: 600,
+    }
+    .is_valid());
+}
@@ -0,0 +1,116 @@
+use crate::get_conn_memory;
+use crate::library;
+use crate::playlist::InsertablePlaylist;
+use crate::playlist::Playlist;
+use crate::progress::Progress;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_playlists() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let user = super::user_tests::insert_user(&mut tx).await;
+    super::user_tests::insert_many(&mut tx, 1).await;
+
+    let id = InsertablePlaylist {
+        name: "Watch later".into(),
+    }
+    .insert(&mut tx, &user)
+    .await
+    .unwrap();
+
+    InsertablePlaylist {
+        name: "Anime".into(),
+    }
+    .insert(&mut tx, &user)
+    .await
+    .unwrap();
+
+    let playlist = Playlist::get(&mut tx, id, &user).await.unwrap();
+    assert_eq!(playlist.name, "Watch later");
+    assert_eq!(playlist.item_count, 0);
+
+    let names = Playlist::get_of_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Anime", "Watch later"]);
+
+    // playlists of other users are never returned nor deleted.
+    assert!(Playlist::get(&mut tx, id, "test0").await.is_err());
+    assert!(Playlist::get_of_user(&mut tx, "test0")
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(Playlist::delete(&mut tx, id, "test0").await.unwrap(), 0);
+
+    assert_eq!(Playlist::delete(&mut tx, id, &user).await.unwrap(), 1);
+    assert!(Playlist::get(&mut tx, id, &user).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_playlist_items() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = super::user_tests::insert_user(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let id = InsertablePlaylist {
+        name: "Watch later".into(),
+    }
+    .insert(&mut tx, &user)
+    .await
+    .unwrap();
+
+    let first = Playlist::add_item(&mut tx, id, 1).await.unwrap();
+    let second = Playlist::add_item(&mut tx, id, 2).await.unwrap();
+    let third = Playlist::add_item(&mut tx, id, 3).await.unwrap();
+    // the same media can be added twice.
+    let fourth = Playlist::add_item(&mut tx, id, 1).await.unwrap();
+
+    Progress::set(&mut tx, 120, user.clone(), 2).await.unwrap();
+
+    let items = Playlist::get_items(&mut tx, id, &user).await.unwrap();
+    let order = items.iter().map(|x| x.item_id).collect::<Vec<_>>();
+    assert_eq!(order, vec![first, second, third, fourth]);
+    assert_eq!(items[1].media_id, 2);
+    assert_eq!(items[1].progress, 120);
+    assert_eq!(items[0].progress, 0);
+    assert_eq!(
+        Playlist::get(&mut tx, id, &user).await.unwrap().item_count,
+        4
+    );
+
+    assert!(Playlist::move_item(&mut tx, id, fourth, 0).await.unwrap());
+    assert!(Playlist::move_item(&mut tx, id, first, 100).await.unwrap());
+    assert!(!Playlist::move_item(&mut tx, id, 100, 0).await.unwrap());
+
+    let items = Playlist::get_items(&mut tx, id, &user).await.unwrap();
+    let order = items.iter().map(|x| x.item_id).collect::<Vec<_>>();
+    assert_eq!(order, vec![fourth, second, third, first]);
+
+    assert_eq!(Playlist::remove_item(&mut tx, id, second).await.unwrap(), 1);
+    assert_eq!(Playlist::remove_item(&mut tx, id, second).await.unwrap(), 0);
+
+    let items = Playlist::get_items(&mut tx, id, &user).await.unwrap();
+    let positions = items
+        .iter()
+        .map(|x| (x.item_id, x.position))
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![(fourth, 0), (third, 1), (first, 2)]);
+
+    // items in hidden libraries are left out.
+    library::Library::mark_hidden(&mut tx, library)
+        .await
+        .unwrap();
+    assert!(Playlist::get_items(&mut tx, id, &user)
+        .await
+        .unwrap()
+        .is_empty());
+}
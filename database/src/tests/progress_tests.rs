@@ -8,6 +8,8 @@ use crate::write_tx;
 
 use super::library_tests::create_test_library;
 use super::media_tests::insert_media;
+use super::mediafile_tests::insert_mediafile_with_mediaid;
+use super::user_tests::insert_many;
 use super::user_tests::insert_user;
 
 use std::time::SystemTime;
@@ -43,6 +45,31 @@ async fn test_set_and_get_for_media_user() {
     assert!(result.populated <= ts);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_is_same_offset() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    let result = progress::Progress::get_for_media_user(&mut tx, user.clone(), media)
+        .await
+        .unwrap();
+    assert!(!result.is_same_offset(0));
+
+    progress::Progress::set(&mut tx, 100, user.clone(), media)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_for_media_user(&mut tx, user.clone(), media)
+        .await
+        .unwrap();
+    assert!(result.is_same_offset(100));
+    assert!(result.is_same_offset(101));
+    assert!(!result.is_same_offset(110));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_get_total_time_spent_watching() {
     let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
@@ -191,3 +218,456 @@ async fn test_get_continue_watching() {
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], 2);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restore_and_get_all_for_user() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    let result = progress::Progress::get_all_for_user(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    // restoring the same entry twice must not create duplicates.
+    for _ in 0..2 {
+        progress::Progress::restore(&mut tx, 100, user.clone(), media, 1234)
+            .await
+            .unwrap();
+    }
+
+    let result = progress::Progress::get_all_for_user(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].delta, 100);
+    assert_eq!(result[0].populated, 1234);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_unwatched_episodes_of_tv() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let mut episodes = vec![];
+    for i in 1..=3 {
+        let episode = episode::InsertableEpisode {
+            media: media::InsertableMedia {
+                library_id: library,
+                name: format!("TestEpisode{}", i),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: i,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        let mediafile = insert_mediafile_with_mediaid(&mut tx, episode).await;
+        crate::mediafile::UpdateMediaFile {
+            duration: Some(100),
+            ..Default::default()
+        }
+        .update(&mut tx, mediafile)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    let result = progress::Progress::get_unwatched_episodes_of_tv(&mut tx, user.clone(), tv)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(result, episodes);
+
+    progress::Progress::set(&mut tx, 95, user.clone(), episodes[0])
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 10, user.clone(), episodes[1])
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_unwatched_episodes_of_tv(&mut tx, user.clone(), tv)
+        .await
+        .unwrap();
+    assert_eq!(result, vec![(episodes[1], season), (episodes[2], season)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_can_set() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    assert!(progress::Progress::can_set(&mut tx, &user, media)
+        .await
+        .unwrap());
+    assert!(!progress::Progress::can_set(&mut tx, "missing", media)
+        .await
+        .unwrap());
+    assert!(!progress::Progress::can_set(&mut tx, &user, media + 100)
+        .await
+        .unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prune_orphans() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    // orphaned rows can only be created when the foreign key isnt enforced, thus we defer the
+    // checks until the end of the transaction.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    progress::Progress::set(&mut tx, 10, user.clone(), media)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 10, user.clone(), media + 100)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 10, user.clone(), media + 101)
+        .await
+        .unwrap();
+
+    let rows = progress::Progress::prune_orphans(&mut tx).await.unwrap();
+    assert_eq!(rows, 2);
+
+    let result = progress::Progress::get_all_for_user(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].media_id, media);
+
+    media::Media::delete(&mut tx, media).await.unwrap();
+
+    let result = progress::Progress::get_all_for_user(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_stats() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    super::user_tests::insert_many(&mut tx, 1).await;
+    let media = insert_media(&mut tx).await;
+
+    let mediafile = insert_mediafile_with_mediaid(&mut tx, media).await;
+    crate::mediafile::UpdateMediaFile {
+        duration: Some(100),
+        ..Default::default()
+    }
+    .update(&mut tx, mediafile)
+    .await
+    .unwrap();
+
+    let result = progress::Progress::get_stats(&mut tx, media, None)
+        .await
+        .unwrap();
+    assert_eq!(result, progress::ProgressStats::default());
+
+    progress::Progress::set(&mut tx, 100, user.clone(), media)
+        .await
+        .unwrap();
+    progress::Progress::set(&mut tx, 50, "test0".into(), media)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_stats(&mut tx, media, None)
+        .await
+        .unwrap();
+    assert_eq!(result.watch_count, 1);
+    assert!((result.average_completion - 75.0).abs() < f64::EPSILON);
+    assert!(result.last_watched > 0);
+
+    let result = progress::Progress::get_stats(&mut tx, media, Some("test0".into()))
+        .await
+        .unwrap();
+    assert_eq!(result.watch_count, 0);
+    assert!((result.average_completion - 50.0).abs() < f64::EPSILON);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_in_progress_shows() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let mut episodes = vec![];
+    for i in 1..=4 {
+        let episode = episode::InsertableEpisode {
+            media: media::InsertableMedia {
+                library_id: library,
+                name: format!("TestEpisode{}", i),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: i,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        let mediafile = insert_mediafile_with_mediaid(&mut tx, episode).await;
+        crate::mediafile::UpdateMediaFile {
+            duration: Some(100),
+            ..Default::default()
+        }
+        .update(&mut tx, mediafile)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    // shows that havent been started are excluded.
+    let result = progress::Progress::get_in_progress_shows(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    progress::Progress::set(&mut tx, 95, user.clone(), episodes[0])
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_in_progress_shows(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, tv);
+    assert_eq!(result[0].watched_episodes, 1);
+    assert_eq!(result[0].total_episodes, 4);
+    assert!((result[0].percent() - 25.0).abs() < f64::EPSILON);
+
+    // fully watched shows are excluded.
+    for episode in &episodes[1..] {
+        progress::Progress::set(&mut tx, 95, user.clone(), *episode)
+            .await
+            .unwrap();
+    }
+
+    let result = progress::Progress::get_in_progress_shows(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_for_medias() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+    let other = media::InsertableMedia {
+        library_id: library,
+        name: "OtherMedia".into(),
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let mediafile = insert_mediafile_with_mediaid(&mut tx, media).await;
+    crate::mediafile::UpdateMediaFile {
+        duration: Some(100),
+        ..Default::default()
+    }
+    .update(&mut tx, mediafile)
+    .await
+    .unwrap();
+
+    progress::Progress::set(&mut tx, 95, user.clone(), media)
+        .await
+        .unwrap();
+
+    let mut result = progress::Progress::get_for_medias(&mut tx, user.clone(), &[media, other, -1])
+        .await
+        .unwrap();
+    result.sort_by_key(|x| x.media_id);
+
+    assert_eq!(result.len(), 2);
+    assert_eq!((result[0].delta, result[0].duration), (95, 100));
+    assert!(result[0].is_finished());
+    assert_eq!((result[1].delta, result[1].duration), (0, 0));
+    assert!(!result[1].is_finished());
+
+    let result = progress::Progress::get_for_medias(&mut tx, user, &[])
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_last_in_progress() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+
+    let result = progress::Progress::get_last_in_progress(&mut tx, user.clone())
+        .await
+        .unwrap();
+    assert!(result.is_none());
+
+    let mut medias = Vec::new();
+    for name in vec!["First", "Second", "Third"] {
+        let media = media::InsertableMedia {
+            library_id: library,
+            name: name.into(),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        let mediafile = insert_mediafile_with_mediaid(&mut tx, media).await;
+        crate::mediafile::UpdateMediaFile {
+            duration: Some(100),
+            ..Default::default()
+        }
+        .update(&mut tx, mediafile)
+        .await
+        .unwrap();
+
+        medias.push(media);
+    }
+
+    progress::Progress::restore(&mut tx, 50, user.clone(), medias[0], 10)
+        .await
+        .unwrap();
+    progress::Progress::restore(&mut tx, 20, user.clone(), medias[1], 20)
+        .await
+        .unwrap();
+    // finished media are never resumed even if they were watched last.
+    progress::Progress::restore(&mut tx, 95, user.clone(), medias[2], 30)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_last_in_progress(&mut tx, user.clone())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.media_id, medias[1]);
+    assert_eq!((result.delta, result.duration), (20, 100));
+
+    progress::Progress::restore(&mut tx, 60, user.clone(), medias[0], 40)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_last_in_progress(&mut tx, user)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.media_id, medias[0]);
+    assert_eq!(result.delta, 60);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_watched_by_all() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let library = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+
+    let mut medias = Vec::new();
+    for name in vec!["Watched", "Unfinished", "Recent"] {
+        let media = media::InsertableMedia {
+            library_id: library,
+            name: name.into(),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        let mediafile = insert_mediafile_with_mediaid(&mut tx, media).await;
+        crate::mediafile::UpdateMediaFile {
+            duration: Some(100),
+            ..Default::default()
+        }
+        .update(&mut tx, mediafile)
+        .await
+        .unwrap();
+
+        medias.push(media);
+    }
+
+    progress::Progress::restore(&mut tx, 95, user.clone(), medias[0], 10)
+        .await
+        .unwrap();
+    progress::Progress::restore(&mut tx, 50, user.clone(), medias[1], 10)
+        .await
+        .unwrap();
+    progress::Progress::restore(&mut tx, 95, user.clone(), medias[2], 100)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_watched_by_all(&mut tx, 50)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, medias[0]);
+    assert_eq!(result[0].name, "Watched");
+    assert_eq!(result[0].last_watched, 10);
+
+    // media are only returned once every user watched them.
+    insert_many(&mut tx, 1).await;
+
+    let result = progress::Progress::get_watched_by_all(&mut tx, 50)
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+
+    progress::Progress::restore(&mut tx, 99, "test0".into(), medias[0], 20)
+        .await
+        .unwrap();
+
+    let result = progress::Progress::get_watched_by_all(&mut tx, 50)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].last_watched, 20);
+}
@@ -0,0 +1,86 @@
+use crate::get_conn_memory;
+use crate::rating;
+use crate::user;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::user_tests::insert_user;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_and_get() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    let result = rating::Rating::get_for_media_user(&mut tx, user.clone(), media)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+
+    let rating = rating::InsertableRating {
+        user_id: user.clone(),
+        media_id: media,
+        score: 7,
+    };
+    rating.insert(&mut tx).await.unwrap();
+
+    // rating the same media twice should replace the old score.
+    let rating = rating::InsertableRating { score: 9, ..rating };
+    rating.insert(&mut tx).await.unwrap();
+
+    let result = rating::Rating::get_for_media_user(&mut tx, user.clone(), media)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.score, 9);
+
+    let rows = rating::Rating::delete(&mut tx, user, media).await.unwrap();
+    assert_eq!(rows, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_community_rating() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _ = create_test_library(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    let (average, count) = rating::Rating::get_community_rating(&mut tx, media)
+        .await
+        .unwrap();
+    assert!(average.is_none());
+    assert_eq!(count, 0);
+
+    let first = insert_user(&mut tx).await;
+    let invite = user::Login::new_invite(&mut tx).await.unwrap();
+    let second = user::InsertableUser {
+        username: "test2".into(),
+        password: "test".into(),
+        roles: vec!["User".into()],
+        prefs: Default::default(),
+        claimed_invite: invite,
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    for (user_id, score) in vec![(first, 4), (second, 8)] {
+        rating::InsertableRating {
+            user_id,
+            media_id: media,
+            score,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    let (average, count) = rating::Rating::get_community_rating(&mut tx, media)
+        .await
+        .unwrap();
+    assert_eq!(average, Some(6.0));
+    assert_eq!(count, 2);
+}
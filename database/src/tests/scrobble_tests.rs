@@ -0,0 +1,96 @@
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::user_tests::insert_user;
+use crate::get_conn_memory;
+use crate::media::Media;
+use crate::scrobble::Scrobble;
+use crate::scrobble::ScrobbleIntegration;
+use crate::scrobble::ScrobbleTarget;
+use crate::write_tx;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_integration() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let user = insert_user(&mut tx).await;
+    assert!(ScrobbleIntegration::get_for_user(&mut tx, &user)
+        .await
+        .unwrap()
+        .is_none());
+
+    let mut integration = ScrobbleIntegration {
+        user_id: user.clone(),
+        url: "http://localhost/a".into(),
+        token: "a".into(),
+    };
+    integration.set(&mut tx).await.unwrap();
+
+    integration.token = "b".into();
+    integration.set(&mut tx).await.unwrap();
+
+    let result = ScrobbleIntegration::get_for_user(&mut tx, &user)
+        .await
+        .unwrap();
+    assert_eq!(result, Some(integration));
+
+    let rows = ScrobbleIntegration::delete_for_user(&mut tx, &user)
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_queue() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let user = insert_user(&mut tx).await;
+    let _library = create_test_library(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+
+    // unmatched media cant be scrobbled.
+    assert!(ScrobbleTarget::get(&mut tx, media).await.unwrap().is_none());
+
+    Media::set_tmdb_id(&mut tx, media, 603).await.unwrap();
+    let target = ScrobbleTarget::get(&mut tx, media).await.unwrap().unwrap();
+    assert_eq!(target.media_type, "movie");
+    assert_eq!(target.tmdb_id, 603);
+
+    ScrobbleIntegration {
+        user_id: user.clone(),
+        url: "http://localhost".into(),
+        token: "token".into(),
+    }
+    .set(&mut tx)
+    .await
+    .unwrap();
+
+    let first = Scrobble::queue(&mut tx, &user, "start", &target, 1.0)
+        .await
+        .unwrap();
+    let second = Scrobble::queue(&mut tx, &user, "stop", &target, 95.0)
+        .await
+        .unwrap();
+    assert_eq!(Scrobble::count_for_user(&mut tx, &user).await.unwrap(), 2);
+
+    Scrobble::postpone(&mut tx, first, 100).await.unwrap();
+
+    let due = Scrobble::get_due(&mut tx, 50, 10).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, second);
+    assert_eq!(due[0].target, target);
+    assert_eq!(due[0].token, "token");
+
+    let due = Scrobble::get_due(&mut tx, 100, 10).await.unwrap();
+    assert_eq!(due.len(), 2);
+    assert_eq!(due[0].attempts, 1);
+
+    Scrobble::delete(&mut tx, second).await.unwrap();
+
+    // removing the integration drops its queued scrobbles.
+    ScrobbleIntegration::delete_for_user(&mut tx, &user)
+        .await
+        .unwrap();
+    assert_eq!(Scrobble::count_for_user(&mut tx, &user).await.unwrap(), 0);
+}
@@ -0,0 +1,87 @@
+use crate::alternate_title::InsertableAlternateTitle;
+use crate::get_conn_memory;
+use crate::media::Media;
+use crate::media::UpdateMedia;
+use crate::search_index::SearchIndex;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_triggers() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 3).await;
+
+    let result = SearchIndex::search(&mut tx, "\"testmedia1\"")
+        .await
+        .unwrap();
+    assert_eq!(result, vec![2]);
+
+    UpdateMedia {
+        name: Some("The Godfather".into()),
+        ..Default::default()
+    }
+    .update(&mut tx, 1)
+    .await
+    .unwrap();
+
+    InsertableAlternateTitle {
+        country: "FR".into(),
+        title: "Le Parrain".into(),
+    }
+    .insert_for_media(&mut tx, 1)
+    .await
+    .unwrap();
+
+    let result = SearchIndex::search(&mut tx, "\"godfa\"*").await.unwrap();
+    assert_eq!(result, vec![1]);
+    let result = SearchIndex::search(&mut tx, "\"parrain\"").await.unwrap();
+    assert_eq!(result, vec![1]);
+
+    Media::delete(&mut tx, 1).await.unwrap();
+
+    let result = SearchIndex::search(&mut tx, "\"godfa\"*").await.unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reindex() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 5).await;
+
+    // simulate an index that got out of sync.
+    sqlx::query("DELETE FROM media_search WHERE rowid <= 2")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO media_search (rowid, name) VALUES (42, 'stale')")
+        .execute(&mut tx)
+        .await
+        .unwrap();
+
+    assert_eq!(SearchIndex::count_indexable(&mut tx).await.unwrap(), 5);
+
+    let mut after = 0;
+    let mut indexed = 0;
+    while let Some((last, count)) = SearchIndex::reindex(&mut tx, after, 2).await.unwrap() {
+        after = last;
+        indexed += count;
+    }
+
+    assert_eq!(indexed, 5);
+    assert_eq!(SearchIndex::prune(&mut tx, after).await.unwrap(), 1);
+
+    let result = SearchIndex::search(&mut tx, "\"testmedia\"*")
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 5);
+    assert!(SearchIndex::search(&mut tx, "\"stale\"")
+        .await
+        .unwrap()
+        .is_empty());
+}
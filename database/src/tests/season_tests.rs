@@ -93,3 +93,46 @@ async fn test_update() {
     let result = season::Season::get_by_id(&mut tx, _season).await.unwrap();
     assert_eq!(result.season_number, 1);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_metadata() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_tv(&mut tx).await;
+
+    let _season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let result = season::Season::get(&mut tx, tv, 1).await.unwrap();
+    assert_eq!(result.name, None);
+    assert_eq!(result.overview, None);
+
+    // inserting a season that already exists fills in its metadata.
+    let id = season::InsertableSeason {
+        season_number: 1,
+        name: Some("Season 1".into()),
+        overview: Some("Overview".into()),
+        air_date: Some("2020-01-01".into()),
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+    assert_eq!(id, _season);
+
+    let result = season::Season::get(&mut tx, tv, 1).await.unwrap();
+    assert_eq!(result.name, Some("Season 1".into()));
+    assert_eq!(result.overview, Some("Overview".into()));
+    assert_eq!(result.air_date, Some("2020-01-01".into()));
+
+    let result = season::Season::get_all(&mut tx, tv).await.unwrap();
+    assert_eq!(result[0].name, Some("Season 1".into()));
+
+    assert!(season::Season::get(&mut tx, tv, 2).await.is_err());
+}
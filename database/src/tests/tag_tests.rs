@@ -0,0 +1,92 @@
+use crate::get_conn_memory;
+use crate::tag;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_many;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_for_media() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let id = tag::InsertableTag {
+        name: "Watch with kids".into(),
+    }
+    .insert_for_media(&mut tx, 1)
+    .await
+    .unwrap();
+
+    // tags are deduplicated case insensitively.
+    let result = tag::InsertableTag {
+        name: "watch WITH kids".into(),
+    }
+    .insert_for_media(&mut tx, 2)
+    .await
+    .unwrap();
+    assert_eq!(result, id);
+
+    let result = tag::Tag::get_by_media(&mut tx, 2).await.unwrap();
+    assert_eq!(
+        result,
+        vec![tag::Tag {
+            id,
+            name: "Watch with kids".into(),
+            user_created: true,
+        }]
+    );
+
+    let result = tag::Tag::get_media(&mut tx, id, "test").await.unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 2]);
+
+    let result = tag::Tag::get_all(&mut tx, "test").await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].count, 2);
+
+    // media the user hid arent counted.
+    super::user_tests::insert_many(&mut tx, 1).await;
+    crate::hidden_media::HiddenMedia::hide(&mut tx, "test0", 2)
+        .await
+        .unwrap();
+
+    let result = tag::Tag::get_all(&mut tx, "test0").await.unwrap();
+    assert_eq!(result[0].count, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remove_from_media() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _library = create_test_library(&mut tx).await;
+    insert_many(&mut tx, 2).await;
+
+    let id = tag::InsertableTag {
+        name: "Favourites".into(),
+    }
+    .insert_for_media(&mut tx, 1)
+    .await
+    .unwrap();
+    tag::InsertableTag {
+        name: "Favourites".into(),
+    }
+    .insert_for_media(&mut tx, 2)
+    .await
+    .unwrap();
+
+    let result = tag::Tag::remove_from_media(&mut tx, 1, "favourites")
+        .await
+        .unwrap();
+    assert_eq!(result, 1);
+    assert!(tag::Tag::get_by_media(&mut tx, 1).await.unwrap().is_empty());
+    assert!(tag::Tag::get_by_id(&mut tx, id).await.is_ok());
+
+    // the tag is dropped once nothing is tagged with it anymore.
+    tag::Tag::remove_from_media(&mut tx, 2, "Favourites")
+        .await
+        .unwrap();
+    assert!(tag::Tag::get_by_id(&mut tx, id).await.is_err());
+    assert!(tag::Tag::get_all(&mut tx, "test").await.unwrap().is_empty());
+}
@@ -0,0 +1,215 @@
+use crate::episode;
+use crate::get_conn_memory;
+use crate::media;
+use crate::season;
+use crate::tmdb_episode::TmdbEpisode;
+use crate::tv;
+use crate::write_tx;
+
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::mediafile_tests::insert_mediafile_with_mediaid;
+
+fn catalog() -> Vec<TmdbEpisode> {
+    vec![
+        TmdbEpisode {
+            season: 2,
+            episode: 1,
+            name: Some("Third".into()),
+            air_date: Some("2021-01-01".into()),
+        },
+        TmdbEpisode {
+            season: 1,
+            episode: 2,
+            name: Some("Second".into()),
+            air_date: None,
+        },
+        TmdbEpisode {
+            season: 1,
+            episode: 1,
+            name: Some("First".into()),
+            air_date: Some("2020-01-01".into()),
+        },
+    ]
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_and_set() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+
+    TmdbEpisode::set_for_show(&mut tx, tv, &catalog())
+        .await
+        .unwrap();
+
+    let result = TmdbEpisode::get_for_show(&mut tx, tv).await.unwrap();
+    let order = result
+        .iter()
+        .map(|x| (x.season, x.episode))
+        .collect::<Vec<_>>();
+    assert_eq!(order, vec![(1, 1), (1, 2), (2, 1)]);
+
+    TmdbEpisode::set_for_show(&mut tx, tv, &catalog()[..1])
+        .await
+        .unwrap();
+    assert_eq!(
+        TmdbEpisode::get_for_show(&mut tx, tv).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_mapping() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let episode = episode::InsertableEpisode {
+        media: media::InsertableMedia {
+            library_id: _lib,
+            name: "TestEpisode".into(),
+            ..Default::default()
+        },
+        seasonid: season,
+        episode: 2,
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let mediafile = insert_mediafile_with_mediaid(&mut tx, episode).await;
+
+    TmdbEpisode::set_for_show(&mut tx, tv, &catalog())
+        .await
+        .unwrap();
+
+    let result = TmdbEpisode::get_mapping(&mut tx, tv).await.unwrap();
+    assert_eq!(result.len(), 3);
+
+    assert_eq!((result[0].season, result[0].episode), (1, 1));
+    assert_eq!(result[0].episode_id, None);
+    assert_eq!(result[0].mediafile_id, None);
+
+    assert_eq!((result[1].season, result[1].episode), (1, 2));
+    assert_eq!(result[1].episode_id, Some(episode));
+    assert_eq!(result[1].mediafile_id, Some(mediafile));
+
+    assert_eq!((result[2].season, result[2].episode), (2, 1));
+    assert_eq!(result[2].air_date, Some("2021-01-01".into()));
+    assert_eq!(result[2].episode_id, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_aired_between() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    let episode = episode::InsertableEpisode {
+        media: media::InsertableMedia {
+            library_id: _lib,
+            name: "TestEpisode".into(),
+            ..Default::default()
+        },
+        seasonid: season,
+        episode: 1,
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let mediafile = insert_mediafile_with_mediaid(&mut tx, episode).await;
+
+    TmdbEpisode::set_for_show(&mut tx, tv, &catalog())
+        .await
+        .unwrap();
+
+    let result = TmdbEpisode::get_aired_between(&mut tx, "2019-01-01", "2021-01-01")
+        .await
+        .unwrap();
+    let order = result
+        .iter()
+        .map(|x| (x.season, x.episode))
+        .collect::<Vec<_>>();
+    // the second episode has no air date, thus its excluded.
+    assert_eq!(order, vec![(1, 1), (2, 1)]);
+    assert_eq!(result[0].tvshow_id, tv);
+    assert_eq!(result[0].tvshow_name, "TestMedia");
+    assert_eq!(result[0].mediafile_id, Some(mediafile));
+    assert_eq!(result[1].episode_id, None);
+
+    let result = TmdbEpisode::get_aired_between(&mut tx, "2020-01-02", "2020-12-31")
+        .await
+        .unwrap();
+    assert!(result.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_absolute_numbers() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let _lib = create_test_library(&mut tx).await;
+    let tv = insert_media(&mut tx).await;
+
+    let mut catalog = catalog();
+    catalog.push(TmdbEpisode {
+        season: 0,
+        episode: 1,
+        ..Default::default()
+    });
+
+    TmdbEpisode::set_for_show(&mut tx, tv, &catalog)
+        .await
+        .unwrap();
+
+    // specials aren't counted.
+    let result = TmdbEpisode::resolve_absolute(&mut tx, tv, 1).await.unwrap();
+    assert_eq!(result, Some((1, 1)));
+
+    let result = TmdbEpisode::resolve_absolute(&mut tx, tv, 3).await.unwrap();
+    assert_eq!(result, Some((2, 1)));
+
+    let result = TmdbEpisode::resolve_absolute(&mut tx, tv, 4).await.unwrap();
+    assert_eq!(result, None);
+
+    assert_eq!(
+        TmdbEpisode::get_absolute_number(&mut tx, tv, 2, 1)
+            .await
+            .unwrap(),
+        Some(3)
+    );
+    assert_eq!(
+        TmdbEpisode::get_absolute_number(&mut tx, tv, 0, 1)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        TmdbEpisode::get_absolute_number(&mut tx, tv, 3, 1)
+            .await
+            .unwrap(),
+        None
+    );
+}
@@ -1,6 +1,9 @@
+use crate::episode;
 use crate::get_conn_memory;
 use crate::library;
 use crate::media;
+use crate::mediafile;
+use crate::season;
 use crate::tv;
 use crate::write_tx;
 
@@ -57,3 +60,97 @@ async fn test_upgrade() {
 
     assert!(result.is_ok());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_episodes_across_libraries() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let first_lib = create_test_library(&mut tx).await;
+    let second_lib = create_test_library(&mut tx).await;
+
+    let tv = media::InsertableMedia {
+        library_id: first_lib,
+        name: "TestShow".into(),
+        media_type: library::MediaType::Tv,
+        ..Default::default()
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+    tv::TVShow::insert(&mut tx, tv).await.unwrap();
+
+    let season = season::InsertableSeason {
+        season_number: 1,
+        ..Default::default()
+    }
+    .insert(&mut tx, tv)
+    .await
+    .unwrap();
+
+    // the second episode was scanned into another library.
+    let mut episodes = vec![];
+    for (i, library_id) in [(1, first_lib), (2, second_lib)] {
+        let episode = episode::InsertableEpisode {
+            media: media::InsertableMedia {
+                library_id,
+                name: format!("TestEpisode{}", i),
+                ..Default::default()
+            },
+            seasonid: season,
+            episode: i,
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+
+        episodes.push(episode);
+    }
+
+    // the first episode has two versions, only the longest counts.
+    for (library_id, media_id, duration) in [
+        (first_lib, episodes[0], 100),
+        (first_lib, episodes[0], 90),
+        (second_lib, episodes[1], 50),
+    ] {
+        mediafile::InsertableMediaFile {
+            library_id,
+            media_id: Some(media_id),
+            target_file: format!("/dev/null/{}", duration),
+            raw_name: "Test".into(),
+            duration: Some(duration),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await
+        .unwrap();
+    }
+
+    // episodes belong to the library of their show.
+    let result = media::Media::get(&mut tx, episodes[1]).await.unwrap();
+    assert_eq!(result.library_id, first_lib);
+
+    let result = episode::Episode::get_all_of_tv(&mut tx, tv).await.unwrap();
+    let ids = result.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, episodes);
+
+    let next = result[0].get_next_episode(&mut tx).await.unwrap();
+    assert_eq!(next.id, episodes[1]);
+    let prev = result[1].get_prev_episode(&mut tx).await.unwrap();
+    assert_eq!(prev.id, episodes[0]);
+
+    assert_eq!(
+        tv::TVShow::get_total_episodes(&mut tx, tv).await.unwrap(),
+        2
+    );
+    assert_eq!(
+        tv::TVShow::get_total_duration(&mut tx, tv).await.unwrap(),
+        150
+    );
+
+    // hiding the library of the show hides all of its episodes.
+    library::Library::mark_hidden(&mut tx, first_lib)
+        .await
+        .unwrap();
+    let result = media::Media::get_many(&mut tx, &episodes).await.unwrap();
+    assert!(result.is_empty());
+}
@@ -47,6 +47,25 @@ async fn test_get_one() {
     assert_eq!(&result.roles, &["User".to_string()]);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_id() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let result = user::User::get_id(&mut tx, "test").await;
+    assert!(result.is_err());
+
+    let uname = insert_user(&mut tx).await;
+    let id = user::User::get_id(&mut tx, &uname).await.unwrap();
+
+    user::User::set_username(&mut tx, uname, "renamed".into())
+        .await
+        .unwrap();
+
+    let result = user::User::get_id(&mut tx, "renamed").await.unwrap();
+    assert_eq!(result, id);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_get_all() {
     let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
@@ -129,3 +148,28 @@ async fn test_invites() {
     .unwrap();
     assert_eq!(result, 0);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_playback_preferences() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+    let uname = insert_user(&mut tx).await;
+
+    let mut prefs = user::User::get(&mut tx, &uname).await.unwrap().prefs;
+    let playback = user::PlaybackPreferences {
+        preferred_audio_lang: Some("jpn".into()),
+        preferred_subtitle_lang: None,
+        subtitles_enabled: false,
+        auto_mark_watched: false,
+    };
+
+    prefs.set_playback_preferences(playback.clone());
+
+    user::UpdateableUser { prefs: Some(prefs) }
+        .update(&mut tx, &uname)
+        .await
+        .unwrap();
+
+    let result = user::User::get(&mut tx, &uname).await.unwrap().prefs;
+    assert_eq!(result.playback_preferences(), playback);
+}
@@ -0,0 +1,62 @@
+use super::library_tests::create_test_library;
+use super::media_tests::insert_media;
+use super::user_tests::insert_user;
+use crate::get_conn_memory;
+use crate::mediafile::InsertableMediaFile;
+use crate::version_progress::VersionProgress;
+use crate::write_tx;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_switch() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let _library = create_test_library(&mut tx).await;
+    let media = insert_media(&mut tx).await;
+    let user = insert_user(&mut tx).await;
+
+    let mut versions = vec![];
+    for i in 0..2 {
+        let file = InsertableMediaFile {
+            library_id: 1,
+            media_id: Some(media),
+            target_file: format!("/movies/{}.mkv", i),
+            raw_name: "Test".into(),
+            ..Default::default()
+        };
+
+        versions.push(file.insert(&mut tx).await.unwrap());
+    }
+
+    assert_eq!(
+        VersionProgress::get_active(&mut tx, &user, media)
+            .await
+            .unwrap(),
+        None
+    );
+
+    VersionProgress::switch(&mut tx, &user, None, versions[0], 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        VersionProgress::get_active(&mut tx, &user, media)
+            .await
+            .unwrap(),
+        Some(versions[0])
+    );
+
+    VersionProgress::switch(&mut tx, &user, Some((versions[0], 600)), versions[1], 30)
+        .await
+        .unwrap();
+
+    let result = VersionProgress::get_for_media(&mut tx, &user, media)
+        .await
+        .unwrap();
+    assert_eq!(
+        result
+            .iter()
+            .map(|x| (x.mediafile_id, x.delta))
+            .collect::<Vec<_>>(),
+        vec![(versions[1], 30), (versions[0], 600)]
+    );
+}
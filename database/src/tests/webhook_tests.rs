@@ -0,0 +1,28 @@
+use crate::get_conn_memory;
+use crate::webhook;
+use crate::write_tx;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_insert_get_and_delete() {
+    let mut conn = get_conn_memory().await.unwrap().writer().lock_owned().await;
+    let mut tx = write_tx(&mut conn).await.unwrap();
+
+    let id = webhook::InsertableWebhook {
+        url: "http://localhost/hook".into(),
+        event_types: vec!["EventNewCard".into(), "EventRemoveCard".into()],
+        secret: "secret".into(),
+    }
+    .insert(&mut tx)
+    .await
+    .unwrap();
+
+    let result = webhook::Webhook::get_all(&mut tx).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].id, id);
+    assert!(result[0].accepts("EventNewCard"));
+    assert!(!result[0].accepts("EventStreamIsReady"));
+
+    let rows = webhook::Webhook::delete(&mut tx, id).await.unwrap();
+    assert_eq!(rows, 1);
+    assert!(webhook::Webhook::get_all(&mut tx).await.unwrap().is_empty());
+}
@@ -0,0 +1,244 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+
+/// Struct represents a episode of a tv show as listed on TMDB. Unlike
+/// [`Episode`](crate::episode::Episode) these exist for every episode of a show whether or not we
+/// have a file for it.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct TmdbEpisode {
+    pub season: i64,
+    pub episode: i64,
+    pub name: Option<String>,
+    /// Date the episode first aired formatted as `YYYY-MM-DD`.
+    pub air_date: Option<String>,
+}
+
+/// A TMDB episode along with the local episode and file it maps to, if any.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct EpisodeMapping {
+    pub season: i64,
+    pub episode: i64,
+    pub name: Option<String>,
+    pub air_date: Option<String>,
+    /// Id of the local episode.
+    pub episode_id: Option<i64>,
+    /// Id of the file of the local episode.
+    pub mediafile_id: Option<i64>,
+}
+
+/// A TMDB episode that aired within a date range along with the tv show it belongs to and the local
+/// episode and file it maps to, if any.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, sqlx::FromRow)]
+pub struct AiredEpisode {
+    pub tvshow_id: i64,
+    pub tvshow_name: String,
+    pub season: i64,
+    pub episode: i64,
+    pub name: Option<String>,
+    pub air_date: String,
+    /// Id of the local episode.
+    pub episode_id: Option<i64>,
+    /// Id of the file of the local episode.
+    pub mediafile_id: Option<i64>,
+}
+
+impl TmdbEpisode {
+    /// Method returns the TMDB episode catalog of a tv show ordered by season and episode.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the tv show.
+    pub async fn get_for_show(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            TmdbEpisode,
+            "SELECT season, episode, name, air_date FROM tmdb_episodes
+            WHERE tvshow_id = ?
+            ORDER BY season, episode",
+            tv_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method replaces the TMDB episode catalog of a tv show.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the tv show.
+    /// * `episodes` - every episode of the show listed on TMDB.
+    pub async fn set_for_show(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        episodes: &[Self],
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!("DELETE FROM tmdb_episodes WHERE tvshow_id = ?", tv_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for episode in episodes {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO tmdb_episodes (tvshow_id, season, episode, name, air_date)
+                VALUES ($1, $2, $3, $4, $5)",
+                tv_id,
+                episode.season,
+                episode.episode,
+                episode.name,
+                episode.air_date
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Method resolves a absolute episode number, ie the position of a episode across all regular
+    /// seasons of a tv show, to its season and episode number. Specials (season 0) aren't counted.
+    /// Returns `None` if the catalog has fewer episodes.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the tv show.
+    /// * `absolute_number` - absolute episode number starting at 1.
+    pub async fn resolve_absolute(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        absolute_number: i64,
+    ) -> Result<Option<(i64, i64)>, DatabaseError> {
+        if absolute_number < 1 {
+            return Ok(None);
+        }
+
+        let offset = absolute_number - 1;
+
+        Ok(sqlx::query!(
+            "SELECT season, episode FROM tmdb_episodes
+            WHERE tvshow_id = ? AND season > 0
+            ORDER BY season, episode
+            LIMIT 1 OFFSET ?",
+            tv_id,
+            offset
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|x| (x.season, x.episode)))
+    }
+
+    /// Method returns the absolute number of a episode, the inverse of
+    /// [`resolve_absolute`](TmdbEpisode::resolve_absolute). Returns `None` for specials and
+    /// episodes missing from the catalog.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the tv show.
+    /// * `season` - season number of the episode.
+    /// * `episode` - episode number of the episode.
+    pub async fn get_absolute_number(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+        season: i64,
+        episode: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        let exists = sqlx::query!(
+            "SELECT id FROM tmdb_episodes WHERE tvshow_id = ? AND season = ? AND episode = ?",
+            tv_id,
+            season,
+            episode
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some();
+
+        if !exists || season < 1 {
+            return Ok(None);
+        }
+
+        let absolute_number = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM tmdb_episodes
+            WHERE tvshow_id = ?1 AND season > 0
+                AND (season < ?2 OR (season = ?2 AND episode <= ?3))"#,
+            tv_id,
+            season,
+            episode
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(Some(absolute_number))
+    }
+
+    /// Method returns every TMDB episode of a tv show along with the local episode and file it
+    /// maps to, ordered by season and episode.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `tv_id` - id of the tv show.
+    pub async fn get_mapping(
+        conn: &mut crate::Transaction<'_>,
+        tv_id: i64,
+    ) -> Result<Vec<EpisodeMapping>, DatabaseError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, EpisodeMapping>(
+            r#"SELECT tmdb_episodes.season, tmdb_episodes.episode, tmdb_episodes.name,
+                tmdb_episodes.air_date, episode.id as episode_id, MIN(mediafile.id) as mediafile_id
+            FROM tmdb_episodes
+            LEFT JOIN season ON season.tvshowid = tmdb_episodes.tvshow_id
+                AND season.season_number = tmdb_episodes.season
+            LEFT JOIN episode ON episode.seasonid = season.id
+                AND episode.episode_ = tmdb_episodes.episode
+            LEFT JOIN mediafile ON mediafile.media_id = episode.id
+
+            WHERE tmdb_episodes.tvshow_id = ?
+
+            GROUP BY tmdb_episodes.id
+            ORDER BY tmdb_episodes.season, tmdb_episodes.episode"#,
+        )
+        .bind(tv_id)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the TMDB episodes of every tv show that aired within a date range, ordered by
+    /// air date. Episodes with an unknown air date and shows in hidden libraries are excluded.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `from` - first day of the range formatted as `YYYY-MM-DD`, inclusive.
+    /// * `to` - last day of the range formatted as `YYYY-MM-DD`, inclusive.
+    pub async fn get_aired_between(
+        conn: &mut crate::Transaction<'_>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<AiredEpisode>, DatabaseError> {
+        // FIXME: Use query_as macro instead of query_as function when https://github.com/launchbadge/sqlx/issues/1249 is fixed.
+        Ok(sqlx::query_as::<_, AiredEpisode>(
+            r#"SELECT tmdb_episodes.tvshow_id, _tblmedia.name as tvshow_name, tmdb_episodes.season,
+                tmdb_episodes.episode, tmdb_episodes.name, tmdb_episodes.air_date,
+                episode.id as episode_id, MIN(mediafile.id) as mediafile_id
+            FROM tmdb_episodes
+            INNER JOIN _tblmedia ON _tblmedia.id = tmdb_episodes.tvshow_id
+            INNER JOIN library ON library.id = _tblmedia.library_id
+            LEFT JOIN season ON season.tvshowid = tmdb_episodes.tvshow_id
+                AND season.season_number = tmdb_episodes.season
+            LEFT JOIN episode ON episode.seasonid = season.id
+                AND episode.episode_ = tmdb_episodes.episode
+            LEFT JOIN mediafile ON mediafile.media_id = episode.id
+
+            WHERE tmdb_episodes.air_date IS NOT NULL
+                AND tmdb_episodes.air_date BETWEEN $1 AND $2
+                AND NOT library.hidden
+
+            GROUP BY tmdb_episodes.id
+            ORDER BY tmdb_episodes.air_date, _tblmedia.name, tmdb_episodes.season,
+                tmdb_episodes.episode"#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+}
@@ -51,7 +51,8 @@ impl TVShow {
         Ok(media)
     }
 
-    /// Returns total duration of the files on disk for a tv show.
+    /// Returns total duration of the episodes of a tv show. Episodes with several files only count
+    /// the duration of their longest file.
     pub async fn get_total_duration(
         conn: &mut crate::Transaction<'_>,
         id: i64,
@@ -64,13 +65,12 @@ impl TVShow {
         // FIXME: The sqlx proc macro crashes on this query with the message: "no entry found for
         // key"
         Ok(sqlx::query_as::<_, Row>(
-            r#"SELECT COALESCE(SUM(mediafile.duration), 0) as "total: i64"
+            r#"SELECT COALESCE(SUM(_tblmedia.duration), 0) as total
             FROM tv_show
             INNER JOIN season on season.tvshowid = tv_show.id
             INNER JOIN episode on episode.seasonid = season.id
-            INNER JOIN mediafile on mediafile.media_id = episode.id
+            INNER JOIN _tblmedia on _tblmedia.id = episode.id
             WHERE tv_show.id = ?
-            GROUP BY episode.id
             "#,
         )
         .bind(id)
@@ -91,7 +91,7 @@ impl TVShow {
 
         // FIXME: See `get_total_duration`
         Ok(sqlx::query_as::<_, Row>(
-            r#"SELECT COALESCE(COUNT(episode.id), 0) as "total: i64" FROM tv_show
+            r#"SELECT COALESCE(COUNT(episode.id), 0) as total FROM tv_show
             INNER JOIN season on season.tvshowid = tv_show.id
             INNER JOIN episode on episode.seasonid = season.id
             WHERE tv_show.id = ?"#,
@@ -102,6 +102,28 @@ impl TVShow {
         .total)
     }
 
+    /// Returns the number of seasons of a tv show.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the tv show.
+    /// * `include_specials` - whether the specials season (season 0) should be counted.
+    pub async fn get_season_count(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+        include_specials: bool,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query!(
+            r#"SELECT COUNT(id) as "count!: i64" FROM _tblseason
+            WHERE tvshowid = ? AND (season_number > 0 OR ?)"#,
+            id,
+            include_specials
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .count)
+    }
+
     /// Method inserts a new tv show in the database.
     ///
     /// # Arguments
@@ -61,6 +61,9 @@ pub struct UserSettings {
     default_subtitle_language: Option<String>,
     /// If a file has audio then the audio track with this language will be selected, otherwise the first one.
     default_audio_language: Option<String>,
+    /// Whether a subtitle track should be selected by default.
+    #[serde(default = "default_true")]
+    subtitles_enabled: bool,
     /// Represents the default video quality for user.
     pub default_video_quality: DefaultVideoQuality,
     /// Any other external args.
@@ -71,6 +74,9 @@ pub struct UserSettings {
     show_hovercards: bool,
     /// Whether to auto play next video
     enable_autoplay: bool,
+    /// Whether finishing a media records a play in the watch history of the user.
+    #[serde(default = "default_true")]
+    auto_mark_watched: bool,
 }
 
 impl Default for UserSettings {
@@ -83,12 +89,56 @@ impl Default for UserSettings {
             filebrowser_list_view: true,
             default_subtitle_language: Some("english".into()),
             default_audio_language: Some("english".into()),
+            subtitles_enabled: true,
             external_args: HashMap::new(),
             show_hovercards: true,
             default_video_quality: DefaultVideoQuality::DirectPlay,
             enable_autoplay: true,
+            auto_mark_watched: true,
+        }
+    }
+}
+
+impl UserSettings {
+    /// Method returns the playback preferences of the user.
+    pub fn playback_preferences(&self) -> PlaybackPreferences {
+        PlaybackPreferences {
+            preferred_audio_lang: self.default_audio_language.clone(),
+            preferred_subtitle_lang: self.default_subtitle_language.clone(),
+            subtitles_enabled: self.subtitles_enabled,
+            auto_mark_watched: self.auto_mark_watched,
         }
     }
+
+    /// Method returns whether finishing a media records a play in the watch history of the user.
+    pub fn auto_mark_watched(&self) -> bool {
+        self.auto_mark_watched
+    }
+
+    /// Method replaces the playback preferences of the user.
+    pub fn set_playback_preferences(&mut self, prefs: PlaybackPreferences) {
+        self.default_audio_language = prefs.preferred_audio_lang;
+        self.default_subtitle_language = prefs.preferred_subtitle_lang;
+        self.subtitles_enabled = prefs.subtitles_enabled;
+        self.auto_mark_watched = prefs.auto_mark_watched;
+    }
+}
+
+/// Playback preferences of a user. The audio and subtitle preferences are used to pick the default
+/// tracks when streaming.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaybackPreferences {
+    /// Language of the audio track to select, ie `english` or `eng`.
+    pub preferred_audio_lang: Option<String>,
+    /// Language of the subtitle track to select.
+    pub preferred_subtitle_lang: Option<String>,
+    /// Whether a subtitle track should be selected at all.
+    #[serde(default = "default_true")]
+    pub subtitles_enabled: bool,
+    /// Whether finishing a media records a play in the watch history of the user. Otherwise only
+    /// the offset is stored.
+    #[serde(default = "default_true")]
+    pub auto_mark_watched: bool,
 }
 
 // NOTE: Figure out the bug with this not being a valid postgres type
@@ -148,6 +198,24 @@ impl User {
         })?)
     }
 
+    /// Method returns the id of a user. Users are keyed by their username, thus this is the rowid
+    /// of the user which stays the same when the user is renamed.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `username` - username of the user.
+    pub async fn get_id(
+        conn: &mut crate::Transaction<'_>,
+        username: &str,
+    ) -> Result<i64, DatabaseError> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT rowid as "id!: i64" FROM users WHERE username = ?"#,
+            username
+        )
+        .fetch_one(&mut *conn)
+        .await?)
+    }
+
     /// Method gets one entry from the table users based on the username supplied and password.
     ///
     /// # Arguments
@@ -0,0 +1,110 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Where a user left off in a version, ie a single file, of a media.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct VersionProgress {
+    pub mediafile_id: i64,
+    /// Offset in seconds the user left the version at.
+    pub delta: i64,
+    /// Unix timestamp of when the user last switched to or away from the version.
+    pub populated: i64,
+}
+
+impl VersionProgress {
+    /// Method returns the versions of a media a user played, the one played last first.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_id` - id of the media.
+    pub async fn get_for_media(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_id: i64,
+    ) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(
+            VersionProgress,
+            "SELECT version_progress.mediafile_id, version_progress.delta, version_progress.populated
+            FROM version_progress
+            INNER JOIN mediafile ON mediafile.id = version_progress.mediafile_id
+            WHERE version_progress.user_id = ? AND mediafile.media_id = ?
+            ORDER BY version_progress.populated DESC, version_progress.rowid DESC",
+            uid,
+            media_id
+        )
+        .fetch_all(&mut *conn)
+        .await?)
+    }
+
+    /// Method returns the id of the version of a media a user played last, if any.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `media_id` - id of the media.
+    pub async fn get_active(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        media_id: i64,
+    ) -> Result<Option<i64>, DatabaseError> {
+        Ok(Self::get_for_media(&mut *conn, uid, media_id)
+            .await?
+            .first()
+            .map(|x| x.mediafile_id))
+    }
+
+    /// Method records a user switching from the version `from`, which they left at `delta`, to the
+    /// version `to`, which they start playing at `offset`. `from` is `None` if the user hasnt
+    /// played any version of the media before.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `uid` - username of the user.
+    /// * `from` - id of the version and offset the user left it at.
+    /// * `to` - id of the version the user switched to.
+    /// * `offset` - offset in seconds the user starts playing `to` at.
+    pub async fn switch(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        from: Option<(i64, i64)>,
+        to: i64,
+        offset: i64,
+    ) -> Result<(), DatabaseError> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        if let Some((mediafile_id, delta)) = from {
+            Self::set(&mut *conn, uid, mediafile_id, delta, timestamp).await?;
+        }
+
+        Self::set(&mut *conn, uid, to, offset, timestamp).await
+    }
+
+    async fn set(
+        conn: &mut crate::Transaction<'_>,
+        uid: &str,
+        mediafile_id: i64,
+        delta: i64,
+        populated: i64,
+    ) -> Result<(), DatabaseError> {
+        // the row is replaced so that its rowid breaks ties between versions switched within the
+        // same second.
+        sqlx::query!(
+            "INSERT OR REPLACE INTO version_progress (user_id, mediafile_id, delta, populated)
+            VALUES ($1, $2, $3, $4)",
+            uid,
+            mediafile_id,
+            delta,
+            populated
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use crate::DatabaseError;
+
+use serde::Serialize;
+
+/// Struct represents a outbound webhook which receives events as they are dispatched to clients.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Comma separated list of event types this webhook receives. Empty means all events.
+    pub event_types: String,
+    /// Key used to sign the payloads delivered to this webhook.
+    #[serde(skip_serializing)]
+    pub secret: String,
+}
+
+impl Webhook {
+    /// Method returns all registered webhooks.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn get_all(conn: &mut crate::Transaction<'_>) -> Result<Vec<Self>, DatabaseError> {
+        Ok(sqlx::query_as!(Webhook, "SELECT * FROM webhooks")
+            .fetch_all(&mut *conn)
+            .await?)
+    }
+
+    /// Method deletes a webhook.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    /// * `id` - id of the webhook.
+    pub async fn delete(
+        conn: &mut crate::Transaction<'_>,
+        id: i64,
+    ) -> Result<usize, DatabaseError> {
+        Ok(sqlx::query!("DELETE FROM webhooks WHERE id = ?", id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected() as usize)
+    }
+
+    /// Returns whether this webhook wants to receive events of `event_type`.
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.split(',').any(|x| x.trim() == event_type)
+    }
+}
+
+/// Struct represents a new webhook.
+#[derive(Debug, Clone, Default)]
+pub struct InsertableWebhook {
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+}
+
+impl InsertableWebhook {
+    /// Method inserts a new webhook and returns its id.
+    ///
+    /// # Arguments
+    /// * `conn` - mutable reference to a sqlx transaction.
+    pub async fn insert(&self, conn: &mut crate::Transaction<'_>) -> Result<i64, DatabaseError> {
+        let event_types = self.event_types.join(",");
+
+        Ok(sqlx::query!(
+            "INSERT INTO webhooks (url, event_types, secret) VALUES ($1, $2, $3)",
+            self.url,
+            event_types,
+            self.secret
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid())
+    }
+}
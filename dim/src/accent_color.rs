@@ -0,0 +1,269 @@
+//! Accent colors of media, the dominant color of their poster used by clients to tint their UI.
+//!
+//! Colors are computed from a downscaled copy of the poster decoded with ffmpeg. Posters that
+//! havent been fetched yet are downloaded and decoded in memory, they are left for the fetcher to
+//! store so that a half written file is never served. Media matched by a scan are queued with
+//! [`queue`], media scanned before accent colors existed are backfilled by
+//! `POST /api/v1/admin/maintenance/backfill_accent_colors`. Colors are cleared by the database
+//! whenever the poster of a media changes.
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use database::asset::Asset;
+use database::media::Media;
+use database::DbConnection;
+
+use once_cell::sync::Lazy;
+use tokio::sync::Semaphore;
+use tokio::task::spawn_blocking;
+use tracing::warn;
+
+use crate::errors::DimError;
+
+/// Width and height posters are scaled down to before their colors are counted.
+const SAMPLE_SIZE: (u32, u32) = (20, 30);
+
+/// Max number of accent colors computed at once for queued media.
+const MAX_CONCURRENT: usize = 2;
+
+static PERMITS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(MAX_CONCURRENT));
+
+/// Client posters which havent been fetched yet are downloaded with.
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to build the accent color client")
+});
+
+/// Media whose accent color is queued or being computed.
+static PENDING: Lazy<Mutex<HashSet<i64>>> = Lazy::new(Default::default);
+
+/// Computes the accent color of the media `id` in the background. Shows are matched once per
+/// episode, media already queued are thus skipped.
+pub fn queue(conn: DbConnection, id: i64) {
+    if !PENDING.lock().unwrap().insert(id) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        if let Ok(_permit) = PERMITS.acquire().await {
+            if let Err(e) = update(&conn, id).await {
+                warn!(media_id = id, reason = ?e, "Failed to compute accent color.");
+            }
+        }
+
+        PENDING.lock().unwrap().remove(&id);
+    });
+}
+
+/// Computes the accent color of the media `id` from its poster and stores it. Returns whether a
+/// color was set, media which already have one, have no poster or whose poster cant be decoded
+/// are left alone.
+pub async fn update(conn: &DbConnection, id: i64) -> Result<bool, DimError> {
+    let (poster_path, remote_url) = {
+        let mut tx = conn.read().begin().await?;
+
+        if Media::get_accent_color(&mut tx, id).await?.is_some() {
+            return Ok(false);
+        }
+
+        let poster_path = match Media::get(&mut tx, id)
+            .await?
+            .poster_path
+            .filter(|x| !x.is_empty())
+        {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        let remote_url = Asset::get_url_by_file(&mut tx, &PathBuf::from(&poster_path))
+            .await
+            .ok();
+
+        (poster_path, remote_url)
+    };
+
+    let local_path = Path::new(crate::core::METADATA_PATH.get().unwrap())
+        .join(poster_path.trim_start_matches("images/"));
+
+    // posters of freshly matched media are usually still queued in the fetcher.
+    let source = if local_path.exists() {
+        Source::File(local_path)
+    } else {
+        let remote_url = match remote_url {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        let bytes = CLIENT
+            .get(remote_url.as_str())
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            .map_err(|_| DimError::IOError)?
+            .bytes()
+            .await
+            .map_err(|_| DimError::IOError)?;
+
+        Source::Memory(bytes.to_vec())
+    };
+
+    let pixels = match spawn_blocking(move || sample(source)).await {
+        Ok(Some(x)) => x,
+        _ => return Ok(false),
+    };
+
+    let color = match dominant_color(&pixels) {
+        Some(x) => to_hex(x),
+        None => return Ok(false),
+    };
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    Media::set_accent_color(&mut tx, id, &color).await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Image whose colors are sampled.
+enum Source {
+    /// Path of the image on disk.
+    File(PathBuf),
+    /// Contents of a image file.
+    Memory(Vec<u8>),
+}
+
+/// Decodes `source` scaled down to [`SAMPLE_SIZE`] into packed `rgb24` pixels.
+fn sample(source: Source) -> Option<Vec<u8>> {
+    let (width, height) = SAMPLE_SIZE;
+
+    let mut command = Command::new(*crate::streaming::FFMPEG_BIN);
+
+    match &source {
+        Source::File(path) => command.arg("-i").arg(path),
+        Source::Memory(_) => command.arg("-i").arg("pipe:0").stdin(Stdio::piped()),
+    };
+
+    let mut child = command
+        .arg("-vf")
+        .arg(format!("scale={}:{}", width, height))
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-f")
+        .arg("rawvideo")
+        .arg("-pix_fmt")
+        .arg("rgb24")
+        .arg("pipe:1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // the sample is small enough to fit into the pipe buffer, thus ffmpeg never blocks on stdout
+    // while we are still writing the image. stdin is closed once written.
+    if let Source::Memory(bytes) = source {
+        let mut stdin = child.stdin.take()?;
+        let _ = stdin.write_all(&bytes);
+    }
+
+    let output = child.wait_with_output().ok()?;
+
+    Some(output.stdout).filter(|_| output.status.success())
+}
+
+/// Returns the dominant color of `pixels`, packed `rgb24` pixels. Similar colors are grouped into
+/// buckets and the mean of the most populated bucket is returned. Near black and near white
+/// pixels, ie letterboxing and titles, are only used if the image has no other colors.
+pub fn dominant_color(pixels: &[u8]) -> Option<[u8; 3]> {
+    let mut buckets: BTreeMap<[u8; 3], (u32, [u32; 3])> = BTreeMap::new();
+    let mut all = (0, [0; 3]);
+
+    for pixel in pixels.chunks_exact(3) {
+        add(&mut all, pixel);
+
+        let max = pixel.iter().max().copied().unwrap_or_default();
+        let min = pixel.iter().min().copied().unwrap_or_default();
+
+        if max >= 32 && min <= 224 {
+            let key = [pixel[0] >> 5, pixel[1] >> 5, pixel[2] >> 5];
+            add(buckets.entry(key).or_default(), pixel);
+        }
+    }
+
+    let (count, sums) = buckets
+        .values()
+        .copied()
+        .max_by_key(|(count, _)| *count)
+        .unwrap_or(all);
+
+    if count == 0 {
+        return None;
+    }
+
+    Some([
+        (sums[0] / count) as u8,
+        (sums[1] / count) as u8,
+        (sums[2] / count) as u8,
+    ])
+}
+
+fn add((count, sums): &mut (u32, [u32; 3]), pixel: &[u8]) {
+    *count += 1;
+
+    for (sum, x) in sums.iter_mut().zip(pixel) {
+        *sum += *x as u32;
+    }
+}
+
+/// Formats `color` as `#rrggbb`.
+pub fn to_hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dominant_color;
+    use super::to_hex;
+
+    fn image(colors: &[([u8; 3], usize)]) -> Vec<u8> {
+        colors
+            .iter()
+            .flat_map(|(color, n)| color.repeat(*n))
+            .collect()
+    }
+
+    #[test]
+    fn test_dominant_color() {
+        let pixels = image(&[([200, 30, 30], 10), ([30, 30, 200], 5)]);
+        assert_eq!(dominant_color(&pixels), Some([200, 30, 30]));
+
+        // similar colors are averaged.
+        let pixels = image(&[([200, 30, 30], 1), ([210, 20, 20], 1), ([30, 30, 200], 1)]);
+        assert_eq!(dominant_color(&pixels), Some([205, 25, 25]));
+    }
+
+    #[test]
+    fn test_dominant_color_skips_black_and_white() {
+        let pixels = image(&[([0, 0, 0], 50), ([255, 255, 255], 20), ([30, 120, 60], 3)]);
+        assert_eq!(dominant_color(&pixels), Some([30, 120, 60]));
+
+        let pixels = image(&[([0, 0, 0], 3), ([10, 10, 10], 1)]);
+        assert_eq!(dominant_color(&pixels), Some([2, 2, 2]));
+
+        assert_eq!(dominant_color(&[]), None);
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(to_hex([26, 43, 255]), "#1a2bff");
+    }
+}
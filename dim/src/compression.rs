@@ -0,0 +1,227 @@
+//! Compression of JSON responses honouring the `Accept-Encoding` header of the request.
+//!
+//! Only responses with a `application/json` content type are compressed, this way streamed
+//! files, images and video chunks which are either already compressed or too large to be
+//! buffered are passed through untouched. Responses smaller than `compression_min_size` in the
+//! global settings aren't compressed as the overhead outweighs the savings.
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use warp::http::header::HeaderValue;
+use warp::http::header::CONTENT_ENCODING;
+use warp::http::header::CONTENT_LENGTH;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::VARY;
+use warp::hyper::body::Body;
+use warp::reply::Response;
+use warp::Reply;
+
+/// Responses smaller than this many bytes aren't compressed if nothing is configured.
+pub const DEFAULT_MIN_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// Supported encodings in order of preference.
+    const PREFERENCE: [Self; 3] = [Self::Brotli, Self::Gzip, Self::Deflate];
+
+    /// Picks the encoding to use from the value of a `Accept-Encoding` header. Encodings with a
+    /// higher quality value win, ties are broken by our own preference. Returns `None` if the
+    /// client accepts none of the supported encodings.
+    pub fn negotiate(accept_encoding: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+
+        for value in accept_encoding.split(',') {
+            let mut parts = value.split(';');
+            let name = parts.next().unwrap_or_default().trim().to_lowercase();
+            let quality = parts
+                .filter_map(|x| x.trim().strip_prefix("q="))
+                .filter_map(|x| x.trim().parse::<f32>().ok())
+                .next()
+                .unwrap_or(1.0);
+
+            if quality <= 0.0 {
+                continue;
+            }
+
+            let candidates = match name.as_str() {
+                "br" => vec![Self::Brotli],
+                "gzip" | "x-gzip" => vec![Self::Gzip],
+                "deflate" => vec![Self::Deflate],
+                "*" => Self::PREFERENCE.to_vec(),
+                _ => continue,
+            };
+
+            for encoding in candidates {
+                let better = match best {
+                    Some((current, q)) => {
+                        quality > q || (quality == q && encoding.rank() < current.rank())
+                    }
+                    None => true,
+                };
+
+                if better {
+                    best = Some((encoding, quality));
+                }
+            }
+        }
+
+        best.map(|(encoding, _)| encoding)
+    }
+
+    fn rank(&self) -> usize {
+        Self::PREFERENCE.iter().position(|x| x == self).unwrap()
+    }
+
+    /// Returns the name of the encoding as used in the `Content-Encoding` header.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        }
+    }
+
+    /// Compresses `data` with this encoding.
+    pub fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                writer.write_all(data)?;
+                Ok(writer.into_inner())
+            }
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            // `deflate` in http refers to the zlib format rather than raw deflate.
+            Self::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Compresses `reply` with the best encoding accepted by the client if it is a JSON response of
+/// at least `min_size` bytes. Any other reply is returned as is.
+pub async fn compress(
+    accept_encoding: Option<String>,
+    reply: impl Reply,
+    min_size: usize,
+) -> Response {
+    let response = reply.into_response();
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .map_or(false, |x| x.starts_with("application/json"));
+
+    if !is_json || response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+
+    let encoding = match accept_encoding.as_deref().and_then(Encoding::negotiate) {
+        Some(x) => x,
+        None => return response,
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match warp::hyper::body::to_bytes(body).await {
+        Ok(x) => x,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+
+    if bytes.len() < min_size {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    match encoding.compress(&bytes) {
+        Ok(compressed) => {
+            parts.headers.remove(CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            Encoding::negotiate("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(Encoding::negotiate("gzip, deflate"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("deflate"), Some(Encoding::Deflate));
+        assert_eq!(
+            Encoding::negotiate("br;q=0.5, gzip;q=0.8"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(Encoding::negotiate("br;q=0, gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("identity"), None);
+        assert_eq!(Encoding::negotiate(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_compress() {
+        let data = serde_json::json!({ "name": "a".repeat(4096) });
+        let reply = warp::reply::json(&data);
+
+        let response = compress(Some("gzip".into()), reply, 1024).await;
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_skip_compression() {
+        // too small to be worth compressing.
+        let reply = warp::reply::json(&serde_json::json!({ "id": 1 }));
+        let response = compress(Some("gzip".into()), reply, 1024).await;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+
+        // images and other binary data are never compressed.
+        let reply = warp::reply::with_header(vec![0u8; 4096], "content-type", "image/jpeg");
+        let response = compress(Some("gzip".into()), reply, 1024).await;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+
+        // the client doesnt accept any encoding we support.
+        let reply = warp::reply::json(&serde_json::json!({ "name": "a".repeat(4096) }));
+        let response = compress(None, reply, 1024).await;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+    }
+}
@@ -1,15 +1,18 @@
 use crate::balanced_or_tree;
+use crate::compression;
 use crate::logger::RequestLogger;
 use crate::routes;
 use crate::scanners;
 use crate::stream_tracking::StreamTracking;
+use crate::watch_party::WatchParties;
+use crate::webhooks::Webhooks;
 use crate::websocket;
 
 use once_cell::sync::OnceCell;
 
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::mpsc::UnboundedSender;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use warp::http::status::StatusCode;
 use warp::Filter;
@@ -19,6 +22,9 @@ use crate::routes::*;
 pub type StateManager = nightfall::StateManager;
 pub type DbConnection = database::DbConnection;
 pub type EventTx = UnboundedSender<String>;
+/// Sends a event only to the event sockets of the listed users, events sent this way arent
+/// mirrored to webhooks.
+pub type UserEventTx = UnboundedSender<(Vec<String>, String)>;
 
 /// Path to where metadata is stored and should be fetched to.
 pub static METADATA_PATH: OnceCell<String> = OnceCell::new();
@@ -49,7 +55,10 @@ pub async fn run_scanners(tx: EventTx) {
                     tx_clone.clone(),
                 );
 
-                tokio::spawn(scanners::start(conn.clone(), library_id, tx_clone.clone()));
+                let scan = scanners::start(conn.clone(), library_id, tx_clone.clone());
+                crate::tasks::submit(format!("Scan library {}", library_id), async move {
+                    let _ = scan.await;
+                });
                 tokio::spawn(async move {
                     watcher
                         .start_daemon()
@@ -71,10 +80,35 @@ pub async fn warp_core(
 ) {
     let state = stream_manager;
     let stream_tracking = StreamTracking::default();
+    let (party_tx, party_rx) = tokio::sync::mpsc::unbounded_channel();
+    let parties = WatchParties::new(party_tx.clone());
+    parties.start_expiry();
     let conn = database::get_conn()
         .await
         .expect("Failed to grab a handle to the connection pool.");
 
+    crate::progress_buffer::start(conn.clone());
+    crate::scrobble::start(conn.clone());
+
+    let webhooks = Webhooks::new(conn.clone()).await;
+
+    // mirror all events to the registered webhooks before they are relayed to the event socket.
+    let (socket_tx, socket_rx) = tokio::sync::mpsc::unbounded_channel();
+    {
+        let webhooks = webhooks.clone();
+        let mut event_rx = event_rx;
+
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                webhooks.dispatch(&event).await;
+
+                if socket_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     let request_logger = RequestLogger::new();
 
     let api_routes = balanced_or_tree![
@@ -82,6 +116,7 @@ pub async fn warp_core(
         /* /api/v1/auth and /user routes */
         auth::filters::login(conn.clone()),
         auth::filters::whoami(conn.clone()),
+        auth::filters::mint_scoped_token(),
         auth::filters::admin_exists(conn.clone()),
         auth::filters::register(conn.clone()),
         auth::filters::get_all_invites(conn.clone()),
@@ -91,40 +126,145 @@ pub async fn warp_core(
         auth::filters::user_delete_self(conn.clone()),
         auth::filters::user_change_username(conn.clone()),
         auth::filters::user_upload_avatar(conn.clone()),
+        auth::filters::user_me(conn.clone()),
+        auth::filters::get_playback_windows(conn.clone()),
+        auth::filters::set_playback_windows(conn.clone()),
+        auth::filters::set_scrobble_integration(conn.clone()),
+        auth::filters::delete_scrobble_integration(conn.clone()),
+        auth::filters::user_export(conn.clone()),
+        auth::filters::user_import(conn.clone()),
         /* general routes */
         routes::general::filters::search(conn.clone()),
+        routes::general::filters::parse_filename(),
+        routes::general::filters::get_tasks(),
+        routes::general::filters::cancel_task(),
+        routes::general::filters::health(conn.clone()),
+        routes::general::filters::clear_cache(conn.clone()),
+        routes::general::filters::prune_progress(conn.clone()),
+        routes::general::filters::purge_watched(conn.clone(), event_tx.clone()),
+        routes::general::filters::recompute_durations(conn.clone(), event_tx.clone()),
+        routes::general::filters::backfill_posters(conn.clone(), event_tx.clone()),
+        routes::general::filters::backfill_accent_colors(conn.clone(), event_tx.clone()),
+        routes::general::filters::backfill_captions(conn.clone(), event_tx.clone()),
+        routes::general::filters::reindex_search(conn.clone(), event_tx.clone()),
+        routes::webhook::filters::register_webhook(conn.clone(), webhooks.clone()),
+        routes::webhook::filters::get_webhooks(conn.clone()),
+        routes::webhook::filters::delete_webhook(conn.clone(), webhooks.clone()),
         routes::general::filters::get_directory_structure(),
         /* library routes */
         routes::library::filters::library_get(conn.clone()),
         routes::library::filters::library_post(conn.clone(), event_tx.clone()),
+        routes::library::filters::validate_path(),
         routes::library::filters::library_delete(conn.clone(), event_tx.clone()),
         routes::library::filters::library_get_self(conn.clone()),
+        routes::library::filters::library_rescan(conn.clone(), event_tx.clone()),
+        routes::library::filters::scan_status(),
         routes::library::filters::get_all_of_library(conn.clone()),
         routes::library::filters::get_all_unmatched_media(conn.clone()),
+        routes::library::filters::get_genre_stats(conn.clone()),
+        routes::library::filters::get_library_size(conn.clone()),
+        routes::library::filters::get_library_agent(conn.clone()),
+        routes::library::filters::set_library_agent(conn.clone()),
         /* dashboard routes */
         routes::dashboard::filters::dashboard(conn.clone(), rt.clone()),
+        routes::dashboard::filters::last_watched(conn.clone()),
+        routes::dashboard::filters::user_stats(conn.clone()),
         routes::dashboard::filters::banners(conn.clone()),
+        routes::dashboard::filters::home(conn.clone()),
         /* media routes */
+        routes::media::filters::get_duplicates(conn.clone()),
+        routes::media::filters::get_media_by_tmdb_id(conn.clone()),
+        routes::media::filters::merge_media(conn.clone(), event_tx.clone()),
+        routes::media::filters::merge_media_into(conn.clone(), event_tx.clone()),
         routes::media::filters::get_media_by_id(conn.clone()),
+        routes::media::filters::get_media_by_ids(conn.clone()),
         routes::media::filters::get_media_files(conn.clone()),
+        routes::media::filters::decide_playback(conn.clone()),
+        routes::media::filters::get_resume_points(conn.clone()),
+        routes::media::filters::get_media_videos(conn.clone()),
+        routes::media::filters::get_media_external_ids(conn.clone()),
+        routes::media::filters::get_metadata_diff(conn.clone()),
+        routes::media::filters::refresh_metadata(conn.clone()),
+        routes::media::filters::get_media_keywords(conn.clone()),
+        routes::media::filters::get_media_alternate_titles(conn.clone()),
+        routes::collection::filters::get_collection(conn.clone()),
+        routes::person::filters::get_person(conn.clone()),
+        routes::keyword::filters::get_keyword_media(conn.clone()),
+        routes::media::filters::add_media_tag(conn.clone()),
+        routes::media::filters::remove_media_tag(conn.clone()),
+        routes::tag::filters::get_tags(conn.clone()),
+        routes::tag::filters::get_tag_media(conn.clone()),
+        routes::playlist::filters::get_playlists(conn.clone()),
+        routes::playlist::filters::create_playlist(conn.clone()),
+        routes::playlist::filters::get_playlist(conn.clone()),
+        routes::playlist::filters::delete_playlist(conn.clone()),
+        routes::playlist::filters::add_playlist_item(conn.clone()),
+        routes::playlist::filters::remove_playlist_item(conn.clone()),
+        routes::playlist::filters::move_playlist_item(conn.clone()),
+        routes::media::filters::get_media_source_files(conn.clone()),
+        routes::media::filters::add_placeholder_media(conn.clone(), event_tx.clone()),
         routes::media::filters::update_media_by_id(conn.clone()),
+        routes::media::filters::batch_update_media(conn.clone()),
         routes::media::filters::delete_media_by_id(conn.clone()),
+        routes::media::filters::get_media_overrides(conn.clone()),
+        routes::media::filters::clear_media_overrides(conn.clone()),
         routes::media::filters::tmdb_search(),
-        routes::media::filters::map_progress(conn.clone()),
+        routes::media::filters::query_progress(conn.clone()),
+        routes::media::filters::needs_review(conn.clone()),
+        routes::media::filters::map_progress(conn.clone(), parties.clone()),
+        routes::media::filters::authorize_playback(conn.clone()),
+        routes::media::filters::rate_media(conn.clone()),
+        routes::media::filters::delete_rating(conn.clone()),
+        routes::media::filters::get_episode_progress(conn.clone()),
+        routes::media::filters::set_episode_watched(conn.clone(), party_tx.clone()),
+        routes::media::filters::set_episode_markers(conn.clone()),
+        routes::media::filters::get_media_stats(conn.clone()),
+        routes::media::filters::because_you_watched(conn.clone()),
+        routes::media::filters::get_random_media(conn.clone()),
+        routes::media::filters::get_in_progress_shows(conn.clone()),
+        routes::media::filters::get_flat_episodes(conn.clone()),
+        routes::media::filters::get_episode_orders(conn.clone()),
+        routes::media::filters::set_episode_order(conn.clone()),
+        routes::media::filters::get_hidden_media(conn.clone()),
+        routes::media::filters::hide_media(conn.clone()),
+        routes::media::filters::unhide_media(conn.clone()),
+        routes::media::filters::get_popular(conn.clone()),
+        routes::media::filters::get_added_between(conn.clone()),
+        routes::media::filters::get_recently_added(conn.clone()),
+        routes::media::filters::get_stale(conn.clone()),
+        routes::watch_party::filters::create_watch_party(conn.clone(), parties.clone()),
+        routes::watch_party::filters::join_watch_party(parties.clone()),
+        routes::watch_party::filters::leave_watch_party(parties.clone()),
+        routes::watch_party::filters::update_watch_party(parties.clone()),
+        routes::media::filters::get_movable_libraries(conn.clone()),
+        routes::media::filters::move_media_to_library(conn.clone(), event_tx.clone()),
         routes::rematch_media::filters::rematch_media_by_id(conn.clone(), event_tx.clone()),
         /* tv routes */
         routes::tv::filters::get_tv_seasons(conn.clone()),
+        routes::tv::filters::get_season_meta(conn.clone()),
+        routes::tv::filters::get_episode_map(conn.clone()),
+        routes::tv::filters::get_aired_episodes(conn.clone()),
         routes::tv::filters::patch_episode_by_id(conn.clone()),
         routes::tv::filters::delete_season_by_id(conn.clone()),
         routes::tv::filters::get_season_episodes(conn.clone()),
+        routes::tv::filters::get_episode_still(conn.clone()),
         routes::tv::filters::patch_episode_by_id(conn.clone()),
         routes::tv::filters::delete_episode_by_id(conn.clone()),
         /* mediafile routes */
         routes::mediafile::filters::get_mediafile_info(conn.clone()),
+        routes::mediafile::filters::get_mediafile_chapters(conn.clone()),
         routes::mediafile::filters::rematch_mediafile(conn.clone()),
+        routes::mediafile::filters::get_orphans(conn.clone()),
+        routes::mediafile::filters::attach_mediafile(conn.clone(), event_tx.clone()),
+        routes::mediafile::filters::search_subtitles(conn.clone()),
+        routes::mediafile::filters::download_subtitles(conn.clone()),
+        routes::mediafile::filters::stream_mediafile(conn.clone()),
+        routes::mediafile::filters::get_stream_url(conn.clone()),
         /* settings routes */
         routes::settings::filters::get_user_settings(conn.clone()),
         routes::settings::filters::post_user_settings(conn.clone()),
+        routes::settings::filters::get_user_preferences(conn.clone()),
+        routes::settings::filters::put_user_preferences(conn.clone()),
         routes::settings::filters::get_global_settings(),
         routes::settings::filters::set_global_settings(),
         /* stream routes */
@@ -153,6 +293,17 @@ pub async fn warp_core(
     ]
     .recover(routes::global_filters::handle_rejection);
 
+    // Compression settings are read on every request so that changes made through the settings
+    // route apply without a restart.
+    let api_routes = warp::header::optional::<String>("accept-encoding")
+        .and(api_routes)
+        .and_then(|accept_encoding: Option<String>, reply| async move {
+            let settings = crate::get_global_settings();
+            let accept_encoding = accept_encoding.filter(|_| settings.compress_responses);
+            let min_size = settings.compression_min_size;
+            Ok::<_, warp::Rejection>(compression::compress(accept_encoding, reply, min_size).await)
+        });
+
     cfg_if::cfg_if! {
         if #[cfg(debug_assertions)] {
             let api_routes = api_routes.boxed();
@@ -162,10 +313,15 @@ pub async fn warp_core(
     let routes = balanced_or_tree![
         api_routes,
         /* NOTE: This is a barrier to 404 any rest api calls that dont match till here */
-        routes::global_filters::api_not_found(),
+        routes::catchers::api_not_found(),
         /* websocket route */
-        websocket::event_socket(tokio::runtime::Handle::current(), event_rx)
-            .recover(routes::global_filters::handle_rejection),
+        websocket::event_socket(
+            tokio::runtime::Handle::current(),
+            socket_rx,
+            party_rx,
+            parties.clone(),
+        )
+        .recover(routes::global_filters::handle_rejection),
         /* static routes */
         routes::statik::filters::dist_static(),
         routes::statik::filters::get_image(conn.clone()),
@@ -182,8 +338,70 @@ pub async fn warp_core(
 
     tokio::select! {
         _ = warp::serve(routes).run(([0, 0, 0, 0], port)) => {},
-        _ = tokio::signal::ctrl_c() => {
+        _ = terminate_signal() => {
+            shutdown().await;
             std::process::exit(0);
         }
     }
 }
+
+/// Function resolves once we are asked to exit, either by ctrl-c or by SIGTERM, ie from
+/// `docker stop` or a service manager.
+async fn terminate_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::signal;
+        use tokio::signal::unix::SignalKind;
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = sigterm.recv() => {},
+                }
+            }
+            Err(e) => {
+                warn!(reason = ?e, "Failed to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Function drains the background task pool before we exit, so that scans and rematches arent
+/// killed in the middle of writing metadata.
+async fn shutdown() {
+    let timeout = crate::routes::settings::get_global_settings().shutdown_timeout_secs;
+    info!(
+        timeout_secs = timeout,
+        "Shutting down, draining background tasks"
+    );
+
+    let report = crate::tasks::shutdown(std::time::Duration::from_secs(timeout)).await;
+
+    for task in report.drained.iter() {
+        info!(task = %task.name, "Drained task");
+    }
+
+    for task in report.dropped.iter() {
+        info!(task = %task.name, "Dropped queued task");
+    }
+
+    for task in report.aborted.iter() {
+        warn!(task = %task.name, "Aborted task that didnt finish in time");
+    }
+
+    info!(
+        drained = report.drained.len(),
+        dropped = report.dropped.len(),
+        aborted = report.aborted.len(),
+        "Background tasks shut down",
+    );
+
+    if let Ok(conn) = database::get_conn().await {
+        crate::progress_buffer::shutdown(&conn).await;
+    }
+}
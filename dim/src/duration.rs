@@ -0,0 +1,99 @@
+//! Human readable durations of media as returned in `duration_pretty`.
+//!
+//! How a duration is formatted depends on the media type, ie for tv shows the duration is that of
+//! a single episode. The style can be overridden per library through the `duration_styles` global
+//! setting.
+use database::library::MediaType;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Trait implemented by everything that can turn a duration in seconds into a human readable
+/// string.
+pub trait DurationFormatter: Send + Sync {
+    fn format(&self, duration: i64) -> String;
+}
+
+/// Styles in which durations can be formatted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationStyle {
+    /// Hours and minutes, ie `1h 52m`.
+    Runtime,
+    /// Total minutes, ie `112 min`.
+    Minutes,
+    /// Hours, minutes and seconds, ie `1:52:03`.
+    Clock,
+}
+
+impl Default for DurationStyle {
+    fn default() -> Self {
+        Self::Runtime
+    }
+}
+
+impl DurationFormatter for DurationStyle {
+    fn format(&self, duration: i64) -> String {
+        let duration = duration.max(0);
+        let (hours, minutes, seconds) = (duration / 3600, duration % 3600 / 60, duration % 60);
+
+        match self {
+            Self::Runtime if hours > 0 => format!("{}h {}m", hours, minutes),
+            Self::Runtime => format!("{}m", minutes),
+            Self::Minutes => format!("{} min", duration / 60),
+            Self::Clock if hours > 0 => format!("{}:{:02}:{:02}", hours, minutes, seconds),
+            Self::Clock => format!("{}:{:02}", minutes, seconds),
+        }
+    }
+}
+
+/// Formatter used for tv shows whose duration is that of a single episode.
+pub struct PerEpisode<T>(pub T);
+
+impl<T: DurationFormatter> DurationFormatter for PerEpisode<T> {
+    fn format(&self, duration: i64) -> String {
+        format!("{} per episode", self.0.format(duration))
+    }
+}
+
+/// Returns the formatter to use for media of `media_type`. `style` is the style configured for
+/// the library of the media, if any.
+pub fn formatter_for(
+    media_type: MediaType,
+    style: Option<DurationStyle>,
+) -> Box<dyn DurationFormatter> {
+    let style = style.unwrap_or_default();
+
+    match media_type {
+        MediaType::Movie | MediaType::Episode => Box::new(style),
+        MediaType::Tv => Box::new(PerEpisode(style)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styles() {
+        assert_eq!(DurationStyle::Runtime.format(6723), "1h 52m");
+        assert_eq!(DurationStyle::Runtime.format(2700), "45m");
+        assert_eq!(DurationStyle::Minutes.format(6723), "112 min");
+        assert_eq!(DurationStyle::Clock.format(6723), "1:52:03");
+        assert_eq!(DurationStyle::Clock.format(2705), "45:05");
+    }
+
+    #[test]
+    fn test_formatter_for() {
+        assert_eq!(formatter_for(MediaType::Movie, None).format(6723), "1h 52m");
+        assert_eq!(formatter_for(MediaType::Episode, None).format(2700), "45m");
+        assert_eq!(
+            formatter_for(MediaType::Tv, None).format(2700),
+            "45m per episode"
+        );
+        assert_eq!(
+            formatter_for(MediaType::Tv, Some(DurationStyle::Minutes)).format(2700),
+            "45 min per episode"
+        );
+    }
+}
@@ -4,6 +4,8 @@ use serde::Serialize;
 use serde_json::json;
 
 use crate::scanners::base::ScannerError;
+use crate::scanners::tmdb::TmdbError;
+use crate::subtitles::SubtitleError;
 use nightfall::error::NightfallError;
 
 use http::StatusCode;
@@ -31,6 +33,8 @@ pub enum DimError {
     StreamingError(#[error(source)] StreamingErrors),
     #[error(display = "You do not have permission to access this route")]
     Unauthorized,
+    #[error(display = "Access to the requested resource is forbidden.")]
+    Forbidden,
     #[error(display = "A error has occured when matching.")]
     ScannerError(#[error(source)] ScannerError),
     #[error(display = "Upload failed.")]
@@ -47,6 +51,72 @@ pub enum DimError {
     InvalidCredentials,
     #[error(display = "Requested username is not available.")]
     UsernameNotAvailable,
+    #[error(display = "Rating must be between {} and {}.", min, max)]
+    InvalidRating { min: i64, max: i64 },
+    #[error(display = "The media type of the target library doesnt match.")]
+    LibraryTypeMismatch,
+    #[error(display = "A scan is already running for this library.")]
+    ScanInProgress,
+    #[error(display = "The metadata of this media is already being updated.")]
+    MediaLocked,
+    #[error(display = "This media hasnt been matched against TMDB.")]
+    NoTmdbId,
+    #[error(display = "TMDB did not respond in time.")]
+    TmdbUnavailable,
+    #[error(display = "The duration of this media is unknown.")]
+    UnknownDuration,
+    #[error(display = "At most {} items can be processed at once.", max)]
+    BatchTooLarge { max: usize },
+    #[error(display = "{}", reason)]
+    PlaybackNotAllowed { reason: String },
+    #[error(display = "Playback windows must lie within a single day.")]
+    InvalidPlaybackWindow,
+    #[error(display = "Invalid window supplied, options are [week, month, all].")]
+    InvalidWindow,
+    #[error(display = "Dates must be formatted as YYYY-MM-DD and `from` must not be after `to`.")]
+    InvalidDateRange,
+    #[error(display = "Markers must not be negative and the intro must end before the credits.")]
+    InvalidMarkers,
+    #[error(display = "The stream url is invalid or has expired.")]
+    InvalidStreamToken,
+    #[error(display = "This mediafile is already attached to a media.")]
+    MediaFileNotOrphan,
+    #[error(display = "No subtitle provider is configured.")]
+    NoSubtitleProvider,
+    #[error(display = "A error has occured with the subtitle provider.")]
+    SubtitleError(#[error(source)] SubtitleError),
+    #[error(display = "The field `{}` cannot be changed here.", field)]
+    ImmutableField { field: String },
+    #[error(display = "The confirmation token is missing or outdated, run a dry run first.")]
+    InvalidConfirmation,
+    #[error(display = "Tags must not be empty or longer than {} characters.", max)]
+    InvalidTag { max: usize },
+    #[error(display = "This token lacks the `{}` scope.", scope)]
+    MissingScope { scope: String },
+    #[error(display = "Invalid scopes supplied, options are [read:media, write:media, admin].")]
+    InvalidScopes,
+    #[error(display = "The search index is already being rebuilt.")]
+    ReindexInProgress,
+    #[error(display = "This show has no watch order named `{}`.", name)]
+    UnknownEpisodeOrder { name: String },
+    #[error(display = "Watch orders must be named and only hold episodes of the show once.")]
+    InvalidEpisodeOrder,
+    #[error(display = "Libraries must be located at a absolute path within the library roots.")]
+    LocationNotAllowed,
+    #[error(display = "Ids must be passed as a comma separated list of integers.")]
+    InvalidIds,
+    #[error(display = "Playlist names must be 1 to {} characters long.", max)]
+    InvalidPlaylistName { max: usize },
+    #[error(display = "Only movies and episodes can be added to playlists.")]
+    InvalidPlaylistMedia,
+    #[error(display = "A media cant be merged into itself.")]
+    InvalidMerge,
+    #[error(display = "The mediafile and the media belong to different libraries.")]
+    MediaFileLibraryMismatch,
+    #[error(display = "The task was dropped before it completed, ie on shutdown.")]
+    TaskDropped,
+    #[error(display = "Scrobbles can only be sent over https to the hosts allowed by the owner.")]
+    ScrobbleHostNotAllowed,
 }
 
 impl From<sqlx::Error> for DimError {
@@ -55,6 +125,24 @@ impl From<sqlx::Error> for DimError {
     }
 }
 
+impl From<TmdbError> for DimError {
+    fn from(e: TmdbError) -> Self {
+        match e {
+            TmdbError::Timeout => Self::TmdbUnavailable,
+            _ => Self::NotFoundError,
+        }
+    }
+}
+
+impl From<SubtitleError> for DimError {
+    fn from(e: SubtitleError) -> Self {
+        match e {
+            SubtitleError::NotFound => Self::NotFoundError,
+            e => Self::SubtitleError(e),
+        }
+    }
+}
+
 impl warp::reject::Reject for DimError {}
 
 impl warp::Reply for DimError {
@@ -72,20 +160,56 @@ impl warp::Reply for DimError {
             | Self::Unauthorized
             | Self::InvalidCredentials
             | Self::NoToken => StatusCode::UNAUTHORIZED,
-            Self::UsernameNotAvailable => StatusCode::BAD_REQUEST,
+            Self::UsernameNotAvailable
+            | Self::InvalidRating { .. }
+            | Self::BatchTooLarge { .. }
+            | Self::InvalidPlaybackWindow
+            | Self::InvalidWindow
+            | Self::InvalidDateRange
+            | Self::InvalidMarkers
+            | Self::InvalidConfirmation
+            | Self::InvalidTag { .. }
+            | Self::InvalidScopes
+            | Self::UnknownEpisodeOrder { .. }
+            | Self::InvalidEpisodeOrder
+            | Self::InvalidIds
+            | Self::InvalidPlaylistName { .. }
+            | Self::InvalidPlaylistMedia
+            | Self::InvalidMerge
+            | Self::MediaFileLibraryMismatch => StatusCode::BAD_REQUEST,
+            Self::PlaybackNotAllowed { .. }
+            | Self::Forbidden
+            | Self::InvalidStreamToken
+            | Self::MissingScope { .. }
+            | Self::LocationNotAllowed
+            | Self::ScrobbleHostNotAllowed => StatusCode::FORBIDDEN,
             Self::UnsupportedFile | Self::InvalidMediaType | Self::MissingFieldInBody { .. } => {
                 StatusCode::NOT_ACCEPTABLE
             }
+            Self::LibraryTypeMismatch
+            | Self::NoTmdbId
+            | Self::UnknownDuration
+            | Self::NoSubtitleProvider
+            | Self::ImmutableField { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::ScanInProgress
+            | Self::MediaLocked
+            | Self::MediaFileNotOrphan
+            | Self::ReindexInProgress => StatusCode::CONFLICT,
+            Self::TmdbUnavailable => StatusCode::GATEWAY_TIMEOUT,
+            Self::TaskDropped => StatusCode::SERVICE_UNAVAILABLE,
+            Self::SubtitleError(SubtitleError::RateLimited) => StatusCode::TOO_MANY_REQUESTS,
+            Self::SubtitleError(_) => StatusCode::BAD_GATEWAY,
         };
 
         let resp = json!({
             "error": json!(&self)["error"],
             "messsage": self.to_string(),
+            "status": status.as_u16(),
         });
 
         warp::http::Response::builder()
             .status(status)
-            .header("ContentType", "application/json")
+            .header(http::header::CONTENT_TYPE, "application/json")
             .body(serde_json::to_string(&resp).unwrap().into())
             .unwrap()
     }
@@ -115,6 +239,8 @@ pub enum StreamingErrors {
     FFProbeCtxFailed,
     #[error(display = "Could not parse the gid")]
     GidParseError,
+    #[error(display = "{}", _0)]
+    PlaybackNotAllowed(String),
 }
 
 impl From<sqlx::Error> for StreamingErrors {
@@ -130,6 +256,7 @@ impl warp::Reply for StreamingErrors {
         let status = match self {
             Self::OtherNightfall(NightfallError::ChunkNotDone) => StatusCode::PROCESSING,
             Self::NoMediaFileFound(_) => StatusCode::NOT_FOUND,
+            Self::PlaybackNotAllowed(_) => StatusCode::FORBIDDEN,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -0,0 +1,232 @@
+//! Idempotency keys for mutating endpoints.
+//!
+//! Clients retrying a request on a flaky network can't tell whether the first attempt was
+//! applied. If they send a `Idempotency-Key` header, the first successful response for a key is
+//! cached and replayed for every retry with the same key within [`TTL`](TTL), instead of applying
+//! the mutation again. Keys are scoped to the user sending them and to the method and path of the
+//! request, thus reusing a key for another endpoint or media doesnt replay a unrelated response.
+//!
+//! Endpoints honoring the header:
+//! * `POST /api/v1/media`
+//! * `PATCH /api/v1/media/<id>`
+//! * `PATCH /api/v1/media/batch`
+//! * `POST /api/v1/media/<id>/merge`
+//! * `POST /api/v1/media/<id>/refresh`
+//! * `PATCH /api/v1/media/<id>/library`
+//! * `POST /api/v1/media/<id>/rate`
+//! * `POST /api/v1/media/episode/<id>/watched`
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+
+use warp::http::header::HeaderMap;
+use warp::http::header::HeaderValue;
+use warp::http::Method;
+use warp::http::StatusCode;
+use warp::hyper::body::Body;
+use warp::hyper::body::Bytes;
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::Filter;
+use warp::Reply;
+
+use crate::errors::DimError;
+
+/// Name of the header carrying the idempotency key.
+pub const HEADER: &str = "idempotency-key";
+
+/// How long responses are replayed for.
+pub const TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A successful response cached for a key.
+#[derive(Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+impl CachedResponse {
+    fn is_expired(&self) -> bool {
+        self.stored_at.elapsed() >= TTL
+    }
+
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert("idempotent-replayed", HeaderValue::from_static("true"));
+
+        response
+    }
+}
+
+/// Slot holding the response for a key. The slot is locked while the first request is in flight
+/// so that retries arriving in the meantime wait for its response rather than running again.
+type Slot = Arc<tokio::sync::Mutex<Option<CachedResponse>>>;
+
+/// Cached responses keyed by user and idempotency key.
+static RESPONSES: Lazy<Mutex<HashMap<(String, String), Slot>>> = Lazy::new(Default::default);
+
+/// Filter extracting the idempotency key of a request, if any, scoped to the method and path of
+/// the request.
+pub fn key() -> impl Filter<Extract = (Option<String>,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(HEADER)
+        .and(warp::method())
+        .and(warp::path::full())
+        .map(|key: Option<String>, method: Method, path: FullPath| {
+            key.filter(|x| !x.is_empty())
+                .map(|x| format!("{} {} {}", method, path.as_str(), x))
+        })
+}
+
+/// Runs `handler` unless a response has already been cached for `key` and `user`, in which case
+/// the cached response is returned instead. Only successful responses are cached, thus failed
+/// requests can be retried with the same key. Requests without a key always run `handler`.
+pub async fn run<F, Fut, R>(
+    key: Option<String>,
+    user: &str,
+    handler: F,
+) -> Result<Response, DimError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<R, DimError>>,
+    R: Reply,
+{
+    let key = match key.filter(|x| !x.is_empty()) {
+        Some(x) => x,
+        None => return handler().await.map(Reply::into_response),
+    };
+
+    let slot = {
+        let mut responses = RESPONSES.lock().unwrap();
+        // forget about expired responses nobody is waiting on.
+        responses.retain(|_, slot| {
+            Arc::strong_count(slot) > 1
+                || slot
+                    .try_lock()
+                    .map_or(true, |x| x.as_ref().map_or(false, |x| !x.is_expired()))
+        });
+
+        responses
+            .entry((user.to_string(), key))
+            .or_default()
+            .clone()
+    };
+
+    let mut cached = slot.lock().await;
+
+    if let Some(response) = cached.as_ref().filter(|x| !x.is_expired()) {
+        return Ok(response.to_response());
+    }
+
+    let response = handler().await?.into_response();
+
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = warp::hyper::body::to_bytes(body)
+        .await
+        .map_err(|_| DimError::InternalServerError)?;
+
+    *cached = Some(CachedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+        stored_at: Instant::now(),
+    });
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    async fn rate(calls: &AtomicUsize) -> Result<impl Reply, DimError> {
+        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(warp::reply::json(&serde_json::json!({ "calls": n })))
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let calls = AtomicUsize::new(0);
+
+        let first = run(Some("a".into()), "test", || rate(&calls))
+            .await
+            .unwrap();
+        let retry = run(Some("a".into()), "test", || rate(&calls))
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(retry.headers().contains_key("idempotent-replayed"));
+        assert_eq!(body(first).await, body(retry).await);
+
+        // keys are scoped to users and requests without a key are never cached.
+        run(Some("a".into()), "other", || rate(&calls))
+            .await
+            .unwrap();
+        run(None, "test", || rate(&calls)).await.unwrap();
+        run(None, "test", || rate(&calls)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    async fn scoped(method: &str, path: &str) -> Option<String> {
+        warp::test::request()
+            .method(method)
+            .path(path)
+            .header(HEADER, "a")
+            .filter(&key())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_key_scoped_to_request() {
+        let rate = scoped("POST", "/api/v1/media/1/rate").await;
+        assert_eq!(rate, scoped("POST", "/api/v1/media/1/rate").await);
+        assert_ne!(rate, scoped("POST", "/api/v1/media/2/rate").await);
+        assert_ne!(rate, scoped("PATCH", "/api/v1/media/1/rate").await);
+
+        let result = warp::test::request().filter(&key()).await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn test_errors_arent_cached() {
+        let calls = AtomicUsize::new(0);
+
+        let result = run(Some("b".into()), "test", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<StatusCode, _>(DimError::MediaLocked)
+        })
+        .await;
+        assert!(result.is_err());
+
+        run(Some("b".into()), "test", || rate(&calls))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,193 @@
+//! Size variants of posters and backdrops, so clients can fetch a image sized for where it is
+//! shown rather than always downloading the full size image.
+//!
+//! Images matched from TMDB link to TMDB directly for the sizes it serves. Other images, ie custom
+//! or fallback posters, are scaled down on demand by `GET /images/<path>?w=<width>` and cached in
+//! the metadata directory. The widths offered are set with `poster_sizes` and `backdrop_sizes` in
+//! the global settings.
+use std::collections::BTreeMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use database::asset::TMDB_IMAGE_URL;
+
+use tokio::task::spawn_blocking;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Widths posters are offered in by default, matching the common sizes of TMDB.
+pub const DEFAULT_POSTER_SIZES: &[u32] = &[185, 500];
+
+/// Widths backdrops are offered in by default, matching the common sizes of TMDB.
+pub const DEFAULT_BACKDROP_SIZES: &[u32] = &[780, 1280];
+
+/// Widths TMDB serves images in, other widths are always scaled down by us.
+const TMDB_SIZES: &[u32] = &[92, 154, 185, 300, 342, 500, 780, 1280];
+
+/// Directory in the metadata directory scaled down images are cached in.
+const CACHE_DIR: &str = "variants";
+
+/// Returns the urls of `local_path`, a image served under `images/`, keyed by size, ie `w185` and
+/// `original`. Images with a `image_path` on TMDB link to TMDB for the sizes it serves.
+pub fn variants(
+    local_path: &str,
+    image_path: Option<&str>,
+    sizes: &[u32],
+) -> BTreeMap<String, String> {
+    let mut result = sizes
+        .iter()
+        .map(|width| {
+            let url = match image_path {
+                Some(x) if TMDB_SIZES.contains(width) => {
+                    format!("{}w{}{}", TMDB_IMAGE_URL, width, x)
+                }
+                _ => format!("{}?w={}", local_path, width),
+            };
+
+            (format!("w{}", width), url)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let original = match image_path {
+        Some(x) => format!("{}original{}", TMDB_IMAGE_URL, x),
+        None => local_path.to_string(),
+    };
+
+    result.insert("original".into(), original);
+    result
+}
+
+/// Returns whether images are offered in `width` according to the global settings. Other widths
+/// are not scaled down to keep the cache from growing unbounded.
+pub fn is_offered(width: u32) -> bool {
+    let settings = crate::routes::settings::get_global_settings();
+
+    settings.poster_sizes.contains(&width) || settings.backdrop_sizes.contains(&width)
+}
+
+/// Returns where the copy of `path`, relative to the metadata directory, scaled down to `width` is
+/// cached. Returns `None` for paths escaping the metadata directory and images that cant be
+/// scaled, ie svg placeholders.
+fn cache_path(meta_path: &str, path: &str, width: u32) -> Option<PathBuf> {
+    let path = Path::new(path);
+
+    if !path.components().all(|x| matches!(x, Component::Normal(_))) {
+        return None;
+    }
+
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("jpg") | Some("jpeg") | Some("png") => {}
+        _ => return None,
+    }
+
+    Some(
+        Path::new(meta_path)
+            .join(CACHE_DIR)
+            .join(format!("w{}", width))
+            .join(path),
+    )
+}
+
+/// Returns the path of a copy of `path`, relative to the metadata directory, scaled down to
+/// `width`. The copy is generated with ffmpeg on first request and cached afterwards. Images
+/// narrower than `width` are never scaled up. Returns `None` if the image doesnt exist or cant be
+/// scaled.
+///
+/// ffmpeg writes into a temporary file which is only renamed into place once it is complete, thus
+/// concurrent requests never serve a half written copy.
+pub async fn resized(meta_path: &str, path: &str, width: u32) -> Option<PathBuf> {
+    let target = cache_path(meta_path, path, width)?;
+
+    if target.exists() {
+        return Some(target);
+    }
+
+    let source = Path::new(meta_path).join(path);
+
+    if !source.exists() {
+        return None;
+    }
+
+    if let Some(parent) = target.parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+
+    // the extension is kept as ffmpeg picks the output format from it.
+    let ext = target.extension()?.to_str()?.to_string();
+    let temp = target.with_extension(format!("{}.{}", Uuid::new_v4().to_simple(), ext));
+
+    let output_path = temp.clone();
+    let result = spawn_blocking(move || {
+        Command::new(*crate::streaming::FFMPEG_BIN)
+            .arg("-i")
+            .arg(&source)
+            .arg("-vf")
+            .arg(format!("scale='min({},iw)':-1", width))
+            .arg("-q:v")
+            .arg("3")
+            .arg("-y")
+            .arg(&output_path)
+            .output()
+    })
+    .await;
+
+    if !matches!(result, Ok(Ok(ref x)) if x.status.success()) {
+        warn!(
+            path = path,
+            width = width,
+            "Failed to scale image with ffmpeg"
+        );
+        let _ = tokio::fs::remove_file(&temp).await;
+        return None;
+    }
+
+    if let Err(e) = tokio::fs::rename(&temp, &target).await {
+        warn!(reason = ?e, path = path, "Failed to move scaled image into place");
+        let _ = tokio::fs::remove_file(&temp).await;
+        return None;
+    }
+
+    Some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cache_path;
+    use super::variants;
+
+    #[test]
+    fn test_variants_of_tmdb_image() {
+        let result = variants("images/abc.jpg", Some("/abc.jpg"), &[185, 400]);
+
+        assert_eq!(result["w185"], "https://image.tmdb.org/t/p/w185/abc.jpg");
+        // TMDB doesnt serve this width, thus it is scaled down by us.
+        assert_eq!(result["w400"], "images/abc.jpg?w=400");
+        assert_eq!(
+            result["original"],
+            "https://image.tmdb.org/t/p/original/abc.jpg"
+        );
+    }
+
+    #[test]
+    fn test_variants_of_local_image() {
+        let result = variants("images/frames/1.jpg", None, &[185, 500]);
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result["w185"], "images/frames/1.jpg?w=185");
+        assert_eq!(result["w500"], "images/frames/1.jpg?w=500");
+        assert_eq!(result["original"], "images/frames/1.jpg");
+    }
+
+    #[test]
+    fn test_cache_path() {
+        assert_eq!(
+            cache_path("/meta", "frames/1.jpg", 185),
+            Some("/meta/variants/w185/frames/1.jpg".into())
+        );
+        assert_eq!(cache_path("/meta", "placeholders/1.svg", 185), None);
+        assert_eq!(cache_path("/meta", "../1.jpg", 185), None);
+        assert_eq!(cache_path("/meta", "/etc/1.jpg", 185), None);
+    }
+}
@@ -24,26 +24,54 @@ use tracing_subscriber::fmt;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::EnvFilter;
 
+/// Dominant colors of posters used to tint the UI.
+pub mod accent_color;
+/// Compression of JSON responses.
+pub mod compression;
 /// Module contains our core initialization logic.
 pub mod core;
+/// Human readable durations of media.
+pub mod duration;
 /// Module contains all the error definitions used in dim, and returned by the web-service.
 pub mod errors;
 /// Contains the code for fetching assets like posters and stills.
 pub mod fetcher;
+/// Idempotency keys for mutating endpoints.
+pub mod idempotency;
+/// Size variants of posters and backdrops.
+pub mod image_variants;
 /// Contains our custom logger for rocket
 pub mod logger;
+/// Server side decisions on whether media can be direct played.
+pub mod playback;
+/// Generation of fallback posters for media without a TMDB poster.
+pub mod posters;
+/// Write-behind buffer for playback progress.
+pub mod progress_buffer;
 /// Contains all of the routes exposed by the webapi.
 pub mod routes;
 /// Contains our media scanners and so on.
 pub mod scanners;
+/// Scrobbling of watch status to Trakt-like services.
+pub mod scrobble;
+/// Full-text search over the names of media.
+pub mod search_index;
 /// Contains the fairing which tracks streams across rest api
 pub mod stream_tracking;
 /// Contains all the logic needed for streaming and on-the-fly transcoding.
 pub mod streaming;
+/// Online subtitle providers.
+pub mod subtitles;
+/// Bounded pool on which background scans and rematches are executed.
+pub mod tasks;
 #[cfg(test)]
 mod tests;
 /// Various utilities
 pub mod utils;
+/// In-memory watch parties used for synchronized viewing.
+pub mod watch_party;
+/// Outbound webhooks mirroring the events sent over the event socket.
+pub mod webhooks;
 /// Websocket related logic.
 pub mod websocket;
 
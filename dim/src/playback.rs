@@ -0,0 +1,216 @@
+//! Server side playback decisions.
+//!
+//! Clients describe what they can decode and we tell them, per version of a media, whether the
+//! file can be played as is or has to be transcoded. Keeping this logic here means clients dont
+//! have to second guess the codec information we collected during scans.
+use database::mediafile::MediaFile;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// What a client is able to play. Lists left empty are not checked, ie a client that only sends
+/// `video_codecs` is assumed to handle any container and audio codec.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClientCapabilities {
+    /// Video codecs the client can decode, ie `h264` or `hevc`.
+    pub video_codecs: Vec<String>,
+    /// Audio codecs the client can decode, ie `aac` or `ac3`.
+    pub audio_codecs: Vec<String>,
+    /// Containers the client can demux, ie `mp4` or `matroska`.
+    pub containers: Vec<String>,
+    /// Tallest resolution the client can display, in pixels.
+    pub max_height: Option<i64>,
+    /// Whether the client can display HDR video. Otherwise HDR files have to be tone mapped.
+    pub hdr: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Decision {
+    DirectPlay,
+    Transcode,
+}
+
+/// Decision for a single version of a media.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct VersionDecision {
+    pub mediafile_id: i64,
+    pub decision: Decision,
+    /// Why the file has to be transcoded, empty for direct play.
+    pub reasons: Vec<String>,
+    pub codec: Option<String>,
+    pub audio: Option<String>,
+    pub container: Option<String>,
+    pub height: Option<i64>,
+}
+
+/// Returns whether `value` is in `supported`, ignoring case. Containers reported by ffprobe can
+/// name several formats, ie `mov,mp4,m4a,3gp,3g2,mj2`, of which any needs to match.
+fn supports(supported: &[String], value: &str) -> bool {
+    value
+        .split(',')
+        .any(|x| supported.iter().any(|y| y.eq_ignore_ascii_case(x.trim())))
+}
+
+/// Checks `value` of the field `what` against the list of supported values, pushing a reason to
+/// `reasons` if it isnt supported.
+fn check(reasons: &mut Vec<String>, what: &str, supported: &[String], value: Option<&str>) {
+    if supported.is_empty() {
+        return;
+    }
+
+    match value {
+        Some(x) if supports(supported, x) => {}
+        Some(x) => reasons.push(format!("{} `{}` is not supported", what, x)),
+        None => reasons.push(format!("{} is unknown", what)),
+    }
+}
+
+/// Decides whether `file` can be direct played by a client with `caps`.
+pub fn decide(file: &MediaFile, caps: &ClientCapabilities) -> VersionDecision {
+    let mut reasons = Vec::new();
+    let height = file.quality.as_deref().and_then(|x| x.parse::<i64>().ok());
+
+    check(
+        &mut reasons,
+        "video codec",
+        &caps.video_codecs,
+        file.codec.as_deref(),
+    );
+    check(
+        &mut reasons,
+        "audio codec",
+        &caps.audio_codecs,
+        file.audio.as_deref(),
+    );
+    check(
+        &mut reasons,
+        "container",
+        &caps.containers,
+        file.container.as_deref(),
+    );
+
+    if let (Some(max), Some(height)) = (caps.max_height, height) {
+        if height > max {
+            reasons.push(format!("resolution {}p exceeds {}p", height, max));
+        }
+    }
+
+    if file.is_hdr() && !caps.hdr {
+        reasons.push("HDR is not supported".into());
+    }
+
+    VersionDecision {
+        mediafile_id: file.id,
+        decision: if reasons.is_empty() {
+            Decision::DirectPlay
+        } else {
+            Decision::Transcode
+        },
+        reasons,
+        codec: file.codec.clone(),
+        audio: file.audio.clone(),
+        container: file.container.clone(),
+        height,
+    }
+}
+
+/// Decides every version of a media, ordered such that the recommended version comes first.
+/// Versions that can be direct played are preferred, then those needing the fewest changes, then
+/// the tallest resolution.
+pub fn decide_all(files: &[MediaFile], caps: &ClientCapabilities) -> Vec<VersionDecision> {
+    let mut decisions = files.iter().map(|x| decide(x, caps)).collect::<Vec<_>>();
+
+    decisions.sort_by_key(|x| {
+        (
+            x.reasons.len(),
+            std::cmp::Reverse(x.height.unwrap_or(0)),
+            x.mediafile_id,
+        )
+    });
+
+    decisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: i64, codec: &str, container: &str, height: i64, hdr: Option<&str>) -> MediaFile {
+        MediaFile {
+            id,
+            media_id: Some(1),
+            library_id: 1,
+            target_file: format!("/movies/{}.mkv", id),
+            raw_name: "movie".into(),
+            raw_year: None,
+            quality: Some(height.to_string()),
+            codec: Some(codec.into()),
+            container: Some(container.into()),
+            audio: Some("aac".into()),
+            original_resolution: None,
+            duration: None,
+            episode: None,
+            season: None,
+            corrupt: None,
+            channels: None,
+            profile: None,
+            audio_language: None,
+            episode_end: None,
+            hdr: hdr.map(Into::into),
+            has_cc: false,
+            has_sdh: false,
+            size: None,
+        }
+    }
+
+    fn caps() -> ClientCapabilities {
+        ClientCapabilities {
+            video_codecs: vec!["h264".into()],
+            audio_codecs: vec!["AAC".into()],
+            containers: vec!["mp4".into()],
+            max_height: Some(1080),
+            hdr: false,
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let decision = decide(
+            &file(1, "h264", "mov,mp4,m4a,3gp,3g2,mj2", 1080, None),
+            &caps(),
+        );
+        assert_eq!(decision.decision, Decision::DirectPlay);
+        assert!(decision.reasons.is_empty());
+
+        let decision = decide(
+            &file(2, "hevc", "matroska,webm", 2160, Some("hdr10")),
+            &caps(),
+        );
+        assert_eq!(decision.decision, Decision::Transcode);
+        assert_eq!(decision.reasons.len(), 4);
+
+        // nothing is checked if the client didnt tell us what it supports.
+        let decision = decide(
+            &file(3, "hevc", "matroska,webm", 2160, Some("sdr")),
+            &ClientCapabilities::default(),
+        );
+        assert_eq!(decision.decision, Decision::DirectPlay);
+    }
+
+    #[test]
+    fn test_decide_all() {
+        let files = vec![
+            file(1, "hevc", "mp4", 2160, None),
+            file(2, "h264", "mp4", 720, None),
+            file(3, "h264", "mp4", 1080, None),
+        ];
+
+        let decisions = decide_all(&files, &caps());
+        let ids = decisions.iter().map(|x| x.mediafile_id).collect::<Vec<_>>();
+
+        assert_eq!(ids, vec![3, 2, 1]);
+        assert_eq!(decisions[2].decision, Decision::Transcode);
+    }
+}
@@ -0,0 +1,149 @@
+//! Generation of fallback posters for media which have no poster on TMDB.
+//!
+//! Fallback posters are resolved by [`Media::poster_url`](database::media::Media::poster_url)
+//! following the `poster_fallback` chain in the global settings. They are only generated once
+//! their url is requested and are cached in the metadata directory afterwards. If no frame can be
+//! extracted a placeholder is served in its place. The placeholder is stored next to where the
+//! frame would have been, which also keeps us from trying to extract the frame again on every
+//! request. Deleting it retries the extraction.
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use database::library::MediaType;
+use database::media::Media;
+use database::mediafile::MediaFile;
+
+use tokio::task::spawn_blocking;
+use tracing::warn;
+
+/// Width and height of generated placeholders, matching the aspect ratio of TMDB posters.
+const PLACEHOLDER_SIZE: (u32, u32) = (600, 900);
+
+/// Generates the fallback poster served under `images/<path>` if `path` points at one. Returns
+/// the path of the poster to serve, which is a placeholder if no frame could be extracted.
+pub async fn generate(tx: &mut database::Transaction<'_>, path: &str) -> Option<PathBuf> {
+    let (kind, file_name) = path.split_once('/')?;
+    let id = file_name
+        .rsplit_once('.')
+        .and_then(|(id, _)| id.parse::<i64>().ok())?;
+
+    let media = Media::get(&mut *tx, id).await.ok()?;
+    let meta_path = crate::core::METADATA_PATH.get().unwrap();
+    let local_path = format!("{}/{}", meta_path, path);
+
+    if let Some(parent) = Path::new(&local_path).parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+
+    match kind {
+        "frames" => {
+            // files that cant be decoded, ie because they are corrupt, still get a poster.
+            let placeholder = format!("{}/frames/{}.svg", meta_path, id);
+
+            if Path::new(&placeholder).exists() {
+                return Some(placeholder.into());
+            }
+
+            let mediafiles = match media.media_type {
+                MediaType::Tv => MediaFile::get_of_show(&mut *tx, id).await,
+                _ => MediaFile::get_of_media(&mut *tx, id).await,
+            };
+
+            if let Some(mediafile) = mediafiles.ok().and_then(|x| x.into_iter().next()) {
+                if extract_frame(mediafile, local_path.clone()).await {
+                    return Some(local_path.into());
+                }
+            }
+
+            write_placeholder(&placeholder, &media.name).await
+        }
+        "placeholders" => write_placeholder(&local_path, &media.name).await,
+        _ => None,
+    }
+}
+
+/// Writes the placeholder poster of a media titled `title` to `local_path` unless it exists
+/// already.
+async fn write_placeholder(local_path: &str, title: &str) -> Option<PathBuf> {
+    let path = Path::new(local_path);
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+
+        tokio::fs::write(path, render_placeholder(title))
+            .await
+            .ok()?;
+    }
+
+    Some(path.to_path_buf())
+}
+
+/// Extracts a single frame 10% into `mediafile` to skip over cold opens and black frames.
+async fn extract_frame(mediafile: MediaFile, local_path: String) -> bool {
+    let offset = mediafile.duration.map(|x| x / 10).unwrap_or(0);
+    let output_path = local_path.clone();
+    let mediafile_id = mediafile.id;
+
+    let result = spawn_blocking(move || {
+        Command::new(*crate::streaming::FFMPEG_BIN)
+            .arg("-ss")
+            .arg(offset.to_string())
+            .arg("-i")
+            .arg(&mediafile.target_file)
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-q:v")
+            .arg("3")
+            .arg("-y")
+            .arg(&output_path)
+            .output()
+    })
+    .await;
+
+    if !matches!(result, Ok(Ok(ref x)) if x.status.success()) {
+        warn!(
+            mediafile_id = mediafile_id,
+            "Failed to extract poster frame with ffmpeg"
+        );
+    }
+
+    Path::new(&local_path).exists()
+}
+
+/// Renders a svg poster showing `title` centered on a plain background.
+pub fn render_placeholder(title: &str) -> String {
+    let (width, height) = PLACEHOLDER_SIZE;
+
+    let escaped = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            r##"<rect width="100%" height="100%" fill="#1f1f1f"/>"##,
+            r##"<text x="50%" y="50%" fill="#e0e0e0" font-family="sans-serif" font-size="40" text-anchor="middle" dominant-baseline="middle">{title}</text>"##,
+            "</svg>"
+        ),
+        w = width,
+        h = height,
+        title = escaped
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_placeholder;
+
+    #[test]
+    fn test_render_placeholder_escapes_title() {
+        let svg = render_placeholder("Tom & Jerry <3");
+        assert!(svg.contains("Tom &amp; Jerry &lt;3"));
+        assert!(svg.starts_with("<svg"));
+    }
+}
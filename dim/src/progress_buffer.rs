@@ -0,0 +1,321 @@
+//! Write-behind buffer for playback progress.
+//!
+//! Clients report their progress every few seconds while playing, each report costing a write
+//! and a turn on the single writer connection. If `progress_flush_interval_secs` is set in the
+//! global settings, reports are instead coalesced per user and media in memory, only the latest
+//! offset is kept, and written to the database every interval as well as on shutdown.
+//!
+//! Buffered progress must never be hidden from reads. Point reads of the progress of a user
+//! through a media go through [`overlay`](overlay) which substitutes the buffered offset. Handlers
+//! that read progress through larger queries, ie continue watching, call
+//! [`flush_user`](flush_user) first so that the database is up to date for that user.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use database::progress::Progress;
+use database::user::User;
+use database::DbConnection;
+
+use once_cell::sync::Lazy;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::errors::DimError;
+
+/// Seconds between checks for buffered progress while buffering is disabled. Progress buffered
+/// before the buffer got disabled is still written out.
+const IDLE_INTERVAL_SECS: u64 = 5;
+
+static BUFFER: Lazy<ProgressBuffer> = Lazy::new(Default::default);
+
+/// Serializes flushes so that entries are written in the order they were taken.
+static FLUSH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(Default::default);
+
+type Key = (String, i64);
+
+/// Latest progress reported by a user for a media.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    delta: i64,
+    /// Unix timestamp of when the progress was reported.
+    populated: i64,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Progress that hasn't been written yet.
+    pending: HashMap<Key, Entry>,
+    /// Progress currently being written. Still visible to reads until the write is committed.
+    flushing: HashMap<Key, Entry>,
+}
+
+#[derive(Default)]
+pub struct ProgressBuffer {
+    entries: Mutex<Entries>,
+}
+
+impl ProgressBuffer {
+    /// Buffers the progress of `user` through `media_id`, replacing any progress buffered for it
+    /// before.
+    pub fn record(&self, user: &str, media_id: i64, delta: i64) {
+        let populated = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.entries
+            .lock()
+            .unwrap()
+            .pending
+            .insert((user.to_string(), media_id), Entry { delta, populated });
+    }
+
+    /// Returns the offset and timestamp of the progress buffered for `user` through `media_id`.
+    fn get(&self, user: &str, media_id: i64) -> Option<Entry> {
+        let entries = self.entries.lock().unwrap();
+        let key = (user.to_string(), media_id);
+
+        entries
+            .pending
+            .get(&key)
+            .or_else(|| entries.flushing.get(&key))
+            .copied()
+    }
+
+    /// Returns `progress` with its offset replaced by the buffered one, if any.
+    pub fn overlay(&self, progress: Progress) -> Progress {
+        match self.get(&progress.user_id, progress.media_id) {
+            Some(entry) => Progress {
+                delta: entry.delta,
+                populated: entry.populated,
+                ..progress
+            },
+            None => progress,
+        }
+    }
+
+    /// Returns the offset buffered for `user` through `media_id`, if any.
+    pub fn delta(&self, user: &str, media_id: i64) -> Option<i64> {
+        self.get(user, media_id).map(|x| x.delta)
+    }
+
+    /// Moves the pending entries, only those of `user` if set, to the entries being flushed and
+    /// returns them.
+    fn take(&self, user: Option<&str>) -> Vec<(Key, Entry)> {
+        let mut entries = self.entries.lock().unwrap();
+        let keys = entries
+            .pending
+            .keys()
+            .filter(|(x, _)| user.map_or(true, |user| x == user))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut taken = Vec::with_capacity(keys.len());
+        for key in keys {
+            let entry = entries.pending.remove(&key).unwrap();
+            entries.flushing.insert(key.clone(), entry);
+            taken.push((key, entry));
+        }
+
+        taken
+    }
+
+    /// Marks `taken` as written. If the write failed the entries are put back, unless newer
+    /// progress has been buffered for them in the meantime. Failed writes are always transient as
+    /// entries that can never be written are dropped by [`write`](ProgressBuffer::write).
+    fn finish(&self, taken: &[(Key, Entry)], written: bool) {
+        let mut entries = self.entries.lock().unwrap();
+
+        for (key, entry) in taken {
+            entries.flushing.remove(key);
+
+            if !written {
+                entries.pending.entry(key.clone()).or_insert(*entry);
+            }
+        }
+    }
+
+    /// Writes the buffered progress, only that of `user` if set, to the database. Returns the
+    /// number of entries written.
+    pub async fn flush(&self, conn: &DbConnection, user: Option<&str>) -> Result<usize, DimError> {
+        let _guard = FLUSH_LOCK.lock().await;
+
+        let taken = self.take(user);
+        if taken.is_empty() {
+            return Ok(0);
+        }
+
+        let result = Self::write(conn, &taken).await;
+        self.finish(&taken, result.is_ok());
+
+        result.map(|_| taken.len())
+    }
+
+    /// Writes `taken` in a single transaction. Entries whose user or media got deleted in the
+    /// meantime can never be written, they are dropped rather than failing the whole batch.
+    async fn write(conn: &DbConnection, taken: &[(Key, Entry)]) -> Result<(), DimError> {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+        let mut auto_mark_watched = HashMap::new();
+
+        for ((user, media_id), entry) in taken {
+            if !Progress::can_set(&mut tx, user, *media_id).await? {
+                warn!(
+                    user = %user,
+                    media_id = media_id,
+                    "Dropping buffered progress of a deleted user or media"
+                );
+                continue;
+            }
+
+            let record_play = match auto_mark_watched.get(user) {
+                Some(x) => *x,
+                // a user that cant be loaded is treated like a deleted one.
+                None => match User::get(&mut tx, user).await {
+                    Ok(x) => {
+                        let x = x.prefs.auto_mark_watched();
+                        auto_mark_watched.insert(user.clone(), x);
+                        x
+                    }
+                    Err(e) => {
+                        warn!(
+                            user = %user,
+                            reason = ?e,
+                            "Dropping buffered progress of a user that cant be loaded"
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            Progress::set_at(
+                &mut tx,
+                entry.delta,
+                user.clone(),
+                *media_id,
+                entry.populated,
+                record_play,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+/// Returns whether progress is buffered rather than written right away.
+pub fn is_enabled() -> bool {
+    crate::routes::settings::get_global_settings().progress_flush_interval_secs > 0
+}
+
+/// Buffers progress in the global buffer, see [`ProgressBuffer::record`](ProgressBuffer::record).
+pub fn record(user: &str, media_id: i64, delta: i64) {
+    BUFFER.record(user, media_id, delta)
+}
+
+/// See [`ProgressBuffer::overlay`](ProgressBuffer::overlay).
+pub fn overlay(progress: Progress) -> Progress {
+    BUFFER.overlay(progress)
+}
+
+/// See [`ProgressBuffer::delta`](ProgressBuffer::delta).
+pub fn delta(user: &str, media_id: i64) -> Option<i64> {
+    BUFFER.delta(user, media_id)
+}
+
+/// Writes all buffered progress to the database.
+pub async fn flush(conn: &DbConnection) -> Result<usize, DimError> {
+    BUFFER.flush(conn, None).await
+}
+
+/// Writes the buffered progress of `user` to the database.
+pub async fn flush_user(conn: &DbConnection, user: &str) -> Result<usize, DimError> {
+    BUFFER.flush(conn, Some(user)).await
+}
+
+/// Spawns the task flushing the buffer every `progress_flush_interval_secs`. The interval is
+/// read again after every flush, thus changes to the settings apply without a restart.
+pub fn start(conn: DbConnection) {
+    tokio::spawn(async move {
+        loop {
+            let interval =
+                match crate::routes::settings::get_global_settings().progress_flush_interval_secs {
+                    0 => IDLE_INTERVAL_SECS,
+                    x => x,
+                };
+
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            if let Err(e) = flush(&conn).await {
+                error!(reason = ?e, "Failed to flush buffered progress");
+            }
+        }
+    });
+}
+
+/// Writes all buffered progress before we exit so that it isn't lost.
+pub async fn shutdown(conn: &DbConnection) {
+    match flush(conn).await {
+        Ok(written) => info!(written = written, "Flushed buffered progress"),
+        Err(e) => error!(reason = ?e, "Failed to flush buffered progress on shutdown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(user: &str, media_id: i64, delta: i64) -> Progress {
+        Progress {
+            delta,
+            media_id,
+            user_id: user.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let buffer = ProgressBuffer::default();
+        buffer.record("a", 1, 10);
+        buffer.record("a", 1, 20);
+        buffer.record("b", 1, 5);
+
+        assert_eq!(buffer.delta("a", 1), Some(20));
+        assert_eq!(buffer.overlay(progress("b", 1, 0)).delta, 5);
+        assert_eq!(buffer.overlay(progress("a", 2, 7)).delta, 7);
+
+        let taken = buffer.take(Some("a"));
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].1.delta, 20);
+
+        // entries being written stay visible until the write went through.
+        assert_eq!(buffer.delta("a", 1), Some(20));
+        buffer.finish(&taken, true);
+        assert_eq!(buffer.delta("a", 1), None);
+        assert_eq!(buffer.delta("b", 1), Some(5));
+    }
+
+    #[test]
+    fn test_failed_flush() {
+        let buffer = ProgressBuffer::default();
+        buffer.record("a", 1, 10);
+        buffer.record("a", 2, 10);
+
+        let taken = buffer.take(None);
+        assert_eq!(taken.len(), 2);
+
+        // newer progress reported while the write was in flight must win over the failed entry.
+        buffer.record("a", 1, 30);
+        buffer.finish(&taken, false);
+
+        assert_eq!(buffer.delta("a", 1), Some(30));
+        assert_eq!(buffer.delta("a", 2), Some(10));
+        assert_eq!(buffer.take(None).len(), 2);
+    }
+}
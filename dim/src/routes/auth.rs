@@ -1,22 +1,34 @@
 use crate::core::DbConnection;
 use crate::errors;
-use auth::{jwt_generate, Wrapper as Auth};
+use crate::progress_buffer;
+use auth::{jwt_generate, jwt_generate_with_scopes, Wrapper as Auth};
 use bytes::BufMut;
 
 use database::asset::Asset;
 use database::asset::InsertableAsset;
+use database::library::Library;
+use database::media::Media;
+use database::playback_window::PlaybackWindow;
 use database::progress::Progress;
+use database::rating::InsertableRating;
+use database::rating::Rating;
+use database::scrobble::Scrobble;
+use database::scrobble::ScrobbleIntegration;
 use database::user::verify;
 use database::user::InsertableUser;
 use database::user::Login;
 use database::user::User;
 
+use serde::Deserialize;
+use serde::Serialize;
 use serde_json::json;
 
 use warp::reply;
 
 use http::StatusCode;
 
+use chrono::Datelike;
+use chrono::Timelike;
 use futures::TryStreamExt;
 use uuid::Uuid;
 
@@ -59,6 +71,26 @@ pub mod filters {
             })
     }
 
+    pub fn mint_scoped_token(
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        pub struct Params {
+            scopes: Vec<String>,
+        }
+
+        warp::path!("api" / "v1" / "auth" / "scoped_token")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(warp::body::json::<Params>())
+            .and_then(
+                |user: auth::Wrapper, Params { scopes }: Params| async move {
+                    super::mint_scoped_token(user, scopes)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn admin_exists(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -203,6 +235,130 @@ pub mod filters {
                 })
     }
 
+    pub fn user_me(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "me")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_db(conn))
+            .and_then(|user: auth::Wrapper, conn: DbConnection| async move {
+                super::user_me(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_playback_windows(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / String / "playback_windows")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_db(conn))
+            .and_then(
+                |username: String, user: auth::Wrapper, conn: DbConnection| async move {
+                    super::get_playback_windows(conn, user, username)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn set_playback_windows(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / String / "playback_windows")
+            .and(warp::put())
+            .and(auth::with_auth())
+            .and(warp::body::json::<Vec<super::PlaybackWindow>>())
+            .and(with_db(conn))
+            .and_then(
+                |username: String,
+                 user: auth::Wrapper,
+                 windows: Vec<super::PlaybackWindow>,
+                 conn: DbConnection| async move {
+                    super::set_playback_windows(conn, user, username, windows)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn set_scrobble_integration(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "integrations" / "scrobble")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(warp::body::json::<super::NewScrobbleIntegration>())
+            .and(with_db(conn))
+            .and_then(
+                |user: auth::Wrapper,
+                 data: super::NewScrobbleIntegration,
+                 conn: DbConnection| async move {
+                    super::set_scrobble_integration(conn, user, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn delete_scrobble_integration(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "integrations" / "scrobble")
+            .and(warp::delete())
+            .and(auth::with_auth())
+            .and(with_db(conn))
+            .and_then(|user: auth::Wrapper, conn: DbConnection| async move {
+                super::delete_scrobble_integration(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn user_export(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "export")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_db(conn))
+            .and_then(|user: auth::Wrapper, conn: DbConnection| async move {
+                super::user_export(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn user_import(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        pub struct Params {
+            #[serde(default)]
+            confirm: bool,
+        }
+
+        warp::path!("api" / "v1" / "user" / "import")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(warp::query::query::<Params>())
+            .and(warp::body::json::<super::UserExport>())
+            .and(with_db(conn))
+            .and_then(
+                |user: auth::Wrapper,
+                 Params { confirm }: Params,
+                 data: super::UserExport,
+                 conn: DbConnection| async move {
+                    super::user_import(conn, user, data, confirm)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn user_upload_avatar(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -244,6 +400,7 @@ pub async fn login(
 }
 
 pub async fn whoami(user: Auth, conn: DbConnection) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
     let username = user.0.claims.get_user();
     let mut tx = conn.read().begin().await?;
 
@@ -257,6 +414,42 @@ pub async fn whoami(user: Auth, conn: DbConnection) -> Result<impl warp::Reply,
     })))
 }
 
+/// Method mapped to `POST /api/v1/auth/scoped_token` mints a token for the current user which is
+/// restricted to `scopes`, ie to hand out to third party clients. Scoped tokens can't mint tokens
+/// themselves unless they have the `admin` scope.
+///
+/// # Arguments
+/// * `user` - Auth middleware
+/// * `scopes` - scopes to restrict the token to, options are `read:media`, `write:media` and
+/// `admin`.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "token": string,
+///     "scopes": [string],
+/// }
+/// ```
+pub async fn mint_scoped_token(
+    user: Auth,
+    scopes: Vec<String>,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if scopes.is_empty() || scopes.iter().any(|x| !auth::SCOPES.contains(&x.as_str())) {
+        return Err(errors::DimError::InvalidScopes);
+    }
+
+    let token = jwt_generate_with_scopes(
+        user.0.claims.get_user(),
+        user.0.claims.clone_roles(),
+        Some(scopes.clone()),
+    );
+
+    Ok(reply::json(&json!({
+        "token": token,
+        "scopes": scopes,
+    })))
+}
+
 pub async fn admin_exists(conn: DbConnection) -> Result<impl warp::Reply, errors::DimError> {
     let mut tx = conn.read().begin().await?;
     Ok(reply::json(&json!({
@@ -443,6 +636,433 @@ pub async fn user_change_username(
     Ok(StatusCode::OK)
 }
 
+/// A per-user export of the data a user has generated, ie their progress and ratings.
+#[derive(Serialize, Deserialize)]
+pub struct UserExport {
+    #[serde(default)]
+    pub progress: Vec<ExportedProgress>,
+    #[serde(default)]
+    pub ratings: Vec<ExportedRating>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedProgress {
+    pub media_id: i64,
+    pub delta: i64,
+    pub populated: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedRating {
+    pub media_id: i64,
+    pub score: i64,
+}
+
+/// Method mapped to `GET /api/v1/user/me` returns the profile of the current user as derived from
+/// the claims of their token. Tokens of users that have since been deleted or renamed are rejected
+/// with `401`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "username": string,
+///     "roles": [string],
+///     "owner": bool,
+///     "libraries": [int],
+///     "token_expires": int,
+/// }
+/// ```
+pub async fn user_me(conn: DbConnection, user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let username = user.0.claims.get_user();
+
+    let id = User::get_id(&mut tx, &username)
+        .await
+        .map_err(|_| errors::DimError::Unauthorized)?;
+
+    // there are no per user permissions for libraries yet, thus every user can access every
+    // library that isnt queued for deletion.
+    let libraries = Library::get_all(&mut tx)
+        .await
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "username": username,
+        "roles": user.0.claims.clone_roles(),
+        "owner": user.0.claims.has_role("owner"),
+        "libraries": libraries,
+        "token_expires": user.0.claims.expires_at(),
+    })))
+}
+
+/// Method mapped to `GET /api/v1/user/<username>/playback_windows` returns the windows of time
+/// during which a user is allowed to play media. Users can only see their own windows, the owner
+/// can see the windows of everyone. No windows means that playback is never restricted.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+/// * `username` - user whose windows we want
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "weekday": int,
+///         "start_minute": int,
+///         "end_minute": int,
+///     }
+/// ]
+/// ```
+pub async fn get_playback_windows(
+    conn: DbConnection,
+    user: Auth,
+    username: String,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") && user.0.claims.get_user_ref() != username {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    User::get(&mut tx, &username)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    Ok(reply::json(
+        &PlaybackWindow::get_for_user(&mut tx, &username).await?,
+    ))
+}
+
+/// Method mapped to `PUT /api/v1/user/<username>/playback_windows` replaces the windows of time
+/// during which a user is allowed to play media, ie for parental controls. Weekdays go from `0`
+/// (monday) to `6` (sunday) and times are given in minutes since midnight in the local time of
+/// the server. Sending no windows lifts the restriction. Only the owner can change windows and
+/// the owner is never restricted by them.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+/// * `username` - user whose windows we want to change
+/// * `windows` - the new playback windows
+pub async fn set_playback_windows(
+    conn: DbConnection,
+    user: Auth,
+    username: String,
+    windows: Vec<PlaybackWindow>,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if !windows.iter().all(PlaybackWindow::is_valid) {
+        return Err(errors::DimError::InvalidPlaybackWindow);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    User::get(&mut tx, &username)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    PlaybackWindow::set_for_user(&mut tx, &username, &windows).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Function checks whether `user` is allowed to play media right now. Returns
+/// [`DimError::PlaybackNotAllowed`](errors::DimError::PlaybackNotAllowed) with the windows of
+/// today if the current time lies outside of the playback windows of the user. The owner is
+/// never restricted.
+///
+/// # Arguments
+/// * `conn` - mutable reference to a sqlx transaction.
+/// * `user` - Auth middleware
+pub async fn check_playback_window(
+    conn: &mut database::Transaction<'_>,
+    user: &Auth,
+) -> Result<(), errors::DimError> {
+    if user.0.claims.has_role("owner") {
+        return Ok(());
+    }
+
+    check_playback_window_of(&mut *conn, user.0.claims.get_user_ref()).await
+}
+
+/// Function checks whether the user `uid` is allowed to play media right now, like
+/// [`check_playback_window`](check_playback_window) but for places where only the name of the
+/// user is known, ie signed stream urls. The roles of the user are looked up in the database.
+///
+/// # Arguments
+/// * `conn` - mutable reference to a sqlx transaction.
+/// * `uid` - username of the user.
+pub async fn check_playback_window_for(
+    conn: &mut database::Transaction<'_>,
+    uid: &str,
+) -> Result<(), errors::DimError> {
+    let user = User::get(&mut *conn, uid)
+        .await
+        .map_err(|_| errors::DimError::InvalidStreamToken)?;
+
+    if user.roles.iter().any(|x| x == "owner") {
+        return Ok(());
+    }
+
+    check_playback_window_of(&mut *conn, uid).await
+}
+
+async fn check_playback_window_of(
+    conn: &mut database::Transaction<'_>,
+    uid: &str,
+) -> Result<(), errors::DimError> {
+    let windows = PlaybackWindow::get_for_user(&mut *conn, uid).await?;
+
+    let now = chrono::Local::now();
+    let weekday = now.weekday().num_days_from_monday() as i64;
+    let minute = (now.hour() * 60 + now.minute()) as i64;
+
+    if PlaybackWindow::allows(&windows, weekday, minute) {
+        return Ok(());
+    }
+
+    let today = windows
+        .iter()
+        .filter(|x| x.weekday == weekday)
+        .map(|x| {
+            format!(
+                "{:02}:{:02}-{:02}:{:02}",
+                x.start_minute / 60,
+                x.start_minute % 60,
+                x.end_minute / 60,
+                x.end_minute % 60
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let reason = if today.is_empty() {
+        "Playback is not allowed today.".to_string()
+    } else {
+        format!("Playback is only allowed today at {}.", today.join(", "))
+    };
+
+    Err(errors::DimError::PlaybackNotAllowed { reason })
+}
+
+#[derive(Deserialize)]
+pub struct NewScrobbleIntegration {
+    /// Base url of the service, ie `https://api.trakt.tv`.
+    pub url: String,
+    /// Access token the service issued for the user.
+    pub token: String,
+}
+
+/// Method mapped to `POST /api/v1/user/integrations/scrobble` sets up scrobbling for the current
+/// user, replacing their existing integration. Once set up, starting and finishing a movie or
+/// episode sends a scrobble to `<url>/scrobble/start` and `<url>/scrobble/stop` respectively,
+/// authorized with `token` as a bearer token. Only media matched against TMDB are scrobbled.
+///
+/// `url` must use https and point at one of the `scrobble_hosts` in the global settings, other
+/// urls are rejected with `403`.
+///
+/// # Arguments
+/// * `user` - Auth middleware
+/// * `data` - url and access token of the service
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "url": string,
+///     "queued": int,
+/// }
+/// ```
+///
+/// `queued` is the number of scrobbles waiting to be sent, ie because the service was
+/// unreachable.
+pub async fn set_scrobble_integration(
+    conn: DbConnection,
+    user: Auth,
+    data: NewScrobbleIntegration,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if reqwest::Url::parse(&data.url).is_err() {
+        return Err(errors::DimError::MissingFieldInBody {
+            description: "Invalid scrobble url".into(),
+        });
+    }
+
+    let allowed_hosts = crate::routes::settings::get_global_settings().scrobble_hosts;
+    if !crate::scrobble::is_allowed_url(&data.url, &allowed_hosts) {
+        return Err(errors::DimError::ScrobbleHostNotAllowed);
+    }
+
+    let integration = ScrobbleIntegration {
+        user_id: user.0.claims.get_user(),
+        url: data.url,
+        token: data.token,
+    };
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    integration.set(&mut tx).await?;
+    let queued = Scrobble::count_for_user(&mut tx, &integration.user_id).await?;
+    tx.commit().await?;
+
+    Ok(reply::json(&json!({
+        "url": integration.url,
+        "queued": queued,
+    })))
+}
+
+/// Method mapped to `DELETE /api/v1/user/integrations/scrobble` stops scrobbling for the current
+/// user. Scrobbles that are yet to be sent are dropped.
+///
+/// # Arguments
+/// * `user` - Auth middleware
+pub async fn delete_scrobble_integration(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let rows = ScrobbleIntegration::delete_for_user(&mut tx, user.0.claims.get_user_ref()).await?;
+    tx.commit().await?;
+
+    if rows == 0 {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `GET /api/v1/user/export` returns the progress and ratings of the current
+/// user as a single json document which can later be restored with `POST /api/v1/user/import`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+pub async fn user_export(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+    let mut tx = conn.read().begin().await?;
+    let username = user.0.claims.get_user();
+
+    let progress = Progress::get_all_for_user(&mut tx, username.clone())
+        .await?
+        .into_iter()
+        .map(|x| ExportedProgress {
+            media_id: x.media_id,
+            delta: x.delta,
+            populated: x.populated,
+        })
+        .collect();
+
+    let ratings = Rating::get_all_for_user(&mut tx, username)
+        .await?
+        .into_iter()
+        .map(|x| ExportedRating {
+            media_id: x.media_id,
+            score: x.score,
+        })
+        .collect();
+
+    Ok(reply::json(&UserExport { progress, ratings }))
+}
+
+/// Method mapped to `POST /api/v1/user/import` restores a document previously returned by
+/// `GET /api/v1/user/export` for the current user. Entries referencing media that no longer
+/// exist are skipped. Importing the same document multiple times yields the same result.
+///
+/// Unless `confirm` is set, nothing is written and the route only reports what would be imported.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+/// * `data` - the exported document
+/// * `confirm` - whether to actually write the imported data
+pub async fn user_import(
+    conn: DbConnection,
+    user: Auth,
+    data: UserExport,
+    confirm: bool,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use database::rating::MAX_SCORE;
+    use database::rating::MIN_SCORE;
+
+    let username = user.0.claims.get_user();
+
+    // imported progress must not be overwritten by progress buffered before the import.
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    let mut progress = 0;
+    let mut ratings = 0;
+    let mut skipped = 0;
+
+    for entry in data.progress {
+        if Media::get(&mut tx, entry.media_id).await.is_err() {
+            skipped += 1;
+            continue;
+        }
+
+        if confirm {
+            Progress::restore(
+                &mut tx,
+                entry.delta,
+                username.clone(),
+                entry.media_id,
+                entry.populated,
+            )
+            .await?;
+        }
+
+        progress += 1;
+    }
+
+    for entry in data.ratings {
+        if !(MIN_SCORE..=MAX_SCORE).contains(&entry.score)
+            || Media::get(&mut tx, entry.media_id).await.is_err()
+        {
+            skipped += 1;
+            continue;
+        }
+
+        if confirm {
+            InsertableRating {
+                user_id: username.clone(),
+                media_id: entry.media_id,
+                score: entry.score,
+            }
+            .insert(&mut tx)
+            .await?;
+        }
+
+        ratings += 1;
+    }
+
+    if confirm {
+        tx.commit().await?;
+    }
+
+    Ok(reply::json(&json!({
+        "confirmed": confirm,
+        "progress": progress,
+        "ratings": ratings,
+        "skipped": skipped,
+    })))
+}
+
 pub async fn user_upload_avatar(
     conn: DbConnection,
     user: Auth,
@@ -1,14 +1,45 @@
+//! Catchers turning requests which no api route handled into JSON responses.
 use crate::errors::DimError;
 
-use rocket_contrib::json::json;
-use rocket_contrib::json::JsonValue;
+use warp::http::StatusCode;
+use warp::Filter;
+use warp::Reply;
 
-#[catch(404)]
-pub async fn not_found() -> DimError {
-    DimError::NotFoundError
+/// Builds the response returned for `status` in the same shape as the errors returned by route
+/// handlers, ie `{ "error": "NotFoundError", "messsage": "...", "status": 404 }`. Statuses
+/// without a catcher of their own, ie `500`, are answered with a `500`.
+pub fn catch(status: StatusCode) -> warp::reply::Response {
+    let error = match status {
+        StatusCode::UNAUTHORIZED => DimError::Unauthenticated,
+        StatusCode::FORBIDDEN => DimError::Forbidden,
+        StatusCode::NOT_FOUND => DimError::NotFoundError,
+        _ => DimError::InternalServerError,
+    };
+
+    error.into_response()
+}
+
+/// Returns whether `err` is a rejection warp answers with a status of its own, ie `405` if no
+/// route accepts the method of a request.
+pub fn is_builtin_rejection(err: &warp::Rejection) -> bool {
+    use warp::reject::*;
+
+    err.is_not_found()
+        || err.find::<MethodNotAllowed>().is_some()
+        || err.find::<InvalidHeader>().is_some()
+        || err.find::<MissingHeader>().is_some()
+        || err.find::<MissingCookie>().is_some()
+        || err.find::<InvalidQuery>().is_some()
+        || err.find::<LengthRequired>().is_some()
+        || err.find::<PayloadTooLarge>().is_some()
+        || err.find::<UnsupportedMediaType>().is_some()
 }
 
-#[catch(500)]
-pub async fn internal_server_error() -> DimError {
-    DimError::InternalServerError
+/// Filter answering every request under `/api` with a JSON `404`. It must be mounted after all
+/// api routes but before the static routes, this way routing misses of the api never fall through
+/// to the html of the web ui while the web ui is still served for every other path.
+pub fn api_not_found() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("api" / ..)
+        .and(warp::any())
+        .map(|| catch(StatusCode::NOT_FOUND))
 }
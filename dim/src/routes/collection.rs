@@ -0,0 +1,106 @@
+use crate::core::DbConnection;
+use crate::errors;
+
+use database::library::MediaType;
+use database::library::MetadataAgent;
+use database::media::Media;
+
+use serde_json::json;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn get_collection(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "collection" / u64)
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: u64, _user: Auth, conn: DbConnection| async move {
+                super::get_collection(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+}
+
+/// Method mapped to `GET /api/v1/collection/<tmdb_collection_id>` returns a TMDB collection, ie
+/// all movies of a franchise, along with which of its movies are in the library.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the collection on TMDB
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "overview": string | null,
+///     "poster_path": string | null,
+///     "backdrop_path": string | null,
+///     "parts": [{
+///         "tmdb_id": int,
+///         "title": string,
+///         "release_date": string | null,
+///         "poster_path": string | null,
+///         "owned": bool,
+///         "media_id": int | null,
+///     }],
+/// }
+/// ```
+///
+/// `parts` are ordered by release date, unreleased movies come last. `media_id` is the id of the
+/// movie in the library and is `null` for movies that are missing. Movies in hidden libraries, ie
+/// libraries that are being deleted, count as missing.
+pub async fn get_collection(
+    conn: DbConnection,
+    id: u64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    // collections are identified by their TMDB id, thus they are always fetched from TMDB.
+    let mut tmdb = crate::scanners::metadata_agent(MetadataAgent::Tmdb, MediaType::Movie);
+    let collection = tmdb.get_collection(id).await?;
+
+    let mut tx = conn.read().begin().await?;
+    let mut parts = Vec::with_capacity(collection.parts.len());
+
+    for part in collection.parts {
+        let media_id = Media::get_all_by_tmdb_id(&mut tx, part.id as i64)
+            .await?
+            .into_iter()
+            .find(|x| x.media_type == MediaType::Movie)
+            .map(|x| x.id);
+
+        parts.push(json!({
+            "tmdb_id": part.id,
+            "title": part.title,
+            "release_date": part.release_date.filter(|x| !x.is_empty()),
+            "poster_path": part.poster_path.map(poster_url),
+            "owned": media_id.is_some(),
+            "media_id": media_id,
+        }));
+    }
+
+    Ok(reply::json(&json!({
+        "id": collection.id,
+        "name": collection.name,
+        "overview": collection.overview,
+        "poster_path": collection.poster_path.map(poster_url),
+        "backdrop_path": collection
+            .backdrop_path
+            .map(|x| format!("https://image.tmdb.org/t/p/original{}", x)),
+        "parts": parts,
+    })))
+}
+
+fn poster_url(path: String) -> String {
+    format!("https://image.tmdb.org/t/p/w600_and_h900_bestv2{}", path)
+}
@@ -1,19 +1,33 @@
 use crate::core::DbConnection;
 use crate::errors;
 use crate::json;
+use crate::progress_buffer;
 
 use auth::Wrapper as Auth;
 
+use database::chapter::Chapter;
 use database::episode::Episode;
 use database::genre::*;
+use database::history::History;
 use database::library::MediaType;
 use database::media::Media;
 use database::mediafile::MediaFile;
 use database::progress::Progress;
+use database::progress::WATCHED_THRESHOLD;
+use database::season::Season;
 
 use serde_json::Value;
 
+use tracing::warn;
+use warp::http::StatusCode;
 use warp::reply;
+use warp::Reply;
+
+/// Number of items returned per section of the home screen.
+const HOME_SECTION_SIZE: i64 = 10;
+
+/// Number of genres returned by the watch stats of a user.
+const STATS_GENRE_COUNT: i64 = 5;
 
 pub mod filters {
     use database::DbConnection;
@@ -23,6 +37,7 @@ pub mod filters {
 
     use super::super::global_filters::with_state;
 
+    use serde::Deserialize;
     use tokio::runtime::Handle as TokioHandle;
 
     use auth::Wrapper as Auth;
@@ -33,7 +48,7 @@ pub mod filters {
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "dashboard")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and(with_state::<TokioHandle>(rt))
             .and_then(
@@ -45,12 +60,61 @@ pub mod filters {
             )
     }
 
+    pub fn home(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "home")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|user: Auth, conn: DbConnection| async move {
+                super::home(conn, user).await.map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn last_watched(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "last_watched")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|user: Auth, conn: DbConnection| async move {
+                super::last_watched(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn user_stats(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            from: String,
+            to: String,
+        }
+
+        warp::path!("api" / "v1" / "user" / "stats")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |RouteArgs { from, to }: RouteArgs, user: Auth, conn: DbConnection| async move {
+                    super::user_stats(conn, user, from, to)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn banners(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "dashboard" / "banner")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|user: Auth, conn: DbConnection| async move {
                 super::banners(conn, user)
@@ -65,10 +129,11 @@ pub async fn dashboard(
     user: Auth,
     _rt: tokio::runtime::Handle,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
     let mut tx = conn.read().begin().await?;
 
     let mut top_rated = Vec::new();
-    for media in Media::get_top_rated(&mut tx, 10).await? {
+    for media in Media::get_top_rated(&mut tx, user.0.claims.get_user_ref(), 10).await? {
         let item = match sqlx::query!(
             "SELECT _tblmedia.name, assets.local_path FROM _tblmedia LEFT JOIN assets ON assets.id = _tblmedia.poster
             WHERE _tblmedia.id = ?",
@@ -86,7 +151,7 @@ pub async fn dashboard(
     }
 
     let mut recently_added = Vec::new();
-    for media in Media::get_recently_added(&mut tx, 10).await? {
+    for media in Media::get_recently_added(&mut tx, user.0.claims.get_user_ref(), 10).await? {
         let item = match sqlx::query!(
             "SELECT _tblmedia.name, assets.local_path FROM _tblmedia LEFT JOIN assets ON assets.id = _tblmedia.poster
             WHERE _tblmedia.id = ?",
@@ -136,10 +201,289 @@ pub async fn dashboard(
     })))
 }
 
+/// Method mapped to `GET /api/v1/home` returns the rows of the home screen in one response, ie
+/// the shows the user is watching, recently added media and the next episode of every show in
+/// progress. Every section is loaded on its own, if one of them fails its `failed` flag is set
+/// and its items are left empty while the other sections are still returned. Media the user hid
+/// and media in hidden libraries are never returned.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "continue_watching": {
+///         "items": [{ "id": int, "name": string, "poster_path": string | null }],
+///         "failed": bool,
+///     },
+///     "recently_added": {
+///         "items": [{ "id": int, "name": string, "poster_path": string | null }],
+///         "failed": bool,
+///     },
+///     "up_next": {
+///         "items": [{
+///             "id": int,
+///             "show_id": int,
+///             "name": string,
+///             "poster_path": string | null,
+///             "season": int,
+///             "episode": int,
+///         }],
+///         "failed": bool,
+///     },
+/// }
+/// ```
+pub async fn home(conn: DbConnection, user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    let continue_watching = home_continue_watching(&conn, &user).await;
+    let recently_added = home_recently_added(&conn, &user).await;
+    let up_next = home_up_next(&conn, &user).await;
+
+    Ok(reply::json(&json!({
+        "continue_watching": home_section("continue_watching", continue_watching),
+        "recently_added": home_section("recently_added", recently_added),
+        "up_next": home_section("up_next", up_next),
+    })))
+}
+
+fn home_section(name: &str, items: Result<Vec<Value>, errors::DimError>) -> Value {
+    match items {
+        Ok(items) => json!({ "items": items, "failed": false }),
+        Err(e) => {
+            warn!(section = name, reason = ?e, "Failed to load home screen section.");
+            json!({ "items": [], "failed": true })
+        }
+    }
+}
+
+async fn home_card(conn: &mut database::Transaction<'_>, id: i64) -> Option<Value> {
+    let media = Media::get(&mut *conn, id).await.ok()?;
+
+    Some(json!({
+        "id": media.id,
+        "name": media.name,
+        "poster_path": media.poster_path,
+    }))
+}
+
+async fn home_continue_watching(
+    conn: &DbConnection,
+    user: &Auth,
+) -> Result<Vec<Value>, errors::DimError> {
+    progress_buffer::flush_user(conn, user.0.claims.get_user_ref()).await?;
+    let mut tx = conn.read().begin().await?;
+    let ids = Progress::get_continue_watching(&mut tx, user.0.claims.get_user(), HOME_SECTION_SIZE)
+        .await?;
+
+    let mut items = Vec::with_capacity(ids.len());
+    for id in ids {
+        items.extend(home_card(&mut tx, id).await);
+    }
+
+    Ok(items)
+}
+
+async fn home_recently_added(
+    conn: &DbConnection,
+    user: &Auth,
+) -> Result<Vec<Value>, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let ids =
+        Media::get_recently_added(&mut tx, user.0.claims.get_user_ref(), HOME_SECTION_SIZE).await?;
+
+    let mut items = Vec::with_capacity(ids.len());
+    for id in ids {
+        items.extend(home_card(&mut tx, id).await);
+    }
+
+    Ok(items)
+}
+
+async fn home_up_next(conn: &DbConnection, user: &Auth) -> Result<Vec<Value>, errors::DimError> {
+    progress_buffer::flush_user(conn, user.0.claims.get_user_ref()).await?;
+    let mut tx = conn.read().begin().await?;
+    let shows = Progress::get_in_progress_shows(&mut tx, user.0.claims.get_user()).await?;
+
+    let mut items = Vec::new();
+    for show in shows.into_iter().take(HOME_SECTION_SIZE as usize) {
+        let last =
+            match Episode::get_last_watched_episode(&mut tx, show.id, user.0.claims.get_user())
+                .await?
+            {
+                Some(x) => x,
+                None => continue,
+            };
+
+        let (delta, duration) =
+            Progress::get_progress_for_media(&mut tx, last.id, user.0.claims.get_user())
+                .await
+                .unwrap_or((0, 1));
+
+        // the last episode counts as finished once 90% of it were watched.
+        let next = if (delta as f64 / duration as f64) > WATCHED_THRESHOLD {
+            match last.get_next_episode(&mut tx).await {
+                Ok(x) => x,
+                Err(_) => continue,
+            }
+        } else {
+            last
+        };
+
+        items.push(json!({
+            "id": next.id,
+            "show_id": show.id,
+            "name": show.name,
+            "poster_path": show.poster_path,
+            "season": next.get_season_number(&mut tx).await.unwrap_or(0),
+            "episode": next.episode,
+        }));
+    }
+
+    Ok(items)
+}
+
+/// Method mapped to `GET /api/v1/user/last_watched` returns the single media the user most
+/// recently made progress on without finishing it, ie for a resume button on the home screen. For
+/// tv shows this resolves to the episode to resume, with `show` describing the show it belongs
+/// to. Returns `204` if the user has nothing in progress.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "media_type": "movie" | "episode",
+///     "poster_path": string | null,
+///     "backdrop_path": string | null,
+///     "delta": int,
+///     "duration": int,
+///     "show": {
+///         "id": int,
+///         "name": string,
+///         "poster_path": string | null,
+///         "season": int,
+///         "episode": int,
+///     } | null,
+/// }
+/// ```
+pub async fn last_watched(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<warp::reply::Response, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+    let mut tx = conn.read().begin().await?;
+    let progress = match Progress::get_last_in_progress(&mut tx, user.0.claims.get_user()).await? {
+        Some(x) => x,
+        None => return Ok(StatusCode::NO_CONTENT.into_response()),
+    };
+
+    let media = Media::get(&mut tx, progress.media_id).await?;
+
+    let show = if media.media_type == MediaType::Episode {
+        let episode = Episode::get_by_id(&mut tx, media.id).await?;
+        let season = Season::get_by_id(&mut tx, episode.seasonid).await?;
+        let show = Media::get(&mut tx, season.tvshowid).await?;
+
+        Some(json!({
+            "id": show.id,
+            "name": show.name,
+            "poster_path": show.poster_path,
+            "season": season.season_number,
+            "episode": episode.episode,
+        }))
+    } else {
+        None
+    };
+
+    Ok(reply::json(&json!({
+        "id": media.id,
+        "name": media.name,
+        "media_type": media.media_type,
+        "poster_path": media.poster_path,
+        "backdrop_path": media.backdrop_path,
+        "delta": progress.delta,
+        "duration": progress.duration,
+        "show": show,
+    }))
+    .into_response())
+}
+
+/// Method mapped to `GET /api/v1/user/stats?<from>&<to>` returns how much the user watched
+/// within a date range, used for year in review style summaries. Minutes watched add up the
+/// duration of every finished media and the progress through media started but not finished in
+/// the range. Plays of episodes count towards the genres of their tv show. Only the data of the
+/// user is counted, a range without activity returns zeros.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+/// * `from` - first day of the range formatted as `YYYY-MM-DD`, inclusive.
+/// * `to` - last day of the range formatted as `YYYY-MM-DD`, inclusive.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "from": string,
+///     "to": string,
+///     "minutes_watched": int,
+///     "items_finished": int,
+///     "top_genres": [
+///         {
+///             "name": string,
+///             "plays": int,
+///         }
+///     ],
+/// }
+/// ```
+pub async fn user_stats(
+    conn: DbConnection,
+    user: Auth,
+    from: String,
+    to: String,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use chrono::NaiveDate;
+
+    let parse = |x: &str| {
+        NaiveDate::parse_from_str(x, "%Y-%m-%d").map_err(|_| errors::DimError::InvalidDateRange)
+    };
+
+    let (from, to) = (parse(&from)?, parse(&to)?);
+
+    if from > to {
+        return Err(errors::DimError::InvalidDateRange);
+    }
+
+    // `to` is inclusive, thus we count up until the start of the next day.
+    let timestamp = |x: NaiveDate| {
+        x.and_hms_opt(0, 0, 0)
+            .map(|x| x.timestamp())
+            .ok_or(errors::DimError::InvalidDateRange)
+    };
+
+    let until = to.succ_opt().ok_or(errors::DimError::InvalidDateRange)?;
+    let (start, end) = (timestamp(from)?, timestamp(until)?);
+
+    let uid = user.0.claims.get_user_ref();
+    progress_buffer::flush_user(&conn, uid).await?;
+
+    let mut tx = conn.read().begin().await?;
+    let stats = History::get_stats_for_user(&mut tx, uid, start, end, STATS_GENRE_COUNT).await?;
+
+    Ok(reply::json(&json!({
+        "from": from.to_string(),
+        "to": to.to_string(),
+        "minutes_watched": stats.seconds_watched / 60,
+        "items_finished": stats.items_finished,
+        "top_genres": stats.top_genres,
+    })))
+}
+
 pub async fn banners(conn: DbConnection, user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
     let mut tx = conn.read().begin().await?;
     let mut banners = Vec::new();
-    for media in Media::get_random_with(&mut tx, 10).await? {
+    for media in Media::get_random_with(&mut tx, user.0.claims.get_user_ref(), 10).await? {
         if let Ok(x) = match media.media_type {
             MediaType::Tv => banner_for_show(&mut tx, &user, &media).await,
             MediaType::Movie => banner_for_movie(&mut tx, &user, &media).await,
@@ -162,7 +506,14 @@ async fn banner_for_movie(
         .map(|x| x.delta)
         .unwrap_or(0);
 
-    let mediafiles = MediaFile::get_of_media(&mut *conn, media.id).await?;
+    let mut mediafiles = MediaFile::get_of_media(&mut *conn, media.id).await?;
+    mediafiles.sort_by(MediaFile::cmp_quality);
+
+    let mut chapters = Vec::with_capacity(mediafiles.len());
+    for file in mediafiles.iter() {
+        chapters.push(Chapter::get_of_mediafile(&mut *conn, file.id).await?);
+    }
+
     let media_duration = MediaFile::get_largest_duration(&mut *conn, media.id).await?;
 
     let genres = Genre::get_by_media(&mut *conn, media.id)
@@ -186,9 +537,14 @@ async fn banner_for_movie(
         "genres": genres,
         "delta": progress,
         "banner_caption": caption,
-        "versions": mediafiles.iter().map(|x| json!({
+        "versions": mediafiles.iter().zip(chapters).enumerate().map(|(rank, (x, chapters))| json!({
             "id": x.id,
             "file": x.target_file,
+            "quality_rank": rank,
+            "hdr": x.hdr.as_deref().unwrap_or("sdr"),
+            "has_cc": x.has_cc,
+            "has_sdh": x.has_sdh,
+            "chapters": chapters,
             "display_name": format!("{} - {} - {} - Library {}",
                                     x.codec.as_ref().unwrap_or(&"Unknown VC".to_string()),
                                     x.audio.as_ref().unwrap_or(&"Unknwon AC".to_string()),
@@ -211,7 +567,7 @@ async fn banner_for_show(
                 .await
                 .unwrap_or((0, 1));
 
-        if (delta as f64 / duration as f64) > 0.90 {
+        if (delta as f64 / duration as f64) > WATCHED_THRESHOLD {
             ep.get_next_episode(&mut *conn)
                 .await
                 .unwrap_or(ep)
@@ -238,7 +594,13 @@ async fn banner_for_show(
         .await
         .unwrap_or(0);
 
-    let mediafiles = MediaFile::get_of_media(&mut *conn, episode.id).await?;
+    let mut mediafiles = MediaFile::get_of_media(&mut *conn, episode.id).await?;
+    mediafiles.sort_by(MediaFile::cmp_quality);
+
+    let mut chapters = Vec::with_capacity(mediafiles.len());
+    for file in mediafiles.iter() {
+        chapters.push(Chapter::get_of_mediafile(&mut *conn, file.id).await?);
+    }
 
     let caption = if progress > 0 {
         "CONTINUE WATCHING"
@@ -258,9 +620,14 @@ async fn banner_for_show(
         "banner_caption": caption,
         "episode": episode.episode,
         "season": episode.get_season_number(&mut *conn).await.unwrap_or(0),
-        "versions": mediafiles.iter().map(|x| json!({
+        "versions": mediafiles.iter().zip(chapters).enumerate().map(|(rank, (x, chapters))| json!({
             "id": x.id,
             "file": x.target_file,
+            "quality_rank": rank,
+            "hdr": x.hdr.as_deref().unwrap_or("sdr"),
+            "has_cc": x.has_cc,
+            "has_sdh": x.has_sdh,
+            "chapters": chapters,
             "display_name": format!("{} - {} - {} - Library {}",
                                     x.codec.as_ref().unwrap_or(&"Unknown VC".to_string()),
                                     x.audio.as_ref().unwrap_or(&"Unknwon AC".to_string()),
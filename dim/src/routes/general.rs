@@ -1,20 +1,46 @@
 use crate::core::DbConnection;
+use crate::core::EventTx;
 use crate::errors;
+use crate::progress_buffer;
+use crate::routes::pagination::PageArgs;
+use crate::routes::pagination::Paginated;
+use crate::scanners::tmdb::Tmdb;
+use crate::scanners::MediaLock;
+use crate::search_index;
+use crate::streaming::ffprobe::FFProbeCtx;
 
 use auth::Wrapper as Auth;
+use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
 
+use database::asset::Asset;
+use database::asset::InsertableAsset;
 use database::genre::*;
+use database::library::Library;
+use database::media::Media;
+use database::media::UpdateMedia;
+use database::mediafile::MediaFile;
+use database::mediafile::UpdateMediaFile;
+use database::progress::Progress;
+use database::search_index::SearchIndex;
+
+use events::Message;
+use events::PushEventType;
 
 use tokio::task::spawn_blocking;
+use tracing::error;
+use tracing::info;
 
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 
+use warp::http::StatusCode;
 use warp::reply;
 
 pub mod filters {
+    use crate::core::EventTx;
     use database::DbConnection;
 
     use auth::Wrapper as Auth;
@@ -24,6 +50,7 @@ pub mod filters {
     use warp::Rejection;
 
     use super::super::global_filters::with_state;
+    use super::super::pagination::PageArgs;
     use serde::Deserialize;
 
     pub fn get_directory_structure(
@@ -43,6 +70,183 @@ pub mod filters {
             })
     }
 
+    pub fn get_tasks() -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "tasks")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and_then(|user: Auth| async move {
+                super::get_tasks(user).await.map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn cancel_task() -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "tasks" / u64)
+            .and(warp::delete())
+            .and(auth::with_auth())
+            .and_then(|id: u64, user: Auth| async move {
+                super::cancel_task(id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn health(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "health")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|conn: DbConnection| async move {
+                super::health(conn).await.map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn clear_cache(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct ClearCacheArgs {
+            #[serde(default)]
+            scope: super::CacheScope,
+        }
+
+        warp::path!("api" / "v1" / "admin" / "cache" / "clear")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(warp::query::query::<ClearCacheArgs>())
+            .and_then(
+                |user: Auth, conn: DbConnection, args: ClearCacheArgs| async move {
+                    super::clear_cache(conn, args.scope, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn prune_progress(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "prune_progress")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|user: Auth, conn: DbConnection| async move {
+                super::prune_progress(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn purge_watched(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "purge_watched")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and(warp::query::query::<super::PurgeArgs>())
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx, args: super::PurgeArgs| async move {
+                    super::purge_watched(conn, event_tx, args, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn recompute_durations(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "recompute_durations")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::recompute_durations(conn, event_tx, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn backfill_posters(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "backfill_posters")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::backfill_posters(conn, event_tx, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn backfill_accent_colors(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "backfill_accent_colors")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::backfill_accent_colors(conn, event_tx, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn backfill_captions(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "backfill_captions")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::backfill_captions(conn, event_tx, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn reindex_search(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "maintenance" / "reindex_search")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::reindex_search(conn, event_tx, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn search(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
@@ -53,15 +257,18 @@ pub mod filters {
             library_id: Option<i32>,
             genre: Option<String>,
             quick: Option<bool>,
+            include_cast: Option<bool>,
+            include_hidden: Option<bool>,
         }
 
         warp::path!("api" / "v1" / "search")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and(warp::query::query::<SearchArgs>())
+            .and(warp::query::query::<PageArgs>())
             .and_then(
-                |auth: Auth, conn: DbConnection, args: SearchArgs| async move {
+                |auth: Auth, conn: DbConnection, args: SearchArgs, page: PageArgs| async move {
                     super::search(
                         conn,
                         args.query,
@@ -69,6 +276,9 @@ pub mod filters {
                         args.library_id,
                         args.genre,
                         args.quick,
+                        args.include_cast.unwrap_or(false),
+                        args.include_hidden.unwrap_or(false),
+                        page,
                         auth,
                     )
                     .await
@@ -76,6 +286,30 @@ pub mod filters {
                 },
             )
     }
+
+    pub fn parse_filename() -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            filename: String,
+            media_type: Option<String>,
+        }
+
+        warp::path!("api" / "v1" / "scanners" / "parse")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_auth())
+            .and_then(
+                |RouteArgs {
+                     filename,
+                     media_type,
+                 }: RouteArgs,
+                 auth: Auth| async move {
+                    super::parse_filename(filename, media_type, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
 }
 
 pub fn enumerate_directory<T: AsRef<std::path::Path>>(path: T) -> io::Result<Vec<String>> {
@@ -131,6 +365,865 @@ pub async fn get_directory_structure(
     ))
 }
 
+/// Method mapped to `GET /api/v1/tasks` returns all queued and running background tasks, ie
+/// library scans and rematches.
+pub async fn get_tasks(_user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    Ok(reply::json(&crate::tasks::list()))
+}
+
+/// Method mapped to `DELETE /api/v1/tasks/<id>` cancels a queued or running background task.
+/// Tasks whose result is awaited by a request, ie rematches, cant be cancelled. Only the owner
+/// can call this route.
+pub async fn cancel_task(id: u64, user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if !crate::tasks::cancel(id) {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `GET /api/v1/scanners/parse?<filename>&<media_type>` runs the filename parser
+/// of the scanner against `filename` and returns what it extracted, without touching the database
+/// or TMDB. This is useful to understand why a file was matched wrongly.
+///
+/// # Arguments
+/// * `filename` - name of the file to parse, including its extension.
+/// * `media_type` - either `movie` or `tv`, defaults to `movie`. For `tv` the anime heuristics used
+/// when matching episodes are applied too, for `movie` season and episode are always `null`.
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "title": string,
+///     "year": int | null,
+///     "season": int | null,
+///     "episode": int | null,
+///     "episode_end": int | null,
+/// }
+/// ```
+pub async fn parse_filename(
+    filename: String,
+    media_type: Option<String>,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use crate::scanners::base;
+    use crate::scanners::base::ScannerError;
+
+    let is_tv = match media_type.as_deref().unwrap_or("movie") {
+        "movie" => false,
+        "tv" => true,
+        _ => return Err(errors::DimError::InvalidMediaType),
+    };
+
+    let parsed = spawn_blocking(move || {
+        let mut parsed = base::parse_filename(&filename)?;
+
+        if !is_tv {
+            parsed.season = None;
+            parsed.episode = None;
+            parsed.episode_end = None;
+        } else if let Some((season, episode)) = base::parse_anime_episode(&filename) {
+            parsed.season = Some(season);
+            parsed.episode = Some(episode);
+        }
+
+        Ok::<_, ScannerError>(parsed)
+    })
+    .await
+    .map_err(|_| ScannerError::FilenameParserError)??;
+
+    Ok(reply::json(&parsed))
+}
+
+/// Method mapped to `GET /api/v1/health` reports whether the service and its subsystems are
+/// healthy. This route doesn't require authentication so that it can be used by probes. The
+/// status code is `503` if the database can't be reached.
+///
+/// TMDB reachability is taken from the last cached probe and is `null` until the first probe
+/// finishes.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "database": bool,
+///     "tmdb": bool | null,
+///     "active_scans": int,
+///     "version": string,
+/// }
+/// ```
+pub async fn health(conn: DbConnection) -> Result<impl warp::Reply, errors::DimError> {
+    let database = match conn.read().begin().await {
+        Ok(mut tx) => sqlx::query("SELECT 1").execute(&mut tx).await.is_ok(),
+        Err(_) => false,
+    };
+
+    let tmdb = crate::scanners::tmdb_client(database::library::MediaType::Movie).is_reachable();
+
+    let status = if database {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(reply::with_status(
+        reply::json(&json!({
+            "database": database,
+            "tmdb": tmdb,
+            "active_scans": crate::scanners::running_scans(),
+            "version": env!("CARGO_PKG_VERSION"),
+        })),
+        status,
+    ))
+}
+
+/// Caches that can be flushed with [`clear_cache`](clear_cache).
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheScope {
+    /// In-memory cache of TMDB search, genre and video responses.
+    Tmdb,
+    /// Posters and backdrops downloaded into the metadata directory.
+    Images,
+    All,
+}
+
+impl Default for CacheScope {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+/// Method mapped to `POST /api/v1/admin/cache/clear` flushes the TMDB response cache and/or the
+/// locally cached images and returns how many entries were cleared. Removed images are fetched
+/// again the next time they are requested. Only the owner can call this route.
+///
+/// # Arguments
+/// * `scope` - which cache to clear, one of `tmdb`, `images` or `all` (default)
+pub async fn clear_cache(
+    conn: DbConnection,
+    scope: CacheScope,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tmdb = 0;
+    let mut images = 0;
+
+    if matches!(scope, CacheScope::Tmdb | CacheScope::All) {
+        tmdb = Tmdb::clear_cache().await;
+    }
+
+    if matches!(scope, CacheScope::Images | CacheScope::All) {
+        let assets = {
+            let mut tx = conn.read().begin().await?;
+            Asset::get_all_remote(&mut tx).await?
+        };
+
+        let meta_path = crate::core::METADATA_PATH.get().unwrap();
+
+        for asset in assets {
+            let local_path = asset
+                .local_path
+                .strip_prefix("images/")
+                .unwrap_or(&asset.local_path);
+
+            let mut file_path = PathBuf::from(meta_path);
+            file_path.push(local_path);
+
+            if tokio::fs::remove_file(file_path).await.is_ok() {
+                images += 1;
+            }
+        }
+    }
+
+    Ok(reply::json(&json!({
+        "tmdb": tmdb,
+        "images": images,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/admin/maintenance/prune_progress` deletes progress entries
+/// pointing at media that no longer exists and returns the number of entries removed. Only the
+/// owner can call this route.
+pub async fn prune_progress(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    progress_buffer::flush(&conn).await?;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let removed = Progress::prune_orphans(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(reply::json(&json!({ "removed": removed })))
+}
+
+#[derive(Deserialize)]
+pub struct PurgeArgs {
+    older_than_days: u32,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    delete_files: bool,
+    confirm: Option<String>,
+}
+
+/// Computes the token confirming a purge of exactly `media`. It changes whenever the set of media
+/// to purge changes, thus a purge only goes through if the operator reviewed what gets deleted.
+fn purge_token(media: &[i64], delete_files: bool) -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+    use std::hash::Hasher;
+
+    let mut hasher = DefaultHasher::new();
+    media.hash(&mut hasher);
+    delete_files.hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// Method mapped to `POST /api/v1/admin/maintenance/purge_watched` purges movies and episodes
+/// every user has watched to completion and nobody touched in the last `older_than_days` days,
+/// ie to reclaim disk space. Only the owner can call this route.
+///
+/// With `dry_run` set nothing is deleted, instead the media that would be purged are returned
+/// along with a `confirmation_token`. The purge itself requires passing that token as `confirm`,
+/// if the media to purge changed since the dry run the request is rejected. Media are removed from
+/// the database and, if `delete_files` is set, their files are deleted from disk. Episodes
+/// sharing a file with other episodes are never purged. A `EventMediaPurged` event is emitted for
+/// every purged media.
+///
+/// # Arguments
+/// * `older_than_days` - only purge media nobody watched in this many days
+/// * `dry_run` - only list the media that would be purged
+/// * `delete_files` - also delete the files of purged media from disk
+/// * `confirm` - confirmation token returned by the dry run
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "dry_run": bool,
+///     "confirmation_token": string,
+///     "media": [
+///         {
+///             "id": int,
+///             "name": string,
+///             "media_type": "movie" | "episode",
+///             "last_watched": int,
+///             "files": [string],
+///         }
+///     ],
+/// }
+/// ```
+pub async fn purge_watched(
+    conn: DbConnection,
+    event_tx: EventTx,
+    args: PurgeArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    progress_buffer::flush(&conn).await?;
+
+    let cutoff = chrono::Utc::now().timestamp() - args.older_than_days as i64 * 24 * 60 * 60;
+
+    let mut candidates = Vec::new();
+    {
+        let mut tx = conn.read().begin().await?;
+        for media in Progress::get_watched_by_all(&mut tx, cutoff).await? {
+            let files = MediaFile::get_of_media(&mut tx, media.id).await?;
+
+            if files.iter().any(|x| x.episode_count() > 1) {
+                continue;
+            }
+
+            candidates.push((media, files));
+        }
+    }
+
+    let ids = candidates.iter().map(|(x, _)| x.id).collect::<Vec<_>>();
+    let token = purge_token(&ids, args.delete_files);
+
+    if !args.dry_run {
+        if args.confirm.as_deref() != Some(token.as_str()) {
+            return Err(errors::DimError::InvalidConfirmation);
+        }
+
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+
+        for (media, files) in candidates.iter() {
+            for file in files {
+                MediaFile::delete(&mut tx, file.id).await?;
+            }
+
+            Media::delete(&mut tx, media.id).await?;
+        }
+
+        tx.commit().await?;
+        drop(lock);
+
+        for (media, files) in candidates.iter() {
+            let mut files_removed = 0;
+
+            if args.delete_files {
+                for file in files {
+                    match tokio::fs::remove_file(&file.target_file).await {
+                        Ok(_) => files_removed += 1,
+                        Err(e) => {
+                            error!(file = %file.target_file, reason = ?e, "Failed to delete purged file.")
+                        }
+                    }
+                }
+            }
+
+            info!(id = media.id, name = %media.name, files_removed, "Purged watched media.");
+
+            let event = Message {
+                id: media.id,
+                event_type: PushEventType::EventMediaPurged { files_removed },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+    }
+
+    let media = candidates
+        .iter()
+        .map(|(media, files)| {
+            json!({
+                "id": media.id,
+                "name": media.name,
+                "media_type": media.media_type,
+                "last_watched": media.last_watched,
+                "files": files.iter().map(|x| &x.target_file).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&json!({
+        "dry_run": args.dry_run,
+        "confirmation_token": token,
+        "media": media,
+    })))
+}
+
+/// Number of media whose cached duration is recomputed per transaction.
+const RECOMPUTE_BATCH_SIZE: i64 = 500;
+
+/// Method mapped to `POST /api/v1/admin/maintenance/recompute_durations` recomputes the cached
+/// duration of every media from its mediafiles, ie after the way durations are computed changed.
+/// The work is queued as a background task and done in batches, each batch is committed on its
+/// own so cancelling the task with `DELETE /api/v1/tasks/<id>` keeps the batches already done.
+///
+/// A `EventMaintenanceProgress` event tagged with the task id is emitted after every batch. Only
+/// the owner can call this route.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "task_id": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn recompute_durations(
+    conn: DbConnection,
+    event_tx: EventTx,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let total = {
+        let mut tx = conn.read().begin().await?;
+        Media::count(&mut tx).await?
+    };
+
+    let task_id = crate::tasks::submit_with("Recompute durations", move |task_id| async move {
+        let mut after = 0;
+        let mut processed = 0;
+        let mut updated = 0;
+
+        loop {
+            let mut lock = conn.writer().lock_owned().await;
+            let mut tx = match database::write_tx(&mut lock).await {
+                Ok(x) => x,
+                Err(e) => {
+                    error!(reason = ?e, "Failed to recompute durations.");
+                    return;
+                }
+            };
+
+            let (last, changed) =
+                match Media::recompute_cached_durations(&mut tx, after, RECOMPUTE_BATCH_SIZE).await
+                {
+                    Ok(Some(x)) => x,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!(reason = ?e, "Failed to recompute durations.");
+                        return;
+                    }
+                };
+
+            if let Err(e) = tx.commit().await {
+                error!(reason = ?e, "Failed to recompute durations.");
+                return;
+            }
+
+            drop(lock);
+
+            // the last batch can be smaller, thus we count what was actually processed.
+            processed = (processed + RECOMPUTE_BATCH_SIZE).min(total);
+            updated += changed as i64;
+            after = last;
+
+            let event = Message {
+                id: task_id as i64,
+                event_type: PushEventType::EventMaintenanceProgress {
+                    processed,
+                    total,
+                    updated,
+                },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+
+        info!(updated, "Recomputed cached durations.");
+    });
+
+    Ok(reply::json(&json!({
+        "task_id": task_id,
+        "total": total,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/admin/maintenance/backfill_posters` fetches the poster of every
+/// media matched against TMDB that has none, ie libraries scanned before artwork could be
+/// fetched. Unlike a rescan only the poster is touched. Media whose poster was overridden by a
+/// user are skipped, as are media being rematched or refreshed at the same time.
+///
+/// The work is queued as a background task which can be cancelled with
+/// `DELETE /api/v1/tasks/<id>`, posters already fetched are kept. A `EventMaintenanceProgress`
+/// event tagged with the task id is emitted after every media. Only the owner can call this
+/// route.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "task_id": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn backfill_posters(
+    conn: DbConnection,
+    event_tx: EventTx,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let ids = {
+        let mut tx = conn.read().begin().await?;
+        Media::get_missing_poster(&mut tx).await?
+    };
+
+    let total = ids.len() as i64;
+
+    let task_id = crate::tasks::submit_with("Backfill posters", move |task_id| async move {
+        let mut updated = 0;
+
+        for (processed, id) in ids.into_iter().enumerate() {
+            match backfill_poster(&conn, id).await {
+                Ok(true) => updated += 1,
+                Ok(false) => {}
+                Err(e) => error!(media_id = id, reason = ?e, "Failed to backfill poster."),
+            }
+
+            let event = Message {
+                id: task_id as i64,
+                event_type: PushEventType::EventMaintenanceProgress {
+                    processed: processed as i64 + 1,
+                    total,
+                    updated,
+                },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+
+        info!(updated, "Backfilled posters.");
+    });
+
+    Ok(reply::json(&json!({
+        "task_id": task_id,
+        "total": total,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/admin/maintenance/backfill_accent_colors` computes the accent
+/// color of every media that has a poster but no accent color, ie media scanned before accent
+/// colors were computed during scans. See [`accent_color`](crate::accent_color).
+///
+/// The work is queued as a background task which can be cancelled with
+/// `DELETE /api/v1/tasks/<id>`, colors already computed are kept. A `EventMaintenanceProgress`
+/// event tagged with the task id is emitted after every media. Only the owner can call this
+/// route.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "task_id": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn backfill_accent_colors(
+    conn: DbConnection,
+    event_tx: EventTx,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let ids = {
+        let mut tx = conn.read().begin().await?;
+        Media::get_missing_accent_color(&mut tx).await?
+    };
+
+    let total = ids.len() as i64;
+
+    let task_id = crate::tasks::submit_with("Backfill accent colors", move |task_id| async move {
+        let mut updated = 0;
+
+        for (processed, id) in ids.into_iter().enumerate() {
+            match crate::accent_color::update(&conn, id).await {
+                Ok(true) => updated += 1,
+                Ok(false) => {}
+                Err(e) => error!(media_id = id, reason = ?e, "Failed to compute accent color."),
+            }
+
+            let event = Message {
+                id: task_id as i64,
+                event_type: PushEventType::EventMaintenanceProgress {
+                    processed: processed as i64 + 1,
+                    total,
+                    updated,
+                },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+
+        info!(updated, "Backfilled accent colors.");
+    });
+
+    Ok(reply::json(&json!({
+        "task_id": task_id,
+        "total": total,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/admin/maintenance/backfill_captions` probes every mediafile
+/// without a closed caption or SDH track again and records the tracks found, ie files scanned
+/// before caption tracks were detected. Rescans dont probe files already in the database, thus
+/// this is the only way to update them short of removing the library.
+///
+/// The work is queued as a background task which can be cancelled with
+/// `DELETE /api/v1/tasks/<id>`, files already probed are kept. A `EventMaintenanceProgress` event
+/// tagged with the task id is emitted after every file. Only the owner can call this route.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "task_id": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn backfill_captions(
+    conn: DbConnection,
+    event_tx: EventTx,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let ids = {
+        let mut tx = conn.read().begin().await?;
+        MediaFile::get_without_captions(&mut tx).await?
+    };
+
+    let total = ids.len() as i64;
+
+    let task_id = crate::tasks::submit_with("Backfill captions", move |task_id| async move {
+        let mut updated = 0;
+
+        for (processed, id) in ids.into_iter().enumerate() {
+            match backfill_file_captions(&conn, id).await {
+                Ok(true) => updated += 1,
+                Ok(false) => {}
+                Err(e) => error!(mediafile_id = id, reason = ?e, "Failed to backfill captions."),
+            }
+
+            let event = Message {
+                id: task_id as i64,
+                event_type: PushEventType::EventMaintenanceProgress {
+                    processed: processed as i64 + 1,
+                    total,
+                    updated,
+                },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+
+        info!(updated, "Backfilled captions.");
+    });
+
+    Ok(reply::json(&json!({
+        "task_id": task_id,
+        "total": total,
+    })))
+}
+
+/// Probes a single mediafile for caption tracks and stores them. Returns whether any were found.
+async fn backfill_file_captions(conn: &DbConnection, id: i64) -> Result<bool, errors::DimError> {
+    let target_file = {
+        let mut tx = conn.read().begin().await?;
+        MediaFile::get_one(&mut tx, id).await?.target_file
+    };
+
+    let probe = spawn_blocking(move || {
+        FFProbeCtx::new(&crate::streaming::FFPROBE_BIN).get_meta(&target_file)
+    })
+    .await;
+
+    let probe = match probe {
+        Ok(Ok(x)) => x,
+        _ => return Ok(false),
+    };
+
+    let (has_cc, has_sdh) = (probe.has_cc(), probe.has_sdh());
+
+    if !has_cc && !has_sdh {
+        return Ok(false);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    UpdateMediaFile {
+        has_cc: Some(has_cc),
+        has_sdh: Some(has_sdh),
+        ..Default::default()
+    }
+    .update(&mut tx, id)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Number of media reindexed per transaction.
+const REINDEX_BATCH_SIZE: i64 = 500;
+
+/// Method mapped to `POST /api/v1/admin/maintenance/reindex_search` rebuilds the full-text index
+/// searches by name go through from the current media, ie after bulk imports. The work is queued
+/// as a background task and done in batches, each batch is committed on its own so cancelling
+/// the task with `DELETE /api/v1/tasks/<id>` keeps the batches already done.
+///
+/// While the rebuild is running searches fall back to matching names with `LIKE`, see
+/// [`search_index`](crate::search_index). A `EventMaintenanceProgress` event tagged with the task
+/// id is emitted after every batch, `updated` being the number of media indexed so far. Returns
+/// `409` if a rebuild is already running. Only the owner can call this route.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "task_id": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn reindex_search(
+    conn: DbConnection,
+    event_tx: EventTx,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let rebuild = search_index::Rebuild::start().ok_or(errors::DimError::ReindexInProgress)?;
+
+    let total = {
+        let mut tx = conn.read().begin().await?;
+        SearchIndex::count_indexable(&mut tx).await?
+    };
+
+    let task_id = crate::tasks::submit_with("Rebuild search index", move |task_id| async move {
+        // searches use the index again once the task is done or cancelled.
+        let _rebuild = rebuild;
+        let mut after = 0;
+        let mut indexed = 0;
+
+        loop {
+            let mut lock = conn.writer().lock_owned().await;
+            let mut tx = match database::write_tx(&mut lock).await {
+                Ok(x) => x,
+                Err(e) => {
+                    error!(reason = ?e, "Failed to rebuild the search index.");
+                    return;
+                }
+            };
+
+            let batch = match SearchIndex::reindex(&mut tx, after, REINDEX_BATCH_SIZE).await {
+                Ok(x) => x,
+                Err(e) => {
+                    error!(reason = ?e, "Failed to rebuild the search index.");
+                    return;
+                }
+            };
+
+            // entries past the last media belong to media that no longer exist.
+            if batch.is_none() {
+                if let Err(e) = SearchIndex::prune(&mut tx, after).await {
+                    error!(reason = ?e, "Failed to rebuild the search index.");
+                    return;
+                }
+            }
+
+            if let Err(e) = tx.commit().await {
+                error!(reason = ?e, "Failed to rebuild the search index.");
+                return;
+            }
+
+            drop(lock);
+
+            let (last, count) = match batch {
+                Some(x) => x,
+                None => break,
+            };
+
+            after = last;
+            indexed += count as i64;
+
+            let event = Message {
+                id: task_id as i64,
+                event_type: PushEventType::EventMaintenanceProgress {
+                    processed: indexed.min(total),
+                    total,
+                    updated: indexed,
+                },
+            };
+
+            let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+        }
+
+        info!(indexed, "Rebuilt the search index.");
+    });
+
+    Ok(reply::json(&json!({
+        "task_id": task_id,
+        "total": total,
+    })))
+}
+
+/// Fetches the poster of the media `id` from TMDB and sets it. Returns whether a poster was set,
+/// media that are locked or have no poster on TMDB are left alone.
+async fn backfill_poster(conn: &DbConnection, id: i64) -> Result<bool, errors::DimError> {
+    let _lock = match MediaLock::try_acquire(id) {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+
+    let (media, agent, tmdb_id) = {
+        let mut tx = conn.read().begin().await?;
+        let media = Media::get(&mut tx, id).await?;
+        let agent = Library::get_agent(&mut tx, media.library_id).await?;
+        let tmdb_id = Media::get_tmdb_id(&mut tx, id)
+            .await?
+            .ok_or(errors::DimError::NoTmdbId)?;
+
+        (media, agent, tmdb_id)
+    };
+
+    let mut tmdb = crate::scanners::metadata_agent(agent, media.media_type);
+
+    let result: crate::scanners::ApiMedia = tmdb.search_by_id(tmdb_id as i32).await?.into();
+
+    let poster_path = match result.poster_path {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+
+    crate::fetcher::insert_into_queue(poster_path.clone(), 3).await;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    let poster = InsertableAsset {
+        remote_url: Some(poster_path),
+        local_path: crate::scanners::format_path(result.poster_file),
+        file_ext: "jpg".into(),
+    }
+    .insert(&mut tx)
+    .await?;
+
+    UpdateMedia {
+        poster: Some(poster.id),
+        ..Default::default()
+    }
+    .update_automatic(&mut tx, id)
+    .await?;
+
+    tx.commit().await?;
+
+    crate::accent_color::queue(conn.clone(), id);
+
+    Ok(true)
+}
+
+/// Method mapped to `GET /api/v1/search` searches the non-episode media by name, genre or release
+/// year. Results are wrapped in a [`Paginated`](Paginated) envelope unless `flat` is set.
+///
+/// `query` is matched against the alternate titles TMDB knows a media under too, thus media can
+/// be found by their foreign titles.
+///
+/// If `include_cast` is set, `query` is also matched against the names of the cast. Media
+/// matched by their cast carry the name of the matched person in `matched_person` and are
+/// ranked below media matched by their name.
+///
+/// Media the user hid are left out unless `include_hidden` is set.
+///
+/// Searches by name match every word of `query` against the start of the words of the names,
+/// ie `godfa` finds `The Godfather`. While the search index is being rebuilt, and when
+/// `include_cast` is set, `query` is matched anywhere in the names instead.
+///
+/// # Arguments
+/// * `query` - name to search for
+/// * `year` - release year to search for
+/// * `genre` - name of the genre to search for
+/// * `include_cast` - whether to also match `query` against the cast
+/// * `include_hidden` - whether to include media the user hid
+/// * `page` - pagination arguments
 pub async fn search(
     conn: DbConnection,
     query: Option<String>,
@@ -138,10 +1231,24 @@ pub async fn search(
     _library_id: Option<i32>,
     genre: Option<String>,
     _quick: Option<bool>,
-    _user: Auth,
+    include_cast: bool,
+    include_hidden: bool,
+    page: PageArgs,
+    user: Auth,
 ) -> Result<warp::reply::Json, errors::DimError> {
     let mut tx = conn.read().begin().await?;
+    let hidden = Hidden {
+        uid: user.0.claims.get_user(),
+        include: include_hidden,
+    };
+
     if let Some(query_string) = query {
+        if !include_cast && search_index::is_available() {
+            if let Some(x) = search_index::fts_query(&query_string) {
+                return search_by_name_indexed(&mut tx, &x, &hidden, page).await;
+            }
+        }
+
         let query_string = query_string
             .split(' ')
             .map(|x| format!("%{}%", x))
@@ -149,25 +1256,96 @@ pub async fn search(
             .as_slice()
             .join(" ");
 
-        return search_by_name(&mut tx, &query_string, 15).await;
+        if include_cast {
+            return search_by_name_and_cast(&mut tx, &query_string, &hidden, page).await;
+        }
+
+        return search_by_name(&mut tx, &query_string, &hidden, page).await;
     }
 
     if let Some(x) = genre {
         let genre_id = Genre::get_by_name(&mut tx, x).await?.id;
-        return search_by_genre(&mut tx, genre_id).await;
+        return search_by_genre(&mut tx, genre_id, &hidden, page).await;
     }
 
     if let Some(x) = year {
-        return search_by_release_year(&mut tx, x as i64).await;
+        return search_by_release_year(&mut tx, x as i64, &hidden, page).await;
     }
 
     Err(errors::DimError::NotFoundError)
 }
 
+/// Whose hidden media to leave out of search results.
+struct Hidden {
+    uid: String,
+    /// Whether to include hidden media after all.
+    include: bool,
+}
+
+/// Searches media by name through the full-text index, `query` being a FTS5 query.
+async fn search_by_name_indexed(
+    conn: &mut database::Transaction<'_>,
+    query: &str,
+    hidden: &Hidden,
+    page: PageArgs,
+) -> Result<warp::reply::Json, errors::DimError> {
+    #[derive(Serialize)]
+    struct Record {
+        id: i64,
+        library_id: i64,
+        name: String,
+        poster_path: Option<String>,
+    }
+
+    let (limit, offset) = (page.limit(), page.offset());
+    let data = sqlx::query_as!(
+        Record,
+        r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path FROM _tblmedia
+           LEFT JOIN assets on _tblmedia.poster = assets.id
+           WHERE NOT media_type = "episode"
+           AND _tblmedia.id IN (SELECT rowid FROM media_search WHERE media_search MATCH $1)
+           AND ($4 OR NOT EXISTS (
+               SELECT 1 FROM hidden_media
+               WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $5
+           ))
+           ORDER BY COALESCE(sort_title, name)
+           LIMIT $2 OFFSET $3"#,
+        query,
+        limit,
+        offset,
+        hidden.include,
+        hidden.uid
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "total!: i64" FROM _tblmedia
+           WHERE NOT media_type = "episode"
+           AND _tblmedia.id IN (SELECT rowid FROM media_search WHERE media_search MATCH $1)
+           AND ($2 OR NOT EXISTS (
+               SELECT 1 FROM hidden_media
+               WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $3
+           ))"#,
+        query,
+        hidden.include,
+        hidden.uid
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .total;
+
+    Ok(Paginated::new(data, total, &page).into_reply(page.flat))
+}
+
+/// Searches media by matching their name against `query`, a `LIKE` pattern. Used while the
+/// full-text index is being rebuilt.
 async fn search_by_name(
     conn: &mut database::Transaction<'_>,
     query: &str,
-    limit: i64,
+    hidden: &Hidden,
+    page: PageArgs,
 ) -> Result<warp::reply::Json, errors::DimError> {
     #[derive(Serialize)]
     struct Record {
@@ -177,26 +1355,156 @@ async fn search_by_name(
         poster_path: Option<String>,
     }
 
+    let (limit, offset) = (page.limit(), page.offset());
     let data = sqlx::query_as!(
         Record,
         r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path FROM _tblmedia
            LEFT JOIN assets on _tblmedia.poster = assets.id
            WHERE NOT media_type = "episode"
-           AND UPPER(name) LIKE ?
-           LIMIT ?"#,
+           AND (UPPER(name) LIKE $1 OR EXISTS (
+               SELECT 1 FROM alternate_title
+               WHERE alternate_title.media_id = _tblmedia.id
+               AND UPPER(alternate_title.title) LIKE $1
+           ))
+           AND ($4 OR NOT EXISTS (
+               SELECT 1 FROM hidden_media
+               WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $5
+           ))
+           ORDER BY COALESCE(sort_title, name)
+           LIMIT $2 OFFSET $3"#,
+        query,
+        limit,
+        offset,
+        hidden.include,
+        hidden.uid
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "total!: i64" FROM _tblmedia
+           WHERE NOT media_type = "episode"
+           AND (UPPER(name) LIKE $1 OR EXISTS (
+               SELECT 1 FROM alternate_title
+               WHERE alternate_title.media_id = _tblmedia.id
+               AND UPPER(alternate_title.title) LIKE $1
+           ))
+           AND ($2 OR NOT EXISTS (
+               SELECT 1 FROM hidden_media
+               WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $3
+           ))"#,
         query,
-        limit
+        hidden.include,
+        hidden.uid
     )
-    .fetch_all(conn)
+    .fetch_one(&mut *conn)
+    .await?
+    .total;
+
+    Ok(Paginated::new(data, total, &page).into_reply(page.flat))
+}
+
+async fn search_by_name_and_cast(
+    conn: &mut database::Transaction<'_>,
+    query: &str,
+    hidden: &Hidden,
+    page: PageArgs,
+) -> Result<warp::reply::Json, errors::DimError> {
+    #[derive(Serialize, sqlx::FromRow)]
+    struct Record {
+        id: i64,
+        library_id: i64,
+        name: String,
+        poster_path: Option<String>,
+        /// Name of the cast member matching the query, `None` if the name of the media matched.
+        matched_person: Option<String>,
+    }
+
+    // media matched by name or by one of their alternate titles get rank 0 and thus come before
+    // media only matched by their cast.
+    const MATCHES: &str = r#"SELECT _tblmedia.id, _tblmedia.library_id, _tblmedia.name,
+            _tblmedia.sort_title, assets.local_path as poster_path, NULL as matched_person,
+            0 as rank
+        FROM _tblmedia
+        LEFT JOIN assets on _tblmedia.poster = assets.id
+        WHERE NOT media_type = "episode"
+        AND (UPPER(_tblmedia.name) LIKE $1 OR EXISTS (
+            SELECT 1 FROM alternate_title
+            WHERE alternate_title.media_id = _tblmedia.id
+            AND UPPER(alternate_title.title) LIKE $1
+        ))
+        UNION ALL
+        SELECT _tblmedia.id, _tblmedia.library_id, _tblmedia.name,
+            _tblmedia.sort_title, assets.local_path as poster_path,
+            MIN(people.name) as matched_person, 1 as rank
+        FROM _tblmedia
+        LEFT JOIN assets on _tblmedia.poster = assets.id
+        INNER JOIN media_cast ON media_cast.media_id = _tblmedia.id
+        INNER JOIN people ON people.id = media_cast.person_id
+        WHERE NOT media_type = "episode"
+        AND UPPER(people.name) LIKE $1
+        AND NOT UPPER(_tblmedia.name) LIKE $1
+        AND NOT EXISTS (
+            SELECT 1 FROM alternate_title
+            WHERE alternate_title.media_id = _tblmedia.id
+            AND UPPER(alternate_title.title) LIKE $1
+        )
+        GROUP BY _tblmedia.id"#;
+
+    const NOT_HIDDEN: &str = "NOT EXISTS (
+            SELECT 1 FROM hidden_media
+            WHERE hidden_media.media_id = matches.id AND hidden_media.user_id = {uid}
+        )";
+
+    // media in hidden libraries, ie libraries that are being deleted, are never returned.
+    const IN_VISIBLE_LIBRARY: &str = "EXISTS (
+            SELECT 1 FROM library
+            WHERE library.id = matches.library_id AND NOT library.hidden
+        )";
+
+    let (limit, offset) = (page.limit(), page.offset());
+
+    // FIXME: sqlx cant infer the nullability of columns of a compound select thus we cant use
+    // the `query_as!` macro here.
+    let data: Vec<Record> = sqlx::query_as(&format!(
+        "SELECT id, library_id, name, poster_path, matched_person FROM ({}) matches
+        WHERE ($4 OR {}) AND {}
+        ORDER BY rank ASC, COALESCE(sort_title, name) ASC
+        LIMIT $2 OFFSET $3",
+        MATCHES,
+        NOT_HIDDEN.replace("{uid}", "$5"),
+        IN_VISIBLE_LIBRARY
+    ))
+    .bind(query)
+    .bind(limit)
+    .bind(offset)
+    .bind(hidden.include)
+    .bind(&hidden.uid)
+    .fetch_all(&mut *conn)
     .await
     .map_err(|_| errors::DimError::NotFoundError)?;
 
-    Ok(reply::json(&data))
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM ({}) matches WHERE ($2 OR {}) AND {}",
+        MATCHES,
+        NOT_HIDDEN.replace("{uid}", "$3"),
+        IN_VISIBLE_LIBRARY
+    ))
+    .bind(query)
+    .bind(hidden.include)
+    .bind(&hidden.uid)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Paginated::new(data, total, &page).into_reply(page.flat))
 }
 
 async fn search_by_genre(
     conn: &mut database::Transaction<'_>,
     genre_id: i64,
+    hidden: &Hidden,
+    page: PageArgs,
 ) -> Result<warp::reply::Json, errors::DimError> {
     #[derive(Serialize)]
     struct Record {
@@ -206,6 +1514,7 @@ async fn search_by_genre(
         poster_path: Option<String>,
     }
 
+    let (limit, offset) = (page.limit(), page.offset());
     let data = sqlx::query_as!(
         Record,
         r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path
@@ -213,20 +1522,49 @@ async fn search_by_genre(
                 LEFT JOIN assets on _tblmedia.poster = assets.id
                 INNER JOIN genre_media ON genre_media.media_id = _tblmedia.id
                 WHERE NOT media_type = "episode"
-                AND genre_media.genre_id = ?
+                AND genre_media.genre_id = $1
+                AND ($4 OR NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $5
+                ))
+                ORDER BY COALESCE(sort_title, name)
+                LIMIT $2 OFFSET $3
                 "#,
         genre_id,
+        limit,
+        offset,
+        hidden.include,
+        hidden.uid
     )
-    .fetch_all(conn)
+    .fetch_all(&mut *conn)
     .await
     .map_err(|_| errors::DimError::NotFoundError)?;
 
-    Ok(reply::json(&data))
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "total!: i64" FROM _tblmedia
+                INNER JOIN genre_media ON genre_media.media_id = _tblmedia.id
+                WHERE NOT media_type = "episode"
+                AND genre_media.genre_id = $1
+                AND ($2 OR NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $3
+                ))"#,
+        genre_id,
+        hidden.include,
+        hidden.uid
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .total;
+
+    Ok(Paginated::new(data, total, &page).into_reply(page.flat))
 }
 
 async fn search_by_release_year(
     conn: &mut database::Transaction<'_>,
     year: i64,
+    hidden: &Hidden,
+    page: PageArgs,
 ) -> Result<warp::reply::Json, errors::DimError> {
     #[derive(Serialize)]
     struct Record {
@@ -236,19 +1574,46 @@ async fn search_by_release_year(
         poster_path: Option<String>,
     }
 
+    let (limit, offset) = (page.limit(), page.offset());
     let data = sqlx::query_as!(
         Record,
         r#"SELECT _tblmedia.id, library_id, name, assets.local_path as poster_path
                 FROM _tblmedia
             LEFT JOIN assets on _tblmedia.poster = assets.id
                 WHERE NOT media_type = "episode"
-                AND year = ?
+                AND year = $1
+                AND ($4 OR NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $5
+                ))
+                ORDER BY COALESCE(sort_title, name)
+                LIMIT $2 OFFSET $3
                 "#,
         year,
+        limit,
+        offset,
+        hidden.include,
+        hidden.uid
     )
-    .fetch_all(conn)
+    .fetch_all(&mut *conn)
     .await
     .map_err(|_| errors::DimError::NotFoundError)?;
 
-    Ok(warp::reply::json(&data))
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "total!: i64" FROM _tblmedia
+                WHERE NOT media_type = "episode"
+                AND year = $1
+                AND ($2 OR NOT EXISTS (
+                    SELECT 1 FROM hidden_media
+                    WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $3
+                ))"#,
+        year,
+        hidden.include,
+        hidden.uid
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .total;
+
+    Ok(Paginated::new(data, total, &page).into_reply(page.flat))
 }
@@ -0,0 +1,67 @@
+use crate::core::DbConnection;
+use crate::errors;
+
+use auth::Wrapper as Auth;
+
+use database::keyword::Keyword;
+
+use serde_json::json;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn get_keyword_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "keyword" / i64 / "media")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::get_keyword_media(conn, id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+}
+
+/// Method mapped to `GET /api/v1/keyword/<id>/media` returns the keyword along with all media
+/// tagged with it, sorted by name.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the keyword
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "media": [Media],
+/// }
+/// ```
+pub async fn get_keyword_media(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let keyword = Keyword::get_by_id(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let media = Keyword::get_media(&mut tx, id, user.0.claims.get_user_ref()).await?;
+
+    Ok(reply::json(&json!({
+        "id": keyword.id,
+        "name": keyword.name,
+        "media": media,
+    })))
+}
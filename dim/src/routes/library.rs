@@ -1,13 +1,18 @@
 use crate::core::DbConnection;
 use crate::core::EventTx;
 use crate::errors;
+use crate::json;
+use crate::routes::pagination::PageArgs;
+use crate::routes::pagination::Paginated;
 use crate::scanners;
 use crate::scanners::scanner_daemon::FsWatcher;
 
 use auth::Wrapper as Auth;
 
+use database::genre::Genre;
 use database::library::InsertableLibrary;
 use database::library::Library;
+use database::library::MetadataAgent;
 use database::media::Media;
 use database::mediafile::MediaFile;
 
@@ -20,6 +25,7 @@ use std::path::Path;
 use warp::http::StatusCode;
 use warp::reply;
 
+use serde::Deserialize;
 use serde::Serialize;
 
 use tracing::error;
@@ -27,6 +33,7 @@ use tracing::info;
 use tracing::instrument;
 
 pub mod filters {
+    use serde::Deserialize;
     use warp::reject;
     use warp::Filter;
 
@@ -47,7 +54,7 @@ pub mod filters {
         warp::path!("api" / "v1" / "library")
             .and(warp::get())
             .and(with_db(conn))
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and_then(|conn, auth| async move {
                 super::library_get(conn, auth)
                     .await
@@ -77,6 +84,24 @@ pub mod filters {
             )
     }
 
+    pub fn validate_path(
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct PathArgs {
+            path: String,
+        }
+
+        warp::path!("api" / "v1" / "library" / "validate_path")
+            .and(warp::post())
+            .and(warp::body::json::<PathArgs>())
+            .and(auth::with_auth())
+            .and_then(|PathArgs { path }: PathArgs, user: Auth| async move {
+                super::validate_path(path, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
     pub fn library_delete(
         conn: DbConnection,
         event_tx: EventTx,
@@ -95,12 +120,42 @@ pub mod filters {
             )
     }
 
+    pub fn library_rescan(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "library" / i64 / "rescan")
+            .and(warp::post())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |id: i64, user: Auth, conn: DbConnection, event_tx: EventTx| async move {
+                    super::library_rescan(id, user, conn, event_tx)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn scan_status() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+    {
+        warp::path!("api" / "v1" / "library" / i64 / "scan_status")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, user: Auth| async move {
+                super::scan_status(id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
     pub fn library_get_self(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "library" / i64)
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
                 super::get_self(conn, id, user)
@@ -112,12 +167,54 @@ pub mod filters {
     pub fn get_all_of_library(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            #[serde(default)]
+            include_hidden: bool,
+        }
+
         warp::path!("api" / "v1" / "library" / i64 / "media")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and(warp::query::query::<PageArgs>())
+            .and(warp::query::query::<RouteArgs>())
+            .and_then(
+                |id: i64,
+                 user: Auth,
+                 conn: DbConnection,
+                 page: PageArgs,
+                 RouteArgs { include_hidden }: RouteArgs| async move {
+                    super::get_all_library(conn, id, page, include_hidden, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_genre_stats(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "library" / i64 / "genre_stats")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
-                super::get_all_library(conn, id, user)
+                super::get_genre_stats(conn, id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_library_size(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "library" / i64 / "size")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::get_library_size(conn, id, user)
                     .await
                     .map_err(|e| reject::custom(e))
             })
@@ -128,7 +225,7 @@ pub mod filters {
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "library" / i64 / "unmatched")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
                 super::get_all_unmatched_media(conn, id, user)
@@ -136,6 +233,42 @@ pub mod filters {
                     .map_err(|e| reject::custom(e))
             })
     }
+
+    pub fn get_library_agent(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "library" / i64 / "agent")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::get_library_agent(conn, id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn set_library_agent(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "library" / i64 / "agent")
+            .and(warp::put())
+            .and(warp::body::json::<AgentArgs>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, data: AgentArgs, user: Auth, conn: DbConnection| async move {
+                    super::set_library_agent(conn, id, data, user)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AgentArgs {
+    pub agent: MetadataAgent,
 }
 
 /// Method maps to `GET /api/v1/library` and returns a list of all libraries in te database.
@@ -161,6 +294,15 @@ pub async fn library_get(
 /// scanner for it, then dispatches a event to all clients notifying them that a new library has
 /// been created. This method can only be accessed by authenticated users. Method returns 200 OK
 ///
+/// Tv libraries created with `anime` set resolve absolute episode numbers, ie `Show - 125`, to a
+/// season and episode, and navigate episodes by their absolute order.
+///
+/// `language` is the language of the titles in the library, ie `de`, and decides which leading
+/// articles are ignored when sorting its media. It defaults to english.
+///
+/// Returns `403` if a location isnt within the `library_roots` from the global settings, see
+/// [`validate_path`](validate_path).
+///
 /// # Arguments
 /// * `conn` - database connection
 /// * `new_library` - new library information posted by client
@@ -172,6 +314,15 @@ pub async fn library_post(
     event_tx: EventTx,
     _user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    let roots = crate::routes::settings::get_global_settings().library_roots;
+    if !new_library
+        .locations
+        .iter()
+        .all(|x| scanners::is_allowed_location(Path::new(x), &roots))
+    {
+        return Err(errors::DimError::LocationNotAllowed);
+    }
+
     let mut lock = conn.writer().lock_owned().await;
     let mut tx = database::write_tx(&mut lock).await?;
     let id = new_library.insert(&mut tx).await?;
@@ -182,7 +333,10 @@ pub async fn library_post(
 
     let fs_watcher = FsWatcher::new(conn.clone(), id, new_library.media_type, tx_clone.clone());
     tokio::spawn(async move { fs_watcher.start_daemon().await });
-    tokio::spawn(scanners::start(conn, id, tx_clone));
+    let scan = scanners::start(conn, id, tx_clone);
+    crate::tasks::submit(format!("Scan library {}", id), async move {
+        let _ = scan.await;
+    });
 
     let event = Message {
         id,
@@ -194,6 +348,72 @@ pub async fn library_post(
     Ok(StatusCode::CREATED)
 }
 
+/// Method mapped to `POST /api/v1/library/validate_path` checks whether a library could be
+/// scanned from `path` before creating it. Reports whether the path exists, is a directory and
+/// can be read, along with the number of media files a scan would pick up. Nothing is imported.
+/// Only the owner can access this route.
+///
+/// Returns `403` if `path` is relative, contains `..` or isnt within the `library_roots` from the
+/// global settings when any are configured.
+///
+/// # Arguments
+/// * `path` - path on the host the library would be located at
+/// * `user` - Auth middleware
+///
+/// # Data
+/// ```text
+/// {
+///     "path": string,
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "path": string,
+///     "exists": bool,
+///     "is_dir": bool,
+///     "readable": bool,
+///     "media_files": int,
+/// }
+/// ```
+pub async fn validate_path(path: String, user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let roots = crate::routes::settings::get_global_settings().library_roots;
+    if !scanners::is_allowed_location(Path::new(&path), &roots) {
+        return Err(errors::DimError::LocationNotAllowed);
+    }
+
+    let location = Path::new(&path);
+    let exists = location.exists();
+    let is_dir = location.is_dir();
+    let readable = if is_dir {
+        std::fs::read_dir(location).is_ok()
+    } else {
+        std::fs::File::open(location).is_ok()
+    };
+
+    let media_files = if is_dir && readable {
+        scanners::get_subfiles(std::iter::once(location))
+            .await
+            .map(|x| x.len())
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    Ok(reply::json(&json!({
+        "path": path,
+        "exists": exists,
+        "is_dir": is_dir,
+        "readable": readable,
+        "media_files": media_files,
+    })))
+}
+
 /// Method mapped to `DELETE /api/v1/library/<id>` is used to delete a library from the database.
 /// It deletes the database based on the parameter `id`, then dispatches a event notifying all
 /// clients that the database with this id has been removed. Method can only be accessed by
@@ -249,11 +469,83 @@ pub async fn library_delete(
 
     let _ = event_tx.send(serde_json::to_string(&event).unwrap());
 
-    tokio::spawn(delete_lib_fut);
+    crate::tasks::submit_uncancelable(format!("Delete library {}", id), delete_lib_fut);
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Method mapped to `POST /api/v1/library/<id>/rescan` starts a full rescan of the library in the
+/// background. Progress is reported to clients over the event socket. Returns `409` if a scan is
+/// already running for this library, otherwise returns `202` immediately. Only the owner can
+/// access this route.
+///
+/// # Arguments
+/// * `id` - id of the library to rescan
+/// * `user` - Auth middleware
+/// * `conn` - database connection
+/// * `event_tx` - channel over which to dispatch events
+pub async fn library_rescan(
+    id: i64,
+    user: Auth,
+    conn: DbConnection,
+    event_tx: EventTx,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    {
+        let mut tx = conn.read().begin().await?;
+        Library::get_one(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::LibraryNotFound)?;
+    }
+
+    let guard = scanners::ScanGuard::acquire(id).ok_or(errors::DimError::ScanInProgress)?;
+
+    crate::tasks::submit(format!("Rescan library {}", id), async move {
+        if let Err(e) = scanners::start_guarded(conn, guard, event_tx).await {
+            error!(reason = ?e, library_id = id, "Failed to rescan library.");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Method mapped to `GET /api/v1/library/<id>/scan_status` returns the progress of the scans
+/// running for a library, for clients that cant listen on the event socket. While the library
+/// directories are walked `total` keeps growing, thus `percent` is only meaningful once `phase` is
+/// `processing`. Returns `{"scanning": false}` if the library isnt being scanned.
+///
+/// # Arguments
+/// * `id` - id of the library
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "scanning": bool,
+///     "phase": "walking" | "processing",
+///     "processed": int,
+///     "total": int,
+///     "percent": float,
+/// }
+/// ```
+pub async fn scan_status(id: i64, _user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    let status = match scanners::scan_progress(id) {
+        Some(x) => json!({
+            "scanning": true,
+            "phase": x.phase(),
+            "processed": x.processed,
+            "total": x.total,
+            "percent": x.percent(),
+        }),
+        None => json!({ "scanning": false }),
+    };
+
+    Ok(reply::json(&status))
+}
+
 /// Method mapped to `GET /api/v1/library/<id>` returns info about the library with the supplied
 /// id. Method can only be accessed by authenticated users.
 ///
@@ -270,19 +562,110 @@ pub async fn get_self(
     Ok(reply::json(&Library::get_one(&mut tx, id).await?))
 }
 
+/// Method mapped to `GET /api/v1/library/<id>/genre_stats` returns each genre used in the
+/// library along with the number of media tagged with it, sorted by count descending. Method can
+/// only be accessed by authenticated users. Hidden libraries, ie libraries that are being deleted,
+/// return 404.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the library we want the genre breakdown of
+/// * `user` - Auth middleware
+pub async fn get_genre_stats(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    // make sure the library exists so that we 404 instead of returning a empty list.
+    Library::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::LibraryNotFound)?;
+
+    if Library::is_hidden(&mut tx, id).await? {
+        return Err(errors::DimError::LibraryNotFound);
+    }
+
+    Ok(reply::json(
+        &Genre::get_counts_by_library(&mut tx, id, user.0.claims.get_user_ref()).await?,
+    ))
+}
+
+/// Method mapped to `GET /api/v1/library/<id>/size` returns the summed size on disk and number of
+/// files in the library. Method can only be accessed by authenticated users.
+///
+/// Sizes are recorded when a file is scanned and refreshed by every rescan, thus files arent
+/// stat'ed on every request. Files that havent been scanned since sizes are recorded count towards
+/// `files` but not towards `size`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the library
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "library_id": int,
+///     "size": int,
+///     "files": int,
+///     "by_media_type": [{
+///         "media_type": "movie" | "tv",
+///         "size": int,
+///         "files": int,
+///     }],
+/// }
+/// ```
+///
+/// `by_media_type` breaks the totals down for libraries holding more than one media type. Hidden
+/// libraries, ie libraries that are being deleted, return 404.
+pub async fn get_library_size(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    // make sure the library exists so that we 404 instead of returning a empty size.
+    Library::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::LibraryNotFound)?;
+
+    if Library::is_hidden(&mut tx, id).await? {
+        return Err(errors::DimError::LibraryNotFound);
+    }
+
+    let by_media_type = MediaFile::get_size_of_library(&mut tx, id).await?;
+
+    Ok(reply::json(&json!({
+        "library_id": id,
+        "size": by_media_type.iter().map(|x| x.size).sum::<i64>(),
+        "files": by_media_type.iter().map(|x| x.files).sum::<i64>(),
+        "by_media_type": by_media_type,
+    })))
+}
+
 /// Method mapped to `GET /api/v1/library/<id>/media` returns all the movies/tv shows that belong
-/// to the library with the id supplied. Method can only be accessed by authenticated users.
+/// to the library with the id supplied, sorted by their sort title. Method can only be accessed by
+/// authenticated users.
+///
+/// The media are wrapped in a [`Paginated`](Paginated) envelope. If `flat` is set the page is
+/// returned in the legacy format instead, ie a map of the library name to its media.
+///
+/// Media the current user hid are left out unless `include_hidden` is set.
 ///
 /// # Arguments
 /// * `conn` - database connection
 /// * `id` - id of the library we want media of
-/// * `_user` - Auth middleware
+/// * `page` - pagination arguments
+/// * `include_hidden` - whether to include media the user hid
+/// * `user` - Auth middleware
 pub async fn get_all_library(
     conn: DbConnection,
     id: i64,
-    _user: Auth,
+    page: PageArgs,
+    include_hidden: bool,
+    user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
-    let mut result = HashMap::new();
     let mut tx = conn.read().begin().await?;
     let lib = Library::get_one(&mut tx, id).await?;
 
@@ -293,22 +676,52 @@ pub async fn get_all_library(
         poster_path: Option<String>,
     }
 
-    let mut data = sqlx::query_as!(
+    let (limit, offset) = (page.limit(), page.offset());
+    let uid = user.0.claims.get_user();
+    let data = sqlx::query_as!(
         Record,
         r#"SELECT _tblmedia.id, name, assets.local_path as poster_path FROM _tblmedia
         LEFT JOIN assets ON _tblmedia.poster = assets.id
-        WHERE library_id = ? AND NOT media_type = "episode""#,
-        id
+        WHERE library_id = $1 AND NOT media_type = "episode"
+        AND ($4 OR NOT EXISTS (
+            SELECT 1 FROM hidden_media
+            WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $5
+        ))
+        ORDER BY COALESCE(sort_title, name)
+        LIMIT $2 OFFSET $3"#,
+        id,
+        limit,
+        offset,
+        include_hidden,
+        uid
     )
     .fetch_all(&mut tx)
     .await
     .map_err(|_| errors::DimError::NotFoundError)?;
 
-    data.sort_by(|a, b| a.name.cmp(&b.name));
+    if page.flat {
+        let mut result = HashMap::new();
+        result.insert(lib.name, data);
+
+        return Ok(reply::json(&result));
+    }
 
-    result.insert(lib.name, data);
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "total!: i64" FROM _tblmedia
+        WHERE library_id = $1 AND NOT media_type = "episode"
+        AND ($2 OR NOT EXISTS (
+            SELECT 1 FROM hidden_media
+            WHERE hidden_media.media_id = _tblmedia.id AND hidden_media.user_id = $3
+        ))"#,
+        id,
+        include_hidden,
+        uid
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .total;
 
-    Ok(reply::json(&result))
+    Ok(Paginated::new(data, total, &page).into_reply(false))
 }
 
 /// Method mapped to `GET` /api/v1/library/<id>/unmatched` returns a list of all unmatched medias
@@ -361,3 +774,68 @@ pub async fn get_all_unmatched_media(
 
     Ok(reply::json(&result))
 }
+
+/// Method mapped to `GET /api/v1/library/<id>/agent` returns the metadata agent the media of the
+/// library are matched with. Method can only be accessed by authenticated users.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the library
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "agent": "tmdb",
+/// }
+/// ```
+pub async fn get_library_agent(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let agent = Library::get_agent(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::LibraryNotFound)?;
+
+    Ok(reply::json(&json!({ "agent": agent })))
+}
+
+/// Method mapped to `PUT /api/v1/library/<id>/agent` sets the metadata agent the media of the
+/// library are matched with. Scans, rematches and metadata refreshes use the new agent right away
+/// while media already matched keep their metadata until they are rematched or refreshed. Only
+/// the owner can access this route.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the library
+/// * `user` - Auth middleware
+///
+/// # Data
+/// ```text
+/// {
+///     "agent": "tmdb",
+/// }
+/// ```
+pub async fn set_library_agent(
+    conn: DbConnection,
+    id: i64,
+    data: AgentArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    if Library::set_agent(&mut tx, id, data.agent).await? == 0 {
+        return Err(errors::DimError::LibraryNotFound);
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
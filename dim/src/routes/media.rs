@@ -1,21 +1,89 @@
 use crate::core::DbConnection;
+use crate::core::EventTx;
+use crate::core::UserEventTx;
 use crate::errors;
+use crate::image_variants;
 use crate::json;
+use crate::progress_buffer;
+use crate::routes::pagination::PageArgs;
+use crate::routes::pagination::Paginated;
+use crate::scanners::MediaLock;
+use crate::scrobble;
+use crate::watch_party::WatchParties;
 
 use auth::Wrapper as Auth;
 
+use database::alternate_title::AlternateTitle;
+use database::asset::Asset;
+use database::asset::InsertableAsset;
+use database::chapter::Chapter;
 use database::episode::Episode;
+use database::episode_markers::EpisodeMarkers;
+use database::episode_order::EpisodeOrder;
+use database::episode_order::DEFAULT_ORDER;
 use database::genre::Genre;
+use database::genre::InsertableGenre;
+use database::genre::InsertableGenreMedia;
+use database::hidden_media::HiddenMedia;
+use database::history::History;
+use database::keyword::Keyword;
+use database::library::Library;
 use database::library::MediaType;
+use database::match_candidate::MatchCandidate;
+use database::media::InsertableMedia;
 use database::media::Media;
 use database::media::UpdateMedia;
 use database::mediafile::MediaFile;
+use database::movie::InsertableMovie;
+use database::person::Person;
 use database::progress::Progress;
+use database::rating::InsertableRating;
+use database::rating::Rating;
+use database::season::Season;
+use database::tag::InsertableTag;
+use database::tag::Tag;
+use database::tmdb_episode::TmdbEpisode;
+use database::tv::TVShow;
+use database::user::User;
+use database::version_progress::VersionProgress;
+
+use events::Message;
+use events::PushEventType;
+
+use serde::Deserialize;
+use serde::Serialize;
 
 use warp::http::status::StatusCode;
 use warp::reply;
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Path segment addressing a episode of a show by its season and episode number, ie `s1e2` or
+/// `S01E02`.
+pub struct SeasonEpisode {
+    pub season: i64,
+    pub episode: i64,
+}
+
+impl FromStr for SeasonEpisode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let (season, episode) = s
+            .strip_prefix('s')
+            .and_then(|x| x.split_once('e'))
+            .ok_or(())?;
+
+        Ok(Self {
+            season: season.parse().map_err(|_| ())?,
+            episode: episode.parse().map_err(|_| ())?,
+        })
+    }
+}
 
 pub mod filters {
     use warp::reject;
@@ -25,16 +93,23 @@ pub mod filters {
     use auth::Wrapper as Auth;
     use serde::Deserialize;
 
+    use super::super::pagination::PageArgs;
+    use database::episode_markers::EpisodeMarkers;
     use database::media::UpdateMedia;
     use database::DbConnection;
 
+    use crate::core::EventTx;
+    use crate::core::UserEventTx;
+    use crate::idempotency;
+    use crate::watch_party::WatchParties;
+
     pub fn get_media_by_id(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "media" / i64)
             .and(warp::get())
             .and(with_state::<DbConnection>(conn))
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and_then(|id: i64, conn: DbConnection, user: Auth| async move {
                 super::get_media_by_id(conn, id, user)
                     .await
@@ -42,416 +117,4112 @@ pub mod filters {
             })
     }
 
-    pub fn get_media_files(
+    pub fn get_media_by_ids(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        warp::path!("api" / "v1" / "media" / i64 / "files")
+        #[derive(Deserialize)]
+        struct IdArgs {
+            ids: String,
+        }
+
+        warp::path!("api" / "v1" / "media")
             .and(warp::get())
+            .and(warp::query::query::<IdArgs>())
             .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |IdArgs { ids }: IdArgs, conn: DbConnection, _user: Auth| async move {
+                    super::get_media_by_ids(conn, ids)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn add_placeholder_media(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media")
+            .and(warp::post())
+            .and(warp::body::json::<super::NewPlaceholder>())
             .and(auth::with_auth())
-            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
-                super::get_media_files(conn, id)
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and(idempotency::key())
+            .and_then(
+                |data: super::NewPlaceholder,
+                 auth: Auth,
+                 conn: DbConnection,
+                 event_tx: EventTx,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::add_placeholder_media(conn, event_tx, data, auth)
+                    })
                     .await
                     .map_err(|e| reject::custom(e))
-            })
+                },
+            )
     }
 
-    pub fn update_media_by_id(
+    pub fn get_duplicates(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        warp::path!("api" / "v1" / "media" / i64)
-            .and(warp::patch())
-            .and(warp::body::json::<UpdateMedia>())
-            .and(auth::with_auth())
+        warp::path!("api" / "v1" / "media" / "duplicates")
+            .and(warp::get())
             .and(with_state::<DbConnection>(conn))
-            .and_then(|id, body, auth, conn| async move {
-                super::update_media_by_id(id, body, auth, conn)
+            .and(auth::with_auth())
+            .and_then(|conn: DbConnection, auth: Auth| async move {
+                super::get_duplicates(conn, auth)
                     .await
                     .map_err(|e| reject::custom(e))
             })
     }
 
-    pub fn delete_media_by_id(
+    pub fn get_media_by_tmdb_id(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        warp::path!("api" / "v1" / "media" / i64)
-            .and(warp::delete())
-            .and(auth::with_auth())
+        warp::path!("api" / "v1" / "media" / "by_tmdb" / i64)
+            .and(warp::get())
             .and(with_state::<DbConnection>(conn))
-            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
-                super::delete_media_by_id(conn, id, auth)
+            .and(auth::with_scope("read:media"))
+            .and_then(|tmdb_id: i64, conn: DbConnection, auth: Auth| async move {
+                super::get_media_by_tmdb_id(conn, tmdb_id, auth)
                     .await
                     .map_err(|e| reject::custom(e))
             })
     }
 
-    pub fn tmdb_search() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
-    {
+    pub fn merge_media(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         #[derive(Deserialize)]
         struct RouteArgs {
-            query: String,
-            year: Option<i32>,
-            media_type: String,
+            into: i64,
         }
 
-        warp::path!("api" / "v1" / "media" / "tmdb_search")
-            .and(warp::get())
+        warp::path!("api" / "v1" / "media" / i64 / "merge")
+            .and(warp::post())
             .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
             .and(auth::with_auth())
+            .and(idempotency::key())
             .and_then(
-                |RouteArgs {
-                     query,
-                     year,
-                     media_type,
-                 }: RouteArgs,
-                 auth: Auth| async move {
-                    super::tmdb_search(query, year, media_type, auth)
-                        .await
-                        .map_err(|e| reject::custom(e))
+                |id: i64,
+                 RouteArgs { into }: RouteArgs,
+                 conn: DbConnection,
+                 event_tx: EventTx,
+                 auth: Auth,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::merge_media(conn, event_tx, id, into, auth)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
                 },
             )
     }
 
-    pub fn map_progress(
+    pub fn merge_media_into(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "merge_into" / i64)
+            .and(warp::post())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and(auth::with_auth())
+            .and(idempotency::key())
+            .and_then(
+                |id: i64,
+                 into: i64,
+                 conn: DbConnection,
+                 event_tx: EventTx,
+                 auth: Auth,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::merge_media_into(conn, event_tx, id, into, auth)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_media_videos(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "videos")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
+                super::get_media_videos(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_media_external_ids(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "external_ids")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
+                super::get_media_external_ids(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_metadata_diff(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "metadata_diff")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
+                super::get_metadata_diff(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn refresh_metadata(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         #[derive(Deserialize)]
         struct RouteArgs {
-            offset: i64,
+            fields: Option<String>,
         }
 
-        warp::path!("api" / "v1" / "media" / i64 / "progress")
+        warp::path!("api" / "v1" / "media" / i64 / "refresh")
             .and(warp::post())
             .and(warp::query::query::<RouteArgs>())
             .and(with_state::<DbConnection>(conn))
-            .and(auth::with_auth())
-            .and_then(|id: i64, RouteArgs { offset }: RouteArgs, conn: DbConnection, auth: Auth| async move {
-                super::map_progress(conn, id, offset, auth)
+            .and(auth::with_scope("write:media"))
+            .and(idempotency::key())
+            .and_then(
+                |id: i64,
+                 RouteArgs { fields }: RouteArgs,
+                 conn: DbConnection,
+                 user: Auth,
+                 key: Option<String>| async move {
+                    let fields = fields.map(|x| {
+                        x.split(',')
+                            .map(|x| x.trim().to_lowercase())
+                            .filter(|x| !x.is_empty())
+                            .collect::<Vec<_>>()
+                    });
+
+                    idempotency::run(key, &user.get_user(), || {
+                        super::refresh_metadata(conn, id, fields)
+                    })
                     .await
                     .map_err(|e| reject::custom(e))
-            })
+                },
+            )
     }
-}
 
-/// Method mapped to `GET /api/v1/media/<id>` returns info about a media based on the id queried.
-/// This method can only be accessed by authenticated users.
-///
-/// # Arguments
-/// * `conn` - database connection
-/// * `id` - id of the media we want to query info of
-/// * `_user` - Auth middleware
-///
-/// # Return Schema
-/// ```text
-/// {
-///     "id": int,
-///     "library_id": int,
-///     "name": string,
-///     "description": string,
-///     "rating": int,
-///     "year": int,
-///     "added": string | date,
-///     "poster_path": string | uri_path,
-///     "backdrop_path": string | uri_path,
-///     "media_type": string | enum,
-///     "genres": [string],
-///     "duration": int,
-///     "duration_pretty": string,
-/// }
-/// ```
-///
-/// # Additional types
-/// [`MediaType`](`database::library::MediaType`)
-pub async fn get_media_by_id(
-    conn: DbConnection,
-    id: i64,
-    user: Auth,
-) -> Result<impl warp::Reply, errors::DimError> {
-    let mut tx = conn.read().begin().await?;
-    let media = Media::get(&mut tx, id).await?;
+    pub fn get_media_keywords(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "keywords")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
+                super::get_media_keywords(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
 
-    let media_id = match media.media_type {
-        MediaType::Movie | MediaType::Episode => id,
-        MediaType::Tv => Episode::get_first_for_show(&mut tx, id).await?.id,
-    };
+    pub fn get_media_alternate_titles(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "alternate_titles")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _user: Auth| async move {
+                super::get_media_alternate_titles(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
 
-    // TODO: at some point we want to issue a warning to the UI that none of the mediafiles with
-    // this media have a duration (maybe because of corruption).
-    let duration = match MediaFile::get_of_media(&mut tx, media_id).await {
-        Ok(x) => x
-            .iter()
-            .filter_map(|x| x.duration)
-            .collect::<Vec<_>>()
-            .pop()
-            .unwrap_or(0),
-        Err(_) => 0,
-    };
+    pub fn add_media_tag(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "tags")
+            .and(warp::post())
+            .and(warp::body::json::<super::TagArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("write:media"))
+            .and_then(
+                |id: i64, data: super::TagArgs, conn: DbConnection, _user: Auth| async move {
+                    super::add_media_tag(conn, id, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
 
-    let genres = Genre::get_by_media(&mut tx, id)
-        .await?
-        .into_iter()
-        .map(|x| x.name)
-        .collect::<Vec<String>>();
+    pub fn remove_media_tag(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "tags")
+            .and(warp::delete())
+            .and(warp::body::json::<super::TagArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("write:media"))
+            .and_then(
+                |id: i64, data: super::TagArgs, conn: DbConnection, _user: Auth| async move {
+                    super::remove_media_tag(conn, id, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
 
-    let progress = match media.media_type {
-        MediaType::Episode | MediaType::Movie => {
-            Progress::get_for_media_user(&mut tx, user.0.claims.get_user(), id)
-                .await
-                .map(|x| json!({"progress": x.delta}))
-                .ok()
+    pub fn get_media_files(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            hdr: Option<bool>,
+            cc: Option<bool>,
         }
-        MediaType::Tv => {
-            if let Ok(Some(ep)) =
-                Episode::get_last_watched_episode(&mut tx, id, user.0.claims.get_user()).await
-            {
-                let (delta, duration) =
-                    Progress::get_progress_for_media(&mut tx, ep.id, user.0.claims.get_user())
+
+        warp::path!("api" / "v1" / "media" / i64 / "files")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |id: i64, RouteArgs { hdr, cc }: RouteArgs, conn: DbConnection, _user: Auth| async move {
+                    super::get_media_files(conn, id, hdr, cc)
                         .await
-                        .unwrap_or((0, 1));
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
 
-                // NOTE: When we get to the last episode of a tv show we want to return the last
-                // episode even if the client finished watching it.
-                let next_episode = ep.get_next_episode(&mut tx).await;
-                if (delta as f64 / duration as f64) > 0.90 && next_episode.is_ok() {
-                    let next_episode = next_episode.unwrap();
-                    let (delta, _duration) =
-                        Progress::get_progress_for_media(&mut tx, ep.id, user.0.claims.get_user())
-                            .await
-                            .unwrap_or((0, 1));
+    pub fn get_resume_points(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "resume_points")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::get_resume_points(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
 
-                    Some(json!({
-                        "progress": delta,
-                        "season": next_episode.get_season_number(&mut tx).await.unwrap_or(0),
+    pub fn decide_playback(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "decide")
+            .and(warp::post())
+            .and(warp::body::json::<crate::playback::ClientCapabilities>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |id: i64, caps, conn: DbConnection, _user: Auth| async move {
+                    super::decide_playback(conn, id, caps)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_flat_episodes(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct OrderArgs {
+            order: Option<String>,
+        }
+
+        warp::path!("api" / "v1" / "media" / i64 / "episodes")
+            .and(warp::get())
+            .and(warp::query::query::<PageArgs>())
+            .and(warp::query::query::<OrderArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |id: i64,
+                 page: PageArgs,
+                 OrderArgs { order }: OrderArgs,
+                 conn: DbConnection,
+                 auth: Auth| async move {
+                    super::get_flat_episodes(conn, id, page, order, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_episode_orders(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "orders")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, _auth: Auth| async move {
+                super::get_episode_orders(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn set_episode_order(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "orders" / String)
+            .and(warp::put())
+            .and(warp::body::json::<Vec<i64>>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_auth())
+            .and_then(
+                |id: i64, name: String, episodes: Vec<i64>, conn: DbConnection, auth: Auth| async move {
+                    super::set_episode_order(conn, id, name, episodes, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_media_source_files(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "source_files")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_auth())
+            .and_then(|id: i64, conn: DbConnection, user: Auth| async move {
+                super::get_media_source_files(conn, id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn update_media_by_id(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64)
+            .and(warp::patch())
+            .and(warp::body::json::<UpdateMedia>())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and(idempotency::key())
+            .and_then(
+                |id, body, auth: Auth, conn, key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::update_media_by_id(id, body, auth, conn)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn batch_update_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "batch")
+            .and(warp::patch())
+            .and(warp::body::json::<super::BatchUpdate>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(idempotency::key())
+            .and_then(
+                |body: super::BatchUpdate,
+                 auth: Auth,
+                 conn: DbConnection,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::batch_update_media(body, auth, conn)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_media_overrides(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "overrides")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::get_media_overrides(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn clear_media_overrides(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "overrides")
+            .and(warp::delete())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::clear_media_overrides(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn delete_media_by_id(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64)
+            .and(warp::delete())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::delete_media_by_id(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn tmdb_search() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+    {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            query: String,
+            year: Option<i32>,
+            media_type: String,
+        }
+
+        warp::path!("api" / "v1" / "media" / "tmdb_search")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |RouteArgs {
+                     query,
+                     year,
+                     media_type,
+                 }: RouteArgs,
+                 auth: Auth| async move {
+                    super::tmdb_search(query, year, media_type, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn map_progress(
+        conn: DbConnection,
+        parties: WatchParties,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            offset: i64,
+            version: Option<i64>,
+        }
+
+        warp::path!("api" / "v1" / "media" / i64 / "progress")
+            .and(warp::post())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<WatchParties>(parties))
+            .and(auth::with_scope("write:media"))
+            .and_then(
+                |id: i64,
+                 RouteArgs { offset, version }: RouteArgs,
+                 conn: DbConnection,
+                 parties: WatchParties,
+                 auth: Auth| async move {
+                    super::map_progress(conn, parties, id, offset, version, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn query_progress(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "progress" / "query")
+            .and(warp::post())
+            .and(warp::body::json::<super::ProgressQuery>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |data: super::ProgressQuery, conn: DbConnection, auth: Auth| async move {
+                    super::query_progress(conn, data, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn needs_review(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "needs_review")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|conn: DbConnection, auth: Auth| async move {
+                super::needs_review(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn authorize_playback(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "authorize_playback")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::authorize_playback(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn set_episode_watched(
+        conn: DbConnection,
+        user_tx: UserEventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            state: bool,
+        }
+
+        warp::path!("api" / "v1" / "media" / "episode" / i64 / "watched")
+            .and(warp::post())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<UserEventTx>(user_tx))
+            .and(auth::with_scope("write:media"))
+            .and(idempotency::key())
+            .and_then(
+                |id: i64,
+                 RouteArgs { state }: RouteArgs,
+                 conn: DbConnection,
+                 user_tx: UserEventTx,
+                 auth: Auth,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::set_episode_watched(conn, user_tx, id, state, auth)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn set_episode_markers(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "episode" / i64 / "markers")
+            .and(warp::put())
+            .and(warp::body::json::<EpisodeMarkers>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_auth())
+            .and_then(
+                |id: i64, markers: EpisodeMarkers, conn: DbConnection, auth: Auth| async move {
+                    super::set_episode_markers(conn, id, markers, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_episode_progress(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / super::SeasonEpisode / "progress")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |id: i64, ep: super::SeasonEpisode, conn: DbConnection, auth: Auth| async move {
+                    super::get_episode_progress(conn, id, ep.season, ep.episode, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_media_stats(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "stats")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::get_media_stats(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn because_you_watched(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "because_you_watched")
+            .and(warp::get())
+            .and(warp::query::query::<PageArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |id: i64, page: PageArgs, conn: DbConnection, auth: Auth| async move {
+                    super::because_you_watched(conn, id, page, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_random_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            count: Option<i64>,
+            media_type: Option<String>,
+            include_watched: Option<bool>,
+            seed: Option<i64>,
+        }
+
+        warp::path!("api" / "v1" / "media" / "random")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |RouteArgs {
+                     count,
+                     media_type,
+                     include_watched,
+                     seed,
+                 }: RouteArgs,
+                 conn: DbConnection,
+                 auth: Auth| async move {
+                    super::get_random_media(
+                        conn,
+                        count,
+                        media_type,
+                        include_watched.unwrap_or(false),
+                        seed,
+                        auth,
+                    )
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_in_progress_shows(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "in_progress_shows")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|conn: DbConnection, auth: Auth| async move {
+                super::get_in_progress_shows(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn hide_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "hide")
+            .and(warp::post())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("write:media"))
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::hide_media(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn unhide_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "hide")
+            .and(warp::delete())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("write:media"))
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::unhide_media(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_hidden_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "hidden")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(|conn: DbConnection, auth: Auth| async move {
+                super::get_hidden_media(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_popular(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            window: Option<String>,
+            limit: Option<i64>,
+        }
+
+        warp::path!("api" / "v1" / "media" / "popular")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |RouteArgs { window, limit }: RouteArgs,
+                 conn: DbConnection,
+                 auth: Auth| async move {
+                    super::get_popular(conn, window, limit, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_added_between(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            from: String,
+            to: String,
+            #[serde(default)]
+            by_episode: bool,
+        }
+
+        warp::path!("api" / "v1" / "media" / "added_between")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(warp::query::query::<PageArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |RouteArgs {
+                     from,
+                     to,
+                     by_episode,
+                 }: RouteArgs,
+                 page: PageArgs,
+                 conn: DbConnection,
+                 auth: Auth| async move {
+                    super::get_added_between(conn, from, to, by_episode, page, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_recently_added(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / "recently_added")
+            .and(warp::get())
+            .and(warp::query::query::<PageArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("read:media"))
+            .and_then(
+                |page: PageArgs, conn: DbConnection, auth: Auth| async move {
+                    super::get_recently_added(conn, page, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_stale(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            older_than_days: Option<i64>,
+        }
+
+        warp::path!("api" / "v1" / "media" / "stale")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(warp::query::query::<PageArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_auth())
+            .and_then(
+                |RouteArgs { older_than_days }: RouteArgs,
+                 page: PageArgs,
+                 conn: DbConnection,
+                 auth: Auth| async move {
+                    super::get_stale(conn, older_than_days, page, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_movable_libraries(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "library")
+            .and(warp::get())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_auth())
+            .and_then(|id: i64, conn: DbConnection, auth: Auth| async move {
+                super::get_movable_libraries(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn move_media_to_library(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            library_id: i64,
+        }
+
+        warp::path!("api" / "v1" / "media" / i64 / "library")
+            .and(warp::patch())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and(auth::with_auth())
+            .and(idempotency::key())
+            .and_then(
+                |id: i64,
+                 RouteArgs { library_id }: RouteArgs,
+                 conn: DbConnection,
+                 event_tx: EventTx,
+                 auth: Auth,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::move_media_to_library(conn, event_tx, id, library_id, auth)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn rate_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            score: i64,
+        }
+
+        warp::path!("api" / "v1" / "media" / i64 / "rate")
+            .and(warp::post())
+            .and(warp::query::query::<RouteArgs>())
+            .and(with_state::<DbConnection>(conn))
+            .and(auth::with_scope("write:media"))
+            .and(idempotency::key())
+            .and_then(
+                |id: i64,
+                 RouteArgs { score }: RouteArgs,
+                 conn: DbConnection,
+                 auth: Auth,
+                 key: Option<String>| async move {
+                    idempotency::run(key, &auth.get_user(), || {
+                        super::rate_media(conn, id, score, auth)
+                    })
+                    .await
+                    .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn delete_rating(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "rate")
+            .and(warp::delete())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::delete_rating(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+}
+
+/// Method mapped to `GET /api/v1/media/<id>` returns info about a media based on the id queried.
+/// This method can only be accessed by authenticated users.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media we want to query info of
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "library_id": int,
+///     "name": string,
+///     "sort_title": string | null,
+///     "collection_id": int | null,
+///     "description": string,
+///     "rating": int,
+///     "community_rating": float | null,
+///     "user_ratings": {
+///         "average": float | null,
+///         "count": int,
+///         "score": int | null,
+///     },
+///     "year": int,
+///     "added": string | date,
+///     "poster_path": string | uri_path,
+///     "posters": {
+///         "w185": string,
+///         "w500": string,
+///         "original": string,
+///     } | null,
+///     "poster_source": "tmdb" | "frame" | "placeholder" | null,
+///     "accent_color": string | null,
+///     "backdrop_path": string | uri_path,
+///     "backdrops": {
+///         "w780": string,
+///         "w1280": string,
+///         "original": string,
+///     } | null,
+///     "media_type": string | enum,
+///     "genres": [string],
+///     "cast": [{
+///         "id": int,
+///         "name": string,
+///         "character": string | null,
+///         "ordering": int,
+///     }],
+///     "user_tags": [Tag],
+///     "duration": int,
+///     "duration_source": "file" | "tmdb" | null,
+///     "duration_pretty": string | null,
+///     "season_count": int, // only for tv shows
+/// }
+/// ```
+///
+/// `duration_pretty` is formatted according to the media type, ie for tv shows it describes the
+/// length of a episode. The style can be changed per library with the `duration_styles` setting.
+/// It is `null` if the duration is unknown.
+///
+/// If TMDB has no poster for the media, the poster is resolved with the `poster_fallback` chain
+/// from the global settings and `poster_source` tells which source was used.
+///
+/// `posters` and `backdrops` hold the urls of the poster and backdrop in several sizes keyed by
+/// width, so clients can fetch a image sized for where it is shown. The widths offered are set
+/// with `poster_sizes` and `backdrop_sizes` in the global settings. Images from TMDB link to TMDB,
+/// other images are scaled down on demand by `GET /images/<path>?w=<width>`. `poster_path` and
+/// `backdrop_path` keep pointing at the locally cached image.
+///
+/// `accent_color` is the dominant color of the TMDB poster formatted as `#rrggbb`, see
+/// [`accent_color`](crate::accent_color). It is `null` for media without a TMDB poster and until
+/// the color has been computed.
+///
+/// If none of the files of the media have a duration, ie for placeholders, the runtime reported
+/// by TMDB is returned instead and `duration_source` is set to `tmdb`.
+///
+/// `user_tags` holds the tags users tagged the media with, `tags` describes the quality of the
+/// files of the media.
+///
+/// `sort_title` is the title the media is sorted by in lists, ie `Matrix` for `The Matrix`. It is
+/// `null` if the media is sorted by its name.
+///
+/// `collection_id` is the id of the TMDB collection a movie belongs to, which can be passed to
+/// `GET /api/v1/collection/<id>`.
+///
+/// `cast` is ordered by billing, the `id` of a cast member can be passed to
+/// `GET /api/v1/person/<id>`.
+///
+/// # Additional types
+/// [`MediaType`](`database::library::MediaType`)
+/// [`Tag`](`database::tag::Tag`)
+pub async fn get_media_by_id(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    // the last watched episode of a show is looked up across all of its episodes, thus buffered
+    // progress has to be written before the transaction takes its snapshot.
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+
+    let settings = crate::routes::settings::get_global_settings();
+    let poster_chain = settings.poster_fallback;
+    let (poster_path, poster_source) = match media.poster_url(&mut tx, &poster_chain).await? {
+        Some((url, source)) => (Some(url), Some(source)),
+        None => (None, None),
+    };
+
+    let posters = match poster_path.as_deref() {
+        Some(x) => Some(image_variants::variants(
+            x,
+            Asset::get_image_path_by_file(&mut tx, x).await?.as_deref(),
+            &settings.poster_sizes,
+        )),
+        None => None,
+    };
+
+    let backdrops = match media.backdrop_path.as_deref().filter(|x| !x.is_empty()) {
+        Some(x) => Some(image_variants::variants(
+            x,
+            Asset::get_image_path_by_file(&mut tx, x).await?.as_deref(),
+            &settings.backdrop_sizes,
+        )),
+        None => None,
+    };
+
+    let duration_formatter = crate::duration::formatter_for(
+        media.media_type,
+        settings.duration_styles.get(&media.library_id).copied(),
+    );
+
+    let user_tags = Tag::get_by_media(&mut tx, id).await?;
+    let cast = Person::get_cast(&mut tx, id).await?;
+    let sort_title = Media::get_sort_title(&mut tx, id).await?;
+    let collection_id = Media::get_collection_id(&mut tx, id).await?;
+    let accent_color = Media::get_accent_color(&mut tx, id).await?;
+
+    // placeholders dont have any files attached to them, thus they cant be played.
+    if Media::is_placeholder(&mut tx, id).await? {
+        let genres = Genre::get_by_media(&mut tx, id)
+            .await?
+            .into_iter()
+            .map(|x| x.name)
+            .collect::<Vec<String>>();
+
+        let runtime = Media::get_tmdb_runtime(&mut tx, id).await?;
+
+        return Ok(reply::json(&json!({
+            "id": media.id,
+            "library_id": media.library_id,
+            "name": media.name,
+            "sort_title": sort_title,
+            "collection_id": collection_id,
+            "description": media.description,
+            "rating": media.rating,
+            "year": media.year,
+            "added": media.added,
+            "poster_path": poster_path,
+            "posters": posters,
+            "poster_source": poster_source,
+            "accent_color": accent_color,
+            "backdrop_path": media.backdrop_path,
+            "backdrops": backdrops,
+            "media_type": media.media_type,
+            "genres": genres,
+            "cast": cast,
+            "user_tags": user_tags,
+            "duration": runtime.unwrap_or(0),
+            "duration_source": runtime.map(|_| "tmdb"),
+            "duration_pretty": runtime.map(|x| duration_formatter.format(x)),
+            "placeholder": true,
+        })));
+    }
+
+    let media_id = match media.media_type {
+        MediaType::Movie | MediaType::Episode => id,
+        MediaType::Tv => Episode::get_first_for_show(&mut tx, id).await?.id,
+    };
+
+    // TODO: at some point we want to issue a warning to the UI that none of the mediafiles with
+    // this media have a duration (maybe because of corruption).
+    let duration = match Media::get_cached_duration(&mut tx, media_id).await {
+        Ok(Some(x)) => x,
+        _ => MediaFile::get_largest_duration(&mut tx, media_id)
+            .await
+            .unwrap_or(0),
+    };
+
+    // the duration of the files always wins over the runtime reported by TMDB.
+    let (duration, duration_source) = match duration {
+        0 => match Media::get_tmdb_runtime(&mut tx, id).await? {
+            Some(x) => (x, Some("tmdb")),
+            None => (0, None),
+        },
+        x => (x, Some("file")),
+    };
+
+    let genres = Genre::get_by_media(&mut tx, id)
+        .await?
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<String>>();
+
+    let progress = match media.media_type {
+        MediaType::Episode | MediaType::Movie => {
+            Progress::get_for_media_user(&mut tx, user.0.claims.get_user(), id)
+                .await
+                .map(progress_buffer::overlay)
+                .map(|x| json!({"progress": x.delta}))
+                .ok()
+        }
+        MediaType::Tv => {
+            if let Ok(Some(ep)) =
+                Episode::get_last_watched_episode(&mut tx, id, user.0.claims.get_user()).await
+            {
+                let (delta, duration) =
+                    Progress::get_progress_for_media(&mut tx, ep.id, user.0.claims.get_user())
+                        .await
+                        .unwrap_or((0, 1));
+
+                // NOTE: When we get to the last episode of a tv show we want to return the last
+                // episode even if the client finished watching it.
+                let next_episode = ep.get_next_episode(&mut tx).await;
+                if (delta as f64 / duration as f64) > 0.90 && next_episode.is_ok() {
+                    let next_episode = next_episode.unwrap();
+                    let (delta, _duration) =
+                        Progress::get_progress_for_media(&mut tx, ep.id, user.0.claims.get_user())
+                            .await
+                            .unwrap_or((0, 1));
+
+                    Some(json!({
+                        "progress": delta,
+                        "season": next_episode.get_season_number(&mut tx).await.unwrap_or(0),
                         "episode": next_episode.episode,
                         "play_btn_id": next_episode.id,
                     }))
                 } else {
-                    Some(json!({
-                        "progress": delta,
-                        "season": ep.get_season_number(&mut tx).await.unwrap_or(0),
-                        "episode": ep.episode,
-                        "play_btn_id": ep.id,
-                    }))
+                    Some(json!({
+                        "progress": delta,
+                        "season": ep.get_season_number(&mut tx).await.unwrap_or(0),
+                        "episode": ep.episode,
+                        "play_btn_id": ep.id,
+                    }))
+                }
+            } else {
+                let ep = Episode::get_first_for_show(&mut tx, id).await?;
+                Some(json!({
+                    "progress": 0,
+                    "season": ep.get_season_number(&mut tx).await.unwrap_or(0),
+                    "episode": ep.episode,
+                    "play_btn_id": ep.id,
+                }))
+            }
+        }
+    };
+
+    fn mediafile_tags(x: &MediaFile) -> serde_json::Value {
+        let video_tag = format!(
+            "{} ({})",
+            x.quality
+                .as_ref()
+                .map(|x| format!("{}p", x))
+                .unwrap_or("Unknown".into()),
+            crate::utils::codec_pretty(x.codec.as_deref().unwrap_or("Unknown"))
+        );
+
+        let audio_lang = x.audio_language.as_deref().unwrap_or("Unknown");
+        let audio_codec = crate::utils::codec_pretty(x.audio.as_deref().unwrap_or("Unknown"));
+        let audio_ch = crate::utils::channels_pretty(x.channels.unwrap_or(2));
+
+        let audio_tag = format!("{} ({} {})", audio_lang, audio_codec, audio_ch);
+
+        json!({
+            "video": video_tag,
+            "audio": audio_tag,
+        })
+    }
+
+    let quality_tags = match media.media_type {
+        MediaType::Episode | MediaType::Movie => json!({
+                media.id.to_string(): MediaFile::get_of_media(&mut tx, media.id)
+                    .await?
+                    .iter()
+                    .min_by(|a, b| a.cmp_quality(b))
+                    .map(mediafile_tags)
+        }),
+        MediaType::Tv => json!(MediaFile::get_of_show(&mut tx, media.id)
+            .await?
+            .iter()
+            .map(|x| (x.media_id.unwrap(), mediafile_tags(x)))
+            .collect::<HashMap<_, _>>()),
+    };
+
+    let season_episode_tag = match media.media_type {
+        MediaType::Episode => {
+            let result = Episode::get_season_episode_by_id(&mut tx, id).await?;
+            let markers = EpisodeMarkers::get(&mut tx, id).await?;
+            Some(json!({
+                "season": result.0,
+                "episode": result.1,
+                "intro": markers.intro,
+                "credits": markers.credits,
+            }))
+        }
+        _ => None,
+    };
+
+    let (community_rating, rating_count) = Rating::get_community_rating(&mut tx, id).await?;
+    let user_score = Rating::get_for_media_user(&mut tx, user.0.claims.get_user(), id)
+        .await?
+        .map(|x| x.score);
+
+    let season_count = match media.media_type {
+        MediaType::Tv => {
+            let include_specials =
+                crate::routes::settings::get_global_settings().include_specials_in_counts;
+            Some(json!({
+                "season_count": TVShow::get_season_count(&mut tx, id, include_specials).await?,
+            }))
+        }
+        _ => None,
+    };
+
+    const EPISODE_DONE_THRESH: f64 = 0.9;
+
+    let next_episode_id = match Episode::get_by_id(&mut tx, id).await {
+        Ok(x) => {
+            let next_episode = if let Ok(x) = x.get_next_episode(&mut tx).await {
+                Some(json!({
+                    "next_episode_id": x.id,
+                    "chapters": {
+                        "credits": x.media.get_first_duration(&mut tx).await as f64 * EPISODE_DONE_THRESH
+                    }
+                }))
+            } else {
+                None
+            };
+
+            let prev_episode = x
+                .get_prev_episode(&mut tx)
+                .await
+                .map(|x| json!({"prev_episode_id": x.id}))
+                .ok();
+
+            if next_episode.is_some() || prev_episode.is_some() {
+                Some(json!({
+                    ..?next_episode,
+                    ..?prev_episode,
+                }))
+            } else {
+                None
+            }
+        },
+        Err(_) => None,
+    };
+
+    // FIXME: Remove the duration tag once the UI transitioned to using duration_pretty
+    Ok(reply::json(&json!({
+        "id": media.id,
+        "library_id": media.library_id,
+        "name": media.name,
+        "sort_title": sort_title,
+        "collection_id": collection_id,
+        "description": media.description,
+        "rating": media.rating,
+        "community_rating": community_rating,
+        "user_ratings": {
+            "average": community_rating,
+            "count": rating_count,
+            "score": user_score,
+        },
+        "year": media.year,
+        "added": media.added,
+        "poster_path": poster_path,
+        "posters": posters,
+        "poster_source": poster_source,
+        "accent_color": accent_color,
+        "backdrop_path": media.backdrop_path,
+        "backdrops": backdrops,
+        "media_type": media.media_type,
+        "genres": genres,
+        "cast": cast,
+        "user_tags": user_tags,
+        "duration": duration,
+        "duration_source": duration_source,
+        "duration_pretty": duration_source.map(|_| duration_formatter.format(duration)),
+        "tags": quality_tags,
+        ..?next_episode_id,
+        ..?season_episode_tag,
+        ..?season_count,
+        ..?progress
+    })))
+}
+
+/// Maximum number of media that can be fetched at once with `GET /api/v1/media?ids=`.
+pub const MAX_MEDIA_BY_IDS: usize = 100;
+
+/// Method mapped to `GET /api/v1/media?ids=1,5,3` returns several media at once in the order their
+/// ids were passed in, ie to render a playlist ordered by the client. Ids of media that dont exist
+/// or are in hidden libraries yield `null` in their place. Unlike `POST` based batch endpoints the
+/// response can be cached by the url.
+///
+/// Returns `400` if `ids` isnt a comma separated list of ids or holds more than
+/// [`MAX_MEDIA_BY_IDS`](MAX_MEDIA_BY_IDS) ids.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `ids` - comma separated ids of the media
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "id": int,
+///     "library_id": int,
+///     "name": string,
+///     "description": string | null,
+///     "rating": int | null,
+///     "year": int | null,
+///     "added": string | null,
+///     "poster_path": string | null,
+///     "backdrop_path": string | null,
+///     "media_type": string | enum,
+/// } | null]
+/// ```
+pub async fn get_media_by_ids(
+    conn: DbConnection,
+    ids: String,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let ids = ids
+        .split(',')
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| x.trim().parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| errors::DimError::InvalidIds)?;
+
+    if ids.len() > MAX_MEDIA_BY_IDS {
+        return Err(errors::DimError::BatchTooLarge {
+            max: MAX_MEDIA_BY_IDS,
+        });
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get_many(&mut tx, &ids)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect::<HashMap<_, _>>();
+
+    let result = ids.iter().map(|x| media.get(x)).collect::<Vec<_>>();
+
+    Ok(reply::json(&result))
+}
+
+#[derive(Deserialize)]
+pub struct NewPlaceholder {
+    pub tmdb_id: i32,
+    pub library_id: i64,
+}
+
+/// Method mapped to `POST /api/v1/media` is used to manually add a media entry which doesnt have
+/// any files yet, for example to track wanted or unreleased titles. The metadata is fetched from
+/// TMDB and the media is flagged as a placeholder. Once a matching file gets scanned, the scanner
+/// reuses this entry instead of creating a duplicate. Only the owner can access this route.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `event_tx` - channel over which to dispatch events
+/// * `data` - the TMDB id and the id of the library the media should be added to
+/// * `user` - Auth middleware
+pub async fn add_placeholder_media(
+    conn: DbConnection,
+    event_tx: EventTx,
+    data: NewPlaceholder,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use crate::scanners::format_path;
+
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let library = {
+        let mut tx = conn.read().begin().await?;
+        Library::get_one(&mut tx, data.library_id)
+            .await
+            .map_err(|_| errors::DimError::LibraryNotFound)?
+    };
+
+    let mut tmdb = crate::scanners::metadata_agent(library.agent, library.media_type);
+
+    let result: crate::scanners::ApiMedia = tmdb.search_by_id(data.tmdb_id).await?.into();
+
+    let year = result
+        .release_date
+        .as_ref()
+        .and_then(|x| x.split('-').next())
+        .and_then(|x| x.parse::<i64>().ok());
+
+    if let Some(poster_path) = result.poster_path.as_ref() {
+        let _ = crate::fetcher::insert_into_queue(poster_path.clone(), 3).await;
+    }
+
+    if let Some(backdrop_path) = result.backdrop_path.as_ref() {
+        let _ = crate::fetcher::insert_into_queue(backdrop_path.clone(), 3).await;
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    let poster = match result.poster_path.clone() {
+        Some(path) => InsertableAsset {
+            remote_url: Some(path),
+            local_path: format_path(result.poster_file.clone()),
+            file_ext: "jpg".into(),
+        }
+        .insert(&mut tx)
+        .await
+        .ok()
+        .map(|x| x.id),
+        None => None,
+    };
+
+    let backdrop = match result.backdrop_path.clone() {
+        Some(path) => InsertableAsset {
+            remote_url: Some(path),
+            local_path: format_path(result.backdrop_file.clone()),
+            file_ext: "jpg".into(),
+        }
+        .insert(&mut tx)
+        .await
+        .ok()
+        .map(|x| x.id),
+        None => None,
+    };
+
+    let media = InsertableMedia {
+        library_id: library.id,
+        name: result.title.clone(),
+        description: result.overview.clone(),
+        rating: result.rating.map(|x| x as i64),
+        year,
+        added: chrono::Utc::now().to_string(),
+        poster,
+        backdrop,
+        media_type: library.media_type,
+    };
+
+    let media_id = match Media::get_id_by_tmdb_id(
+        &mut tx,
+        library.id,
+        data.tmdb_id as i64,
+        library.media_type,
+    )
+    .await?
+    {
+        Some(id) => id,
+        None => media.insert_placeholder(&mut tx).await?,
+    };
+
+    Media::set_tmdb_id(&mut tx, media_id, data.tmdb_id as i64).await?;
+
+    if let Some(runtime) = result.runtime {
+        Media::set_tmdb_runtime(&mut tx, media_id, runtime as i64).await?;
+    }
+
+    if let MediaType::Movie = library.media_type {
+        Media::set_collection_id(&mut tx, media_id, result.collection_id.map(|x| x as i64)).await?;
+    }
+
+    // NOTE: these can fail if the media already existed, thus we ignore the result.
+    match library.media_type {
+        MediaType::Tv => {
+            let _ = TVShow::insert(&mut tx, media_id).await;
+        }
+        _ => {
+            let _ = InsertableMovie::insert(&mut tx, media_id).await;
+        }
+    }
+
+    for name in result.genres {
+        let genre = InsertableGenre { name };
+
+        if let Ok(x) = genre.insert(&mut tx).await {
+            let _ = InsertableGenreMedia::insert_pair(x, media_id, &mut tx).await;
+        }
+    }
+
+    let media = Media::get(&mut tx, media_id).await?;
+    let placeholder = Media::is_placeholder(&mut tx, media_id).await?;
+
+    tx.commit().await?;
+
+    crate::accent_color::queue(conn.clone(), media_id);
+
+    let event = Message {
+        id: media_id,
+        event_type: PushEventType::EventNewCard {
+            lib_id: library.id,
+        },
+    };
+
+    let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+
+    Ok(reply::with_status(
+        reply::json(&json!({
+            "id": media.id,
+            "library_id": media.library_id,
+            "name": media.name,
+            "description": media.description,
+            "rating": media.rating,
+            "year": media.year,
+            "added": media.added,
+            "poster_path": media.poster_path,
+            "backdrop_path": media.backdrop_path,
+            "media_type": media.media_type,
+            "placeholder": placeholder,
+        })),
+        StatusCode::CREATED,
+    ))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/keywords` returns the keywords a media was tagged
+/// with on TMDB, ie "time travel". Media can be browsed by keyword with
+/// `GET /api/v1/keyword/<id>/media`. Returns `404` if the media doesnt exist.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+pub async fn get_media_keywords(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    Ok(reply::json(&Keyword::get_by_media(&mut tx, id).await?))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/alternate_titles` returns the titles a media is known
+/// under in other countries according to TMDB, keyed by the ISO 3166-1 code of the country.
+/// Searching for any of these titles finds the media.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "FR": ["Le Parrain"],
+///     "IT": ["Il Padrino"],
+/// }
+/// ```
+pub async fn get_media_alternate_titles(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    Media::get(&mut tx, id).await?;
+
+    let mut result: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for x in AlternateTitle::get_by_media(&mut tx, id).await? {
+        result.entry(x.country).or_default().push(x.title);
+    }
+
+    Ok(reply::json(&result))
+}
+
+/// Maximum length of a tag name.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+#[derive(Deserialize)]
+pub struct TagArgs {
+    pub name: String,
+}
+
+impl TagArgs {
+    /// Returns the trimmed name of the tag if it is valid.
+    fn name(&self) -> Result<&str, errors::DimError> {
+        let name = self.name.trim();
+
+        if name.is_empty() || name.chars().count() > MAX_TAG_LENGTH {
+            return Err(errors::DimError::InvalidTag {
+                max: MAX_TAG_LENGTH,
+            });
+        }
+
+        Ok(name)
+    }
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/tags` tags a media with a free-form tag, ie "Watch
+/// with kids". Tags are shared by all users and are deduplicated case insensitively, thus tagging
+/// a media with `favourites` reuses a existing `Favourites` tag. Unlike genres, tags are never
+/// touched when the media is rematched. Media can be browsed by tag with
+/// `GET /api/v1/tag/<id>/media`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `data` - name of the tag
+///
+/// # Data
+/// ```text
+/// {
+///     "name": string,
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "user_created": bool,
+/// }
+/// ```
+pub async fn add_media_tag(
+    conn: DbConnection,
+    id: i64,
+    data: TagArgs,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let name = data.name()?;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let tag_id = InsertableTag { name: name.into() }
+        .insert_for_media(&mut tx, id)
+        .await?;
+    let tag = Tag::get_by_id(&mut tx, tag_id).await?;
+    tx.commit().await?;
+
+    Ok(reply::json(&tag))
+}
+
+/// Method mapped to `DELETE /api/v1/media/<id>/tags` removes a tag from a media. The tag is matched
+/// case insensitively and deleted once no media is tagged with it anymore. Returns `404` if the
+/// media wasnt tagged with it.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `data` - name of the tag
+///
+/// # Data
+/// ```text
+/// {
+///     "name": string,
+/// }
+/// ```
+pub async fn remove_media_tag(
+    conn: DbConnection,
+    id: i64,
+    data: TagArgs,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    if Tag::remove_from_media(&mut tx, id, data.name.trim()).await? == 0 {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/videos` returns the trailers, teasers and clips TMDB
+/// has for a media. Official trailers are returned first. Returns an empty list if there are no
+/// videos, `422` if the media hasnt been matched against TMDB and `503` if TMDB timed out.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+pub async fn get_media_videos(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use crate::scanners::tmdb::TmdbError;
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+    let tmdb_id = Media::get_tmdb_id(&mut tx, id)
+        .await?
+        .ok_or(errors::DimError::NoTmdbId)?;
+
+    let agent = Library::get_agent(&mut tx, media.library_id).await?;
+    let mut tmdb = crate::scanners::metadata_agent(agent, media.media_type);
+
+    let videos = match tmdb.get_videos_for(tmdb_id as u64).await {
+        Ok(x) => x,
+        Err(TmdbError::NoVideosFound { .. }) => vec![],
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(reply::json(&videos))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/external_ids` returns the ids of a media on TMDB,
+/// IMDB and TVDB so that clients can link out. IMDB and TVDB ids are fetched while scanning, ids
+/// of media scanned before are fetched from TMDB on the first request and cached. Ids TMDB doesnt
+/// know are `null`. Returns `404` if the media doesnt exist and `422` if it hasnt been matched
+/// against TMDB.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "tmdb_id": int,
+///     "imdb_id": string | null,
+///     "tvdb_id": int | null,
+/// }
+/// ```
+pub async fn get_media_external_ids(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use database::media::ExternalIds;
+
+    let (media, agent, tmdb_id, cached) = {
+        let mut tx = conn.read().begin().await?;
+        let media = Media::get(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
+        let tmdb_id = Media::get_tmdb_id(&mut tx, id)
+            .await?
+            .ok_or(errors::DimError::NoTmdbId)?;
+
+        let agent = Library::get_agent(&mut tx, media.library_id).await?;
+
+        (
+            media,
+            agent,
+            tmdb_id,
+            Media::get_external_ids(&mut tx, id).await?,
+        )
+    };
+
+    let ids = match cached {
+        Some(x) => x,
+        None => {
+            let mut tmdb = crate::scanners::metadata_agent(agent, media.media_type);
+
+            match tmdb.get_external_ids_for(tmdb_id as u64).await {
+                Ok(x) => {
+                    let ids = ExternalIds {
+                        imdb_id: x.imdb_id,
+                        tvdb_id: x.tvdb_id.map(|x| x as i64),
+                    };
+
+                    let mut lock = conn.writer().lock_owned().await;
+                    let mut tx = database::write_tx(&mut lock).await?;
+                    Media::set_external_ids(&mut tx, id, &ids).await?;
+                    tx.commit().await?;
+
+                    ids
+                }
+                // TMDB being unreachable isnt cached so that the ids are fetched next time.
+                Err(_) => ExternalIds::default(),
+            }
+        }
+    };
+
+    Ok(reply::json(&json!({
+        "tmdb_id": tmdb_id,
+        "imdb_id": ids.imdb_id,
+        "tvdb_id": ids.tvdb_id,
+    })))
+}
+
+/// Metadata of a media as currently found on TMDB.
+struct RemoteMetadata {
+    name: String,
+    description: Option<String>,
+    rating: Option<i64>,
+    year: Option<i64>,
+    genres: Vec<String>,
+}
+
+impl RemoteMetadata {
+    /// Method fetches the current TMDB metadata of the media `id` from the metadata agent of its
+    /// library. Returns `NoTmdbId` if the media hasnt been matched against TMDB.
+    async fn fetch(
+        tx: &mut database::Transaction<'_>,
+        media: &Media,
+    ) -> Result<Self, errors::DimError> {
+        let tmdb_id = Media::get_tmdb_id(&mut *tx, media.id)
+            .await?
+            .ok_or(errors::DimError::NoTmdbId)?;
+
+        let agent = Library::get_agent(&mut *tx, media.library_id).await?;
+        let mut tmdb = crate::scanners::metadata_agent(agent, media.media_type);
+
+        let result: crate::scanners::ApiMedia = tmdb.search_by_id(tmdb_id as i32).await?.into();
+
+        let year = result
+            .release_date
+            .as_ref()
+            .and_then(|x| x.split('-').next())
+            .and_then(|x| x.parse::<i64>().ok());
+
+        Ok(Self {
+            name: result.title,
+            description: result.overview,
+            rating: result.rating.map(|x| x as i64),
+            year,
+            genres: result.genres,
+        })
+    }
+
+    /// Method fetches the current episode catalog of the show `media`, ie the episodes TMDB knows
+    /// of along with their air dates. Seasons whose episodes couldnt be fetched are left out.
+    async fn fetch_catalog(
+        tx: &mut database::Transaction<'_>,
+        media: &Media,
+    ) -> Result<Vec<TmdbEpisode>, errors::DimError> {
+        let tmdb_id = Media::get_tmdb_id(&mut *tx, media.id)
+            .await?
+            .ok_or(errors::DimError::NoTmdbId)?;
+
+        let agent = Library::get_agent(&mut *tx, media.library_id).await?;
+        let mut tmdb = crate::scanners::metadata_agent(agent, media.media_type);
+
+        let mut catalog = Vec::new();
+
+        for season in tmdb.get_seasons_for(tmdb_id as u64).await? {
+            let season: crate::scanners::ApiSeason = season.into();
+            let episodes = tmdb
+                .get_episodes_for(tmdb_id as u64, season.season_number)
+                .await
+                .unwrap_or_default();
+
+            catalog.extend(episodes.into_iter().filter_map(|x| {
+                let x: crate::scanners::ApiEpisode = x.into();
+
+                Some(TmdbEpisode {
+                    season: season.season_number as i64,
+                    episode: x.episode? as i64,
+                    name: x.name,
+                    air_date: x.air_date,
+                })
+            }));
+        }
+
+        Ok(catalog)
+    }
+
+    /// Method returns the fields that differ between the local metadata and `self` as tuples of
+    /// `(field, local, remote)`.
+    fn diff(
+        &self,
+        media: &Media,
+        genres: &[String],
+    ) -> Vec<(&'static str, serde_json::Value, serde_json::Value)> {
+        let mut changes = vec![];
+
+        if media.name != self.name {
+            changes.push(("name", json!(media.name), json!(self.name)));
+        }
+
+        if media.description != self.description {
+            changes.push((
+                "description",
+                json!(media.description),
+                json!(self.description),
+            ));
+        }
+
+        if media.rating != self.rating {
+            changes.push(("rating", json!(media.rating), json!(self.rating)));
+        }
+
+        if media.year != self.year {
+            changes.push(("year", json!(media.year), json!(self.year)));
+        }
+
+        let mut local_genres = genres.iter().map(|x| x.to_lowercase()).collect::<Vec<_>>();
+        let mut remote_genres = self
+            .genres
+            .iter()
+            .map(|x| x.to_lowercase())
+            .collect::<Vec<_>>();
+
+        local_genres.sort();
+        remote_genres.sort();
+
+        if local_genres != remote_genres {
+            changes.push(("genres", json!(genres), json!(self.genres)));
+        }
+
+        changes
+    }
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/metadata_diff` fetches the current metadata of a media
+/// from TMDB and returns the fields which differ from the local metadata without applying any of
+/// them. The changes can then be applied with `POST /api/v1/media/<id>/refresh`. Returns `422` if
+/// the media hasnt been matched against TMDB.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "field": "name" | "description" | "rating" | "year" | "genres",
+///         "local": any,
+///         "remote": any,
+///     }
+/// ]
+/// ```
+pub async fn get_metadata_diff(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+    let genres = Genre::get_by_media(&mut tx, id)
+        .await?
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<String>>();
+
+    let remote = RemoteMetadata::fetch(&mut tx, &media).await?;
+
+    let changes = remote
+        .diff(&media, &genres)
+        .into_iter()
+        .map(|(field, local, remote)| json!({ "field": field, "local": local, "remote": remote }))
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&changes))
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/refresh` applies the current TMDB metadata to a
+/// media. If `fields` is supplied only the listed fields are updated, otherwise every field
+/// returned by `GET /api/v1/media/<id>/metadata_diff` is. Fields a user edited manually are
+/// never updated, see `GET /api/v1/media/<id>/overrides`. Returns the fields that were updated.
+/// Returns `409` if the metadata of the media is already being refreshed or rematched.
+///
+/// The episode catalog of shows, ie the air dates listed by `GET /api/v1/episodes/aired`, is
+/// refreshed as well unless it couldnt be fetched.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `fields` - optional list of fields to update, ie `?fields=name,genres`
+pub async fn refresh_metadata(
+    conn: DbConnection,
+    id: i64,
+    fields: Option<Vec<String>>,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let _lock = MediaLock::try_acquire(id).ok_or(errors::DimError::MediaLocked)?;
+
+    let (media, genres, overrides) = {
+        let mut tx = conn.read().begin().await?;
+        let media = Media::get(&mut tx, id).await?;
+        let genres = Genre::get_by_media(&mut tx, id)
+            .await?
+            .into_iter()
+            .map(|x| x.name)
+            .collect::<Vec<String>>();
+        let overrides = Media::get_overrides(&mut tx, id).await?;
+
+        (media, genres, overrides)
+    };
+
+    let (remote, catalog) = {
+        let mut tx = conn.read().begin().await?;
+        let remote = RemoteMetadata::fetch(&mut tx, &media).await?;
+        let catalog = match media.media_type {
+            MediaType::Tv => RemoteMetadata::fetch_catalog(&mut tx, &media)
+                .await
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+
+        (remote, catalog)
+    };
+
+    let applied = remote
+        .diff(&media, &genres)
+        .into_iter()
+        .map(|(field, _, _)| field)
+        .filter(|field| match fields.as_ref() {
+            Some(fields) => fields.iter().any(|x| x == field),
+            None => true,
+        })
+        .filter(|field| !overrides.iter().any(|x| x == field))
+        // fields missing on TMDB cant be cleared with a `UpdateMedia`, thus they are left as is.
+        .filter(|field| match *field {
+            "description" => remote.description.is_some(),
+            "rating" => remote.rating.is_some(),
+            "year" => remote.year.is_some(),
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    let apply = |field: &str| applied.iter().any(|x| *x == field);
+
+    let update = UpdateMedia {
+        name: Some(remote.name.clone()).filter(|_| apply("name")),
+        description: remote.description.clone().filter(|_| apply("description")),
+        rating: remote.rating.filter(|_| apply("rating")),
+        year: remote.year.filter(|_| apply("year")),
+        ..Default::default()
+    };
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    // overrides are applied again as a user may have edited the media while we fetched TMDB.
+    update.update_automatic(&mut tx, id).await?;
+
+    if apply("genres") {
+        Genre::set_for_media(&mut tx, id, &remote.genres).await?;
+    }
+
+    if !catalog.is_empty() {
+        TmdbEpisode::set_for_show(&mut tx, id, &catalog).await?;
+    }
+
+    Media::mark_metadata_refreshed(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(reply::json(&applied))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/files` returns the files, or versions, of a media.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `hdr` - if set only HDR or only SDR files are returned. Files whose HDR format is unknown
+/// count as SDR.
+/// * `cc` - if set only files with or only files without a closed caption track are returned.
+///
+/// Every file reports whether it has a closed caption (`has_cc`) or a SDH (`has_sdh`) subtitle
+/// track. Subtitle tracks not marked as either count as neither. Files scanned before caption
+/// tracks were detected report neither until `POST /api/v1/admin/maintenance/backfill_captions`
+/// probed them again. Every file also carries the chapters embedded in its container as
+/// `chapters`, see `GET /api/v1/mediafile/<id>/chapters`.
+pub async fn get_media_files(
+    conn: DbConnection,
+    id: i64,
+    hdr: Option<bool>,
+    cc: Option<bool>,
+) -> Result<impl warp::Reply, errors::DimError> {
+    #[derive(Serialize)]
+    struct Version {
+        #[serde(flatten)]
+        file: MediaFile,
+        chapters: Vec<Chapter>,
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let mut mediafiles = MediaFile::get_of_media(&mut tx, id).await?;
+
+    // episodes contained in a multi-episode file dont have files of their own.
+    if mediafiles.is_empty() {
+        if let Ok(x) = MediaFile::get_multi_episode_file(&mut tx, id).await {
+            mediafiles.push(x);
+        }
+    }
+
+    if let Some(hdr) = hdr {
+        mediafiles.retain(|x| x.is_hdr() == hdr);
+    }
+
+    if let Some(cc) = cc {
+        mediafiles.retain(|x| x.has_cc == cc);
+    }
+
+    let mut versions = Vec::with_capacity(mediafiles.len());
+
+    for file in mediafiles {
+        let chapters = Chapter::get_of_mediafile(&mut tx, file.id).await?;
+        versions.push(Version { file, chapters });
+    }
+
+    Ok(reply::json(&versions))
+}
+
+/// Versions whose durations differ by at most this many seconds are considered the same cut, thus
+/// offsets into one map onto the other.
+const SAME_CUT_EPSILON_SECS: i64 = 2;
+
+/// Method mapped to `GET /api/v1/media/<id>/resume_points` returns where the user left off in
+/// each version of a media, such that switching versions resumes at the right offset.
+/// `last_active` is the version the user played last, its offset is the progress of the user
+/// through the media.
+///
+/// Versions the user never played but which are of the same length as the last active one, ie a
+/// 4K and a 1080p copy of the same cut, resume at the offset of the last active version and have
+/// `mapped` set. Versions of other lengths, ie an extended cut, are left out.
+///
+/// Only switches reported through the `version` param of `POST /api/v1/media/<id>/progress` are
+/// known, `versions` is empty if the user never reported any.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "last_active": int | null,
+///     "versions": [{
+///         "mediafile_id": int,
+///         "offset": int,
+///         "mapped": bool,
+///     }],
+/// }
+/// ```
+///
+/// `versions` are ordered by when the user last played them, mapped versions come last.
+pub async fn get_resume_points(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let versions =
+        VersionProgress::get_for_media(&mut tx, user.0.claims.get_user_ref(), id).await?;
+
+    let active = match versions.first() {
+        Some(x) => x.mediafile_id,
+        None => {
+            return Ok(reply::json(&json!({
+                "last_active": null,
+                "versions": [],
+            })))
+        }
+    };
+
+    let current = progress_buffer::overlay(
+        Progress::get_for_media_user(&mut tx, user.0.claims.get_user(), id).await?,
+    )
+    .delta;
+
+    let mut points = versions
+        .iter()
+        .map(|x| {
+            json!({
+                "mediafile_id": x.mediafile_id,
+                "offset": if x.mediafile_id == active { current } else { x.delta },
+                "mapped": false,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let files = MediaFile::get_of_media(&mut tx, id).await?;
+
+    if let Some(length) = files
+        .iter()
+        .find(|x| x.id == active)
+        .and_then(|x| x.duration)
+    {
+        for file in files {
+            let same_cut = file
+                .duration
+                .map_or(false, |x| (x - length).abs() <= SAME_CUT_EPSILON_SECS);
+
+            if same_cut && !versions.iter().any(|x| x.mediafile_id == file.id) {
+                points.push(json!({
+                    "mediafile_id": file.id,
+                    "offset": current,
+                    "mapped": true,
+                }));
+            }
+        }
+    }
+
+    Ok(reply::json(&json!({
+        "last_active": active,
+        "versions": points,
+    })))
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/decide` decides for every version of a media whether
+/// a client can direct play it or needs it transcoded, based on the codecs we probed during scans.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `caps` - what the client can play, lists that are left out arent checked
+///
+/// # Data
+/// ```text
+/// {
+///     "video_codecs": [string],
+///     "audio_codecs": [string],
+///     "containers": [string],
+///     "max_height": int | null,
+///     "hdr": bool,
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "recommended": int | null,
+///     "versions": [{
+///         "mediafile_id": int,
+///         "decision": "directPlay" | "transcode",
+///         "reasons": [string],
+///         "codec": string | null,
+///         "audio": string | null,
+///         "container": string | null,
+///         "height": int | null,
+///     }],
+/// }
+/// ```
+///
+/// `recommended` is the id of the mediafile clients should play and is always the first entry of
+/// `versions`. Versions that can be direct played are recommended over those that need to be
+/// transcoded. Returns 404 if the media doesnt exist.
+pub async fn decide_playback(
+    conn: DbConnection,
+    id: i64,
+    caps: crate::playback::ClientCapabilities,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let mut mediafiles = MediaFile::get_of_media(&mut tx, id).await?;
+
+    if mediafiles.is_empty() {
+        if let Ok(x) = MediaFile::get_multi_episode_file(&mut tx, id).await {
+            mediafiles.push(x);
+        }
+    }
+
+    let versions = crate::playback::decide_all(&mediafiles, &caps);
+
+    Ok(reply::json(&json!({
+        "recommended": versions.first().map(|x| x.mediafile_id),
+        "versions": versions,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/episodes` returns the episodes of a show across all
+/// seasons as a flat list, ordered by season and episode with specials last, along with the
+/// progress of the current user through them. This is a lighter alternative to walking the seasons
+/// of large shows. The episodes are wrapped in a [`Paginated`](Paginated) envelope unless `flat`
+/// is set.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the show
+/// * `page` - pagination arguments
+/// * `order` - name of the watch order to return the episodes in
+/// * `user` - Auth middleware
+///
+/// # Query params
+/// * `order` - one of the orders listed by `GET /api/v1/media/<id>/orders`, defaults to
+/// `default`. Episodes missing from a alternate order come last. Returns `400` if the show has no
+/// order of that name.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "items": [{
+///         "id": int,
+///         "name": string,
+///         "season": int,
+///         "episode": int,
+///         "thumbnail_url": string | null,
+///         "progress": int,
+///         "duration": int,
+///     }],
+///     "page": int,
+///     "per_page": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn get_flat_episodes(
+    conn: DbConnection,
+    id: i64,
+    page: PageArgs,
+    order: Option<String>,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if media.media_type != MediaType::Tv {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    let order = order.filter(|x| x != DEFAULT_ORDER);
+
+    if let Some(name) = order.as_ref() {
+        if !EpisodeOrder::exists(&mut tx, id, name).await? {
+            return Err(errors::DimError::UnknownEpisodeOrder { name: name.clone() });
+        }
+    }
+
+    let (items, total) = Episode::get_flat_for_show(
+        &mut tx,
+        id,
+        user.0.claims.get_user_ref(),
+        order.as_deref(),
+        page.limit(),
+        page.offset(),
+    )
+    .await?;
+
+    Ok(Paginated::new(items, total, &page).into_reply(page.flat))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/orders` returns the watch orders episodes of a show
+/// can be listed in with `GET /api/v1/media/<id>/episodes?order=<name>`. The standard order by
+/// season and episode is named `default` and always comes first, alternate orders such as
+/// `chronological` follow by name.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the show
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "name": string,
+///     "episode_count": int,
+/// }]
+/// ```
+pub async fn get_episode_orders(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if media.media_type != MediaType::Tv {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    let mut orders = vec![EpisodeOrder {
+        name: DEFAULT_ORDER.into(),
+        episode_count: Episode::count_of_show(&mut tx, id).await?,
+    }];
+
+    orders.append(&mut EpisodeOrder::get_for_show(&mut tx, id).await?);
+
+    Ok(reply::json(&orders))
+}
+
+/// Method mapped to `PUT /api/v1/media/<id>/orders/<name>` replaces the alternate watch order
+/// `name` of a show, ie `chronological`, with the episodes passed in watch order. Passing no
+/// episodes removes the order. The `default` order cant be replaced. Only the owner can access
+/// this route.
+///
+/// Returns `400` if a episode doesnt belong to the show or is passed more than once.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the show
+/// * `name` - name of the order
+/// * `episodes` - ids of the episodes in watch order
+/// * `user` - Auth middleware
+///
+/// # Data
+/// ```text
+/// [int]
+/// ```
+pub async fn set_episode_order(
+    conn: DbConnection,
+    id: i64,
+    name: String,
+    episodes: Vec<i64>,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let name = percent_encoding::percent_decode_str(&name)
+        .decode_utf8_lossy()
+        .trim()
+        .to_string();
+
+    if name.is_empty() || name == DEFAULT_ORDER {
+        return Err(errors::DimError::InvalidEpisodeOrder);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    let media = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if media.media_type != MediaType::Tv {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    let of_show = Episode::get_all_of_tv(&mut tx, id)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect::<HashSet<_>>();
+
+    let mut seen = HashSet::new();
+    if !episodes
+        .iter()
+        .all(|x| of_show.contains(x) && seen.insert(*x))
+    {
+        return Err(errors::DimError::InvalidEpisodeOrder);
+    }
+
+    EpisodeOrder::set(&mut tx, id, &name, &episodes).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/source_files` returns the files backing a media as
+/// the scanner saw them, ie the original filename and parent directory along with the title, year,
+/// season and episode that were parsed out of it. For tv shows the files of all episodes are
+/// returned. This is meant for troubleshooting bad matches and is thus only accessible by the
+/// owner as it discloses paths on the host.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `user` - Auth middleware
+pub async fn get_media_source_files(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+
+    let mediafiles = match media.media_type {
+        MediaType::Movie | MediaType::Episode => MediaFile::get_of_media(&mut tx, id).await?,
+        MediaType::Tv => {
+            let mut mediafiles = vec![];
+            for episode in Episode::get_all_of_tv(&mut tx, id).await? {
+                mediafiles.append(&mut MediaFile::get_of_media(&mut tx, episode.id).await?);
+            }
+
+            mediafiles
+        }
+    };
+
+    let files = mediafiles
+        .into_iter()
+        .map(|x| {
+            let path = std::path::Path::new(&x.target_file);
+
+            json!({
+                "id": x.id,
+                "media_id": x.media_id,
+                "file_name": path.file_name().map(|x| x.to_string_lossy()),
+                "directory": path.parent().map(|x| x.to_string_lossy()),
+                "raw_name": x.raw_name,
+                "raw_year": x.raw_year,
+                "season": x.season,
+                "episode": x.episode,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&files))
+}
+
+/// Method mapped to `PATCH /api/v1/media/<id>` is used to edit information about a media entry
+/// manually. It is used in the web ui to manually edit metadata of a media. The `media_type` and
+/// `library_id` of a media cannot be changed here, requests setting them are rejected with `422`.
+/// Media are moved with `PATCH /api/v1/media/<id>/library` instead.
+///
+/// Setting `sort_title` changes the title the media is sorted by, ie to sort `The Matrix` under
+/// `M`. Once set it is kept across renames and rematches.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media we want to edit
+/// * `data` - the info that we changed about the media entry
+/// * `_user` - Auth middleware
+pub async fn update_media_by_id(
+    id: i64,
+    data: UpdateMedia,
+    _user: Auth,
+    conn: DbConnection,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if let Some(field) = data.immutable_fields().first() {
+        return Err(errors::DimError::ImmutableField {
+            field: field.to_string(),
+        });
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let status = if data.update_manual(&mut tx, id).await.is_ok() {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_MODIFIED
+    };
+
+    tx.commit().await?;
+
+    Ok(status)
+}
+
+/// Maximum number of media that can be edited with a single batch update.
+pub const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatchUpdate {
+    pub ids: Vec<i64>,
+    pub patch: MediaPatch,
+}
+
+/// Fields applied to every media of a batch update.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MediaPatch {
+    #[serde(flatten)]
+    pub media: UpdateMedia,
+    /// Genres replacing the current genres of the media.
+    pub genres: Option<Vec<String>>,
+}
+
+/// Method mapped to `PATCH /api/v1/media/batch` applies the same edits to several media at once,
+/// ie to fix the rating or genres of a whole library. Every media is updated in its own savepoint
+/// of a single transaction, thus a failing media doesnt affect the others. Only the owner can
+/// access this route.
+///
+/// # Arguments
+/// * `data` - ids of the media to edit and the fields to apply to each of them
+/// * `user` - Auth middleware
+/// * `conn` - database connection
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": int,
+///         "success": bool,
+///         "error": string | null,
+///     }
+/// ]
+/// ```
+pub async fn batch_update_media(
+    data: BatchUpdate,
+    user: Auth,
+    conn: DbConnection,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use sqlx::Acquire;
+
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if data.ids.len() > MAX_BATCH_SIZE {
+        return Err(errors::DimError::BatchTooLarge {
+            max: MAX_BATCH_SIZE,
+        });
+    }
+
+    if let Some(field) = data.patch.media.immutable_fields().first() {
+        return Err(errors::DimError::ImmutableField {
+            field: field.to_string(),
+        });
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let mut results = Vec::with_capacity(data.ids.len());
+
+    for id in data.ids {
+        let mut savepoint = tx.begin().await?;
+
+        let result = async {
+            Media::get(&mut savepoint, id)
+                .await
+                .map_err(|_| errors::DimError::NotFoundError)?;
+
+            data.patch.media.update_manual(&mut savepoint, id).await?;
+
+            if let Some(genres) = data.patch.genres.as_ref() {
+                Genre::set_for_media(&mut savepoint, id, genres).await?;
+            }
+
+            Ok::<_, errors::DimError>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                savepoint.commit().await?;
+                results.push(json!({ "id": id, "success": true, "error": null }));
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                results.push(json!({ "id": id, "success": false, "error": e.to_string() }));
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(reply::json(&results))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/overrides` returns the metadata fields of a media
+/// that were manually edited. These fields are preserved when the media is rematched.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `_user` - Auth middleware
+pub async fn get_media_overrides(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let overrides = Media::get_overrides(&mut tx, id).await?;
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "fields": overrides,
+    })))
+}
+
+/// Method mapped to `DELETE /api/v1/media/<id>/overrides` clears all manual overrides of a media
+/// so that automatic metadata takes over again on the next rematch.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `_user` - Auth middleware
+pub async fn clear_media_overrides(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    Media::clear_overrides(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `DELETE /api/v1/media/<id>` is used to delete a media entry for the library.
+/// ONly authenticated users can query this.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media we want to delete
+/// * `_user` - auth middleware
+pub async fn delete_media_by_id(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    Media::delete(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `GET /api/v1/media/tmdb_search` is used to quickly search TMDB based on 3
+/// params, one of which is optional. This is used client side in the rematch utility
+///
+/// # Arguments
+/// * `query` - the query we want to send to tmdb, ie movie title, tv show title
+/// * `year` - optional parameter specifying the release year of the media we want to look up
+/// * `media_type` - parameter that tells us what media type we are querying, ie movie or tv show
+pub async fn tmdb_search(
+    query: String,
+    year: Option<i32>,
+    media_type: String,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use crate::scanners::tmdb::Tmdb;
+
+    let media_type = match media_type.as_ref() {
+        "movie" => MediaType::Movie,
+        "tv" => MediaType::Tv,
+        _ => return Err(errors::DimError::InvalidMediaType),
+    };
+
+    let mut tmdb_session = Tmdb::new("38c372f5bc572c8aadde7a802638534e".to_string(), media_type);
+
+    Ok(reply::json(
+        &tmdb_session
+            .search_by_name(query, year, None)
+            .await?
+            .into_iter()
+            .map(Into::<crate::scanners::ApiMedia>::into)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Maximum number of media whose progress can be queried at once.
+pub const MAX_PROGRESS_QUERY_SIZE: usize = 200;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProgressQuery {
+    pub ids: Vec<i64>,
+}
+
+/// Method mapped to `POST /api/v1/media/progress/query` returns the progress of the user through
+/// several media at once, ie to render progress badges on a grid of media without querying each
+/// one individually. Every requested id is present in the response, media the user never started
+/// or that dont exist have a zeroed entry. At most 200 ids can be queried at once.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `data` - ids of the media to query
+/// * `user` - Auth middleware
+///
+/// # Data
+/// ```text
+/// {
+///     "ids": [int],
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "<media_id>": {
+///         "delta": int,
+///         "duration": int,
+///         "finished": bool,
+///     }
+/// }
+/// ```
+pub async fn query_progress(
+    conn: DbConnection,
+    data: ProgressQuery,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if data.ids.len() > MAX_PROGRESS_QUERY_SIZE {
+        return Err(errors::DimError::BatchTooLarge {
+            max: MAX_PROGRESS_QUERY_SIZE,
+        });
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let progress = Progress::get_for_medias(&mut tx, user.0.claims.get_user(), &data.ids)
+        .await?
+        .into_iter()
+        .map(|mut x| {
+            if let Some(delta) = progress_buffer::delta(user.0.claims.get_user_ref(), x.media_id) {
+                x.delta = delta;
+            }
+
+            (x.media_id, x)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let result = data
+        .ids
+        .iter()
+        .map(|id| {
+            let entry = match progress.get(id) {
+                Some(x) => json!({
+                    "delta": x.delta,
+                    "duration": x.duration,
+                    "finished": x.is_finished(),
+                }),
+                None => json!({
+                    "delta": 0,
+                    "duration": 0,
+                    "finished": false,
+                }),
+            };
+
+            (id.to_string(), entry)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    Ok(reply::json(&result))
+}
+
+/// Method mapped to `GET /api/v1/media/needs_review` returns the media the scanner couldnt match
+/// with confidence because TMDB returned several results that fit the file about equally well.
+/// Every media comes with the candidates it could be, most confident first, the media is currently
+/// matched to the first one. A media is resolved by rematching it to one of the candidates with
+/// `PATCH /api/v1/media/<id>/match?external_id=<tmdb_id>&media_type=<movie|tv>`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": int,
+///         "name": string,
+///         "media_type": "movie" | "tv",
+///         "library_id": int,
+///         "poster_path": string | null,
+///         "candidates": [
+///             {
+///                 "tmdb_id": int,
+///                 "title": string,
+///                 "release_date": string | null,
+///                 "poster_path": string | null,
+///                 "confidence": float,
+///             }
+///         ],
+///     }
+/// ]
+/// ```
+pub async fn needs_review(
+    conn: DbConnection,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let mut result = vec![];
+
+    for id in MatchCandidate::get_media_needing_review(&mut tx).await? {
+        let media = Media::get(&mut tx, id).await?;
+        let candidates = MatchCandidate::get_for_media(&mut tx, id).await?;
+
+        result.push(json!({
+            "id": media.id,
+            "name": media.name,
+            "media_type": media.media_type,
+            "library_id": media.library_id,
+            "poster_path": media.poster_path,
+            "candidates": candidates,
+        }));
+    }
+
+    Ok(reply::json(&result))
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/progress` is used to map progress for a certain media
+/// to the user. This is useful for remembering progress for a movie etc.
+///
+/// If the media is a episode stored in a file that spans multiple episodes, `offset` is treated
+/// as the position within that file. The progress is then attributed to the episode playing at
+/// that position, and episodes of the file before it are marked as watched.
+///
+/// If the user hosts a watch party for this media, the new offset is relayed to its members.
+///
+/// Players tend to report progress every second, so if `offset` hasn't changed from the stored
+/// progress nothing is written and the stored timestamp is left as is. If
+/// `progress_flush_interval_secs` is set in the global settings, progress is buffered in memory
+/// and written periodically instead, see [`progress_buffer`](crate::progress_buffer).
+///
+/// If the user set up a scrobble integration, starting and finishing a movie or episode queues a
+/// scrobble, see [`scrobble`](crate::scrobble).
+///
+/// Finishing a media records a play in the watch history of the user, unless the user turned off
+/// `auto_mark_watched` in their preferences.
+///
+/// If `version` is set, the user switching between versions of the media is recorded, such that
+/// they can resume each version where they left it, see
+/// `GET /api/v1/media/<id>/resume_points`. `version` is ignored for files spanning multiple
+/// episodes. Returns `404` if `version` isnt a file of the media.
+///
+/// Returns `403` if the user isn't allowed to play media right now because of their playback
+/// windows.
+///
+/// # Arguments
+/// * `id` - id of the media to modify
+///
+/// # Query params
+/// * `offset` - offset in seconds
+/// * `version` - id of the mediafile being played
+pub async fn map_progress(
+    conn: DbConnection,
+    parties: WatchParties,
+    id: i64,
+    offset: i64,
+    version: Option<i64>,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    // check on the read pool first so that repeated offsets never take the writer.
+    let (updates, scrobbles, switch, record_play) = {
+        let mut tx = conn.read().begin().await?;
+        crate::routes::auth::check_playback_window(&mut tx, &user).await?;
+
+        let current = progress_buffer::overlay(
+            Progress::get_for_media_user(&mut tx, user.0.claims.get_user(), id).await?,
+        );
+
+        // the version the user left and the one they switched to, only written on a switch so
+        // that reports while playing the same version stay cheap.
+        let switch = match version {
+            Some(to) => {
+                let active =
+                    VersionProgress::get_active(&mut tx, user.0.claims.get_user_ref(), id).await?;
+
+                if active == Some(to) {
+                    None
+                } else {
+                    let file = MediaFile::get_one(&mut tx, to)
+                        .await
+                        .map_err(|_| errors::DimError::NotFoundError)?;
+
+                    if file.media_id != Some(id) {
+                        return Err(errors::DimError::NotFoundError);
+                    }
+
+                    Some((active.map(|x| (x, current.delta)), to))
+                }
+            }
+            None => None,
+        };
+
+        let updates = match MediaFile::get_multi_episode_file(&mut tx, id).await {
+            // without a duration we cant tell where the episodes start.
+            Ok(file) if file.episode_length() > 0 => {
+                let length = file.episode_length();
+                let mut updates = vec![];
+
+                for (episode_id, episode) in file.get_contained_episodes(&mut tx).await? {
+                    let start = file.episode_offset(episode).unwrap_or(0);
+
+                    if offset < start {
+                        break;
+                    }
+
+                    updates.push((episode_id, (offset - start).min(length)));
                 }
+
+                updates
+            }
+            // offsets into files spanning multiple episodes dont map onto the stored progress.
+            Err(_) if switch.is_none() && current.is_same_offset(offset) => {
+                return Ok(StatusCode::OK)
+            }
+            _ => vec![(id, offset)],
+        };
+
+        let switch = switch.filter(|_| updates == [(id, offset)]);
+        let scrobbles = scrobble::detect(&mut tx, user.0.claims.get_user_ref(), &updates).await?;
+        let record_play = User::get(&mut tx, user.0.claims.get_user_ref())
+            .await?
+            .prefs
+            .auto_mark_watched();
+
+        (updates, scrobbles, switch, record_play)
+    };
+
+    let buffered = progress_buffer::is_enabled();
+
+    if buffered {
+        for &(media_id, delta) in &updates {
+            progress_buffer::record(user.0.claims.get_user_ref(), media_id, delta);
+        }
+    }
+
+    // scrobbles are queued even if progress is buffered so that they are sent right away.
+    if !buffered || !scrobbles.is_empty() || switch.is_some() {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+
+        if !buffered {
+            for &(media_id, delta) in &updates {
+                Progress::set_with(
+                    &mut tx,
+                    delta,
+                    user.0.claims.get_user(),
+                    media_id,
+                    record_play,
+                )
+                .await?;
+            }
+        }
+
+        if let Some((from, to)) = switch {
+            VersionProgress::switch(&mut tx, user.0.claims.get_user_ref(), from, to, offset)
+                .await?;
+        }
+
+        scrobble::queue(&mut tx, user.0.claims.get_user_ref(), &scrobbles).await?;
+        tx.commit().await?;
+    }
+
+    parties.update_progress(user.0.claims.get_user_ref(), id, offset);
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/authorize_playback` checks whether the current user
+/// may start playing a media right now. Clients should call this before starting playback.
+/// Returns `403` along with the reason if the current time lies outside of the playback windows
+/// of the user. The owner is never restricted.
+///
+/// # Arguments
+/// * `id` - id of the media
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "allowed": bool,
+/// }
+/// ```
+pub async fn authorize_playback(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    crate::routes::auth::check_playback_window(&mut tx, &user).await?;
+
+    Ok(reply::json(&json!({ "allowed": true })))
+}
+
+/// Method mapped to `PUT /api/v1/media/episode/<id>/markers` sets the timestamps of the intro and
+/// credits of a episode, which players use to offer skipping them. Timestamps are in seconds and
+/// omitted markers are cleared. The markers are returned as part of `GET /api/v1/media/<id>` for
+/// episodes. Only the owner can access this route.
+///
+/// # Arguments
+/// * `id` - id of the episode
+/// * `markers` - the new markers
+///
+/// # Data
+/// This route additionally requires you to pass in a json object by the format of
+/// `database::episode_markers::EpisodeMarkers`.
+/// ```text
+/// {
+///     "intro": { "start": int, "end": int } | null,
+///     "credits": { "start": int } | null,
+/// }
+/// ```
+pub async fn set_episode_markers(
+    conn: DbConnection,
+    id: i64,
+    markers: EpisodeMarkers,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if !markers.is_valid() {
+        return Err(errors::DimError::InvalidMarkers);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    Episode::get_by_id(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    markers.set(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `POST /api/v1/media/episode/<id>/watched` explicitly marks a episode as
+/// watched or unwatched for the current user, regardless of actual playback. This is useful for
+/// episodes watched elsewhere. Watched episodes get their progress set to their full duration,
+/// unwatched episodes have their progress cleared. Files spanning multiple episodes are watched
+/// as a whole, thus every episode contained in the file of the episode is marked.
+///
+/// The new progress is pushed to the event sockets of the current user only.
+///
+/// # Arguments
+/// * `id` - id of the episode
+///
+/// # Query params
+/// * `state` - whether the episode has been watched
+pub async fn set_episode_watched(
+    conn: DbConnection,
+    user_tx: UserEventTx,
+    id: i64,
+    state: bool,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    // the episode must not be marked unwatched again by progress buffered before this request.
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+
+    let durations = {
+        let mut tx = conn.read().begin().await?;
+        Episode::get_by_id(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
+
+        match MediaFile::get_multi_episode_file(&mut tx, id).await {
+            Ok(file) if file.episode_length() > 0 => file
+                .get_contained_episodes(&mut tx)
+                .await?
+                .into_iter()
+                .map(|(episode_id, _)| (episode_id, Some(file.episode_length())))
+                .collect::<Vec<_>>(),
+            _ => vec![(id, Media::get_cached_duration(&mut tx, id).await?)],
+        }
+    };
+
+    let updates = durations
+        .into_iter()
+        .map(|(episode_id, duration)| {
+            let delta = if state {
+                duration.ok_or(errors::DimError::UnknownDuration)?
             } else {
-                let ep = Episode::get_first_for_show(&mut tx, id).await?;
-                Some(json!({
-                    "progress": 0,
-                    "season": ep.get_season_number(&mut tx).await.unwrap_or(0),
-                    "episode": ep.episode,
-                    "play_btn_id": ep.id,
-                }))
-            }
+                0
+            };
+
+            Ok((episode_id, delta))
+        })
+        .collect::<Result<Vec<_>, errors::DimError>>()?;
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    for (episode_id, delta) in updates.iter().copied() {
+        if state {
+            Progress::set(&mut tx, delta, user.0.claims.get_user(), episode_id).await?;
+        } else {
+            // a timestamp of 0 keeps the episode out of continue watching.
+            Progress::restore(&mut tx, 0, user.0.claims.get_user(), episode_id, 0).await?;
         }
+    }
+
+    tx.commit().await?;
+
+    for (episode_id, delta) in updates.iter().copied() {
+        let event = Message {
+            id: episode_id,
+            event_type: PushEventType::EventProgress {
+                user: user.0.claims.get_user(),
+                delta,
+            },
+        };
+
+        let _ = user_tx.send((
+            vec![user.0.claims.get_user()],
+            serde_json::to_string(&event).unwrap(),
+        ));
+    }
+
+    let delta = updates
+        .iter()
+        .find(|(episode_id, _)| *episode_id == id)
+        .map(|(_, delta)| *delta)
+        .unwrap_or(0);
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "watched": state,
+        "progress": delta,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/s<season>e<episode>/progress` returns the progress of
+/// the user for a episode of a show addressed by its season and episode number. This lets clients
+/// resume playback from deep links without fetching the whole show first.
+///
+/// # Arguments
+/// * `id` - id of the tv show
+/// * `season` - season number of the episode
+/// * `episode` - episode number
+pub async fn get_episode_progress(
+    conn: DbConnection,
+    id: i64,
+    season: i64,
+    episode: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let ep = Episode::get(&mut tx, id, season, episode)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let progress = progress_buffer::overlay(
+        Progress::get_for_media_user(&mut tx, user.0.claims.get_user(), ep.id).await?,
+    );
+    let duration = Media::get_cached_duration(&mut tx, ep.id)
+        .await
+        .ok()
+        .flatten();
+
+    Ok(reply::json(&json!({
+        "id": ep.id,
+        "season": season,
+        "episode": episode,
+        "progress": progress.delta,
+        "duration": duration,
+        "populated": progress.populated,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/media/in_progress_shows` returns the shows of which the user has
+/// watched some but not all episodes along with how far through each show they are. Shows are
+/// ordered by when they were last watched.
+pub async fn get_in_progress_shows(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+    let mut tx = conn.read().begin().await?;
+    let shows = Progress::get_in_progress_shows(&mut tx, user.0.claims.get_user()).await?;
+
+    Ok(reply::json(
+        &shows
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "id": x.id,
+                    "name": x.name,
+                    "poster_path": x.poster_path,
+                    "watched_episodes": x.watched_episodes,
+                    "total_episodes": x.total_episodes,
+                    "percent": x.percent(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/hide` hides a media from the browse of the user.
+/// Hidden media are left out of library listings and searches unless `include_hidden` is set,
+/// other users still see them. Hiding a media that is already hidden is a noop.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media to hide
+/// * `user` - Auth middleware
+pub async fn hide_media(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    // make sure the media exists before hiding it.
+    let _ = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    HiddenMedia::hide(&mut tx, user.0.claims.get_user_ref(), id).await?;
+
+    tx.commit().await?;
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `DELETE /api/v1/media/<id>/hide` makes a hidden media show up in the browse
+/// of the user again. Returns `404` if the user didnt hide the media.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media to unhide
+/// * `user` - Auth middleware
+pub async fn unhide_media(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    if HiddenMedia::unhide(&mut tx, user.0.claims.get_user_ref(), id).await? == 0 {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `GET /api/v1/media/hidden` returns the media the user hid, most recently
+/// hidden first.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "id": int,
+///     "library_id": int,
+///     "name": string,
+///     "poster_path": string | null,
+///     "media_type": string,
+/// }]
+/// ```
+pub async fn get_hidden_media(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = HiddenMedia::get_for_user(&mut tx, user.0.claims.get_user_ref()).await?;
+
+    Ok(reply::json(
+        &media
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "id": x.id,
+                    "library_id": x.library_id,
+                    "name": x.name,
+                    "poster_path": x.poster_path,
+                    "media_type": x.media_type,
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Method mapped to `GET /api/v1/media/popular` returns the most watched media within a time
+/// window ordered by how many times they were played to completion, ties are broken by rating.
+/// Plays of episodes count towards their show.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `window` - time window to count plays in, one of `week`, `month` or `all`. Defaults to
+/// `week`.
+/// * `limit` - max number of media to return, defaults to 20.
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "id": int,
+///     "name": string,
+///     "poster_path": string | null,
+///     "rating": int | null,
+///     "plays": int,
+/// }]
+/// ```
+pub async fn get_popular(
+    conn: DbConnection,
+    window: Option<String>,
+    limit: Option<i64>,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    const DAY: i64 = 60 * 60 * 24;
+
+    let window = match window.as_deref().unwrap_or("week") {
+        "week" => Some(7 * DAY),
+        "month" => Some(30 * DAY),
+        "all" => None,
+        _ => return Err(errors::DimError::InvalidWindow),
     };
 
-    fn mediafile_tags(x: &MediaFile) -> serde_json::Value {
-        let video_tag = format!(
-            "{} ({})",
-            x.quality
-                .as_ref()
-                .map(|x| format!("{}p", x))
-                .unwrap_or("Unknown".into()),
-            crate::utils::codec_pretty(x.codec.as_deref().unwrap_or("Unknown"))
-        );
+    let since = window.map_or(0, |x| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        let audio_lang = x.audio_language.as_deref().unwrap_or("Unknown");
-        let audio_codec = crate::utils::codec_pretty(x.audio.as_deref().unwrap_or("Unknown"));
-        let audio_ch = crate::utils::channels_pretty(x.channels.unwrap_or(2));
+        now - x
+    });
+
+    let mut tx = conn.read().begin().await?;
+    let popular = History::get_popular(
+        &mut tx,
+        user.0.claims.get_user_ref(),
+        since,
+        limit.unwrap_or(20).clamp(1, 100),
+    )
+    .await?;
+
+    Ok(reply::json(&popular))
+}
+
+/// Method mapped to `GET /api/v1/media/added_between?<from>&<to>` returns the media added within
+/// a date range, most recent first. Unlike the recently added section of the dashboard this is
+/// bounded by date rather than count. The media are wrapped in a [`Paginated`](Paginated)
+/// envelope unless `flat` is set.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `from` - first day of the range formatted as `YYYY-MM-DD`, inclusive.
+/// * `to` - last day of the range formatted as `YYYY-MM-DD`, inclusive.
+/// * `by_episode` - if set tv shows are considered added when their newest episode was added.
+/// * `page` - pagination arguments
+/// * `_user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "items": [{
+///         "id": int,
+///         "name": string,
+///         "poster_path": string | null,
+///         "media_type": "movie" | "tv",
+///         "added": string,
+///     }],
+///     "page": int,
+///     "per_page": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn get_added_between(
+    conn: DbConnection,
+    from: String,
+    to: String,
+    by_episode: bool,
+    page: PageArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use chrono::NaiveDate;
+
+    let parse = |x: &str| {
+        NaiveDate::parse_from_str(x, "%Y-%m-%d").map_err(|_| errors::DimError::InvalidDateRange)
+    };
+
+    let (from, to) = (parse(&from)?, parse(&to)?);
+
+    if from > to {
+        return Err(errors::DimError::InvalidDateRange);
+    }
+
+    // `to` is inclusive, thus we query up until the start of the next day.
+    let to = to.succ_opt().ok_or(errors::DimError::InvalidDateRange)?;
+
+    let mut tx = conn.read().begin().await?;
+    let (items, total) = Media::get_added_between(
+        &mut tx,
+        user.0.claims.get_user_ref(),
+        &from.to_string(),
+        &to.to_string(),
+        by_episode,
+        page.limit(),
+        page.offset(),
+    )
+    .await?;
+
+    Ok(Paginated::new(items, total, &page).into_reply(page.flat))
+}
+
+/// Method mapped to `GET /api/v1/media/recently_added` returns the movies and shows most recently
+/// added to the library, most recent first. The media are wrapped in a [`Paginated`](Paginated)
+/// envelope unless `flat` is set. Media the user hid and media in hidden libraries are left out.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `page` - pagination arguments
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "items": [{
+///         "id": int,
+///         "name": string,
+///         "poster_path": string | null,
+///         "media_type": "movie" | "tv",
+///         "added": string,
+///     }],
+///     "page": int,
+///     "per_page": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn get_recently_added(
+    conn: DbConnection,
+    page: PageArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let (items, total) = Media::get_recently_added_page(
+        &mut tx,
+        user.0.claims.get_user_ref(),
+        page.limit(),
+        page.offset(),
+    )
+    .await?;
+
+    Ok(Paginated::new(items, total, &page).into_reply(page.flat))
+}
+
+/// Number of days after which the metadata of a media is considered stale by default.
+const DEFAULT_STALE_DAYS: i64 = 30;
+
+/// Method mapped to `GET /api/v1/media/stale?<older_than_days>` returns the media whose metadata
+/// was last fetched from TMDB more than `older_than_days` days ago, oldest first, so that they can
+/// be refreshed with `POST /api/v1/media/<id>/refresh`. Media matched before refreshes were
+/// tracked come first. The media are wrapped in a [`Paginated`](Paginated) envelope unless `flat`
+/// is set. Only the owner can access this route.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `older_than_days` - age in days after which metadata is stale, defaults to 30.
+/// * `page` - pagination arguments
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "items": [{
+///         "id": int,
+///         "name": string,
+///         "media_type": "movie" | "tv",
+///         "metadata_refreshed_at": int | null,
+///     }],
+///     "page": int,
+///     "per_page": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn get_stale(
+    conn: DbConnection,
+    older_than_days: Option<i64>,
+    page: PageArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    // huge ages saturate, which simply returns no media instead of overflowing.
+    let days = older_than_days.unwrap_or(DEFAULT_STALE_DAYS).max(0);
+    let cutoff = chrono::Utc::now()
+        .timestamp()
+        .saturating_sub(days.saturating_mul(60 * 60 * 24));
+
+    let mut tx = conn.read().begin().await?;
+    let (items, total) = Media::get_stale(&mut tx, cutoff, page.limit(), page.offset()).await?;
+
+    Ok(Paginated::new(items, total, &page).into_reply(page.flat))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/stats` returns the watch statistics of a media, ie
+/// how many times it was watched to completion, the average completion percentage and when it was
+/// last watched. For tv shows the statistics of all episodes are aggregated. The owner gets the
+/// statistics of all users, everyone else only gets their own. Returns `404` if the media doesnt
+/// exist.
+///
+/// # Arguments
+/// * `id` - id of the media
+pub async fn get_media_stats(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if user.0.claims.has_role("owner") {
+        progress_buffer::flush(&conn).await?;
+    } else {
+        progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let _ = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let uid = if user.0.claims.has_role("owner") {
+        None
+    } else {
+        Some(user.0.claims.get_user())
+    };
+
+    let stats = Progress::get_stats(&mut tx, id, uid).await?;
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "watch_count": stats.watch_count,
+        "average_completion": stats.average_completion,
+        "last_watched": stats.last_watched,
+    })))
+}
+
+/// Max number of media returned by `GET /api/v1/media/<id>/because_you_watched` over all pages.
+pub const MAX_SIMILAR_LIMIT: i64 = 50;
+
+/// Method mapped to `GET /api/v1/media/<id>/because_you_watched` returns the movies and shows most
+/// similar to a seed media that the user hasn't watched yet, used to build "Because you watched"
+/// rows. Media score points for every genre and cast member they share with the seed and for
+/// belonging to the same collection. Episodes are seeded by their show. The seed, watched media,
+/// media hidden by the user and media sharing nothing with the seed are left out. Returns `404`
+/// if the seed doesnt exist.
+///
+/// The media are wrapped in a [`Paginated`](Paginated) envelope unless `flat` is set. Only the 50
+/// most similar media are ever returned, thus `total` is capped at 50.
+///
+/// # Arguments
+/// * `id` - id of the seed media
+/// * `page` - pagination arguments
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "items": [{
+///         "id": int,
+///         "name": string,
+///         "poster_path": string | null,
+///         "media_type": "movie" | "tv",
+///         "score": int,
+///     }],
+///     "page": int,
+///     "per_page": int,
+///     "total": int,
+/// }
+/// ```
+pub async fn because_you_watched(
+    conn: DbConnection,
+    id: i64,
+    page: PageArgs,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let uid = user.0.claims.get_user_ref();
+    progress_buffer::flush_user(&conn, uid).await?;
 
-        let audio_tag = format!("{} ({} {})", audio_lang, audio_codec, audio_ch);
+    let mut tx = conn.read().begin().await?;
+    let mut seed = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
 
-        json!({
-            "video": video_tag,
-            "audio": audio_tag,
-        })
+    if let MediaType::Episode = seed.media_type {
+        let episode = Episode::get_by_id(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
+        let season = Season::get_by_id(&mut tx, episode.seasonid)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
+        seed = Media::get(&mut tx, season.tvshowid)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
     }
 
-    let quality_tags = match media.media_type {
-        MediaType::Episode | MediaType::Movie => json!({
-                media.id.to_string(): MediaFile::get_of_media(&mut tx, media.id)
-                    .await?
-                    .first()
-                    .map(mediafile_tags)
-        }),
-        MediaType::Tv => json!(MediaFile::get_of_show(&mut tx, media.id)
-            .await?
-            .iter()
-            .map(|x| (x.media_id.unwrap(), mediafile_tags(x)))
-            .collect::<HashMap<_, _>>()),
-    };
+    let media = Media::get_similar(&mut tx, seed.id, uid, MAX_SIMILAR_LIMIT).await?;
 
-    let season_episode_tag = match media.media_type {
-        MediaType::Episode => {
-            let result = Episode::get_season_episode_by_id(&mut tx, id).await?;
-            Some(json!({
-                "season": result.0,
-                "episode": result.1,
-            }))
-        }
-        _ => None,
-    };
+    Ok(Paginated::from_vec(media, &page).into_reply(page.flat))
+}
 
-    const EPISODE_DONE_THRESH: f64 = 0.9;
+/// Default number of media returned by `GET /api/v1/media/random`.
+pub const DEFAULT_RANDOM_COUNT: i64 = 10;
+/// Max number of media returned by `GET /api/v1/media/random`.
+pub const MAX_RANDOM_COUNT: i64 = 50;
 
-    let next_episode_id = match Episode::get_by_id(&mut tx, id).await {
-        Ok(x) => {
-            let next_episode = if let Ok(x) = x.get_next_episode(&mut tx).await {
-                Some(json!({
-                    "next_episode_id": x.id,
-                    "chapters": {
-                        "credits": x.media.get_first_duration(&mut tx).await as f64 * EPISODE_DONE_THRESH
-                    }
-                }))
-            } else {
-                None
-            };
+/// Method mapped to `GET /api/v1/media/random` returns a random sample of movies and shows, used
+/// by the shuffle button. Media the user has watched or hidden are left out by default. Passing
+/// the same `seed` returns the same sample, which lets clients build picks that only change once
+/// a day by seeding with the current date.
+///
+/// # Query params
+/// * `count` - max number of media to return, defaults to 10 and is capped at 50
+/// * `media_type` - only return media of this type, either `movie` or `tv`
+/// * `include_watched` - whether media the user has watched are returned, defaults to false
+/// * `seed` - seed of the sample, a new sample is returned on every call if not set
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": int,
+///         "library_id": int,
+///         "name": string,
+///         "description": string | null,
+///         "rating": int | null,
+///         "year": int | null,
+///         "added": string | null,
+///         "poster_path": string | null,
+///         "backdrop_path": string | null,
+///         "media_type": "movie" | "tv",
+///     }
+/// ]
+/// ```
+pub async fn get_random_media(
+    conn: DbConnection,
+    count: Option<i64>,
+    media_type: Option<String>,
+    include_watched: bool,
+    seed: Option<i64>,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let media_type = match media_type.as_deref() {
+        None => None,
+        Some("movie") => Some(MediaType::Movie),
+        Some("tv") => Some(MediaType::Tv),
+        _ => return Err(errors::DimError::InvalidMediaType),
+    };
 
-            let prev_episode = x
-                .get_prev_episode(&mut tx)
-                .await
-                .map(|x| json!({"prev_episode_id": x.id}))
-                .ok();
+    let uid = user.0.claims.get_user_ref();
+    progress_buffer::flush_user(&conn, uid).await?;
 
-            if next_episode.is_some() || prev_episode.is_some() {
-                Some(json!({
-                    ..?next_episode,
-                    ..?prev_episode,
-                }))
-            } else {
-                None
-            }
-        },
-        Err(_) => None,
-    };
+    let count = count
+        .unwrap_or(DEFAULT_RANDOM_COUNT)
+        .clamp(1, MAX_RANDOM_COUNT);
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get_random(&mut tx, uid, media_type, !include_watched, seed, count).await?;
 
-    // FIXME: Remove the duration tag once the UI transitioned to using duration_pretty
-    Ok(reply::json(&json!({
-        "id": media.id,
-        "library_id": media.library_id,
-        "name": media.name,
-        "description": media.description,
-        "rating": media.rating,
-        "year": media.year,
-        "added": media.added,
-        "poster_path": media.poster_path,
-        "backdrop_path": media.backdrop_path,
-        "media_type": media.media_type,
-        "genres": genres,
-        "duration": duration,
-        "tags": quality_tags,
-        ..?next_episode_id,
-        ..?season_episode_tag,
-        ..?progress
-    })))
+    Ok(reply::json(&media))
 }
 
-pub async fn get_media_files(
+/// Method mapped to `POST /api/v1/media/<id>/rate` is used to rate a media. Rating the same media
+/// again replaces the previous score of the user. Returns `404` if the media doesnt exist.
+///
+/// # Arguments
+/// * `id` - id of the media to rate
+///
+/// # Query params
+/// * `score` - score between 1 and 10
+pub async fn rate_media(
     conn: DbConnection,
     id: i64,
+    score: i64,
+    user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
-    let mut tx = conn.read().begin().await?;
-    let mediafiles = MediaFile::get_of_media(&mut tx, id).await?;
-    Ok(reply::json(&mediafiles))
+    use database::rating::MAX_SCORE;
+    use database::rating::MIN_SCORE;
+
+    if !(MIN_SCORE..=MAX_SCORE).contains(&score) {
+        return Err(errors::DimError::InvalidRating {
+            min: MIN_SCORE,
+            max: MAX_SCORE,
+        });
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    // make sure the media exists before rating it.
+    let _ = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    InsertableRating {
+        user_id: user.0.claims.get_user(),
+        media_id: id,
+        score,
+    }
+    .insert(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(StatusCode::OK)
 }
 
-/// Method mapped to `PATCH /api/v1/media/<id>` is used to edit information about a media entry
-/// manually. It is used in the web ui to manually edit metadata of a media.
+/// Method mapped to `DELETE /api/v1/media/<id>/rate` removes the rating the user has given to a
+/// media. Removing a rating that doesnt exist is a no-op, `404` is returned if the media doesnt
+/// exist.
 ///
 /// # Arguments
-/// * `conn` - database connection
-/// * `id` - id of the media we want to edit
-/// * `data` - the info that we changed about the media entry
-/// * `_user` - Auth middleware
-pub async fn update_media_by_id(
-    id: i64,
-    data: UpdateMedia,
-    _user: Auth,
+/// * `id` - id of the media to remove the rating of
+pub async fn delete_rating(
     conn: DbConnection,
+    id: i64,
+    user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
     let mut lock = conn.writer().lock_owned().await;
     let mut tx = database::write_tx(&mut lock).await?;
-    let status = if data.update(&mut tx, id).await.is_ok() {
-        StatusCode::NO_CONTENT
-    } else {
-        StatusCode::NOT_MODIFIED
-    };
+
+    let _ = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    Rating::delete(&mut tx, user.0.claims.get_user(), id).await?;
 
     tx.commit().await?;
+    Ok(StatusCode::OK)
+}
 
-    Ok(status)
+/// Method mapped to `GET /api/v1/media/<id>/library` returns all libraries the media could be
+/// moved to, ie all libraries with the same media type, except the one the media is currently in.
+/// Only the owner can access this route.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the media
+/// * `user` - Auth middleware
+pub async fn get_movable_libraries(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+
+    let libraries = Library::get_all(&mut tx)
+        .await
+        .into_iter()
+        .filter(|x| x.media_type == media.media_type && x.id != media.library_id)
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&libraries))
 }
 
-/// Method mapped to `DELETE /api/v1/media/<id>` is used to delete a media entry for the library.
-/// ONly authenticated users can query this.
+/// Method mapped to `PATCH /api/v1/media/<id>/library` moves a media, its episodes and all of
+/// their mediafiles into another library. The target library must have the same media type as the
+/// media, otherwise `422` is returned. Only the owner can access this route.
 ///
 /// # Arguments
 /// * `conn` - database connection
-/// * `id` - id of the media we want to delete
-/// * `_user` - auth middleware
-pub async fn delete_media_by_id(
+/// * `event_tx` - channel over which to dispatch events
+/// * `id` - id of the media to move
+/// * `library_id` - id of the target library
+/// * `user` - Auth middleware
+pub async fn move_media_to_library(
     conn: DbConnection,
+    event_tx: EventTx,
     id: i64,
-    _user: Auth,
+    library_id: i64,
+    user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
     let mut lock = conn.writer().lock_owned().await;
     let mut tx = database::write_tx(&mut lock).await?;
-    Media::delete(&mut tx, id).await?;
+
+    let media = Media::get(&mut tx, id).await?;
+    let target = Library::get_one(&mut tx, library_id)
+        .await
+        .map_err(|_| errors::DimError::LibraryNotFound)?;
+
+    if target.media_type != media.media_type {
+        return Err(errors::DimError::LibraryTypeMismatch);
+    }
+
+    Media::move_to_library(&mut tx, id, target.id).await?;
     tx.commit().await?;
+
+    let event = Message {
+        id,
+        event_type: PushEventType::EventRemoveCard,
+    };
+
+    let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+
+    let event = Message {
+        id,
+        event_type: PushEventType::EventNewCard { lib_id: target.id },
+    };
+
+    let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+
     Ok(StatusCode::OK)
 }
 
-/// Method mapped to `GET /api/v1/media/tmdb_search` is used to quickly search TMDB based on 3
-/// params, one of which is optional. This is used client side in the rematch utility
+/// Method mapped to `GET /api/v1/media/duplicates` returns groups of medias which were matched
+/// against the same TMDB id, so that they can be merged with `POST /api/v1/media/<id>/merge`.
+/// Only the owner can access this route.
 ///
-/// # Arguments
-/// * `query` - the query we want to send to tmdb, ie movie title, tv show title
-/// * `year` - optional parameter specifying the release year of the media we want to look up
-/// * `media_type` - parameter that tells us what media type we are querying, ie movie or tv show
-pub async fn tmdb_search(
-    query: String,
-    year: Option<i32>,
-    media_type: String,
-    _user: Auth,
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "tmdb_id": int,
+///         "media": [Media],
+///     }
+/// ]
+/// ```
+pub async fn get_duplicates(
+    conn: DbConnection,
+    user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
-    use crate::scanners::tmdb::Tmdb;
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
 
-    let media_type = match media_type.as_ref() {
-        "movie" => MediaType::Movie,
-        "tv" => MediaType::Tv,
-        _ => return Err(errors::DimError::InvalidMediaType),
-    };
+    let mut tx = conn.read().begin().await?;
 
-    let mut tmdb_session = Tmdb::new("38c372f5bc572c8aadde7a802638534e".to_string(), media_type);
+    let mut groups: Vec<(i64, Vec<Media>)> = Vec::new();
+    for (tmdb_id, media) in Media::get_duplicates(&mut tx).await? {
+        match groups.last_mut() {
+            Some((last, medias)) if *last == tmdb_id => medias.push(media),
+            _ => groups.push((tmdb_id, vec![media])),
+        }
+    }
 
     Ok(reply::json(
-        &tmdb_session
-            .search_by_name(query, year, None)
-            .await
-            .map_err(|_| errors::DimError::NotFoundError)?
+        &groups
             .into_iter()
-            .map(Into::<crate::scanners::ApiMedia>::into)
+            .map(|(tmdb_id, media)| json!({ "tmdb_id": tmdb_id, "media": media }))
             .collect::<Vec<_>>(),
     ))
 }
 
-/// Method mapped to `POST /api/v1/media/<id>/progress` is used to map progress for a certain media
-/// to the user. This is useful for remembering progress for a movie etc.
+/// Method mapped to `GET /api/v1/media/by_tmdb/<tmdb_id>` returns all local medias matched
+/// against a TMDB id. Several medias are returned if multiple libraries contain the same title.
 ///
 /// # Arguments
-/// * `id` - id of the media to modify
+/// * `conn` - database connection
+/// * `tmdb_id` - id of the media on TMDB
+/// * `_user` - Auth middleware
+pub async fn get_media_by_tmdb_id(
+    conn: DbConnection,
+    tmdb_id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get_all_by_tmdb_id(&mut tx, tmdb_id).await?;
+
+    if media.is_empty() {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    Ok(reply::json(&media))
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/merge` merges the media `id` into the media `into`.
+/// All files, episodes, progress and ratings are moved over and `id` is removed afterwards, see
+/// `POST /api/v1/media/<id>/merge_into/<into>`. Both medias must have the same media type and
+/// merging a media into itself is rejected with `400`. Only the owner can access this route.
 ///
-/// # Query params
-/// * `offset` - offset in seconds
-pub async fn map_progress(
+/// # Arguments
+/// * `conn` - database connection
+/// * `event_tx` - channel over which to dispatch events
+/// * `id` - id of the media which gets merged and removed
+/// * `into` - id of the media which is kept
+/// * `user` - Auth middleware
+pub async fn merge_media(
     conn: DbConnection,
+    event_tx: EventTx,
     id: i64,
-    offset: i64,
+    into: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if id == into {
+        return Err(errors::DimError::InvalidMerge);
+    }
+
+    merge(&conn, &event_tx, id, into).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/merge_into/<into>` merges the media `id` into the
+/// media `into` and returns the media that survives, used to clean up duplicates created by the
+/// scanner. All files, episodes, progress, ratings, plays, tags, playlist items and watch orders
+/// are moved over in a single transaction and `id` is removed afterwards. Users who have progress
+/// on both medias keep the furthest offset, watch orders both shows have keep the one of `into`.
+/// Both medias must have the same media type and `404` is returned if either doesnt exist. Only
+/// the owner can access this route.
+///
+/// # Arguments
+/// * `id` - id of the media which gets merged and removed
+/// * `into` - id of the media which is kept
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "library_id": int,
+///     "name": string,
+///     "description": string | null,
+///     "rating": int | null,
+///     "year": int | null,
+///     "added": string | null,
+///     "poster_path": string | null,
+///     "backdrop_path": string | null,
+///     "media_type": "movie" | "tv" | "episode",
+/// }
+/// ```
+pub async fn merge_media_into(
+    conn: DbConnection,
+    event_tx: EventTx,
+    id: i64,
+    into: i64,
     user: Auth,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    if id == into {
+        return Err(errors::DimError::InvalidMerge);
+    }
+
+    Ok(reply::json(&merge(&conn, &event_tx, id, into).await?))
+}
+
+/// Merges the media `id` into the media `into` and returns the media that survives. Fails if
+/// either media is being rematched or refreshed, or if their media types differ.
+async fn merge(
+    conn: &DbConnection,
+    event_tx: &EventTx,
+    id: i64,
+    into: i64,
+) -> Result<Media, errors::DimError> {
+    let _lock = MediaLock::try_acquire(id).ok_or(errors::DimError::MediaLocked)?;
+    let _target_lock = MediaLock::try_acquire(into).ok_or(errors::DimError::MediaLocked)?;
+
     let mut lock = conn.writer().lock_owned().await;
     let mut tx = database::write_tx(&mut lock).await?;
-    Progress::set(&mut tx, offset, user.0.claims.get_user(), id).await?;
+
+    let media = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let target = Media::get(&mut tx, into)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if media.media_type != target.media_type {
+        return Err(errors::DimError::InvalidMediaType);
+    }
+
+    Media::merge_into(&mut tx, id, into).await?;
+    let target = Media::get(&mut tx, into).await?;
     tx.commit().await?;
-    Ok(StatusCode::OK)
+
+    let event = Message {
+        id,
+        event_type: PushEventType::EventRemoveCard,
+    };
+
+    let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+
+    Ok(target)
 }
@@ -1,13 +1,36 @@
 use crate::core::DbConnection;
+use crate::core::EventTx;
 use crate::errors;
+use crate::subtitles;
+use crate::subtitles::SubtitleQuery;
 
 use auth::Wrapper as Auth;
+use database::chapter::Chapter;
+use database::library::Library;
+use database::library::MediaType;
+use database::media::Media;
 use database::mediafile::MediaFile;
+use database::mediafile::UpdateMediaFile;
 
+use events::Message;
+use events::PushEventType;
+
+use std::io::SeekFrom;
+use std::path::Path;
+
+use futures::stream;
 use serde_json::json;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use warp::http::response::Response;
 use warp::http::status::StatusCode;
+use warp::hyper::body::Body;
 use warp::reply;
 
+use serde::Deserialize;
+use tokio::task::spawn_blocking;
+
 pub mod filters {
     use warp::reject;
     use warp::Filter;
@@ -18,12 +41,14 @@ pub mod filters {
 
     use serde::Deserialize;
 
+    use crate::core::EventTx;
+
     pub fn get_mediafile_info(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / "mediafile" / i64)
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
                 super::get_mediafile_info(conn, id, auth)
@@ -32,6 +57,106 @@ pub mod filters {
             })
     }
 
+    pub fn stream_mediafile(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            token: Option<String>,
+        }
+
+        // requests made through a signed url dont carry a auth header.
+        let auth = auth::with_scope("read:media")
+            .map(Some)
+            .or(warp::any().map(|| None))
+            .unify();
+
+        warp::path!("api" / "v1" / "mediafile" / i64 / "stream")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth)
+            .and(with_state::<DbConnection>(conn))
+            .and(warp::header::optional::<String>("range"))
+            .and_then(
+                |id: i64,
+                 RouteArgs { token }: RouteArgs,
+                 auth: Option<Auth>,
+                 conn: DbConnection,
+                 range: Option<String>| async move {
+                    super::stream_mediafile(conn, id, auth, token, range)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_stream_url(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "mediafile" / i64 / "stream_url")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::get_stream_url(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_mediafile_chapters(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "mediafile" / i64 / "chapters")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
+                super::get_mediafile_chapters(conn, id, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn search_subtitles(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            lang: String,
+        }
+
+        warp::path!("api" / "v1" / "mediafile" / i64 / "search_subtitles")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, RouteArgs { lang }: RouteArgs, _auth: Auth, conn: DbConnection| async move {
+                    super::search_subtitles(conn, id, lang)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn download_subtitles(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "mediafile" / i64 / "download_subtitles")
+            .and(warp::post())
+            .and(warp::body::json::<super::DownloadSubtitles>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, body: super::DownloadSubtitles, auth: Auth, conn: DbConnection| async move {
+                    super::download_subtitles(conn, id, body, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn rematch_mediafile(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -43,7 +168,7 @@ pub mod filters {
 
         warp::path!("api" / "v1" / "mediafile" / i64 / "match")
             .and(warp::patch())
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and(with_state::<DbConnection>(conn))
             .and(warp::query::query::<RouteArgs>())
             .and_then(
@@ -55,7 +180,50 @@ pub mod filters {
                      tmdb_id,
                      media_type,
                  }: RouteArgs| async move {
-                    super::rematch_mediafile(conn, id, tmdb_id, media_type)
+                    let rematch = super::rematch_mediafile(conn, id, tmdb_id, media_type);
+                    crate::tasks::run(format!("Rematch mediafile {}", id), rematch)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_orphans(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path!("api" / "v1" / "mediafile" / "orphans")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|auth: Auth, conn: DbConnection| async move {
+                super::get_orphans(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn attach_mediafile(
+        conn: DbConnection,
+        event_tx: EventTx,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            media_id: i64,
+        }
+
+        warp::path!("api" / "v1" / "mediafile" / i64 / "attach")
+            .and(warp::post())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<EventTx>(event_tx))
+            .and_then(
+                |id: i64,
+                 RouteArgs { media_id }: RouteArgs,
+                 auth: Auth,
+                 conn: DbConnection,
+                 event_tx: EventTx| async move {
+                    super::attach_mediafile(conn, event_tx, id, media_id, auth)
                         .await
                         .map_err(|e| reject::custom(e))
                 },
@@ -85,6 +253,417 @@ pub async fn get_mediafile_info(
     })))
 }
 
+/// Method mapped to `GET /api/v1/mediafile/<id>/chapters` returns the chapters embedded in the
+/// container of a mediafile ordered by when they start, used by players for chapter navigation.
+/// Timestamps are in seconds. Returns a empty list if the file has no chapters or was scanned
+/// before chapters were stored.
+///
+/// # Arguments
+/// * `id` - id of the mediafile
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "title": string | null,
+///         "start": float,
+///         "end": float,
+///     }
+/// ]
+/// ```
+pub async fn get_mediafile_chapters(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    MediaFile::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    Ok(reply::json(&Chapter::get_of_mediafile(&mut tx, id).await?))
+}
+
+/// Size of the chunks we read from disk when serving a mediafile directly.
+const STREAM_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Number of seconds signed stream urls stay valid for if nothing is configured.
+pub const DEFAULT_STREAM_URL_TTL: i64 = 6 * 60 * 60;
+
+/// Method mapped to `GET /api/v1/mediafile/<id>/stream_url` mints a signed url which can be used
+/// to stream a mediafile without sending the auth header, ie from a `<video>` tag or when
+/// casting. The url is only valid for this mediafile and expires after `stream_url_ttl` seconds.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want to stream
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "url": string,
+///     "expires_at": int,
+/// }
+/// ```
+pub async fn get_stream_url(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let mediafile = MediaFile::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if !Library::get_all(&mut tx)
+        .await
+        .iter()
+        .any(|x| x.id == mediafile.library_id)
+    {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    crate::routes::auth::check_playback_window(&mut tx, &user).await?;
+
+    let ttl = crate::get_global_settings().stream_url_ttl;
+    let expires_at = chrono::Utc::now().timestamp() + ttl;
+    let token = auth::stream_token_generate(id, user.0.claims.get_user(), ttl);
+
+    Ok(reply::json(&json!({
+        "url": format!("/api/v1/mediafile/{}/stream?token={}", id, token),
+        "expires_at": expires_at,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/mediafile/<id>/stream` serves the raw bytes of a mediafile,
+/// allowing clients to direct play files whose codecs they support without transcoding. Single
+/// byte ranges are honoured through the `Range` header, in which case a `206` is returned.
+///
+/// Requests without a auth header must carry a signed `token` minted by
+/// `GET /api/v1/mediafile/<id>/stream_url`. The token is verified without touching the database
+/// and a `403` is returned if it was tampered with, has expired or belongs to another mediafile.
+///
+/// Like every other way of playing media, a `403` is returned outside of the playback windows of
+/// the user. For signed urls these are the windows of the user that minted the url, which are
+/// checked whenever the url is used rather than only when it was minted.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want to stream
+/// * `user` - Auth middleware, `None` if no valid auth header was sent
+/// * `token` - signed token taken from the query string of a signed url
+/// * `range` - optional value of the `Range` header sent by the client
+pub async fn stream_mediafile(
+    conn: DbConnection,
+    id: i64,
+    user: Option<Auth>,
+    token: Option<String>,
+    range: Option<String>,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let token = match user {
+        Some(_) => None,
+        None => {
+            let token = token.ok_or(errors::DimError::Unauthenticated)?;
+            Some(
+                auth::stream_token_check(&token, id)
+                    .map_err(|_| errors::DimError::InvalidStreamToken)?,
+            )
+        }
+    };
+
+    let mut tx = conn.read().begin().await?;
+    let mediafile = MediaFile::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    // Files belonging to libraries that are hidden (pending deletion) must not be accessible.
+    if !Library::get_all(&mut tx)
+        .await
+        .iter()
+        .any(|x| x.id == mediafile.library_id)
+    {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    // signed urls outlive the playback window they were minted in, thus we check the window of
+    // their user on every request.
+    if let Some(user) = user.as_ref() {
+        crate::routes::auth::check_playback_window(&mut tx, user).await?;
+    }
+
+    if let Some(token) = token {
+        crate::routes::auth::check_playback_window_for(&mut tx, &token.user).await?;
+    }
+
+    let mut file = File::open(&mediafile.target_file)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let file_len = file
+        .metadata()
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?
+        .len();
+
+    let content_type = mime_for_file(&mediafile.target_file);
+    let partial = range.is_some();
+
+    let (start, end) = match range {
+        Some(range) => match parse_range(&range, file_len) {
+            Some(x) => x,
+            None => {
+                return Ok(Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header("Accept-Ranges", "bytes")
+                    .header("Content-Range", format!("bytes */{}", file_len))
+                    .body(Body::empty())
+                    .unwrap());
+            }
+        },
+        None => (0, file_len.saturating_sub(1)),
+    };
+
+    let len = if file_len == 0 { 0 } else { end - start + 1 };
+
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    let body = stream::unfold((file, len), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return None;
+        }
+
+        let mut buf = vec![0; remaining.min(STREAM_CHUNK_SIZE) as usize];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(bytes::Bytes::from(buf)), (file, remaining - n as u64)))
+            }
+            Err(e) => Some((Err(e), (file, 0))),
+        }
+    });
+
+    let mut response = Response::builder()
+        .header("Content-Type", content_type)
+        .header("Accept-Ranges", "bytes")
+        .header("Content-Length", len);
+
+    response = if partial {
+        response.status(StatusCode::PARTIAL_CONTENT).header(
+            "Content-Range",
+            format!("bytes {}-{}/{}", start, end, file_len),
+        )
+    } else {
+        response.status(StatusCode::OK)
+    };
+
+    Ok(response.body(Body::wrap_stream(body)).unwrap())
+}
+
+/// Parses the value of a `Range` header into an inclusive `(start, end)` byte range. Only single
+/// ranges are supported, multipart ranges and ranges outside of the file yield `None`.
+fn parse_range(header: &str, file_len: u64) -> Option<(u64, u64)> {
+    let range = header.trim().strip_prefix("bytes=")?;
+
+    if range.contains(',') || file_len == 0 {
+        return None;
+    }
+
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let (start, end) = if start.is_empty() {
+        // suffix range, ie the last `n` bytes of the file.
+        let suffix = end.parse::<u64>().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+
+        (file_len.saturating_sub(suffix), file_len - 1)
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() {
+            file_len - 1
+        } else {
+            end.parse::<u64>().ok()?.min(file_len - 1)
+        };
+
+        (start, end)
+    };
+
+    if start > end || start >= file_len {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+/// Returns the mime type of a media file based on its extension.
+fn mime_for_file(path: &str) -> &'static str {
+    let ext = Path::new(path)
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "avi" => "video/x-msvideo",
+        "mov" => "video/quicktime",
+        "ts" => "video/mp2t",
+        "ogv" => "video/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Method mapped to `GET /api/v1/mediafile/<id>/search_subtitles` searches the configured
+/// subtitle provider for subtitles of a mediafile. The search uses the hash of the file along
+/// with the title and year of its media, results matched by the hash are returned first. Returns
+/// `422` if no subtitle provider is configured.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want subtitles for
+/// * `lang` - ISO 639-1 code of the language of the subtitles, ie `?lang=en`
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": string,
+///         "provider": string,
+///         "language": string,
+///         "release": string | null,
+///         "downloads": int,
+///         "hash_match": bool,
+///     }
+/// ]
+/// ```
+pub async fn search_subtitles(
+    conn: DbConnection,
+    id: i64,
+    lang: String,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let provider = subtitles::get_provider().ok_or(errors::DimError::NoSubtitleProvider)?;
+
+    let (mediafile, media) = {
+        let mut tx = conn.read().begin().await?;
+        let mediafile = MediaFile::get_one(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?;
+
+        let media = match mediafile.media_id {
+            Some(media_id) => Media::get(&mut tx, media_id).await.ok(),
+            None => None,
+        };
+
+        (mediafile, media)
+    };
+
+    let target_file = mediafile.target_file.clone();
+    let hash = spawn_blocking(move || subtitles::opensubtitles_hash(target_file))
+        .await
+        .ok()
+        .and_then(Result::ok);
+
+    // episodes are searched by the name of their show which we only have in the parsed filename.
+    let query = match media.filter(|x| x.media_type == MediaType::Movie) {
+        Some(media) => SubtitleQuery {
+            title: media.name,
+            year: media.year.or(mediafile.raw_year),
+            language: lang,
+            hash,
+            ..Default::default()
+        },
+        None => SubtitleQuery {
+            title: mediafile.raw_name,
+            year: mediafile.raw_year,
+            season: mediafile.season,
+            episode: mediafile.episode,
+            language: lang,
+            hash,
+        },
+    };
+
+    Ok(reply::json(&provider.search(&query).await?))
+}
+
+#[derive(Deserialize)]
+pub struct DownloadSubtitles {
+    /// Id of the subtitle as returned by `search_subtitles`.
+    pub id: String,
+    pub lang: String,
+}
+
+/// Method mapped to `POST /api/v1/mediafile/<id>/download_subtitles` downloads a subtitle
+/// returned by `GET /api/v1/mediafile/<id>/search_subtitles` and stores it as a sidecar file next
+/// to the mediafile, ie `movie.en.srt`. Existing sidecar files are never overwritten. Only the
+/// owner can download subtitles.
+///
+/// # Arguments
+/// * `id` - id of the mediafile we want subtitles for
+/// * `body` - id and language of the subtitle to download
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "path": string,
+/// }
+/// ```
+pub async fn download_subtitles(
+    conn: DbConnection,
+    id: i64,
+    body: DownloadSubtitles,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let provider = subtitles::get_provider().ok_or(errors::DimError::NoSubtitleProvider)?;
+
+    let mediafile = {
+        let mut tx = conn.read().begin().await?;
+        MediaFile::get_one(&mut tx, id)
+            .await
+            .map_err(|_| errors::DimError::NotFoundError)?
+    };
+
+    let lang = body
+        .lang
+        .chars()
+        .filter(|x| x.is_ascii_alphanumeric() || *x == '-')
+        .collect::<String>()
+        .to_lowercase();
+
+    if lang.is_empty() {
+        return Err(errors::DimError::MissingFieldInBody {
+            description: "lang".into(),
+        });
+    }
+
+    let content = provider.download(&body.id).await?;
+
+    let target_file = Path::new(&mediafile.target_file);
+    let stem = target_file
+        .file_stem()
+        .ok_or(errors::DimError::NotFoundError)?
+        .to_string_lossy();
+
+    let mut path = target_file.with_file_name(format!("{}.{}.srt", stem, lang));
+    let mut suffix = 1;
+    while path.exists() {
+        path = target_file.with_file_name(format!("{}.{}.{}.srt", stem, lang, suffix));
+        suffix += 1;
+    }
+
+    tokio::fs::write(&path, content).await?;
+
+    Ok(reply::json(&json!({
+        "path": path.to_string_lossy(),
+    })))
+}
+
 /// Method mapped to `PATCH /api/v1/mediafile/<id>/match` used to match a unmatched(orphan)
 /// mediafile to a tmdb id.
 ///
@@ -102,33 +681,197 @@ pub async fn rematch_mediafile(
     tmdb_id: i32,
     media_type: String,
 ) -> Result<impl warp::Reply, errors::DimError> {
-    use crate::scanners::tmdb::Tmdb;
+    use database::library::Library;
     use database::library::MediaType;
 
     let mut tx = conn.read().begin().await?;
     let mediafile = MediaFile::get_one(&mut tx, id).await?;
     let matcher = crate::scanners::get_matcher_unchecked();
 
-    let mut tmdb = match media_type.to_lowercase().as_ref() {
-        "movie" => Tmdb::new("38c372f5bc572c8aadde7a802638534e".into(), MediaType::Movie),
-        "tv" => Tmdb::new("38c372f5bc572c8aadde7a802638534e".into(), MediaType::Tv),
+    let media_type = match media_type.to_lowercase().as_ref() {
+        "movie" => MediaType::Movie,
+        "tv" => MediaType::Tv,
         _ => return Err(errors::DimError::InvalidMediaType),
     };
 
-    let result = tmdb
-        .search_by_id(tmdb_id)
-        .await
-        .map_err(|_| errors::DimError::NotFoundError)?;
+    let agent = Library::get_agent(&mut tx, mediafile.library_id).await?;
+    let mut tmdb = crate::scanners::metadata_agent(agent, media_type);
 
-    match media_type.to_lowercase().as_ref() {
-        "movie" => {
+    let result = tmdb.search_by_id(tmdb_id).await?;
+
+    match media_type {
+        MediaType::Movie => {
             matcher
                 .match_movie_to_result(mediafile, result.into())
                 .await?
         }
-        "tv" => matcher.match_tv_to_result(mediafile, result.into()).await?,
-        _ => unreachable!(),
+        MediaType::Tv => matcher.match_tv_to_result(mediafile, result.into()).await?,
+        MediaType::Episode => unreachable!(),
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Method mapped to `GET /api/v1/mediafile/orphans` returns all mediafiles that were scanned but
+/// couldnt be matched to a media. Only the owner can access this route.
+///
+/// # Return Schema
+/// ```text
+/// [{
+///     "id": int,
+///     "library_id": int,
+///     "target_file": string,
+///     "raw_name": string,
+///     "raw_year": int | null,
+///     "season": int | null,
+///     "episode": int | null,
+///     "duration": int | null,
+/// }]
+/// ```
+pub async fn get_orphans(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let orphans = MediaFile::get_orphans(&mut tx).await?;
+
+    Ok(reply::json(
+        &orphans
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "id": x.id,
+                    "library_id": x.library_id,
+                    "target_file": x.target_file,
+                    "raw_name": x.raw_name,
+                    "raw_year": x.raw_year,
+                    "season": x.season,
+                    "episode": x.episode,
+                    "duration": x.duration,
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Method mapped to `POST /api/v1/mediafile/<id>/attach?<media_id>` manually attaches a orphan
+/// mediafile to a existing media as a new version. The media must belong to the library of the
+/// mediafile, thus files of movie libraries can only be attached to movies and files of tv
+/// libraries only to episodes. Only the owner can access this route.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `event_tx` - websocket channel over which we dispatch a event notifying other clients
+/// * `id` - id of the orphan mediafile
+/// * `media_id` - id of the media to attach the mediafile to
+/// * `user` - Auth middleware
+pub async fn attach_mediafile(
+    conn: DbConnection,
+    event_tx: EventTx,
+    id: i64,
+    media_id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    let mediafile = MediaFile::get_one(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if mediafile.media_id.is_some() {
+        return Err(errors::DimError::MediaFileNotOrphan);
+    }
+
+    let media = Media::get(&mut tx, media_id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let library = Library::get_one(&mut tx, mediafile.library_id)
+        .await
+        .map_err(|_| errors::DimError::LibraryNotFound)?;
+
+    if media.library_id != library.id {
+        return Err(errors::DimError::MediaFileLibraryMismatch);
+    }
+
+    let compatible = match library.media_type {
+        MediaType::Movie => media.media_type == MediaType::Movie,
+        MediaType::Tv | MediaType::Episode => media.media_type == MediaType::Episode,
+    };
+
+    if !compatible {
+        return Err(errors::DimError::LibraryTypeMismatch);
     }
 
+    // the cached duration of the media is updated by a trigger once the media_id is set.
+    UpdateMediaFile {
+        media_id: Some(media_id),
+        ..Default::default()
+    }
+    .update(&mut tx, id)
+    .await?;
+
+    tx.commit().await?;
+
+    let event = Message {
+        id,
+        event_type: PushEventType::EventMediaFileAttached { media_id },
+    };
+
+    let _ = event_tx.send(serde_json::to_string(&event).unwrap());
+
     Ok(StatusCode::OK)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        // the end is clamped to the last byte of the file.
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn test_parse_range_open_ended() {
+        assert_eq!(parse_range("bytes=100-", 1000), Some((100, 999)));
+        assert_eq!(parse_range("bytes=999-", 1000), Some((999, 999)));
+    }
+
+    #[test]
+    fn test_parse_range_suffix() {
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        // suffixes longer than the file return the whole file.
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+    }
+
+    #[test]
+    fn test_parse_range_invalid() {
+        assert_eq!(parse_range("bytes=100-50", 1000), None);
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=0-10,20-30", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("items=0-10", 1000), None);
+        assert_eq!(parse_range("bytes=0-10", 0), None);
+    }
+
+    #[test]
+    fn test_mime_for_file() {
+        assert_eq!(mime_for_file("/media/movie.mp4"), "video/mp4");
+        assert_eq!(mime_for_file("/media/movie.MKV"), "video/x-matroska");
+        assert_eq!(mime_for_file("/media/movie.webm"), "video/webm");
+        assert_eq!(mime_for_file("/media/a.iso"), "application/octet-stream");
+        assert_eq!(mime_for_file("/media/movie"), "application/octet-stream");
+    }
+}
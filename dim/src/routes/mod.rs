@@ -1,14 +1,23 @@
 pub mod auth;
+pub mod catchers;
+pub mod collection;
 pub mod dashboard;
 pub mod general;
+pub mod keyword;
 pub mod library;
 pub mod media;
 pub mod mediafile;
+pub mod pagination;
+pub mod person;
+pub mod playlist;
 pub mod rematch_media;
 pub mod settings;
 pub mod statik;
 pub mod stream;
+pub mod tag;
 pub mod tv;
+pub mod watch_party;
+pub mod webhook;
 
 pub mod global_filters {
     use crate::errors;
@@ -36,6 +45,11 @@ pub mod global_filters {
     ) -> Result<impl warp::Reply, warp::reject::Rejection> {
         if let Some(e) = err.find::<errors::DimError>() {
             return Ok(e.clone().into_response());
+        } else if let Some(auth::JWTError::MissingScope(scope)) = err.find::<auth::JWTError>() {
+            return Ok(errors::DimError::MissingScope {
+                scope: scope.to_string(),
+            }
+            .into_response());
         } else if err.find::<auth::JWTError>().is_some() {
             return Ok(errors::DimError::Unauthenticated.into_response());
         } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
@@ -43,15 +57,14 @@ pub mod global_filters {
                 description: e.source().unwrap().to_string(),
             }
             .into_response());
+        } else if !super::catchers::is_builtin_rejection(&err) {
+            // rejections nothing knows how to answer are bugs, thus they are answered with a 500
+            // instead of the plain text response of warp.
+            return Ok(super::catchers::catch(
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ));
         }
 
         Err(err)
     }
-
-    pub fn api_not_found(
-    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        warp::path!("api" / ..)
-            .and(warp::any())
-            .map(|| crate::errors::DimError::NotFoundError)
-    }
 }
@@ -0,0 +1,128 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use warp::reply;
+
+/// Number of items returned per page when neither the client nor the server config set a page
+/// size.
+pub const DEFAULT_PER_PAGE: i64 = 50;
+/// Upper bound on the page size, this applies to both the client and the server config.
+pub const MAX_PER_PAGE: i64 = 500;
+
+fn default_page() -> i64 {
+    1
+}
+
+/// Query arguments shared by all paginated list endpoints.
+///
+/// The page size is picked in the following order:
+/// 1. `per_page` supplied by the client.
+/// 2. `default_page_size` from the server config.
+///
+/// Either way the page size is clamped between 1 and [`MAX_PER_PAGE`](MAX_PER_PAGE), requests
+/// with an out of range page size thus never fail.
+///
+/// # Query params
+/// * `page` - 1-based index of the page to return
+/// * `per_page` - number of items per page
+/// * `flat` - return the bare list of items instead of the [`Paginated`](Paginated) envelope.
+/// This only exists for backwards compatibility and will be removed once clients have migrated.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct PageArgs {
+    #[serde(default = "default_page")]
+    pub page: i64,
+    #[serde(default)]
+    pub per_page: Option<i64>,
+    #[serde(default)]
+    pub flat: bool,
+}
+
+impl PageArgs {
+    /// Returns the clamped page number.
+    pub fn page(&self) -> i64 {
+        self.page.max(1)
+    }
+
+    /// Returns the clamped page size, this should be used as the `LIMIT` of a query.
+    pub fn limit(&self) -> i64 {
+        self.per_page
+            .unwrap_or_else(|| crate::get_global_settings().default_page_size)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// Returns the number of items to skip, this should be used as the `OFFSET` of a query.
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1) * self.limit()
+    }
+}
+
+/// Envelope returned by list endpoints.
+#[derive(Clone, Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+impl<T: Serialize> Paginated<T> {
+    /// Builds a page out of the items returned by a query limited with
+    /// [`PageArgs::limit`](PageArgs::limit) and [`PageArgs::offset`](PageArgs::offset) and the
+    /// total count of rows matching the same query.
+    pub fn new(items: Vec<T>, total: i64, args: &PageArgs) -> Self {
+        Self {
+            items,
+            page: args.page(),
+            per_page: args.limit(),
+            total,
+        }
+    }
+
+    /// Builds a page out of a list that hasnt been paginated by the database.
+    pub fn from_vec(items: Vec<T>, args: &PageArgs) -> Self {
+        let total = items.len() as i64;
+        let items = items
+            .into_iter()
+            .skip(args.offset() as usize)
+            .take(args.limit() as usize)
+            .collect();
+
+        Self::new(items, total, args)
+    }
+
+    /// Serializes the page, or only its items if the client requested a flat response.
+    pub fn into_reply(self, flat: bool) -> reply::Json {
+        if flat {
+            reply::json(&self.items)
+        } else {
+            reply::json(&self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_page_is_clamped() {
+        let args = PageArgs {
+            page: 0,
+            per_page: Some(MAX_PER_PAGE + 1),
+            flat: false,
+        };
+
+        assert_eq!(args.page(), 1);
+        assert_eq!(args.limit(), MAX_PER_PAGE);
+        assert_eq!(args.offset(), 0);
+
+        let args = PageArgs {
+            page: 3,
+            per_page: Some(-5),
+            flat: false,
+        };
+
+        assert_eq!(args.limit(), 1);
+        assert_eq!(args.offset(), 2);
+    }
+}
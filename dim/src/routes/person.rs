@@ -0,0 +1,98 @@
+use crate::core::DbConnection;
+use crate::errors;
+
+use database::person::Person;
+
+use serde_json::json;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn get_person(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "person" / i64)
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, _user: Auth, conn: DbConnection| async move {
+                super::get_person(conn, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+}
+
+/// Method mapped to `GET /api/v1/person/<id>` returns a person, ie a actor or director, along
+/// with the media in the library they appear in or worked on, grouped by role. Media of libraries
+/// that are being deleted are left out.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the person, as returned with the cast of a media
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "profile_path": string | null,
+///     "credits": [{
+///         "role": string,
+///         "media": [{
+///             "id": int,
+///             "name": string,
+///             "media_type": string,
+///             "year": int | null,
+///             "poster_path": string | null,
+///             "character": string | null,
+///         }],
+///     }],
+/// }
+/// ```
+///
+/// `role` is `Actor` for the cast, otherwise the job of the person, ie `Director`. The cast comes
+/// first, the other roles are sorted by name. Media are ordered by release year, newest first.
+/// `character` is only set for the cast.
+pub async fn get_person(conn: DbConnection, id: i64) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let person = Person::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    // credits come sorted by role thus grouping consecutive ones is enough.
+    let mut credits: Vec<(String, Vec<serde_json::Value>)> = Vec::new();
+
+    for credit in Person::get_credits(&mut tx, id).await? {
+        let media = json!({
+            "id": credit.media_id,
+            "name": credit.name,
+            "media_type": credit.media_type,
+            "year": credit.year,
+            "poster_path": credit.poster_path,
+            "character": credit.character,
+        });
+
+        match credits.last_mut() {
+            Some((role, media_list)) if *role == credit.role => media_list.push(media),
+            _ => credits.push((credit.role, vec![media])),
+        }
+    }
+
+    Ok(reply::json(&json!({
+        "id": person.id,
+        "name": person.name,
+        "profile_path": person.profile_path,
+        "credits": credits
+            .into_iter()
+            .map(|(role, media)| json!({ "role": role, "media": media }))
+            .collect::<Vec<_>>(),
+    })))
+}
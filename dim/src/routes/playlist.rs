@@ -0,0 +1,410 @@
+use crate::core::DbConnection;
+use crate::errors;
+use crate::progress_buffer;
+
+use auth::Wrapper as Auth;
+
+use database::library::MediaType;
+use database::media::Media;
+use database::playlist::InsertablePlaylist;
+use database::playlist::Playlist;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use warp::http::status::StatusCode;
+use warp::reply;
+
+/// Max length of playlist names in characters.
+pub const MAX_PLAYLIST_NAME_LENGTH: usize = 128;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use super::ItemArgs;
+    use super::MoveArgs;
+    use super::PlaylistArgs;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn get_playlists(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|user: Auth, conn: DbConnection| async move {
+                super::get_playlists(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn create_playlist(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist")
+            .and(warp::post())
+            .and(warp::body::json::<PlaylistArgs>())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |data: PlaylistArgs, user: Auth, conn: DbConnection| async move {
+                    super::create_playlist(conn, user, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_playlist(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist" / i64)
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::get_playlist(conn, user, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn delete_playlist(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist" / i64)
+            .and(warp::delete())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::delete_playlist(conn, user, id)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn add_playlist_item(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist" / i64 / "items")
+            .and(warp::post())
+            .and(warp::body::json::<ItemArgs>())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, data: ItemArgs, user: Auth, conn: DbConnection| async move {
+                    super::add_playlist_item(conn, user, id, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn remove_playlist_item(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist" / i64 / "items" / i64)
+            .and(warp::delete())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, item_id: i64, user: Auth, conn: DbConnection| async move {
+                    super::remove_playlist_item(conn, user, id, item_id)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn move_playlist_item(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "playlist" / i64 / "items" / i64)
+            .and(warp::patch())
+            .and(warp::body::json::<MoveArgs>())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, item_id: i64, data: MoveArgs, user: Auth, conn: DbConnection| async move {
+                    super::move_playlist_item(conn, user, id, item_id, data)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PlaylistArgs {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ItemArgs {
+    pub media_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct MoveArgs {
+    pub position: usize,
+}
+
+/// Method mapped to `GET /api/v1/playlist` returns the playlists of the user sorted by name.
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": int,
+///         "name": string,
+///         "created_at": int,
+///         "item_count": int,
+///     }
+/// ]
+/// ```
+pub async fn get_playlists(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+
+    Ok(reply::json(
+        &Playlist::get_of_user(&mut tx, user.0.claims.get_user_ref()).await?,
+    ))
+}
+
+/// Method mapped to `POST /api/v1/playlist` creates a empty playlist owned by the user.
+///
+/// # Data
+/// ```text
+/// {
+///     "name": string,
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+/// }
+/// ```
+pub async fn create_playlist(
+    conn: DbConnection,
+    user: Auth,
+    data: PlaylistArgs,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let name = data.name.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_PLAYLIST_NAME_LENGTH {
+        return Err(errors::DimError::InvalidPlaylistName {
+            max: MAX_PLAYLIST_NAME_LENGTH,
+        });
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let id = InsertablePlaylist { name: name.into() }
+        .insert(&mut tx, user.0.claims.get_user_ref())
+        .await?;
+    tx.commit().await?;
+
+    Ok(reply::with_status(
+        reply::json(&json!({ "id": id })),
+        StatusCode::CREATED,
+    ))
+}
+
+/// Method mapped to `GET /api/v1/playlist/<id>` returns a playlist of the user along with its
+/// items in order. Items whose media are in hidden libraries are left out. `progress` is the
+/// offset in seconds the user has watched the media up to.
+///
+/// # Arguments
+/// * `id` - id of the playlist
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "created_at": int,
+///     "item_count": int,
+///     "items": [
+///         {
+///             "item_id": int,
+///             "position": int,
+///             "media_id": int,
+///             "name": string,
+///             "media_type": "movie" | "episode",
+///             "poster_path": string | null,
+///             "backdrop_path": string | null,
+///             "progress": int,
+///             "duration": int,
+///         }
+///     ],
+/// }
+/// ```
+pub async fn get_playlist(
+    conn: DbConnection,
+    user: Auth,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let uid = user.0.claims.get_user_ref();
+    progress_buffer::flush_user(&conn, uid).await?;
+
+    let mut tx = conn.read().begin().await?;
+    let playlist = Playlist::get(&mut tx, id, uid)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let items = Playlist::get_items(&mut tx, id, uid).await?;
+
+    Ok(reply::json(&json!({
+        "id": playlist.id,
+        "name": playlist.name,
+        "created_at": playlist.created_at,
+        "item_count": playlist.item_count,
+        "items": items,
+    })))
+}
+
+/// Method mapped to `DELETE /api/v1/playlist/<id>` deletes a playlist of the user.
+///
+/// # Arguments
+/// * `id` - id of the playlist
+pub async fn delete_playlist(
+    conn: DbConnection,
+    user: Auth,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    if Playlist::delete(&mut tx, id, user.0.claims.get_user_ref()).await? == 0 {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `POST /api/v1/playlist/<id>/items` appends a movie or episode to the end of a
+/// playlist of the user. The same media can be added more than once.
+///
+/// # Arguments
+/// * `id` - id of the playlist
+///
+/// # Data
+/// ```text
+/// {
+///     "media_id": int,
+/// }
+/// ```
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "item_id": int,
+/// }
+/// ```
+pub async fn add_playlist_item(
+    conn: DbConnection,
+    user: Auth,
+    id: i64,
+    data: ItemArgs,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    Playlist::get(&mut tx, id, user.0.claims.get_user_ref())
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    // media in hidden libraries are not returned here.
+    let media = Media::get_many(&mut tx, &[data.media_id])
+        .await?
+        .pop()
+        .ok_or(errors::DimError::NotFoundError)?;
+
+    if !matches!(media.media_type, MediaType::Movie | MediaType::Episode) {
+        return Err(errors::DimError::InvalidPlaylistMedia);
+    }
+
+    let item_id = Playlist::add_item(&mut tx, id, media.id).await?;
+    tx.commit().await?;
+
+    Ok(reply::with_status(
+        reply::json(&json!({ "item_id": item_id })),
+        StatusCode::CREATED,
+    ))
+}
+
+/// Method mapped to `DELETE /api/v1/playlist/<id>/items/<item_id>` removes a item from a playlist
+/// of the user, the items after it move up.
+///
+/// # Arguments
+/// * `id` - id of the playlist
+/// * `item_id` - id of the item
+pub async fn remove_playlist_item(
+    conn: DbConnection,
+    user: Auth,
+    id: i64,
+    item_id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    Playlist::get(&mut tx, id, user.0.claims.get_user_ref())
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if Playlist::remove_item(&mut tx, id, item_id).await? == 0 {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Method mapped to `PATCH /api/v1/playlist/<id>/items/<item_id>` moves a item of a playlist of
+/// the user to a new position, the items in between shift to make room. Positions past the end
+/// move the item to the end.
+///
+/// # Arguments
+/// * `id` - id of the playlist
+/// * `item_id` - id of the item
+///
+/// # Data
+/// ```text
+/// {
+///     "position": int,
+/// }
+/// ```
+pub async fn move_playlist_item(
+    conn: DbConnection,
+    user: Auth,
+    id: i64,
+    item_id: i64,
+    data: MoveArgs,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+
+    Playlist::get(&mut tx, id, user.0.claims.get_user_ref())
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if !Playlist::move_item(&mut tx, id, item_id, data.position).await? {
+        return Err(errors::DimError::NotFoundError);
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
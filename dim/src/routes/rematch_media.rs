@@ -2,19 +2,19 @@ use crate::core::DbConnection;
 use crate::core::EventTx;
 use crate::errors::*;
 use crate::scanners::base::patch_tv_metadata;
+use crate::scanners::MediaLock;
 use crate::scanners::tmdb::MediaType as ExternalMediaType;
-use crate::scanners::tmdb::Tmdb;
 use crate::scanners::movie::MovieMatcher;
 use crate::scanners::tv_show::TvShowMatcher;
 
+use database::library::Library;
 use database::library::MediaType;
+use database::match_candidate::MatchCandidate;
 use database::media::Media;
 use database::mediafile::MediaFile;
 
 use http::status::StatusCode;
 
-const API_KEY: &str = "38c372f5bc572c8aadde7a802638534e";
-
 pub mod filters {
     use crate::core::EventTx;
     use crate::routes::global_filters::with_state;
@@ -40,7 +40,7 @@ pub mod filters {
             .and(warp::query::query::<RouteArgs>())
             .and(with_state(conn))
             .and(with_state(event_tx))
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and_then(
                 |id,
                  RouteArgs {
@@ -50,7 +50,8 @@ pub mod filters {
                  conn: DbConnection,
                  event_tx: EventTx,
                  _: Auth| async move {
-                    super::rematch_media(conn, event_tx, id, external_id, media_type)
+                    let rematch = super::rematch_media(conn, event_tx, id, external_id, media_type);
+                    crate::tasks::run(format!("Rematch media {}", id), rematch)
                         .await
                         .map_err(|e| reject::custom(e))
                 },
@@ -65,6 +66,8 @@ pub async fn rematch_media(
     external_id: i32,
     media_type: String,
 ) -> Result<impl warp::Reply, DimError> {
+    let _lock = MediaLock::try_acquire(id).ok_or(DimError::MediaLocked)?;
+
     // first fetch the data from tmdb
     let target_type = match media_type.to_lowercase().as_ref() {
         "movie" => ExternalMediaType::Movie,
@@ -72,12 +75,20 @@ pub async fn rematch_media(
         _ => return Err(DimError::InvalidMediaType),
     };
 
-    let mut tmdb = Tmdb::new(API_KEY.into(), target_type);
-    let mut result: crate::scanners::ApiMedia = tmdb
-        .search_by_id(external_id)
+    // the new match is fetched from the metadata agent of the library of the media.
+    let agent = {
+        let mut tx = conn.read().begin().await?;
+        let library_id = Media::get(&mut tx, id).await?.library_id;
+        Library::get_agent(&mut tx, library_id).await?
+    };
+
+    let mut tmdb = crate::scanners::metadata_agent(agent, target_type);
+    let mut result: crate::scanners::ApiMedia = tmdb.search_by_id(external_id).await?.into();
+    result.external_ids = tmdb
+        .get_external_ids_for(result.id)
         .await
-        .map_err(|_| DimError::NotFoundError)?
-        .into();
+        .ok()
+        .map(Into::into);
 
     if let ExternalMediaType::Tv = target_type {
         let mut seasons: Vec<crate::scanners::ApiSeason> = tmdb
@@ -107,6 +118,9 @@ pub async fn rematch_media(
 
     let target = Media::get(&mut tx, id).await?;
 
+    // fields the user edited manually must survive the rematch.
+    let overrides = Media::get_overridden_values(&mut tx, id).await?;
+
     use database::episode::Episode;
 
     let orphans = match target.media_type {
@@ -123,6 +137,8 @@ pub async fn rematch_media(
     };
 
     Media::delete(&mut tx, id).await?;
+    // picking a match resolves the review of the media.
+    MatchCandidate::clear_for_media(&mut tx, id).await?;
 
     for orphan in orphans {
         let mut orphan = MediaFile::get_one(&mut tx, orphan).await?;
@@ -153,7 +169,13 @@ pub async fn rematch_media(
         }
     }
 
+    if Media::get(&mut tx, id).await.is_ok() {
+        overrides.update_manual(&mut tx, id).await?;
+    }
+
     tx.commit().await?;
 
+    crate::accent_color::queue(conn.clone(), id);
+
     Ok(StatusCode::OK)
 }
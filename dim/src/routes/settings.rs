@@ -1,7 +1,11 @@
 use crate::core::DbConnection;
+use crate::duration::DurationStyle;
 use crate::errors;
+use crate::subtitles::SubtitleProviderConfig;
 use crate::utils::ffpath;
 
+use database::media::PosterSource;
+use database::user::PlaybackPreferences;
 use database::user::UpdateableUser;
 use database::user::User;
 use database::user::UserSettings;
@@ -10,6 +14,7 @@ use auth::Wrapper as Auth;
 use serde::Deserialize;
 use serde::Serialize;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::fs::OpenOptions;
@@ -38,6 +43,121 @@ pub struct GlobalSettings {
     pub verbose: bool,
     pub secret_key: Option<[u8; 16]>,
     pub enable_hwaccel: bool,
+
+    /// Whether specials (season 0) should be counted when reporting the number of seasons of a
+    /// show.
+    #[serde(default)]
+    pub include_specials_in_counts: bool,
+
+    /// Connect and read timeout in seconds for requests made to TMDB.
+    #[serde(default = "default_tmdb_timeout_secs")]
+    pub tmdb_timeout_secs: u64,
+
+    /// Maximum number of background tasks (scans, rematches) that can run at the same time.
+    #[serde(default = "default_max_concurrent_tasks")]
+    pub max_concurrent_tasks: usize,
+
+    /// Seconds running background tasks are given to finish on shutdown before being aborted.
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Number of items returned by paginated endpoints if the client doesnt request a page size.
+    #[serde(default = "default_page_size")]
+    pub default_page_size: i64,
+
+    /// Sources posters are resolved from in order of preference, used when TMDB has no poster for
+    /// a media.
+    #[serde(default = "default_poster_fallback")]
+    pub poster_fallback: Vec<PosterSource>,
+
+    /// Whether JSON responses are compressed if the client supports it.
+    #[serde(default = "default_true")]
+    pub compress_responses: bool,
+
+    /// JSON responses smaller than this many bytes are never compressed.
+    #[serde(default = "default_compression_min_size")]
+    pub compression_min_size: usize,
+
+    /// Provider used to search for subtitles online, disabled if not set.
+    #[serde(default)]
+    pub subtitle_provider: Option<SubtitleProviderConfig>,
+
+    /// Number of seconds signed stream urls stay valid for.
+    #[serde(default = "default_stream_url_ttl")]
+    pub stream_url_ttl: i64,
+
+    /// Style in which `duration_pretty` is formatted for media of a library, keyed by the id of
+    /// the library. Libraries not listed use the default for their media type.
+    #[serde(default)]
+    pub duration_styles: HashMap<i64, DurationStyle>,
+
+    /// Number of seconds playback progress is buffered in memory for before it is written to the
+    /// database. `0` writes progress as soon as it is reported.
+    #[serde(default)]
+    pub progress_flush_interval_secs: u64,
+
+    /// Directories libraries must be located in, libraries can be located anywhere if empty.
+    #[serde(default)]
+    pub library_roots: Vec<String>,
+
+    /// Widths in pixels posters are offered in besides their original size.
+    #[serde(default = "default_poster_sizes")]
+    pub poster_sizes: Vec<u32>,
+
+    /// Widths in pixels backdrops are offered in besides their original size.
+    #[serde(default = "default_backdrop_sizes")]
+    pub backdrop_sizes: Vec<u32>,
+
+    /// Hosts users may send scrobbles to, see [`scrobble`](crate::scrobble).
+    #[serde(default = "default_scrobble_hosts")]
+    pub scrobble_hosts: Vec<String>,
+}
+
+fn default_tmdb_timeout_secs() -> u64 {
+    crate::scanners::tmdb::DEFAULT_TIMEOUT.as_secs()
+}
+
+fn default_max_concurrent_tasks() -> usize {
+    crate::tasks::DEFAULT_MAX_CONCURRENT_TASKS
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    crate::tasks::DEFAULT_SHUTDOWN_TIMEOUT_SECS
+}
+
+fn default_page_size() -> i64 {
+    crate::routes::pagination::DEFAULT_PER_PAGE
+}
+
+fn default_poster_fallback() -> Vec<PosterSource> {
+    PosterSource::default_chain()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_compression_min_size() -> usize {
+    crate::compression::DEFAULT_MIN_SIZE
+}
+
+fn default_stream_url_ttl() -> i64 {
+    crate::routes::mediafile::DEFAULT_STREAM_URL_TTL
+}
+
+fn default_poster_sizes() -> Vec<u32> {
+    crate::image_variants::DEFAULT_POSTER_SIZES.to_vec()
+}
+
+fn default_backdrop_sizes() -> Vec<u32> {
+    crate::image_variants::DEFAULT_BACKDROP_SIZES.to_vec()
+}
+
+fn default_scrobble_hosts() -> Vec<String> {
+    crate::scrobble::DEFAULT_HOSTS
+        .iter()
+        .map(ToString::to_string)
+        .collect()
 }
 
 impl Default for GlobalSettings {
@@ -62,6 +182,22 @@ impl Default for GlobalSettings {
             verbose: false,
             secret_key: None,
             enable_hwaccel: true,
+            include_specials_in_counts: false,
+            tmdb_timeout_secs: default_tmdb_timeout_secs(),
+            max_concurrent_tasks: default_max_concurrent_tasks(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            default_page_size: default_page_size(),
+            poster_fallback: default_poster_fallback(),
+            compress_responses: true,
+            compression_min_size: default_compression_min_size(),
+            subtitle_provider: None,
+            stream_url_ttl: default_stream_url_ttl(),
+            duration_styles: HashMap::new(),
+            progress_flush_interval_secs: 0,
+            library_roots: vec![],
+            poster_sizes: default_poster_sizes(),
+            backdrop_sizes: default_backdrop_sizes(),
+            scrobble_hosts: default_scrobble_hosts(),
         }
     }
 }
@@ -116,6 +252,7 @@ pub fn set_global_settings(settings: GlobalSettings) -> Result<(), Box<dyn Error
 }
 
 pub mod filters {
+    use database::user::PlaybackPreferences;
     use database::user::UserSettings;
     use database::DbConnection;
 
@@ -159,6 +296,37 @@ pub mod filters {
             )
     }
 
+    pub fn get_user_preferences(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "preferences")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|auth: Auth, conn: DbConnection| async move {
+                super::get_user_preferences(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn put_user_preferences(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "user" / "preferences")
+            .and(warp::put())
+            .and(warp::body::json::<PlaybackPreferences>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |prefs: PlaybackPreferences, auth: Auth, conn: DbConnection| async move {
+                    super::put_user_preferences(conn, auth, prefs)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn get_global_settings(
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "host" / "settings")
@@ -216,9 +384,66 @@ pub async fn post_user_settings(
     Ok(reply::json(&new_settings))
 }
 
-// TODO: Hide secret key.
-pub async fn http_get_global_settings(_user: Auth) -> Result<impl warp::Reply, errors::DimError> {
-    Ok(reply::json(&get_global_settings()))
+/// Method mapped to `GET /api/v1/user/preferences` returns the playback preferences of the current
+/// user. The audio and subtitle preferences are used to pick the default tracks when streaming a
+/// file, `auto_mark_watched` decides whether finishing a media records a play in the watch history.
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "preferred_audio_lang": string | null,
+///     "preferred_subtitle_lang": string | null,
+///     "subtitles_enabled": bool,
+///     "auto_mark_watched": bool,
+/// }
+/// ```
+pub async fn get_user_preferences(
+    db: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = db.read().begin().await?;
+    let prefs = User::get(&mut tx, user.0.claims.get_user_ref())
+        .await?
+        .prefs;
+
+    Ok(reply::json(&prefs.playback_preferences()))
+}
+
+/// Method mapped to `PUT /api/v1/user/preferences` replaces the playback preferences of the
+/// current user. `auto_mark_watched` defaults to `true` when omitted. The remaining user settings
+/// are left untouched.
+pub async fn put_user_preferences(
+    db: DbConnection,
+    user: Auth,
+    new_prefs: PlaybackPreferences,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut lock = db.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock).await?;
+    let username = user.0.claims.get_user();
+
+    let mut prefs = User::get(&mut tx, &username).await?.prefs;
+    prefs.set_playback_preferences(new_prefs.clone());
+
+    UpdateableUser { prefs: Some(prefs) }
+        .update(&mut tx, &username)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(reply::json(&new_prefs))
+}
+
+/// Method mapped to `GET /api/v1/host/settings` returns the global settings. The secret key and
+/// the credentials of the subtitle provider are blanked out unless the user is the owner.
+pub async fn http_get_global_settings(user: Auth) -> Result<impl warp::Reply, errors::DimError> {
+    let mut settings = get_global_settings();
+
+    if !user.0.claims.has_role("owner") {
+        settings.secret_key = None;
+        settings.subtitle_provider = settings.subtitle_provider.map(|x| x.redacted());
+    }
+
+    Ok(reply::json(&settings))
 }
 
 // TODO: Disallow setting secret key over http.
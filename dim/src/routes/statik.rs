@@ -9,6 +9,7 @@ use std::path::PathBuf;
 
 use crate::errors;
 use crate::fetcher::insert_into_queue;
+use crate::image_variants;
 
 pub mod filters {
     use super::super::global_filters::with_state;
@@ -131,7 +132,7 @@ pub async fn react_routes() -> Result<impl warp::Reply, warp::Rejection> {
 
 pub async fn get_image(
     path: path::Tail,
-    _resize_w: Option<u32>,
+    resize_w: Option<u32>,
     _resize_h: Option<u32>,
     meta_path: String,
     conn: database::DbConnection,
@@ -142,27 +143,35 @@ pub async fn get_image(
     let mut url_path = PathBuf::from("images/");
     url_path.push(path.as_str());
 
-    /*
-    let image = if let (Some(w), Some(h)) = (resize_w, resize_h) {
-        spawn_blocking(move ||  { resize_image(file_path, w, h).ok() }).await.unwrap()
-    } else {
-        tokio::fs::read(file_path).await.ok()
-    };
-    */
-
     let mut tx = conn.read().begin().await?;
     if !Path::new(&file_path).exists() {
         if let Ok(x) = asset::Asset::get_url_by_file(&mut tx, &url_path).await {
             insert_into_queue(x, 5).await;
+        } else if let Some(x) = crate::posters::generate(&mut tx, path.as_str()).await {
+            // fallback posters are generated on first request.
+            file_path = x;
+        }
+    }
+
+    // images that cant be scaled down are served in their original size.
+    if let Some(width) = resize_w.filter(|x| image_variants::is_offered(*x)) {
+        if let Some(x) = image_variants::resized(&meta_path, path.as_str(), width).await {
+            file_path = x;
         }
     }
 
+    let content_type = match file_path.extension().and_then(|x| x.to_str()) {
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+
     let image = tokio::fs::read(file_path).await.ok();
 
     if let Some(data) = image {
         return warp::http::Response::builder()
             .status(StatusCode::OK)
-            .header("ContentType", "image/jpeg")
+            .header(warp::http::header::CONTENT_TYPE, content_type)
             .body(data)
             .map_err(|_| errors::DimError::NotFoundError);
     }
@@ -8,6 +8,7 @@ use crate::stream_tracking::StreamTracking;
 use crate::stream_tracking::VirtualManifest;
 use crate::streaming::ffprobe::FFPWrapper;
 use crate::streaming::ffprobe::FFProbeCtx;
+use crate::streaming::ffprobe::Stream;
 use crate::streaming::get_avc1_tag;
 use crate::streaming::get_qualities;
 use crate::streaming::level_to_tag;
@@ -15,6 +16,7 @@ use crate::utils::quality_to_label;
 
 use database::mediafile::MediaFile;
 use database::user::DefaultVideoQuality;
+use database::user::PlaybackPreferences;
 use database::user::User;
 use database::user::UserSettings;
 
@@ -69,7 +71,7 @@ pub mod filters {
         warp::path!("api" / "v1" / "stream" / i64 / "manifest")
             .and(warp::get())
             .and(warp::query::query::<QueryArgs>())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and(with_state::<StateManager>(state))
             .and(with_state::<StreamTracking>(stream_tracking))
@@ -105,7 +107,7 @@ pub mod filters {
         warp::path!("api" / "v1" / "stream" / String / "manifest.mpd")
             .and(warp::get())
             .and(warp::query::query::<QueryArgs>())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and(with_state::<StateManager>(state))
             .and(with_state::<StreamTracking>(stream_tracking))
@@ -273,8 +275,26 @@ pub mod filters {
     }
 }
 
+/// Function checks whether `user` is allowed to play media right now, see
+/// [`check_playback_window`](crate::routes::auth::check_playback_window).
+async fn check_playback_window(
+    conn: &DbConnection,
+    user: &Auth,
+) -> Result<(), errors::StreamingErrors> {
+    let mut tx = conn.read().begin().await?;
+
+    crate::routes::auth::check_playback_window(&mut tx, user)
+        .await
+        .map_err(|e| match e {
+            errors::DimError::PlaybackNotAllowed { reason } => {
+                errors::StreamingErrors::PlaybackNotAllowed(reason)
+            }
+            e => errors::StreamingErrors::DatabaseError(e.to_string()),
+        })
+}
+
 /// Method mapped to `GET /api/v1/stream/<id>/manifest?<gid>` returns or creates a virtual
-/// manifest.
+/// manifest. Returns `403` outside of the playback windows of the user.
 pub async fn return_virtual_manifest(
     state: StateManager,
     stream_tracking: StreamTracking,
@@ -284,6 +304,8 @@ pub async fn return_virtual_manifest(
     gid: Option<Uuid>,
     force_ass: bool,
 ) -> Result<impl warp::Reply, errors::StreamingErrors> {
+    check_playback_window(&conn, &auth).await?;
+
     if let Some(gid) = gid {
         return Ok(reply::json(&json!({
             "tracks": stream_tracking.get_for_gid(&gid).await,
@@ -332,8 +354,19 @@ pub async fn return_virtual_manifest(
         should_stream_default,
     )
     .await?;
-    create_audio(&info, &media, &stream_tracking, &gid, &state).await?;
-    create_subtitles(&info, &media, &stream_tracking, &gid, &state, force_ass).await?;
+    let prefs = user_prefs.playback_preferences();
+
+    create_audio(&info, &media, &stream_tracking, &gid, &state, &prefs).await?;
+    create_subtitles(
+        &info,
+        &media,
+        &stream_tracking,
+        &gid,
+        &state,
+        &prefs,
+        force_ass,
+    )
+    .await?;
 
     stream_tracking.generate_sids(&gid).await;
 
@@ -538,11 +571,19 @@ pub async fn create_audio(
     stream_tracking: &StreamTracking,
     gid: &Uuid,
     state: &StateManager,
+    prefs: &PlaybackPreferences,
 ) -> Result<(), errors::StreamingErrors> {
     let audio_streams = info.find_by_type("audio");
 
-    for stream in audio_streams {
-        let is_default = info.get_primary("audio") == Some(stream);
+    // the track in the users preferred language wins over the primary track of the file.
+    let default_stream = prefs
+        .preferred_audio_lang
+        .as_deref()
+        .and_then(|lang| find_by_language(&audio_streams, lang))
+        .or_else(|| info.get_primary("audio"));
+
+    for stream in audio_streams.iter().copied() {
+        let is_default = default_stream == Some(stream);
         let bitrate = stream
             .bit_rate
             .as_ref()
@@ -598,12 +639,23 @@ pub async fn create_subtitles(
     stream_tracking: &StreamTracking,
     gid: &Uuid,
     state: &StateManager,
+    prefs: &PlaybackPreferences,
     force_ass: bool,
 ) -> Result<(), errors::StreamingErrors> {
     let subtitles = info.find_by_type("subtitle");
 
-    for stream in subtitles {
-        let is_default = info.get_primary("subtitle") == Some(stream);
+    let default_stream = if prefs.subtitles_enabled {
+        prefs
+            .preferred_subtitle_lang
+            .as_deref()
+            .and_then(|lang| find_by_language(&subtitles, lang))
+            .or_else(|| info.get_primary("subtitle"))
+    } else {
+        None
+    };
+
+    for stream in subtitles.iter().copied() {
+        let is_default = default_stream == Some(stream);
         let is_ssa = ["ssa", "ass"].contains(&stream.codec_name.as_str()) && force_ass;
 
         if !["subrip", "ass", "ssa", "srt", "webvtt", "vtt"].contains(&stream.codec_name.as_str()) {
@@ -664,8 +716,21 @@ pub async fn create_subtitles(
     Ok(())
 }
 
+/// Method returns the first stream whose language matches `lang`. `lang` can either be a ISO 639-2
+/// code like `eng` or the name of the language like `english`.
+fn find_by_language<'a>(streams: &[&'a Stream], lang: &str) -> Option<&'a Stream> {
+    streams.iter().copied().find(|stream| {
+        stream.get_language().map_or(false, |tag| {
+            tag.eq_ignore_ascii_case(lang)
+                || crate::utils::lang_from_iso639(&tag)
+                    .map_or(false, |name| name.eq_ignore_ascii_case(lang))
+        })
+    })
+}
+
 /// Method mapped to `/api/v1/stream/<gid>/manifest.mpd` compiles a virtual manifest into a
-/// mpeg-dash manifest.
+/// mpeg-dash manifest. Returns `403` outside of the playback windows of the user, thus streams
+/// stop once a window ends.
 ///
 /// # Query args
 /// * `start_num` - first chunk number
@@ -675,13 +740,15 @@ pub async fn create_subtitles(
 pub async fn return_manifest(
     state: StateManager,
     stream_tracking: StreamTracking,
-    _auth: Auth,
-    _conn: DbConnection,
+    auth: Auth,
+    conn: DbConnection,
     gid: Uuid,
     start_num: Option<u64>,
     should_kill: Option<bool>,
     includes: Option<String>,
 ) -> Result<impl warp::Reply, errors::StreamingErrors> {
+    check_playback_window(&conn, &auth).await?;
+
     if should_kill.unwrap_or(true) {
         let ids = stream_tracking
             .get_for_gid(&gid)
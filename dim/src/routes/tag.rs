@@ -0,0 +1,110 @@
+use crate::core::DbConnection;
+use crate::errors;
+
+use auth::Wrapper as Auth;
+
+use database::tag::Tag;
+
+use serde_json::json;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn get_tags(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "tags")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|user: Auth, conn: DbConnection| async move {
+                super::get_tags(conn, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn get_tag_media(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "tag" / i64 / "media")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, user: Auth, conn: DbConnection| async move {
+                super::get_tag_media(conn, id, user)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+}
+
+/// Method mapped to `GET /api/v1/tags` returns every tag created by users along with the number of
+/// media tagged with it, sorted by name. Media the user hid and media in hidden libraries arent
+/// counted.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "id": int,
+///         "name": string,
+///         "count": int,
+///     }
+/// ]
+/// ```
+pub async fn get_tags(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+
+    Ok(reply::json(
+        &Tag::get_all(&mut tx, user.0.claims.get_user_ref()).await?,
+    ))
+}
+
+/// Method mapped to `GET /api/v1/tag/<id>/media` returns the tag along with all media tagged with
+/// it, sorted by their sort title.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `id` - id of the tag
+/// * `user` - Auth middleware
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "name": string,
+///     "media": [Media],
+/// }
+/// ```
+pub async fn get_tag_media(
+    conn: DbConnection,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let tag = Tag::get_by_id(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+    let media = Tag::get_media(&mut tx, id, user.0.claims.get_user_ref()).await?;
+
+    Ok(reply::json(&json!({
+        "id": tag.id,
+        "name": tag.name,
+        "media": media,
+    })))
+}
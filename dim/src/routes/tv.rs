@@ -1,14 +1,32 @@
 use crate::core::DbConnection;
 use crate::errors;
+use crate::progress_buffer;
 
 use auth::Wrapper as Auth;
 
+use database::asset::InsertableAsset;
 use database::episode::{Episode, UpdateEpisode};
+use database::library::MediaType;
+use database::media::{Media, UpdateMedia};
+use database::mediafile::MediaFile;
+use database::progress::Progress;
 use database::season::{Season, UpdateSeason};
+use database::tmdb_episode::TmdbEpisode;
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::json;
+use tokio::task::spawn_blocking;
+use tracing::warn;
 
 use warp::http::status::StatusCode;
 use warp::reply;
 
+/// Max number of days `GET /api/v1/episodes/aired` returns episodes for at once.
+pub const MAX_AIRED_RANGE_DAYS: i64 = 366;
+
 pub mod filters {
     use warp::reject;
     use warp::Filter;
@@ -20,26 +38,93 @@ pub mod filters {
     use database::season::UpdateSeason;
     use database::DbConnection;
 
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct UnwatchedArgs {
+        #[serde(default)]
+        unwatched_only: bool,
+    }
+
     pub fn get_tv_seasons(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "tv" / i64 / "season")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
-            .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
-                super::get_tv_seasons(conn, id, auth)
+            .and(warp::query::query::<UnwatchedArgs>())
+            .and_then(
+                |id: i64,
+                 auth: Auth,
+                 conn: DbConnection,
+                 UnwatchedArgs { unwatched_only }: UnwatchedArgs| async move {
+                    super::get_tv_seasons(conn, id, auth, unwatched_only)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_season_meta(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "season" / i64 / "meta")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |id: i64, season_number: i64, _auth: Auth, conn: DbConnection| async move {
+                    super::get_season_meta(conn, id, season_number)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_episode_map(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "episode_map")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|id: i64, _auth: Auth, conn: DbConnection| async move {
+                super::get_episode_map(conn, id)
                     .await
                     .map_err(|e| reject::custom(e))
             })
     }
 
+    pub fn get_aired_episodes(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        #[derive(Deserialize)]
+        struct RouteArgs {
+            from: String,
+            to: String,
+        }
+
+        warp::path!("api" / "v1" / "episodes" / "aired")
+            .and(warp::get())
+            .and(warp::query::query::<RouteArgs>())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and_then(
+                |RouteArgs { from, to }: RouteArgs, _auth: Auth, conn: DbConnection| async move {
+                    super::get_aired_episodes(conn, from, to)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
     pub fn get_season_by_id(
         conn: DbConnection,
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "season" / i64)
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
                 super::get_season_by_id(conn, id, auth)
@@ -54,7 +139,7 @@ pub mod filters {
         warp::path!("api" / "v1" / "season" / i64)
             .and(warp::patch())
             .and(warp::body::json::<UpdateSeason>())
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(
                 |id: i64, data: UpdateSeason, auth: Auth, conn: DbConnection| async move {
@@ -70,7 +155,7 @@ pub mod filters {
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "season" / i64)
             .and(warp::delete())
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
                 super::delete_season_by_id(conn, id, auth)
@@ -84,12 +169,32 @@ pub mod filters {
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "season" / i64 / "episodes")
             .and(warp::get())
-            .and(auth::with_auth())
+            .and(auth::with_scope("read:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and(warp::query::query::<UnwatchedArgs>())
+            .and_then(
+                |id: i64,
+                 auth: Auth,
+                 conn: DbConnection,
+                 UnwatchedArgs { unwatched_only }: UnwatchedArgs| async move {
+                    super::get_season_episodes(conn, id, auth, unwatched_only)
+                        .await
+                        .map_err(reject::custom)
+                },
+            )
+    }
+
+    pub fn get_episode_still(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "episode" / i64 / "still")
+            .and(warp::get())
+            .and(auth::with_scope("read:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
-                super::get_season_episodes(conn, id, auth)
+                super::get_episode_still(conn, id, auth)
                     .await
-                        .map_err(reject::custom)
+                    .map_err(reject::custom)
             })
     }
 
@@ -99,7 +204,7 @@ pub mod filters {
         warp::path!("api" / "v1" / "episode" / i64)
             .and(warp::patch())
             .and(warp::body::json::<UpdateEpisode>())
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(
                 |id: i64, data: UpdateEpisode, auth: Auth, conn: DbConnection| async move {
@@ -115,7 +220,7 @@ pub mod filters {
     ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
         warp::path!("api" / "v1" / "episode" / i64)
             .and(warp::delete())
-            .and(auth::with_auth())
+            .and(auth::with_scope("write:media"))
             .and(with_state::<DbConnection>(conn))
             .and_then(|id: i64, auth: Auth, conn: DbConnection| async move {
                 super::delete_episode_by_id(conn, id, auth)
@@ -130,13 +235,211 @@ pub mod filters {
 ///
 /// # Arguments
 /// * `id` - id of the tv show we want info about
+/// * `unwatched_only` - when set, seasons whose episodes the user has all finished are omitted
 pub async fn get_tv_seasons(
     conn: DbConnection,
     id: i64,
-    _user: Auth,
+    user: Auth,
+    unwatched_only: bool,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
     let mut tx = conn.read().begin().await?;
-    Ok(reply::json(&Season::get_all(&mut tx, id).await?))
+    let mut seasons = Season::get_all(&mut tx, id).await?;
+
+    if unwatched_only {
+        let unwatched =
+            Progress::get_unwatched_episodes_of_tv(&mut tx, user.0.claims.get_user(), id)
+                .await?
+                .into_iter()
+                .map(|(_, season_id)| season_id)
+                .collect::<HashSet<_>>();
+
+        seasons.retain(|x| unwatched.contains(&x.id));
+    }
+
+    Ok(reply::json(&seasons))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/season/<season_number>/meta` returns the poster, name,
+/// overview and air date of a season of a tv show. The metadata is stored when the show is
+/// matched, fields are null for seasons matched before that until the show is rematched.
+///
+/// # Arguments
+/// * `id` - id of the tv show
+/// * `season_number` - number of the season
+///
+/// # Return Schema
+/// ```text
+/// {
+///     "id": int,
+///     "season_number": int,
+///     "poster": string | null,
+///     "name": string | null,
+///     "overview": string | null,
+///     "air_date": string | null,
+/// }
+/// ```
+pub async fn get_season_meta(
+    conn: DbConnection,
+    id: i64,
+    season_number: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let season = Season::get(&mut tx, id, season_number)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    Ok(reply::json(&json!({
+        "id": season.id,
+        "season_number": season.season_number,
+        "poster": season.poster,
+        "name": season.name,
+        "overview": season.overview,
+        "air_date": season.air_date,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/media/<id>/episode_map` returns every episode of a tv show listed
+/// on TMDB along with the local episode and file it maps to. Episodes without a `mediafile_id`
+/// are missing locally. Episodes contained in a file spanning multiple episodes map to that file.
+/// The catalog is stored when the show is matched, shows matched before that return no episodes
+/// until they are rematched.
+///
+/// # Arguments
+/// * `id` - id of the tv show
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "season": int,
+///         "episode": int,
+///         "name": string | null,
+///         "air_date": string | null,
+///         "episode_id": int | null,
+///         "mediafile_id": int | null,
+///         "available": bool,
+///     }
+/// ]
+/// ```
+pub async fn get_episode_map(
+    conn: DbConnection,
+    id: i64,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id)
+        .await
+        .map_err(|_| errors::DimError::NotFoundError)?;
+
+    if media.media_type != MediaType::Tv {
+        return Err(errors::DimError::InvalidMediaType);
+    }
+
+    let mut episodes = TmdbEpisode::get_mapping(&mut tx, id).await?;
+
+    for episode in episodes.iter_mut() {
+        if let (Some(episode_id), None) = (episode.episode_id, episode.mediafile_id) {
+            episode.mediafile_id = MediaFile::get_multi_episode_file(&mut tx, episode_id)
+                .await
+                .ok()
+                .map(|x| x.id);
+        }
+    }
+
+    Ok(reply::json(
+        &episodes
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "season": x.season,
+                    "episode": x.episode,
+                    "name": x.name,
+                    "air_date": x.air_date,
+                    "episode_id": x.episode_id,
+                    "mediafile_id": x.mediafile_id,
+                    "available": x.mediafile_id.is_some(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Method mapped to `GET /api/v1/episodes/aired?<from>&<to>` returns the episodes of every tv
+/// show that aired within a date range, ordered by air date, to power a release calendar.
+/// Episodes without a `mediafile_id` are missing locally. Episodes with an unknown air date are
+/// excluded. Air dates are stored when a show is matched and updated when its metadata is
+/// refreshed, shows matched before that return no episodes until they are rematched or refreshed.
+/// Ranges spanning more than [`MAX_AIRED_RANGE_DAYS`] days are rejected with `400`.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `from` - first day of the range formatted as `YYYY-MM-DD`, inclusive.
+/// * `to` - last day of the range formatted as `YYYY-MM-DD`, inclusive.
+///
+/// # Return Schema
+/// ```text
+/// [
+///     {
+///         "tvshow_id": int,
+///         "tvshow_name": string,
+///         "season": int,
+///         "episode": int,
+///         "name": string | null,
+///         "air_date": string,
+///         "episode_id": int | null,
+///         "mediafile_id": int | null,
+///         "available": bool,
+///     }
+/// ]
+/// ```
+pub async fn get_aired_episodes(
+    conn: DbConnection,
+    from: String,
+    to: String,
+) -> Result<impl warp::Reply, errors::DimError> {
+    use chrono::NaiveDate;
+
+    let parse = |x: &str| {
+        NaiveDate::parse_from_str(x, "%Y-%m-%d").map_err(|_| errors::DimError::InvalidDateRange)
+    };
+
+    let (from, to) = (parse(&from)?, parse(&to)?);
+
+    if from > to || (to - from).num_days() >= MAX_AIRED_RANGE_DAYS {
+        return Err(errors::DimError::InvalidDateRange);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    let mut episodes =
+        TmdbEpisode::get_aired_between(&mut tx, &from.to_string(), &to.to_string()).await?;
+
+    for episode in episodes.iter_mut() {
+        if let (Some(episode_id), None) = (episode.episode_id, episode.mediafile_id) {
+            episode.mediafile_id = MediaFile::get_multi_episode_file(&mut tx, episode_id)
+                .await
+                .ok()
+                .map(|x| x.id);
+        }
+    }
+
+    Ok(reply::json(
+        &episodes
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "tvshow_id": x.tvshow_id,
+                    "tvshow_name": x.tvshow_name,
+                    "season": x.season,
+                    "episode": x.episode,
+                    "name": x.name,
+                    "air_date": x.air_date,
+                    "episode_id": x.episode_id,
+                    "mediafile_id": x.mediafile_id,
+                    "available": x.mediafile_id.is_some(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    ))
 }
 
 /// Method mapped to `GET /api/v1/tv/<id>/season/<season_num>` returns info about the season
@@ -200,11 +503,14 @@ pub async fn delete_season_by_id(
 ///
 /// # Arguments
 /// * `id` - id of the episode.
+/// * `unwatched_only` - when set, only episodes the user hasn't finished are returned
 pub async fn get_season_episodes(
     conn: DbConnection,
     season_id: i64,
-    _user: Auth,
+    user: Auth,
+    unwatched_only: bool,
 ) -> Result<impl warp::Reply, errors::DimError> {
+    progress_buffer::flush_user(&conn, user.0.claims.get_user_ref()).await?;
     let mut tx = conn.read().begin().await?;
     #[derive(serde::Serialize)]
     pub struct Record {
@@ -214,6 +520,16 @@ pub async fn get_season_episodes(
         pub episode: i64,
     }
 
+    #[derive(serde::Serialize)]
+    pub struct EpisodeRecord {
+        #[serde(flatten)]
+        pub record: Record,
+        /// Id of the file containing this episode if the file spans multiple episodes.
+        pub shared_file: Option<i64>,
+        /// Offset in seconds at which this episode roughly starts in `shared_file`.
+        pub offset: Option<i64>,
+    }
+
     let result = sqlx::query_as!(Record,
         r#"SELECT episode.id as "id!", _tblmedia.name, assets.local_path as thumbnail_url, episode.episode_ as "episode!"
         FROM episode
@@ -223,7 +539,161 @@ pub async fn get_season_episodes(
         season_id
     ).fetch_all(&mut tx).await?;
 
-    Ok(reply::json(&result))
+    let result = if unwatched_only {
+        let tv_id = Season::get_by_id(&mut tx, season_id).await?.tvshowid;
+        let unwatched =
+            Progress::get_unwatched_episodes_of_tv(&mut tx, user.0.claims.get_user(), tv_id)
+                .await?
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<HashSet<_>>();
+
+        result
+            .into_iter()
+            .filter(|x| unwatched.contains(&x.id))
+            .collect()
+    } else {
+        result
+    };
+
+    let shared_files = MediaFile::get_multi_episode_files_of_season(&mut tx, season_id).await?;
+
+    let episodes = result
+        .into_iter()
+        .map(|record| {
+            let shared_file = shared_files.get(&record.id);
+
+            EpisodeRecord {
+                offset: shared_file.and_then(|x| x.episode_offset(record.episode)),
+                shared_file: shared_file.map(|x| x.id),
+                record,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(reply::json(&episodes))
+}
+
+/// Method mapped to `GET /api/v1/episode/<id>/still` returns the still image of a episode.
+///
+/// The still fetched from TMDB during the scan is preferred. If TMDB had no still for the episode
+/// we extract a frame from the episode's file with ffmpeg. The extracted frame is cached in the
+/// metadata directory and linked to the episode, so this only happens on the first request.
+/// Failed extractions are remembered with a marker file next to the cache, thus ffmpeg isn't run
+/// again on every request for files it cant read. `still_path` is `null` if neither source is
+/// available.
+///
+/// # Arguments
+/// * `id` - id of the episode.
+pub async fn get_episode_still(
+    conn: DbConnection,
+    id: i64,
+    _user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    let mut tx = conn.read().begin().await?;
+    let media = Media::get(&mut tx, id).await?;
+
+    if !matches!(media.media_type, MediaType::Episode) {
+        return Err(errors::DimError::InvalidMediaType);
+    }
+
+    if let Some(still_path) = media.backdrop_path {
+        return Ok(reply::json(&json!({
+            "id": id,
+            "still_path": still_path,
+        })));
+    }
+
+    let mediafile = MediaFile::get_of_media(&mut tx, id)
+        .await?
+        .into_iter()
+        .next();
+    drop(tx);
+
+    let still_path = match mediafile {
+        Some(mediafile) => extract_still(id, mediafile).await,
+        None => None,
+    };
+
+    if let Some(path) = still_path.as_ref() {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+
+        let asset = InsertableAsset {
+            local_path: path.clone(),
+            file_ext: "jpg".into(),
+            ..Default::default()
+        }
+        .insert(&mut tx)
+        .await?;
+
+        UpdateMedia {
+            backdrop: Some(asset.id),
+            ..Default::default()
+        }
+        .update_automatic(&mut tx, id)
+        .await?;
+
+        tx.commit().await?;
+    }
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "still_path": still_path,
+    })))
+}
+
+/// Extracts a single frame from `mediafile` into the metadata directory and returns the path it
+/// is served under. If the frame was already extracted previously the cached image is reused. If
+/// extracting a frame from this mediafile failed before `None` is returned without retrying.
+async fn extract_still(id: i64, mediafile: MediaFile) -> Option<String> {
+    let metadata_path = crate::core::METADATA_PATH.get().unwrap();
+    let file_name = format!("still_{}.jpg", id);
+    let local_path = format!("{}/{}", metadata_path, &file_name);
+    // keyed by the mediafile so that replacing a unreadable file gives it another chance.
+    let failed_marker = format!("{}/still_{}_{}.failed", metadata_path, id, mediafile.id);
+
+    if Path::new(&failed_marker).exists() {
+        return None;
+    }
+
+    if !Path::new(&local_path).exists() {
+        // Grab a frame 10% into the episode to skip over cold opens and black frames.
+        let offset = mediafile.duration.map(|x| x / 10).unwrap_or(0);
+        let output_path = local_path.clone();
+
+        let result = spawn_blocking(move || {
+            Command::new(*crate::streaming::FFMPEG_BIN)
+                .arg("-ss")
+                .arg(offset.to_string())
+                .arg("-i")
+                .arg(&mediafile.target_file)
+                .arg("-frames:v")
+                .arg("1")
+                .arg("-q:v")
+                .arg("3")
+                .arg("-y")
+                .arg(&output_path)
+                .output()
+        })
+        .await;
+
+        if !matches!(result, Ok(Ok(ref x)) if x.status.success()) {
+            warn!(episode_id = id, "Failed to extract still with ffmpeg");
+            // dont serve a partially written frame on the next request.
+            let _ = std::fs::remove_file(&local_path);
+
+            if let Err(e) = std::fs::File::create(&failed_marker) {
+                warn!(reason = ?e, episode_id = id, "Failed to persist failed still extraction");
+            }
+
+            return None;
+        }
+    }
+
+    Path::new(&local_path)
+        .exists()
+        .then(|| format!("images/{}", file_name))
 }
 
 /// TODO: Move all of these into a unified update interface for media items
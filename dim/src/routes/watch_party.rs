@@ -0,0 +1,182 @@
+use crate::core::DbConnection;
+use crate::errors;
+use crate::watch_party::WatchParties;
+
+use auth::Wrapper as Auth;
+
+use database::media::Media;
+
+use serde::Deserialize;
+
+use warp::http::status::StatusCode;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use super::PartyState;
+    use crate::watch_party::WatchParties;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn create_watch_party(
+        conn: DbConnection,
+        parties: WatchParties,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "media" / i64 / "watch_party")
+            .and(warp::post())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<WatchParties>(parties))
+            .and_then(
+                |id: i64, auth: Auth, conn: DbConnection, parties: WatchParties| async move {
+                    super::create_watch_party(conn, parties, id, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn join_watch_party(
+        parties: WatchParties,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "watch_party" / String / "join")
+            .and(warp::post())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<WatchParties>(parties))
+            .and_then(
+                |token: String, auth: Auth, parties: WatchParties| async move {
+                    super::join_watch_party(parties, token, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn leave_watch_party(
+        parties: WatchParties,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "watch_party" / String / "leave")
+            .and(warp::post())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<WatchParties>(parties))
+            .and_then(
+                |token: String, auth: Auth, parties: WatchParties| async move {
+                    super::leave_watch_party(parties, token, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn update_watch_party(
+        parties: WatchParties,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "watch_party" / String / "state")
+            .and(warp::post())
+            .and(warp::body::json::<PartyState>())
+            .and(auth::with_scope("write:media"))
+            .and(with_state::<WatchParties>(parties))
+            .and_then(
+                |token: String, state: PartyState, auth: Auth, parties: WatchParties| async move {
+                    super::update_watch_party(parties, token, state, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+}
+
+/// Playback state of a watch party as reported by its host.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct PartyState {
+    /// Playback offset in seconds.
+    pub offset: i64,
+    pub paused: bool,
+}
+
+/// Method mapped to `POST /api/v1/media/<id>/watch_party` creates a new watch party for a media
+/// hosted by the current user. The returned token is used by other users to join the party.
+///
+/// # Arguments
+/// * `conn` - database connection
+/// * `parties` - registry of watch parties
+/// * `id` - id of the media to watch
+/// * `user` - Auth middleware
+pub async fn create_watch_party(
+    conn: DbConnection,
+    parties: WatchParties,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    {
+        let mut tx = conn.read().begin().await?;
+        Media::get(&mut tx, id).await?;
+    }
+
+    Ok(reply::json(&parties.create(user.0.claims.get_user(), id)))
+}
+
+/// Method mapped to `POST /api/v1/watch_party/<token>/join` adds the current user to a watch
+/// party. The current state of the party is pushed to the user over the event socket.
+///
+/// # Arguments
+/// * `parties` - registry of watch parties
+/// * `token` - token of the party returned on creation
+/// * `user` - Auth middleware
+pub async fn join_watch_party(
+    parties: WatchParties,
+    token: String,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    parties
+        .join(&token, user.0.claims.get_user())
+        .map(|x| reply::json(&x))
+        .ok_or(errors::DimError::NotFoundError)
+}
+
+/// Method mapped to `POST /api/v1/watch_party/<token>/leave` removes the current user from a
+/// watch party. If the user is the host, the party is closed for everyone.
+///
+/// # Arguments
+/// * `parties` - registry of watch parties
+/// * `token` - token of the party
+/// * `user` - Auth middleware
+pub async fn leave_watch_party(
+    parties: WatchParties,
+    token: String,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    parties
+        .leave(&token, user.0.claims.get_user_ref())
+        .map(|_| StatusCode::NO_CONTENT)
+        .ok_or(errors::DimError::NotFoundError)
+}
+
+/// Method mapped to `POST /api/v1/watch_party/<token>/state` is used by the host to broadcast
+/// play, pause and seek events to the members of a watch party.
+///
+/// # Arguments
+/// * `parties` - registry of watch parties
+/// * `token` - token of the party
+/// * `state` - new playback state
+/// * `user` - Auth middleware
+pub async fn update_watch_party(
+    parties: WatchParties,
+    token: String,
+    state: PartyState,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    parties
+        .update(
+            &token,
+            user.0.claims.get_user_ref(),
+            state.offset,
+            state.paused,
+        )
+        .map(|x| reply::json(&x))
+        .ok_or(errors::DimError::Unauthorized)
+}
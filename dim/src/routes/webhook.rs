@@ -0,0 +1,182 @@
+use crate::core::DbConnection;
+use crate::errors;
+use crate::webhooks::Webhooks;
+
+use auth::Wrapper as Auth;
+
+use database::webhook::InsertableWebhook;
+use database::webhook::Webhook;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use warp::http::status::StatusCode;
+use warp::reply;
+
+pub mod filters {
+    use warp::reject;
+    use warp::Filter;
+    use warp::Rejection;
+
+    use super::super::global_filters::with_state;
+    use super::NewWebhook;
+    use crate::webhooks::Webhooks;
+    use auth::Wrapper as Auth;
+    use database::DbConnection;
+
+    pub fn register_webhook(
+        conn: DbConnection,
+        webhooks: Webhooks,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "webhooks")
+            .and(warp::post())
+            .and(warp::body::json::<NewWebhook>())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<Webhooks>(webhooks))
+            .and_then(
+                |data: NewWebhook, auth: Auth, conn: DbConnection, webhooks: Webhooks| async move {
+                    super::register_webhook(conn, webhooks, data, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+
+    pub fn get_webhooks(
+        conn: DbConnection,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "webhooks")
+            .and(warp::get())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and_then(|auth: Auth, conn: DbConnection| async move {
+                super::get_webhooks(conn, auth)
+                    .await
+                    .map_err(|e| reject::custom(e))
+            })
+    }
+
+    pub fn delete_webhook(
+        conn: DbConnection,
+        webhooks: Webhooks,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+        warp::path!("api" / "v1" / "admin" / "webhooks" / i64)
+            .and(warp::delete())
+            .and(auth::with_auth())
+            .and(with_state::<DbConnection>(conn))
+            .and(with_state::<Webhooks>(webhooks))
+            .and_then(
+                |id: i64, auth: Auth, conn: DbConnection, webhooks: Webhooks| async move {
+                    super::delete_webhook(conn, webhooks, id, auth)
+                        .await
+                        .map_err(|e| reject::custom(e))
+                },
+            )
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Event types the webhook receives, ie `EventNewCard`. All events are delivered if empty.
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Key used to sign payloads, a random key is generated if none is supplied.
+    pub secret: Option<String>,
+}
+
+/// Method mapped to `POST /api/v1/admin/webhooks` registers a new webhook which receives events
+/// as they are dispatched to clients. The secret used to sign payloads is only returned by this
+/// route. Only the owner can call this route.
+///
+/// # Arguments
+/// * `data` - url, event filter and optional secret of the webhook
+pub async fn register_webhook(
+    conn: DbConnection,
+    webhooks: Webhooks,
+    data: NewWebhook,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let is_http = reqwest::Url::parse(&data.url)
+        .map(|x| matches!(x.scheme(), "http" | "https"))
+        .unwrap_or(false);
+
+    if !is_http {
+        return Err(errors::DimError::MissingFieldInBody {
+            description: "Webhook urls must be absolute http or https urls".into(),
+        });
+    }
+
+    let webhook = InsertableWebhook {
+        url: data.url,
+        event_types: data.event_types,
+        secret: data
+            .secret
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_simple().to_string()),
+    };
+
+    let id = {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+        let id = webhook.insert(&mut tx).await?;
+        tx.commit().await?;
+        id
+    };
+
+    webhooks.reload().await;
+
+    Ok(reply::json(&json!({
+        "id": id,
+        "url": webhook.url,
+        "event_types": webhook.event_types,
+        "secret": webhook.secret,
+    })))
+}
+
+/// Method mapped to `GET /api/v1/admin/webhooks` returns all registered webhooks. Only the owner
+/// can call this route.
+pub async fn get_webhooks(
+    conn: DbConnection,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    let mut tx = conn.read().begin().await?;
+    Ok(reply::json(&Webhook::get_all(&mut tx).await?))
+}
+
+/// Method mapped to `DELETE /api/v1/admin/webhooks/<id>` removes a webhook. Only the owner can
+/// call this route.
+///
+/// # Arguments
+/// * `id` - id of the webhook
+pub async fn delete_webhook(
+    conn: DbConnection,
+    webhooks: Webhooks,
+    id: i64,
+    user: Auth,
+) -> Result<impl warp::Reply, errors::DimError> {
+    if !user.0.claims.has_role("owner") {
+        return Err(errors::DimError::Unauthorized);
+    }
+
+    {
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+        if Webhook::delete(&mut tx, id).await? == 0 {
+            return Err(errors::DimError::NotFoundError);
+        }
+        tx.commit().await?;
+    }
+
+    webhooks.reload().await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
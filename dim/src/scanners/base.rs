@@ -1,6 +1,10 @@
 use err_derive::Error;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
 use tracing::debug;
 use tracing::debug_span;
@@ -10,21 +14,46 @@ use tracing::instrument;
 use tracing::warn;
 use tracing::Instrument;
 
+use database::chapter::Chapter;
+use database::episode::Episode;
+use database::library::Library;
 use database::library::MediaType;
+use database::match_candidate::MatchCandidate;
+use database::media::Media;
 use database::mediafile::InsertableMediaFile;
 use database::mediafile::MediaFile;
 use database::mediafile::UpdateMediaFile;
+use database::season::Season;
 use database::DbConnection;
 
 use crate::core::EventTx;
 use crate::scanners::movie::MovieMatcher;
-use crate::scanners::tmdb::Tmdb;
+use crate::scanners::tmdb::ScoredMatch;
 use crate::scanners::tv_show::TvShowMatcher;
+use crate::scanners::MetadataProvider;
 use crate::streaming::ffprobe::FFProbeCtx;
 use crate::streaming::FFPROBE_BIN;
 
+use super::ApiAlternateTitle;
+use super::ApiCast;
+use super::ApiCrew;
+use super::ApiExternalIds;
 use super::ApiMedia;
+use super::ApiSeason;
 
+/// Number of search results stored as candidates when a match is flagged for review.
+const MAX_CANDIDATES: usize = 5;
+/// Interval for which the details of a show fetched while matching are reused for its other
+/// episodes.
+const SHOW_DETAILS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Details of shows fetched while matching keyed by the id of the show on its metadata agent,
+/// along with when they were fetched. Every episode file of a show is matched on its own, thus
+/// without this the same details are fetched once per episode.
+static SHOW_DETAILS: Lazy<Mutex<HashMap<u64, (Instant, ShowDetails)>>> =
+    Lazy::new(Default::default);
+
+use once_cell::sync::Lazy;
 use torrent_name_parser::Metadata;
 
 use serde::Serialize;
@@ -51,6 +80,10 @@ pub enum ScannerError {
     UnknownError,
     #[error(display = "Database error why={}", _0)]
     DatabaseError(String),
+    #[error(display = "A scan is already running for this library")]
+    ScanInProgress,
+    #[error(display = "Dim is shutting down")]
+    ShuttingDown,
 }
 
 impl From<database::DatabaseError> for ScannerError {
@@ -89,6 +122,12 @@ impl MetadataExtractor {
         library_id: i64,
         _media_type: MediaType,
     ) -> Result<MediaFile, ScannerError> {
+        // files are our checkpoints on shutdown, a file is either mounted and matched fully or
+        // left alone to be picked up by the next scan.
+        if crate::tasks::is_shutting_down() {
+            return Err(ScannerError::ShuttingDown);
+        }
+
         let target_file = file.to_str().unwrap().to_owned();
 
         let _file_name = if let Some(file_name) = file.file_name().and_then(|x| x.to_str()) {
@@ -111,34 +150,47 @@ impl MetadataExtractor {
             MediaFile::get_by_file(&mut tx, &target_file_clone).await
         };
 
-        if let Ok(_media_file) = res {
+        let size = tokio::fs::metadata(&file)
+            .await
+            .ok()
+            .map(|x| x.len() as i64);
+
+        if let Ok(media_file) = res {
             debug!(
                 file = ?file.to_string_lossy(),
                 library_id = library_id,
                 "File already exists in the db",
             );
 
+            // sizes are only ever refreshed by scans so that reading them stays cheap.
+            if size.is_some() && size != media_file.size {
+                let mut lock = self.conn.writer().lock_owned().await;
+                let mut tx = database::write_tx(&mut lock)
+                    .await
+                    .map_err(|e| ScannerError::DatabaseError(format!("{:?}", e)))?;
+
+                UpdateMediaFile {
+                    size,
+                    ..Default::default()
+                }
+                .update(&mut tx, media_file.id)
+                .await?;
+
+                tx.commit()
+                    .await
+                    .map_err(|e| ScannerError::DatabaseError(format!("{:?}", e)))?;
+            }
+
             return Err(ScannerError::UnknownError);
         }
 
-        // we clone so that we can strip the extension.
-        let mut file_name_clone = file.to_owned();
-        file_name_clone.set_extension("");
         // unwrap will never panic because we validate the path earlier on.
-        let file_name_clone = file_name_clone
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_owned();
-
-        let clone = file_name_clone.clone().replace(|c: char| !c.is_ascii(), "");
+        let file_name_clone = file.file_name().unwrap().to_str().unwrap().to_owned();
 
         // closure needs to be bound because of a lifetime bug where the closure passed to
         // `spawn_blocking` lives more than the data moved into it thus we cant pass a reference to
-        // `Metadata::from` directly.
-        let meta_from_string =
-            move || Metadata::from(&clone).map_err(|_| ScannerError::FilenameParserError);
+        // `parse_filename` directly.
+        let meta_from_string = move || parse_filename(&file_name_clone);
 
         let metadata = match spawn_blocking(meta_from_string)
             .instrument(debug_span!("ParseFilename"))
@@ -146,7 +198,7 @@ impl MetadataExtractor {
         {
             Ok(x) => x?,
             Err(e) => {
-                error!(e = ?e, "Metadata::from possibly panicked");
+                error!(e = ?e, "parse_filename possibly panicked");
                 return Err(ScannerError::UnknownError);
             }
         };
@@ -169,10 +221,11 @@ impl MetadataExtractor {
             media_id: None,
             target_file: target_file.to_string(),
 
-            raw_name: metadata.title().to_owned(),
-            raw_year: metadata.year().map(|x| x as i64),
-            season: metadata.season().map(|x| x as i64),
-            episode: metadata.episode().map(|x| x as i64),
+            raw_name: metadata.title.clone(),
+            raw_year: metadata.year,
+            season: metadata.season,
+            episode: metadata.episode,
+            episode_end: metadata.episode_end,
 
             quality: ffprobe_data.get_height().map(|x| x.to_string()),
             codec: ffprobe_data.get_video_codec(),
@@ -185,6 +238,10 @@ impl MetadataExtractor {
             corrupt: ffprobe_data.is_corrupt(),
             channels: ffprobe_data.get_primary_channels(),
             profile: ffprobe_data.get_video_profile(),
+            hdr: ffprobe_data.get_hdr_format().map(ToString::to_string),
+            has_cc: ffprobe_data.has_cc(),
+            has_sdh: ffprobe_data.has_sdh(),
+            size,
             audio_language: ffprobe_data
                 .get_audio_lang()
                 .or_else(|| ffprobe_data.get_video_lang())
@@ -193,6 +250,16 @@ impl MetadataExtractor {
                 .map(ToString::to_string),
         };
 
+        let chapters = ffprobe_data
+            .get_chapters()
+            .into_iter()
+            .map(|x| Chapter {
+                title: x.get_title().filter(|x| !x.is_empty()),
+                start: x.get_start().unwrap_or_default(),
+                end: x.get_end().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
         let mediafile = {
             let mut lock = self.conn.writer().lock_owned().await;
             let mut tx = database::write_tx(&mut lock)
@@ -204,6 +271,8 @@ impl MetadataExtractor {
                 .instrument(debug_span!("media_file_insert"))
                 .await?;
 
+            Chapter::set_for_mediafile(&mut tx, file_id, &chapters).await?;
+
             let mediafile = MediaFile::get_one(&mut tx, file_id)
                 .instrument(debug_span!("media_file_select"))
                 .await?;
@@ -223,8 +292,8 @@ impl MetadataExtractor {
             file = ?&target_file,
             library_id = library_id,
             id = mediafile.id,
-            season = metadata.season().unwrap_or(0),
-            episode = metadata.episode().unwrap_or(0),
+            season = metadata.season.unwrap_or(0),
+            episode = metadata.episode.unwrap_or(0),
         );
 
         Ok(mediafile)
@@ -233,8 +302,6 @@ impl MetadataExtractor {
 
 #[actor]
 pub struct MetadataMatcher {
-    pub movie_tmdb: Tmdb,
-    pub tv_tmdb: Tmdb,
     pub conn: DbConnection,
     pub event_tx: EventTx,
 }
@@ -242,19 +309,15 @@ pub struct MetadataMatcher {
 #[actor]
 impl MetadataMatcher {
     pub fn new(conn: DbConnection, event_tx: EventTx) -> Self {
-        Self {
-            conn,
-            event_tx,
-            movie_tmdb: Tmdb::new("38c372f5bc572c8aadde7a802638534e".into(), MediaType::Movie),
-            tv_tmdb: Tmdb::new("38c372f5bc572c8aadde7a802638534e".into(), MediaType::Tv),
-        }
+        Self { conn, event_tx }
     }
 
     #[handler]
     pub async fn match_movie(&mut self, media: MediaFile) -> Result<(), ScannerError> {
-        let result = match self
-            .movie_tmdb
-            .search(media.raw_name.clone(), media.raw_year.map(|x| x as i32))
+        let mut tmdb = library_agent(&self.conn, media.library_id, MediaType::Movie).await?;
+
+        let matches = match tmdb
+            .search_scored(media.raw_name.clone(), media.raw_year.map(|x| x as i32))
             .await
         {
             Ok(v) => v,
@@ -265,7 +328,18 @@ impl MetadataMatcher {
             }
         };
 
-        self.match_movie_to_result(media, result).await
+        let mediafile = media.id;
+        let exists =
+            media_exists(&self.conn, media.library_id, MediaType::Movie, &matches[0]).await?;
+        self.match_movie_to_result(media, matches[0].media.clone().into())
+            .await?;
+
+        // files matched into an existing media dont flag it again, ie after it was reviewed.
+        if exists {
+            return Ok(());
+        }
+
+        flag_for_review(&self.conn, mediafile, &matches).await
     }
 
     #[handler]
@@ -274,6 +348,49 @@ impl MetadataMatcher {
         media: MediaFile,
         result: ApiMedia,
     ) -> Result<(), ScannerError> {
+        // FIXME: Our handler macro cant handle `mut` keyword yet.
+        let mut result = result;
+        let mut tmdb = library_agent(&self.conn, media.library_id, MediaType::Movie).await?;
+
+        let credits = tmdb.get_credits_for(result.id).await.unwrap_or_default();
+
+        result.cast = credits.cast.into_iter().map(Into::into).collect();
+        result.crew = credits.crew.into_iter().map(Into::into).collect();
+
+        result.keywords = tmdb
+            .get_keywords_for(result.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+
+        result.alternate_titles = tmdb
+            .get_alternate_titles_for(result.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        result.external_ids = tmdb
+            .get_external_ids_for(result.id)
+            .await
+            .ok()
+            .map(Into::into);
+
+        // the details of a movie hold both its runtime and the collection it belongs to.
+        if let Ok(details) = tmdb.search_by_id(result.id as i32).await {
+            result.runtime = details.runtime.filter(|x| *x > 0).map(|x| x * 60);
+            result.collection_id = details.collection_id;
+        }
+
+        // wait for any rematch that is currently writing the metadata of this media.
+        let _lock = match media.media_id {
+            Some(id) => Some(super::MediaLock::acquire(id).await),
+            None => None,
+        };
+
         let matcher = MovieMatcher {
             conn: &self.conn,
             event_tx: &self.event_tx,
@@ -305,16 +422,17 @@ impl MetadataMatcher {
             Ok(v) | Err(v) => v,
         };
 
-        let mut result = self
-            .tv_tmdb
-            .search(media.raw_name.clone(), media.raw_year.map(|x| x as i32))
+        let mut tmdb = library_agent(&self.conn, media.library_id, MediaType::Tv).await?;
+
+        let mut result = tmdb
+            .search_scored(media.raw_name.clone(), media.raw_year.map(|x| x as i32))
             .await;
 
         if let Some(x) = els.get(ElementCategory::AnimeTitle) {
             if result.is_err() {
                 // NOTE: If we got here then we assume that the file uses common anime release naming schemes.
                 // Thus we prioritise metadata extracted by anitomy.
-                result = self.tv_tmdb.search(x.to_string(), None).await;
+                result = tmdb.search_scored(x.to_string(), None).await;
 
                 // NOTE: Some releases dont include season number, so we just assume its the first one.
                 let anitomy_episode = els
@@ -350,7 +468,7 @@ impl MetadataMatcher {
             }
         }
 
-        let result = match result {
+        let matches = match result {
             Ok(v) => v,
             Err(e) => {
                 error!(media = ?media, reason = ?e, "Could not match tv show to tmdb");
@@ -358,7 +476,17 @@ impl MetadataMatcher {
             }
         };
 
-        self.match_tv_to_result(media, result).await
+        let mediafile = media.id;
+        let exists = media_exists(&self.conn, media.library_id, MediaType::Tv, &matches[0]).await?;
+        self.match_tv_to_result(media, matches[0].media.clone().into())
+            .await?;
+
+        // files matched into an existing media dont flag it again, ie after it was reviewed.
+        if exists {
+            return Ok(());
+        }
+
+        flag_for_review(&self.conn, mediafile, &matches).await
     }
 
     #[handler]
@@ -370,6 +498,7 @@ impl MetadataMatcher {
         // FIXME: Our handler macro cant handle `mut` keyword yet.
         let mut media = media;
         let mut result = result;
+        let mut tmdb = library_agent(&self.conn, media.library_id, MediaType::Tv).await?;
 
         let mut lock = self.conn.writer().lock_owned().await;
         let mut tx = database::write_tx(&mut lock)
@@ -383,9 +512,70 @@ impl MetadataMatcher {
             .map_err(|e| ScannerError::DatabaseError(format!("{:?}", e)))?;
         drop(lock);
 
-        let mut seasons: Vec<super::ApiSeason> = self
-            .tv_tmdb
-            .get_seasons_for(result.id)
+        let details = ShowDetails::get(&mut *tmdb, result.id).await;
+
+        result.seasons = details.seasons;
+        result.cast = details.cast;
+        result.crew = details.crew;
+        result.keywords = details.keywords;
+        result.alternate_titles = details.alternate_titles;
+        result.external_ids = details.external_ids;
+        result.runtime = details.runtime;
+
+        // wait for any rematch that is currently writing the metadata of this media.
+        let _lock = match media.media_id {
+            Some(id) => Some(super::MediaLock::acquire(id).await),
+            None => None,
+        };
+
+        let matcher = TvShowMatcher {
+            conn: &self.conn,
+            event_tx: &self.event_tx,
+        };
+
+        matcher.match_to_result(result, &media).await;
+        Ok(())
+    }
+}
+
+/// Details of a show that are the same for all of its episodes.
+#[derive(Clone, Default)]
+struct ShowDetails {
+    seasons: Vec<ApiSeason>,
+    cast: Vec<ApiCast>,
+    crew: Vec<ApiCrew>,
+    keywords: Vec<String>,
+    alternate_titles: Vec<ApiAlternateTitle>,
+    external_ids: Option<ApiExternalIds>,
+    runtime: Option<u64>,
+}
+
+impl ShowDetails {
+    /// Method returns the details of the show `id`, fetching them from `tmdb` unless they were
+    /// fetched within [`SHOW_DETAILS_TTL`]. Details are only reused if the seasons of the show
+    /// could be fetched, such that a show isnt stuck without metadata while TMDB is down.
+    async fn get(tmdb: &mut dyn MetadataProvider, id: u64) -> Self {
+        if let Some((at, details)) = SHOW_DETAILS.lock().unwrap().get(&id) {
+            if at.elapsed() < SHOW_DETAILS_TTL {
+                return details.clone();
+            }
+        }
+
+        let details = Self::fetch(tmdb, id).await;
+
+        let mut lock = SHOW_DETAILS.lock().unwrap();
+        lock.retain(|_, (at, _)| at.elapsed() < SHOW_DETAILS_TTL);
+
+        if !details.seasons.is_empty() {
+            lock.insert(id, (Instant::now(), details.clone()));
+        }
+
+        details
+    }
+
+    async fn fetch(tmdb: &mut dyn MetadataProvider, id: u64) -> Self {
+        let mut seasons: Vec<ApiSeason> = tmdb
+            .get_seasons_for(id)
             .await
             .unwrap_or_default()
             .into_iter()
@@ -393,9 +583,8 @@ impl MetadataMatcher {
             .collect();
 
         for season in seasons.iter_mut() {
-            season.episodes = self
-                .tv_tmdb
-                .get_episodes_for(result.id, season.season_number)
+            season.episodes = tmdb
+                .get_episodes_for(id, season.season_number)
                 .await
                 .unwrap_or_default()
                 .into_iter()
@@ -403,18 +592,48 @@ impl MetadataMatcher {
                 .collect();
         }
 
-        result.seasons = seasons;
-
-        let matcher = TvShowMatcher {
-            conn: &self.conn,
-            event_tx: &self.event_tx,
-        };
+        let credits = tmdb.get_credits_for(id).await.unwrap_or_default();
 
-        matcher.match_to_result(result, &media).await;
-        Ok(())
+        Self {
+            seasons,
+            cast: credits.cast.into_iter().map(Into::into).collect(),
+            crew: credits.crew.into_iter().map(Into::into).collect(),
+            keywords: tmdb
+                .get_keywords_for(id)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| x.name)
+                .collect(),
+            alternate_titles: tmdb
+                .get_alternate_titles_for(id)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            external_ids: tmdb.get_external_ids_for(id).await.ok().map(Into::into),
+            runtime: tmdb.get_runtime_for(id).await.ok().map(|x| x * 60),
+        }
     }
 }
 
+/// Function returns a client of the metadata agent configured on the library `library_id`.
+async fn library_agent(
+    conn: &DbConnection,
+    library_id: i64,
+    media_type: MediaType,
+) -> Result<Box<dyn MetadataProvider>, ScannerError> {
+    let mut tx = conn
+        .read()
+        .begin()
+        .await
+        .map_err(|_| ScannerError::DatabaseConnectionError)?;
+    let agent = Library::get_agent(&mut tx, library_id).await?;
+
+    Ok(super::metadata_agent(agent, media_type))
+}
+
 #[instrument(skip(media, tx))]
 pub async fn patch_tv_metadata(
     media: &mut MediaFile,
@@ -431,40 +650,301 @@ pub async fn patch_tv_metadata(
         .map(ToString::to_string)
         .unwrap_or_default();
 
-    // FIXME: Use into_ok_or_err when it hits stable.
-    let els: Elements = match spawn_blocking(move || {
-        let mut anitomy = Anitomy::new();
-        anitomy.parse(filename.as_str())
-    })
-    .await
-    .unwrap()
+    let (season, episode) = match spawn_blocking(move || parse_anime_episode(&filename))
+        .await
+        .unwrap()
     {
-        Ok(v) => v,
-        Err(_) => {
+        Some(v) => v,
+        None => {
             debug!(media = ?media, "patch_tv_metadata exited early");
             return Ok(());
         }
     };
 
-    if let Some(episode) = els
+    let updated_mediafile = UpdateMediaFile {
+        episode: Some(episode),
+        season: Some(season),
+        ..Default::default()
+    };
+
+    let _ = updated_mediafile.update(&mut *tx, media.id).await;
+    media.episode = Some(episode);
+    media.season = Some(season);
+
+    Ok(())
+}
+
+/// Function returns whether matching a file of `library_id` to `result` attaches it to a media
+/// that already exists instead of creating one. Like the matchers, media are looked up by their
+/// TMDB id and their name.
+async fn media_exists(
+    conn: &DbConnection,
+    library_id: i64,
+    media_type: MediaType,
+    result: &ScoredMatch,
+) -> Result<bool, ScannerError> {
+    let mut tx = conn
+        .read()
+        .begin()
+        .await
+        .map_err(|_| ScannerError::DatabaseConnectionError)?;
+
+    if Media::get_id_by_tmdb_id(&mut tx, library_id, result.media.id as i64, media_type)
+        .await?
+        .is_some()
+    {
+        return Ok(true);
+    }
+
+    Ok(
+        Media::get_by_name_and_lib(&mut tx, library_id, &result.media.title)
+            .await
+            .is_ok(),
+    )
+}
+
+/// Function flags the media a mediafile got matched to for review if the search results it was
+/// matched from are ambiguous, storing the best few results as candidates the user can pick from.
+/// Episodes are flagged through their show. Only called when the match created a new media.
+async fn flag_for_review(
+    conn: &DbConnection,
+    mediafile: i64,
+    matches: &[ScoredMatch],
+) -> Result<(), ScannerError> {
+    if !ScoredMatch::is_ambiguous(matches) {
+        return Ok(());
+    }
+
+    let mut lock = conn.writer().lock_owned().await;
+    let mut tx = database::write_tx(&mut lock)
+        .await
+        .map_err(|e| ScannerError::DatabaseError(format!("{:?}", e)))?;
+
+    let media_id = match MediaFile::get_one(&mut tx, mediafile).await?.media_id {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let media_id = match Media::get(&mut tx, media_id).await?.media_type {
+        MediaType::Episode => {
+            let episode = Episode::get_by_id(&mut tx, media_id).await?;
+            Season::get_by_id(&mut tx, episode.seasonid).await?.tvshowid
+        }
+        _ => media_id,
+    };
+
+    let candidates = matches
+        .iter()
+        .take(MAX_CANDIDATES)
+        .map(Into::into)
+        .collect::<Vec<MatchCandidate>>();
+
+    MatchCandidate::set_for_media(&mut tx, media_id, &candidates).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| ScannerError::DatabaseError(format!("{:?}", e)))?;
+
+    info!(
+        media_id = media_id,
+        candidates = candidates.len(),
+        "Flagged ambiguous match for review"
+    );
+
+    Ok(())
+}
+
+/// Title, year and episode information the scanner extracts from a filename.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ParsedFilename {
+    pub title: String,
+    pub year: Option<i64>,
+    pub season: Option<i64>,
+    pub episode: Option<i64>,
+    /// Last episode contained in the file if it spans multiple episodes.
+    pub episode_end: Option<i64>,
+}
+
+/// Function parses the title, year, season and episode out of a filename the same way the scanner
+/// does when mounting a file. The extension is stripped and non ascii characters are dropped
+/// before parsing.
+///
+/// The filename parser can panic on some inputs, thus callers should run this function with
+/// `spawn_blocking`.
+pub fn parse_filename(filename: &str) -> Result<ParsedFilename, ScannerError> {
+    let stem = Path::new(filename)
+        .with_extension("")
+        .file_name()
+        .and_then(|x| x.to_str())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+
+    let metadata = Metadata::from(&stem.replace(|c: char| !c.is_ascii(), ""))
+        .map_err(|_| ScannerError::FilenameParserError)?;
+    let episode_range = parse_episode_range(&stem);
+
+    Ok(ParsedFilename {
+        title: metadata.title().to_owned(),
+        year: metadata.year().map(|x| x as i64),
+        season: metadata.season().map(|x| x as i64),
+        episode: episode_range
+            .map(|(start, _)| start)
+            .or_else(|| metadata.episode().map(|x| x as i64)),
+        episode_end: episode_range.map(|(_, end)| end),
+    })
+}
+
+/// Function runs a second pass over the filename of a episode with anitomy as
+/// `torrent-name-parser` sometimes fails to parse anime filenames. Returns the season and episode
+/// number if anitomy could parse the episode number, the season defaults to 1.
+pub fn parse_anime_episode(filename: &str) -> Option<(i64, i64)> {
+    // FIXME: Use into_ok_or_err when it hits stable.
+    let els: Elements = Anitomy::new().parse(filename).ok()?;
+
+    let episode = els
         .get(ElementCategory::EpisodeNumber)
+        .and_then(|x| x.parse::<i64>().ok())?;
+    let season = els
+        .get(ElementCategory::AnimeSeason)
         .and_then(|x| x.parse::<i64>().ok())
-    {
-        let season = els
-            .get(ElementCategory::AnimeSeason)
-            .and_then(|x| x.parse::<i64>().ok())
-            .or(Some(1));
-
-        let updated_mediafile = UpdateMediaFile {
-            episode: Some(episode),
-            season,
-            ..Default::default()
+        .unwrap_or(1);
+
+    Some((season, episode))
+}
+
+/// Function returns the absolute episode number of filenames that number episodes across all
+/// seasons of a show rather than per season, ie `[Group] Show - 125 [1080p].mkv`, as is common
+/// for anime. Returns `None` if the filename references a season.
+///
+/// The filename parsers can panic on some inputs, thus callers should run this function with
+/// `spawn_blocking`.
+pub fn parse_absolute_episode(filename: &str) -> Option<i64> {
+    // FIXME: Use into_ok_or_err when it hits stable.
+    let els: Elements = Anitomy::new().parse(filename).ok()?;
+
+    if els.get(ElementCategory::AnimeSeason).is_some() {
+        return None;
+    }
+
+    let has_season = Metadata::from(&filename.replace(|c: char| !c.is_ascii(), ""))
+        .map_or(false, |x| x.season().is_some());
+
+    if has_season {
+        return None;
+    }
+
+    els.get(ElementCategory::EpisodeNumber)
+        .and_then(|x| x.parse::<i64>().ok())
+}
+
+/// Function parses the episode range out of filenames of files that contain multiple episodes
+/// such as `S01E01-E02`, `S01E01E02` or `S01E01-02`. Returns `None` if the filename only
+/// references a single episode.
+pub fn parse_episode_range(filename: &str) -> Option<(i64, i64)> {
+    fn take_number(bytes: &[u8], start: usize) -> Option<(i64, usize)> {
+        let end = bytes[start..]
+            .iter()
+            .position(|x| !x.is_ascii_digit())
+            .map(|x| start + x)
+            .unwrap_or(bytes.len());
+
+        if end == start {
+            return None;
+        }
+
+        let number = std::str::from_utf8(&bytes[start..end]).ok()?.parse().ok()?;
+        Some((number, end))
+    }
+
+    let bytes = filename.as_bytes();
+
+    for idx in 0..bytes.len() {
+        if !bytes[idx].eq_ignore_ascii_case(&b's') {
+            continue;
+        }
+
+        let (_, pos) = match take_number(bytes, idx + 1) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if !bytes
+            .get(pos)
+            .map_or(false, |x| x.eq_ignore_ascii_case(&b'e'))
+        {
+            continue;
+        }
+
+        let (start, mut pos) = match take_number(bytes, pos + 1) {
+            Some(x) => x,
+            None => continue,
         };
 
-        let _ = updated_mediafile.update(&mut *tx, media.id).await;
-        media.episode = Some(episode);
-        media.season = season;
+        let dash = bytes.get(pos) == Some(&b'-');
+        if dash {
+            pos += 1;
+        }
+
+        let prefixed = bytes
+            .get(pos)
+            .map_or(false, |x| x.eq_ignore_ascii_case(&b'e'));
+        if prefixed {
+            pos += 1;
+        }
+
+        if !dash && !prefixed {
+            return None;
+        }
+
+        let (end, pos) = take_number(bytes, pos)?;
+
+        // reject things like `S01E01-720p`
+        if bytes.get(pos).map_or(false, |x| x.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        return (end > start).then(|| (start, end));
     }
 
-    Ok(())
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_absolute_episode;
+    use super::parse_episode_range;
+    use super::parse_filename;
+
+    #[test]
+    fn test_parse_episode_range() {
+        assert_eq!(parse_episode_range("Show.S01E01-E02.1080p"), Some((1, 2)));
+        assert_eq!(parse_episode_range("Show S02E03E04"), Some((3, 4)));
+        assert_eq!(parse_episode_range("show.s01e09-10.mkv"), Some((9, 10)));
+        assert_eq!(parse_episode_range("Show.S01E01.1080p"), None);
+        assert_eq!(parse_episode_range("Show.S01E01-720p"), None);
+        assert_eq!(parse_episode_range("Show.S01E02-E01"), None);
+    }
+
+    #[test]
+    fn test_parse_filename() {
+        let parsed = parse_filename("The.Matrix.1999.1080p.BluRay.x264.mkv").unwrap();
+        assert_eq!(parsed.title, "The Matrix");
+        assert_eq!(parsed.year, Some(1999));
+        assert_eq!(parsed.episode, None);
+
+        let parsed = parse_filename("Show.Name.S02E03-E04.720p.mkv").unwrap();
+        assert_eq!(parsed.title, "Show Name");
+        assert_eq!(parsed.season, Some(2));
+        assert_eq!(parsed.episode, Some(3));
+        assert_eq!(parsed.episode_end, Some(4));
+    }
+
+    #[test]
+    fn test_parse_absolute_episode() {
+        assert_eq!(
+            parse_absolute_episode("[Group] Show Name - 125 [1080p].mkv"),
+            Some(125)
+        );
+        assert_eq!(parse_absolute_episode("Show.Name.S02E03.720p.mkv"), None);
+    }
 }
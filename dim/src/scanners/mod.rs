@@ -6,18 +6,26 @@ pub mod tv_show;
 
 use database::library::Library;
 use database::library::MediaType;
+use database::library::MetadataAgent;
 
 use tracing::info;
 use tracing::instrument;
+use tracing::warn;
 
 use crate::core::DbConnection;
 use crate::core::EventTx;
 
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
 use once_cell::sync::OnceCell;
 use walkdir::WalkDir;
 
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Instant;
 
 use serde::Deserialize;
@@ -36,12 +44,69 @@ pub struct ApiMedia {
     pub genres: Vec<String>,
     pub rating: Option<i32>,
     pub seasons: Vec<ApiSeason>,
+    #[serde(default)]
+    pub cast: Vec<ApiCast>,
+    #[serde(default)]
+    pub crew: Vec<ApiCrew>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub alternate_titles: Vec<ApiAlternateTitle>,
+    /// Ids of the media on IMDB and TVDB, `None` if they couldnt be fetched.
+    #[serde(default)]
+    pub external_ids: Option<ApiExternalIds>,
+    /// Runtime in seconds as reported by TMDB.
+    #[serde(default)]
+    pub runtime: Option<u64>,
+    /// Id of the TMDB collection a movie belongs to.
+    #[serde(default)]
+    pub collection_id: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiCast {
+    /// Id of the person on TMDB.
+    pub id: u64,
+    pub name: String,
+    pub character: Option<String>,
+    /// Url of the profile picture of the person.
+    #[serde(default)]
+    pub profile_path: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiCrew {
+    /// Id of the person on TMDB.
+    pub id: u64,
+    pub name: String,
+    /// Job of the person, ie `Director`.
+    pub job: String,
+    /// Url of the profile picture of the person.
+    #[serde(default)]
+    pub profile_path: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiAlternateTitle {
+    /// Country the title is used in, ie `FR`.
+    pub country: String,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiExternalIds {
+    /// Id of the media on IMDB, ie `tt0133093`.
+    pub imdb_id: Option<String>,
+    /// Id of the media on TVDB, only known for tv shows.
+    pub tvdb_id: Option<u64>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ApiSeason {
     pub id: u64,
     pub name: Option<String>,
+    pub overview: Option<String>,
+    pub air_date: Option<String>,
     pub poster_path: Option<String>,
     pub poster_file: Option<String>,
     pub season_number: u64,
@@ -56,12 +121,207 @@ pub struct ApiEpisode {
     pub episode: Option<u64>,
     pub still: Option<String>,
     pub still_file: Option<String>,
+    #[serde(default)]
+    pub air_date: Option<String>,
 }
 
 pub(super) static METADATA_EXTRACTOR: OnceCell<base::MetadataExtractor> = OnceCell::new();
 pub(super) static METADATA_MATCHER: OnceCell<base::MetadataMatcher> = OnceCell::new();
 pub(super) static SUPPORTED_EXTS: &[&str] = &["mp4", "mkv", "avi", "webm"];
 
+/// Ids of the libraries for which a full scan is currently running.
+static RUNNING_SCANS: Lazy<Mutex<HashSet<i64>>> = Lazy::new(Default::default);
+
+/// Guard which marks a library as being scanned for as long as it is alive.
+pub struct ScanGuard(i64);
+
+impl ScanGuard {
+    /// Marks the library as being scanned. Returns `None` if a scan is already running for it.
+    pub fn acquire(library_id: i64) -> Option<Self> {
+        let mut lock = RUNNING_SCANS.lock().unwrap();
+        if lock.insert(library_id) {
+            Some(Self(library_id))
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        RUNNING_SCANS.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Phase a library scan is in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanPhase {
+    /// The library directories are walked to find the files to scan.
+    Walking,
+    /// The files found are probed and matched.
+    Processing,
+}
+
+/// Progress of the scans currently running for a library. Scans running concurrently for the
+/// same library, ie a full rescan and a directory picked up by the fs watcher, are reported as one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScanProgress {
+    scans: usize,
+    walking: usize,
+    /// Number of files processed so far.
+    pub processed: usize,
+    /// Number of files found so far.
+    pub total: usize,
+}
+
+impl ScanProgress {
+    /// Returns the phase of the scan, scans are walking until all of them found their files.
+    pub fn phase(&self) -> ScanPhase {
+        if self.walking > 0 {
+            ScanPhase::Walking
+        } else {
+            ScanPhase::Processing
+        }
+    }
+
+    /// Returns the percentage of files processed.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        self.processed as f64 / self.total as f64 * 100.0
+    }
+}
+
+/// Progress of the running scans keyed by library id.
+static SCAN_PROGRESS: Lazy<Mutex<HashMap<i64, ScanProgress>>> = Lazy::new(Default::default);
+
+/// Guard which reports the progress of a scan for as long as it is alive.
+struct ProgressGuard {
+    library_id: i64,
+    walking: bool,
+}
+
+impl ProgressGuard {
+    fn start(library_id: i64) -> Self {
+        let mut lock = SCAN_PROGRESS.lock().unwrap();
+        let progress = lock.entry(library_id).or_default();
+        progress.scans += 1;
+        progress.walking += 1;
+
+        Self {
+            library_id,
+            walking: true,
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ScanProgress)) {
+        if let Some(progress) = SCAN_PROGRESS.lock().unwrap().get_mut(&self.library_id) {
+            f(progress);
+        }
+    }
+
+    /// Marks the directories as walked, `files` were found.
+    fn walked(&mut self, files: usize) {
+        self.walking = false;
+        self.update(|x| {
+            x.walking -= 1;
+            x.total += files;
+        });
+    }
+
+    /// Marks another file as processed.
+    fn processed(&self) {
+        self.update(|x| x.processed += 1);
+    }
+}
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        let mut lock = SCAN_PROGRESS.lock().unwrap();
+
+        if let Some(progress) = lock.get_mut(&self.library_id) {
+            progress.scans -= 1;
+
+            if self.walking {
+                progress.walking -= 1;
+            }
+
+            if progress.scans == 0 {
+                lock.remove(&self.library_id);
+            }
+        }
+    }
+}
+
+/// Returns the progress of the scans running for a library, `None` if the library isnt being
+/// scanned.
+pub fn scan_progress(library_id: i64) -> Option<ScanProgress> {
+    SCAN_PROGRESS.lock().unwrap().get(&library_id).cloned()
+}
+
+/// Locks held for media whose metadata is currently being written, keyed by media id.
+static MEDIA_LOCKS: Lazy<Mutex<HashMap<i64, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+/// Guard which gives exclusive access to the metadata of a media for as long as it is alive.
+/// Rematches and refreshes must hold it while writing metadata so that two of them targeting the
+/// same media cant interleave their writes.
+pub struct MediaLock {
+    id: i64,
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl MediaLock {
+    /// Waits until no other task holds the lock for the media and acquires it.
+    pub async fn acquire(media_id: i64) -> Self {
+        let lock = MEDIA_LOCKS
+            .lock()
+            .unwrap()
+            .entry(media_id)
+            .or_default()
+            .clone();
+
+        Self {
+            id: media_id,
+            guard: Some(lock.lock_owned().await),
+        }
+    }
+
+    /// Acquires the lock for the media. Returns `None` if another task already holds it.
+    pub fn try_acquire(media_id: i64) -> Option<Self> {
+        let mut locks = MEDIA_LOCKS.lock().unwrap();
+        let lock = locks.entry(media_id).or_default().clone();
+
+        lock.try_lock_owned().ok().map(|guard| Self {
+            id: media_id,
+            guard: Some(guard),
+        })
+    }
+}
+
+impl Drop for MediaLock {
+    fn drop(&mut self) {
+        let mut locks = MEDIA_LOCKS.lock().unwrap();
+        self.guard.take();
+
+        // nobody is waiting for the lock anymore, so we can forget about it.
+        if locks
+            .get(&self.id)
+            .map_or(false, |x| Arc::strong_count(x) == 1)
+        {
+            locks.remove(&self.id);
+        }
+    }
+}
+
+/// Returns the number of library scans currently running.
+pub fn running_scans() -> usize {
+    RUNNING_SCANS.lock().unwrap().len()
+}
+
 pub fn get_extractor(_tx: &EventTx) -> &'static base::MetadataExtractor {
     let mut handle = xtra::spawn::Tokio::Global;
 
@@ -81,6 +341,85 @@ pub fn get_matcher_unchecked() -> &'static base::MetadataMatcher {
     METADATA_MATCHER.get().unwrap()
 }
 
+/// Source of metadata that media are matched against and refreshed from. Scanners and handlers
+/// only talk to metadata agents through this trait, such that libraries can pick their agent.
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    /// Method searches for `title` and returns the results scored by how confident we are that
+    /// they are what was searched for, best match first.
+    async fn search_scored(
+        &mut self,
+        title: String,
+        year: Option<i32>,
+    ) -> Result<Vec<tmdb::ScoredMatch>, tmdb::TmdbError>;
+
+    /// Method returns the media with the id `id`.
+    async fn search_by_id(&mut self, id: i32) -> Result<tmdb::Media, tmdb::TmdbError>;
+
+    /// Method returns the seasons of the show `id`.
+    async fn get_seasons_for(&mut self, id: u64) -> Result<Vec<tmdb::Season>, tmdb::TmdbError>;
+
+    /// Method returns the episodes of the season `season` of the show `id`.
+    async fn get_episodes_for(
+        &mut self,
+        id: u64,
+        season: u64,
+    ) -> Result<Vec<tmdb::Episode>, tmdb::TmdbError>;
+
+    /// Method returns the trailers, teasers and clips of the media `id`.
+    async fn get_videos_for(&mut self, id: u64) -> Result<Vec<tmdb::Video>, tmdb::TmdbError>;
+
+    /// Method returns the cast and crew of the media `id`.
+    async fn get_credits_for(&mut self, id: u64) -> Result<tmdb::Credits, tmdb::TmdbError>;
+
+    /// Method returns the keywords of the media `id`.
+    async fn get_keywords_for(&mut self, id: u64) -> Result<Vec<tmdb::Keyword>, tmdb::TmdbError>;
+
+    /// Method returns the alternate titles of the media `id`.
+    async fn get_alternate_titles_for(
+        &mut self,
+        id: u64,
+    ) -> Result<Vec<tmdb::AlternateTitle>, tmdb::TmdbError>;
+
+    /// Method returns the ids of the media `id` on other databases.
+    async fn get_external_ids_for(&mut self, id: u64)
+        -> Result<tmdb::ExternalIds, tmdb::TmdbError>;
+
+    /// Method returns the runtime of the media `id` in minutes.
+    async fn get_runtime_for(&mut self, id: u64) -> Result<u64, tmdb::TmdbError>;
+
+    /// Method returns the collection `id` and the movies that are part of it.
+    async fn get_collection(&mut self, id: u64) -> Result<tmdb::Collection, tmdb::TmdbError>;
+}
+
+/// TMDB clients keyed by media type, along with the timeout they were built with. Clients are
+/// shared such that their connections are reused across matches.
+static TMDB_CLIENTS: Lazy<Mutex<HashMap<MediaType, (u64, tmdb::Tmdb)>>> =
+    Lazy::new(Default::default);
+
+/// Returns a client of the metadata agent `agent` used to match and refresh media of
+/// `media_type`. Clients are only rebuilt if the TMDB timeout in the global settings changed.
+pub fn metadata_agent(agent: MetadataAgent, media_type: MediaType) -> Box<dyn MetadataProvider> {
+    match agent {
+        MetadataAgent::Tmdb => Box::new(tmdb_client(media_type)),
+    }
+}
+
+/// Returns the shared TMDB client for `media_type`, see [`metadata_agent`](metadata_agent).
+pub fn tmdb_client(media_type: MediaType) -> tmdb::Tmdb {
+    let timeout = crate::routes::settings::get_global_settings().tmdb_timeout_secs;
+    let mut clients = TMDB_CLIENTS.lock().unwrap();
+
+    match clients.get(&media_type) {
+        Some((x, client)) if *x == timeout => client.clone(),
+        _ => {
+            let client = tmdb::Tmdb::new("38c372f5bc572c8aadde7a802638534e".into(), media_type);
+            clients.insert(media_type, (timeout, client.clone()));
+            client
+        }
+    }
+}
+
 #[doc(hidden)]
 pub async fn get_subfiles(
     paths: impl Iterator<Item = impl AsRef<Path>>,
@@ -136,12 +475,16 @@ where
     )
     .unwrap();
 
+    let mut progress = ProgressGuard::start(library_id);
+
     let extractor = get_extractor(&tx);
     let matcher = get_matcher(&tx);
 
     let files = get_subfiles(paths).await?;
 
     let total_files = files.len();
+    progress.walked(total_files);
+    let progress = &progress;
 
     info!(
         library_id = library_id,
@@ -168,11 +511,20 @@ where
                     _ => unreachable!(),
                 }
             }
+
+            progress.processed();
         })
     }
 
     futures::future::join_all(futures).await;
 
+    if crate::tasks::is_shutting_down() {
+        warn!(
+            library_id = library_id,
+            "Scan was interrupted by shutdown, remaining files are picked up by the next scan",
+        );
+    }
+
     info!(
         library_id = library_id,
         files = total_files,
@@ -197,6 +549,18 @@ pub async fn start(
     id: i64,
     tx: EventTx,
 ) -> Result<(), self::base::ScannerError> {
+    let guard = ScanGuard::acquire(id).ok_or(self::base::ScannerError::ScanInProgress)?;
+    start_guarded(conn, guard, tx).await
+}
+
+/// Function runs a full scan of a library for which a [`ScanGuard`](ScanGuard) has already been
+/// acquired. The library is marked as not being scanned anymore once the scan finishes.
+pub async fn start_guarded(
+    conn: DbConnection,
+    guard: ScanGuard,
+    tx: EventTx,
+) -> Result<(), self::base::ScannerError> {
+    let id = guard.0;
     let mut tx_ = conn
         .read()
         .begin()
@@ -208,8 +572,132 @@ pub async fn start(
     start_custom(id, tx, lib.locations.into_iter(), lib.media_type).await
 }
 
+/// Returns whether a library may be located at `path`. Libraries must be located in one of
+/// `roots` unless none are configured. Paths are resolved before comparing them so that neither
+/// `..` nor symlinks can be used to escape the roots, relative paths are never allowed.
+pub fn is_allowed_location(path: &Path, roots: &[String]) -> bool {
+    if !path.is_absolute()
+        || path
+            .components()
+            .any(|x| matches!(x, std::path::Component::ParentDir))
+    {
+        return false;
+    }
+
+    if roots.is_empty() {
+        return true;
+    }
+
+    let resolve = |x: &Path| x.canonicalize().unwrap_or_else(|_| x.to_path_buf());
+    let path = resolve(path);
+
+    roots
+        .iter()
+        .any(|root| path.starts_with(resolve(Path::new(root))))
+}
+
 /// Function formats the path where assets are stored.
 pub fn format_path(x: Option<String>) -> String {
     x.map(|x| format!("images/{}", x.trim_start_matches('/')))
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_allowed_location;
+    use super::scan_progress;
+    use super::MediaLock;
+    use super::ProgressGuard;
+    use super::ScanPhase;
+
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Simulates a rematch which reads the current metadata of a media, fetches the new metadata
+    /// and then writes it back.
+    async fn rematch(metadata: Arc<Mutex<String>>, id: i64, suffix: &'static str) {
+        let _lock = MediaLock::acquire(id).await;
+
+        let name = metadata.lock().unwrap().clone();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        *metadata.lock().unwrap() = format!("{}{}", name, suffix);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_rematch() {
+        let metadata = Arc::new(Mutex::new("media".to_string()));
+
+        for _ in 0..10 {
+            *metadata.lock().unwrap() = "media".into();
+
+            let first = tokio::spawn(rematch(metadata.clone(), 1, "-a"));
+            let second = tokio::spawn(rematch(metadata.clone(), 1, "-b"));
+            first.await.unwrap();
+            second.await.unwrap();
+
+            // both writes must be visible, neither rematch may overwrite the other one.
+            let name = metadata.lock().unwrap().clone();
+            assert!(name == "media-a-b" || name == "media-b-a", "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire() {
+        let lock = MediaLock::try_acquire(-1).unwrap();
+        assert!(MediaLock::try_acquire(-1).is_none());
+        assert!(MediaLock::try_acquire(-2).is_some());
+
+        drop(lock);
+        assert!(MediaLock::try_acquire(-1).is_some());
+    }
+
+    #[test]
+    fn test_is_allowed_location() {
+        let root = std::env::temp_dir();
+        let roots = vec![root.to_string_lossy().to_string()];
+
+        assert!(is_allowed_location(&root.join("movies"), &[]));
+        assert!(is_allowed_location(&root.join("movies"), &roots));
+        assert!(!is_allowed_location(Path::new("movies"), &[]));
+
+        // paths can neither traverse out of a root nor be outside of every root.
+        assert!(!is_allowed_location(&root.join("..").join("etc"), &roots));
+        assert!(!is_allowed_location(&root.join("movies/../.."), &[]));
+        assert!(!is_allowed_location(
+            Path::new("/dim-library-root-test"),
+            &roots
+        ));
+    }
+
+    #[test]
+    fn test_scan_progress() {
+        assert!(scan_progress(-1).is_none());
+
+        let mut full = ProgressGuard::start(-1);
+        assert_eq!(scan_progress(-1).unwrap().phase(), ScanPhase::Walking);
+
+        full.walked(3);
+        full.processed();
+        let progress = scan_progress(-1).unwrap();
+        assert_eq!(progress.phase(), ScanPhase::Processing);
+        assert_eq!((progress.processed, progress.total), (1, 3));
+
+        // concurrent scans of the same library are aggregated.
+        let mut partial = ProgressGuard::start(-1);
+        assert_eq!(scan_progress(-1).unwrap().phase(), ScanPhase::Walking);
+        partial.walked(1);
+        partial.processed();
+        full.processed();
+        assert_eq!(scan_progress(-1).unwrap().percent(), 75.0);
+
+        // other libraries report independently.
+        assert!(scan_progress(-2).is_none());
+
+        drop(partial);
+        assert!(scan_progress(-1).is_some());
+        drop(full);
+        assert!(scan_progress(-1).is_none());
+    }
+}
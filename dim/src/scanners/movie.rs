@@ -1,13 +1,19 @@
+use database::alternate_title::InsertableAlternateTitle;
 use database::asset::InsertableAsset;
 use database::genre::InsertableGenre;
 use database::genre::InsertableGenreMedia;
+use database::keyword::InsertableKeyword;
 use database::movie::InsertableMovie;
 use database::DbConnection;
 
 use database::library::MediaType;
+use database::media::ExternalIds;
 use database::media::InsertableMedia;
+use database::media::Media;
 use database::mediafile::MediaFile;
 use database::mediafile::UpdateMediaFile;
+use database::person::InsertablePerson;
+use database::person::Person;
 
 use chrono::prelude::Utc;
 use chrono::Datelike;
@@ -57,6 +63,7 @@ impl<'a> MovieMatcher<'a> {
         }
 
         self.push_event(media_id, library_id).await;
+        crate::accent_color::queue(self.conn.clone(), media_id);
     }
 
     pub async fn inner_match(
@@ -171,11 +178,46 @@ impl<'a> MovieMatcher<'a> {
         tx: &mut database::Transaction<'_>,
         reuse_media_id: Option<i64>,
     ) -> Result<i64, super::base::ScannerError> {
+        let existing = Media::get_id_by_tmdb_id(
+            &mut *tx,
+            media.library_id,
+            result.id as i64,
+            MediaType::Movie,
+        )
+        .await?;
+
         let media_id = if let Some(id) = reuse_media_id {
             media.insert_with_id(&mut *tx, id).await?
+        } else if let Some(id) = existing {
+            // another file of the same media has already been matched, thus we attach this file
+            // to the existing media instead of creating a duplicate.
+            id
         } else {
             media.insert(&mut *tx).await?
         };
+
+        Media::set_tmdb_id(&mut *tx, media_id, result.id as i64).await?;
+
+        if let Some(runtime) = result.runtime {
+            Media::set_tmdb_runtime(&mut *tx, media_id, runtime as i64).await?;
+        }
+
+        if let Some(ids) = result.external_ids {
+            let ids = ExternalIds {
+                imdb_id: ids.imdb_id,
+                tvdb_id: ids.tvdb_id.map(|x| x as i64),
+            };
+
+            Media::set_external_ids(&mut *tx, media_id, &ids).await?;
+        }
+
+        Media::set_collection_id(&mut *tx, media_id, result.collection_id.map(|x| x as i64))
+            .await?;
+
+        // if this media was added manually as a placeholder we reuse it instead of creating a
+        // duplicate entry.
+        Media::reconcile_placeholder(&mut *tx, media_id).await?;
+
         // the reason we ignore the result here is that in some cases this can fail. Specifically when there are multiple mediafiles for a movie.
         let _ = InsertableMovie::insert(&mut *tx, media_id).await;
 
@@ -187,6 +229,53 @@ impl<'a> MovieMatcher<'a> {
             }
         }
 
+        for name in result.keywords {
+            let _ = InsertableKeyword { name }
+                .insert_for_media(&mut *tx, media_id)
+                .await;
+        }
+
+        for title in result.alternate_titles {
+            let _ = InsertableAlternateTitle {
+                country: title.country,
+                title: title.title,
+            }
+            .insert_for_media(&mut *tx, media_id)
+            .await;
+        }
+
+        // the media might have been matched before, thus we drop its old cast and crew so that
+        // people who arent credited anymore dont linger. Either is kept if it couldnt be fetched.
+        if !result.cast.is_empty() {
+            let _ = Person::clear_cast(&mut *tx, media_id).await;
+        }
+
+        if !result.crew.is_empty() {
+            let _ = Person::clear_crew(&mut *tx, media_id).await;
+        }
+
+        for (ordering, cast) in result.cast.into_iter().enumerate() {
+            let person = InsertablePerson {
+                tmdb_id: cast.id as i64,
+                name: cast.name,
+                profile_path: cast.profile_path,
+            };
+
+            let _ = person
+                .insert_cast(&mut *tx, media_id, cast.character, ordering as i64)
+                .await;
+        }
+
+        for crew in result.crew {
+            let person = InsertablePerson {
+                tmdb_id: crew.id as i64,
+                name: crew.name,
+                profile_path: crew.profile_path,
+            };
+
+            let _ = person.insert_crew(&mut *tx, media_id, &crew.job).await;
+        }
+
         let updated_mediafile = UpdateMediaFile {
             media_id: Some(media_id),
             ..Default::default()
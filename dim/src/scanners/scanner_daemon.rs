@@ -31,6 +31,7 @@ pub enum FsWatcherError {
     NotifyError(#[source] notify::Error),
 }
 
+#[derive(Clone)]
 pub struct FsWatcher {
     media_type: MediaType,
     library_id: i64,
@@ -68,12 +69,25 @@ impl FsWatcher {
 
         let (mut rx, _watcher) = async_watch(library.locations.iter())?;
 
+        // Events are handled one at a time on the task pool so that shutdown drains the event
+        // being handled instead of aborting it mid-write.
         while let Some(e) = rx.recv().await {
-            match e {
-                DebouncedEvent::Create(path) => self.handle_create(path).await,
-                DebouncedEvent::Rename(from, to) => self.handle_rename(from, to).await,
-                DebouncedEvent::Remove(path) => self.handle_remove(path).await,
-                event => debug!("Tried to handle unmatched event {:?}", event),
+            if crate::tasks::is_shutting_down() {
+                break;
+            }
+
+            let watcher = self.clone();
+            let handled = crate::tasks::run(
+                format!("Handle filesystem event in library {}", self.library_id),
+                async move {
+                    watcher.handle_event(e).await;
+                    Ok(())
+                },
+            )
+            .await;
+
+            if handled.is_err() {
+                break;
             }
         }
 
@@ -82,6 +96,15 @@ impl FsWatcher {
         Ok(())
     }
 
+    async fn handle_event(&self, event: DebouncedEvent) {
+        match event {
+            DebouncedEvent::Create(path) => self.handle_create(path).await,
+            DebouncedEvent::Rename(from, to) => self.handle_rename(from, to).await,
+            DebouncedEvent::Remove(path) => self.handle_remove(path).await,
+            event => debug!("Tried to handle unmatched event {:?}", event),
+        }
+    }
+
     async fn handle_create(&self, path: PathBuf) {
         debug!("Received handle_create event type: {:?}", path);
 
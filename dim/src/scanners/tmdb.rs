@@ -3,8 +3,13 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
+use std::time::Instant;
 
 use reqwest::Client;
 use reqwest::ClientBuilder;
@@ -16,9 +21,46 @@ use futures::StreamExt;
 use tokio::sync::RwLock;
 
 use async_recursion::async_recursion;
+use async_trait::async_trait;
+
+use super::MetadataProvider;
 
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
+/// Connect and read timeout used for requests to TMDB when none is configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval after which the result of the last reachability probe of TMDB is considered stale.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+/// Number of billed cast members stored for a media.
+pub const MAX_CAST: usize = 20;
+/// Jobs of the crew of a media that are stored, the rest of the crew is dropped.
+pub const CREW_JOBS: &[&str] = &[
+    "Director",
+    "Screenplay",
+    "Writer",
+    "Novel",
+    "Creator",
+    "Producer",
+    "Original Music Composer",
+];
+/// Search results whose confidence is within this margin of the best result are considered
+/// equally good matches.
+pub const AMBIGUITY_MARGIN: f64 = 0.1;
+
+type SearchCacheKey = (String, Option<i32>, MediaType);
+
+lazy_static::lazy_static! {
+    static ref SEARCH_CACHE: Arc<RwLock<HashMap<SearchCacheKey, Vec<Media>>>> = Arc::new(RwLock::new(HashMap::new()));
+    static ref VIDEO_CACHE: Arc<RwLock<HashMap<(u64, MediaType), Vec<Video>>>> = Arc::new(RwLock::new(HashMap::new()));
+    static ref GENRE_CACHE: Arc<RwLock<HashMap<MediaType, Vec<Genre>>>> = Arc::new(RwLock::new(HashMap::new()));
+    static ref COLLECTION_CACHE: Arc<RwLock<HashMap<u64, Collection>>> = Arc::new(RwLock::new(HashMap::new()));
+    /// Result of the last reachability probe along with when it was taken.
+    static ref LAST_PROBE: Mutex<Option<(bool, Instant)>> = Mutex::new(None);
+}
+
+/// Whether a reachability probe is currently in flight.
+static PROBING: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug, Error, Serialize)]
 pub enum TmdbError {
     #[error(display = "The request timeouted")]
@@ -37,6 +79,30 @@ pub enum TmdbError {
     NoEpisodesFound { id: u64, season: u64 },
     #[error(display = "Could not find genre with supplied id")]
     NoGenreFound { id: u64 },
+    #[error(display = "No videos found for the id supplied")]
+    NoVideosFound { id: u64 },
+    #[error(display = "No cast found for the id supplied")]
+    NoCastFound { id: u64 },
+    #[error(display = "No keywords found for the id supplied")]
+    NoKeywordsFound { id: u64 },
+    #[error(display = "No runtime found for the id supplied")]
+    NoRuntimeFound { id: u64 },
+    #[error(display = "No alternate titles found for the id supplied")]
+    NoAlternateTitlesFound { id: u64 },
+    #[error(display = "No collection found for the id supplied")]
+    NoCollectionFound { id: u64 },
+    #[error(display = "No external ids found for the id supplied")]
+    NoExternalIdsFound { id: u64 },
+}
+
+impl From<reqwest::Error> for TmdbError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::ReqwestError
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -48,8 +114,19 @@ pub struct Tmdb {
 }
 
 impl Tmdb {
+    /// Creates a new TMDB client using the timeout configured in the global settings.
     pub fn new(api_key: String, media_type: MediaType) -> Self {
-        let client = ClientBuilder::new().user_agent(APP_USER_AGENT);
+        let timeout = crate::routes::settings::get_global_settings().tmdb_timeout_secs;
+        Self::with_timeout(api_key, media_type, Duration::from_secs(timeout))
+    }
+
+    /// Creates a new TMDB client whose requests fail with [`TmdbError::Timeout`] if TMDB doesn't
+    /// accept the connection or respond within `timeout`.
+    pub fn with_timeout(api_key: String, media_type: MediaType, timeout: Duration) -> Self {
+        let client = ClientBuilder::new()
+            .user_agent(APP_USER_AGENT)
+            .connect_timeout(timeout)
+            .timeout(timeout);
 
         Self {
             api_key,
@@ -59,17 +136,108 @@ impl Tmdb {
         }
     }
 
+    /// Method clears all cached TMDB responses and returns the number of entries removed. This
+    /// is useful after metadata was corrected on TMDB.
+    pub async fn clear_cache() -> usize {
+        let mut cleared = 0;
+
+        {
+            let mut lock = (*SEARCH_CACHE).write().await;
+            cleared += lock.len();
+            lock.clear();
+        }
+
+        {
+            let mut lock = (*VIDEO_CACHE).write().await;
+            cleared += lock.len();
+            lock.clear();
+        }
+
+        {
+            let mut lock = (*GENRE_CACHE).write().await;
+            cleared += lock.len();
+            lock.clear();
+        }
+
+        {
+            let mut lock = (*COLLECTION_CACHE).write().await;
+            cleared += lock.len();
+            lock.clear();
+        }
+
+        cleared
+    }
+
+    /// Method returns whether TMDB was reachable when it was last probed, `None` if it was never
+    /// probed. If the last result is older than [`PROBE_INTERVAL`](PROBE_INTERVAL) a new probe is
+    /// started in the background, thus this never waits on TMDB.
+    pub fn is_reachable(&self) -> Option<bool> {
+        let last = *LAST_PROBE.lock().unwrap();
+
+        if last.map_or(true, |(_, at)| at.elapsed() >= PROBE_INTERVAL)
+            && !PROBING.swap(true, Ordering::AcqRel)
+        {
+            let this = self.clone();
+            tokio::spawn(async move {
+                let reachable = this.probe().await;
+                *LAST_PROBE.lock().unwrap() = Some((reachable, Instant::now()));
+                PROBING.store(false, Ordering::Release);
+            });
+        }
+
+        last.map(|(reachable, _)| reachable)
+    }
+
+    async fn probe(&self) -> bool {
+        let args = vec![("api_key".to_string(), self.api_key.clone())];
+
+        self.client
+            .get(format!("{}/configuration", self.base))
+            .query(&args)
+            .send()
+            .await
+            .map_or(false, |x| x.status().is_success())
+    }
+
     pub async fn search(
         &mut self,
         title: String,
         year: Option<i32>,
     ) -> Result<super::ApiMedia, TmdbError> {
-        self.search_by_name(title.clone(), year, None)
+        Ok(self
+            .search_scored(title, year)
             .await?
-            .first()
-            .cloned()
-            .map(Into::into)
-            .ok_or(TmdbError::NoResults { query: title, year })
+            .remove(0)
+            .media
+            .into())
+    }
+
+    /// Method searches TMDB like [`search_by_name`](Tmdb::search_by_name) and scores every result
+    /// by how confident we are that it is what was searched for, best match first. Results with
+    /// the same confidence keep the order TMDB returned them in. Returns
+    /// [`TmdbError::NoResults`] if nothing was found.
+    pub async fn search_scored(
+        &mut self,
+        title: String,
+        year: Option<i32>,
+    ) -> Result<Vec<ScoredMatch>, TmdbError> {
+        let mut results = self
+            .search_by_name(title.clone(), year, None)
+            .await?
+            .into_iter()
+            .map(|media| ScoredMatch {
+                confidence: media.confidence(&title, year),
+                media,
+            })
+            .collect::<Vec<_>>();
+
+        if results.is_empty() {
+            return Err(TmdbError::NoResults { query: title, year });
+        }
+
+        results.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+
+        Ok(results)
     }
 
     pub async fn search_by_id(&mut self, id: i32) -> Result<Media, TmdbError> {
@@ -79,13 +247,7 @@ impl Tmdb {
         ];
 
         let url = format!("{}/{}/{}", self.base, self.media_type, id);
-        let req = self
-            .client
-            .get(url)
-            .query(&args)
-            .send()
-            .await
-            .map_err(|_| TmdbError::ReqwestError)?;
+        let req = self.client.get(url).query(&args).send().await?;
 
         #[derive(Deserialize, Clone, Debug)]
         struct WMedia {
@@ -99,6 +261,15 @@ impl Tmdb {
             pub poster_path: Option<String>,
             pub backdrop_path: Option<String>,
             pub genres: Vec<GenrePair>,
+            pub runtime: Option<u64>,
+            #[serde(default)]
+            pub episode_run_time: Vec<u64>,
+            pub belongs_to_collection: Option<CollectionRef>,
+        }
+
+        #[derive(Deserialize, Clone, Debug)]
+        struct CollectionRef {
+            pub id: u64,
         }
 
         #[derive(Deserialize, Clone, Debug)]
@@ -122,6 +293,11 @@ impl Tmdb {
             poster_path: result.poster_path,
             backdrop_path: result.backdrop_path,
             genre_ids: None,
+            // tv shows only report the runtime of their episodes.
+            runtime: result
+                .runtime
+                .or_else(|| result.episode_run_time.first().copied()),
+            collection_id: result.belongs_to_collection.map(|x| x.id),
             genres: result
                 .genres
                 .into_iter()
@@ -137,15 +313,8 @@ impl Tmdb {
         year: Option<i32>,
         max_tries: Option<usize>,
     ) -> Result<Vec<Media>, TmdbError> {
-        type CacheKey = (String, Option<i32>, MediaType);
-        type CacheStore = Arc<RwLock<HashMap<CacheKey, Vec<Media>>>>;
-
-        lazy_static::lazy_static! {
-            static ref __CACHE: CacheStore = Arc::new(RwLock::new(HashMap::new()));
-        }
-
         {
-            let lock = (*__CACHE).read().await;
+            let lock = (*SEARCH_CACHE).read().await;
             let key = (title.clone(), year, self.media_type);
 
             if let Some(x) = lock.get(&key) {
@@ -173,13 +342,7 @@ impl Tmdb {
 
         let url = format!("{}/search/{}", self.base, self.media_type);
 
-        let req = self
-            .client
-            .get(url)
-            .query(&args)
-            .send()
-            .await
-            .map_err(|_| TmdbError::ReqwestError)?;
+        let req = self.client.get(url).query(&args).send().await?;
 
         if matches!(req.status(), StatusCode::TOO_MANY_REQUESTS) {
             tokio::time::sleep(Duration::from_millis(1000)).await;
@@ -199,13 +362,7 @@ impl Tmdb {
             let ids = media.genre_ids.clone().unwrap_or_default();
             media.genres = stream::iter(ids)
                 .filter_map(|x| {
-                    let client = ClientBuilder::new().user_agent(APP_USER_AGENT);
-                    let mut this = Tmdb {
-                        api_key: self.api_key.clone(),
-                        client: client.build().unwrap(),
-                        base: self.base.clone(),
-                        media_type: self.media_type,
-                    };
+                    let mut this = self.clone();
 
                     async move { this.get_genre_detail(x).await.ok().map(|x| x.name) }
                 })
@@ -214,7 +371,7 @@ impl Tmdb {
         }
 
         {
-            let mut lock = (*__CACHE).write().await;
+            let mut lock = (*SEARCH_CACHE).write().await;
             let key = (title.clone(), year, self.media_type);
             lock.insert(key, result.clone());
         }
@@ -230,8 +387,7 @@ impl Tmdb {
             .get(format!("{}/tv/{}", self.base, id))
             .query(&args)
             .send()
-            .await
-            .map_err(|_| TmdbError::ReqwestError)?;
+            .await?;
 
         #[derive(Deserialize)]
         struct Wrapper {
@@ -257,8 +413,7 @@ impl Tmdb {
             .get(format!("{}/tv/{}/season/{}", self.base, id, season))
             .query(&args)
             .send()
-            .await
-            .map_err(|_| TmdbError::ReqwestError)?;
+            .await?;
 
         #[derive(Deserialize)]
         struct Wrapper {
@@ -272,13 +427,237 @@ impl Tmdb {
             .ok_or(TmdbError::NoEpisodesFound { id, season })
     }
 
-    pub async fn get_genre_detail(&mut self, genre_id: u64) -> Result<Genre, TmdbError> {
-        lazy_static::lazy_static! {
-            static ref __CACHE: Arc<RwLock<HashMap<MediaType, Vec<Genre>>>> = Arc::new(RwLock::new(HashMap::new()));
+    /// Method returns the videos (trailers, clips, teasers etc) TMDB has for a media. Official
+    /// trailers are ordered first. Results are cached until [`Tmdb::clear_cache`] is called.
+    pub async fn get_videos_for(&mut self, id: u64) -> Result<Vec<Video>, TmdbError> {
+        {
+            let lock = (*VIDEO_CACHE).read().await;
+            if let Some(x) = lock.get(&(id, self.media_type)) {
+                return Ok(x.clone());
+            }
         }
 
+        let args = vec![
+            ("api_key".to_string(), self.api_key.clone()),
+            ("language".to_string(), "en-US".into()),
+        ];
+
+        let req = self
+            .client
+            .get(format!("{}/{}/{}/videos", self.base, self.media_type, id))
+            .query(&args)
+            .send()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            results: Option<Vec<Video>>,
+        }
+
+        let mut videos = req
+            .json::<Wrapper>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?
+            .results
+            .ok_or(TmdbError::NoVideosFound { id })?;
+
+        videos.sort_by_key(|x| (x.video_type != "Trailer", !x.official));
+
         {
-            let lock = (*__CACHE).read().await;
+            let mut lock = (*VIDEO_CACHE).write().await;
+            lock.insert((id, self.media_type), videos.clone());
+        }
+
+        Ok(videos)
+    }
+
+    /// Method returns the credits of a media. The cast is the top billed cast in billing order,
+    /// at most [`MAX_CAST`](MAX_CAST) people. The crew only holds people whose job is one of
+    /// [`CREW_JOBS`](CREW_JOBS).
+    pub async fn get_credits_for(&mut self, id: u64) -> Result<Credits, TmdbError> {
+        let args = vec![
+            ("api_key".to_string(), self.api_key.clone()),
+            ("language".to_string(), "en-US".into()),
+        ];
+
+        let req = self
+            .client
+            .get(format!("{}/{}/{}/credits", self.base, self.media_type, id))
+            .query(&args)
+            .send()
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            cast: Option<Vec<Cast>>,
+            #[serde(default)]
+            crew: Vec<Crew>,
+        }
+
+        let credits = req
+            .json::<Wrapper>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?;
+
+        let mut cast = credits.cast.ok_or(TmdbError::NoCastFound { id })?;
+
+        cast.sort_by_key(|x| x.order.unwrap_or(u64::MAX));
+        cast.truncate(MAX_CAST);
+
+        let crew = credits
+            .crew
+            .into_iter()
+            .filter(|x| CREW_JOBS.contains(&x.job.as_str()))
+            .collect();
+
+        Ok(Credits { cast, crew })
+    }
+
+    /// Method returns the keywords a media is tagged with on TMDB, ie "time travel".
+    pub async fn get_keywords_for(&mut self, id: u64) -> Result<Vec<Keyword>, TmdbError> {
+        let args = vec![("api_key".to_string(), self.api_key.clone())];
+
+        let req = self
+            .client
+            .get(format!("{}/{}/{}/keywords", self.base, self.media_type, id))
+            .query(&args)
+            .send()
+            .await?;
+
+        // movies list their keywords under `keywords` while tv shows list them under `results`.
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(alias = "results")]
+            keywords: Option<Vec<Keyword>>,
+        }
+
+        req.json::<Wrapper>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?
+            .keywords
+            .ok_or(TmdbError::NoKeywordsFound { id })
+    }
+
+    /// Method returns the titles a media is known under in other countries, ie "Le Parrain" for
+    /// "The Godfather" in France.
+    pub async fn get_alternate_titles_for(
+        &mut self,
+        id: u64,
+    ) -> Result<Vec<AlternateTitle>, TmdbError> {
+        let args = vec![("api_key".to_string(), self.api_key.clone())];
+
+        let req = self
+            .client
+            .get(format!(
+                "{}/{}/{}/alternative_titles",
+                self.base, self.media_type, id
+            ))
+            .query(&args)
+            .send()
+            .await?;
+
+        // movies list their titles under `titles` while tv shows list them under `results`.
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(alias = "results")]
+            titles: Option<Vec<AlternateTitle>>,
+        }
+
+        req.json::<Wrapper>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?
+            .titles
+            .ok_or(TmdbError::NoAlternateTitlesFound { id })
+    }
+
+    /// Method returns the ids of a media on other databases, ie IMDB and TVDB. Ids TMDB doesnt
+    /// know are `None`.
+    pub async fn get_external_ids_for(&mut self, id: u64) -> Result<ExternalIds, TmdbError> {
+        let args = vec![("api_key".to_string(), self.api_key.clone())];
+
+        let req = self
+            .client
+            .get(format!(
+                "{}/{}/{}/external_ids",
+                self.base, self.media_type, id
+            ))
+            .query(&args)
+            .send()
+            .await?;
+
+        if matches!(req.status(), StatusCode::NOT_FOUND) {
+            return Err(TmdbError::NoExternalIdsFound { id });
+        }
+
+        let mut ids = req
+            .json::<ExternalIds>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?;
+
+        // unknown imdb ids are sometimes returned as empty strings.
+        ids.imdb_id = ids.imdb_id.filter(|x| !x.is_empty());
+
+        Ok(ids)
+    }
+
+    /// Method returns the runtime of a media in minutes. For tv shows this is the runtime of a
+    /// single episode.
+    pub async fn get_runtime_for(&mut self, id: u64) -> Result<u64, TmdbError> {
+        self.search_by_id(id as i32)
+            .await?
+            .runtime
+            .filter(|x| *x > 0)
+            .ok_or(TmdbError::NoRuntimeFound { id })
+    }
+
+    /// Method returns a collection of movies, ie all movies of a franchise, with its parts ordered
+    /// by release date. Unreleased parts without a release date are ordered last. Results are
+    /// cached until [`Tmdb::clear_cache`] is called.
+    pub async fn get_collection(&mut self, id: u64) -> Result<Collection, TmdbError> {
+        {
+            let lock = (*COLLECTION_CACHE).read().await;
+            if let Some(x) = lock.get(&id) {
+                return Ok(x.clone());
+            }
+        }
+
+        let args = vec![
+            ("api_key".to_string(), self.api_key.clone()),
+            ("language".to_string(), "en-US".into()),
+        ];
+
+        let req = self
+            .client
+            .get(format!("{}/collection/{}", self.base, id))
+            .query(&args)
+            .send()
+            .await?;
+
+        if matches!(req.status(), StatusCode::NOT_FOUND) {
+            return Err(TmdbError::NoCollectionFound { id });
+        }
+
+        let mut collection = req
+            .json::<Collection>()
+            .await
+            .map_err(|_| TmdbError::DeserializationError)?;
+
+        collection.parts.sort_by_key(|x| {
+            let release_date = x.release_date.clone().filter(|x| !x.is_empty());
+            (release_date.is_none(), release_date)
+        });
+
+        {
+            let mut lock = (*COLLECTION_CACHE).write().await;
+            lock.insert(id, collection.clone());
+        }
+
+        Ok(collection)
+    }
+
+    pub async fn get_genre_detail(&mut self, genre_id: u64) -> Result<Genre, TmdbError> {
+        {
+            let lock = (*GENRE_CACHE).read().await;
             if let Some(x) = lock.get(&self.media_type) {
                 if let Some(x) = x.iter().find(|x| x.id == genre_id) {
                     return Ok(x.clone());
@@ -289,13 +668,7 @@ impl Tmdb {
         let args = vec![("api_key".to_string(), self.api_key.clone())];
 
         let url = format!("{}/genre/{}/list", self.base.clone(), self.media_type);
-        let req = self
-            .client
-            .get(url)
-            .query(&args)
-            .send()
-            .await
-            .map_err(|_| TmdbError::ReqwestError)?;
+        let req = self.client.get(url).query(&args).send().await?;
 
         #[derive(Deserialize)]
         struct Wrapper {
@@ -309,7 +682,7 @@ impl Tmdb {
             .genres;
 
         {
-            let mut lock = (*__CACHE).write().await;
+            let mut lock = (*GENRE_CACHE).write().await;
             lock.insert(self.media_type, genres.clone());
         }
 
@@ -320,6 +693,61 @@ impl Tmdb {
             .ok_or(TmdbError::NoGenreFound { id: genre_id })
     }
 }
+
+#[async_trait]
+impl MetadataProvider for Tmdb {
+    async fn search_scored(
+        &mut self,
+        title: String,
+        year: Option<i32>,
+    ) -> Result<Vec<ScoredMatch>, TmdbError> {
+        Tmdb::search_scored(self, title, year).await
+    }
+
+    async fn search_by_id(&mut self, id: i32) -> Result<Media, TmdbError> {
+        Tmdb::search_by_id(self, id).await
+    }
+
+    async fn get_seasons_for(&mut self, id: u64) -> Result<Vec<Season>, TmdbError> {
+        Tmdb::get_seasons_for(self, id).await
+    }
+
+    async fn get_episodes_for(&mut self, id: u64, season: u64) -> Result<Vec<Episode>, TmdbError> {
+        Tmdb::get_episodes_for(self, id, season).await
+    }
+
+    async fn get_videos_for(&mut self, id: u64) -> Result<Vec<Video>, TmdbError> {
+        Tmdb::get_videos_for(self, id).await
+    }
+
+    async fn get_credits_for(&mut self, id: u64) -> Result<Credits, TmdbError> {
+        Tmdb::get_credits_for(self, id).await
+    }
+
+    async fn get_keywords_for(&mut self, id: u64) -> Result<Vec<Keyword>, TmdbError> {
+        Tmdb::get_keywords_for(self, id).await
+    }
+
+    async fn get_alternate_titles_for(
+        &mut self,
+        id: u64,
+    ) -> Result<Vec<AlternateTitle>, TmdbError> {
+        Tmdb::get_alternate_titles_for(self, id).await
+    }
+
+    async fn get_external_ids_for(&mut self, id: u64) -> Result<ExternalIds, TmdbError> {
+        Tmdb::get_external_ids_for(self, id).await
+    }
+
+    async fn get_runtime_for(&mut self, id: u64) -> Result<u64, TmdbError> {
+        Tmdb::get_runtime_for(self, id).await
+    }
+
+    async fn get_collection(&mut self, id: u64) -> Result<Collection, TmdbError> {
+        Tmdb::get_collection(self, id).await
+    }
+}
+
 /*
 
  {
@@ -369,6 +797,90 @@ pub struct Media {
     pub genre_ids: Option<Vec<u64>>,
     #[serde(skip_deserializing)]
     pub genres: Vec<String>,
+    /// Runtime in minutes, only returned when querying a media by its id.
+    #[serde(default)]
+    pub runtime: Option<u64>,
+    /// Id of the collection a movie belongs to, only returned when querying a media by its id.
+    #[serde(skip_deserializing)]
+    pub collection_id: Option<u64>,
+}
+
+impl Media {
+    /// Returns the year this media was released in.
+    pub fn year(&self) -> Option<i32> {
+        self.release_date
+            .as_ref()
+            .and_then(|x| x.get(..4))
+            .and_then(|x| x.parse().ok())
+    }
+
+    /// Returns how confident we are that this media is what was searched for with `title` and
+    /// `year`, between `0.0` and `1.0`. Titles are compared word by word ignoring case and
+    /// punctuation, release years one year apart still count halfway as releases dont always line
+    /// up across regions.
+    pub fn confidence(&self, title: &str, year: Option<i32>) -> f64 {
+        fn words(x: &str) -> HashSet<String> {
+            x.to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|x| !x.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        }
+
+        let query = words(title);
+        let candidate = words(&self.title);
+        let union = query.union(&candidate).count();
+
+        let title_score = if union == 0 {
+            0.0
+        } else {
+            query.intersection(&candidate).count() as f64 / union as f64
+        };
+
+        let year_score = match (year, self.year()) {
+            (Some(a), Some(b)) if a == b => 1.0,
+            (Some(a), Some(b)) if (a - b).abs() == 1 => 0.5,
+            (Some(_), Some(_)) => 0.0,
+            _ => 0.5,
+        };
+
+        title_score * 0.75 + year_score * 0.25
+    }
+}
+
+/// A search result along with how confident we are that it is what was searched for.
+#[derive(Clone, Debug)]
+pub struct ScoredMatch {
+    pub media: Media,
+    pub confidence: f64,
+}
+
+impl ScoredMatch {
+    /// Returns whether the best of `matches` isnt clearly better than the runner up, in which
+    /// case the match should be reviewed by a user. `matches` must be sorted best match first.
+    pub fn is_ambiguous(matches: &[Self]) -> bool {
+        match matches {
+            [best, runner_up, ..] => best.confidence - runner_up.confidence < AMBIGUITY_MARGIN,
+            _ => false,
+        }
+    }
+}
+
+impl From<&ScoredMatch> for database::match_candidate::MatchCandidate {
+    fn from(this: &ScoredMatch) -> Self {
+        Self {
+            tmdb_id: this.media.id as i64,
+            title: this.media.title.clone(),
+            release_date: this.media.release_date.clone(),
+            poster_path: this
+                .media
+                .poster_path
+                .as_ref()
+                .map(|s| format!("https://image.tmdb.org/t/p/w600_and_h900_bestv2{}", s)),
+            confidence: this.confidence,
+            ..Default::default()
+        }
+    }
 }
 
 impl From<Media> for super::ApiMedia {
@@ -396,6 +908,13 @@ impl From<Media> for super::ApiMedia {
             genres: this.genres,
             rating: this.vote_average.map(|x| x as i32),
             seasons: Vec::new(),
+            cast: Vec::new(),
+            crew: Vec::new(),
+            keywords: Vec::new(),
+            alternate_titles: Vec::new(),
+            external_ids: None,
+            runtime: this.runtime.map(|x| x * 60),
+            collection_id: this.collection_id,
         }
     }
 }
@@ -406,6 +925,58 @@ pub struct Genre {
     pub name: String,
 }
 
+/// A collection of movies on TMDB, ie all movies of a franchise.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Collection {
+    pub id: u64,
+    pub name: String,
+    pub overview: Option<String>,
+    pub poster_path: Option<String>,
+    pub backdrop_path: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<Media>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Keyword {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AlternateTitle {
+    /// Country the title is used in, ie `FR`.
+    pub iso_3166_1: String,
+    pub title: String,
+}
+
+/// Ids of a media on other databases.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ExternalIds {
+    /// Id of the media on IMDB, ie `tt0133093`.
+    pub imdb_id: Option<String>,
+    /// Id of the media on TVDB, only known for tv shows.
+    pub tvdb_id: Option<u64>,
+}
+
+impl From<ExternalIds> for super::ApiExternalIds {
+    fn from(this: ExternalIds) -> Self {
+        Self {
+            imdb_id: this.imdb_id,
+            tvdb_id: this.tvdb_id,
+        }
+    }
+}
+
+impl From<AlternateTitle> for super::ApiAlternateTitle {
+    fn from(this: AlternateTitle) -> Self {
+        Self {
+            country: this.iso_3166_1,
+            title: this.title,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct Season {
     pub id: u64,
@@ -422,6 +993,8 @@ impl From<Season> for super::ApiSeason {
         Self {
             id: this.id,
             name: this.name,
+            overview: this.overview.filter(|x| !x.is_empty()),
+            air_date: this.air_date.filter(|x| !x.is_empty()),
             poster_path: this
                 .poster_path
                 .clone()
@@ -440,6 +1013,7 @@ pub struct Episode {
     pub overview: Option<String>,
     pub episode_number: Option<u64>,
     pub still_path: Option<String>,
+    pub air_date: Option<String>,
 }
 
 impl From<Episode> for super::ApiEpisode {
@@ -454,16 +1028,157 @@ impl From<Episode> for super::ApiEpisode {
                 .clone()
                 .map(|s| format!("https://image.tmdb.org/t/p/w600_and_h900_bestv2{}", s)),
             still_file: other.still_path,
+            air_date: other.air_date.filter(|x| !x.is_empty()),
         }
     }
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct Credits {
+    pub cast: Vec<Cast>,
+    pub crew: Vec<Crew>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Cast {
+    pub id: u64,
+    pub name: String,
+    pub character: Option<String>,
+    pub order: Option<u64>,
+    pub profile_path: Option<String>,
+}
+
+impl From<Cast> for super::ApiCast {
+    fn from(this: Cast) -> Self {
+        Self {
+            id: this.id,
+            name: this.name,
+            character: this.character.filter(|x| !x.is_empty()),
+            profile_path: this.profile_path.map(profile_url),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Crew {
+    pub id: u64,
+    pub name: String,
+    pub job: String,
+    pub profile_path: Option<String>,
+}
+
+impl From<Crew> for super::ApiCrew {
+    fn from(this: Crew) -> Self {
+        Self {
+            id: this.id,
+            name: this.name,
+            job: this.job,
+            profile_path: this.profile_path.map(profile_url),
+        }
+    }
+}
+
+fn profile_url(path: String) -> String {
+    format!("https://image.tmdb.org/t/p/w185{}", path)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Video {
+    pub name: String,
+    /// Key of the video on the site it is hosted on, ie the YouTube video id.
+    pub key: String,
+    pub site: String,
+    #[serde(rename = "type")]
+    pub video_type: String,
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub official: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     const API_KEY: &str = "38c372f5bc572c8aadde7a802638534e";
 
+    #[tokio::test]
+    async fn test_timeout() {
+        // a server that accepts connections but never responds.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let mut conns = vec![];
+            for conn in listener.incoming() {
+                conns.push(conn);
+            }
+        });
+
+        let mut tmdb = Tmdb::with_timeout(
+            API_KEY.to_string(),
+            MediaType::Movie,
+            Duration::from_millis(200),
+        );
+        tmdb.base = format!("http://{}", addr);
+
+        let result = tmdb.search_by_id(1).await;
+        assert!(matches!(result, Err(TmdbError::Timeout)));
+    }
+
+    fn media(title: &str, release_date: Option<&str>) -> Media {
+        Media {
+            id: 0,
+            title: title.into(),
+            release_date: release_date.map(Into::into),
+            overview: None,
+            vote_average: None,
+            poster_path: None,
+            backdrop_path: None,
+            genre_ids: None,
+            genres: Vec::new(),
+            runtime: None,
+            collection_id: None,
+        }
+    }
+
+    #[test]
+    fn test_confidence() {
+        let exact = media("Blade Runner 2049", Some("2017-10-04"));
+        assert_eq!(exact.confidence("blade runner: 2049", Some(2017)), 1.0);
+        assert_eq!(exact.confidence("Blade Runner 2049", None), 0.875);
+
+        let original = media("Blade Runner", Some("1982-06-25"));
+        assert!(original.confidence("Blade Runner 2049", Some(2017)) < 0.6);
+
+        let dune = media("Dune", Some("2021-09-15"));
+        assert_eq!(dune.confidence("Dune", Some(2020)), 0.875);
+    }
+
+    #[test]
+    fn test_is_ambiguous() {
+        let scored = |title, date, query: &str, year| {
+            let media = media(title, date);
+            ScoredMatch {
+                confidence: media.confidence(query, year),
+                media,
+            }
+        };
+
+        // remakes sharing a title are only told apart by their release year.
+        let matches = vec![
+            scored("Dune", Some("2021-09-15"), "Dune", None),
+            scored("Dune", Some("1984-12-14"), "Dune", None),
+        ];
+        assert!(ScoredMatch::is_ambiguous(&matches));
+
+        let matches = vec![
+            scored("Dune", Some("2021-09-15"), "Dune", Some(2021)),
+            scored("Dune", Some("1984-12-14"), "Dune", Some(2021)),
+        ];
+        assert!(!ScoredMatch::is_ambiguous(&matches));
+        assert!(!ScoredMatch::is_ambiguous(&matches[..1]));
+    }
+
     // #[test]
     // fn test_search_by_name() {
     //     let mut tmdb = Tmdb::new(API_KEY.to_string(), MediaType::Movie);
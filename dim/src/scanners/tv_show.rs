@@ -1,15 +1,24 @@
+use database::alternate_title::InsertableAlternateTitle;
 use database::asset::InsertableAsset;
 use database::genre::InsertableGenre;
 use database::genre::InsertableGenreMedia;
+use database::keyword::InsertableKeyword;
 use database::DbConnection;
 
+use database::episode::Episode;
 use database::episode::InsertableEpisode;
+use database::library::Library;
 use database::library::MediaType;
+use database::media::ExternalIds;
 use database::media::InsertableMedia;
+use database::media::Media;
 use database::mediafile::MediaFile;
 use database::mediafile::UpdateMediaFile;
 use database::movie::InsertableMovie;
+use database::person::InsertablePerson;
+use database::person::Person;
 use database::season::InsertableSeason;
+use database::tmdb_episode::TmdbEpisode;
 use database::tv::TVShow;
 
 use chrono::prelude::Utc;
@@ -26,6 +35,11 @@ use tracing::instrument;
 use tracing::warn;
 use tracing::Instrument;
 
+use std::path::Path;
+
+use tokio::task::spawn_blocking;
+
+use super::base::parse_absolute_episode;
 use super::format_path;
 use crate::core::EventTx;
 use crate::fetcher::insert_into_queue;
@@ -64,6 +78,7 @@ impl<'a> TvShowMatcher<'a> {
         }
 
         self.push_event(media_id, library_id).await;
+        crate::accent_color::queue(self.conn.clone(), media_id);
     }
 
     pub async fn inner_match(
@@ -178,14 +193,41 @@ impl<'a> TvShowMatcher<'a> {
         tx: &mut database::Transaction<'_>,
         reuse_media_id: Option<i64>,
     ) -> Result<i64, super::base::ScannerError> {
+        let existing =
+            Media::get_id_by_tmdb_id(&mut *tx, media.library_id, result.id as i64, MediaType::Tv)
+                .await?;
+
         let media_id = if let Some(id) = reuse_media_id {
             media.insert_with_id(&mut *tx, id).await?
+        } else if let Some(id) = existing {
+            // another file of the same media has already been matched, thus we attach this file
+            // to the existing media instead of creating a duplicate.
+            id
         } else {
             media.insert(&mut *tx).await?
         };
 
+        Media::set_tmdb_id(&mut *tx, media_id, result.id as i64).await?;
+
+        if let Some(runtime) = result.runtime {
+            Media::set_tmdb_runtime(&mut *tx, media_id, runtime as i64).await?;
+        }
+
+        if let Some(ids) = result.external_ids {
+            let ids = ExternalIds {
+                imdb_id: ids.imdb_id,
+                tvdb_id: ids.tvdb_id.map(|x| x as i64),
+            };
+
+            Media::set_external_ids(&mut *tx, media_id, &ids).await?;
+        }
+
         let _ = TVShow::insert(&mut *tx, media_id).await;
 
+        // if this media was added manually as a placeholder we reuse it instead of creating a
+        // duplicate entry.
+        Media::reconcile_placeholder(&mut *tx, media_id).await?;
+
         for name in result.genres {
             let genre = InsertableGenre { name };
 
@@ -194,6 +236,116 @@ impl<'a> TvShowMatcher<'a> {
             }
         }
 
+        for name in result.keywords {
+            let _ = InsertableKeyword { name }
+                .insert_for_media(&mut *tx, media_id)
+                .await;
+        }
+
+        for title in result.alternate_titles {
+            let _ = InsertableAlternateTitle {
+                country: title.country,
+                title: title.title,
+            }
+            .insert_for_media(&mut *tx, media_id)
+            .await;
+        }
+
+        // the media might have been matched before, thus we drop its old cast and crew so that
+        // people who arent credited anymore dont linger. Either is kept if it couldnt be fetched.
+        if !result.cast.is_empty() {
+            let _ = Person::clear_cast(&mut *tx, media_id).await;
+        }
+
+        if !result.crew.is_empty() {
+            let _ = Person::clear_crew(&mut *tx, media_id).await;
+        }
+
+        for (ordering, cast) in result.cast.into_iter().enumerate() {
+            let person = InsertablePerson {
+                tmdb_id: cast.id as i64,
+                name: cast.name,
+                profile_path: cast.profile_path,
+            };
+
+            let _ = person
+                .insert_cast(&mut *tx, media_id, cast.character, ordering as i64)
+                .await;
+        }
+
+        for crew in result.crew {
+            let person = InsertablePerson {
+                tmdb_id: crew.id as i64,
+                name: crew.name,
+                profile_path: crew.profile_path,
+            };
+
+            let _ = person.insert_crew(&mut *tx, media_id, &crew.job).await;
+        }
+
+        // the catalog is only stored when the show is first matched or rematched as it is the
+        // same for every file of the show.
+        if existing.is_none() || reuse_media_id.is_some() {
+            let catalog = result
+                .seasons
+                .iter()
+                .flat_map(|season| {
+                    season.episodes.iter().filter_map(move |x| {
+                        Some(TmdbEpisode {
+                            season: season.season_number as i64,
+                            episode: x.episode? as i64,
+                            name: x.name.clone(),
+                            air_date: x.air_date.clone(),
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if !catalog.is_empty() {
+                TmdbEpisode::set_for_show(&mut *tx, media_id, &catalog).await?;
+            }
+        }
+
+        let anime = Library::get_one(&mut *tx, orphan.library_id)
+            .await
+            .map_or(false, |x| x.anime);
+
+        let mut orphan = orphan.clone();
+
+        // anime releases often number episodes across seasons, ie `Show - 125`, in which case we
+        // resolve the absolute number to a season and episode with the catalog of the show.
+        if anime {
+            let filename = Path::new(&orphan.target_file)
+                .file_name()
+                .and_then(|x| x.to_str())
+                .map(ToString::to_string)
+                .unwrap_or_default();
+
+            let absolute_number = spawn_blocking(move || parse_absolute_episode(&filename))
+                .await
+                .ok()
+                .flatten();
+
+            if let Some(absolute_number) = absolute_number {
+                if let Some((season, episode)) =
+                    TmdbEpisode::resolve_absolute(&mut *tx, media_id, absolute_number).await?
+                {
+                    UpdateMediaFile {
+                        season: Some(season),
+                        episode: Some(episode),
+                        ..Default::default()
+                    }
+                    .update(&mut *tx, orphan.id)
+                    .await?;
+
+                    orphan.season = Some(season);
+                    orphan.episode = Some(episode);
+                }
+            }
+        }
+
+        let orphan = &orphan;
+
         let season = {
             let orphan_season = orphan.season.unwrap_or(0) as u64;
 
@@ -239,6 +391,9 @@ impl<'a> TvShowMatcher<'a> {
             season_number: orphan.season.unwrap_or(0),
             added: Utc::now().to_string(),
             poster: season_poster,
+            name: season.and_then(|x| x.name.clone()),
+            overview: season.and_then(|x| x.overview.clone()),
+            air_date: season.and_then(|x| x.air_date.clone()),
         };
 
         let seasonid = match insertable_season.insert(&mut *tx, media_id).await {
@@ -332,6 +487,19 @@ impl<'a> TvShowMatcher<'a> {
         }
 
         let episode_id = episode.insert(&mut *tx).await?;
+        let season_number = orphan.season.unwrap_or(0);
+
+        if anime {
+            let absolute_number = TmdbEpisode::get_absolute_number(
+                &mut *tx,
+                media_id,
+                season_number,
+                orphan.episode.unwrap_or(0),
+            )
+            .await?;
+
+            Episode::set_absolute_number(&mut *tx, episode_id, absolute_number).await?;
+        }
 
         let updated_mediafile = UpdateMediaFile {
             media_id: Some(episode_id),
@@ -343,6 +511,50 @@ impl<'a> TvShowMatcher<'a> {
             .instrument(debug_span!("UpdateMediafile"))
             .await?;
 
+        // files like `S01E01-E02` contain more than one episode. The file is linked to the first
+        // episode, the remaining episodes are created without a file of their own and resolve to
+        // this file through its episode range.
+        let first_episode = orphan.episode.unwrap_or(0);
+        for number in (first_episode + 1)..=orphan.episode_end.unwrap_or(first_episode) {
+            let search_ep = season.and_then(|x| {
+                x.episodes
+                    .iter()
+                    .find(|&s| s.episode == Some(number as u64))
+            });
+
+            debug!(
+                seasonid = seasonid,
+                episode = number,
+                target_file = ?&orphan.target_file,
+                "Inserting episode contained in a multi-episode file",
+            );
+
+            let episode = InsertableEpisode {
+                episode: number,
+                seasonid,
+                media: InsertableMedia {
+                    library_id: orphan.library_id,
+                    name: search_ep
+                        .and_then(|x| x.name.clone())
+                        .unwrap_or_else(|| number.to_string()),
+                    added: Utc::now().to_string(),
+                    media_type: MediaType::Episode,
+                    description: search_ep.map(|x| x.overview.clone()).unwrap_or_default(),
+                    ..Default::default()
+                },
+            };
+
+            let episode_id = episode.insert(&mut *tx).await?;
+
+            if anime {
+                let absolute_number =
+                    TmdbEpisode::get_absolute_number(&mut *tx, media_id, season_number, number)
+                        .await?;
+
+                Episode::set_absolute_number(&mut *tx, episode_id, absolute_number).await?;
+            }
+        }
+
         Ok(media_id)
     }
 
@@ -0,0 +1,455 @@
+//! Scrobbling of watch status to Trakt-like services.
+//!
+//! Users that set up an integration have a `start` scrobble sent when they start watching a movie
+//! or episode, and a `stop` scrobble once they watched past
+//! [`WATCHED_THRESHOLD`](database::progress::WATCHED_THRESHOLD) of it. Scrobbles are queued in the
+//! database and sent in the background, failed ones are retried with exponential backoff.
+//!
+//! Scrobbles are only sent over https to the hosts the owner allowed with `scrobble_hosts` in the
+//! global settings, and never to loopback, link-local or private addresses. Otherwise any user
+//! could make the server send requests to hosts on its internal network.
+use std::net::IpAddr;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use database::mediafile::MediaFile;
+use database::progress::Progress;
+use database::progress::WATCHED_THRESHOLD;
+use database::scrobble::Scrobble;
+use database::scrobble::ScrobbleIntegration;
+use database::scrobble::ScrobbleTarget;
+use database::DbConnection;
+
+use serde_json::json;
+use tracing::error;
+use tracing::warn;
+
+use crate::errors::DimError;
+
+/// Fraction of a media's duration after which we consider it started.
+pub const STARTED_THRESHOLD: f64 = 0.01;
+/// Number of times a scrobble is attempted before it is dropped.
+pub const MAX_ATTEMPTS: i64 = 8;
+/// Delay before the first retry of a failed scrobble. The delay doubles with every retry.
+pub const INITIAL_BACKOFF_SECS: i64 = 30;
+/// Hosts users may scrobble to if nothing is configured.
+pub const DEFAULT_HOSTS: &[&str] = &["api.trakt.tv"];
+/// Seconds between checks for due scrobbles.
+const POLL_INTERVAL_SECS: u64 = 5;
+/// Maximum number of scrobbles sent per check.
+const BATCH_SIZE: i64 = 50;
+
+/// Returns whether `ip` is reachable from the internet, ie isnt a loopback, link-local or private
+/// address.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(x) => {
+            !(x.is_loopback()
+                || x.is_private()
+                || x.is_link_local()
+                || x.is_unspecified()
+                || x.is_broadcast())
+        }
+        IpAddr::V6(x) => {
+            if let Some(x) = x.to_ipv4() {
+                return is_public(IpAddr::V4(x));
+            }
+
+            let first = x.segments()[0];
+
+            // fc00::/7 are unique local and fe80::/10 link-local addresses.
+            !(x.is_loopback()
+                || x.is_unspecified()
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Returns whether scrobbles may be sent to `url`. The url must use https and its host must be
+/// one of `allowed_hosts`, literal addresses are only allowed if they are public.
+pub fn is_allowed_url(url: &str, allowed_hosts: &[String]) -> bool {
+    let url = match reqwest::Url::parse(url) {
+        Ok(x) => x,
+        Err(_) => return false,
+    };
+
+    if url.scheme() != "https" {
+        return false;
+    }
+
+    let host = match url.host_str() {
+        Some(x) => x
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_lowercase(),
+        None => return false,
+    };
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if !is_public(ip) {
+            return false;
+        }
+    }
+
+    allowed_hosts.iter().any(|x| x.eq_ignore_ascii_case(&host))
+}
+
+/// Returns whether the host of `url` only resolves to public addresses. Allowed hosts could still
+/// point at the internal network, ie through a stale DNS record.
+async fn resolves_to_public(url: &reqwest::Url) -> Option<bool> {
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs = tokio::task::spawn_blocking(move || {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .map(|x| x.collect::<Vec<_>>())
+    })
+    .await
+    .ok()?
+    .ok()?;
+
+    Some(!addrs.is_empty() && addrs.iter().all(|x| is_public(x.ip())))
+}
+
+/// Scrobble a change in progress warrants.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Start,
+    Stop,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+        }
+    }
+}
+
+/// Returns the scrobble to send when progress through a media of `duration` seconds moves from
+/// `from` to `to`, if any.
+pub fn crossed(from: i64, to: i64, duration: i64) -> Option<Action> {
+    if duration <= 0 {
+        return None;
+    }
+
+    let from = from as f64 / duration as f64;
+    let to = to as f64 / duration as f64;
+
+    if from <= WATCHED_THRESHOLD && to > WATCHED_THRESHOLD {
+        Some(Action::Stop)
+    } else if from <= STARTED_THRESHOLD && to > STARTED_THRESHOLD {
+        Some(Action::Start)
+    } else {
+        None
+    }
+}
+
+/// A scrobble that is yet to be queued.
+#[derive(Clone, Debug)]
+pub struct PendingScrobble {
+    action: Action,
+    target: ScrobbleTarget,
+    progress: f64,
+}
+
+/// Returns the scrobbles `user` moving to the offsets in `updates`, pairs of media id and offset,
+/// warrants. This must be called before the new offsets are written. Empty if the user has no
+/// integration.
+///
+/// # Arguments
+/// * `conn` - mutable reference to a sqlx transaction.
+/// * `user` - username of the user.
+/// * `updates` - media ids along with their new offset.
+pub async fn detect(
+    conn: &mut database::Transaction<'_>,
+    user: &str,
+    updates: &[(i64, i64)],
+) -> Result<Vec<PendingScrobble>, DimError> {
+    if ScrobbleIntegration::get_for_user(&mut *conn, user)
+        .await?
+        .is_none()
+    {
+        return Ok(vec![]);
+    }
+
+    let mut scrobbles = vec![];
+
+    for &(media_id, delta) in updates {
+        let current = crate::progress_buffer::overlay(
+            Progress::get_for_media_user(&mut *conn, user.to_string(), media_id).await?,
+        );
+        let duration = MediaFile::get_largest_duration(&mut *conn, media_id)
+            .await
+            .unwrap_or(0);
+
+        let action = match crossed(current.delta, delta, duration) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if let Some(target) = ScrobbleTarget::get(&mut *conn, media_id).await? {
+            scrobbles.push(PendingScrobble {
+                action,
+                target,
+                progress: (delta as f64 / duration as f64 * 100.0).min(100.0),
+            });
+        }
+    }
+
+    Ok(scrobbles)
+}
+
+/// Queues `scrobbles` of `user` to be sent in the background.
+///
+/// # Arguments
+/// * `conn` - mutable reference to a sqlx transaction.
+/// * `user` - username of the user.
+/// * `scrobbles` - scrobbles returned by [`detect`](detect).
+pub async fn queue(
+    conn: &mut database::Transaction<'_>,
+    user: &str,
+    scrobbles: &[PendingScrobble],
+) -> Result<(), DimError> {
+    for scrobble in scrobbles {
+        Scrobble::queue(
+            &mut *conn,
+            user,
+            scrobble.action.as_str(),
+            &scrobble.target,
+            scrobble.progress,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the body of a scrobble, shaped like the scrobble API of Trakt.
+pub fn payload(scrobble: &Scrobble) -> serde_json::Value {
+    let target = &scrobble.target;
+
+    if target.media_type == "episode" {
+        json!({
+            "show": { "ids": { "tmdb": target.tmdb_id } },
+            "episode": { "season": target.season, "number": target.episode },
+            "progress": scrobble.progress,
+        })
+    } else {
+        json!({
+            "movie": { "ids": { "tmdb": target.tmdb_id } },
+            "progress": scrobble.progress,
+        })
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Outcome of sending a scrobble.
+enum Sent {
+    Ok,
+    /// The service rejected the scrobble, retrying wont help.
+    Rejected,
+    Failed,
+}
+
+async fn send(client: &reqwest::Client, scrobble: &Scrobble) -> Sent {
+    // the allowed hosts might have changed since the integration was set up.
+    let allowed_hosts = crate::routes::settings::get_global_settings().scrobble_hosts;
+    if !is_allowed_url(&scrobble.url, &allowed_hosts) {
+        warn!(
+            scrobble = scrobble.id,
+            "Dropping scrobble to a host that isnt allowed"
+        );
+        return Sent::Rejected;
+    }
+
+    let url = match reqwest::Url::parse(&format!(
+        "{}/scrobble/{}",
+        scrobble.url.trim_end_matches('/'),
+        scrobble.action
+    )) {
+        Ok(x) => x,
+        Err(_) => return Sent::Rejected,
+    };
+
+    match resolves_to_public(&url).await {
+        Some(true) => {}
+        Some(false) => {
+            warn!(
+                scrobble = scrobble.id,
+                "Dropping scrobble to a private address"
+            );
+            return Sent::Rejected;
+        }
+        None => return Sent::Failed,
+    }
+
+    let result = client
+        .post(&url)
+        .bearer_auth(&scrobble.token)
+        .json(&payload(scrobble))
+        .send()
+        .await
+        .and_then(|x| x.error_for_status());
+
+    match result {
+        Ok(_) => Sent::Ok,
+        Err(e) => {
+            warn!(
+                reason = ?e,
+                scrobble = scrobble.id,
+                attempt = scrobble.attempts + 1,
+                "Failed to send scrobble"
+            );
+
+            match e.status() {
+                Some(x) if x.is_client_error() && x != http::StatusCode::TOO_MANY_REQUESTS => {
+                    Sent::Rejected
+                }
+                _ => Sent::Failed,
+            }
+        }
+    }
+}
+
+/// Sends all due scrobbles.
+async fn send_due(conn: &DbConnection, client: &reqwest::Client) -> Result<(), DimError> {
+    let due = {
+        let mut tx = conn.read().begin().await?;
+        Scrobble::get_due(&mut tx, now(), BATCH_SIZE).await?
+    };
+
+    for scrobble in due {
+        let sent = send(client, &scrobble).await;
+
+        let mut lock = conn.writer().lock_owned().await;
+        let mut tx = database::write_tx(&mut lock).await?;
+
+        match sent {
+            Sent::Failed if scrobble.attempts + 1 < MAX_ATTEMPTS => {
+                let backoff = INITIAL_BACKOFF_SECS << scrobble.attempts;
+                Scrobble::postpone(&mut tx, scrobble.id, now() + backoff).await?;
+            }
+            _ => Scrobble::delete(&mut tx, scrobble.id).await?,
+        }
+
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Spawns the task sending queued scrobbles.
+pub fn start(conn: DbConnection) {
+    // redirects could lead to hosts that arent allowed.
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build the scrobble client");
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = send_due(&conn, &client).await {
+                error!(reason = ?e, "Failed to send scrobbles");
+            }
+
+            tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_url() {
+        let hosts = vec!["api.trakt.tv".to_string(), "1.1.1.1".to_string()];
+
+        assert!(is_allowed_url("https://api.trakt.tv", &hosts));
+        assert!(is_allowed_url("https://API.trakt.tv/v2", &hosts));
+        assert!(is_allowed_url("https://1.1.1.1", &hosts));
+        assert!(!is_allowed_url("http://api.trakt.tv", &hosts));
+        assert!(!is_allowed_url("https://example.com", &hosts));
+        assert!(!is_allowed_url("not a url", &hosts));
+
+        // private addresses are rejected even if the owner allowed them.
+        let hosts = vec![
+            "127.0.0.1".to_string(),
+            "10.0.0.1".to_string(),
+            "::1".to_string(),
+        ];
+        assert!(!is_allowed_url("https://127.0.0.1", &hosts));
+        assert!(!is_allowed_url("https://10.0.0.1", &hosts));
+        assert!(!is_allowed_url("https://[::1]", &hosts));
+    }
+
+    #[test]
+    fn test_is_public() {
+        for ip in &["8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_crossed() {
+        assert_eq!(crossed(0, 60, 3600), Some(Action::Start));
+        assert_eq!(crossed(60, 120, 3600), None);
+        assert_eq!(crossed(3000, 3300, 3600), Some(Action::Stop));
+        // skipping straight to the credits only sends the stop.
+        assert_eq!(crossed(0, 3500, 3600), Some(Action::Stop));
+        assert_eq!(crossed(3300, 3400, 3600), None);
+        assert_eq!(crossed(0, 60, 0), None);
+    }
+
+    #[test]
+    fn test_payload() {
+        let mut scrobble = Scrobble {
+            target: ScrobbleTarget {
+                media_type: "episode".into(),
+                tmdb_id: 1399,
+                season: Some(1),
+                episode: Some(2),
+            },
+            progress: 50.0,
+            ..Default::default()
+        };
+
+        let body = payload(&scrobble);
+        assert_eq!(body["show"]["ids"]["tmdb"], 1399);
+        assert_eq!(body["episode"]["number"], 2);
+
+        scrobble.target.media_type = "movie".into();
+        assert_eq!(payload(&scrobble)["movie"]["ids"]["tmdb"], 1399);
+    }
+}
@@ -0,0 +1,81 @@
+//! Full-text search over the names of media.
+//!
+//! Searches by name go through the full-text index kept in the database, see
+//! [`SearchIndex`](database::search_index::SearchIndex). The index is rebuilt batch by batch,
+//! thus while a rebuild is running parts of it can be missing and searches fall back to matching
+//! names with `LIKE` until the rebuild is done or cancelled.
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+static REBUILDING: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether searches can use the full-text index, ie no rebuild is running.
+pub fn is_available() -> bool {
+    !REBUILDING.load(Ordering::SeqCst)
+}
+
+/// Guard marking the index as being rebuilt for as long as it is alive. Dropping it, ie when the
+/// rebuild task finishes or is cancelled, makes the index available again.
+pub struct Rebuild(());
+
+impl Rebuild {
+    /// Marks the index as being rebuilt. Returns `None` if a rebuild is already running.
+    pub fn start() -> Option<Self> {
+        REBUILDING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| Self(()))
+    }
+}
+
+impl Drop for Rebuild {
+    fn drop(&mut self) {
+        REBUILDING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Turns a search query into a FTS5 query matching media whose name contains every word of the
+/// query as a prefix of one of its words, ie `godfa part` matches `The Godfather Part II`.
+/// Returns `None` if the query has no words.
+pub fn fts_query(query: &str) -> Option<String> {
+    let words = query
+        .split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("\"{}\"*", x))
+        .collect::<Vec<_>>();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    Some(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query("godfa part").as_deref(),
+            Some("\"godfa\"* \"part\"*")
+        );
+        // quotes and operators cant leak into the query.
+        assert_eq!(
+            fts_query("\"spider-man\" OR").as_deref(),
+            Some("\"spider\"* \"man\"* \"OR\"*")
+        );
+        assert_eq!(fts_query(" - ").as_deref(), None);
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let rebuild = Rebuild::start().unwrap();
+        assert!(!is_available());
+        assert!(Rebuild::start().is_none());
+
+        drop(rebuild);
+        assert!(is_available());
+    }
+}
@@ -12,6 +12,30 @@ pub struct FFPWrapper {
 struct FFPStream {
     streams: Vec<Stream>,
     format: Format,
+    #[serde(default)]
+    chapters: Vec<Chapter>,
+}
+
+/// Chapter embedded in the container, timestamps are seconds encoded as strings.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start_time: String,
+    pub end_time: String,
+    pub tags: Option<Tags>,
+}
+
+impl Chapter {
+    pub fn get_start(&self) -> Option<f64> {
+        self.start_time.parse::<f64>().ok()
+    }
+
+    pub fn get_end(&self) -> Option<f64> {
+        self.end_time.parse::<f64>().ok()
+    }
+
+    pub fn get_title(&self) -> Option<String> {
+        self.tags.as_ref()?.title.clone()
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -39,9 +63,18 @@ pub struct Stream {
     pub duration: Option<String>,
     pub color_range: Option<String>,
     pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    pub codec_tag_string: Option<String>,
+    pub side_data_list: Option<Vec<SideData>>,
     pub disposition: Option<Disposition>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SideData {
+    pub side_data_type: Option<String>,
+}
+
 impl Stream {
     pub fn get_bitrate(&self) -> Option<u64> {
         self.tags.as_ref()?.bps_eng.as_ref()?.parse::<u64>().ok()
@@ -58,6 +91,59 @@ impl Stream {
     pub fn get_title(&self) -> Option<String> {
         self.tags.as_ref()?.title.clone()
     }
+
+    /// Method returns whether this subtitle stream is a closed caption track. Closed captions are
+    /// either embedded as EIA-608 captions or marked with `CC` in the title of the track.
+    pub fn is_cc(&self) -> bool {
+        self.codec_name == "eia_608"
+            || self.title_has_marker(|x| x == "cc" || x == "captions" || x == "caption")
+    }
+
+    /// Method returns whether this subtitle stream is a SDH track, ie subtitles for the deaf and
+    /// hard of hearing. These are either marked as hearing impaired or with `SDH` in the title of
+    /// the track.
+    pub fn is_sdh(&self) -> bool {
+        self.disposition
+            .as_ref()
+            .map_or(false, |x| x.hearing_impaired == 1)
+            || self.title_has_marker(|x| x == "sdh")
+    }
+
+    fn title_has_marker(&self, marker: impl Fn(&str) -> bool) -> bool {
+        self.get_title().map_or(false, |title| {
+            title
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .any(&marker)
+        })
+    }
+
+    /// Method returns the HDR format of this stream based on its color metadata, one of `hdr10`,
+    /// `dolby_vision`, `hlg` or `sdr`. Returns `None` if the stream carries no color metadata.
+    pub fn get_hdr_format(&self) -> Option<&'static str> {
+        let is_dovi = self
+            .side_data_list
+            .iter()
+            .flatten()
+            .filter_map(|x| x.side_data_type.as_deref())
+            .any(|x| x.starts_with("DOVI"))
+            || matches!(
+                self.codec_tag_string.as_deref(),
+                Some("dvhe") | Some("dvh1") | Some("dav1")
+            );
+
+        if is_dovi {
+            return Some("dolby_vision");
+        }
+
+        match self.color_transfer.as_deref() {
+            Some("smpte2084") => Some("hdr10"),
+            Some("arib-std-b67") => Some("hlg"),
+            Some(_) => Some("sdr"),
+            None if self.color_primaries.is_some() => Some("sdr"),
+            None => None,
+        }
+    }
 }
 
 impl From<Stream> for nightfall::profiles::InputCtx {
@@ -132,6 +218,7 @@ impl FFProbeCtx {
             .arg("json")
             .arg("-show_streams")
             .arg("-show_format")
+            .arg("-show_chapters")
             .output()?;
 
         let json = String::from_utf8_lossy(probe.stdout.as_slice());
@@ -184,6 +271,20 @@ impl FFPWrapper {
         self.find_by_type("video").first()?.profile.clone()
     }
 
+    pub fn get_hdr_format(&self) -> Option<&'static str> {
+        self.get_primary("video")?.get_hdr_format()
+    }
+
+    /// Method returns whether any subtitle stream of the file is a closed caption track.
+    pub fn has_cc(&self) -> bool {
+        self.find_by_type("subtitle").iter().any(|x| x.is_cc())
+    }
+
+    /// Method returns whether any subtitle stream of the file is a SDH track.
+    pub fn has_sdh(&self) -> bool {
+        self.find_by_type("subtitle").iter().any(|x| x.is_sdh())
+    }
+
     pub fn get_height(&self) -> Option<i64> {
         self.find_by_type("video").first()?.height
     }
@@ -239,6 +340,20 @@ impl FFPWrapper {
             .ok()
     }
 
+    /// Method returns the chapters of the file in the order ffprobe lists them. Chapters whose
+    /// timestamps cant be parsed are skipped.
+    pub fn get_chapters(&self) -> Vec<&Chapter> {
+        self.ffpstream
+            .as_ref()
+            .map(|x| {
+                x.chapters
+                    .iter()
+                    .filter(|x| x.get_start().is_some() && x.get_end().is_some())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn is_corrupt(&self) -> Option<bool> {
         Some(self.corrupt.unwrap_or(false))
     }
@@ -271,3 +386,49 @@ pub struct Disposition {
     pub hearing_impaired: i64,
     pub visual_impaired: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subtitle(codec_name: &str, title: Option<&str>, hearing_impaired: i64) -> Stream {
+        Stream {
+            codec_name: codec_name.into(),
+            codec_type: "subtitle".into(),
+            tags: Some(Tags {
+                title: title.map(ToString::to_string),
+                ..Default::default()
+            }),
+            disposition: Some(Disposition {
+                default: 0,
+                dub: 0,
+                original: 0,
+                comment: 0,
+                lyrics: 0,
+                karaoke: 0,
+                forced: 0,
+                hearing_impaired,
+                visual_impaired: 0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_cc() {
+        assert!(subtitle("eia_608", None, 0).is_cc());
+        assert!(subtitle("subrip", Some("English (CC)"), 0).is_cc());
+        assert!(subtitle("subrip", Some("Closed Captions"), 0).is_cc());
+        // markers must be whole words.
+        assert!(!subtitle("subrip", Some("Accented"), 0).is_cc());
+        assert!(!subtitle("subrip", None, 0).is_cc());
+    }
+
+    #[test]
+    fn test_is_sdh() {
+        assert!(subtitle("subrip", None, 1).is_sdh());
+        assert!(subtitle("subrip", Some("English [SDH]"), 0).is_sdh());
+        assert!(!subtitle("subrip", Some("English"), 0).is_sdh());
+        assert!(!subtitle("subrip", None, 0).is_sdh());
+    }
+}
@@ -0,0 +1,429 @@
+//! Online subtitle providers used to fetch subtitles for files that ship without any.
+//!
+//! Providers implement [`SubtitleProvider`](SubtitleProvider) and are selected with
+//! `subtitle_provider` in the global settings. The configured provider is kept alive between
+//! requests so that it can cache auth tokens and keep track of its rate limit.
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use err_derive::Error;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde::Serialize;
+
+static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), " v", env!("CARGO_PKG_VERSION"));
+
+/// Size of the chunks at the start and end of a file which are hashed by
+/// [`opensubtitles_hash`](opensubtitles_hash).
+const HASH_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Provider built from the current settings along with the settings it was built from.
+static PROVIDER: Lazy<Mutex<Option<(SubtitleProviderConfig, Arc<dyn SubtitleProvider>)>>> =
+    Lazy::new(Default::default);
+
+#[derive(Clone, Debug, Error, Serialize)]
+pub enum SubtitleError {
+    #[error(display = "The subtitle provider rejected our credentials")]
+    Unauthorized,
+    #[error(display = "The subtitle provider is rate limiting us")]
+    RateLimited,
+    #[error(display = "The requested subtitle does not exist")]
+    NotFound,
+    #[error(display = "The subtitle provider returned an error: {}", _0)]
+    ProviderError(String),
+}
+
+impl From<reqwest::Error> for SubtitleError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::ProviderError(e.to_string())
+    }
+}
+
+/// Settings of the subtitle provider to use.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum SubtitleProviderConfig {
+    Opensubtitles {
+        api_key: String,
+        /// Credentials are optional, but without them the number of daily downloads is limited.
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+impl SubtitleProviderConfig {
+    /// Returns the config with its credentials blanked out, used to show which provider is
+    /// configured to users who arent allowed to see the credentials.
+    pub fn redacted(&self) -> Self {
+        match self {
+            Self::Opensubtitles { .. } => Self::Opensubtitles {
+                api_key: String::new(),
+                username: None,
+                password: None,
+            },
+        }
+    }
+}
+
+/// Information about a file we want subtitles for.
+#[derive(Clone, Debug, Default)]
+pub struct SubtitleQuery {
+    /// Title of the movie or show.
+    pub title: String,
+    pub year: Option<i64>,
+    pub season: Option<i64>,
+    pub episode: Option<i64>,
+    /// ISO 639-1 code of the language of the subtitles.
+    pub language: String,
+    /// Hash of the file as computed by [`opensubtitles_hash`](opensubtitles_hash).
+    pub hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SubtitleCandidate {
+    /// Id of the subtitle, used to download it.
+    pub id: String,
+    pub provider: &'static str,
+    pub language: String,
+    /// Name of the release the subtitle was made for.
+    pub release: Option<String>,
+    pub downloads: i64,
+    /// Whether the subtitle was matched by the hash of the file, in which case it is most likely
+    /// in sync.
+    pub hash_match: bool,
+}
+
+#[async_trait]
+pub trait SubtitleProvider: Send + Sync {
+    /// Name of the provider.
+    fn name(&self) -> &'static str;
+
+    /// Method returns the subtitles matching `query`, best matches first.
+    async fn search(&self, query: &SubtitleQuery) -> Result<Vec<SubtitleCandidate>, SubtitleError>;
+
+    /// Method downloads the subtitle with the id `id` returning its content as srt.
+    async fn download(&self, id: &str) -> Result<Vec<u8>, SubtitleError>;
+}
+
+/// Function returns the subtitle provider configured in the global settings, or `None` if no
+/// provider is configured.
+pub fn get_provider() -> Option<Arc<dyn SubtitleProvider>> {
+    let config = crate::routes::settings::get_global_settings().subtitle_provider?;
+    let mut lock = PROVIDER.lock().unwrap();
+
+    match lock.as_ref() {
+        Some((current, provider)) if *current == config => Some(provider.clone()),
+        _ => {
+            let provider: Arc<dyn SubtitleProvider> = match config.clone() {
+                SubtitleProviderConfig::Opensubtitles {
+                    api_key,
+                    username,
+                    password,
+                } => Arc::new(OpenSubtitles::new(api_key, username.zip(password))),
+            };
+
+            *lock = Some((config, provider.clone()));
+            Some(provider)
+        }
+    }
+}
+
+/// Function computes the hash opensubtitles uses to identify files. The hash is the size of the
+/// file plus the sum of the first and last 64KiB of the file read as little endian `u64`s.
+pub fn opensubtitles_hash(path: impl AsRef<Path>) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    if size < HASH_CHUNK_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "file is too small to be hashed",
+        ));
+    }
+
+    let mut hash = size;
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE as usize];
+
+    for offset in [0, size - HASH_CHUNK_SIZE] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+
+        for word in buf.chunks_exact(8) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(word);
+            hash = hash.wrapping_add(u64::from_le_bytes(bytes));
+        }
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+/// Client for the opensubtitles.com REST api.
+pub struct OpenSubtitles {
+    client: Client,
+    base: String,
+    api_key: String,
+    credentials: Option<(String, String)>,
+    /// Cached auth token along with when it expires.
+    token: tokio::sync::Mutex<Option<(String, Instant)>>,
+    /// Time at which the last request was sent.
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+}
+
+impl OpenSubtitles {
+    /// Minimum interval between two requests, opensubtitles allows at most 5 requests a second.
+    const REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+    /// Lifetime of auth tokens, opensubtitles tokens are valid for 24 hours.
+    const TOKEN_LIFETIME: Duration = Duration::from_secs(23 * 60 * 60);
+
+    pub fn new(api_key: String, credentials: Option<(String, String)>) -> Self {
+        Self {
+            client: Client::builder()
+                .user_agent(APP_USER_AGENT)
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("Failed to build the opensubtitles client"),
+            base: "https://api.opensubtitles.com/api/v1".into(),
+            api_key,
+            credentials,
+            token: Default::default(),
+            last_request: Default::default(),
+        }
+    }
+
+    /// Waits until we are allowed to send the next request.
+    async fn throttle(&self) {
+        let mut last_request = self.last_request.lock().await;
+
+        if let Some(elapsed) = last_request.map(|x| x.elapsed()) {
+            if elapsed < Self::REQUEST_INTERVAL {
+                tokio::time::sleep(Self::REQUEST_INTERVAL - elapsed).await;
+            }
+        }
+
+        *last_request = Some(Instant::now());
+    }
+
+    /// Method returns a valid auth token, logging in if we have no cached token or it expired.
+    /// Returns `None` if no credentials are configured.
+    async fn token(&self) -> Result<Option<String>, SubtitleError> {
+        #[derive(Deserialize)]
+        struct LoginResponse {
+            token: String,
+        }
+
+        let (username, password) = match self.credentials.as_ref() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let mut token = self.token.lock().await;
+
+        if let Some((token, expires)) = token.as_ref() {
+            if *expires > Instant::now() {
+                return Ok(Some(token.clone()));
+            }
+        }
+
+        self.throttle().await;
+        let resp = self
+            .client
+            .post(format!("{}/login", self.base))
+            .header("Api-Key", &self.api_key)
+            .json(&serde_json::json!({ "username": username, "password": password }))
+            .send()
+            .await?;
+
+        let resp: LoginResponse = Self::check_status(resp)?.json().await?;
+        *token = Some((resp.token.clone(), Instant::now() + Self::TOKEN_LIFETIME));
+
+        Ok(Some(resp.token))
+    }
+
+    fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, SubtitleError> {
+        match resp.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(SubtitleError::Unauthorized),
+            StatusCode::TOO_MANY_REQUESTS => Err(SubtitleError::RateLimited),
+            StatusCode::NOT_FOUND => Err(SubtitleError::NotFound),
+            x if !x.is_success() => Err(SubtitleError::ProviderError(x.to_string())),
+            _ => Ok(resp),
+        }
+    }
+}
+
+#[async_trait]
+impl SubtitleProvider for OpenSubtitles {
+    fn name(&self) -> &'static str {
+        "opensubtitles"
+    }
+
+    async fn search(&self, query: &SubtitleQuery) -> Result<Vec<SubtitleCandidate>, SubtitleError> {
+        #[derive(Deserialize)]
+        struct SearchResponse {
+            data: Vec<SearchResult>,
+        }
+
+        #[derive(Deserialize)]
+        struct SearchResult {
+            attributes: SearchAttributes,
+        }
+
+        #[derive(Deserialize)]
+        struct SearchAttributes {
+            language: Option<String>,
+            release: Option<String>,
+            #[serde(default)]
+            download_count: i64,
+            #[serde(default)]
+            moviehash_match: bool,
+            files: Vec<SearchFile>,
+        }
+
+        #[derive(Deserialize)]
+        struct SearchFile {
+            file_id: i64,
+        }
+
+        let mut args = vec![
+            ("query", query.title.clone()),
+            ("languages", query.language.to_lowercase()),
+        ];
+
+        if let Some(year) = query.year {
+            args.push(("year", year.to_string()));
+        }
+
+        if let Some(season) = query.season {
+            args.push(("season_number", season.to_string()));
+        }
+
+        if let Some(episode) = query.episode {
+            args.push(("episode_number", episode.to_string()));
+        }
+
+        if let Some(hash) = query.hash.as_ref() {
+            args.push(("moviehash", hash.clone()));
+        }
+
+        self.throttle().await;
+        let resp = self
+            .client
+            .get(format!("{}/subtitles", self.base))
+            .header("Api-Key", &self.api_key)
+            .query(&args)
+            .send()
+            .await?;
+
+        let resp: SearchResponse = Self::check_status(resp)?.json().await?;
+
+        let mut candidates = resp
+            .data
+            .into_iter()
+            .filter_map(|x| {
+                let file = x.attributes.files.first()?;
+
+                Some(SubtitleCandidate {
+                    id: file.file_id.to_string(),
+                    provider: self.name(),
+                    language: x
+                        .attributes
+                        .language
+                        .unwrap_or_else(|| query.language.clone()),
+                    release: x.attributes.release,
+                    downloads: x.attributes.download_count,
+                    hash_match: x.attributes.moviehash_match,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_by(|a, b| {
+            b.hash_match
+                .cmp(&a.hash_match)
+                .then(b.downloads.cmp(&a.downloads))
+        });
+
+        Ok(candidates)
+    }
+
+    async fn download(&self, id: &str) -> Result<Vec<u8>, SubtitleError> {
+        #[derive(Deserialize)]
+        struct DownloadResponse {
+            link: String,
+        }
+
+        let file_id = id.parse::<i64>().map_err(|_| SubtitleError::NotFound)?;
+        let token = self.token().await?;
+
+        let mut req = self
+            .client
+            .post(format!("{}/download", self.base))
+            .header("Api-Key", &self.api_key)
+            .json(&serde_json::json!({ "file_id": file_id, "sub_format": "srt" }));
+
+        if let Some(token) = token {
+            req = req.bearer_auth(token);
+        }
+
+        self.throttle().await;
+        let resp: DownloadResponse = Self::check_status(req.send().await?)?.json().await?;
+
+        self.throttle().await;
+        let resp = Self::check_status(self.client.get(resp.link).send().await?)?;
+
+        Ok(resp.bytes().await?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::opensubtitles_hash;
+    use super::SubtitleProviderConfig;
+
+    #[test]
+    fn test_redacted() {
+        let config = SubtitleProviderConfig::Opensubtitles {
+            api_key: "key".into(),
+            username: Some("user".into()),
+            password: Some("hunter2".into()),
+        };
+
+        assert_eq!(
+            config.redacted(),
+            SubtitleProviderConfig::Opensubtitles {
+                api_key: String::new(),
+                username: None,
+                password: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_opensubtitles_hash() {
+        let path = std::env::temp_dir().join("dim_opensubtitles_hash_test");
+
+        // a file of zeroes hashes to its size.
+        std::fs::write(&path, vec![0u8; 131072]).unwrap();
+        assert_eq!(opensubtitles_hash(&path).unwrap(), "0000000000020000");
+
+        let mut content = vec![0u8; 131072];
+        content[0] = 1;
+        content[131064] = 2;
+        std::fs::write(&path, content).unwrap();
+        assert_eq!(opensubtitles_hash(&path).unwrap(), "0000000000020003");
+
+        std::fs::write(&path, vec![0u8; 1024]).unwrap();
+        assert!(opensubtitles_hash(&path).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,433 @@
+//! Bounded pool on which background work like library scans and rematches is executed.
+//!
+//! Every task submitted to the pool is queued and only starts once a worker slot frees up. The
+//! number of slots is configured with `max_concurrent_tasks` in the global settings, this stops
+//! many simultaneous scans or rematches from exhausting resources and hammering TMDB.
+//!
+//! Tasks can be cancelled, in which case their future is dropped at the next await point.
+//!
+//! On shutdown the pool stops accepting work, drops queued tasks and gives running tasks until
+//! `shutdown_timeout_secs` in the global settings to finish before they are aborted. Long running
+//! tasks like scans check [`is_shutting_down`](is_shutting_down) between units of work so that
+//! they wind down at a safe checkpoint instead of being aborted mid-write.
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::errors::DimError;
+
+/// Number of tasks that can run at the same time if nothing is configured.
+pub const DEFAULT_MAX_CONCURRENT_TASKS: usize = 2;
+
+/// Seconds running tasks are given to finish on shutdown if nothing is configured.
+pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+static POOL: Lazy<TaskPool> = Lazy::new(|| {
+    TaskPool::new(crate::routes::settings::get_global_settings().max_concurrent_tasks)
+});
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Queued,
+    Running,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    pub state: TaskState,
+    /// Unix timestamp of when the task was submitted.
+    pub queued_at: i64,
+    /// Unix timestamp of when the task started running.
+    pub started_at: Option<i64>,
+    /// Whether the task can be cancelled.
+    pub cancelable: bool,
+}
+
+/// Outcome of shutting down a pool.
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// Tasks that were running and finished before the timeout.
+    pub drained: Vec<TaskInfo>,
+    /// Tasks that were queued and thus dropped without ever running.
+    pub dropped: Vec<TaskInfo>,
+    /// Tasks that were still running when the timeout elapsed.
+    pub aborted: Vec<TaskInfo>,
+}
+
+pub struct TaskPool {
+    slots: Arc<Semaphore>,
+    tasks: Arc<Mutex<BTreeMap<u64, TaskInfo>>>,
+    handles: Arc<Mutex<HashMap<u64, JoinHandle<()>>>>,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+}
+
+impl TaskPool {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(size.max(1))),
+            tasks: Default::default(),
+            handles: Default::default(),
+            next_id: AtomicU64::new(0),
+            closed: Default::default(),
+        }
+    }
+
+    /// Queues `fut` for execution and returns the id of the task. The task is removed from the
+    /// pool once it finishes.
+    pub fn submit<F>(&self, name: impl Into<String>, fut: F) -> u64
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.submit_with(name, |_| fut)
+    }
+
+    /// Same as [`submit`](TaskPool::submit) except that the future is built from the id of the
+    /// task, ie to tag events the task emits.
+    pub fn submit_with<F, Fut>(&self, name: impl Into<String>, f: F) -> u64
+    where
+        F: FnOnce(u64) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.spawn(id, name.into(), f(id), true);
+
+        id
+    }
+
+    /// Same as [`submit`](TaskPool::submit) except that the task cant be cancelled. This is used
+    /// for tasks whose result is awaited by someone else.
+    pub fn submit_uncancelable<F>(&self, name: impl Into<String>, fut: F) -> u64
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.spawn(id, name.into(), fut, false);
+
+        id
+    }
+
+    fn spawn<F>(&self, id: u64, name: String, fut: F, cancelable: bool)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if self.is_closed() {
+            warn!(task = %name, "Rejecting task as the pool is shutting down");
+            return;
+        }
+
+        self.tasks.lock().unwrap().insert(
+            id,
+            TaskInfo {
+                id,
+                name,
+                state: TaskState::Queued,
+                queued_at: timestamp(),
+                started_at: None,
+                cancelable,
+            },
+        );
+
+        let slots = self.slots.clone();
+        let tasks = self.tasks.clone();
+        let handles = self.handles.clone();
+        let closed = self.closed.clone();
+
+        // the handle is stored before the task can remove it, otherwise a task finishing right
+        // away would leave a stale handle behind.
+        let mut handles_lock = self.handles.lock().unwrap();
+
+        let handle = tokio::spawn(async move {
+            // removes the task from the pool however it ends, even if it panics.
+            let _guard = Registration {
+                id,
+                tasks: tasks.clone(),
+                handles,
+            };
+
+            // the semaphore is never closed thus acquiring can't fail.
+            let _permit = slots.acquire_owned().await.unwrap();
+
+            // the pool was shut down while we were queued.
+            if closed.load(Ordering::SeqCst) {
+                return;
+            }
+
+            if let Some(task) = tasks.lock().unwrap().get_mut(&id) {
+                task.state = TaskState::Running;
+                task.started_at = Some(timestamp());
+            }
+
+            fut.await;
+        });
+
+        handles_lock.insert(id, handle);
+    }
+
+    /// Cancels a queued or running task. Returns whether a cancelable task with this id existed.
+    pub fn cancel(&self, id: u64) -> bool {
+        let cancelable = self
+            .tasks
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map_or(false, |x| x.cancelable);
+
+        if !cancelable {
+            return false;
+        }
+
+        let handle = self.handles.lock().unwrap().remove(&id);
+
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                self.tasks.lock().unwrap().remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns all queued and running tasks in submission order.
+    pub fn list(&self) -> Vec<TaskInfo> {
+        self.tasks.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Returns whether the pool was shut down and no longer accepts tasks.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Stops accepting new tasks, drops the queued ones and waits up to `timeout` for the running
+    /// ones to finish. Tasks still running after that are aborted.
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        let tasks = self.list();
+        let mut handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        let mut report = ShutdownReport::default();
+
+        for task in tasks {
+            let mut handle = match handles.remove(&task.id) {
+                Some(x) => x,
+                // the task finished in the meantime.
+                None => continue,
+            };
+
+            if task.state == TaskState::Queued {
+                handle.abort();
+                report.dropped.push(task);
+                continue;
+            }
+
+            match tokio::time::timeout_at(deadline, &mut handle).await {
+                Ok(_) => report.drained.push(task),
+                Err(_) => {
+                    handle.abort();
+                    report.aborted.push(task);
+                }
+            }
+        }
+
+        self.tasks.lock().unwrap().clear();
+
+        report
+    }
+}
+
+/// Removes a task from its pool once dropped.
+struct Registration {
+    id: u64,
+    tasks: Arc<Mutex<BTreeMap<u64, TaskInfo>>>,
+    handles: Arc<Mutex<HashMap<u64, JoinHandle<()>>>>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.tasks.lock().unwrap().remove(&self.id);
+        self.handles.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Queues `fut` on the global pool without waiting for it to finish.
+pub fn submit<F>(name: impl Into<String>, fut: F) -> u64
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    POOL.submit(name, fut)
+}
+
+/// Queues the future built by `f` on the global pool without waiting for it to finish. `f` receives
+/// the id of the task.
+pub fn submit_with<F, Fut>(name: impl Into<String>, f: F) -> u64
+where
+    F: FnOnce(u64) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    POOL.submit_with(name, f)
+}
+
+/// Queues `fut` on the global pool without waiting for it to finish. The task cant be cancelled,
+/// this is used for work that must not be interrupted halfway, like deleting a library.
+pub fn submit_uncancelable<F>(name: impl Into<String>, fut: F) -> u64
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    POOL.submit_uncancelable(name, fut)
+}
+
+/// Queues `fut` on the global pool and waits for its result. This is used by request handlers
+/// that must report the outcome of their work to the client. Returns
+/// [`TaskDropped`](DimError::TaskDropped) if the task never completed, ie because the pool was
+/// shut down or the task panicked.
+pub async fn run<F, T>(name: impl Into<String>, fut: F) -> Result<T, DimError>
+where
+    F: Future<Output = Result<T, DimError>> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    POOL.submit_uncancelable(name, async move {
+        let _ = tx.send(fut.await);
+    });
+
+    rx.await.map_err(|_| DimError::TaskDropped)?
+}
+
+/// Returns all queued and running tasks of the global pool.
+pub fn list() -> Vec<TaskInfo> {
+    POOL.list()
+}
+
+/// Cancels a task of the global pool. Returns whether a cancelable task with this id existed.
+pub fn cancel(id: u64) -> bool {
+    POOL.cancel(id)
+}
+
+/// Returns whether the global pool is shutting down. Long running tasks should check this between
+/// units of work and stop early if it is set.
+pub fn is_shutting_down() -> bool {
+    POOL.is_closed()
+}
+
+/// Shuts down the global pool, see [`TaskPool::shutdown`](TaskPool::shutdown).
+pub async fn shutdown(timeout: Duration) -> ShutdownReport {
+    POOL.shutdown(timeout).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pool_limits_concurrency() {
+        let pool = TaskPool::new(1);
+        let (tx, rx) = oneshot::channel::<()>();
+
+        pool.submit("first", async move {
+            let _ = rx.await;
+        });
+        pool.submit("second", async {});
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let tasks = pool.list();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].state, TaskState::Running);
+        assert_eq!(tasks[1].state, TaskState::Queued);
+
+        let _ = tx.send(());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(pool.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let pool = TaskPool::new(1);
+        let (_tx, rx) = oneshot::channel::<()>();
+
+        let id = pool.submit("blocked", async move {
+            let _ = rx.await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(pool.cancel(id));
+        assert!(pool.list().is_empty());
+        assert!(!pool.cancel(id));
+
+        let (tx, rx) = oneshot::channel::<()>();
+        let id = pool.submit_uncancelable("awaited", async move {
+            let _ = rx.await;
+        });
+
+        assert!(!pool.cancel(id));
+        assert_eq!(pool.list().len(), 1);
+
+        let _ = tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let pool = TaskPool::new(2);
+        let (_tx, rx) = oneshot::channel::<()>();
+
+        pool.submit("quick", tokio::time::sleep(Duration::from_millis(50)));
+        pool.submit("stuck", async move {
+            let _ = rx.await;
+        });
+        pool.submit("queued", async {});
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let report = pool.shutdown(Duration::from_millis(200)).await;
+        let names = |x: &[TaskInfo]| x.iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(&report.drained), vec!["quick"]);
+        assert_eq!(names(&report.aborted), vec!["stuck"]);
+        assert_eq!(names(&report.dropped), vec!["queued"]);
+        assert!(pool.list().is_empty());
+
+        // new work is rejected once the pool is shut down.
+        pool.submit("late", async {});
+        assert!(pool.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_is_removed() {
+        let pool = TaskPool::new(1);
+
+        pool.submit("panics", async { panic!("task failed") });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(pool.list().is_empty());
+        assert!(pool.handles.lock().unwrap().is_empty());
+    }
+}
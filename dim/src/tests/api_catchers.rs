@@ -0,0 +1,86 @@
+use crate::routes::catchers::api_not_found;
+use crate::routes::catchers::catch;
+use crate::routes::global_filters::handle_rejection;
+
+use serde_json::json;
+use serde_json::Value;
+use warp::http::StatusCode;
+use warp::test::request;
+use warp::Filter;
+
+#[tokio::test]
+async fn test_api_not_found() {
+    let resp = request()
+        .path("/api/v1/does/not/exist")
+        .reply(&api_not_found())
+        .await;
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+
+    let body: Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["error"], "NotFoundError");
+    assert_eq!(body["status"], 404);
+}
+
+#[tokio::test]
+async fn test_catch() {
+    for (status, error) in vec![
+        (StatusCode::UNAUTHORIZED, "Unauthenticated"),
+        (StatusCode::FORBIDDEN, "Forbidden"),
+        (StatusCode::INTERNAL_SERVER_ERROR, "InternalServerError"),
+    ] {
+        let resp = catch(status);
+        assert_eq!(resp.status(), status);
+        assert_eq!(resp.headers()["content-type"], "application/json");
+
+        let body = warp::hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], error);
+        assert_eq!(body["status"], json!(status.as_u16()));
+    }
+}
+
+#[derive(Debug)]
+struct Unhandled;
+
+impl warp::reject::Reject for Unhandled {}
+
+#[tokio::test]
+async fn test_unhandled_rejection() {
+    let routes = warp::any()
+        .and_then(|| async { Err::<String, _>(warp::reject::custom(Unhandled)) })
+        .recover(handle_rejection);
+
+    let resp = request().path("/api/v1/broken").reply(&routes).await;
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let body: Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["error"], "InternalServerError");
+}
+
+#[tokio::test]
+async fn test_api_not_found_any_method() {
+    let resp = request()
+        .method("DELETE")
+        .path("/api/v1/nothing")
+        .reply(&api_not_found())
+        .await;
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_static_routes_not_caught() {
+    let routes = api_not_found().or(warp::any().map(|| warp::reply::html("<html></html>")));
+
+    let resp = request().path("/library/1").reply(&routes).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body().as_ref(), b"<html></html>");
+
+    let resp = request()
+        .path("/api/v1/library/1/nothing")
+        .reply(&routes)
+        .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
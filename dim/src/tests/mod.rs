@@ -1,2 +1,3 @@
 // NOTE: Might want to add a v1 module.
 pub mod api_auth;
+pub mod api_catchers;
@@ -0,0 +1,309 @@
+//! In-memory registry of watch parties used for synchronized viewing.
+//!
+//! A watch party is created by a host for a media. Other users join the party with the token
+//! returned on creation. Whenever the host seeks, plays or pauses, the new state is relayed to all
+//! members over the event socket. Parties are not persisted and are closed once the last event
+//! socket of the host disconnects, or after [`HOST_CONNECT_TIMEOUT`](HOST_CONNECT_TIMEOUT) if the
+//! host never connects an event socket at all.
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use events::Message;
+use events::PushEventType;
+
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+/// Parties whose host has no event socket open this long after creation are closed.
+pub const HOST_CONNECT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Channel over which events targetted at a set of users are sent to the event socket.
+pub type PartyTx = UnboundedSender<(Vec<String>, String)>;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchParty {
+    pub token: String,
+    pub media_id: i64,
+    pub host: String,
+    pub members: HashSet<String>,
+    /// Playback offset of the host in seconds.
+    pub offset: i64,
+    pub paused: bool,
+    #[serde(skip)]
+    created_at: Instant,
+}
+
+impl WatchParty {
+    fn state_event(&self) -> String {
+        Message {
+            id: self.media_id,
+            event_type: PushEventType::EventPartyState {
+                offset: self.offset,
+                paused: self.paused,
+            },
+        }
+        .to_string()
+    }
+
+    fn members(&self) -> Vec<String> {
+        self.members.iter().cloned().collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct WatchParties {
+    parties: Arc<Mutex<HashMap<String, WatchParty>>>,
+    /// Number of event sockets every user has open, ie one per tab or device.
+    sockets: Arc<Mutex<HashMap<String, usize>>>,
+    tx: PartyTx,
+}
+
+impl WatchParties {
+    pub fn new(tx: PartyTx) -> Self {
+        Self {
+            parties: Default::default(),
+            sockets: Default::default(),
+            tx,
+        }
+    }
+
+    /// Creates a new paused party for `media_id` hosted by `host` and returns it.
+    pub fn create(&self, host: String, media_id: i64) -> WatchParty {
+        let party = WatchParty {
+            token: Uuid::new_v4().to_hyphenated().to_string(),
+            media_id,
+            members: std::iter::once(host.clone()).collect(),
+            host,
+            offset: 0,
+            paused: true,
+            created_at: Instant::now(),
+        };
+
+        self.parties
+            .lock()
+            .unwrap()
+            .insert(party.token.clone(), party.clone());
+
+        party
+    }
+
+    /// Adds `user` to the party with the token supplied. The current state of the party is sent
+    /// to the new member so that it can catch up with the host.
+    pub fn join(&self, token: &str, user: String) -> Option<WatchParty> {
+        let party = {
+            let mut lock = self.parties.lock().unwrap();
+            let party = lock.get_mut(token)?;
+            party.members.insert(user.clone());
+            party.clone()
+        };
+
+        let _ = self.tx.send((vec![user], party.state_event()));
+
+        Some(party)
+    }
+
+    /// Removes `user` from the party. If `user` is the host the party is closed.
+    pub fn leave(&self, token: &str, user: &str) -> Option<()> {
+        let is_host = self.parties.lock().unwrap().get(token)?.host == user;
+
+        if is_host {
+            self.close(token);
+        } else if let Some(party) = self.parties.lock().unwrap().get_mut(token) {
+            party.members.remove(user);
+        }
+
+        Some(())
+    }
+
+    /// Updates the playback state of the party and relays it to all members. Only the host can
+    /// update the state of a party, `None` is returned for everyone else.
+    pub fn update(&self, token: &str, user: &str, offset: i64, paused: bool) -> Option<WatchParty> {
+        let party = {
+            let mut lock = self.parties.lock().unwrap();
+            let party = lock.get_mut(token).filter(|x| x.host == user)?;
+            party.offset = offset;
+            party.paused = paused;
+            party.clone()
+        };
+
+        let _ = self.tx.send((party.members(), party.state_event()));
+
+        Some(party)
+    }
+
+    /// Relays progress reported by `user` for `media_id` to the members of all parties the user
+    /// hosts for that media.
+    pub fn update_progress(&self, user: &str, media_id: i64, offset: i64) {
+        let parties = self
+            .parties
+            .lock()
+            .unwrap()
+            .values_mut()
+            .filter(|x| x.host == user && x.media_id == media_id)
+            .map(|x| {
+                x.offset = offset;
+                x.clone()
+            })
+            .collect::<Vec<_>>();
+
+        for party in parties {
+            let _ = self.tx.send((party.members(), party.state_event()));
+        }
+    }
+
+    /// Registers a event socket of `user`. This is called once the socket authenticated.
+    pub fn host_connected(&self, user: &str) {
+        *self
+            .sockets
+            .lock()
+            .unwrap()
+            .entry(user.to_string())
+            .or_default() += 1;
+    }
+
+    /// Unregisters a event socket of `user` and closes all parties hosted by `user` if it was the
+    /// last socket of the user. Other sockets of the host, ie in another tab, keep its parties
+    /// open.
+    pub fn host_disconnected(&self, user: &str) {
+        {
+            let mut sockets = self.sockets.lock().unwrap();
+
+            if let Some(count) = sockets.get_mut(user) {
+                *count = count.saturating_sub(1);
+
+                if *count > 0 {
+                    return;
+                }
+            }
+
+            sockets.remove(user);
+        }
+
+        let tokens = self
+            .parties
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|x| x.host == user)
+            .map(|x| x.token.clone())
+            .collect::<Vec<_>>();
+
+        for token in tokens {
+            self.close(&token);
+        }
+    }
+
+    /// Closes all parties older than `timeout` whose host has no event socket open, ie because
+    /// the host created the party but never connected.
+    pub fn expire_unhosted(&self, timeout: Duration) {
+        let tokens = {
+            let sockets = self.sockets.lock().unwrap();
+
+            self.parties
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|x| x.created_at.elapsed() >= timeout && !sockets.contains_key(&x.host))
+                .map(|x| x.token.clone())
+                .collect::<Vec<_>>()
+        };
+
+        for token in tokens {
+            self.close(&token);
+        }
+    }
+
+    /// Spawns the task closing parties whose host never connected, see
+    /// [`expire_unhosted`](WatchParties::expire_unhosted).
+    pub fn start_expiry(&self) {
+        let parties = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(HOST_CONNECT_TIMEOUT).await;
+                parties.expire_unhosted(HOST_CONNECT_TIMEOUT);
+            }
+        });
+    }
+
+    fn close(&self, token: &str) {
+        if let Some(party) = self.parties.lock().unwrap().remove(token) {
+            let event = Message {
+                id: party.media_id,
+                event_type: PushEventType::EventPartyClosed,
+            };
+
+            let _ = self.tx.send((party.members(), event.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn test_party_lifecycle() {
+        let (tx, mut rx) = unbounded_channel();
+        let parties = WatchParties::new(tx);
+
+        let party = parties.create("host".into(), 1);
+        assert!(parties.join(&party.token, "guest".into()).is_some());
+        let (users, _) = rx.try_recv().unwrap();
+        assert_eq!(users, vec!["guest".to_string()]);
+
+        // only the host can control playback.
+        assert!(parties.update(&party.token, "guest", 10, false).is_none());
+
+        let updated = parties.update(&party.token, "host", 10, false).unwrap();
+        assert_eq!(updated.offset, 10);
+        let (mut users, _) = rx.try_recv().unwrap();
+        users.sort();
+        assert_eq!(users, vec!["guest".to_string(), "host".to_string()]);
+
+        parties.host_disconnected("host");
+        assert!(rx.try_recv().is_ok());
+        assert!(parties.join(&party.token, "guest".into()).is_none());
+    }
+
+    #[test]
+    fn test_party_survives_other_socket_closing() {
+        let (tx, _rx) = unbounded_channel();
+        let parties = WatchParties::new(tx);
+
+        parties.host_connected("host");
+        parties.host_connected("host");
+        let party = parties.create("host".into(), 1);
+
+        // the host still has a socket open, ie in another tab.
+        parties.host_disconnected("host");
+        assert!(parties.join(&party.token, "guest".into()).is_some());
+
+        parties.host_disconnected("host");
+        assert!(parties.join(&party.token, "guest".into()).is_none());
+    }
+
+    #[test]
+    fn test_party_expires_without_host() {
+        let (tx, _rx) = unbounded_channel();
+        let parties = WatchParties::new(tx);
+
+        parties.host_connected("connected");
+        let hosted = parties.create("connected".into(), 1);
+        let unhosted = parties.create("absent".into(), 1);
+
+        // parties are given some time for their host to connect.
+        parties.expire_unhosted(Duration::from_secs(60));
+        assert!(parties.join(&unhosted.token, "guest".into()).is_some());
+
+        parties.expire_unhosted(Duration::from_secs(0));
+        assert!(parties.join(&unhosted.token, "guest".into()).is_none());
+        assert!(parties.join(&hosted.token, "guest".into()).is_some());
+    }
+}
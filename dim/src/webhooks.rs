@@ -0,0 +1,171 @@
+//! Outbound webhooks which mirror the events dispatched over the event socket.
+//!
+//! Every event is POSTed as-is to the registered webhooks that accept its type. Each delivery
+//! carries the unix timestamp of the attempt in the [`TIMESTAMP_HEADER`](TIMESTAMP_HEADER) header.
+//! The timestamp and payload are signed as `<timestamp>.<payload>` with HMAC-SHA256 using the
+//! secret of the webhook, the hex encoded signature is sent in the
+//! [`SIGNATURE_HEADER`](SIGNATURE_HEADER) header prefixed with `sha256=`. Receivers should reject
+//! deliveries with an old timestamp so that captured payloads cant be replayed. Failed deliveries
+//! are retried with exponential backoff.
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use database::webhook::Webhook;
+use database::DbConnection;
+
+use ring::hmac;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Number of times a delivery is attempted before it is dropped.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry of a failed delivery. The delay doubles with every retry.
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Header holding the signature of the payload.
+pub const SIGNATURE_HEADER: &str = "X-Dim-Signature";
+/// Header holding the unix timestamp at which the delivery was attempted.
+pub const TIMESTAMP_HEADER: &str = "X-Dim-Timestamp";
+
+#[derive(Clone)]
+pub struct Webhooks {
+    conn: DbConnection,
+    hooks: Arc<RwLock<Vec<Webhook>>>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub async fn new(conn: DbConnection) -> Self {
+        let webhooks = Self {
+            conn,
+            hooks: Default::default(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("Failed to build the webhook client"),
+        };
+
+        webhooks.reload().await;
+        webhooks
+    }
+
+    /// Reloads the registered webhooks from the database. This must be called whenever webhooks
+    /// are added or removed.
+    pub async fn reload(&self) {
+        let hooks = match self.conn.read().begin().await {
+            Ok(mut tx) => Webhook::get_all(&mut tx).await,
+            Err(e) => Err(e.into()),
+        };
+
+        match hooks {
+            Ok(hooks) => *self.hooks.write().await = hooks,
+            Err(e) => warn!(reason = ?e, "Failed to load webhooks"),
+        }
+    }
+
+    /// Delivers `event` to all webhooks accepting its type. Deliveries happen in the background,
+    /// thus this never blocks on the receivers.
+    pub async fn dispatch(&self, event: &str) {
+        let event_type = serde_json::from_str::<serde_json::Value>(event)
+            .ok()
+            .and_then(|x| x.get("type")?.as_str().map(ToString::to_string));
+
+        let event_type = match event_type {
+            Some(x) => x,
+            None => return,
+        };
+
+        for hook in self.hooks.read().await.iter() {
+            if hook.accepts(&event_type) {
+                tokio::spawn(deliver(
+                    self.client.clone(),
+                    hook.clone(),
+                    event.to_string(),
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the hex encoded HMAC-SHA256 of `payload` keyed with `secret`.
+pub fn sign(secret: &str, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+
+    hmac::sign(&key, payload.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+
+/// Returns the signature sent with a delivery of `payload` at `timestamp`.
+pub fn signature(secret: &str, timestamp: u64, payload: &str) -> String {
+    format!(
+        "sha256={}",
+        sign(secret, &format!("{}.{}", timestamp, payload))
+    )
+}
+
+async fn deliver(client: reqwest::Client, hook: Webhook, payload: String) {
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        // every attempt is signed with a fresh timestamp so that retries aren't rejected as stale.
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let result = client
+            .post(&hook.url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                signature(&hook.secret, timestamp, &payload),
+            )
+            .body(payload.clone())
+            .send()
+            .await
+            .and_then(|x| x.error_for_status());
+
+        match result {
+            Ok(_) => return,
+            Err(e) => warn!(
+                reason = ?e,
+                webhook = hook.id,
+                attempt = attempt,
+                "Failed to deliver webhook"
+            ),
+        }
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+    use super::signature;
+
+    #[test]
+    fn test_sign() {
+        // test case 2 of RFC 4231
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_signature_covers_timestamp() {
+        assert_eq!(
+            signature("secret", 1, "{}"),
+            format!("sha256={}", sign("secret", "1.{}"))
+        );
+        assert_ne!(signature("secret", 1, "{}"), signature("secret", 2, "{}"));
+    }
+}
@@ -13,6 +13,7 @@ use futures::prelude::*;
 use futures::stream::SplitSink;
 
 use crate::routes;
+use crate::watch_party::WatchParties;
 
 pub enum CtrlEvent<A, M>
 where
@@ -34,6 +35,12 @@ where
     },
 
     SendAll(M),
+
+    /// Send a message to all peers authenticated as one of `users`.
+    SendToUsers {
+        users: Vec<String>,
+        message: M,
+    },
 }
 
 pub trait IntoCtrlEvent<A, M>: Sync + Send + Clone + 'static
@@ -87,6 +94,21 @@ where
                     }
                 }
 
+                CtrlEvent::SendToUsers { users, message } => {
+                    for (addr, (sink, auth)) in peers.iter_mut() {
+                        if !users.iter().any(|x| x == auth.user_ref()) {
+                            continue;
+                        }
+
+                        let result = sink.send(Message::text(message.clone())).await;
+
+                        if result.is_err() {
+                            let _ = sink.close().await;
+                            discard.push(addr.clone());
+                        }
+                    }
+                }
+
                 CtrlEvent::SendTo { addr, message } => {
                     if let Some((sink, _)) = peers.get_mut(&addr) {
                         let result = sink.send(Message::text(message.clone())).await;
@@ -112,6 +134,8 @@ pub enum ClientActions {
 pub fn event_socket(
     rt_handle: Handle,
     mut event_rx: UnboundedReceiver<String>,
+    mut party_rx: UnboundedReceiver<(Vec<String>, String)>,
+    parties: WatchParties,
 ) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     let (i_tx, i_rx) = unbounded_channel::<CtrlEvent<SocketAddr, String>>();
 
@@ -129,15 +153,29 @@ pub fn event_socket(
 
     let _forwarder = rt_handle.spawn(forwarder_fut);
 
+    let party_forwarder_fut = {
+        let i_tx = i_tx.clone();
+
+        async move {
+            while let Some((users, message)) = party_rx.recv().await {
+                let _ = i_tx.send(CtrlEvent::SendToUsers { users, message });
+            }
+        }
+    };
+
+    let _party_forwarder = rt_handle.spawn(party_forwarder_fut);
+
     warp::path("ws")
         .and(warp::filters::addr::remote())
         .and(routes::global_filters::with_state(i_tx))
         .and(routes::global_filters::with_state(rt_handle))
+        .and(routes::global_filters::with_state(parties))
         .and(warp::ws())
         .map(
             |addr: Option<SocketAddr>,
              i_tx: UnboundedSender<CtrlEvent<SocketAddr, String>>,
              rt_handle: Handle,
+             parties: WatchParties,
              ws: warp::ws::Ws| {
                 ws.on_upgrade(move |websocket| async move {
                     let addr = match addr {
@@ -147,13 +185,23 @@ pub fn event_socket(
 
                     let (m_tx, mut m_rx) = unbounded_channel::<(SocketAddr, Message)>();
                     let (ws_tx, mut ws_rx) = websocket.split();
+                    let mut user = None;
 
                     'auth_loop: while let Some(Ok(x)) = ws_rx.next().await {
                         if x.is_text() {
                             if let Ok(ClientActions::Authenticate { token }) =
                                 serde_json::from_slice(x.as_bytes())
                             {
-                                if let Ok(token_data) = auth::jwt_check(token) {
+                                // the socket pushes library and media events, thus tokens have
+                                // to be allowed to read media.
+                                if let Some(token_data) = auth::jwt_check(token)
+                                    .ok()
+                                    .filter(|x| x.claims.has_scope("read:media"))
+                                {
+                                    let username = token_data.claims.get_user();
+                                    parties.host_connected(&username);
+                                    user = Some(username);
+
                                     let _ = i_tx.send(CtrlEvent::Track {
                                         addr,
                                         sink: ws_tx,
@@ -193,6 +241,11 @@ pub fn event_socket(
                             }
                         }
 
+                        // watch parties are closed once the last socket of their host disconnects.
+                        if let Some(user) = user {
+                            parties.host_disconnected(&user);
+                        }
+
                         i_tx.send(CtrlEvent::Forget { addr })
                     });
 
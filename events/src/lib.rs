@@ -44,4 +44,22 @@ pub enum PushEventType {
     EventAuthOk,
     /// Tell client their token is wrong or missing
     EventAuthErr,
+    /// The host of a watch party played, paused or seeked.
+    EventPartyState { offset: i64, paused: bool },
+    /// The watch party has been closed by its host.
+    EventPartyClosed,
+    /// The progress of a user for a media has been changed explicitly, ie by marking it watched.
+    EventProgress { user: String, delta: i64 },
+    /// A orphaned mediafile has been manually attached to a media.
+    EventMediaFileAttached { media_id: i64 },
+    /// A maintenance task processed another batch of items. `updated` is the number of items
+    /// changed so far.
+    EventMaintenanceProgress {
+        processed: i64,
+        total: i64,
+        updated: i64,
+    },
+    /// A watched media has been purged by maintenance. `files_removed` is the number of files
+    /// deleted from disk along with it.
+    EventMediaPurged { files_removed: i64 },
 }